@@ -0,0 +1,56 @@
+// The pyrs interpreter as a library: the parser, the bytecode compiler,
+// the VM and the object model. The Pyrs binary is a thin CLI over it and
+// a host program embeds it with PyRuntime:
+//
+//     let mut py = pyrs::PyRuntime::new();
+//     py.exec("x = 1 + 2", "script.py")?;
+//     let x: i64 = py.get_global("x")?;
+//
+// Parts of the standard library are behind features, all on by default:
+//     math    the math module
+//     cmath   the cmath module
+//     fs      open() and file objects, without it open isn't a builtin
+// Without math or cmath importing that module fails.
+
+pub mod pyrs_obj;
+pub mod pyrs_parsing;
+pub mod pyrs_std;
+pub mod pyrs_error;
+pub mod pyrs_format;
+pub mod pyrs_userclass;
+pub mod pyrs_utils;
+pub mod pyrs_interpreter;
+pub mod pyrs_bytecode;
+pub mod pyrs_vm;
+pub mod pyrs_codeobject;
+pub mod pyrs_generator;
+pub mod pyrs_functools;
+#[cfg(feature = "cmath")]
+pub mod pyrs_cmath;
+#[cfg(feature = "math")]
+pub mod pyrs_math;
+pub mod pyrs_fractions;
+pub mod pyrs_decimal;
+pub mod pyrs_bytes;
+pub mod pyrs_str;
+pub mod pyrs_builtins;
+pub mod pyrs_import;
+pub mod pyrs_native;
+pub mod pyrs_embed;
+pub mod pyrs_io;
+pub mod pyrs_contextlib;
+#[cfg(feature = "fs")]
+pub mod pyrs_file;
+#[cfg(test)]
+mod pyrs_tests;
+
+pub use crate::{
+    pyrs_bytecode::PyBytecode,
+    pyrs_embed::{InputSource, OutputSink, PyRuntime},
+    pyrs_error::{PyError, PyException},
+    pyrs_interpreter::Interpreter,
+    pyrs_native::{FromObj, NativeClass, NativeModule, NativeObj},
+    pyrs_obj::{Obj, PyObj, ToObj},
+    pyrs_parsing::Expression,
+    pyrs_vm::PyVM,
+};
@@ -1,26 +1,11 @@
+use pyrs::pyrs_error::PyException;
+use pyrs::pyrs_interpreter::{Interpreter, InterpreterCommand, InterpreterFlags};
 
-pub mod pyrs_obj;
-pub mod pyrs_parsing;
-pub mod pyrs_std;
-pub mod pyrs_error;
-pub mod pyrs_userclass;
-pub mod pyrs_utils;
-pub mod pyrs_interpreter;
-pub mod pyrs_bytecode;
-pub mod pyrs_vm;
-pub mod pyrs_codeobject;
-mod pyrs_tests; 
-
-#[allow(unused_imports)]
-use crate::{
-    pyrs_interpreter::{Interpreter, InterpreterCommand, InterpreterFlags},
-    pyrs_obj::{Obj},
-    pyrs_error::{PyException}, 
-    pyrs_parsing::{Expression, Token, Op},
-    pyrs_std::{FnPtr, Funcs},
-    pyrs_bytecode::{PyBytecode},
-    pyrs_vm::{PyVM, IntrinsicFunc},
-};
+// an uncaught error ends the script like it does in python
+fn exit_with(e: PyException) -> ! {
+    eprintln!("{e}");
+    std::process::exit(1);
+}
 
 fn main() -> std::io::Result<()> {
 
@@ -39,6 +24,9 @@ fn main() -> std::io::Result<()> {
                 if flags.contains(&InterpreterFlags::Debug) {
                     interp.set_debug_mode(true);
                 }
+                if flags.contains(&InterpreterFlags::Optimize) {
+                    interp.set_optimize(true);
+                }
 
                 let is_py_file = filepath.ends_with(".py");
                 if !flags.contains(&InterpreterFlags::AnyFile) && !is_py_file {
@@ -47,11 +35,13 @@ fn main() -> std::io::Result<()> {
                 }
 
                 if flags.contains(&InterpreterFlags::Compile) {
-                    let bytecode = Interpreter::compile_file(&filepath);
-                    Interpreter::seralize_bytecode(&filepath, &bytecode)?;
+                    match Interpreter::compile_file(&filepath) {
+                        Ok(bytecode) => Interpreter::seralize_bytecode(&filepath, &bytecode)?,
+                        Err(e) => exit_with(e),
+                    }
                 } 
-                else {
-                    interp.interpret_file(&filepath);
+                else if let Err(e) = interp.interpret_file(&filepath) {
+                    exit_with(e);
                 }
             }
             InterpreterCommand::FromString(words) => interp.interpret_line(&words),
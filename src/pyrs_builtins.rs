@@ -0,0 +1,1710 @@
+use crate::{
+    pyrs_bytecode::PyBytecode,
+    pyrs_bytes as bytes,
+    pyrs_codeobject::CodeObject,
+    pyrs_decimal::{self as decimal, Decimal},
+    pyrs_error::{PyError, PyException},
+    pyrs_format,
+    pyrs_fractions as fractions,
+    pyrs_functools as functools,
+    pyrs_embed::{compile_expr, in_file},
+    pyrs_generator::GeneratorStep,
+    pyrs_import::PyModule,
+    pyrs_interpreter::Interpreter,
+    pyrs_obj::{Obj, ObjIter, PyObj},
+    pyrs_parsing::Op,
+    pyrs_std::{bind_native_args, Funcs, NativeFn, NativeFunc, RangeObj, BUILTIN_TYPES},
+    pyrs_str,
+    pyrs_userclass::{self as userclass, Property, UserClassDef},
+    pyrs_vm::PyVM,
+};
+#[cfg(feature = "fs")]
+use crate::pyrs_file as file;
+use rug::{Integer, Rational};
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+
+pub const MODULE_NAME: &str = "builtins";
+// The builtins namespace, what both the VM and the REPL look a builtin
+// function up in when no variable has the name, see PyVM::load_name. The
+// types, like int and list, are in BUILTIN_TYPES
+const FUNCTIONS: &[(&str, NativeFn)] = &[
+    ("__import__", import),
+    ("abs", abs),
+    ("aiter", aiter),
+    ("all", all),
+    ("anext", anext),
+    ("any", any),
+    ("ascii", ascii),
+    ("bin", bin),
+    ("breakpoint", breakpoint),
+    ("callable", callable),
+    ("chr", chr),
+    ("compile", compile),
+    ("classmethod", classmethod),
+    ("delattr", delattr),
+    ("dir", dir),
+    ("divmod", divmod),
+    ("enumerate", enumerate),
+    ("eval", eval),
+    ("exec", exec),
+    ("filter", filter),
+    ("format", format),
+    ("getattr", getattr),
+    ("globals", globals),
+    ("hasattr", hasattr),
+    ("hash", hash),
+    ("help", help),
+    ("hex", hex),
+    ("id", id),
+    ("input", input),
+    ("isinstance", isinstance),
+    ("issubclass", issubclass),
+    ("iter", iter),
+    ("len", len),
+    ("locals", locals),
+    ("map", map),
+    ("max", max),
+    ("min", min),
+    ("next", next),
+    ("oct", oct),
+    #[cfg(feature = "fs")]
+    ("open", file::open),
+    ("ord", ord),
+    ("pow", pow),
+    ("print", print),
+    ("property", property),
+    ("repr", repr),
+    ("reversed", reversed),
+    ("round", round),
+    ("setattr", setattr),
+    ("sorted", sorted),
+    ("staticmethod", staticmethod),
+    ("sum", sum),
+    ("vars", vars),
+    ("zip", zip),
+];
+
+// the names dir() tries on the objects the VM implements, besides their methods
+const ATTR_NAMES: &[&str] = &[
+    "__doc__", "__func__", "__module__", "__name__", "__next__", "__qualname__", "__self__", "__wrapped__",
+    "__stderr__", "__stdin__", "__stdout__", "as_integer_ratio", "bit_count", "bit_length", "cache_clear", "cache_info",
+    "close", "conjugate", "denominator", "deleter", "encode", "fdel", "fget", "format", "format_map", "from_bytes",
+    "fromhex", "fset", "getter", "hex", "imag", "is_integer", "maketrans", "modules", "nbytes", "numerator", "obj",
+    "path", "readonly", "real", "send", "setter", "start", "stderr", "stdin", "stdout", "step", "stop", "throw",
+    "to_bytes", "translate",
+];
+
+pub fn lookup(name: &str) -> Option<NativeFunc> {
+    let (name, ptr) = *FUNCTIONS.iter().find(|(n, _)| *n == name)?;
+    Some(NativeFunc { name, ptr })
+}
+
+// any builtin, the functions, the types and object
+pub fn lookup_name(name: &str) -> Option<Obj> {
+    if let Some(t) = BUILTIN_TYPES.iter().find(|t| **t == name) {
+        return Some(Obj::BuiltinType(t));
+    }
+    match name {
+        "object" => Some(Obj::ClassDef(userclass::object_class())),
+        _ => lookup(name).map(Obj::Function),
+    }
+}
+
+// map(), filter(), zip() and enumerate() only take the next item from the
+// iterators they were made with when they are asked for one
+#[derive(Debug)]
+pub enum LazyIter {
+    Map(Arc<Obj>, Vec<Arc<Obj>>),  // function, iterators
+    Filter(Arc<Obj>, Arc<Obj>),    // function or None, iterator
+    Zip(Vec<Arc<Obj>>, bool),      // iterators, strict
+    Enumerate(Arc<Obj>, Integer),  // iterator, the next count
+    Call(Option<(Arc<Obj>, Arc<Obj>)>), // function and sentinel, None once it was returned
+}
+
+impl LazyIter
+{
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LazyIter::Map(..) => "map",
+            LazyIter::Filter(..) => "filter",
+            LazyIter::Zip(..) => "zip",
+            LazyIter::Enumerate(..) => "enumerate",
+            LazyIter::Call(..) => "callable_iterator",
+        }
+    }
+}
+
+pub(crate) fn int_arg(obj: &Obj) -> Result<Integer, PyException> {
+    match obj {
+        Obj::Int(_) | Obj::Bool(_) => Ok(obj.to_integer().unwrap()),
+        o => Err(PyException::type_error(format!("'{}' object cannot be interpreted as an integer", o.type_name()))),
+    }
+}
+
+// the type name errors show, an instance's is its class's
+pub(crate) fn class_name(obj: &Obj) -> String {
+    match obj {
+        Obj::Class(instance) => instance.class.name.clone(),
+        Obj::Native(native) => native.class.name.clone(),
+        o => o.type_name().to_string(),
+    }
+}
+
+// what type(obj) gives
+pub fn type_of(obj: &Obj) -> Obj {
+    match obj {
+        Obj::Class(instance) => Obj::ClassDef(instance.class.clone()),
+        Obj::Native(native) => Obj::NativeClass(native.class.clone()),
+        Obj::Except(e) => Obj::ExceptType(e.error),
+        Obj::Fraction(_) => Obj::BuiltinType(fractions::TYPE_NAME),
+        Obj::ExceptType(_) => Obj::BuiltinType("type"),
+        o => Obj::BuiltinType(o.type_name()),
+    }
+}
+
+// isinstance(obj, t) for one type
+pub(crate) fn is_instance(obj: &Obj, t: &Obj) -> bool {
+    is_subtype(&type_of(obj), t)
+}
+
+// An instance of a builtin type made without arguments, the methods an
+// instance has are the ones its type has unbound, like str.lower
+pub(crate) fn empty_instance(type_name: &str) -> Option<Obj> {
+    let instance = match type_name {
+        "int" => Obj::Int(Integer::ZERO),
+        "float" => Obj::Float(0.0),
+        "complex" => Funcs::complex(&[]).ok()?,
+        "str" => Obj::Str(String::new()),
+        "bytes" => Obj::Bytes(vec![]),
+        "bytearray" => bytes::new_bytearray(vec![]),
+        fractions::TYPE_NAME => Obj::Fraction(Rational::new()),
+        decimal::TYPE_NAME => Obj::Decimal(Box::new(Decimal::from_integer(&Integer::ZERO))),
+        _ => return None,
+    };
+    Some(instance)
+}
+
+// a class is a subclass of itself, bool is one of int
+fn is_subtype(t: &Obj, of: &Obj) -> bool {
+    match (t, of) {
+        (Obj::BuiltinType("bool"), Obj::BuiltinType("int")) => true,
+        (_, Obj::ClassDef(base)) if userclass::is_object(base) => true,
+        (Obj::ClassDef(class), Obj::ClassDef(base)) => {
+            Arc::ptr_eq(class, base) || class.bases.iter().any(|b| is_subtype(&Obj::ClassDef(b.clone()), of))
+        }
+        _ => t == of,
+    }
+}
+
+// the second argument of isinstance() and issubclass(), a type or a tuple
+// of them
+fn matches_classinfo(func: &str, t: &Obj, info: &Obj) -> Result<bool, PyException> {
+    match info {
+        Obj::Tuple(items) => {
+            for item in items {
+                if matches_classinfo(func, t, item)? {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        Obj::BuiltinType(_) | Obj::ClassDef(_) | Obj::ExceptType(_) | Obj::NativeClass(_) => Ok(is_subtype(t, info)),
+        _ => Err(PyException::type_error(format!("{func}() arg 2 must be a type, a tuple of types, or a union"))),
+    }
+}
+
+// Equal numbers hash the same whatever their type, like CPython they hash
+// to their value mod 2**61 - 1
+fn number_hash(obj: &Obj) -> Option<Integer> {
+    let modulus = Integer::from((1u64 << 61) - 1);
+    let r = match obj {
+        Obj::Float(f) if f.is_nan() => return Some(Integer::ZERO),
+        Obj::Float(f) if f.is_infinite() => return Some(Integer::from(314159 * f.signum() as i64)),
+        Obj::Int(_) | Obj::Bool(_) | Obj::Float(_) | Obj::Fraction(_) | Obj::Decimal(_) => obj.to_rational()?,
+        // the real part's hash plus 1000003 times the imaginary part's, in a
+        // machine word, so a complex with no imaginary part hashes as its real
+        Obj::Complex(c) => {
+            let real = number_hash(&Obj::Float(c.real().to_f64()))?.to_i64_wrapping();
+            let imag = number_hash(&Obj::Float(c.imag().to_f64()))?.to_i64_wrapping();
+            let hash = real.wrapping_add(imag.wrapping_mul(1_000_003));
+            return Some(Integer::from(if hash == -1 { -2 } else { hash }));
+        }
+        _ => return None,
+    };
+    let inverse = Integer::from(r.denom()).pow_mod(&(modulus.clone() - 2), &modulus).unwrap();
+    let mut hash = Integer::from(r.numer().abs_ref()) % &modulus * inverse % &modulus;
+    if r < 0 {
+        hash = -hash;
+    }
+    // -1 is an error code in CPython
+    Some(if hash == -1 { Integer::from(-2) } else { hash })
+}
+
+// range(stop) or range(start, stop[, step])
+fn new_range(args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+    let ints = args.iter().map(|a| int_arg(a)).collect::<Result<Vec<_>, _>>()?;
+    let (start, stop, step) = match ints.as_slice() {
+        [stop] => (Integer::ZERO, stop.clone(), Integer::from(1)),
+        [start, stop] => (start.clone(), stop.clone(), Integer::from(1)),
+        [start, stop, step] => (start.clone(), stop.clone(), step.clone()),
+        [] => return Err(PyException::type_error("range expected at least 1 argument, got 0")),
+        _ => return Err(PyException::type_error(format!("range expected at most 3 arguments, got {}", args.len()))),
+    };
+    if step == 0 {
+        return Err(PyException {
+            error: PyError::ValueError,
+            msg: "range() arg 3 must not be zero".to_string(),
+        });
+    }
+    // the repr only shows the step when it isn't 1
+    let step = (step != 1).then_some(step);
+    Ok(Obj::Range(RangeObj::from(Some(start), Some(stop), step)))
+}
+
+// builtins other than print() and the ones that bind their arguments by
+// name take no keywords
+pub(crate) fn no_kwargs(name: &str, kwargs: &[(String, Arc<Obj>)]) -> Result<(), PyException> {
+    match kwargs.is_empty() {
+        true => Ok(()),
+        false => Err(PyException::type_error(format!("{name}() takes no keyword arguments"))),
+    }
+}
+
+// the argument of a builtin that takes exactly one
+pub(crate) fn one_arg(name: &str, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs(name, kwargs)?;
+    match args {
+        [arg] => Ok(arg.clone()),
+        _ => Err(PyException::type_error(format!("{name}() takes exactly one argument ({} given)", args.len()))),
+    }
+}
+
+fn arg_count(
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+    min: usize,
+    max: usize,
+) -> Result<(), PyException> {
+    no_kwargs(name, kwargs)?;
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let expected = match args.len() {
+        n if min == max && n != min => format!("{min} argument{}", plural(min)),
+        n if n < min => format!("at least {min} argument{}", plural(min)),
+        n if n > max => format!("at most {max} argument{}", plural(max)),
+        _ => return Ok(()),
+    };
+    Err(PyException::type_error(format!("{name} expected {expected}, got {}", args.len())))
+}
+
+// __import__(name, globals=None, locals=None, fromlist=(), level=0), what
+// an import statement calls. The globals and locals aren't used, a
+// relative import is from the package of the running code
+fn import(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let params = bind_native_args("__import__", &["name", "globals", "locals", "fromlist", "level"], 5, args, kwargs)?;
+    let name = match params[0].as_deref() {
+        Some(Obj::Str(name)) => name.clone(),
+        Some(o) => return Err(PyException::type_error(format!("__import__() argument 1 must be str, not {}", o.type_name()))),
+        None => return Err(PyException::type_error("__import__() missing required argument 'name' (pos 1)")),
+    };
+    let fromlist = match &params[3] {
+        Some(fromlist) if **fromlist != Obj::None => vm.iter_items(fromlist)?.iter().map(|n| n.__str__()).collect(),
+        _ => vec![],
+    };
+    let level = params[4].as_deref().map(int_arg).transpose()?.unwrap_or_default();
+    let value_err = |msg: &str| PyException {
+        error: PyError::ValueError,
+        msg: msg.to_string(),
+    };
+    let Some(level) = level.to_usize() else {
+        return Err(value_err("level must be >= 0"));
+    };
+    if name.is_empty() && level == 0 {
+        return Err(value_err("Empty module name"));
+    }
+    vm.import(&name, level, &fromlist)
+}
+
+fn abs(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("abs", kwargs)?;
+    vm.abs(args)
+}
+
+fn divmod(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("divmod", kwargs)?;
+    Funcs::divmod(args).map(Arc::from)
+}
+
+fn pow(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("pow", kwargs)?;
+    Funcs::pow(args).map(Arc::from)
+}
+
+fn round(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("round", kwargs)?;
+    vm.round(args)
+}
+
+fn format(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("format", kwargs)?;
+    vm.format_builtin(args)
+}
+
+fn next(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("next", kwargs)?;
+    vm.next(args)
+}
+
+// print(*objects, sep=' ', end='\n', file=None, flush=False), the pieces
+// go one by one to file.write, sys.stdout's when file is None
+fn print(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let (mut sep, mut end, mut file, mut flush) = (" ".to_string(), "\n".to_string(), None, false);
+    for (key, val) in kwargs {
+        match (key.as_str(), val.as_ref()) {
+            ("sep" | "end" | "file", Obj::None) => {}
+            ("sep", Obj::Str(s)) => sep = s.clone(),
+            ("end", Obj::Str(s)) => end = s.clone(),
+            ("sep" | "end", o) => return Err(PyException::type_error(format!("{key} must be None or a string, not {}", o.type_name()))),
+            ("file", _) => file = Some(val.clone()),
+            ("flush", _) => flush = vm.truthy(val)?,
+            _ => return Err(PyException::type_error(format!("'{key}' is an invalid keyword argument for print()"))),
+        }
+    }
+    let file = match file {
+        Some(file) => file,
+        None => vm.sys_stream("stdout"),
+    };
+    // with no sys.stdout there's nowhere to print to
+    if *file == Obj::None {
+        return Ok(Obj::None.into());
+    }
+    let write = vm.get_attr(&file, "write")?;
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            vm.call_object(write.clone(), vec![Obj::Str(sep.clone()).into()], vec![])?;
+        }
+        let text = vm.str_of(arg, false)?;
+        vm.call_object(write.clone(), vec![Obj::Str(text).into()], vec![])?;
+    }
+    vm.call_object(write, vec![Obj::Str(end).into()], vec![])?;
+    if flush {
+        let flush = vm.get_attr(&file, "flush")?;
+        vm.call_object(flush, vec![], vec![])?;
+    }
+    Ok(Obj::None.into())
+}
+
+// input([prompt]), a line of sys.stdin without its newline
+fn input(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("input", args, kwargs, 0, 1)?;
+    let lost = |name: &str| PyException {
+        error: PyError::RuntimeError,
+        msg: format!("input(): lost sys.{name}"),
+    };
+    if let Some(prompt) = args.first() {
+        let stdout = vm.sys_stream("stdout");
+        if *stdout == Obj::None {
+            return Err(lost("stdout"));
+        }
+        let text = vm.str_of(prompt, false)?;
+        let write = vm.get_attr(&stdout, "write")?;
+        vm.call_object(write, vec![Obj::Str(text).into()], vec![])?;
+        let flush = vm.get_attr(&stdout, "flush")?;
+        vm.call_object(flush, vec![], vec![])?;
+    }
+    let stdin = vm.sys_stream("stdin");
+    if *stdin == Obj::None {
+        return Err(lost("stdin"));
+    }
+    let readline = vm.get_attr(&stdin, "readline")?;
+    match vm.call_object(readline, vec![], vec![])?.as_ref() {
+        Obj::Str(line) if line.is_empty() => Err(PyException {
+            error: PyError::EOFError,
+            msg: "EOF when reading a line".to_string(),
+        }),
+        Obj::Str(line) => Ok(Obj::Str(line.strip_suffix('\n').unwrap_or(line).to_string()).into()),
+        o => Err(PyException::type_error(format!("object.readline() returned non-string ({})", o.type_name()))),
+    }
+}
+
+// all() and any() stop at the first item that decides them
+fn all_or_any(
+    vm: &mut PyVM,
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+) -> Result<Arc<Obj>, PyException> {
+    let iter = vm.iter_of(&one_arg(name, args, kwargs)?)?;
+    while let Some(item) = vm.next_item(&iter)? {
+        if vm.truthy(&item)? == (name == "any") {
+            return Ok(Obj::Bool(name == "any").into());
+        }
+    }
+    Ok(Obj::Bool(name == "all").into())
+}
+
+fn all(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    all_or_any(vm, "all", args, kwargs)
+}
+
+fn any(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    all_or_any(vm, "any", args, kwargs)
+}
+
+fn len(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    vm.len(&one_arg("len", args, kwargs)?).map(|n| Obj::Int(n.into()).into())
+}
+
+fn repr(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    Ok(Obj::Str(vm.str_of(&one_arg("repr", args, kwargs)?, true)?).into())
+}
+
+fn ascii(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let repr = vm.str_of(&one_arg("ascii", args, kwargs)?, true)?;
+    Ok(Obj::Str(pyrs_format::ascii_escape(&repr)).into())
+}
+
+// bin(), oct() and hex()
+fn int_to_base(name: &str, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let obj = one_arg(name, args, kwargs)?;
+    let i = int_arg(&obj)?;
+    let digits = match name {
+        "bin" => format!("0b{:b}", i.clone().abs()),
+        "oct" => format!("0o{:o}", i.clone().abs()),
+        _ => format!("0x{:x}", i.clone().abs()),
+    };
+    Ok(Obj::Str(if i < 0 { format!("-{digits}") } else { digits }).into())
+}
+
+fn bin(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    int_to_base("bin", args, kwargs)
+}
+
+fn oct(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    int_to_base("oct", args, kwargs)
+}
+
+fn hex(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    int_to_base("hex", args, kwargs)
+}
+
+fn ord(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let obj = one_arg("ord", args, kwargs)?;
+    let (code, len) = match obj.as_ref() {
+        Obj::Str(s) => {
+            let mut chars = s.chars();
+            (chars.next().map(|c| c as u32), s.chars().count())
+        }
+        Obj::Bytes(_) | Obj::ByteArray(_) => {
+            let b = bytes::bytes_of(&obj).unwrap();
+            (b.first().map(|&b| b as u32), b.len())
+        }
+        o => {
+            return Err(PyException::type_error(format!(
+                "ord() expected string of length 1, but {} found",
+                o.type_name()
+            )))
+        }
+    };
+    match (code, len) {
+        (Some(code), 1) => Ok(Obj::Int(code.into()).into()),
+        _ => Err(PyException::type_error(format!("ord() expected a character, but string of length {len} found"))),
+    }
+}
+
+fn chr(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let obj = one_arg("chr", args, kwargs)?;
+    let code = int_arg(&obj)?;
+    match code.to_u32().and_then(char::from_u32) {
+        Some(c) => Ok(Obj::Str(c.to_string()).into()),
+        None => Err(PyException {
+            error: PyError::ValueError,
+            msg: "chr() arg not in range(0x110000)".to_string(),
+        }),
+    }
+}
+
+fn callable(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    Ok(Obj::Bool(one_arg("callable", args, kwargs)?.is_callable()).into())
+}
+
+fn id(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    Ok(Obj::Int((Arc::as_ptr(&one_arg("id", args, kwargs)?) as usize).into()).into())
+}
+
+fn hash(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    vm.hash(&one_arg("hash", args, kwargs)?).map(|h| Obj::Int(h).into())
+}
+
+// iter(iterable), or iter(callable, sentinel) which calls it until it
+// returns the sentinel
+fn iter(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("iter", args, kwargs, 1, 2)?;
+    match args {
+        [func, sentinel] if func.is_callable() => {
+            Ok(Obj::LazyIter(Arc::new(Mutex::new(LazyIter::Call(Some((func.clone(), sentinel.clone())))))).into())
+        }
+        [_, _] => Err(PyException::type_error("iter(v, w): v must be callable")),
+        _ => vm.iter_of(&args[0]),
+    }
+}
+
+fn isinstance(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("isinstance", args, kwargs, 2, 2)?;
+    matches_classinfo("isinstance", &type_of(&args[0]), &args[1]).map(|b| Obj::Bool(b).into())
+}
+
+fn issubclass(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("issubclass", args, kwargs, 2, 2)?;
+    match args[0].as_ref() {
+        t @ (Obj::BuiltinType(_) | Obj::ClassDef(_) | Obj::ExceptType(_) | Obj::NativeClass(_)) => {
+            matches_classinfo("issubclass", t, &args[1]).map(|b| Obj::Bool(b).into())
+        }
+        _ => Err(PyException::type_error("issubclass() arg 1 must be a class")),
+    }
+}
+
+// the attribute name getattr(), hasattr(), setattr() and delattr() take second
+fn attr_name<'a>(func: &str, args: &'a [Arc<Obj>]) -> Result<&'a str, PyException> {
+    match args[1].as_ref() {
+        Obj::Str(attr) => Ok(attr),
+        _ => Err(PyException::type_error(format!("{func}(): attribute name must be string"))),
+    }
+}
+
+fn getattr(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("getattr", args, kwargs, 2, 3)?;
+    let attr = attr_name("getattr", args)?;
+    match (vm.get_attr(&args[0], attr), args.get(2)) {
+        (Err(e), Some(default)) if e.error == PyError::AttributeError => Ok(default.clone()),
+        (ret, _) => ret,
+    }
+}
+
+fn hasattr(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("hasattr", args, kwargs, 2, 2)?;
+    let attr = attr_name("hasattr", args)?;
+    match vm.get_attr(&args[0], attr) {
+        Ok(_) => Ok(Obj::Bool(true).into()),
+        Err(e) if e.error == PyError::AttributeError => Ok(Obj::Bool(false).into()),
+        Err(e) => Err(e),
+    }
+}
+
+// aiter(obj) and anext(it) call __aiter__ and __anext__. The VM has no
+// await, so anext gives the awaitable and its default is never needed
+fn aiter(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let obj = one_arg("aiter", args, kwargs)?;
+    call_special(vm, &obj, "__aiter__", "is not an async iterable")
+}
+
+fn anext(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("anext", args, kwargs, 1, 2)?;
+    call_special(vm, &args[0], "__anext__", "is not an async iterator")
+}
+
+fn call_special(vm: &mut PyVM, obj: &Arc<Obj>, method: &str, missing: &str) -> Result<Arc<Obj>, PyException> {
+    match obj.as_ref() {
+        Obj::Class(instance) if instance.class.lookup(method).is_some() => {
+            let method = instance.class.lookup(method).unwrap();
+            vm.call_object(Obj::Method(obj.clone(), method).into(), vec![], vec![])
+        }
+        _ => Err(PyException::type_error(format!("'{}' object {missing}", class_name(obj)))),
+    }
+}
+
+// breakpoint() calls the function PYTHONBREAKPOINT names, pdb.set_trace
+// unless it is set, and 0 turns it off. One that can't be imported is
+// warned about and skipped
+fn breakpoint(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let hook = std::env::var("PYTHONBREAKPOINT").ok().filter(|h| !h.is_empty());
+    let hook = hook.as_deref().unwrap_or("pdb.set_trace");
+    if hook == "0" {
+        return Ok(Obj::None.into());
+    }
+    let (module, name) = hook.rsplit_once('.').unwrap_or(("builtins", hook));
+    let func = match vm.import(module, 0, &[]) {
+        Ok(module) => vm.get_attr(&module, name).ok(),
+        Err(_) => None,
+    };
+    match func {
+        Some(func) => vm.call_object(func, args.to_vec(), kwargs.to_vec()),
+        None => {
+            let stderr = vm.sys_stream("stderr");
+            if *stderr != Obj::None {
+                let warning = format!("RuntimeWarning: Ignoring unimportable $PYTHONBREAKPOINT: \"{hook}\"\n");
+                let write = vm.get_attr(&stderr, "write")?;
+                vm.call_object(write, vec![Obj::Str(warning).into()], vec![])?;
+            }
+            Ok(Obj::None.into())
+        }
+    }
+}
+
+// help(obj) prints what it is and its docstring, there's no interactive help
+fn help(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("help", args, kwargs, 0, 1)?;
+    let Some(obj) = args.first() else {
+        let text = Obj::Str("Type help(object) for help about object.".to_string());
+        return print(vm, &[text.into()], &[]);
+    };
+    let kind = match obj.as_ref() {
+        Obj::UserFunc(_) | Obj::Method(..) => "function".to_string(),
+        Obj::Function(_) | Obj::BuiltinMethod(..) => "built-in function".to_string(),
+        Obj::ClassDef(_) | Obj::BuiltinType(_) | Obj::ExceptType(_) | Obj::NativeClass(_) => "class".to_string(),
+        Obj::Module(_) | Obj::PyModule(_) => "module".to_string(),
+        _ => format!("{} object", class_name(obj)),
+    };
+    let mut text = format!("Help on {kind} {}:\n", obj.func_name());
+    if let Ok(doc) = vm.get_attr(obj, "__doc__") {
+        if *doc != Obj::None {
+            text.push('\n');
+            for line in doc.__str__().lines().map(str::trim) {
+                match line.is_empty() {
+                    true => text.push('\n'),
+                    false => text.push_str(&format!("    {line}\n")),
+                }
+            }
+        }
+    }
+    print(vm, &[Obj::Str(text).into()], &[])
+}
+
+// The code compile() gives for the source, eval mode code ends by
+// returning the expression's value. A code object is already compiled
+fn compile_source(func: &str, source: &Obj, filename: &str, mode: &str) -> Result<Vec<PyBytecode>, PyException> {
+    let text = match source {
+        Obj::Code(code) => return Ok(code.co_code.clone()),
+        Obj::Str(s) => s.clone(),
+        Obj::Bytes(_) | Obj::ByteArray(_) | Obj::MemoryView(_) => {
+            bytes::decode(&bytes::bytes_of(source).unwrap(), "utf-8", "strict")?
+        }
+        o => {
+            return Err(PyException::type_error(format!(
+                "{func}() arg 1 must be a string, bytes or code object, not {}",
+                o.type_name()
+            )))
+        }
+    };
+    match mode {
+        "eval" => {
+            let mut code = compile_expr(&text, filename)?;
+            code.push(PyBytecode::ReturnValue);
+            Ok(code)
+        }
+        _ => Interpreter::compile_source(&text).map_err(|e| in_file(e, filename)),
+    }
+}
+
+fn compile(_vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let params = bind_native_args(
+        "compile",
+        &["source", "filename", "mode", "flags", "dont_inherit", "optimize"],
+        6,
+        args,
+        kwargs,
+    )?;
+    let [Some(source), Some(filename), Some(mode), ..] = params.as_slice() else {
+        let missing = ["source", "filename", "mode"].iter().zip(&params).position(|(_, p)| p.is_none()).unwrap();
+        return Err(PyException::type_error(format!(
+            "compile() missing required argument '{}' (pos {})",
+            ["source", "filename", "mode"][missing],
+            missing + 1
+        )));
+    };
+    let mode = mode.__str__();
+    if !matches!(mode.as_str(), "exec" | "eval" | "single") {
+        return Err(PyException {
+            error: PyError::ValueError,
+            msg: "compile() mode must be 'exec', 'eval' or 'single'".to_string(),
+        });
+    }
+    if let Obj::Code(_) = source.as_ref() {
+        return Ok(source.clone());
+    }
+    let code = compile_source("compile", source, &filename.__str__(), &mode)?;
+    Ok(Obj::Code(Arc::new(CodeObject::new("<module>", code))).into())
+}
+
+fn eval(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    run_source(vm, "eval", args, kwargs)
+}
+
+fn exec(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    run_source(vm, "exec", args, kwargs).map(|_| Obj::None.into())
+}
+
+// A dict's str keys, for the names exec() and eval() run with
+fn dict_names(dict: &Obj) -> HashMap<String, Arc<Obj>> {
+    match dict {
+        Obj::Dict(map) => map.lock().unwrap().iter().map(|(k, v)| (k.__str__(), v.clone())).collect(),
+        _ => HashMap::new(),
+    }
+}
+
+fn store_names(dict: &Obj, names: impl IntoIterator<Item = (String, Arc<Obj>)>) {
+    if let Obj::Dict(map) = dict {
+        let mut map = map.lock().unwrap();
+        for (name, val) in names {
+            map.insert(Obj::Str(name), val);
+        }
+    }
+}
+
+// eval(source, globals, locals) and exec(). The code runs on a frame of its
+// own, with a globals dict given the names it assigns go in the dict. Without
+// one it runs in the caller's scope, at the top level its names are the
+// caller's globals and in a function they go in a copy of the locals
+fn run_source(vm: &mut PyVM, func: &str, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let params = bind_native_args(func, &["source", "globals", "locals"], 3, args, kwargs)?;
+    let Some(source) = &params[0] else {
+        return Err(PyException::type_error(format!("{func}() missing required argument 'source' (pos 1)")));
+    };
+    let dict_arg = |arg: &Option<Arc<Obj>>, what: &str| match arg.as_deref() {
+        None | Some(Obj::None) => Ok(None),
+        Some(Obj::Dict(_)) => Ok(arg.clone()),
+        Some(o) => Err(PyException::type_error(format!("{func}() {what} must be a dict, not {}", o.type_name()))),
+    };
+    let globals = dict_arg(&params[1], "globals")?;
+    let locals = dict_arg(&params[2], "locals")?;
+
+    let mut code = compile_source(func, source, "<string>", func)?;
+    // exec leaves an eval mode code object's value on the stack
+    if func == "exec" && code.last() == Some(&PyBytecode::ReturnValue) {
+        code.pop();
+    }
+
+    if let Some(globals) = &globals {
+        let mut names = dict_names(globals);
+        let given = names.clone();
+        names.extend(locals.as_deref().map(dict_names).unwrap_or_default());
+        let module = Arc::new(PyModule::new("__main__", names));
+        let ret = vm.exec_module(code, &module);
+        // with both, the names it didn't change are left in the globals
+        let names = module.attrs.lock().unwrap().clone();
+        match &locals {
+            Some(locals) => store_names(
+                locals,
+                names.into_iter().filter(|(name, val)| !given.get(name).is_some_and(|g| Arc::ptr_eq(g, val))),
+            ),
+            None => store_names(globals, names),
+        }
+        return ret;
+    }
+    if let (Some(module), None) = (vm.module_body(), &locals) {
+        return vm.exec_module(code, &module);
+    }
+
+    // the names it assigns come back from its locals the way a class body's do
+    let assigned: Vec<String> = code
+        .iter()
+        .filter_map(|inst| match inst {
+            PyBytecode::StoreName(name) => Some(name.clone()),
+            _ => None,
+        })
+        .collect();
+    let is_exec = code.last() != Some(&PyBytecode::ReturnValue);
+    if is_exec {
+        code.push(PyBytecode::LoadLocals);
+        code.push(PyBytecode::ReturnValue);
+    }
+    let mut code = CodeObject::new("<module>", code);
+    code.co_varnames = assigned;
+    let names = match &locals {
+        Some(locals) => dict_names(locals),
+        None => {
+            let mut names = vm.global_names();
+            names.extend(vm.local_names());
+            names
+        }
+    };
+    let ret = vm.exec_locals(code, names)?;
+    if !is_exec {
+        return Ok(ret);
+    }
+    match (&locals, vm.in_function()) {
+        (Some(locals), _) => store_names(locals, dict_names(&ret)),
+        (None, false) => vm.set_global_names(dict_names(&ret)),
+        (None, true) => {}
+    }
+    Ok(Obj::None.into())
+}
+
+// whether the code is one of the class's own functions, the way super()
+// finds the class the running method was defined in
+pub(crate) fn defines(class: &UserClassDef, code: &Arc<CodeObject>) -> bool {
+    let is_code = |func: &Obj| matches!(func, Obj::UserFunc(func) if Arc::ptr_eq(&func.code, code));
+    class.attrs.lock().unwrap().values().any(|attr| match attr.as_ref() {
+        Obj::StaticMethod(func) | Obj::ClassMethod(func) => is_code(func),
+        Obj::Property(p) => [&p.fget, &p.fset, &p.fdel].into_iter().flatten().any(|func| is_code(func)),
+        func => is_code(func),
+    })
+}
+
+// object.__init__, what super().__init__() finds when no base has one
+pub(crate) fn object_init(_vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    match args.len() > 1 || !kwargs.is_empty() {
+        true => Err(PyException::type_error("object.__init__() takes exactly one argument (the instance to initialize)")),
+        false => Ok(Obj::None.into()),
+    }
+}
+
+// A dict of the names, a new one each time so storing into it doesn't
+// change the variables
+fn names_dict(names: HashMap<String, Arc<Obj>>) -> Arc<Obj> {
+    Obj::new_dict_from(names.into_iter().map(|(name, val)| (Obj::Str(name), val)).collect()).into()
+}
+
+fn sorted_names(names: impl IntoIterator<Item = String>) -> Arc<Obj> {
+    let mut names: Vec<String> = names.into_iter().collect();
+    names.sort();
+    names.dedup();
+    Obj::new_list(names.into_iter().map(|name| Obj::Str(name).into()).collect()).into()
+}
+
+fn no_args(name: &str, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<(), PyException> {
+    no_kwargs(name, kwargs)?;
+    match args.is_empty() {
+        true => Ok(()),
+        false => Err(PyException::type_error(format!("{name}() takes no arguments ({} given)", args.len()))),
+    }
+}
+
+fn globals(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_args("globals", args, kwargs)?;
+    Ok(names_dict(vm.global_names()))
+}
+
+fn locals(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_args("locals", args, kwargs)?;
+    Ok(names_dict(vm.local_names()))
+}
+
+// vars(obj) is obj.__dict__, vars() is locals()
+fn vars(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("vars", args, kwargs, 0, 1)?;
+    let Some(obj) = args.first() else {
+        return Ok(names_dict(vm.local_names()));
+    };
+    let names = match obj.as_ref() {
+        Obj::Class(instance) => instance.fields.lock().unwrap().clone(),
+        Obj::ClassDef(class) => class.attrs.lock().unwrap().clone(),
+        Obj::UserFunc(func) => func.attrs.clone(),
+        Obj::PyModule(module) => module.attrs.lock().unwrap().clone(),
+        _ => return Err(PyException::type_error("vars() argument must have __dict__ attribute")),
+    };
+    Ok(names_dict(names))
+}
+
+// dir(obj), its attribute names sorted, or dir() the names in scope. An
+// instance's class can give its own with __dir__
+fn dir(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("dir", args, kwargs, 0, 1)?;
+    let Some(obj) = args.first() else {
+        return Ok(sorted_names(vm.local_names().into_keys()));
+    };
+    if let Obj::Class(instance) = obj.as_ref() {
+        if let Some(method) = instance.class.lookup("__dir__") {
+            let names = vm.call_object(Obj::Method(obj.clone(), method).into(), vec![], vec![])?;
+            let names = vm.iter_items(&names)?;
+            return vm.sort(names, None, false).map(|names| Obj::new_list(names).into());
+        }
+    }
+    let names = match obj.as_ref() {
+        Obj::Class(instance) => {
+            let mut names: Vec<String> = instance.fields.lock().unwrap().keys().cloned().collect();
+            names.extend(instance.class.attr_names());
+            names
+        }
+        Obj::ClassDef(class) => class.attr_names(),
+        Obj::UserFunc(func) => func.attrs.keys().cloned().collect(),
+        Obj::PyModule(module) => module.attrs.lock().unwrap().keys().cloned().collect(),
+        Obj::NativeClass(class) => class.names(),
+        Obj::Native(native) => native.class.names(),
+        // the rest have no attribute dict, they have what they answer to
+        _ => {
+            let candidates = [
+                ATTR_NAMES,
+                &pyrs_str::METHODS,
+                &bytes::METHODS,
+                &bytes::BYTEARRAY_METHODS,
+                &bytes::MEMORYVIEW_METHODS,
+                &fractions::METHODS,
+                &decimal::METHODS,
+                &functools::NAMES,
+                &BUILTIN_TYPES,
+            ];
+            let functions = FUNCTIONS.iter().map(|(name, _)| *name);
+            let mut names = vec![];
+            for name in candidates.into_iter().flatten().copied().chain(functions) {
+                if vm.get_attr(obj, name).is_ok() {
+                    names.push(name.to_string());
+                }
+            }
+            names
+        }
+    };
+    Ok(sorted_names(names))
+}
+
+fn delattr(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("delattr", args, kwargs, 2, 2)?;
+    let attr = attr_name("delattr", args)?.to_string();
+    vm.del_attr(&args[0], attr)?;
+    Ok(Obj::None.into())
+}
+
+fn setattr(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("setattr", args, kwargs, 3, 3)?;
+    let attr = attr_name("setattr", args)?.to_string();
+    vm.set_attr(&args[0], attr, args[2].clone())?;
+    Ok(Obj::None.into())
+}
+
+fn sum(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let params = bind_native_args("sum", &["iterable", "start"], 2, args, kwargs)?;
+    let Some(iterable) = &params[0] else {
+        return Err(PyException::type_error(format!("sum() takes at least 1 positional argument ({} given)", args.len())));
+    };
+    let mut total = params[1].clone().unwrap_or(Obj::Int(0.into()).into());
+    let join = match total.as_ref() {
+        Obj::Str(_) => Some("strings [use ''"),
+        Obj::Bytes(_) => Some("bytes [use b''"),
+        Obj::ByteArray(_) => Some("bytearray [use b''"),
+        _ => None,
+    };
+    if let Some(join) = join {
+        return Err(PyException::type_error(format!("sum() can't sum {join}.join(seq) instead]")));
+    }
+    let iter = vm.iter_of(iterable)?;
+    while let Some(item) = vm.next_item(&iter)? {
+        total = Obj::__add__(&total, &item)?;
+    }
+    Ok(total)
+}
+
+fn min(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    vm.min_max("min", args, kwargs)
+}
+
+fn max(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    vm.min_max("max", args, kwargs)
+}
+
+fn sorted(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let params = bind_native_args("sorted", &["iterable", "key", "reverse"], 1, args, kwargs)?;
+    let Some(iterable) = &params[0] else {
+        return Err(PyException::type_error(format!("sorted expected 1 argument, got {}", args.len())));
+    };
+    let items = vm.iter_items(iterable)?;
+    let reverse = match &params[2] {
+        Some(reverse) => int_arg(reverse)? != 0,
+        None => false,
+    };
+    vm.sort(items, params[1].clone(), reverse).map(|items| Obj::new_list(items).into())
+}
+
+fn reversed(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("reversed", args, kwargs, 1, 1)?;
+    vm.reversed(&args[0])
+}
+
+fn enumerate(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let params = bind_native_args("enumerate", &["iterable", "start"], 2, args, kwargs)?;
+    let Some(iterable) = &params[0] else {
+        return Err(PyException::type_error("enumerate() missing required argument 'iterable' (pos 1)"));
+    };
+    let start = params[1].as_deref().map(int_arg).transpose()?.unwrap_or_default();
+    let iter = vm.iter_of(iterable)?;
+    Ok(Obj::LazyIter(Arc::new(Mutex::new(LazyIter::Enumerate(iter, start)))).into())
+}
+
+fn zip(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let params = bind_native_args("zip", &["strict"], 0, &[], kwargs)?;
+    let strict = params[0].as_ref().is_some_and(|s| s.__bool__());
+    let iters = args.iter().map(|a| vm.iter_of(a)).collect::<Result<_, _>>()?;
+    Ok(Obj::LazyIter(Arc::new(Mutex::new(LazyIter::Zip(iters, strict)))).into())
+}
+
+fn map(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("map", kwargs)?;
+    let [func, iterables @ ..] = args else {
+        return Err(PyException::type_error("map() must have at least two arguments."));
+    };
+    if iterables.is_empty() {
+        return Err(PyException::type_error("map() must have at least two arguments."));
+    }
+    let iters = iterables.iter().map(|a| vm.iter_of(a)).collect::<Result<_, _>>()?;
+    Ok(Obj::LazyIter(Arc::new(Mutex::new(LazyIter::Map(func.clone(), iters)))).into())
+}
+
+fn filter(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("filter", args, kwargs, 2, 2)?;
+    let iter = vm.iter_of(&args[1])?;
+    Ok(Obj::LazyIter(Arc::new(Mutex::new(LazyIter::Filter(args[0].clone(), iter)))).into())
+}
+
+fn staticmethod(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("staticmethod", args, kwargs, 1, 1)?;
+    Ok(Obj::StaticMethod(args[0].clone()).into())
+}
+
+fn classmethod(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    arg_count("classmethod", args, kwargs, 1, 1)?;
+    Ok(Obj::ClassMethod(args[0].clone()).into())
+}
+
+// property(fget, fset, fdel, doc), the doc isn't kept
+fn property(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("property", kwargs)?;
+    if args.len() > 4 {
+        return Err(PyException::type_error(format!("property() takes at most 4 arguments ({} given)", args.len())));
+    }
+    let func = |idx: usize| match args.get(idx).map(|a| a.as_ref()) {
+        None | Some(Obj::None) => None,
+        Some(_) => Some(args[idx].clone()),
+    };
+    let p = Property {
+        fget: func(0),
+        fset: func(1),
+        fdel: func(2),
+    };
+    Ok(Obj::Property(Arc::new(p)).into())
+}
+
+impl PyVM
+{
+    fn sys_stream(&mut self, name: &str) -> Arc<Obj> {
+        self.import_state().sys_attr(name).unwrap()
+    }
+
+    // str(), list() and the other types, called to make one
+    pub(crate) fn construct(
+        &mut self,
+        type_name: &str,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+    ) -> Result<Arc<Obj>, PyException> {
+        if !kwargs.is_empty() && !matches!(type_name, "str" | "dict") {
+            return Err(PyException::type_error(format!("{type_name}() takes no keyword arguments")));
+        }
+        let at_most = |max: usize| match args.len() > max {
+            true => Err(PyException::type_error(format!(
+                "{type_name} expected at most {max} argument{}, got {}",
+                if max == 1 { "" } else { "s" },
+                args.len()
+            ))),
+            false => Ok(()),
+        };
+        match type_name {
+            "str" => {
+                let params = bind_native_args("str", &["object", "encoding", "errors"], 3, args, kwargs)?;
+                match (&params[0], params[1].is_some() || params[2].is_some()) {
+                    (None, _) => Ok(Obj::Str(String::new()).into()),
+                    (Some(obj), false) => Ok(Obj::Str(self.str_of(obj, false)?).into()),
+                    (Some(obj), true) => {
+                        let Some(b) = bytes::bytes_of(obj) else {
+                            return Err(PyException::type_error(match obj.as_ref() {
+                                Obj::Str(_) => "decoding str is not supported".to_string(),
+                                o => format!("decoding to str: need a bytes-like object, {} found", o.type_name()),
+                            }));
+                        };
+                        let encoding = params[1].as_deref().map(|e| e.__str__()).unwrap_or("utf-8".to_string());
+                        let errors = params[2].as_deref().map(|e| e.__str__()).unwrap_or("strict".to_string());
+                        bytes::decode(&b, &encoding, &errors).map(|s| Obj::Str(s).into())
+                    }
+                }
+            }
+            "bool" => {
+                at_most(1)?;
+                match args.first() {
+                    Some(obj) => self.truthy(obj).map(|b| Obj::Bool(b).into()),
+                    None => Ok(Obj::Bool(false).into()),
+                }
+            }
+            "list" | "tuple" | "set" | "frozenset" => {
+                at_most(1)?;
+                let items = match args.first() {
+                    Some(iterable) => self.iter_items(iterable)?,
+                    None => vec![],
+                };
+                Ok(match type_name {
+                    "list" => Obj::new_list(items),
+                    "tuple" => Obj::Tuple(items),
+                    _ => {
+                        let mut set = vec![];
+                        for item in items {
+                            PyVM::check_hashable(&item)?;
+                            if !set.contains(&item) {
+                                set.push(item);
+                            }
+                        }
+                        match type_name {
+                            "set" => Obj::Set(set),
+                            _ => Obj::FrozenSet(set),
+                        }
+                    }
+                }
+                .into())
+            }
+            "dict" => {
+                at_most(1)?;
+                self.new_dict(args.first(), kwargs)
+            }
+            "slice" => match args {
+                [stop] => Ok(Obj::Slice(Obj::None.into(), stop.clone(), Obj::None.into()).into()),
+                [start, stop] => Ok(Obj::Slice(start.clone(), stop.clone(), Obj::None.into()).into()),
+                [start, stop, step] => Ok(Obj::Slice(start.clone(), stop.clone(), step.clone()).into()),
+                [] => Err(PyException::type_error("slice expected at least 1 argument, got 0")),
+                _ => Err(PyException::type_error(format!("slice expected at most 3 arguments, got {}", args.len()))),
+            },
+            "range" => new_range(args).map(Arc::from),
+            "complex" => Funcs::complex(args).map(Arc::from),
+            "super" => {
+                let (class, obj) = match args {
+                    [] => self.super_args()?,
+                    [class, obj] => {
+                        let Obj::ClassDef(class) = class.as_ref() else {
+                            return Err(PyException::type_error(format!(
+                                "super() argument 1 must be a type, not {}",
+                                class.type_name()
+                            )));
+                        };
+                        let obj_type = match obj.as_ref() {
+                            Obj::ClassDef(_) => obj.as_ref().clone(),
+                            _ => type_of(obj),
+                        };
+                        if !is_subtype(&obj_type, &Obj::ClassDef(class.clone())) {
+                            return Err(PyException::type_error(
+                                "super(type, obj): obj must be an instance or subtype of type",
+                            ));
+                        }
+                        (class.clone(), obj.clone())
+                    }
+                    _ => return Err(PyException::type_error(format!("super() takes 0 or 2 arguments ({} given)", args.len()))),
+                };
+                Ok(Obj::Super(class, obj).into())
+            }
+            "type" => match args {
+                [obj] => Ok(type_of(obj).into()),
+                [name, bases, attrs] => new_class(name, bases, attrs),
+                _ => Err(PyException::type_error("type() takes 1 or 3 arguments")),
+            },
+            _ => Err(PyException::type_error(format!("cannot create '{type_name}' instances"))),
+        }
+    }
+
+    // dict(mapping or iterable of pairs, **kwargs)
+    #[allow(clippy::mutable_key_type)]
+    fn new_dict(
+        &mut self,
+        source: Option<&Arc<Obj>>,
+        kwargs: &[(String, Arc<Obj>)],
+    ) -> Result<Arc<Obj>, PyException> {
+        let mut map = HashMap::new();
+        match source.map(|s| s.as_ref()) {
+            None => {}
+            Some(Obj::Dict(source)) => map = source.lock().unwrap().clone(),
+            Some(_) => {
+                let pairs = self.iter_items(source.unwrap())?;
+                for (i, pair) in pairs.iter().enumerate() {
+                    let Some(pair) = pair.iter_py() else {
+                        return Err(PyException::type_error(format!(
+                            "cannot convert dictionary update sequence element #{i} to a sequence"
+                        )));
+                    };
+                    let pair = pair.get_items();
+                    let [key, val] = pair.as_slice() else {
+                        return Err(PyException {
+                            error: PyError::ValueError,
+                            msg: format!(
+                                "dictionary update sequence element #{i} has length {}; 2 is required",
+                                pair.len()
+                            ),
+                        });
+                    };
+                    PyVM::check_hashable(key)?;
+                    map.insert(key.as_ref().clone(), val.clone());
+                }
+            }
+        }
+        for (key, val) in kwargs {
+            map.insert(Obj::Str(key.clone()), val.clone());
+        }
+        Ok(Obj::new_dict_from(map).into())
+    }
+
+    // bool(obj), an instance's comes from __bool__ or else __len__
+    pub(crate) fn truthy(&mut self, obj: &Arc<Obj>) -> Result<bool, PyException> {
+        let Obj::Class(instance) = obj.as_ref() else {
+            return Ok(obj.__bool__());
+        };
+        if let Some(method) = instance.class.lookup("__bool__") {
+            return match self.call_object(Obj::Method(obj.clone(), method).into(), vec![], vec![])?.as_ref() {
+                Obj::Bool(b) => Ok(*b),
+                o => Err(PyException::type_error(format!("__bool__ should return bool, returned {}", o.type_name()))),
+            };
+        }
+        match instance.class.lookup("__len__") {
+            Some(_) => Ok(self.len(obj)? != 0),
+            None => Ok(true),
+        }
+    }
+
+    fn len(&mut self, obj: &Arc<Obj>) -> Result<usize, PyException> {
+        let method = match obj.as_ref() {
+            Obj::Class(instance) => instance.class.lookup("__len__"),
+            Obj::Native(native) => native.class.lookup("__len__"),
+            _ => None,
+        };
+        if let Some(method) = method {
+            let len = self.call_object(Obj::Method(obj.clone(), method).into(), vec![], vec![])?;
+            let len = int_arg(&len)?;
+            return len.to_usize().ok_or_else(|| PyException {
+                error: PyError::ValueError,
+                msg: "__len__() should return >= 0".to_string(),
+            });
+        }
+        match obj.as_ref() {
+            Obj::Str(s) => Ok(s.chars().count()),
+            Obj::List(items) => Ok(items.lock().unwrap().len()),
+            Obj::Tuple(items) | Obj::Set(items) | Obj::FrozenSet(items) => Ok(items.len()),
+            Obj::Dict(map) => Ok(map.lock().unwrap().len()),
+            Obj::MemoryView(view) => Ok(view.check()?.nbytes()),
+            Obj::Bytes(_) | Obj::ByteArray(_) => Ok(bytes::bytes_of(obj).unwrap().len()),
+            Obj::Range(range) => range.len().to_usize().ok_or_else(|| PyException {
+                error: PyError::OverflowError,
+                msg: "Python int too large to convert to C ssize_t".to_string(),
+            }),
+            o => Err(PyException::type_error(format!("object of type '{}' has no len()", class_name(o)))),
+        }
+    }
+
+    fn hash(&mut self, obj: &Arc<Obj>) -> Result<Integer, PyException> {
+        if let Obj::Class(instance) = obj.as_ref() {
+            if let Some(method) = instance.class.lookup("__hash__") {
+                let hash = self.call_object(Obj::Method(obj.clone(), method).into(), vec![], vec![])?;
+                return match hash.as_ref() {
+                    Obj::Int(i) => Ok(i.clone()),
+                    _ => Err(PyException::type_error("__hash__ method should return an integer")),
+                };
+            }
+        }
+        PyVM::check_hashable(obj)?;
+        if let Some(hash) = number_hash(obj) {
+            return Ok(hash);
+        }
+        let mut hasher = DefaultHasher::new();
+        obj.hash(&mut hasher);
+        Ok(Integer::from(hasher.finish() as i64))
+    }
+
+    // iter(obj), iterators are their own
+    pub(crate) fn iter_of(&mut self, obj: &Arc<Obj>) -> Result<Arc<Obj>, PyException> {
+        let method = match obj.as_ref() {
+            Obj::Iter(_) | Obj::Generator(_) | Obj::LazyIter(_) => return Ok(obj.clone()),
+            Obj::Class(instance) => instance.class.lookup("__iter__"),
+            Obj::Native(native) => native.class.lookup("__iter__"),
+            _ => None,
+        };
+        if let Some(method) = method {
+            return self.call_object(Obj::Method(obj.clone(), method).into(), vec![], vec![]);
+        }
+        match obj.iter_py() {
+            Some(iter) => Ok(Obj::Iter(iter).into()),
+            None => Err(PyException::type_error(format!("'{}' object is not iterable", class_name(obj)))),
+        }
+    }
+
+    // the next item of an iterator, None once it is exhausted
+    pub(crate) fn next_item(&mut self, iter: &Arc<Obj>) -> Result<Option<Arc<Obj>>, PyException> {
+        let method = match iter.as_ref() {
+            Obj::Iter(items) => return Ok(items.advance()),
+            Obj::Generator(gen) => {
+                return match self.resume_generator(gen, Obj::None.into(), None)? {
+                    GeneratorStep::Yielded(item) => Ok(Some(item)),
+                    GeneratorStep::Returned(_) => Ok(None),
+                }
+            }
+            Obj::LazyIter(lazy) => return self.lazy_next(lazy),
+            Obj::Class(instance) => instance.class.lookup("__next__"),
+            Obj::Native(native) => native.class.lookup("__next__"),
+            _ => None,
+        };
+        let Some(method) = method else {
+            return Err(PyException::type_error(format!("'{}' object is not an iterator", class_name(iter))));
+        };
+        match self.call_object(Obj::Method(iter.clone(), method).into(), vec![], vec![]) {
+            Ok(item) => Ok(Some(item)),
+            Err(e) if e.error == PyError::StopIteration => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // all the items an iterable gives
+    pub(crate) fn iter_items(&mut self, obj: &Arc<Obj>) -> Result<Vec<Arc<Obj>>, PyException> {
+        if let Some(iter) = obj.iter_py() {
+            return Ok(iter.get_items());
+        }
+        let iter = self.iter_of(obj)?;
+        let mut items = vec![];
+        while let Some(item) = self.next_item(&iter)? {
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    // The lock isn't held while the function map() or filter() calls runs,
+    // it could be using the same iterator
+    fn lazy_next(&mut self, lazy: &Mutex<LazyIter>) -> Result<Option<Arc<Obj>>, PyException> {
+        enum Step {
+            Map(Arc<Obj>, Vec<Arc<Obj>>),
+            Filter(Arc<Obj>, Arc<Obj>),
+            Zip(Vec<Arc<Obj>>, bool),
+            Enumerate(Arc<Obj>),
+            Call(Arc<Obj>, Arc<Obj>),
+            Done,
+        }
+        let step = match &*lazy.lock().unwrap() {
+            LazyIter::Map(func, iters) => Step::Map(func.clone(), iters.clone()),
+            LazyIter::Filter(func, iter) => Step::Filter(func.clone(), iter.clone()),
+            LazyIter::Zip(iters, strict) => Step::Zip(iters.clone(), *strict),
+            LazyIter::Enumerate(iter, _) => Step::Enumerate(iter.clone()),
+            LazyIter::Call(Some((func, sentinel))) => Step::Call(func.clone(), sentinel.clone()),
+            LazyIter::Call(None) => Step::Done,
+        };
+        match step {
+            Step::Map(func, iters) => {
+                let mut args = vec![];
+                for iter in &iters {
+                    match self.next_item(iter)? {
+                        Some(item) => args.push(item),
+                        None => return Ok(None),
+                    }
+                }
+                self.call_object(func, args, vec![]).map(Some)
+            }
+            Step::Filter(func, iter) => {
+                while let Some(item) = self.next_item(&iter)? {
+                    let keep = match func.as_ref() {
+                        Obj::None => item.clone(),
+                        _ => self.call_object(func.clone(), vec![item.clone()], vec![])?,
+                    };
+                    if self.truthy(&keep)? {
+                        return Ok(Some(item));
+                    }
+                }
+                Ok(None)
+            }
+            Step::Zip(iters, strict) => {
+                let mut items = vec![];
+                for (i, iter) in iters.iter().enumerate() {
+                    match self.next_item(iter)? {
+                        Some(item) => items.push(item),
+                        None if strict => return self.zip_lengths(&iters, i),
+                        None => return Ok(None),
+                    }
+                }
+                Ok((!iters.is_empty()).then(|| Obj::Tuple(items).into()))
+            }
+            Step::Enumerate(iter) => {
+                let Some(item) = self.next_item(&iter)? else {
+                    return Ok(None);
+                };
+                let LazyIter::Enumerate(_, count) = &mut *lazy.lock().unwrap() else {
+                    unreachable!()
+                };
+                let pair = Obj::Tuple(vec![Obj::Int(count.clone()).into(), item]);
+                *count += 1;
+                Ok(Some(pair.into()))
+            }
+            // the sentinel ends it for good, the function isn't called again
+            Step::Call(func, sentinel) => {
+                let item = self.call_object(func, vec![], vec![])?;
+                if item != sentinel {
+                    return Ok(Some(item));
+                }
+                *lazy.lock().unwrap() = LazyIter::Call(None);
+                Ok(None)
+            }
+            Step::Done => Ok(None),
+        }
+    }
+
+    // zip(strict=True) once iterator `ended` has run out, the others have
+    // to be done too
+    fn zip_lengths(&mut self, iters: &[Arc<Obj>], ended: usize) -> Result<Option<Arc<Obj>>, PyException> {
+        let args = |n: usize| match n {
+            1 => "argument 1".to_string(),
+            n => format!("arguments 1-{n}"),
+        };
+        let value_err = |msg: String| PyException {
+            error: PyError::ValueError,
+            msg,
+        };
+        if ended > 0 {
+            return Err(value_err(format!("zip() argument {} is shorter than {}", ended + 1, args(ended))));
+        }
+        for (i, iter) in iters.iter().enumerate().skip(1) {
+            if self.next_item(iter)?.is_some() {
+                return Err(value_err(format!("zip() argument {} is longer than {}", i + 1, args(i))));
+            }
+        }
+        Ok(None)
+    }
+
+    fn reversed(&mut self, obj: &Arc<Obj>) -> Result<Arc<Obj>, PyException> {
+        if let Obj::Class(instance) = obj.as_ref() {
+            if let Some(method) = instance.class.lookup("__reversed__") {
+                return self.call_object(Obj::Method(obj.clone(), method).into(), vec![], vec![]);
+            }
+        }
+        match obj.as_ref() {
+            Obj::List(_) | Obj::Tuple(_) | Obj::Str(_) | Obj::Dict(_) | Obj::Bytes(_) | Obj::ByteArray(_) => {
+                let mut items = obj.iter_py().unwrap().get_items();
+                items.reverse();
+                Ok(Obj::Iter(ObjIter::new(items)).into())
+            }
+            o => Err(PyException::type_error(format!("'{}' object is not reversible", class_name(o)))),
+        }
+    }
+
+    // a <op> b for the comparison operators, sorted(), min() and max(),
+    // sequences compare item by item and an instance can have its own
+    // __lt__ and friends, tried reflected on the right side too
+    pub(crate) fn compare(&mut self, op: &Op, a: &Arc<Obj>, b: &Arc<Obj>) -> Result<bool, PyException> {
+        let (method, reflected, symbol) = match op {
+            Op::Eq => return Ok(a == b),
+            Op::Neq => return Ok(a != b),
+            Op::LessThan => ("__lt__", "__gt__", "<"),
+            Op::GreaterThan => ("__gt__", "__lt__", ">"),
+            Op::LessEq => ("__le__", "__ge__", "<="),
+            Op::GreaterEq => ("__ge__", "__le__", ">="),
+            _ => return Ok(false),
+        };
+        for (obj, name, other) in [(a, method, b), (b, reflected, a)] {
+            if let Obj::Class(instance) = obj.as_ref() {
+                if let Some(method) = instance.class.lookup(name) {
+                    let ret = self.call_object(Obj::Method(obj.clone(), method).into(), vec![other.clone()], vec![])?;
+                    return self.truthy(&ret);
+                }
+            }
+        }
+        let ordered = |x: &Obj, y: &Obj| match op {
+            Op::LessThan => x < y,
+            Op::GreaterThan => x > y,
+            Op::LessEq => x <= y,
+            _ => x >= y,
+        };
+        let is_real =
+            |o: &Obj| matches!(o, Obj::Int(_) | Obj::Bool(_) | Obj::Float(_) | Obj::Fraction(_) | Obj::Decimal(_));
+        match (a.as_ref(), b.as_ref()) {
+            // copied out of the lists, an item's __lt__ may change them, one
+            // at a time so comparing a list with itself doesn't lock it twice
+            (Obj::List(x), Obj::List(y)) => {
+                let x = x.lock().unwrap().clone();
+                let y = y.lock().unwrap().clone();
+                self.compare(op, &Obj::Tuple(x).into(), &Obj::Tuple(y).into())
+            }
+            (Obj::Tuple(x), Obj::Tuple(y)) => match x.iter().zip(y).find(|(i, j)| i != j) {
+                Some((i, j)) => self.compare(op, i, j),
+                None => Ok(ordered(&Obj::Int(x.len().into()), &Obj::Int(y.len().into()))),
+            },
+            // subsets, sets have no duplicates so a proper one is also shorter
+            (Obj::Set(x) | Obj::FrozenSet(x), Obj::Set(y) | Obj::FrozenSet(y)) => {
+                let (small, big) = match op {
+                    Op::LessThan | Op::LessEq => (x, y),
+                    _ => (y, x),
+                };
+                let proper = matches!(op, Op::LessThan | Op::GreaterThan);
+                Ok(small.iter().all(|i| big.contains(i)) && !(proper && small.len() == big.len()))
+            }
+            (x, y) if is_real(x) && is_real(y) => Ok(ordered(x, y)),
+            (Obj::Str(_), Obj::Str(_)) | (Obj::Bytes(_) | Obj::ByteArray(_), Obj::Bytes(_) | Obj::ByteArray(_)) => {
+                Ok(ordered(a, b))
+            }
+            (x, y) => Err(PyException::type_error(format!(
+                "'{symbol}' not supported between instances of '{}' and '{}'",
+                class_name(x),
+                class_name(y)
+            ))),
+        }
+    }
+
+    // a stable sort by key, only using <
+    fn sort(
+        &mut self,
+        items: Vec<Arc<Obj>>,
+        key: Option<Arc<Obj>>,
+        reverse: bool,
+    ) -> Result<Vec<Arc<Obj>>, PyException> {
+        let mut keyed = vec![];
+        for item in items {
+            let k = match key.as_deref() {
+                None | Some(Obj::None) => item.clone(),
+                Some(_) => self.call_object(key.clone().unwrap(), vec![item.clone()], vec![])?,
+            };
+            keyed.push((k, item));
+        }
+        let mut err = None;
+        keyed.sort_by(|(a, _), (b, _)| {
+            let (a, b) = if reverse { (b, a) } else { (a, b) };
+            if err.is_some() {
+                return std::cmp::Ordering::Equal;
+            }
+            match (self.compare(&Op::LessThan, a, b), self.compare(&Op::LessThan, b, a)) {
+                (Ok(true), _) => std::cmp::Ordering::Less,
+                (Ok(false), Ok(true)) => std::cmp::Ordering::Greater,
+                (Ok(false), Ok(false)) => std::cmp::Ordering::Equal,
+                (Err(e), _) | (_, Err(e)) => {
+                    err = Some(e);
+                    std::cmp::Ordering::Equal
+                }
+            }
+        });
+        match err {
+            Some(e) => Err(e),
+            None => Ok(keyed.into_iter().map(|(_, item)| item).collect()),
+        }
+    }
+
+    // min(iterable, *, key, default) or min(a, b, *args, key), the first of
+    // equal ones wins
+    fn min_max(
+        &mut self,
+        name: &str,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+    ) -> Result<Arc<Obj>, PyException> {
+        let params = bind_native_args(name, &["key", "default"], 0, &[], kwargs)?;
+        let (key, default) = (params[0].clone(), params[1].clone());
+        let items = match args {
+            [] => return Err(PyException::type_error(format!("{name} expected at least 1 argument, got 0"))),
+            [iterable] => self.iter_items(iterable)?,
+            _ if default.is_some() => {
+                return Err(PyException::type_error(format!(
+                    "Cannot specify a default for {name}() with multiple positional arguments"
+                )))
+            }
+            _ => args.to_vec(),
+        };
+        let mut best: Option<(Arc<Obj>, Arc<Obj>)> = None;
+        for item in items {
+            let k = match key.as_deref() {
+                None | Some(Obj::None) => item.clone(),
+                Some(_) => self.call_object(key.clone().unwrap(), vec![item.clone()], vec![])?,
+            };
+            let better = match &best {
+                None => true,
+                Some((best_key, _)) if name == "min" => self.compare(&Op::LessThan, &k, best_key)?,
+                Some((best_key, _)) => self.compare(&Op::LessThan, best_key, &k)?,
+            };
+            if better {
+                best = Some((k, item));
+            }
+        }
+        match (best, default) {
+            (Some((_, item)), _) => Ok(item),
+            (None, Some(default)) => Ok(default),
+            (None, None) => Err(PyException {
+                error: PyError::ValueError,
+                msg: format!("{name}() arg is an empty sequence"),
+            }),
+        }
+    }
+
+    // abs(x), an instance's comes from its class's __abs__
+    pub(crate) fn abs(&mut self, args: &[Arc<Obj>]) -> Result<Arc<Obj>, PyException> {
+        let [arg] = args else {
+            return Err(PyException::type_error(format!("abs() takes exactly one argument ({} given)", args.len())));
+        };
+        if let Obj::Class(instance) = arg.as_ref() {
+            if let Some(method) = instance.class.lookup("__abs__") {
+                return self.call_object(Obj::Method(arg.clone(), method).into(), vec![], vec![]);
+            }
+        }
+        Funcs::abs(arg).map(Arc::from)
+    }
+
+    // round(number[, ndigits]), a class can define its own __round__
+    pub(crate) fn round(&mut self, args: &[Arc<Obj>]) -> Result<Arc<Obj>, PyException> {
+        if let Some(Obj::Class(instance)) = args.first().map(|a| a.as_ref()) {
+            if let Some(method) = instance.class.lookup("__round__") {
+                let method = Obj::Method(args[0].clone(), method).into();
+                return self.call_object(method, args[1..].to_vec(), vec![]);
+            }
+        }
+        Funcs::round(args).map(Arc::from)
+    }
+
+    // format(value[, format_spec])
+    pub(crate) fn format_builtin(&mut self, args: &[Arc<Obj>]) -> Result<Arc<Obj>, PyException> {
+        let (val, spec) = match args {
+            [val] => (val, ""),
+            [val, spec] => match spec.as_ref() {
+                Obj::Str(spec) => (val, spec.as_str()),
+                o => {
+                    return Err(PyException::type_error(format!("format() argument 2 must be str, not {}", o.type_name())))
+                }
+            },
+            [] => {
+                return Err(PyException::type_error("format expected at least 1 argument, got 0"))
+            }
+            _ => {
+                return Err(PyException::type_error(format!("format expected at most 2 arguments, got {}", args.len())))
+            }
+        };
+        Ok(Obj::Str(self.format(val, spec)?).into())
+    }
+
+    // next(iterator[, default])
+    pub(crate) fn next(&mut self, args: &[Arc<Obj>]) -> Result<Arc<Obj>, PyException> {
+        let ret = match args.first() {
+            // a generator's return value goes with its StopIteration
+            Some(iter) if matches!(iter.as_ref(), Obj::Generator(_)) => {
+                let Obj::Generator(gen) = iter.as_ref() else { unreachable!() };
+                self.resume_generator(gen, Obj::None.into(), None).and_then(PyVM::step_to_result)
+            }
+            Some(iter) => match self.next_item(iter) {
+                Ok(Some(item)) => Ok(item),
+                Ok(None) => Err(PyException {
+                    error: PyError::StopIteration,
+                    msg: String::new(),
+                }),
+                Err(e) => Err(e),
+            },
+            None => Err(PyException::type_error("next expected at least 1 argument, got 0")),
+        };
+        match (ret, args.get(1)) {
+            (Err(e), Some(default)) if e.error == PyError::StopIteration => Ok(default.clone()),
+            (ret, _) => ret,
+        }
+    }
+}
+
+// type(name, bases, dict), a class made without a class statement
+fn new_class(name: &Obj, bases: &Obj, attrs: &Obj) -> Result<Arc<Obj>, PyException> {
+    let Obj::Str(name) = name else {
+        return Err(PyException::type_error(format!("type.__new__() argument 1 must be str, not {}", name.type_name())));
+    };
+    let Obj::Tuple(bases) = bases else {
+        return Err(PyException::type_error(format!("type.__new__() argument 2 must be tuple, not {}", bases.type_name())));
+    };
+    let Obj::Dict(attrs) = attrs else {
+        return Err(PyException::type_error(format!("type.__new__() argument 3 must be dict, not {}", attrs.type_name())));
+    };
+    let bases = bases
+        .iter()
+        .map(|b| match b.as_ref() {
+            Obj::ClassDef(class) => Ok(class.clone()),
+            o => Err(PyException::type_error(format!("type.__new__() bases must be classes, not {}", o.type_name()))),
+        })
+        .collect::<Result<_, _>>()?;
+    let attrs = attrs.lock().unwrap().iter().map(|(k, v)| (k.__str__(), v.clone())).collect();
+    Ok(Obj::ClassDef(Arc::new(UserClassDef::new(name, bases, attrs))).into())
+}
@@ -1,14 +1,11 @@
 use crate::{
+    pyrs_codeobject::{CodeObject, CO_GENERATOR, CO_VARARGS, CO_VARKEYWORDS},
+    pyrs_error::PyException,
     pyrs_obj::{Obj, ToObj},
     pyrs_parsing::{Expression, Keyword, Op},
-    pyrs_userclass::UserClassDef,
-    pyrs_vm::IntrinsicFunc,
 };
 
-use std::{ 
-    collections::HashMap,
-    sync::Arc,
-};
+use std::sync::Arc;
 
 // Format: offset INSTRUCTION argument (value)
 // 0 LOAD_CONST 0 (0)      # Load constant at index 0, which is the integer 0
@@ -39,25 +36,41 @@ pub enum PyBytecode {
     BinarySubtract = 83,
     BinaryDivide = 84,
     BinaryXOR = 85,
+    BinarySubscr = 25,
+    StoreSubscr = 60,
+    DeleteSubscr = 61,
 
     LoadConst(Obj) = 100,
     LoadFast(usize) = 101,
     StoreFast(usize) = 102,
     LoadName(String) = 103,
     StoreName(String) = 104,
-    LoadGlobal = 105,
-    StoreGlobal = 106,
+    LoadGlobal(String) = 105,
+    StoreGlobal(String) = 106,
     PushNull = 107,
+    LoadAttr(String) = 108,
+    StoreAttr(String) = 109,
+    ImportName(String) = 111,
+    ImportFrom(String) = 112,
+    ImportStar = 113,
+    DeleteName(String) = 91,
+    DeleteAttr(String) = 96,
+    DeleteGlobal(String) = 98,
 
     Cache = 110,
 
     CallFunction(usize /* argc */) = 120,
-    CallInstrinsic1(IntrinsicFunc) = 121,
-    CallInstrinsic2(IntrinsicFunc) = 122,
+    CallFunctionKw(usize /* argc */) = 119,
+    CallFunctionEx(usize /* flags */) = 118,
     ReturnValue = 123,
     MakeFunction = 124,
+    YieldValue = 125,
+    Send(usize) = 126,
 
     LoadBuildClass = 130,
+    LoadLocals = 131,
+    BuildSlice(usize) = 133,
+    RaiseAssertion(usize /* argc */) = 74,
 
     PopJumpIfFalse(usize) = 140,
     PopJumpIfTrue(usize) = 141,
@@ -65,8 +78,14 @@ pub enum PyBytecode {
     JumpBackward(usize) = 143,
     JumpIfFalse = 144,
     JumpAbsolute = 145,
+    SetupWith(usize /* delta to after the block */) = 146,
+    ExitWith = 147,
+
+    FormatValue(usize /* flags */) = 155,
 
     CompareOp(Op) = 160,
+    ListExtend(usize) = 162,
+    DictMerge(usize) = 164,
 
     UnpackSequence = 170,
     UnpackEx = 171,
@@ -74,118 +93,208 @@ pub enum PyBytecode {
     BuildList(usize) = 181,
     BuildTuple(usize) = 182,
     BuildSet(usize) = 183,
-    BuildMap = 184,
+    BuildMap(usize) = 184,
     BuildString(usize) = 185,
-    ListAppend = 186,
+    ListAppend(usize) = 186,
+    SetAdd(usize) = 187,
+    MapAdd(usize) = 188,
 
     ForIter(usize) = 191,
     GetIter = 192,
+    GetYieldFromIter = 193,
 
     NewStack = 201,
     DestroyStack = 202,
+}
 
-    // not proper
-    Error(String) = 254,
+// an expression missing a part the parser always gives it
+fn invalid_syntax() -> PyException {
+    PyException::syntax_error("invalid syntax")
 }
 
 impl PyBytecode {
-    pub fn from_expr(expr: Expression, queue: &mut Vec<PyBytecode>) {
+    pub fn from_expr(expr: Expression, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
         // println!("Compiling: {}", expr.to_string());
         match expr {
             Expression::Ident(x) => {
                 queue.push(PyBytecode::LoadName(x));
             }
             Expression::Atom(a) => queue.push(PyBytecode::LoadConst(a.to_obj())),
+            Expression::Str(s) => queue.push(PyBytecode::LoadConst(Obj::Str(s))),
+            Expression::Bytes(b) => queue.push(PyBytecode::LoadConst(Obj::Bytes(b))),
             Expression::Operation(op, args) => {
-                let mut name = String::new();
                 match op {
                     Op::Equals => {
-                        for (idx, a) in args.into_iter().enumerate() {
-                            if idx == 0 {
-                                match a {
-                                    Expression::Ident(ident) => name = ident,
-                                    _ => panic!(),
-                                };
-                            } else {
-                                PyBytecode::from_expr(a, queue);
-                            }
+                        let mut args = args.into_iter();
+                        let target = args.next().ok_or_else(invalid_syntax)?;
+                        for a in args {
+                            PyBytecode::from_expr(a, queue)?;
                         }
-                        if name.is_empty() {
-                            panic!();
+                        PyBytecode::from_store(target, queue)?;
+                        return Ok(());
+                    }
+                    Op::AddEquals
+                    | Op::SubEquals
+                    | Op::MulEquals
+                    | Op::DivEquals
+                    | Op::FloorDivEquals
+                    | Op::ModEquals
+                    | Op::PowEquals => {
+                        if args.len() != 2 {
+                            return Err(invalid_syntax());
                         }
-
-                        queue.push(PyBytecode::StoreName(name));
-                        return;
-                    }
-                    Op::AddEquals | Op::SubEquals | Op::MulEquals | Op::DivEquals => {
-                        for (idx, a) in args.into_iter().enumerate() {
-                            if idx == 0 {
-                                match a {
-                                    Expression::Ident(ident) => {
-                                        name = ident;
-                                        queue.push(PyBytecode::LoadName(name.clone()));
-                                    }
-                                    _ => panic!(),
-                                };
-                            } else if idx == 1 {
-                                PyBytecode::from_expr(a, queue);
-                            } else {
-                                panic!("Only 2 args possible for add/sub/mul/div assign op");
+                        let mut args = args.into_iter();
+                        let target = args.next().ok_or_else(invalid_syntax)?;
+                        let value = args.next().ok_or_else(invalid_syntax)?;
+
+                        // obj.x += v keeps obj on the stack to store back into
+                        let store = match target {
+                            Expression::Ident(name) => {
+                                queue.push(PyBytecode::LoadName(name.clone()));
+                                vec![PyBytecode::StoreName(name)]
                             }
-                        }
-                        if name.is_empty() {
-                            panic!();
-                        }
+                            Expression::Operation(Op::Dot, mut sides) => {
+                                let attr = sides.pop().ok_or_else(invalid_syntax)?.get_value_string()?;
+                                PyBytecode::from_expr(sides.pop().ok_or_else(invalid_syntax)?, queue)?;
+                                queue.push(PyBytecode::Copy(1));
+                                queue.push(PyBytecode::LoadAttr(attr.clone()));
+                                vec![PyBytecode::Swap(2), PyBytecode::StoreAttr(attr)]
+                            }
+                            // x[k] += v keeps x and k under the item, then moves the result below them
+                            Expression::Operation(Op::Subscript, mut sides) => {
+                                let key = sides.pop().ok_or_else(invalid_syntax)?;
+                                let container = sides.pop().ok_or_else(invalid_syntax)?;
+                                PyBytecode::from_expr(container, queue)?;
+                                PyBytecode::from_expr(key, queue)?;
+                                queue.push(PyBytecode::Copy(2));
+                                queue.push(PyBytecode::Copy(2));
+                                queue.push(PyBytecode::BinarySubscr);
+                                vec![PyBytecode::Swap(3), PyBytecode::Swap(2), PyBytecode::StoreSubscr]
+                            }
+                            e => return Err(PyException::syntax_error(format!("cannot assign to {e}"))),
+                        };
+                        PyBytecode::from_expr(value, queue)?;
 
                         queue.push(match op {
                             Op::AddEquals => PyBytecode::BinaryAdd,
                             Op::SubEquals => PyBytecode::BinarySubtract,
                             Op::MulEquals => PyBytecode::BinaryMultiply,
                             Op::DivEquals => PyBytecode::BinaryDivide,
+                            Op::FloorDivEquals => PyBytecode::BinaryOp(Op::DoubleSlash),
+                            Op::ModEquals => PyBytecode::BinaryOp(Op::Percent),
+                            Op::PowEquals => PyBytecode::BinaryOp(Op::Power),
                             _ => unreachable!(),
                         });
 
-                        queue.push(PyBytecode::StoreName(name));
-                        return;
+                        queue.extend(store);
+                        return Ok(());
                     }
                     Op::List => {
                         let obj_count = args.len();
                         for a in args {
-                            PyBytecode::from_expr(a, queue);
+                            PyBytecode::from_expr(a, queue)?;
                         }
                         queue.push(PyBytecode::BuildList(obj_count));
-                        return;
+                        return Ok(());
                     }
                     Op::Set => {
                         let obj_cound = args.len();
                         for a in args {
-                            PyBytecode::from_expr(a, queue);
+                            PyBytecode::from_expr(a, queue)?;
                         }
                         queue.push(PyBytecode::BuildSet(obj_cound));
-                        return;
+                        return Ok(());
                     }
                     Op::Tuple => {
                         let obj_cound = args.len();
                         for a in args {
-                            PyBytecode::from_expr(a, queue);
+                            PyBytecode::from_expr(a, queue)?;
                         }
                         queue.push(PyBytecode::BuildTuple(obj_cound));
-                        return;
+                        return Ok(());
                     }
                     Op::Dot => {
-                        dbg!(&args);
                         let mut sides = args;
-                        let lhs = sides.first().unwrap().get_value_string();
-                        match sides.pop().unwrap() {
+                        let rhs = sides.pop().ok_or_else(invalid_syntax)?;
+                        let lhs = sides.pop().ok_or_else(invalid_syntax)?;
+                        PyBytecode::from_expr(lhs, queue)?;
+                        match rhs {
                             Expression::Call(name, args) => {
-                                PyBytecode::from_expr(Expression::Call(format!("{}.{}", lhs, name), args), queue);
+                                queue.push(PyBytecode::LoadAttr(name));
+                                PyBytecode::from_call_args(args, queue)?;
                             }
-                            _ => panic!(),
+                            Expression::Ident(name) => queue.push(PyBytecode::LoadAttr(name)),
+                            e => return Err(PyException::syntax_error(format!("expected an attribute name after '.', found {e}"))),
+                        };
+                        return Ok(());
+                    }
+                    Op::Dict => {
+                        let pair_count = args.len() / 2;
+                        for a in args {
+                            PyBytecode::from_expr(a, queue)?;
+                        }
+                        queue.push(PyBytecode::BuildMap(pair_count));
+                        return Ok(());
+                    }
+                    Op::Call => {
+                        let mut args = args.into_iter();
+                        PyBytecode::from_expr(args.next().ok_or_else(invalid_syntax)?, queue)?;
+                        PyBytecode::from_call_args(args.collect(), queue)?;
+                        return Ok(());
+                    }
+                    Op::Subscript => {
+                        for a in args {
+                            PyBytecode::from_expr(a, queue)?;
+                        }
+                        queue.push(PyBytecode::BinarySubscr);
+                        return Ok(());
+                    }
+                    Op::Slice => {
+                        let part_count = args.len();
+                        for a in args {
+                            PyBytecode::from_expr(a, queue)?;
+                        }
+                        queue.push(PyBytecode::BuildSlice(part_count));
+                        return Ok(());
+                    }
+                    Op::GenExpr | Op::ListComp | Op::SetComp | Op::DictComp => {
+                        PyBytecode::from_comprehension(op, args, queue)?;
+                        return Ok(());
+                    }
+                    Op::Decorator => {
+                        PyBytecode::from_decorated(args, queue)?;
+                        return Ok(());
+                    }
+                    Op::FString => {
+                        let part_count = args.len();
+                        for a in args {
+                            PyBytecode::from_expr(a, queue)?;
+                        }
+                        if part_count > 1 {
+                            queue.push(PyBytecode::BuildString(part_count));
+                        }
+                        return Ok(());
+                    }
+                    Op::FormatValue => {
+                        // the conversion in the low bits, 0x04 if a spec is pushed
+                        let mut args = args.into_iter();
+                        PyBytecode::from_expr(args.next().ok_or_else(invalid_syntax)?, queue)?;
+                        let mut flags = match args.next() {
+                            Some(Expression::Str(conv)) if conv == "s" => 1,
+                            Some(Expression::Str(conv)) if conv == "r" => 2,
+                            Some(Expression::Str(conv)) if conv == "a" => 3,
+                            _ => 0,
                         };
+                        if let Some(spec) = args.next().filter(|s| *s != Expression::None) {
+                            PyBytecode::from_expr(spec, queue)?;
+                            flags |= 0x04;
+                        }
+                        queue.push(PyBytecode::FormatValue(flags));
+                        return Ok(());
                     }
                     _ => {
                         for a in args {
-                            PyBytecode::from_expr(a, queue);
+                            PyBytecode::from_expr(a, queue)?;
                         }
                     }
                 }
@@ -195,6 +304,7 @@ impl PyBytecode {
                     Op::Minus => PyBytecode::BinarySubtract,
                     Op::Asterisk => PyBytecode::BinaryMultiply,
                     Op::ForwardSlash => PyBytecode::BinaryDivide,
+                    Op::Percent | Op::DoubleSlash | Op::Power => PyBytecode::BinaryOp(op),
 
                     Op::Eq
                     | Op::Neq
@@ -206,45 +316,26 @@ impl PyBytecode {
                     Op::Neg => PyBytecode::UnaryNegative,
                     Op::Unpack => PyBytecode::UnpackSequence,
 
-                    e => {
-                        println!("Op {e} to PyBytecode not implemented! Pushed Error to instructions instead");
-                        PyBytecode::Error(format!("{e}"))
-                    },
+                    e => return Err(PyException::syntax_error(format!("invalid syntax at '{e}'"))),
                 });
             }
             Expression::Call(name, args) => {
-                let argc = args.len();
-                // dbg!(&args);
-
-                let intrinsic_option = IntrinsicFunc::try_get(&name);
-                if intrinsic_option.is_some() {
-                    queue.push(PyBytecode::PushNull);
-                }
-
-                for a in args {
-                    //dbg!(&a);
-                    PyBytecode::from_expr(a, queue);
-                }
-
-                if let Some(intrinsic) = intrinsic_option {
-                    queue.push(PyBytecode::CallInstrinsic1(intrinsic));
-                } else {
-                    queue.push(PyBytecode::LoadConst(name.as_str().to_obj()));
-                    queue.push(PyBytecode::CallFunction(argc));
-                    // todo: create tuple that is argc sized??
-                }
+                // builtins like print are names like any other, load_name
+                // finds them when no variable shadows them
+                queue.push(PyBytecode::LoadName(name));
+                PyBytecode::from_call_args(args, queue)?;
             }
             Expression::Keyword(keyword, mut args, body) => {
                 match keyword {
                     Keyword::True => queue.push(PyBytecode::LoadConst(Obj::Bool(true))),
                     Keyword::False => queue.push(PyBytecode::LoadConst(Obj::Bool(false))),
                     Keyword::Elif | Keyword::Else => {
-                        panic!("Shouldn't have a stand alone elif/else expression")
+                        return Err(PyException::syntax_error(format!("'{keyword}' without an 'if' before it")))
                     }
                     Keyword::If => {
                         // Evaluate the if condition first
                         for c in args {
-                            PyBytecode::from_expr(c, queue);
+                            PyBytecode::from_expr(c, queue)?;
                         }
 
                         let parts = Expression::split_if_elif_else(body);
@@ -262,7 +353,7 @@ impl PyBytecode {
                                     elif_else_parts.push((vec![], body)); // Empty condition for else
                                 }
                                 other => {
-                                    PyBytecode::from_expr(other, &mut if_body);
+                                    PyBytecode::from_stmt(other, &mut if_body)?;
                                 }
                             }
                         }
@@ -285,12 +376,12 @@ impl PyBytecode {
                                 if !conds.is_empty() {
                                     // elif block
                                     for cond in conds {
-                                        PyBytecode::from_expr(cond, &mut block_code);
+                                        PyBytecode::from_expr(cond, &mut block_code)?;
                                     }
 
                                     let mut body_code = vec![];
                                     for expr in body_exprs {
-                                        PyBytecode::from_expr(expr, &mut body_code);
+                                        PyBytecode::from_stmt(expr, &mut body_code)?;
                                     }
 
                                     block_code
@@ -301,7 +392,7 @@ impl PyBytecode {
                                 } else {
                                     // else block - no condition
                                     for expr in body_exprs {
-                                        PyBytecode::from_expr(expr, &mut block_code);
+                                        PyBytecode::from_stmt(expr, &mut block_code)?;
                                     }
                                 }
 
@@ -333,7 +424,7 @@ impl PyBytecode {
                         let condition_start = queue.len();
                         let mut condition_code = vec![];
                         for c in args {
-                            PyBytecode::from_expr(c, &mut condition_code);
+                            PyBytecode::from_expr(c, &mut condition_code)?;
                         }
                         for inst in condition_code.iter() {
                             queue.push(inst.clone());
@@ -341,7 +432,7 @@ impl PyBytecode {
 
                         let mut contents_code: Vec<PyBytecode> = vec![];
                         for a in body {
-                            PyBytecode::from_expr(a, &mut contents_code);
+                            PyBytecode::from_stmt(a, &mut contents_code)?;
                         }
 
                         let delta = contents_code.len() + 1;
@@ -355,174 +446,650 @@ impl PyBytecode {
                         queue.push(PyBytecode::LoadConst(Obj::None));
                     }
                     Keyword::For => {
-                        let for_err =
-                            "only for loops of form \'for Ident() in Ident()\' currently supported";
-                        assert_eq!(args.len(), 2);
-
-                        match args.pop().unwrap() {
-                            Expression::Ident(ident) => {
-                                queue.push(PyBytecode::LoadName(ident.clone()))
-                            }
-                            c if matches!(c, Expression::Call(_, _)) => {
-                                PyBytecode::from_expr(c, queue)
-                            }
-                            e => panic!("{} found {}", for_err, e),
-                        };
-
-                        let x = match args.first().unwrap() {
-                            Expression::Ident(ident) => ident,
-                            e => panic!("{} found {}", for_err, e),
-                        };
-
-                        queue.push(PyBytecode::GetIter);
+                        let iter = args.pop().ok_or_else(invalid_syntax)?;
+                        let target = args.pop().ok_or_else(invalid_syntax)?;
 
                         let mut for_code = vec![];
                         for b in body {
-                            PyBytecode::from_expr(b, &mut for_code);
+                            PyBytecode::from_stmt(b, &mut for_code)?;
                         }
-                        let contents_len = for_code.len(); // length of for loops contents
-
-                        queue.push(PyBytecode::ForIter(contents_len + 2));
-                        queue.push(PyBytecode::StoreName(x.into()));
-
-                        queue.append(&mut for_code);
-                        queue.push(PyBytecode::JumpBackward(contents_len + 3));
+                        PyBytecode::from_for_loop(target, iter, for_code, queue)?;
                     }
-                    Keyword::Def => {
-                        let func_args = args.split_off(1);
-                        // dbg!(&func_args);
-
-                        let name = match args.pop() {
-                            Some(Expression::Ident(ident)) => ident,
-                            Some(e) => {
-                                panic!("Syntax Error: function name must be an identifier, not {e}")
-                            }
-                            None => panic!(),
-                        };
-
-                        let func_addr = queue.len() + 3;
-                        let mut body_code = vec![];
-
-                        // define function and location
-                        queue.push(PyBytecode::LoadConst(name.to_obj()));
-                        queue.push(PyBytecode::LoadConst(func_addr.to_obj()));
-                        queue.push(PyBytecode::MakeFunction);
-
-                        for a in func_args {
-                            match a {
-                                Expression::Ident(ident) => {
-                                    body_code.push(PyBytecode::StoreName(ident))
-                                }
-                                Expression::Operation(Op::Equals, vals) => {
-                                    let name = vals.first().unwrap().clone();
-                                    PyBytecode::from_expr(
-                                        Expression::Operation(Op::Equals, vals),
-                                        &mut body_code,
-                                    );
-                                    body_code.push(PyBytecode::LoadName(name.get_value_string()));
-                                }
-                                _ => panic!(),
-                            }
-                        }
-
+                    Keyword::With => PyBytecode::from_with(args, body, queue)?,
+                    Keyword::Def | Keyword::Class => {
+                        let name = PyBytecode::from_definition(Expression::Keyword(keyword, args, body), queue)?;
+                        queue.push(PyBytecode::StoreName(name));
+                    }
+                    Keyword::Lambda => {
+                        let mut code = CodeObject::new("<lambda>", vec![]);
                         for b in body {
-                            PyBytecode::from_expr(b, &mut body_code);
+                            PyBytecode::from_expr(b, &mut code.co_code)?;
                         }
+                        code.co_code.push(PyBytecode::ReturnValue);
 
-                        body_code.push(PyBytecode::ReturnValue);
-
-                        //dbg!(&body_code);
-                        queue.push(PyBytecode::JumpForward(body_code.len()));
-                        queue.append(&mut body_code);
+                        PyBytecode::from_function(code, args, queue)?;
                     }
-                    Keyword::Class => {
-                        //println!("\nClass");
-
-                        //dbg!(&args);
-                        let name = match args.first().unwrap() {
-                            Expression::Ident(ident) => ident.clone(),
-                            e => panic!("class name must be an identifier not: {:?}", e),
-                        };
-
-                        //dbg!(&body);
-                        let mut fields: HashMap<String, (usize, Obj)> = HashMap::new();
-                        let mut methods = UserClassDef::default_methods();
-                        for (idx, f) in body.into_iter().enumerate() {
-                            match f {
-                                Expression::Operation(Op::Equals, mut v) => {
-                                    let default_val = v.pop().unwrap();
-                                    fields.insert(
-                                        v[0].get_value_string(),
-                                        (idx, default_val.to_obj()),
-                                    );
-                                }
-                                Expression::Keyword(Keyword::Def, conds, body) => {
-                                    let mut func = vec![];
-                                    let fn_name = conds.first().unwrap().get_value_string();
-                                    PyBytecode::from_expr(
-                                        Expression::Keyword(Keyword::Def, conds, body),
-                                        &mut func,
-                                    );
-                                    methods.insert(fn_name, func);
+                    // ImportName takes the level and the fromlist off the stack
+                    Keyword::Import => {
+                        for module in args {
+                            let (name, alias) = PyBytecode::import_alias(module)?;
+                            queue.push(PyBytecode::LoadConst(Obj::Int(0.into())));
+                            queue.push(PyBytecode::LoadConst(Obj::None));
+                            queue.push(PyBytecode::ImportName(name.clone()));
+                            match alias {
+                                // import a.b binds a
+                                None => queue.push(PyBytecode::StoreName(name.split('.').next().unwrap_or_default().to_string())),
+                                // import a.b as c gets from a down to b
+                                Some(alias) => {
+                                    for part in name.split('.').skip(1) {
+                                        queue.push(PyBytecode::ImportFrom(part.to_string()));
+                                        queue.push(PyBytecode::Swap(2));
+                                        queue.push(PyBytecode::PopTop);
+                                    }
+                                    queue.push(PyBytecode::StoreName(alias));
                                 }
-                                _ => panic!("invalid expr for default"),
                             }
                         }
-
-                        let class = UserClassDef {
-                            name: name,
-                            fields: fields,
-                            methods: methods,
-                        };
-
-                        queue.push(PyBytecode::LoadConst(Obj::ClassDef(Arc::new(class))));
-                        queue.push(PyBytecode::LoadBuildClass);
-
-                        //panic!("testing class");
+                    }
+                    Keyword::From => {
+                        let mut names = args.into_iter();
+                        let module = names.next().ok_or_else(invalid_syntax)?.get_value_string()?;
+                        // from ..a import b, each dot is a package further up
+                        let level = module.len() - module.trim_start_matches('.').len();
+                        let names = names.map(PyBytecode::import_alias).collect::<Result<Vec<_>, _>>()?;
+                        let fromlist = names.iter().map(|(name, _)| Obj::Str(name.clone()).into()).collect();
+                        queue.push(PyBytecode::LoadConst(Obj::Int(level.into())));
+                        queue.push(PyBytecode::LoadConst(Obj::Tuple(fromlist)));
+                        queue.push(PyBytecode::ImportName(module[level..].to_string()));
+                        if matches!(names.as_slice(), [(star, None)] if star == "*") {
+                            queue.push(PyBytecode::ImportStar);
+                        } else {
+                            for (name, alias) in names {
+                                queue.push(PyBytecode::ImportFrom(name.clone()));
+                                queue.push(PyBytecode::StoreName(alias.unwrap_or(name)));
+                            }
+                            queue.push(PyBytecode::PopTop);
+                        }
                     }
                     Keyword::Return => {
+                        if args.is_empty() {
+                            queue.push(PyBytecode::LoadConst(Obj::None));
+                        }
                         for a in args {
-                            PyBytecode::from_expr(a, queue);
+                            PyBytecode::from_expr(a, queue)?;
                         }
                         queue.push(PyBytecode::ReturnValue);
                     }
+                    Keyword::Yield => {
+                        if args.is_empty() {
+                            queue.push(PyBytecode::LoadConst(Obj::None));
+                        }
+                        for a in args {
+                            PyBytecode::from_expr(a, queue)?;
+                        }
+                        queue.push(PyBytecode::YieldValue);
+                    }
+                    Keyword::YieldFrom => {
+                        for a in args {
+                            PyBytecode::from_expr(a, queue)?;
+                        }
+                        // Send passes each value sent to us on to the sub iterator,
+                        // when that finishes its return value is left on the stack
+                        queue.push(PyBytecode::GetYieldFromIter);
+                        queue.push(PyBytecode::LoadConst(Obj::None));
+                        queue.push(PyBytecode::Send(2));
+                        queue.push(PyBytecode::YieldValue);
+                        queue.push(PyBytecode::JumpBackward(3));
+                    }
+                    // the check is skipped when __debug__ is False, under -O
+                    Keyword::Assert => {
+                        let mut args = args.into_iter();
+                        let mut check = vec![];
+                        PyBytecode::from_expr(args.next().ok_or_else(invalid_syntax)?, &mut check)?;
+                        let mut raise = vec![];
+                        let argc = args.len();
+                        for msg in args {
+                            PyBytecode::from_expr(msg, &mut raise)?;
+                        }
+                        raise.push(PyBytecode::RaiseAssertion(argc));
+                        check.push(PyBytecode::PopJumpIfTrue(raise.len()));
+                        check.append(&mut raise);
+                        queue.push(PyBytecode::LoadName("__debug__".to_string()));
+                        queue.push(PyBytecode::PopJumpIfFalse(check.len()));
+                        queue.append(&mut check);
+                    }
+                    Keyword::Del => {
+                        for target in args {
+                            PyBytecode::from_delete(target, queue)?;
+                        }
+                    }
+                    // see CodeObject::declare, at the top level they change nothing
+                    Keyword::Global | Keyword::Nonlocal => {
+                        queue.push(PyBytecode::NOP);
+                    }
                     Keyword::None => {
                         queue.push(PyBytecode::LoadConst(Obj::None));
                     }
                     Keyword::Pass => {
                         queue.push(PyBytecode::NOP);
                     }
-                    k => panic!("Unknown keyword: {k}"),
+                    k => return Err(PyException::syntax_error(format!("invalid syntax at '{k}'"))),
                 }
             }
             Expression::None => {} //e => panic!("(Expr) {:?} to bytecode not implemented", e),
         }
+        Ok(())
+    }
+
+    // with a as x, b: is with a as x: with b:, each manager's __exit__
+    // runs however its block is left, see PyVM::setup_with
+    fn from_with(mut items: Vec<Expression>, body: Vec<Expression>, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        if items.is_empty() {
+            for b in body {
+                PyBytecode::from_stmt(b, queue)?;
+            }
+            return Ok(());
+        }
+        let rest = items.split_off(1);
+        let (manager, target) = match items.pop().ok_or_else(invalid_syntax)? {
+            Expression::Keyword(Keyword::As, mut conds, _) => {
+                let target = conds.pop();
+                (conds.pop().ok_or_else(invalid_syntax)?, target)
+            }
+            manager => (manager, None),
+        };
+        PyBytecode::from_expr(manager, queue)?;
+
+        // SetupWith leaves what __enter__ returned
+        let mut block = vec![];
+        match target {
+            Some(target) => PyBytecode::from_store(target, &mut block)?,
+            None => block.push(PyBytecode::PopTop),
+        }
+        PyBytecode::from_with(rest, body, &mut block)?;
+        queue.push(PyBytecode::SetupWith(block.len() + 1));
+        queue.append(&mut block);
+        queue.push(PyBytecode::ExitWith);
+        Ok(())
+    }
+
+    // Compiles a def or class leaving the function or class on the stack,
+    // returns the name it is to be stored under
+    fn from_definition(definition: Expression, queue: &mut Vec<PyBytecode>) -> Result<String, PyException> {
+        match definition {
+            Expression::Keyword(Keyword::Def, mut args, body) => {
+                let func_args = args.split_off(1);
+                let name = match args.pop() {
+                    Some(Expression::Ident(ident)) => ident,
+                    Some(e) => {
+                        return Err(PyException::syntax_error(format!("function name must be an identifier, not {e}")))
+                    }
+                    None => return Err(invalid_syntax()),
+                };
+
+                let mut code = CodeObject::new(&name, vec![]);
+                if Expression::contains_yield(&body) {
+                    code.co_flags |= CO_GENERATOR;
+                }
+                // like CPython the docstring is the first constant
+                if let Some(Expression::Str(doc)) = body.first() {
+                    code.co_consts.push(doc.clone());
+                }
+                let globals = Expression::declared_names(&body, Keyword::Global);
+                let nonlocals = Expression::declared_names(&body, Keyword::Nonlocal);
+                // a parameter is bound before the body starts
+                let params = func_args.iter().filter_map(|a| match a {
+                    Expression::Ident(name) => Some(name),
+                    Expression::Operation(_, param) => match param.first() {
+                        Some(Expression::Ident(name)) => Some(name),
+                        _ => None,
+                    },
+                    _ => None,
+                });
+                for name in params {
+                    let keyword = match (globals.contains(name), nonlocals.contains(name)) {
+                        (true, _) => Keyword::Global,
+                        (false, true) => Keyword::Nonlocal,
+                        (false, false) => continue,
+                    };
+                    return Err(PyException::syntax_error(format!("name '{name}' is parameter and {keyword}")));
+                }
+                Expression::check_declarations(&body)?;
+                for b in body {
+                    PyBytecode::from_stmt(b, &mut code.co_code)?;
+                }
+                code.declare(&globals, &nonlocals);
+                code.co_code.push(PyBytecode::LoadConst(Obj::None));
+                code.co_code.push(PyBytecode::ReturnValue);
+
+                PyBytecode::from_function(code, func_args, queue)?;
+                Ok(name)
+            }
+            Expression::Keyword(Keyword::Class, mut args, mut body) => {
+                // class Name: or class Name(Base, ...):
+                let (name, bases) = match args.pop() {
+                    Some(Expression::Ident(ident)) => (ident, vec![]),
+                    Some(Expression::Call(ident, bases)) => (ident, bases),
+                    Some(e) => return Err(PyException::syntax_error(format!("class name must be an identifier, not {e}"))),
+                    None => return Err(invalid_syntax()),
+                };
+
+                // the body runs like a function and hands back its locals,
+                // which become the class attributes
+                let mut code = CodeObject::new(&name, vec![]);
+                if let Some(Expression::Str(_)) = body.first() {
+                    let doc = body.remove(0);
+                    body.insert(0, Expression::Operation(Op::Equals, vec![Expression::Ident("__doc__".into()), doc]));
+                }
+                let globals = Expression::declared_names(&body, Keyword::Global);
+                let nonlocals = Expression::declared_names(&body, Keyword::Nonlocal);
+                Expression::check_declarations(&body)?;
+                for b in body {
+                    PyBytecode::from_stmt(b, &mut code.co_code)?;
+                }
+                code.co_code.push(PyBytecode::LoadLocals);
+                code.co_code.push(PyBytecode::ReturnValue);
+                code.declare(&globals, &nonlocals);
+                code.resolve_scopes();
+                // methods can't see the class body's names, so it has no cells
+                code.co_cellvars.clear();
+                // and a nonlocal in them has to be bound by a function further out
+                for inst in &code.co_code {
+                    if let PyBytecode::LoadConst(Obj::Code(method)) = inst {
+                        for name in &method.co_nonlocals {
+                            if !code.co_nonlocals.contains(name) {
+                                code.co_nonlocals.push(name.clone());
+                            }
+                        }
+                    }
+                }
+
+                queue.push(PyBytecode::BuildTuple(0));
+                queue.push(PyBytecode::LoadConst(Obj::Code(Arc::new(code))));
+                queue.push(PyBytecode::MakeFunction);
+                queue.push(PyBytecode::LoadConst(Obj::Str(name.clone())));
+                let base_count = bases.len();
+                for b in bases {
+                    PyBytecode::from_expr(b, queue)?;
+                }
+                queue.push(PyBytecode::BuildTuple(base_count));
+                queue.push(PyBytecode::LoadBuildClass);
+                Ok(name)
+            }
+            e => Err(PyException::syntax_error(format!("expected a def or class, found {e}"))),
+        }
+    }
+
+    // @a
+    // @b
+    // def f(): ...
+    // is f = a(b(f)), the decorators are evaluated top down before the
+    // def runs and then called bottom up on what it made
+    fn from_decorated(mut decorators: Vec<Expression>, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        let definition = decorators.pop().ok_or_else(invalid_syntax)?;
+        let mut calls = vec![];
+        for d in decorators {
+            PyBytecode::from_expr(d, queue)?;
+            calls.push(PyBytecode::CallFunction(1));
+        }
+        let name = PyBytecode::from_definition(definition, queue)?;
+        queue.extend(calls.into_iter().rev());
+        queue.push(PyBytecode::StoreName(name));
+        Ok(())
+    }
+
+    // Positional args then keyword values, the names of the keyword args
+    // go in a tuple on top for CallFunctionKw to match them up
+    pub fn from_call_args(args: Vec<Expression>, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        let unpacks = args
+            .iter()
+            .any(|a| matches!(a, Expression::Operation(Op::Unpack | Op::UnpackDict, _)));
+        if unpacks {
+            return PyBytecode::from_call_args_ex(args, queue);
+        }
+        let argc = args.len();
+        let mut kw_names = vec![];
+        for a in args {
+            match a {
+                Expression::Operation(Op::Equals, mut kv) => {
+                    let value = kv.pop().ok_or_else(invalid_syntax)?;
+                    kw_names.push(Obj::Str(kv.pop().ok_or_else(invalid_syntax)?.get_value_string()?).into());
+                    PyBytecode::from_expr(value, queue)?;
+                }
+                a if kw_names.is_empty() => PyBytecode::from_expr(a, queue)?,
+                a => return Err(PyException::syntax_error(format!("positional argument {a} follows keyword argument"))),
+            }
+        }
+        match kw_names.is_empty() {
+            true => queue.push(PyBytecode::CallFunction(argc)),
+            false => {
+                queue.push(PyBytecode::LoadConst(Obj::Tuple(kw_names)));
+                queue.push(PyBytecode::CallFunctionKw(argc));
+            }
+        }
+        Ok(())
+    }
+
+    // f(a, *b, k=c, **d) builds a list of the positional args and a dict of
+    // the keyword ones, CallFunctionEx's flag says whether the dict is there
+    fn from_call_args_ex(args: Vec<Expression>, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        let mut keywords = vec![];
+        queue.push(PyBytecode::BuildList(0));
+        for a in args {
+            match a {
+                Expression::Operation(Op::Unpack, mut vals) => {
+                    PyBytecode::from_expr(vals.pop().ok_or_else(invalid_syntax)?, queue)?;
+                    queue.push(PyBytecode::ListExtend(1));
+                }
+                Expression::Operation(Op::Equals | Op::UnpackDict, _) => keywords.push(a),
+                a if keywords.is_empty() => {
+                    PyBytecode::from_expr(a, queue)?;
+                    queue.push(PyBytecode::ListAppend(1));
+                }
+                a => return Err(PyException::syntax_error(format!("positional argument {a} follows keyword argument"))),
+            }
+        }
+        if keywords.is_empty() {
+            queue.push(PyBytecode::CallFunctionEx(0));
+            return Ok(());
+        }
+        queue.push(PyBytecode::BuildMap(0));
+        for k in keywords {
+            match k {
+                Expression::Operation(Op::Equals, mut kv) => {
+                    let value = kv.pop().ok_or_else(invalid_syntax)?;
+                    queue.push(PyBytecode::LoadConst(Obj::Str(kv.pop().ok_or_else(invalid_syntax)?.get_value_string()?)));
+                    PyBytecode::from_expr(value, queue)?;
+                    queue.push(PyBytecode::MapAdd(1));
+                }
+                Expression::Operation(_, mut vals) => {
+                    PyBytecode::from_expr(vals.pop().ok_or_else(invalid_syntax)?, queue)?;
+                    queue.push(PyBytecode::DictMerge(1));
+                }
+                _ => return Err(invalid_syntax()),
+            }
+        }
+        queue.push(PyBytecode::CallFunctionEx(1));
+        Ok(())
+    }
+
+    // Sorts a def or lambda's parameters into the code object then emits
+    // the defaults, evaluated once when the def runs, and MakeFunction.
+    // co_varnames holds positional, keyword only, *args then **kwargs names
+    fn from_function(mut code: CodeObject, params: Vec<Expression>, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        let mut positional = vec![];
+        let mut kwonly = vec![];
+        let mut varargs = None;
+        let mut varkw = None;
+        let mut defaults = vec![];
+        let mut kwdefaults = vec![];
+        for p in params {
+            let (name, default) = match p {
+                Expression::Ident(name) => (name, None),
+                Expression::Operation(Op::Equals, mut vals) => {
+                    let default = vals.pop();
+                    (vals.pop().ok_or_else(invalid_syntax)?.get_value_string()?, default)
+                }
+                Expression::Operation(Op::Unpack, mut vals) => {
+                    // a bare * only starts the keyword only args
+                    varargs = Some(vals.pop().map(|v| v.get_value_string()).transpose()?);
+                    continue;
+                }
+                Expression::Operation(Op::UnpackDict, mut vals) => {
+                    varkw = vals.pop().map(|v| v.get_value_string()).transpose()?;
+                    continue;
+                }
+                e => return Err(PyException::syntax_error(format!("invalid parameter {e}"))),
+            };
+            match (&varargs, default) {
+                (None, Some(d)) => {
+                    defaults.push(d);
+                    positional.push(name);
+                }
+                (None, None) if !defaults.is_empty() => {
+                    return Err(PyException::syntax_error(format!("non-default argument '{name}' follows default argument")))
+                }
+                (None, None) => positional.push(name),
+                (Some(_), Some(d)) => {
+                    kwdefaults.push((name.clone(), d));
+                    kwonly.push(name);
+                }
+                (Some(_), None) => kwonly.push(name),
+            }
+        }
+
+        code.co_argcount = positional.len();
+        code.co_kwonlyargcount = kwonly.len();
+        code.co_varnames = positional;
+        code.co_varnames.append(&mut kwonly);
+        if let Some(Some(name)) = varargs {
+            code.co_varnames.push(name);
+            code.co_flags |= CO_VARARGS;
+        }
+        if let Some(name) = varkw {
+            code.co_varnames.push(name);
+            code.co_flags |= CO_VARKEYWORDS;
+        }
+        code.resolve_scopes();
+
+        let default_count = defaults.len();
+        for d in defaults {
+            PyBytecode::from_expr(d, queue)?;
+        }
+        queue.push(PyBytecode::BuildTuple(default_count));
+        // keyword only defaults are only pushed when there are some
+        if !kwdefaults.is_empty() {
+            let kw_count = kwdefaults.len();
+            for (name, d) in kwdefaults {
+                queue.push(PyBytecode::LoadConst(Obj::Str(name)));
+                PyBytecode::from_expr(d, queue)?;
+            }
+            queue.push(PyBytecode::BuildMap(kw_count));
+        }
+        queue.push(PyBytecode::LoadConst(Obj::Code(Arc::new(code))));
+        queue.push(PyBytecode::MakeFunction);
+        Ok(())
+    }
+
+    // Compiles a statement, dropping the value of expression statements
+    // that always leave one on the stack so loops don't fill the stack up
+    pub fn from_stmt(expr: Expression, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        let pops_result = matches!(
+            &expr,
+            Expression::Call(..)
+                | Expression::Operation(Op::Dot | Op::Subscript, _)
+                | Expression::Keyword(Keyword::Yield | Keyword::YieldFrom | Keyword::While, _, _)
+        );
+        PyBytecode::from_expr(expr, queue)?;
+        if pops_result {
+            queue.push(PyBytecode::PopTop);
+        }
+        Ok(())
     }
 
-    pub fn from_str(s: &str) -> Vec<PyBytecode> {
-        use crate::pyrs_interpreter::Interpreter;
-        use std::fs;
-        use std::io::Write;
-        use std::time::{SystemTime, UNIX_EPOCH};
+    pub fn from_for_loop(
+        target: Expression,
+        iter: Expression,
+        mut body: Vec<PyBytecode>,
+        queue: &mut Vec<PyBytecode>,
+    ) -> Result<(), PyException> {
+        PyBytecode::from_expr(iter, queue)?;
+        queue.push(PyBytecode::GetIter);
+
+        let mut store_code = vec![];
+        PyBytecode::from_store(target, &mut store_code)?;
+        let loop_len = store_code.len() + body.len(); // length of for loops contents
+
+        queue.push(PyBytecode::ForIter(loop_len + 1));
+        queue.append(&mut store_code);
+        queue.append(&mut body);
+        queue.push(PyBytecode::JumpBackward(loop_len + 2));
+        Ok(())
+    }
+
+    // the target of an assignment or for loop, 'x', 'x, y', 'obj.x' or 'x[k]'
+    pub fn from_store(target: Expression, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        match target {
+            Expression::Ident(ident) => queue.push(PyBytecode::StoreName(ident)),
+            Expression::Operation(Op::Dot, mut sides) => {
+                let attr = match sides.pop() {
+                    Some(Expression::Ident(attr)) => attr,
+                    Some(e) => return Err(PyException::syntax_error(format!("cannot assign to {e}"))),
+                    None => return Err(invalid_syntax()),
+                };
+                PyBytecode::from_expr(sides.pop().ok_or_else(invalid_syntax)?, queue)?;
+                queue.push(PyBytecode::StoreAttr(attr));
+            }
+            Expression::Operation(Op::Tuple, names) => {
+                queue.push(PyBytecode::UnpackSequence);
+                for n in names.into_iter().rev() {
+                    PyBytecode::from_store(n, queue)?;
+                }
+            }
+            Expression::Operation(Op::Subscript, mut sides) => {
+                let key = sides.pop().ok_or_else(invalid_syntax)?;
+                let container = sides.pop().ok_or_else(invalid_syntax)?;
+                PyBytecode::from_expr(container, queue)?;
+                PyBytecode::from_expr(key, queue)?;
+                queue.push(PyBytecode::StoreSubscr);
+            }
+            e => return Err(PyException::syntax_error(format!("cannot assign to {e}"))),
+        }
+        Ok(())
+    }
 
-        let thread_id = std::thread::current().id();
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos();
-        let temp_file = format!("__temp_bytecode_{:?}_{}__.py", thread_id, timestamp);
-        //println!("temp_file: {temp_file}");
+    pub fn from_delete(target: Expression, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        match target {
+            Expression::Ident(ident) => queue.push(PyBytecode::DeleteName(ident)),
+            Expression::Operation(Op::Dot, mut sides) => {
+                let attr = match sides.pop() {
+                    Some(Expression::Ident(attr)) => attr,
+                    Some(e) => return Err(PyException::syntax_error(format!("cannot delete {e}"))),
+                    None => return Err(invalid_syntax()),
+                };
+                PyBytecode::from_expr(sides.pop().ok_or_else(invalid_syntax)?, queue)?;
+                queue.push(PyBytecode::DeleteAttr(attr));
+            }
+            Expression::Operation(Op::Tuple, targets) => {
+                for t in targets {
+                    PyBytecode::from_delete(t, queue)?;
+                }
+            }
+            Expression::Operation(Op::Subscript, mut sides) => {
+                let key = sides.pop().ok_or_else(invalid_syntax)?;
+                let container = sides.pop().ok_or_else(invalid_syntax)?;
+                PyBytecode::from_expr(container, queue)?;
+                PyBytecode::from_expr(key, queue)?;
+                queue.push(PyBytecode::DeleteSubscr);
+            }
+            e => return Err(PyException::syntax_error(format!("cannot delete {e}"))),
+        }
+        Ok(())
+    }
 
-        let mut file = fs::File::create(&temp_file).expect("Failed to create temp file");
-        file.write_all(s.as_bytes())
-            .expect("Failed to write to temp file");
+    // a name from an import statement and its alias, 'a.b' or 'a.b as c'
+    fn import_alias(name: Expression) -> Result<(String, Option<String>), PyException> {
+        match name {
+            Expression::Keyword(Keyword::As, names, _) => match names.as_slice() {
+                [name, alias] => Ok((name.get_value_string()?, Some(alias.get_value_string()?))),
+                _ => Err(invalid_syntax()),
+            },
+            name => Ok((name.get_value_string()?, None)),
+        }
+    }
 
-        let code = Interpreter::compile_file(&temp_file);
+    // Comprehensions become a function taking the first iterator as its only
+    // argument '.0', the rest run inside it so the loop variables stay local.
+    // Generator expressions yield each element, the others build up the
+    // result with ListAppend/SetAdd/MapAdd and return it
+    fn from_comprehension(op: Op, mut clauses: Vec<Expression>, queue: &mut Vec<PyBytecode>) -> Result<(), PyException> {
+        let element_count = if op == Op::DictComp { 2 } else { 1 };
+        let elements: Vec<Expression> = clauses.drain(..element_count).collect();
+        let first_iter = match clauses.first_mut() {
+            Some(Expression::Keyword(Keyword::For, conds, _)) if conds.len() == 2 => {
+                std::mem::replace(&mut conds[1], Expression::Ident(".0".into()))
+            }
+            _ => return Err(PyException::syntax_error("comprehension must start with a for clause")),
+        };
+        // the result sits below one iterator per for clause
+        let depth = 1 + clauses
+            .iter()
+            .filter(|c| matches!(c, Expression::Keyword(Keyword::For, _, _)))
+            .count();
+
+        let mut inner = vec![];
+        for e in elements {
+            PyBytecode::from_expr(e, &mut inner)?;
+        }
+        let (name, mut body) = match op {
+            Op::GenExpr => {
+                inner.push(PyBytecode::YieldValue);
+                inner.push(PyBytecode::PopTop);
+                ("<genexpr>", vec![])
+            }
+            Op::ListComp => {
+                inner.push(PyBytecode::ListAppend(depth));
+                ("<listcomp>", vec![PyBytecode::BuildList(0)])
+            }
+            Op::SetComp => {
+                inner.push(PyBytecode::SetAdd(depth));
+                ("<setcomp>", vec![PyBytecode::BuildSet(0)])
+            }
+            Op::DictComp => {
+                inner.push(PyBytecode::MapAdd(depth));
+                ("<dictcomp>", vec![PyBytecode::BuildMap(0)])
+            }
+            op => return Err(PyException::syntax_error(format!("{op} is not a comprehension"))),
+        };
+        body.append(&mut PyBytecode::from_clauses(clauses, inner)?);
+
+        let mut code = CodeObject::new(name, body);
+        if op == Op::GenExpr {
+            code.co_code.push(PyBytecode::LoadConst(Obj::None));
+            code.co_flags |= CO_GENERATOR;
+        }
+        code.co_code.push(PyBytecode::ReturnValue);
+        code.co_varnames.push(".0".into());
+        code.co_argcount = 1;
+        code.resolve_scopes();
+
+        queue.push(PyBytecode::BuildTuple(0));
+        queue.push(PyBytecode::LoadConst(Obj::Code(Arc::new(code))));
+        queue.push(PyBytecode::MakeFunction);
+        PyBytecode::from_expr(first_iter, queue)?;
+        queue.push(PyBytecode::GetIter);
+        queue.push(PyBytecode::CallFunction(1));
+        Ok(())
+    }
 
-        // Clean up
-        fs::remove_file(temp_file).expect("Failed to delete temp file");
+    // wraps the innermost code in the comprehension's for/if clauses, inside out
+    pub fn from_clauses(clauses: Vec<Expression>, inner: Vec<PyBytecode>) -> Result<Vec<PyBytecode>, PyException> {
+        let mut code = inner;
+        for clause in clauses.into_iter().rev() {
+            let mut outer = vec![];
+            match clause {
+                Expression::Keyword(Keyword::For, mut conds, _) => {
+                    let iter = conds.pop().ok_or_else(invalid_syntax)?;
+                    let target = conds.pop().ok_or_else(invalid_syntax)?;
+                    PyBytecode::from_for_loop(target, iter, code, &mut outer)?;
+                }
+                Expression::Keyword(Keyword::If, conds, _) => {
+                    for c in conds {
+                        PyBytecode::from_expr(c, &mut outer)?;
+                    }
+                    outer.push(PyBytecode::PopJumpIfFalse(code.len()));
+                    outer.append(&mut code);
+                }
+                e => return Err(PyException::syntax_error(format!("invalid comprehension clause {e}"))),
+            }
+            code = outer;
+        }
+        Ok(code)
+    }
 
-        code
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Vec<PyBytecode>, PyException> {
+        crate::pyrs_interpreter::Interpreter::compile_source(s)
     }
 
     pub fn to_string(vec: &Vec<Self>) -> String {
@@ -0,0 +1,889 @@
+use crate::{
+    pyrs_error::{PyError, PyException},
+    pyrs_obj::{seq_index, Obj},
+    pyrs_std::bind_native_args,
+};
+use rug::Integer;
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex, MutexGuard,
+};
+
+// the methods bytes and bytearray share, the ones only a bytearray has
+// since it is mutable and the ones of a memoryview
+pub const METHODS: [&str; 8] = ["hex", "decode", "count", "find", "index", "startswith", "endswith", "join"];
+pub const BYTEARRAY_METHODS: [&str; 8] = ["append", "extend", "pop", "insert", "remove", "reverse", "clear", "copy"];
+pub const MEMORYVIEW_METHODS: [&str; 6] = ["tobytes", "tolist", "hex", "release", "__enter__", "__exit__"];
+
+pub fn has_method(obj: &Obj, name: &str) -> bool {
+    match obj {
+        Obj::Bytes(_) => METHODS.contains(&name),
+        Obj::ByteArray(_) => METHODS.contains(&name) || BYTEARRAY_METHODS.contains(&name),
+        Obj::MemoryView(_) => MEMORYVIEW_METHODS.contains(&name),
+        _ => false,
+    }
+}
+
+pub fn new_bytearray(bytes: Vec<u8>) -> Obj {
+    Obj::ByteArray(Arc::new(ByteArray::new(bytes)))
+}
+
+// A bytearray's bytes and how many memoryviews are exported from it.
+// While there are any it can't change size, so a view never covers
+// bytes that are gone
+#[derive(Debug)]
+pub struct ByteArray {
+    bytes: Mutex<Vec<u8>>,
+    exports: AtomicUsize,
+}
+
+impl ByteArray
+{
+    pub fn new(bytes: Vec<u8>) -> Self {
+        ByteArray { bytes: Mutex::new(bytes), exports: AtomicUsize::new(0) }
+    }
+
+    // the bytes, for a change that keeps their number
+    pub fn lock(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.bytes.lock().unwrap()
+    }
+
+    // the bytes, for a change that may grow or shrink them
+    pub fn resize(&self) -> Result<MutexGuard<'_, Vec<u8>>, PyException> {
+        match self.exports.load(Ordering::SeqCst) {
+            0 => Ok(self.lock()),
+            _ => Err(PyException {
+                error: PyError::BufferError,
+                msg: "Existing exports of data: object cannot be re-sized".to_string(),
+            }),
+        }
+    }
+}
+
+// A window onto the bytes of a bytes or bytearray object that shares its
+// storage, writes through a view of a bytearray change the bytearray.
+// Slicing a view gives another view of the same object. Each view counts
+// as an export of a bytearray until it is released or dropped, a released
+// view can't be used any more
+#[derive(Debug)]
+pub struct MemoryView {
+    pub obj: Arc<Obj>,
+    start: usize,
+    step: isize,
+    len: usize,
+    released: AtomicBool,
+}
+
+impl MemoryView
+{
+    pub fn new(obj: &Arc<Obj>) -> Result<MemoryView, PyException> {
+        match obj.as_ref() {
+            Obj::Bytes(b) => Ok(MemoryView::export(obj, 0, 1, b.len())),
+            Obj::ByteArray(b) => {
+                let len = b.lock().len();
+                Ok(MemoryView::export(obj, 0, 1, len))
+            }
+            Obj::MemoryView(view) => Ok(MemoryView::export(&view.check()?.obj, view.start, view.step, view.len)),
+            o => Err(PyException::type_error(format!("memoryview: a bytes-like object is required, not '{}'", o.type_name()))),
+        }
+    }
+
+    // a view of obj, counted on it when it is a bytearray
+    fn export(obj: &Arc<Obj>, start: usize, step: isize, len: usize) -> MemoryView {
+        if let Obj::ByteArray(b) = obj.as_ref() {
+            b.exports.fetch_add(1, Ordering::SeqCst);
+        }
+        MemoryView { obj: obj.clone(), start, step, len, released: AtomicBool::new(false) }
+    }
+
+    // ends the export, the first time only
+    pub fn release(&self) {
+        if !self.released.swap(true, Ordering::SeqCst) {
+            if let Obj::ByteArray(b) = self.obj.as_ref() {
+                b.exports.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    pub fn is_released(&self) -> bool {
+        self.released.load(Ordering::SeqCst)
+    }
+
+    // the view, unless it was released
+    pub fn check(&self) -> Result<&Self, PyException> {
+        match self.is_released() {
+            false => Ok(self),
+            true => Err(PyException {
+                error: PyError::ValueError,
+                msg: "operation forbidden on released memoryview object".to_string(),
+            }),
+        }
+    }
+
+    pub fn nbytes(&self) -> usize {
+        self.len
+    }
+
+    pub fn readonly(&self) -> bool {
+        matches!(self.obj.as_ref(), Obj::Bytes(_))
+    }
+
+    // where the view's i-th byte is in the object
+    fn position(&self, i: usize) -> usize {
+        (self.start as isize + i as isize * self.step) as usize
+    }
+
+    // the bytes in view, a released view has none since the bytearray
+    // may have been resized since
+    pub fn to_vec(&self) -> Vec<u8> {
+        if self.is_released() {
+            return vec![];
+        }
+        let pick = |b: &[u8]| (0..self.len).map(|i| b[self.position(i)]).collect();
+        match self.obj.as_ref() {
+            Obj::Bytes(b) => pick(b),
+            Obj::ByteArray(b) => pick(&b.lock()),
+            _ => unreachable!(),
+        }
+    }
+
+    // the view of the slice that starts at the view's start-th byte
+    fn slice(&self, start: isize, step: isize, len: usize) -> MemoryView {
+        let start = (self.start as isize + start * self.step).max(0) as usize;
+        MemoryView::export(&self.obj, start, self.step * step, len)
+    }
+
+    // writes bytes at the view's positions start, start + step, ...
+    fn write(&self, start: isize, step: isize, bytes: &[u8]) -> Result<(), PyException> {
+        let Obj::ByteArray(buf) = self.obj.as_ref() else {
+            return Err(PyException::type_error("cannot modify read-only memory"));
+        };
+        let mut buf = buf.lock();
+        for (k, byte) in bytes.iter().enumerate() {
+            buf[self.position((start + k as isize * step) as usize)] = *byte;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MemoryView
+{
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+pub fn view_attr(view: &MemoryView, name: &str) -> Result<Option<Arc<Obj>>, PyException> {
+    let attr = match name {
+        "obj" => view.obj.clone(),
+        "readonly" => Obj::Bool(view.readonly()).into(),
+        "nbytes" => Obj::Int(view.nbytes().into()).into(),
+        _ => return Ok(None),
+    };
+    view.check()?;
+    Ok(Some(attr))
+}
+
+// the bytes of a bytes-like object, copied out
+pub fn bytes_of(obj: &Obj) -> Option<Vec<u8>> {
+    match obj {
+        Obj::Bytes(b) => Some(b.clone()),
+        Obj::ByteArray(b) => Some(b.lock().clone()),
+        Obj::MemoryView(view) => Some(view.to_vec()),
+        _ => None,
+    }
+}
+
+// an int that has to fit in a byte
+fn byte(obj: &Obj, what: &str) -> Result<u8, PyException> {
+    match obj.to_integer() {
+        Some(i) => i.to_u8().ok_or(PyException {
+            error: PyError::ValueError,
+            msg: format!("{what} must be in range(0, 256)"),
+        }),
+        None => Err(PyException::type_error(format!("'{}' object cannot be interpreted as an integer", obj.type_name()))),
+    }
+}
+
+// a bytes-like object or an iterable of ints, None for anything else
+fn byte_values(obj: &Obj, what: &str) -> Option<Result<Vec<u8>, PyException>> {
+    if let Obj::MemoryView(view) = obj {
+        if let Err(e) = view.check() {
+            return Some(Err(e));
+        }
+    }
+    if let Some(bytes) = bytes_of(obj) {
+        return Some(Ok(bytes));
+    }
+    let items = obj.iter_py()?;
+    Some(items.map(|item| byte(&item, what)).collect())
+}
+
+// a count or size as an isize, an int past it can't be one
+pub fn index_sized(n: &Integer) -> Result<isize, PyException> {
+    n.to_isize().ok_or_else(|| PyException {
+        error: PyError::OverflowError,
+        msg: "cannot fit 'int' into an index-sized integer".to_string(),
+    })
+}
+
+// an empty buffer with room for len bytes, a MemoryError instead of
+// aborting when there isn't
+pub(crate) fn with_capacity(len: usize) -> Result<Vec<u8>, PyException> {
+    let mut buf = Vec::new();
+    buf.try_reserve_exact(len).map_err(|_| PyException::memory_error())?;
+    Ok(buf)
+}
+
+// b * n, a count below zero gives an empty result
+pub fn repeat(bytes: &[u8], n: &Integer) -> Result<Vec<u8>, PyException> {
+    let n = index_sized(n)?.max(0) as usize;
+    let len = match bytes.len().checked_mul(n) {
+        Some(len) if len <= isize::MAX as usize => len,
+        _ => return Err(PyException::memory_error()),
+    };
+    let mut repeated = with_capacity(len)?;
+    for _ in 0..n {
+        repeated.extend_from_slice(bytes);
+    }
+    Ok(repeated)
+}
+
+// bytes() and bytearray(): a count of zero bytes, a str in an encoding, a
+// bytes-like object or an iterable of ints
+pub fn new(type_name: &str, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Vec<u8>, PyException> {
+    let params = bind_native_args(type_name, &["source", "encoding", "errors"], 3, args, kwargs)?;
+    match (params[0].as_deref(), params[1].as_deref(), params[2].as_deref()) {
+        (Some(Obj::Str(s)), Some(encoding), errors) => {
+            let (encoding, errors) = codec_args(type_name, Some(encoding), errors)?;
+            encode(s, &encoding, &errors)
+        }
+        (Some(Obj::Str(_)), None, _) => Err(PyException::type_error("string argument without an encoding")),
+        (_, Some(_), _) => Err(PyException::type_error("encoding without a string argument")),
+        (_, None, Some(_)) => Err(PyException::type_error("errors without a string argument")),
+        (None, None, None) => Ok(vec![]),
+        (Some(count @ (Obj::Int(_) | Obj::Bool(_))), None, None) => match index_sized(&count.to_integer().unwrap())? {
+            count if count < 0 => Err(PyException {
+                error: PyError::ValueError,
+                msg: "negative count".to_string(),
+            }),
+            count => {
+                let mut zeros = with_capacity(count as usize)?;
+                zeros.resize(count as usize, 0);
+                Ok(zeros)
+            }
+        },
+        (Some(source), None, None) => {
+            let what = if type_name == "bytes" { "bytes" } else { "byte" };
+            byte_values(source, what)
+                .unwrap_or_else(|| Err(PyException::type_error(format!("cannot convert '{}' object to {type_name}", source.type_name()))))
+        }
+    }
+}
+
+// memoryview(object)
+pub fn memoryview(args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Obj, PyException> {
+    let params = bind_native_args("memoryview", &["object"], 1, args, kwargs)?;
+    match &params[0] {
+        Some(obj) => Ok(Obj::MemoryView(Arc::new(MemoryView::new(obj)?))),
+        None => Err(PyException::type_error("memoryview() missing required argument 'object' (pos 1)")),
+    }
+}
+
+// b[i] is an int, b[i:j] a bytes or bytearray like b or a memoryview of
+// the same object when b is a view
+pub fn get_item(obj: &Obj, key: &Obj) -> Result<Obj, PyException> {
+    let bytes = match obj {
+        Obj::MemoryView(view) => match (view.check()?, key) {
+            (view, Obj::Slice(..)) => {
+                let (start, step, len) = key.slice_indices(view.nbytes())?;
+                return Ok(Obj::MemoryView(Arc::new(view.slice(start, step, len))));
+            }
+            (view, _) => view.to_vec(),
+        },
+        _ => bytes_of(obj).unwrap(),
+    };
+    if let Obj::Slice(..) = key {
+        let picked = key.slice_items(&bytes)?;
+        return Ok(match obj {
+            Obj::ByteArray(_) => new_bytearray(picked),
+            _ => Obj::Bytes(picked),
+        });
+    }
+    let Some(idx) = key.to_integer() else {
+        let msg = match obj {
+            Obj::Bytes(_) => format!("byte indices must be integers or slices, not {}", key.type_name()),
+            Obj::ByteArray(_) => format!("bytearray indices must be integers or slices, not {}", key.type_name()),
+            _ => "memoryview: invalid slice key".to_string(),
+        };
+        return Err(PyException::type_error(msg));
+    };
+    match seq_index(&idx, bytes.len()) {
+        Some(i) => Ok(Obj::Int(bytes[i].into())),
+        None => Err(PyException {
+            error: PyError::IndexError,
+            msg: "index out of range".to_string(),
+        }),
+    }
+}
+
+// b[key] = val for a bytearray or a memoryview of one. A slice of a
+// bytearray can be replaced by any number of bytes, a view's can't resize
+pub fn set_item(obj: &Obj, key: &Obj, val: &Obj) -> Result<(), PyException> {
+    let len = match obj {
+        Obj::ByteArray(buf) => buf.lock().len(),
+        Obj::MemoryView(view) if view.check()?.readonly() => {
+            return Err(PyException::type_error("cannot modify read-only memory"))
+        }
+        Obj::MemoryView(view) => view.nbytes(),
+        _ => unreachable!(),
+    };
+    let (start, step, count, new) = match key {
+        Obj::Slice(..) => {
+            let new = match (obj, val) {
+                (Obj::ByteArray(_), Obj::Int(_) | Obj::Bool(_)) => None,
+                (Obj::ByteArray(_), val) => byte_values(val, "byte").transpose()?,
+                (_, val) => bytes_of(val),
+            };
+            let new = new.ok_or_else(|| PyException::type_error(match obj {
+                Obj::ByteArray(_) => "can assign only bytes, buffers, or iterables of ints in range(0, 256)".to_string(),
+                _ => format!("a bytes-like object is required, not '{}'", val.type_name()),
+            }))?;
+            let (start, step, count) = key.slice_indices(len)?;
+            (start, step, count, new)
+        }
+        _ => {
+            let Some(idx) = key.to_integer() else {
+                let msg = match obj {
+                    Obj::ByteArray(_) => format!("bytearray indices must be integers or slices, not {}", key.type_name()),
+                    _ => "memoryview: invalid slice key".to_string(),
+                };
+                return Err(PyException::type_error(msg));
+            };
+            let Some(i) = seq_index(&idx, len) else {
+                let msg = match obj {
+                    Obj::ByteArray(_) => "bytearray index out of range",
+                    _ => "index out of range",
+                };
+                return Err(PyException { error: PyError::IndexError, msg: msg.to_string() });
+            };
+            let new = match obj {
+                Obj::ByteArray(_) => byte(val, "byte")?,
+                _ => val.to_integer().and_then(|b| b.to_u8()).ok_or(PyException {
+                    error: PyError::ValueError,
+                    msg: "memoryview: invalid value for format 'B'".to_string(),
+                })?,
+            };
+            (i as isize, 1, 1, vec![new])
+        }
+    };
+    match obj {
+        // a simple slice of a bytearray can grow or shrink it
+        Obj::ByteArray(buf) if step == 1 => {
+            let start = start as usize;
+            let mut buf = match new.len() == count {
+                true => buf.lock(),
+                false => buf.resize()?,
+            };
+            buf.splice(start..start + count, new);
+            Ok(())
+        }
+        Obj::ByteArray(_) if new.len() != count => Err(PyException {
+            error: PyError::ValueError,
+            msg: format!("attempt to assign bytes of size {} to extended slice of size {count}", new.len()),
+        }),
+        Obj::ByteArray(buf) => {
+            let mut buf = buf.lock();
+            for (k, b) in new.into_iter().enumerate() {
+                buf[(start + k as isize * step) as usize] = b;
+            }
+            Ok(())
+        }
+        Obj::MemoryView(_) if new.len() != count => Err(PyException {
+            error: PyError::ValueError,
+            msg: "memoryview assignment: lvalue and rvalue have different structures".to_string(),
+        }),
+        Obj::MemoryView(view) => view.write(start, step, &new),
+        _ => unreachable!(),
+    }
+}
+
+// del b[key] for a bytearray, by index or slice. A view can't resize
+pub fn del_item(obj: &Obj, key: &Obj) -> Result<(), PyException> {
+    let Obj::ByteArray(buf) = obj else {
+        return Err(PyException::type_error("cannot delete memory"));
+    };
+    let mut buf = buf.resize()?;
+    if let Obj::Slice(..) = key {
+        return key.del_slice(&mut buf);
+    }
+    let Some(idx) = key.to_integer() else {
+        return Err(PyException::type_error(format!("bytearray indices must be integers or slices, not {}", key.type_name())));
+    };
+    match seq_index(&idx, buf.len()) {
+        Some(i) => {
+            buf.remove(i);
+            Ok(())
+        }
+        None => Err(PyException {
+            error: PyError::IndexError,
+            msg: "bytearray index out of range".to_string(),
+        }),
+    }
+}
+
+// The bytes as two hex digits each. A separator goes between every
+// bytes_per_sep bytes, counting from the right or from the left when
+// bytes_per_sep is negative
+fn hex(bytes: &[u8], args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<String, PyException> {
+    let params = bind_native_args("hex", &["sep", "bytes_per_sep"], 2, args, kwargs)?;
+    let value_err = |msg: &str| PyException {
+        error: PyError::ValueError,
+        msg: msg.to_string(),
+    };
+    let digits: Vec<String> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    let sep = match params[0].as_deref() {
+        None => return Ok(digits.concat()),
+        Some(Obj::Str(s)) => s.clone(),
+        Some(o) => match bytes_of(o) {
+            Some(b) => b.into_iter().map(char::from).collect(),
+            None => {
+                return Err(PyException::type_error("sep must be str or bytes."))
+            }
+        },
+    };
+    if sep.chars().count() != 1 {
+        return Err(value_err("sep must be length 1."));
+    }
+    if !sep.is_ascii() {
+        return Err(value_err("sep must be ASCII."));
+    }
+    let group = match params[1].as_deref() {
+        None => 1,
+        Some(o) => o.to_integer().and_then(|i| i.to_i64()).ok_or(PyException::type_error(format!("'{}' object cannot be interpreted as an integer", o.type_name())))?,
+    };
+    let size = group.unsigned_abs() as usize;
+    if size == 0 {
+        return Ok(digits.concat());
+    }
+    // from the right the first group is the one left short
+    let first = if group > 0 { digits.len() % size } else { 0 };
+    let mut groups = vec![];
+    if first > 0 {
+        groups.push(digits[..first].concat());
+    }
+    groups.extend(digits[first..].chunks(size).map(|c| c.concat()));
+    Ok(groups.join(&sep))
+}
+
+// bytes.fromhex() and bytearray.fromhex(), pairs of hex digits with
+// whitespace allowed between them
+pub fn fromhex(type_name: &str, args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+    let [arg] = args else {
+        return Err(PyException::type_error(format!("fromhex() takes exactly one argument ({} given)", args.len())));
+    };
+    let Obj::Str(s) = arg.as_ref() else {
+        return Err(PyException::type_error(format!("fromhex() argument must be str, not {}", arg.type_name())));
+    };
+    let invalid = |pos: usize| PyException {
+        error: PyError::ValueError,
+        msg: format!("non-hexadecimal number found in fromhex() arg at position {pos}"),
+    };
+    let chars: Vec<char> = s.chars().collect();
+    let mut bytes = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let high = chars[i].to_digit(16).ok_or_else(|| invalid(i))?;
+        let low = chars.get(i + 1).and_then(|c| c.to_digit(16)).ok_or_else(|| invalid(i + 1))?;
+        bytes.push((high * 16 + low) as u8);
+        i += 2;
+    }
+    Ok(match type_name {
+        "bytearray" => new_bytearray(bytes),
+        _ => Obj::Bytes(bytes),
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Codec {
+    Utf8,
+    Ascii,
+    Latin1,
+}
+
+// the codec an encoding name means and the name its errors use, case,
+// underscores and hyphens aside
+fn codec(encoding: &str) -> Result<(Codec, &'static str), PyException> {
+    let normalized = encoding.to_ascii_lowercase().replace('_', "-");
+    match normalized.as_str() {
+        "utf-8" | "utf8" | "u8" | "utf" => Ok((Codec::Utf8, "utf-8")),
+        "ascii" | "us-ascii" | "646" => Ok((Codec::Ascii, "ascii")),
+        "latin-1" | "latin1" | "latin" | "l1" | "iso-8859-1" | "iso8859-1" | "8859" | "cp819" => {
+            Ok((Codec::Latin1, "latin-1"))
+        }
+        _ => Err(PyException {
+            error: PyError::LookupError,
+            msg: format!("unknown encoding: {encoding}"),
+        }),
+    }
+}
+
+fn unknown_handler(errors: &str) -> PyException {
+    PyException {
+        error: PyError::LookupError,
+        msg: format!("unknown error handler name '{errors}'"),
+    }
+}
+
+// the encoding and errors arguments, utf-8 and strict when left out
+fn codec_args(func: &str, encoding: Option<&Obj>, errors: Option<&Obj>) -> Result<(String, String), PyException> {
+    let arg = |name: &str, obj: Option<&Obj>, default: &str| match obj {
+        None => Ok(default.to_string()),
+        Some(Obj::Str(s)) => Ok(s.clone()),
+        Some(o) => Err(PyException::type_error(format!("{func}() argument '{name}' must be str, not {}", o.type_name()))),
+    };
+    Ok((arg("encoding", encoding, "utf-8")?, arg("errors", errors, "strict")?))
+}
+
+// how a codec error shows a char, '\xe9', '€' or '\U0001f600'
+fn escaped(c: char) -> String {
+    match c as u32 {
+        code @ 0..=0xff => format!("'\\x{code:02x}'"),
+        code @ 0..=0xffff => format!("'\\u{code:04x}'"),
+        code => format!("'\\U{code:08x}'"),
+    }
+}
+
+// Encodes s. A run of chars the codec has no bytes for is one error,
+// "replace" writes a '?' for each of them
+pub fn encode(s: &str, encoding: &str, errors: &str) -> Result<Vec<u8>, PyException> {
+    let (codec, name) = codec(encoding)?;
+    let limit = match codec {
+        Codec::Utf8 => return Ok(s.as_bytes().to_vec()),
+        Codec::Ascii => 0x80,
+        Codec::Latin1 => 0x100,
+    };
+    let chars: Vec<char> = s.chars().collect();
+    let mut bytes = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if (chars[i] as u32) < limit {
+            bytes.push(chars[i] as u8);
+            i += 1;
+            continue;
+        }
+        let end = i + chars[i..].iter().take_while(|c| **c as u32 >= limit).count();
+        match errors {
+            "strict" => {
+                let what = match end - i {
+                    1 => format!("character {} in position {i}", escaped(chars[i])),
+                    _ => format!("characters in position {i}-{}", end - 1),
+                };
+                return Err(PyException {
+                    error: PyError::UnicodeEncodeError,
+                    msg: format!("'{name}' codec can't encode {what}: ordinal not in range({limit})"),
+                });
+            }
+            "ignore" => {}
+            "replace" => bytes.extend(std::iter::repeat_n(b'?', end - i)),
+            _ => return Err(unknown_handler(errors)),
+        }
+        i = end;
+    }
+    Ok(bytes)
+}
+
+// Decodes bytes. Each invalid sequence is one error, "replace" writes a
+// U+FFFD for it
+pub fn decode(bytes: &[u8], encoding: &str, errors: &str) -> Result<String, PyException> {
+    decode_chars(bytes, encoding, errors, None).map(|(s, _)| s)
+}
+
+// how many bytes at the end start a char the rest of which hasn't been
+// read yet, a reader decoding a chunk at a time leaves them for the next
+pub fn unfinished_tail(bytes: &[u8], encoding: &str) -> Result<usize, PyException> {
+    if !matches!(codec(encoding)?.0, Codec::Utf8) {
+        return Ok(0);
+    }
+    let start = bytes.len().saturating_sub(3);
+    let Some(lead) = (start..bytes.len()).rev().find(|i| bytes[*i] & 0xc0 != 0x80) else {
+        return Ok(0);
+    };
+    let len = match bytes[lead] {
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => 1,
+    };
+    Ok(match bytes.len() - lead < len {
+        true => bytes.len() - lead,
+        false => 0,
+    })
+}
+
+// Decodes the bytes up to the limit-th char, and says how many of the
+// bytes that took
+pub fn decode_chars(bytes: &[u8], encoding: &str, errors: &str, mut limit: Option<usize>) -> Result<(String, usize), PyException> {
+    let (codec, name) = codec(encoding)?;
+    let mut s = String::with_capacity(bytes.len());
+    let mut rest = bytes;
+    let mut pos = 0;
+    loop {
+        // the valid part up to the next invalid sequence, its length and why
+        let (valid, bad, reason) = match codec {
+            Codec::Latin1 => {
+                let end = limit.map_or(bytes.len(), |limit| limit.min(bytes.len()));
+                return Ok((bytes[..end].iter().map(|&b| char::from(b)).collect(), end));
+            }
+            Codec::Ascii => match rest.iter().position(|b| !b.is_ascii()) {
+                Some(valid) => (valid, 1, "ordinal not in range(128)"),
+                None => (rest.len(), 0, ""),
+            },
+            Codec::Utf8 => match std::str::from_utf8(rest) {
+                Ok(_) => (rest.len(), 0, ""),
+                Err(e) => match e.error_len() {
+                    Some(len) if (0xc2..=0xf4).contains(&rest[e.valid_up_to()]) => {
+                        (e.valid_up_to(), len, "invalid continuation byte")
+                    }
+                    Some(len) => (e.valid_up_to(), len, "invalid start byte"),
+                    None => (e.valid_up_to(), rest.len() - e.valid_up_to(), "unexpected end of data"),
+                },
+            },
+        };
+        let text = std::str::from_utf8(&rest[..valid]).unwrap();
+        if let Some(left) = limit.as_mut() {
+            if let Some((cut, _)) = text.char_indices().nth(*left) {
+                s.push_str(&text[..cut]);
+                return Ok((s, pos + cut));
+            }
+            *left -= text.chars().count();
+        }
+        s.push_str(text);
+        let at = pos + valid;
+        if bad == 0 || limit == Some(0) {
+            return Ok((s, at));
+        }
+        match errors {
+            "strict" => {
+                let what = match bad {
+                    1 => format!("byte 0x{:02x} in position {at}", rest[valid]),
+                    _ => format!("bytes in position {at}-{}", at + bad - 1),
+                };
+                return Err(PyException {
+                    error: PyError::UnicodeDecodeError,
+                    msg: format!("'{name}' codec can't decode {what}: {reason}"),
+                });
+            }
+            "ignore" => {}
+            "replace" => {
+                s.push('\u{fffd}');
+                limit = limit.map(|left| left - 1);
+            }
+            _ => return Err(unknown_handler(errors)),
+        }
+        rest = &rest[valid + bad..];
+        pos = at + bad;
+    }
+}
+
+// str.encode(encoding="utf-8", errors="strict")
+pub fn encode_method(s: &str, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Obj, PyException> {
+    let params = bind_native_args("encode", &["encoding", "errors"], 2, args, kwargs)?;
+    let (encoding, errors) = codec_args("encode", params[0].as_deref(), params[1].as_deref())?;
+    encode(s, &encoding, &errors).map(Obj::Bytes)
+}
+
+// the first occurrence of sub in bytes
+fn find(bytes: &[u8], sub: &[u8]) -> Option<usize> {
+    match sub.is_empty() {
+        true => Some(0),
+        false => bytes.windows(sub.len()).position(|w| w == sub),
+    }
+}
+
+// how many times sub is in bytes without overlapping
+fn count(bytes: &[u8], sub: &[u8]) -> usize {
+    if sub.is_empty() {
+        return bytes.len() + 1;
+    }
+    let (mut n, mut i) = (0, 0);
+    while i + sub.len() <= bytes.len() {
+        match &bytes[i..i + sub.len()] == sub {
+            true => {
+                n += 1;
+                i += sub.len();
+            }
+            false => i += 1,
+        }
+    }
+    n
+}
+
+// the part of len bytes the optional start and end arguments pick out
+fn span(len: usize, start: Option<&Arc<Obj>>, end: Option<&Arc<Obj>>) -> Result<(usize, usize), PyException> {
+    let bound = |arg: Option<&Arc<Obj>>| arg.cloned().unwrap_or(Obj::None.into());
+    let slice = Obj::Slice(bound(start), bound(end), Obj::None.into());
+    let (start, _, count) = slice.slice_indices(len)?;
+    Ok((start as usize, start as usize + count))
+}
+
+pub fn call_method(
+    recv: &Obj,
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+) -> Result<Obj, PyException> {
+    let arg_count_err = |expected: &str| PyException::type_error(format!("{name}() takes {expected} ({} given)", args.len()));
+    let as_byte_list = |bytes: Vec<u8>| Obj::new_list(bytes.into_iter().map(|b| Obj::Int(b.into()).into()).collect());
+    match (recv, name, args) {
+        (Obj::MemoryView(view), "release" | "__exit__", _) => {
+            view.release();
+            return Ok(Obj::None);
+        }
+        (Obj::MemoryView(view), "hex", _) => return hex(&view.check()?.to_vec(), args, kwargs).map(Obj::Str),
+        (Obj::MemoryView(view), "tobytes", []) => return Ok(Obj::Bytes(view.check()?.to_vec())),
+        (Obj::MemoryView(view), "tolist", []) => return Ok(as_byte_list(view.check()?.to_vec())),
+        (Obj::MemoryView(_), _, _) => return Err(arg_count_err("no arguments")),
+        (Obj::ByteArray(buf), _, _) if BYTEARRAY_METHODS.contains(&name) => return bytearray_method(buf, name, args),
+        _ => {}
+    }
+    let bytes = bytes_of(recv).unwrap();
+    match name {
+        "hex" => hex(&bytes, args, kwargs).map(Obj::Str),
+        "decode" => {
+            let params = bind_native_args("decode", &["encoding", "errors"], 2, args, kwargs)?;
+            let (encoding, errors) = codec_args("decode", params[0].as_deref(), params[1].as_deref())?;
+            decode(&bytes, &encoding, &errors).map(Obj::Str)
+        }
+        "count" | "find" | "index" | "startswith" | "endswith" => {
+            let [sub, bounds @ ..] = args else {
+                return Err(arg_count_err("at least 1 argument"));
+            };
+            if bounds.len() > 2 {
+                return Err(arg_count_err("at most 3 arguments"));
+            }
+            let (start, end) = span(bytes.len(), bounds.first(), bounds.get(1))?;
+            let part = &bytes[start..end];
+            if name == "startswith" || name == "endswith" {
+                let candidates = match sub.as_ref() {
+                    Obj::Tuple(items) => items.iter().map(|i| bytes_of(i)).collect::<Option<Vec<_>>>(),
+                    o => bytes_of(o).map(|b| vec![b]),
+                };
+                let candidates = candidates.ok_or_else(|| PyException::type_error(format!("{name} first arg must be bytes or a tuple of bytes, not {}", sub.type_name())))?;
+                let found = candidates.iter().any(|c| match name {
+                    "startswith" => part.starts_with(c),
+                    _ => part.ends_with(c),
+                });
+                return Ok(Obj::Bool(found));
+            }
+            let sub = match sub.as_ref() {
+                Obj::Int(_) | Obj::Bool(_) => vec![byte(sub, "byte")?],
+                o => bytes_of(o).ok_or_else(|| PyException::type_error(format!("argument should be integer or bytes-like object, not '{}'", o.type_name())))?,
+            };
+            match (name, find(part, &sub)) {
+                ("count", _) => Ok(Obj::Int(count(part, &sub).into())),
+                (_, Some(i)) => Ok(Obj::Int((start + i).into())),
+                ("find", None) => Ok(Obj::Int((-1).into())),
+                _ => Err(PyException {
+                    error: PyError::ValueError,
+                    msg: "subsection not found".to_string(),
+                }),
+            }
+        }
+        "join" => {
+            let [iterable] = args else {
+                return Err(arg_count_err("exactly one argument"));
+            };
+            let items = iterable.iter_py().ok_or_else(|| PyException::type_error("can only join an iterable"))?;
+            let mut parts = vec![];
+            for (i, item) in items.enumerate() {
+                parts.push(bytes_of(&item).ok_or_else(|| PyException::type_error(format!("sequence item {i}: expected a bytes-like object, {} found", item.type_name())))?);
+            }
+            let joined = parts.join(bytes.as_slice());
+            Ok(match recv {
+                Obj::ByteArray(_) => new_bytearray(joined),
+                _ => Obj::Bytes(joined),
+            })
+        }
+        _ => Err(PyException {
+            error: PyError::AttributeError,
+            msg: format!("'{}' object has no attribute '{name}'", recv.type_name()),
+        }),
+    }
+}
+
+// the methods that change a bytearray in place
+fn bytearray_method(buf: &ByteArray, name: &str, args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+    let index_err = |msg: &str| PyException {
+        error: PyError::IndexError,
+        msg: msg.to_string(),
+    };
+    let index = |obj: &Obj| {
+        obj.to_integer().ok_or_else(|| {
+            PyException::type_error(format!("'{}' object cannot be interpreted as an integer", obj.type_name()))
+        })
+    };
+    match (name, args) {
+        ("append", [x]) => {
+            let b = byte(x, "byte")?;
+            buf.resize()?.push(b);
+        }
+        ("extend", [iterable]) => {
+            let bytes = byte_values(iterable, "byte").unwrap_or_else(|| {
+                Err(PyException::type_error(format!("can't extend bytearray with {}", iterable.type_name())))
+            })?;
+            buf.resize()?.extend(bytes);
+        }
+        ("pop", [] | [_]) => {
+            let mut buf = buf.resize()?;
+            if buf.is_empty() {
+                return Err(index_err("pop from empty bytearray"));
+            }
+            let idx = match args.first() {
+                Some(i) => seq_index(&index(i)?, buf.len()).ok_or_else(|| index_err("pop index out of range"))?,
+                None => buf.len() - 1,
+            };
+            return Ok(Obj::Int(buf.remove(idx).into()));
+        }
+        ("insert", [i, x]) => {
+            let (i, b) = (index(i)?, byte(x, "byte")?);
+            let mut buf = buf.resize()?;
+            // like list.insert an index past either end inserts at that end
+            let len = buf.len();
+            let i = match i < 0 {
+                true => (i + len).to_usize().unwrap_or(0),
+                false => i.to_usize().unwrap_or(len).min(len),
+            };
+            buf.insert(i, b);
+        }
+        ("remove", [x]) => {
+            let b = byte(x, "byte")?;
+            let mut buf = buf.resize()?;
+            match buf.iter().position(|&c| c == b) {
+                Some(i) => {
+                    buf.remove(i);
+                }
+                None => {
+                    return Err(PyException {
+                        error: PyError::ValueError,
+                        msg: "value not found in bytearray".to_string(),
+                    })
+                }
+            }
+        }
+        ("reverse", []) => buf.lock().reverse(),
+        ("clear", []) => buf.resize()?.clear(),
+        ("copy", []) => return Ok(new_bytearray(buf.lock().clone())),
+        ("append" | "extend" | "remove", _) => {
+            return Err(PyException::type_error(format!("{name}() takes exactly one argument ({} given)", args.len())))
+        }
+        ("pop", _) => return Err(PyException::type_error(format!("pop expected at most 1 argument, got {}", args.len()))),
+        ("insert", _) => return Err(PyException::type_error(format!("insert expected 2 arguments, got {}", args.len()))),
+        _ => return Err(PyException::type_error(format!("{name}() takes no arguments ({} given)", args.len()))),
+    }
+    Ok(Obj::None)
+}
@@ -0,0 +1,64 @@
+use crate::{
+    pyrs_builtins::{no_kwargs, one_arg},
+    pyrs_error::PyException,
+    pyrs_native::NativeModule,
+    pyrs_obj::{int_to_f64, Obj},
+};
+use rug::Complex;
+use std::f64::consts;
+
+pub const MODULE_NAME: &str = "cmath";
+
+// cmath.sqrt and the rest, each its own native function
+pub fn module() -> NativeModule {
+    NativeModule::new(MODULE_NAME)
+        .function("exp", |_, args, kwargs| Ok(Obj::Complex(Box::new(complex_arg(one_arg("cmath.exp", args, kwargs)?.as_ref())?.exp())).into()))
+        .function("phase", |_, args, kwargs| {
+            let z = complex_arg(one_arg("cmath.phase", args, kwargs)?.as_ref())?;
+            Ok(Obj::Float(z.imag().to_f64().atan2(z.real().to_f64())).into())
+        })
+        // (abs(z), phase(z))
+        .function("polar", |_, args, kwargs| {
+            let z = complex_arg(one_arg("cmath.polar", args, kwargs)?.as_ref())?;
+            let (real, imag) = (z.real().to_f64(), z.imag().to_f64());
+            Ok(Obj::Tuple(vec![Obj::Float(real.hypot(imag)).into(), Obj::Float(imag.atan2(real)).into()]).into())
+        })
+        // the complex with modulus r and phase phi
+        .function("rect", |_, args, kwargs| {
+            no_kwargs("rect", kwargs)?;
+            let [r, phi] = args else {
+                return Err(PyException::type_error(format!("rect expected 2 arguments, got {}", args.len())));
+            };
+            let (r, phi) = (real_arg(r)?, real_arg(phi)?);
+            Ok(complex(r * phi.cos(), r * phi.sin()).into())
+        })
+        .function("sqrt", |_, args, kwargs| Ok(Obj::Complex(Box::new(complex_arg(one_arg("cmath.sqrt", args, kwargs)?.as_ref())?.sqrt())).into()))
+        .constant("e", Obj::Float(consts::E))
+        .constant("inf", Obj::Float(f64::INFINITY))
+        .constant("infj", complex(0.0, f64::INFINITY))
+        .constant("nan", Obj::Float(f64::NAN))
+        .constant("nanj", complex(0.0, f64::NAN))
+        .constant("pi", Obj::Float(consts::PI))
+        .constant("tau", Obj::Float(consts::TAU))
+}
+
+fn complex(real: f64, imag: f64) -> Obj {
+    Obj::Complex(Box::new(Complex::with_val(53, (real, imag))))
+}
+
+fn not_real(obj: &Obj) -> PyException {
+    PyException::type_error(format!("must be real number, not {}", obj.type_name()))
+}
+
+fn complex_arg(obj: &Obj) -> Result<Complex, PyException> {
+    obj.to_complex().ok_or_else(|| not_real(obj))
+}
+
+fn real_arg(obj: &Obj) -> Result<f64, PyException> {
+    match obj {
+        Obj::Float(f) => Ok(*f),
+        Obj::Int(i) => int_to_f64(i),
+        Obj::Bool(b) => Ok(f64::from(u8::from(*b))),
+        o => Err(not_real(o)),
+    }
+}
@@ -1,27 +1,187 @@
 
+use crate::{
+    pyrs_bytecode::{PyBytecode},
+    pyrs_import::PyModule,
+    pyrs_obj::{Obj},
+};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
 
-use crate::pyrs_bytecode::{PyBytecode};
+// co_flags values, same bits as CPython
+pub const CO_VARARGS: usize = 0x04;
+pub const CO_VARKEYWORDS: usize = 0x08;
+pub const CO_GENERATOR: usize = 0x20;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CodeObject
 {
-    co_nlocals: usize,
-    co_argcount: usize,
-    co_varnames: Vec<String>,
-    co_names: Vec<String>,
-    co_freevars: Vec<String>,
-    co_cellvars: Vec<String>,
-    co_posonlyargcount: usize,
-    co_kwonlyargcount: usize,
-    co_firstlineno: usize,
-    co_lnotab: usize,
-    co_stacksize: usize,
-    co_code: Vec<PyBytecode>,
-    co_consts: Vec<String>,
-    co_flags: usize,
+    pub co_name: String,
+    pub co_nlocals: usize,
+    pub co_argcount: usize,
+    pub co_varnames: Vec<String>,
+    pub co_names: Vec<String>,
+    pub co_freevars: Vec<String>,
+    pub co_cellvars: Vec<String>,
+    pub co_posonlyargcount: usize,
+    pub co_kwonlyargcount: usize,
+    pub co_firstlineno: usize,
+    pub co_lnotab: usize,
+    pub co_stacksize: usize,
+    pub co_code: Vec<PyBytecode>,
+    pub co_consts: Vec<String>,
+    pub co_flags: usize,
+    // the names declared nonlocal here or in nested code that no
+    // enclosing function has bound yet, a module can't leave any
+    pub co_nonlocals: Vec<String>,
 }
 
 impl CodeObject
 {
-    
-}
\ No newline at end of file
+    pub fn new(name: &str, code: Vec<PyBytecode>) -> Self {
+        CodeObject {
+            co_name: name.to_string(),
+            co_nlocals: 0,
+            co_argcount: 0,
+            co_varnames: vec![],
+            co_names: vec![],
+            co_freevars: vec![],
+            co_cellvars: vec![],
+            co_posonlyargcount: 0,
+            co_kwonlyargcount: 0,
+            co_firstlineno: 0,
+            co_lnotab: 0,
+            co_stacksize: 0,
+            co_code: code,
+            co_consts: vec![],
+            co_flags: 0,
+            co_nonlocals: vec![],
+        }
+    }
+
+    pub fn is_generator(&self) -> bool {
+        self.co_flags & CO_GENERATOR != 0
+    }
+
+    // the positional parameter names, co_varnames starts with them
+    pub fn arg_names(&self) -> &[String] {
+        &self.co_varnames[..self.co_argcount]
+    }
+
+    pub fn kwonly_names(&self) -> &[String] {
+        &self.co_varnames[self.co_argcount..self.co_argcount + self.co_kwonlyargcount]
+    }
+
+    // the names *args and **kwargs are bound to, they follow the keyword only args
+    pub fn varargs_name(&self) -> Option<&String> {
+        match self.co_flags & CO_VARARGS {
+            0 => None,
+            _ => self.co_varnames.get(self.co_argcount + self.co_kwonlyargcount),
+        }
+    }
+
+    pub fn varkw_name(&self) -> Option<&String> {
+        match self.co_flags & CO_VARKEYWORDS {
+            0 => None,
+            _ => {
+                let idx = self.co_argcount + self.co_kwonlyargcount + (self.co_flags & CO_VARARGS != 0) as usize;
+                self.co_varnames.get(idx)
+            }
+        }
+    }
+
+    // global x makes x a name of the module wherever the code uses it,
+    // nonlocal x makes it free even though the code assigns it, so it
+    // stays in the enclosing function's cell. Runs before resolve_scopes
+    pub fn declare(&mut self, globals: &[String], nonlocals: &[String]) {
+        for inst in self.co_code.iter_mut() {
+            *inst = match inst {
+                PyBytecode::LoadName(name) if globals.contains(name) => PyBytecode::LoadGlobal(name.clone()),
+                PyBytecode::StoreName(name) if globals.contains(name) => PyBytecode::StoreGlobal(name.clone()),
+                PyBytecode::DeleteName(name) if globals.contains(name) => PyBytecode::DeleteGlobal(name.clone()),
+                _ => continue,
+            };
+        }
+        for name in nonlocals {
+            if !self.co_freevars.contains(name) {
+                self.co_freevars.push(name.clone());
+            }
+            if !self.co_nonlocals.contains(name) {
+                self.co_nonlocals.push(name.clone());
+            }
+        }
+    }
+
+    // Works out the scopes from the names the code loads and stores: what it
+    // assigns is local, what it only reads is free (from an enclosing function
+    // or a global) and its locals that nested functions read become cells
+    pub fn resolve_scopes(&mut self) {
+        let mut loaded = vec![];
+        let mut nested_free = vec![];
+        let mut nested_nonlocals = vec![];
+        let mut globals = vec![];
+        for inst in &self.co_code {
+            match inst {
+                PyBytecode::StoreName(name) | PyBytecode::DeleteName(name)
+                    if !self.co_varnames.contains(name) && !self.co_freevars.contains(name) =>
+                {
+                    self.co_varnames.push(name.clone())
+                }
+                PyBytecode::LoadName(name) => loaded.push(name.clone()),
+                PyBytecode::LoadGlobal(name) | PyBytecode::StoreGlobal(name) | PyBytecode::DeleteGlobal(name) => {
+                    globals.push(name.clone())
+                }
+                PyBytecode::LoadConst(Obj::Code(code)) => {
+                    nested_free.extend(code.co_freevars.iter().cloned());
+                    nested_nonlocals.extend(code.co_nonlocals.iter().cloned());
+                }
+                _ => {}
+            }
+        }
+        self.co_nlocals = self.co_varnames.len();
+
+        // what this code assigns is the binding a nested nonlocal refers to
+        for name in nested_nonlocals {
+            if !self.co_varnames.contains(&name) && !self.co_nonlocals.contains(&name) {
+                self.co_nonlocals.push(name);
+            }
+        }
+
+        for name in loaded.into_iter().chain(nested_free.iter().cloned()) {
+            if !self.co_varnames.contains(&name) && !self.co_freevars.contains(&name) && !globals.contains(&name) {
+                self.co_freevars.push(name);
+            }
+        }
+        for name in nested_free {
+            if self.co_varnames.contains(&name) && !self.co_cellvars.contains(&name) {
+                self.co_cellvars.push(name);
+            }
+        }
+    }
+}
+
+// A function object made by MakeFunction, a code object plus the
+// default values and the cells captured from the enclosing function.
+// attrs holds what functools.wraps copies over, like __name__, and
+// globals the module it was defined in, None in the script itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserFunc
+{
+    pub code: Arc<CodeObject>,
+    pub defaults: Vec<Arc<Obj>>,
+    pub kwdefaults: HashMap<String, Arc<Obj>>,
+    pub closure: HashMap<String, Arc<Obj>>,
+    pub attrs: HashMap<String, Arc<Obj>>,
+    pub globals: Option<Arc<PyModule>>,
+}
+
+impl UserFunc
+{
+    pub fn name(&self) -> &str {
+        match self.attrs.get("__name__").map(|n| n.as_ref()) {
+            Some(Obj::Str(name)) => name,
+            _ => &self.code.co_name,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    pyrs_error::{PyError, PyException},
+    pyrs_native::{with_self, NativeClass, NativeModule, NativeObj},
+    pyrs_obj::Obj,
+    pyrs_vm::PyVM,
+};
+use std::sync::{Arc, OnceLock};
+
+pub const MODULE_NAME: &str = "contextlib";
+
+// @contextmanager
+// def opened(path):
+//     with open(path) as f:
+//         yield f
+//
+// makes opened(path) a context manager. Entering it runs the generator to
+// its yield, leaving it runs the rest, or raises the with block's error at
+// the yield for the generator to clean up after or swallow
+pub fn module() -> NativeModule {
+    NativeModule::new(MODULE_NAME)
+        .function("contextmanager", |_, args, _| match args {
+            [func] => Ok(NativeObj::wrap(helper(), func.clone()).into()),
+            _ => Err(PyException::type_error(format!("contextmanager() takes exactly one argument ({} given)", args.len()))),
+        })
+        .class(manager().clone())
+}
+
+// the generator function or generator a helper or manager holds
+fn wrapped(args: &[Arc<Obj>]) -> Result<Arc<Obj>, PyException> {
+    with_self(args, |obj: &mut Arc<Obj>| obj.clone())
+}
+
+// the decorated function, calling it calls the generator function and
+// hands back a manager for the generator
+fn helper() -> &'static Arc<NativeClass> {
+    static CLASS: OnceLock<Arc<NativeClass>> = OnceLock::new();
+    CLASS.get_or_init(|| {
+        let mut class = NativeClass::new("function")
+            .method("__call__", |vm, args, kwargs| {
+                let gen = vm.call_object(wrapped(args)?, args[1..].to_vec(), kwargs.to_vec())?;
+                Ok(NativeObj::wrap(manager(), gen).into())
+            })
+            .method("__repr__", |vm, args, _| {
+                let name = vm.get_attr(&wrapped(args)?, "__name__")?;
+                Ok(Obj::Str(format!("<function {} at {:p}>", name, Arc::as_ptr(&args[0]))).into())
+            })
+            .attr("__wrapped__", |_, args, _| wrapped(args))
+            .attr("__name__", |vm, args, _| vm.get_attr(&wrapped(args)?, "__name__"))
+            .attr("__qualname__", |vm, args, _| vm.get_attr(&wrapped(args)?, "__qualname__"))
+            .attr("__doc__", |vm, args, _| vm.get_attr(&wrapped(args)?, "__doc__"));
+        class.module = MODULE_NAME.to_string();
+        Arc::new(class)
+    })
+}
+
+fn manager() -> &'static Arc<NativeClass> {
+    static CLASS: OnceLock<Arc<NativeClass>> = OnceLock::new();
+    CLASS.get_or_init(|| {
+        let mut class = NativeClass::new("_GeneratorContextManager")
+            .method("__enter__", |vm, args, _| {
+                let gen = wrapped(args)?;
+                vm.next_item(&gen)?.ok_or_else(|| PyException::new(PyError::RuntimeError, "generator didn't yield"))
+            })
+            .method("__exit__", exit);
+        class.module = MODULE_NAME.to_string();
+        Arc::new(class)
+    })
+}
+
+// __exit__(type, value, traceback), true when the generator swallowed the error
+fn exit(vm: &mut PyVM, args: &[Arc<Obj>], _: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let gen = wrapped(args)?;
+    let error = match args.get(1..3).map(|args| (args[0].as_ref(), args[1].as_ref())) {
+        Some((Obj::None, _)) | None => {
+            return match vm.next_item(&gen)? {
+                Some(_) => Err(PyException::new(PyError::RuntimeError, "generator didn't stop")),
+                None => Ok(Obj::Bool(false).into()),
+            };
+        }
+        Some((_, Obj::Except(e))) => e.clone(),
+        Some((Obj::ExceptType(error), _)) => PyException {
+            error: *error,
+            msg: String::new(),
+        },
+        Some((o, _)) => return Err(PyException::type_error(format!("exceptions must derive from BaseException, not {}", o.type_name()))),
+    };
+    let throw = vm.get_attr(&gen, "throw")?;
+    match vm.call_object(throw, vec![Obj::Except(error.clone()).into()], vec![]) {
+        Ok(_) => Err(PyException::new(PyError::RuntimeError, "generator didn't stop after throw()")),
+        // the generator finished, so it dealt with the error
+        Err(e) if e.error == PyError::StopIteration && error.error != PyError::StopIteration => Ok(Obj::Bool(true).into()),
+        // the error coming back out is left for the with statement to raise
+        Err(e) if e == error => Ok(Obj::Bool(false).into()),
+        Err(e) => Err(e),
+    }
+}
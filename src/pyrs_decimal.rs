@@ -0,0 +1,960 @@
+use crate::{
+    pyrs_error::{PyError, PyException},
+    pyrs_format::FormatSpec,
+    pyrs_native::NativeModule,
+    pyrs_obj::Obj,
+    pyrs_parsing::Op,
+    pyrs_std::bind_native_args,
+    pyrs_utils as Utils,
+};
+use rug::{ops::Pow, Float, Integer, Rational};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    sync::{Arc, Mutex},
+};
+
+pub const MODULE_NAME: &str = "decimal";
+pub const TYPE_NAME: &str = "decimal.Decimal";
+pub const CONTEXT_TYPE_NAME: &str = "decimal.Context";
+
+// the methods of Decimal the VM implements
+pub const METHODS: [&str; 5] = ["quantize", "normalize", "sqrt", "to_integral_value", "as_integer_ratio"];
+
+// CPython's default exponent limits, only x / inf and the context's repr use them
+const EMIN: i64 = -999_999;
+const EMAX: i64 = 999_999;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rounding {
+    Ceiling,
+    Floor,
+    Up,
+    Down,
+    HalfUp,
+    HalfDown,
+    HalfEven,
+    ZeroFiveUp,
+}
+
+// the names are the values of the decimal.ROUND_* constants
+const ROUNDINGS: [(&str, Rounding); 8] = [
+    ("ROUND_CEILING", Rounding::Ceiling),
+    ("ROUND_FLOOR", Rounding::Floor),
+    ("ROUND_UP", Rounding::Up),
+    ("ROUND_DOWN", Rounding::Down),
+    ("ROUND_HALF_UP", Rounding::HalfUp),
+    ("ROUND_HALF_DOWN", Rounding::HalfDown),
+    ("ROUND_HALF_EVEN", Rounding::HalfEven),
+    ("ROUND_05UP", Rounding::ZeroFiveUp),
+];
+
+impl Rounding
+{
+    pub fn name(self) -> &'static str {
+        ROUNDINGS.iter().find(|(_, r)| *r == self).unwrap().0
+    }
+
+    pub fn from_name(name: &str) -> Option<Rounding> {
+        ROUNDINGS.iter().find(|(n, _)| *n == name).map(|(_, r)| *r)
+    }
+}
+
+// decimal.Context, the precision and rounding every Decimal operation uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecimalContext {
+    pub prec: u32,
+    pub rounding: Rounding,
+}
+
+impl Default for DecimalContext
+{
+    fn default() -> Self {
+        DecimalContext {
+            prec: 28,
+            rounding: Rounding::HalfEven,
+        }
+    }
+}
+
+impl std::fmt::Display for DecimalContext
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Context(prec={}, rounding={}, Emin={EMIN}, Emax={EMAX}, capitals=1, clamp=0, flags=[], \
+             traps=[InvalidOperation, DivisionByZero, Overflow])",
+            self.prec,
+            self.rounding.name()
+        )
+    }
+}
+
+thread_local! {
+    // like CPython's, every thread has its own current context
+    static CURRENT: RefCell<Arc<Mutex<DecimalContext>>> = RefCell::new(Arc::default());
+}
+
+// the current context, changes to it change how later operations round
+pub fn getcontext() -> Arc<Mutex<DecimalContext>> {
+    CURRENT.with(|ctx| ctx.borrow().clone())
+}
+
+// a copy of the current context for one operation
+pub fn context() -> DecimalContext {
+    getcontext().lock().unwrap().clone()
+}
+
+// The decimal signals, CPython names the condition in the message. They
+// are ArithmeticErrors, DivisionByZero is also a ZeroDivisionError
+fn signal(error: PyError, condition: &str) -> PyException {
+    PyException {
+        error,
+        msg: format!("[<class 'decimal.{condition}'>]"),
+    }
+}
+
+fn invalid(condition: &str) -> PyException {
+    signal(PyError::ArithmeticError, condition)
+}
+
+fn rounding_error() -> PyException {
+    PyException::type_error(
+        "valid values for rounding are:\n  [ROUND_CEILING, ROUND_FLOOR, ROUND_UP, ROUND_DOWN,\n   \
+         ROUND_HALF_UP, ROUND_HALF_DOWN, ROUND_HALF_EVEN,\n   ROUND_05UP]",
+    )
+}
+
+fn rounding_arg(obj: Option<&Obj>) -> Result<Option<Rounding>, PyException> {
+    match obj {
+        None | Some(Obj::None) => Ok(None),
+        Some(Obj::Str(name)) => Rounding::from_name(name).map(Some).ok_or_else(rounding_error),
+        Some(_) => Err(rounding_error()),
+    }
+}
+
+// decimal.Decimal, the functions for the current context and the names
+// of its signals and roundings
+pub fn module() -> NativeModule {
+    let mut module = NativeModule::new(MODULE_NAME)
+        .function("getcontext", |_, args, kwargs| match (args, kwargs) {
+            ([], []) => Ok(Obj::DecimalContext(getcontext()).into()),
+            _ => Err(PyException::type_error(format!("getcontext() takes no arguments ({} given)", args.len() + kwargs.len()))),
+        })
+        .function("setcontext", |_, args, kwargs| match (args, kwargs) {
+            ([ctx], []) => match ctx.as_ref() {
+                Obj::DecimalContext(ctx) => {
+                    CURRENT.with(|current| *current.borrow_mut() = ctx.clone());
+                    Ok(Obj::None.into())
+                }
+                _ => Err(PyException::type_error("argument must be a context")),
+            },
+            _ => Err(PyException::type_error(format!(
+                "setcontext() takes exactly one argument ({} given)",
+                args.len() + kwargs.len()
+            ))),
+        })
+        .constant("Decimal", Obj::BuiltinType(TYPE_NAME))
+        .constant("Context", Obj::BuiltinType(CONTEXT_TYPE_NAME))
+        .constant("DecimalException", Obj::ExceptType(PyError::ArithmeticError))
+        .constant("InvalidOperation", Obj::ExceptType(PyError::ArithmeticError))
+        .constant("DivisionByZero", Obj::ExceptType(PyError::ZeroDivisionError))
+        .constant("Overflow", Obj::ExceptType(PyError::OverflowError))
+        .constant("MAX_PREC", Obj::Int(u32::MAX.into()));
+    for (name, _) in ROUNDINGS {
+        module = module.constant(name, Obj::Str(name.to_string()));
+    }
+    module
+}
+
+// Context(prec=None, rounding=None), what isn't given is the default's
+pub fn new_context(args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Obj, PyException> {
+    let params = bind_native_args("Context", &["prec", "rounding"], 2, args, kwargs)?;
+    let mut ctx = DecimalContext::default();
+    for (name, val) in ["prec", "rounding"].iter().zip(params) {
+        match val.as_deref() {
+            None | Some(Obj::None) => {}
+            Some(val) => set_context_attr(&mut ctx, name, val)?,
+        }
+    }
+    Ok(Obj::DecimalContext(Arc::new(Mutex::new(ctx))))
+}
+
+pub fn context_attr(ctx: &DecimalContext, name: &str) -> Option<Obj> {
+    match name {
+        "prec" => Some(Obj::Int(ctx.prec.into())),
+        "rounding" => Some(Obj::Str(ctx.rounding.name().to_string())),
+        "Emin" => Some(Obj::Int(EMIN.into())),
+        "Emax" => Some(Obj::Int(EMAX.into())),
+        _ => None,
+    }
+}
+
+pub fn set_context_attr(ctx: &mut DecimalContext, name: &str, val: &Obj) -> Result<(), PyException> {
+    match (name, val) {
+        ("prec", Obj::Int(prec)) => {
+            ctx.prec = prec.to_u32().filter(|p| *p >= 1).ok_or(PyException {
+                error: PyError::ValueError,
+                msg: "valid range for prec is [1, MAX_PREC]".to_string(),
+            })?;
+        }
+        ("prec", o) => {
+            return Err(PyException::type_error(format!("'{}' object cannot be interpreted as an integer", o.type_name())))
+        }
+        ("rounding", val) => ctx.rounding = rounding_arg(Some(val))?.ok_or_else(rounding_error)?,
+        (name, _) => {
+            return Err(PyException {
+                error: PyError::AttributeError,
+                msg: format!("'decimal.Context' object has no attribute '{name}'"),
+            })
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Special {
+    Infinity,
+    NaN,
+}
+
+// (-1)**negative * coef * 10**exp, kept as it was written so 1.10 stays
+// 1.10 and 1.10 * 2 is 2.20. Infinity and NaN only have their sign
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decimal {
+    negative: bool,
+    coef: Integer,
+    exp: i64,
+    special: Option<Special>,
+}
+
+fn pow10(n: u32) -> Integer {
+    Integer::from(10).pow(n)
+}
+
+// digits in a coefficient, 0 has one
+fn digit_count(coef: &Integer) -> u32 {
+    match coef.is_zero() {
+        true => 1,
+        false => coef.to_string_radix(10).len() as u32,
+    }
+}
+
+// coef / 10**places rounded the way rounding says, negative is the sign
+// of the number coef belongs to
+fn round_coef(coef: &Integer, places: u32, negative: bool, rounding: Rounding) -> Integer {
+    // past its digits every coefficient rounds the same, as less than a half
+    let places = places.min(digit_count(coef) + 1);
+    let scale = pow10(places);
+    let (quot, rem) = coef.clone().div_rem(scale.clone());
+    if rem == 0 {
+        return quot;
+    }
+    let half = Integer::from(&rem * 2u32).cmp(&scale);
+    let up = match rounding {
+        Rounding::Down => false,
+        Rounding::Up => true,
+        Rounding::Ceiling => !negative,
+        Rounding::Floor => negative,
+        Rounding::HalfUp => half != Ordering::Less,
+        Rounding::HalfDown => half == Ordering::Greater,
+        Rounding::HalfEven => half == Ordering::Greater || half == Ordering::Equal && quot.is_odd(),
+        Rounding::ZeroFiveUp => matches!(quot.mod_u(10), 0 | 5),
+    };
+    match up {
+        true => quot + 1u32,
+        false => quot,
+    }
+}
+
+impl Decimal
+{
+    fn finite(negative: bool, coef: Integer, exp: i64) -> Decimal {
+        Decimal {
+            negative,
+            coef,
+            exp,
+            special: None,
+        }
+    }
+
+    fn special(negative: bool, special: Special) -> Decimal {
+        Decimal {
+            negative,
+            coef: Integer::ZERO,
+            exp: 0,
+            special: Some(special),
+        }
+    }
+
+    pub fn from_integer(int: &Integer) -> Decimal {
+        Decimal::finite(*int < 0, int.clone().abs(), 0)
+    }
+
+    // the exact value of the float, Decimal(0.1) has 55 digits
+    pub fn from_f64(val: f64) -> Decimal {
+        let Some(ratio) = Rational::from_f64(val) else {
+            return match val.is_nan() {
+                true => Decimal::special(false, Special::NaN),
+                false => Decimal::special(val < 0.0, Special::Infinity),
+            };
+        };
+        // the denominator is 2**k, and n / 2**k is n * 5**k / 10**k
+        let (numer, denom) = ratio.into_numer_denom();
+        let k = denom.significant_bits() - 1;
+        Decimal::finite(val.is_sign_negative(), numer.abs() * Integer::from(5).pow(k), -(k as i64))
+    }
+
+    // [sign]digits[.digits][e[sign]digits], Infinity, Inf or NaN, with
+    // whitespace around it and underscores between digits
+    pub fn parse(s: &str) -> Result<Decimal, PyException> {
+        let syntax_err = || invalid("ConversionSyntax");
+        let trimmed = s.trim();
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        match unsigned.to_ascii_lowercase().as_str() {
+            "inf" | "infinity" => return Ok(Decimal::special(negative, Special::Infinity)),
+            "nan" => return Ok(Decimal::special(negative, Special::NaN)),
+            _ => {}
+        }
+        let (mantissa, exp) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exp)) => (mantissa, Some(exp)),
+            None => (unsigned, None),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let int_part = Utils::strip_underscores(int_part, 10).ok_or_else(syntax_err)?;
+        let frac_part = Utils::strip_underscores(frac_part, 10).ok_or_else(syntax_err)?;
+        let digits = format!("{int_part}{frac_part}");
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(syntax_err());
+        }
+        let exp = match exp {
+            None => 0,
+            Some(exp) => {
+                let exp = Utils::strip_underscores(exp, 10).ok_or_else(syntax_err)?;
+                let digits = exp.strip_prefix(['+', '-']).unwrap_or(&exp);
+                if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(syntax_err());
+                }
+                exp.parse::<i64>().map_err(|_| syntax_err())?
+            }
+        };
+        let coef = Integer::from_str_radix(&digits, 10).map_err(|_| syntax_err())?;
+        Ok(Decimal::finite(negative, coef, exp - frac_part.len() as i64))
+    }
+
+    pub fn is_nan(&self) -> bool {
+        self.special == Some(Special::NaN)
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        self.special == Some(Special::Infinity)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.special.is_none() && self.coef.is_zero()
+    }
+
+    fn signed_coef(&self) -> Integer {
+        match self.negative {
+            true => -self.coef.clone(),
+            false => self.coef.clone(),
+        }
+    }
+
+    // the exact value, None for Infinity and NaN
+    pub fn to_rational(&self) -> Option<Rational> {
+        if self.special.is_some() {
+            return None;
+        }
+        let scale = pow10(self.exp.unsigned_abs() as u32);
+        Some(match self.exp >= 0 {
+            true => Rational::from(self.signed_coef() * scale),
+            false => Rational::from((self.signed_coef(), scale)),
+        })
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let sign = if self.negative { -1.0 } else { 1.0 };
+        match self.special {
+            Some(Special::NaN) => f64::NAN,
+            Some(Special::Infinity) => sign * f64::INFINITY,
+            // rust's parse rounds correctly
+            None => format!("{}e{}", self.signed_coef(), self.exp).parse().unwrap(),
+        }
+    }
+
+    // int(d), rounded toward zero
+    pub fn to_integer(&self) -> Result<Integer, PyException> {
+        match self.special {
+            Some(Special::NaN) => Err(PyException {
+                error: PyError::ValueError,
+                msg: "cannot convert NaN to integer".to_string(),
+            }),
+            Some(Special::Infinity) => Err(PyException {
+                error: PyError::OverflowError,
+                msg: "cannot convert infinity to integer".to_string(),
+            }),
+            None => Ok(self.to_integral(Rounding::Down).signed_coef()),
+        }
+    }
+
+    // round(d), halves to the even integer
+    pub fn round_to_int(&self) -> Result<Integer, PyException> {
+        match self.special {
+            Some(Special::NaN) => Err(PyException {
+                error: PyError::ValueError,
+                msg: "cannot round a NaN".to_string(),
+            }),
+            Some(Special::Infinity) => Err(PyException {
+                error: PyError::OverflowError,
+                msg: "cannot round an infinity".to_string(),
+            }),
+            None => Ok(self.to_integral(Rounding::HalfEven).signed_coef()),
+        }
+    }
+
+    // round(d, ndigits), the same as quantizing to 1E-ndigits
+    pub fn round_to(&self, ndigits: i64) -> Result<Decimal, PyException> {
+        self.quantize(&Decimal::finite(false, Integer::from(1), -ndigits), None, &context())
+    }
+
+    // rounded to the context's precision
+    fn fix(mut self, ctx: &DecimalContext) -> Decimal {
+        if self.special.is_some() {
+            return self;
+        }
+        let digits = digit_count(&self.coef);
+        if digits > ctx.prec {
+            let drop = digits - ctx.prec;
+            self.coef = round_coef(&self.coef, drop, self.negative, ctx.rounding);
+            self.exp += drop as i64;
+            // 999 rounding up to 1000 has one digit too many, and it's a 0
+            if digit_count(&self.coef) > ctx.prec {
+                self.coef /= 10u32;
+                self.exp += 1;
+            }
+        }
+        self
+    }
+
+    // the first NaN operand, any operation with a NaN gives a NaN
+    fn nan_operand(&self, other: &Decimal) -> Option<Decimal> {
+        [self, other].into_iter().find(|d| d.is_nan()).cloned()
+    }
+
+    pub fn neg(&self, ctx: &DecimalContext) -> Decimal {
+        let mut val = self.clone();
+        if !self.is_nan() {
+            // -0 is 0 unless rounding toward -inf, like 0 - 0
+            val.negative = match self.is_zero() {
+                true => ctx.rounding == Rounding::Floor,
+                false => !self.negative,
+            };
+        }
+        val.fix(ctx)
+    }
+
+    pub fn abs(&self, ctx: &DecimalContext) -> Decimal {
+        let mut val = self.clone();
+        if !self.is_nan() {
+            val.negative = false;
+        }
+        val.fix(ctx)
+    }
+
+    fn add(&self, other: &Decimal, ctx: &DecimalContext) -> Result<Decimal, PyException> {
+        if let Some(nan) = self.nan_operand(other) {
+            return Ok(nan);
+        }
+        match (self.is_infinite(), other.is_infinite()) {
+            (true, true) if self.negative != other.negative => return Err(invalid("InvalidOperation")),
+            (true, _) => return Ok(self.clone()),
+            (_, true) => return Ok(other.clone()),
+            _ => {}
+        }
+        let exp = self.exp.min(other.exp);
+        let aligned = |d: &Decimal| d.signed_coef() * pow10((d.exp - exp) as u32);
+        let sum = aligned(self) + aligned(other);
+        let negative = match sum.cmp0() {
+            Ordering::Less => true,
+            Ordering::Greater => false,
+            // an exact 0 is negative if both were, or when rounding toward -inf
+            Ordering::Equal => match self.negative == other.negative {
+                true => self.negative,
+                false => ctx.rounding == Rounding::Floor,
+            },
+        };
+        Ok(Decimal::finite(negative, sum.abs(), exp).fix(ctx))
+    }
+
+    fn sub(&self, other: &Decimal, ctx: &DecimalContext) -> Result<Decimal, PyException> {
+        let mut negated = other.clone();
+        if !other.is_nan() {
+            negated.negative = !other.negative;
+        }
+        self.add(&negated, ctx)
+    }
+
+    fn mul(&self, other: &Decimal, ctx: &DecimalContext) -> Result<Decimal, PyException> {
+        if let Some(nan) = self.nan_operand(other) {
+            return Ok(nan);
+        }
+        let negative = self.negative != other.negative;
+        if self.is_infinite() || other.is_infinite() {
+            return match self.is_zero() || other.is_zero() {
+                true => Err(invalid("InvalidOperation")),
+                false => Ok(Decimal::special(negative, Special::Infinity)),
+            };
+        }
+        Ok(Decimal::finite(negative, Integer::from(&self.coef * &other.coef), self.exp + other.exp).fix(ctx))
+    }
+
+    // The quotient to prec digits. An exact one keeps the exponent the
+    // operands suggest when it can, so 1.00 / 2 is 0.50 and 6 / 2 is 3
+    fn div(&self, other: &Decimal, ctx: &DecimalContext) -> Result<Decimal, PyException> {
+        if let Some(nan) = self.nan_operand(other) {
+            return Ok(nan);
+        }
+        let negative = self.negative != other.negative;
+        match (self.is_infinite(), other.is_infinite()) {
+            (true, true) => return Err(invalid("InvalidOperation")),
+            (true, false) => return Ok(Decimal::special(negative, Special::Infinity)),
+            (false, true) => return Ok(Decimal::finite(negative, Integer::ZERO, EMIN - ctx.prec as i64 + 1)),
+            _ => {}
+        }
+        if other.is_zero() {
+            return Err(match self.is_zero() {
+                true => invalid("DivisionUndefined"),
+                false => signal(PyError::ZeroDivisionError, "DivisionByZero"),
+            });
+        }
+        let ideal = self.exp - other.exp;
+        if self.is_zero() {
+            return Ok(Decimal::finite(negative, Integer::ZERO, ideal));
+        }
+        // shifted so the quotient has at least prec + 1 digits
+        let shift = (ctx.prec + digit_count(&other.coef) + 1).saturating_sub(digit_count(&self.coef));
+        let (mut quot, rem) = (self.coef.clone() * pow10(shift)).div_rem(other.coef.clone());
+        let mut exp = ideal - shift as i64;
+        if rem == 0 {
+            while exp < ideal && quot.mod_u(10) == 0 {
+                quot /= 10u32;
+                exp += 1;
+            }
+        }
+        else if quot.mod_u(5) == 0 {
+            // there's more past the last digit, so a 5 there isn't a half
+            quot += 1u32;
+        }
+        Ok(Decimal::finite(negative, quot, exp).fix(ctx))
+    }
+
+    // // and %, the quotient rounds toward zero and the remainder has the
+    // sign of self, so Decimal(-7) // 2 is -3 and Decimal(-7) % 2 is -1
+    fn divide_int(&self, other: &Decimal, op: Op, ctx: &DecimalContext) -> Result<(Decimal, Decimal), PyException> {
+        if let Some(nan) = self.nan_operand(other) {
+            return Ok((nan.clone(), nan));
+        }
+        let negative = self.negative != other.negative;
+        match (self.is_infinite(), other.is_infinite()) {
+            (true, _) => return Err(invalid("InvalidOperation")),
+            (false, true) => return Ok((Decimal::finite(negative, Integer::ZERO, 0), self.clone().fix(ctx))),
+            _ => {}
+        }
+        if other.is_zero() {
+            return Err(match (self.is_zero(), op) {
+                (true, _) => invalid("DivisionUndefined"),
+                (false, Op::DoubleSlash) => signal(PyError::ZeroDivisionError, "DivisionByZero"),
+                (false, _) => invalid("InvalidOperation"),
+            });
+        }
+        let exp = self.exp.min(other.exp);
+        let aligned = |d: &Decimal| d.coef.clone() * pow10((d.exp - exp) as u32);
+        let (quot, rem) = aligned(self).div_rem(aligned(other));
+        if digit_count(&quot) > ctx.prec {
+            return Err(invalid("DivisionImpossible"));
+        }
+        Ok((
+            Decimal::finite(negative, quot, 0),
+            Decimal::finite(self.negative, rem, exp).fix(ctx),
+        ))
+    }
+
+    // Integral exponents are exact before the one rounding, others go
+    // through mpfr with guard digits past the precision
+    fn pow(&self, other: &Decimal, ctx: &DecimalContext) -> Result<Decimal, PyException> {
+        if let Some(nan) = self.nan_operand(other) {
+            return Ok(nan);
+        }
+        let exp_int = other.to_rational().filter(|r| *r.denom() == 1).map(|r| r.into_numer_denom().0);
+        let Some(n) = exp_int else {
+            return self.pow_inexact(other, ctx);
+        };
+        let negative = self.negative && n.is_odd();
+        if self.is_infinite() || self.is_zero() {
+            // inf ** n and 0 ** -n are inf, inf ** -n and 0 ** n are 0
+            return match (n.cmp0(), self.is_infinite()) {
+                (Ordering::Equal, false) => Err(invalid("InvalidOperation")),
+                (Ordering::Equal, true) => Ok(Decimal::finite(false, Integer::from(1), 0)),
+                (Ordering::Greater, true) | (Ordering::Less, false) => Ok(Decimal::special(negative, Special::Infinity)),
+                _ => Ok(Decimal::finite(negative, Integer::ZERO, 0)),
+            };
+        }
+        let abs_n = n.clone().abs().to_u32().filter(|n| {
+            self.coef == 1 || u64::from(digit_count(&self.coef)) * u64::from(*n) < 10_000_000
+        });
+        let Some(abs_n) = abs_n else {
+            return Err(signal(PyError::OverflowError, "Overflow"));
+        };
+        let power = Decimal::finite(negative, self.coef.clone().pow(abs_n), self.exp * abs_n as i64);
+        match n < 0 {
+            true => Decimal::finite(false, Integer::from(1), 0).div(&power, ctx),
+            false => Ok(power.fix(ctx)),
+        }
+    }
+
+    fn pow_inexact(&self, other: &Decimal, ctx: &DecimalContext) -> Result<Decimal, PyException> {
+        if self.negative && !self.is_zero() {
+            return Err(invalid("InvalidOperation"));
+        }
+        let (Some(base), Some(exp)) = (self.to_rational(), other.to_rational()) else {
+            // inf ** x and x ** inf
+            let grows = match self.is_infinite() {
+                true => !other.negative,
+                false => (self.to_f64() > 1.0) != other.negative,
+            };
+            return Ok(match grows {
+                true => Decimal::special(false, Special::Infinity),
+                false => Decimal::finite(false, Integer::ZERO, 0),
+            });
+        };
+        if self.is_zero() {
+            return match other.negative {
+                true => Ok(Decimal::special(false, Special::Infinity)),
+                false => Ok(Decimal::finite(false, Integer::ZERO, 0)),
+            };
+        }
+        let guard = ctx.prec + 10;
+        let bits = guard * 4 + 64;
+        let val = Float::with_val(bits, &base).pow(Float::with_val(bits, &exp));
+        let digits = val.to_string_radix(10, Some(guard as usize));
+        Ok(Decimal::parse(&digits)?.fix(ctx))
+    }
+
+    // the square root, correctly rounded half even whatever the context says
+    fn sqrt(&self, ctx: &DecimalContext) -> Result<Decimal, PyException> {
+        if self.is_nan() || self.is_zero() || self.is_infinite() && !self.negative {
+            return Ok(self.clone());
+        }
+        if self.negative {
+            return Err(invalid("InvalidOperation"));
+        }
+        let ideal = self.exp.div_euclid(2);
+        // an even exponent halves, and enough digits for prec + 1 in the root
+        let (mut coef, mut exp) = (self.coef.clone(), self.exp);
+        if exp.rem_euclid(2) == 1 {
+            coef *= 10u32;
+            exp -= 1;
+        }
+        let shift = (2 * (ctx.prec + 1)).saturating_sub(digit_count(&coef)).div_ceil(2);
+        let (mut root, rem) = (coef * pow10(2 * shift)).sqrt_rem(Integer::new());
+        let mut exp = exp / 2 - shift as i64;
+        if rem == 0 {
+            while exp < ideal && root.mod_u(10) == 0 {
+                root /= 10u32;
+                exp += 1;
+            }
+        }
+        else if root.mod_u(5) == 0 {
+            root += 1u32;
+        }
+        let ctx = DecimalContext {
+            rounding: Rounding::HalfEven,
+            ..ctx.clone()
+        };
+        Ok(Decimal::finite(false, root, exp).fix(&ctx))
+    }
+
+    // self with the exponent of other, the digits past it rounded off
+    fn quantize(&self, other: &Decimal, rounding: Option<Rounding>, ctx: &DecimalContext) -> Result<Decimal, PyException> {
+        if let Some(nan) = self.nan_operand(other) {
+            return Ok(nan);
+        }
+        if self.is_infinite() || other.is_infinite() {
+            return match self.is_infinite() && other.is_infinite() {
+                true => Ok(self.clone()),
+                false => Err(invalid("InvalidOperation")),
+            };
+        }
+        let target = other.exp;
+        let coef = match self.exp > target {
+            true if self.exp - target > i64::from(ctx.prec) && !self.coef.is_zero() => {
+                return Err(invalid("InvalidOperation"))
+            }
+            true => self.coef.clone() * pow10((self.exp - target) as u32),
+            false => round_coef(&self.coef, (target - self.exp).min(u32::MAX as i64) as u32, self.negative, rounding.unwrap_or(ctx.rounding)),
+        };
+        if digit_count(&coef) > ctx.prec {
+            return Err(invalid("InvalidOperation"));
+        }
+        Ok(Decimal::finite(self.negative, coef, target))
+    }
+
+    // the digits past the point rounded off
+    fn to_integral(&self, rounding: Rounding) -> Decimal {
+        if self.special.is_some() || self.exp >= 0 {
+            return self.clone();
+        }
+        let places = (-self.exp).min(u32::MAX as i64) as u32;
+        Decimal::finite(self.negative, round_coef(&self.coef, places, self.negative, rounding), 0)
+    }
+
+    // self with the exponent exp, zeros added or digits rounded off, with
+    // no limit from the context's precision
+    fn rescale(&self, exp: i64, rounding: Rounding) -> Decimal {
+        let coef = match self.exp >= exp {
+            true => self.coef.clone() * pow10((self.exp - exp).min(u32::MAX as i64) as u32),
+            false => round_coef(&self.coef, (exp - self.exp).min(u32::MAX as i64) as u32, self.negative, rounding),
+        };
+        Decimal::finite(self.negative, coef, exp)
+    }
+
+    // self with places significant digits, a zero keeps its exponent
+    fn round_digits(&self, places: i64, rounding: Rounding) -> Decimal {
+        if self.coef.is_zero() {
+            return self.clone();
+        }
+        let adjusted = self.exp + digit_count(&self.coef) as i64 - 1;
+        let val = self.rescale(adjusted + 1 - places, rounding);
+        // 9.99 rounding up to 10.0 has a digit too many, and it's a 0
+        let rounded_adjusted = val.exp + digit_count(&val.coef) as i64 - 1;
+        match rounded_adjusted == adjusted {
+            true => val,
+            false => val.rescale(rounded_adjusted + 1 - places, rounding),
+        }
+    }
+
+    // format(d, spec), rounded the way the context rounds. With no type a
+    // precision counts significant digits like 'G' does, Infinity and NaN
+    // ignore the type and precision
+    pub fn format(&self, spec: &FormatSpec) -> Result<String, PyException> {
+        let invalid_spec = || PyException {
+            error: PyError::ValueError,
+            msg: "invalid format string".to_string(),
+        };
+        if spec.alternate || spec.grouping == Some('_') {
+            return Err(invalid_spec());
+        }
+        let kind = match spec.kind {
+            None => 'G',
+            Some('n') => 'g',
+            Some(kind @ ('e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%')) => kind,
+            Some(_) => return Err(invalid_spec()),
+        };
+        if self.special.is_some() {
+            let body = Decimal { negative: false, ..self.clone() }.to_string();
+            let body = if kind == '%' { body + "%" } else { body };
+            // zero padding is only for digits
+            let spec = FormatSpec { zero_pad: false, ..spec.clone() };
+            return Ok(spec.pad_number(self.negative, "", "", &body, 3));
+        }
+
+        let rounding = context().rounding;
+        let mut val = match kind {
+            '%' => Decimal::finite(self.negative, self.coef.clone(), self.exp + 2),
+            _ => self.clone(),
+        };
+        let precision = spec.precision.map(|p| match (p, kind) {
+            (0, 'g' | 'G') => 1,
+            (p, _) => p as i64,
+        });
+        if let Some(precision) = precision {
+            val = match kind {
+                'e' | 'E' => val.round_digits(precision + 1, rounding),
+                'f' | 'F' | '%' => val.rescale(-precision, rounding),
+                _ if i64::from(digit_count(&val.coef)) > precision => val.round_digits(precision, rounding),
+                _ => val,
+            };
+        }
+        // a zero with a positive exponent has no fixed point form
+        if val.coef.is_zero() && val.exp > 0 && matches!(kind, 'f' | 'F' | '%') {
+            val = val.rescale(0, rounding);
+        }
+        let negative = val.negative && !(val.coef.is_zero() && spec.no_neg_zero);
+
+        // where the point goes among the digits, the rest is the exponent
+        let digits = val.coef.to_string_radix(10);
+        let len = digits.len() as i64;
+        let left_digits = val.exp + len;
+        let dot = match kind {
+            'e' | 'E' if val.coef.is_zero() && precision.is_some() => 1 - precision.unwrap(),
+            'e' | 'E' => 1,
+            'f' | 'F' | '%' => left_digits,
+            _ if val.exp <= 0 && left_digits > -6 => left_digits,
+            _ => 1,
+        };
+        let (int_part, frac_part) = match dot {
+            _ if dot < 0 => ("0".to_string(), format!("{}{digits}", "0".repeat(-dot as usize))),
+            _ if dot > len => (format!("{digits}{}", "0".repeat((dot - len) as usize)), String::new()),
+            _ => match digits.split_at(dot as usize) {
+                ("", frac) => ("0".to_string(), frac.to_string()),
+                (int, frac) => (int.to_string(), frac.to_string()),
+            },
+        };
+        let exp = left_digits - dot;
+
+        let mut rest = match frac_part.is_empty() {
+            true => String::new(),
+            false => format!(".{frac_part}"),
+        };
+        if exp != 0 || matches!(kind, 'e' | 'E') {
+            let e = if matches!(kind, 'E' | 'G') { 'E' } else { 'e' };
+            rest.push_str(&format!("{e}{exp:+}"));
+        }
+        if kind == '%' {
+            rest.push('%');
+        }
+        Ok(spec.pad_number(negative, "", &int_part, &rest, 3))
+    }
+
+    // the trailing zeros stripped, 1.500 is 1.5 and 100 is 1E+2
+    fn normalize(&self, ctx: &DecimalContext) -> Decimal {
+        let mut val = self.clone().fix(ctx);
+        if val.special.is_some() {
+            return val;
+        }
+        if val.coef.is_zero() {
+            val.exp = 0;
+            return val;
+        }
+        while val.coef.mod_u(10) == 0 {
+            val.coef /= 10u32;
+            val.exp += 1;
+        }
+        val
+    }
+}
+
+// to-scientific-string, plain digits unless the exponent is positive or
+// the number is small enough to need more than 6 leading zeros
+impl std::fmt::Display for Decimal
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.negative { "-" } else { "" };
+        match self.special {
+            Some(Special::Infinity) => return write!(f, "{sign}Infinity"),
+            Some(Special::NaN) => return write!(f, "{sign}NaN"),
+            None => {}
+        }
+        let digits = self.coef.to_string_radix(10);
+        let len = digits.len() as i64;
+        let adjusted = self.exp + len - 1;
+        if self.exp <= 0 && adjusted >= -6 {
+            let point = len + self.exp;
+            return match point > 0 {
+                true if self.exp == 0 => write!(f, "{sign}{digits}"),
+                true => write!(f, "{sign}{}.{}", &digits[..point as usize], &digits[point as usize..]),
+                false => write!(f, "{sign}0.{}{digits}", "0".repeat(-point as usize)),
+            };
+        }
+        let mantissa = match digits.len() > 1 {
+            true => format!("{}.{}", &digits[..1], &digits[1..]),
+            false => digits,
+        };
+        write!(f, "{sign}{mantissa}E{adjusted:+}")
+    }
+}
+
+// the operands a Decimal does arithmetic with, floats and Fractions aren't
+// converted implicitly
+fn operand(obj: &Obj) -> Option<Decimal> {
+    match obj {
+        Obj::Decimal(d) => Some(d.as_ref().clone()),
+        Obj::Int(_) | Obj::Bool(_) => obj.to_integer().map(|i| Decimal::from_integer(&i)),
+        _ => None,
+    }
+}
+
+// Decimal(value="0", context=None)
+pub fn new(args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Obj, PyException> {
+    let params = bind_native_args("Decimal", &["value", "context"], 2, args, kwargs)?;
+    let val = match params[0].as_deref() {
+        None => Decimal::finite(false, Integer::ZERO, 0),
+        Some(Obj::Str(s)) => Decimal::parse(s)?,
+        Some(Obj::Float(f)) => Decimal::from_f64(*f),
+        Some(o) => operand(o).ok_or_else(|| PyException::type_error(format!("conversion from {} to Decimal is not supported", o.type_name())))?,
+    };
+    Ok(Obj::Decimal(Box::new(val)))
+}
+
+// arithmetic with a Decimal on one side and a Decimal or an int on the other
+pub fn binary_op(lhs: &Obj, rhs: &Obj, op: Op) -> Result<Obj, PyException> {
+    let symbol = match op {
+        Op::Power => "** or pow()".to_string(),
+        op => op.to_string(),
+    };
+    let (Some(a), Some(b)) = (operand(lhs), operand(rhs)) else {
+        return Err(Obj::unsupported(lhs, rhs, &symbol));
+    };
+    let ctx = context();
+    let val = match op {
+        Op::Plus => a.add(&b, &ctx)?,
+        Op::Minus => a.sub(&b, &ctx)?,
+        Op::Asterisk => a.mul(&b, &ctx)?,
+        Op::ForwardSlash => a.div(&b, &ctx)?,
+        Op::DoubleSlash => a.divide_int(&b, op, &ctx)?.0,
+        Op::Percent => a.divide_int(&b, op, &ctx)?.1,
+        Op::Power => a.pow(&b, &ctx)?,
+        _ => return Err(Obj::unsupported(lhs, rhs, &symbol)),
+    };
+    Ok(Obj::Decimal(Box::new(val)))
+}
+
+pub fn call_method(
+    val: &Decimal,
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+) -> Result<Obj, PyException> {
+    let ctx = context();
+    let no_args = |ret: Decimal| match args.is_empty() && kwargs.is_empty() {
+        true => Ok(Obj::Decimal(Box::new(ret))),
+        false => Err(PyException::type_error(format!("{name}() takes no arguments ({} given)", args.len() + kwargs.len()))),
+    };
+    match name {
+        "quantize" => {
+            let params = bind_native_args("quantize", &["exp", "rounding", "context"], 3, args, kwargs)?;
+            let exp = params[0].as_deref().and_then(operand).ok_or(PyException::type_error("quantize() argument 'exp' must be a Decimal or an int"))?;
+            let rounding = rounding_arg(params[1].as_deref())?;
+            Ok(Obj::Decimal(Box::new(val.quantize(&exp, rounding, &ctx)?)))
+        }
+        "to_integral_value" => {
+            let params = bind_native_args("to_integral_value", &["rounding", "context"], 2, args, kwargs)?;
+            let rounding = rounding_arg(params[0].as_deref())?.unwrap_or(ctx.rounding);
+            Ok(Obj::Decimal(Box::new(val.to_integral(rounding))))
+        }
+        "normalize" => no_args(val.normalize(&ctx)),
+        "sqrt" => no_args(val.sqrt(&ctx)?),
+        "as_integer_ratio" => match (val.to_rational(), val.is_nan()) {
+            (Some(ratio), _) => {
+                let (numer, denom) = ratio.into_numer_denom();
+                Ok(Obj::Tuple(vec![Obj::Int(numer).into(), Obj::Int(denom).into()]))
+            }
+            (None, true) => Err(PyException {
+                error: PyError::ValueError,
+                msg: "cannot convert NaN to integer ratio".to_string(),
+            }),
+            (None, false) => Err(PyException {
+                error: PyError::OverflowError,
+                msg: "cannot convert Infinity to integer ratio".to_string(),
+            }),
+        },
+        _ => Err(PyException {
+            error: PyError::AttributeError,
+            msg: format!("'decimal.Decimal' object has no attribute '{name}'"),
+        }),
+    }
+}
@@ -0,0 +1,273 @@
+//! Running python from a rust program. A PyRuntime holds one interpreter
+//! whose globals last across calls, errors come back as PyExceptions:
+//!
+//! ```rust
+//! use pyrs::{FromObj, PyException, PyRuntime, ToObj};
+//!
+//! let mut py = PyRuntime::new();
+//! py.capture_output();
+//! py.set_global("n", 3.to_arc());
+//! py.exec("def double(x):\n    return x * 2\nprint(double(n))", "script.py")?;
+//! let ten = i64::from_obj(&*py.call("double", &[5.to_arc()])?)?;
+//! let n: i64 = py.get_global("n")?;
+//! assert_eq!((ten, n), (10, 3));
+//! assert_eq!(py.take_stdout(), "6\n");
+//! # Ok::<(), PyException>(())
+//! ```
+
+use crate::{
+    pyrs_bytecode::PyBytecode,
+    pyrs_error::{PyError, PyException},
+    pyrs_interpreter::Interpreter,
+    pyrs_native::{FromObj, NativeModule},
+    pyrs_obj::Obj,
+    pyrs_parsing::{Expression, Keyword, Op},
+    pyrs_vm::PyVM,
+};
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+// Where the VM's output goes, a buffer is shared with whoever reads it
+#[derive(Debug, Clone)]
+pub enum OutputSink {
+    Stdout,
+    Stderr,
+    Buffer(Arc<Mutex<String>>),
+}
+
+impl OutputSink
+{
+    pub fn buffer() -> Self {
+        OutputSink::Buffer(Arc::new(Mutex::new(String::new())))
+    }
+
+    pub fn write(&self, text: &str) {
+        // a closed stream isn't the script's problem, like print! without the panic
+        match self {
+            OutputSink::Stdout => {
+                let _ = io::stdout().lock().write_all(text.as_bytes());
+            }
+            OutputSink::Stderr => {
+                let _ = io::stderr().lock().write_all(text.as_bytes());
+            }
+            OutputSink::Buffer(buf) => buf.lock().unwrap().push_str(text),
+        }
+    }
+
+    pub fn flush(&self) {
+        match self {
+            OutputSink::Stdout => {
+                let _ = io::stdout().flush();
+            }
+            OutputSink::Stderr => {
+                let _ = io::stderr().flush();
+            }
+            OutputSink::Buffer(_) => {}
+        }
+    }
+
+    // what was written since the last take, nothing for the process's streams
+    pub fn take(&self) -> String {
+        match self {
+            OutputSink::Buffer(buf) => std::mem::take(&mut *buf.lock().unwrap()),
+            _ => String::new(),
+        }
+    }
+}
+
+// Where input reads from, a buffer is used up from the front as it's read
+#[derive(Debug, Clone)]
+pub enum InputSource {
+    Stdin,
+    Buffer(Arc<Mutex<String>>),
+}
+
+impl InputSource
+{
+    pub fn buffer(text: &str) -> Self {
+        InputSource::Buffer(Arc::new(Mutex::new(text.to_string())))
+    }
+
+    // a line with its newline, or '' at the end of the input
+    pub fn read_line(&self) -> Result<String, PyException> {
+        match self {
+            InputSource::Stdin => {
+                let mut line = String::new();
+                io::stdin().read_line(&mut line).map_err(|e| PyException::os_error(e, None))?;
+                Ok(line)
+            }
+            InputSource::Buffer(buf) => {
+                let mut buf = buf.lock().unwrap();
+                let len = buf.find('\n').map_or(buf.len(), |i| i + 1);
+                Ok(buf.drain(..len).collect())
+            }
+        }
+    }
+
+    pub fn read_to_end(&self) -> Result<String, PyException> {
+        match self {
+            InputSource::Stdin => {
+                let mut text = String::new();
+                io::Read::read_to_string(&mut io::stdin(), &mut text).map_err(|e| PyException::os_error(e, None))?;
+                Ok(text)
+            }
+            InputSource::Buffer(buf) => Ok(std::mem::take(&mut *buf.lock().unwrap())),
+        }
+    }
+}
+
+// a syntax error says which file it's in
+pub(crate) fn in_file(e: PyException, filename: &str) -> PyException {
+    match e.error {
+        PyError::SyntaxError => PyException {
+            error: e.error,
+            msg: format!("{} ({filename})", e.msg),
+        },
+        _ => e,
+    }
+}
+
+// the code for one expression, it leaves the value on the stack
+pub(crate) fn compile_expr(source: &str, filename: &str) -> Result<Vec<PyBytecode>, PyException> {
+    let expr = Expression::from_line(source.trim()).map_err(|e| in_file(e, filename))?;
+    if is_statement(&expr) {
+        return Err(in_file(PyException::syntax_error("invalid syntax"), filename));
+    }
+    let mut code = vec![];
+    PyBytecode::from_expr(expr, &mut code).map_err(|e| in_file(e, filename))?;
+    Ok(code)
+}
+
+// the lines eval can't take, anything that doesn't leave a value
+fn is_statement(expr: &Expression) -> bool {
+    match expr {
+        Expression::Operation(op, _) => matches!(
+            op,
+            Op::Equals
+                | Op::AddEquals
+                | Op::SubEquals
+                | Op::MulEquals
+                | Op::DivEquals
+                | Op::FloorDivEquals
+                | Op::ModEquals
+                | Op::PowEquals
+        ),
+        Expression::Keyword(keyword, _, _) => !matches!(keyword, Keyword::True | Keyword::False | Keyword::None | Keyword::Lambda),
+        _ => false,
+    }
+}
+
+// An interpreter for a host program, the script's globals are its __main__
+#[derive(Debug, Clone)]
+pub struct PyRuntime {
+    vm: PyVM,
+    stdout: OutputSink,
+    stderr: OutputSink,
+}
+
+impl Default for PyRuntime {
+    fn default() -> Self {
+        PyRuntime::new()
+    }
+}
+
+impl PyRuntime
+{
+    pub fn new() -> Self {
+        PyRuntime {
+            vm: PyVM::new(),
+            stdout: OutputSink::Stdout,
+            stderr: OutputSink::Stderr,
+        }
+    }
+
+    // the VM underneath, for what the runtime doesn't wrap
+    pub fn vm(&mut self) -> &mut PyVM {
+        &mut self.vm
+    }
+
+    // sys.stdout and sys.stderr write to buffers read back with take_stdout
+    // and take_stderr
+    pub fn capture_output(&mut self) {
+        self.stdout = OutputSink::buffer();
+        self.stderr = OutputSink::buffer();
+        self.vm.set_stdout(self.stdout.clone());
+        self.vm.set_stderr(self.stderr.clone());
+    }
+
+    pub fn take_stdout(&mut self) -> String {
+        self.stdout.take()
+    }
+
+    pub fn take_stderr(&mut self) -> String {
+        self.stderr.take()
+    }
+
+    // what sys.stdin and so input() read, instead of the process's stdin
+    pub fn set_stdin(&mut self, text: &str) {
+        self.vm.set_stdin(InputSource::buffer(text));
+    }
+
+    pub fn register_module(&mut self, module: NativeModule) {
+        self.vm.register_module(module);
+    }
+
+    pub fn set_global(&mut self, name: &str, value: impl Into<Arc<Obj>>) {
+        self.vm.get_global_vars_mut().insert(name.to_string(), value.into());
+    }
+
+    pub fn global(&self, name: &str) -> Option<Arc<Obj>> {
+        self.vm.get_vars()[0].get(name).cloned()
+    }
+
+    // a global as a rust value
+    pub fn get_global<T: FromObj>(&self, name: &str) -> Result<T, PyException> {
+        match self.global(name) {
+            Some(val) => T::from_obj(&val),
+            None => Err(PyException {
+                error: PyError::UndefinedVariableError,
+                msg: format!("name '{name}' is not defined"),
+            }),
+        }
+    }
+
+    // Runs the source as the __main__ module, defining what it defines in
+    // the globals. The filename is its __file__ and goes in syntax errors
+    pub fn exec(&mut self, source: &str, filename: &str) -> Result<(), PyException> {
+        let code = Interpreter::compile_source(source).map_err(|e| in_file(e, filename))?;
+        self.set_global("__file__", Obj::Str(filename.to_string()));
+        self.run(code)
+    }
+
+    // the value of one expression, evaluated in the globals
+    pub fn eval(&mut self, source: &str) -> Result<Arc<Obj>, PyException> {
+        let code = compile_expr(source, "<string>")?;
+        self.run(code)?;
+        // the expression leaves its value on top of the script's stack
+        self.vm.take_result().ok_or_else(|| in_file(PyException::syntax_error("invalid syntax"), "<string>"))
+    }
+
+    // calls the global with this name
+    pub fn call(&mut self, name: &str, args: &[Arc<Obj>]) -> Result<Arc<Obj>, PyException> {
+        let func = self.global(name).ok_or_else(|| PyException {
+            error: PyError::UndefinedVariableError,
+            msg: format!("name '{name}' is not defined"),
+        })?;
+        self.call_object(&func, args, &[])
+    }
+
+    pub fn call_object(
+        &mut self,
+        func: &Arc<Obj>,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+    ) -> Result<Arc<Obj>, PyException> {
+        self.vm.call_object(func.clone(), args.to_vec(), kwargs.to_vec())
+    }
+
+    fn run(&mut self, code: Vec<PyBytecode>) -> Result<(), PyException> {
+        self.vm.execute(code)
+    }
+}
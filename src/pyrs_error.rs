@@ -6,7 +6,7 @@ pub struct PyException
     pub msg: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum PyError 
 {
     ArithmeticError,
@@ -20,6 +20,26 @@ pub enum PyError
     FloatParseError,
     StackError,
     SyntaxError,
+    ValueError,
+    RuntimeError,
+    AttributeError,
+    StopIteration,
+    GeneratorExit,
+    ImportError,
+    ModuleNotFoundError,
+    OverflowError,
+    LookupError,
+    UnicodeDecodeError,
+    UnicodeEncodeError,
+    EOFError,
+    OSError,
+    FileNotFoundError,
+    FileExistsError,
+    PermissionError,
+    IsADirectoryError,
+    AssertionError,
+    BufferError,
+    MemoryError,
 }
 
 impl PyException
@@ -27,6 +47,93 @@ impl PyException
     pub fn print(&self) {
         println!("{self}");   
     }
+
+    pub fn new(error: PyError, msg: impl Into<String>) -> Self {
+        PyException { error, msg: msg.into() }
+    }
+
+    pub fn type_error(msg: impl Into<String>) -> Self {
+        PyException {
+            error: PyError::TypeError,
+            msg: msg.into(),
+        }
+    }
+
+    pub fn syntax_error(msg: impl Into<String>) -> Self {
+        PyException {
+            error: PyError::SyntaxError,
+            msg: msg.into(),
+        }
+    }
+
+    // there isn't room for what was asked for, with no message like python
+    pub fn memory_error() -> Self {
+        PyException {
+            error: PyError::MemoryError,
+            msg: String::new(),
+        }
+    }
+
+    // [Errno 2] No such file or directory: 'path', the error type by the errno
+    pub fn os_error(e: std::io::Error, path: Option<&str>) -> Self {
+        let error = match e.kind() {
+            std::io::ErrorKind::NotFound => PyError::FileNotFoundError,
+            std::io::ErrorKind::AlreadyExists => PyError::FileExistsError,
+            std::io::ErrorKind::PermissionDenied => PyError::PermissionError,
+            std::io::ErrorKind::IsADirectory => PyError::IsADirectoryError,
+            _ => PyError::OSError,
+        };
+        let msg = match e.raw_os_error() {
+            Some(code) => {
+                let desc = e.to_string();
+                let desc = desc.strip_suffix(&format!(" (os error {code})")).unwrap_or(&desc).to_string();
+                match path {
+                    Some(path) => format!("[Errno {code}] {desc}: '{path}'"),
+                    None => format!("[Errno {code}] {desc}"),
+                }
+            }
+            None => e.to_string(),
+        };
+        PyException { error, msg }
+    }
+}
+
+impl PyError
+{
+    pub fn try_from_name(name: &str) -> Option<PyError> {
+        let error = match name {
+            "ArithmeticError" => PyError::ArithmeticError,
+            "IndexError" => PyError::IndexError,
+            "KeyError" => PyError::KeyError,
+            "IndentationError" => PyError::IndentationError,
+            "TypeError" => PyError::TypeError,
+            "NotImplementedError" => PyError::NotImplementedError,
+            "ZeroDivisionError" => PyError::ZeroDivisionError,
+            "SyntaxError" => PyError::SyntaxError,
+            "ValueError" => PyError::ValueError,
+            "RuntimeError" => PyError::RuntimeError,
+            "AttributeError" => PyError::AttributeError,
+            "StopIteration" => PyError::StopIteration,
+            "GeneratorExit" => PyError::GeneratorExit,
+            "ImportError" => PyError::ImportError,
+            "ModuleNotFoundError" => PyError::ModuleNotFoundError,
+            "OverflowError" => PyError::OverflowError,
+            "LookupError" => PyError::LookupError,
+            "UnicodeDecodeError" => PyError::UnicodeDecodeError,
+            "UnicodeEncodeError" => PyError::UnicodeEncodeError,
+            "EOFError" => PyError::EOFError,
+            "OSError" => PyError::OSError,
+            "FileNotFoundError" => PyError::FileNotFoundError,
+            "FileExistsError" => PyError::FileExistsError,
+            "PermissionError" => PyError::PermissionError,
+            "IsADirectoryError" => PyError::IsADirectoryError,
+            "AssertionError" => PyError::AssertionError,
+            "BufferError" => PyError::BufferError,
+            "MemoryError" => PyError::MemoryError,
+            _ => return None,
+        };
+        Some(error)
+    }
 }
 
 impl std::fmt::Display for PyException
@@ -0,0 +1,568 @@
+use crate::{
+    pyrs_bytes as bytes,
+    pyrs_error::{PyError, PyException},
+    pyrs_native::{with_self, NativeClass, NativeObj},
+    pyrs_obj::{Obj, PyObj},
+    pyrs_std::bind_native_args,
+    pyrs_vm::PyVM,
+};
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    sync::{Arc, OnceLock},
+};
+
+// Files open() opens. Reads go through a buffer of what has been read from
+// the file but not handed out yet, so lines can be split without reading
+// a byte at a time. Text files sit on top of that like TextIOWrapper over
+// a BufferedRandom: they take a chunk of it at a time and keep the bytes
+// they haven't decoded, and hold what's written until a flush, tell, seek,
+// close or read to the end, so an r+ file mixing reads and writes ends up
+// the way CPython leaves it. They decode and encode with their encoding
+// and translate line endings the way newline says:
+//
+//     None    \r\n and \r read as \n, \n is written as it is
+//     ''      lines end at any of them, nothing is translated
+//     '\n'    lines end at \n
+//     '\r'    lines end at \r, \n is written as \r
+//     '\r\n'  lines end at \r\n, \n is written as \r\n
+
+#[derive(Debug)]
+struct TextMode {
+    encoding: String,
+    errors: String,
+    newline: Option<String>,
+}
+
+#[derive(Debug)]
+struct File {
+    name: String,
+    mode: String,
+    file: Option<fs::File>, // None once it's closed
+    readable: bool,
+    writable: bool,
+    text: Option<TextMode>, // None in binary mode
+    pending: Vec<u8>,
+    chunk: Vec<u8>,     // taken off pending by the text layer, not decoded yet
+    unwritten: Vec<u8>, // encoded text not passed on to pending's level yet
+}
+
+
+impl File
+{
+    fn handle(&mut self) -> Result<&mut fs::File, PyException> {
+        self.file.as_mut().ok_or_else(|| PyException::new(PyError::ValueError, "I/O operation on closed file."))
+    }
+
+    fn check_readable(&mut self) -> Result<(), PyException> {
+        self.handle()?;
+        match self.readable {
+            true => Ok(()),
+            false => Err(PyException::new(PyError::OSError, "not readable")),
+        }
+    }
+
+    fn check_writable(&mut self) -> Result<(), PyException> {
+        self.handle()?;
+        match self.writable {
+            true => Ok(()),
+            false => Err(PyException::new(PyError::OSError, "not writable")),
+        }
+    }
+
+    // reads more of the file onto the buffer, false at the end of it
+    fn fill(&mut self) -> Result<bool, PyException> {
+        let mut chunk = [0u8; 8192];
+        let n = self.handle()?.read(&mut chunk).map_err(|e| PyException::os_error(e, None))?;
+        self.pending.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+
+    fn read_all(&mut self) -> Result<Vec<u8>, PyException> {
+        while self.fill()? {}
+        Ok(std::mem::take(&mut self.pending))
+    }
+
+    // the text layer's next chunk, what's buffered or else up to 8192 bytes
+    // more of the file, false at the end of it
+    fn fill_chunk(&mut self) -> Result<bool, PyException> {
+        if self.pending.is_empty() && !self.fill()? {
+            return Ok(false);
+        }
+        self.chunk.append(&mut self.pending);
+        Ok(true)
+    }
+
+    // the bytes of the next line with its ending, empty at the end of the file
+    fn line_bytes(&mut self) -> Result<Vec<u8>, PyException> {
+        let newline = self.text.as_ref().map_or(Some("\n".to_string()), |text| text.newline.clone());
+        loop {
+            let buf = match self.text {
+                Some(_) => &self.chunk,
+                None => &self.pending,
+            };
+            let end = match newline.as_deref() {
+                None | Some("") => match buf.iter().position(|b| *b == b'\n' || *b == b'\r') {
+                    // a \r at the end of what's read could be the start of \r\n
+                    Some(i) if buf[i] == b'\r' && i + 1 == buf.len() => None,
+                    Some(i) if buf[i] == b'\r' && buf[i + 1] == b'\n' => Some(i + 2),
+                    Some(i) => Some(i + 1),
+                    None => None,
+                },
+                Some("\r\n") => buf.windows(2).position(|w| w == b"\r\n").map(|i| i + 2),
+                Some(ending) => buf.iter().position(|b| *b == ending.as_bytes()[0]).map(|i| i + 1),
+            };
+            let more = match end {
+                Some(end) => return Ok(self.buffer().drain(..end).collect()),
+                None if self.text.is_some() => self.fill_chunk()?,
+                None => self.fill()?,
+            };
+            if !more {
+                return Ok(std::mem::take(self.buffer()));
+            }
+        }
+    }
+
+    // what reads take from, the text layer's chunk or the file's buffer
+    fn buffer(&mut self) -> &mut Vec<u8> {
+        match self.text {
+            Some(_) => &mut self.chunk,
+            None => &mut self.pending,
+        }
+    }
+
+    // how many chars the decodable part of the chunk reads as, a \r\n read
+    // as \n is one
+    fn chunk_chars(&self) -> Result<usize, PyException> {
+        let text = self.text.as_ref().unwrap();
+        let end = self.chunk.len() - bytes::unfinished_tail(&self.chunk, &text.encoding)?;
+        let s = bytes::decode(&self.chunk[..end], &text.encoding, &text.errors)?;
+        let crlf = match text.newline {
+            None => s.matches("\r\n").count(),
+            Some(_) => 0,
+        };
+        Ok(s.chars().count() - crlf)
+    }
+
+    // Decodes what was read. With size, only that many chars are taken and
+    // the bytes of the rest go back on the chunk as they were read, so the
+    // position stays where the last char ended. A \r\n read as \n counts as
+    // one. A char cut off at the end is left for the next chunk unless the
+    // file ends there
+    fn decode(&mut self, raw: &[u8], size: Option<usize>, last: bool) -> Result<String, PyException> {
+        let text = self.text.as_ref().unwrap();
+        let end = match last {
+            true => raw.len(),
+            false => raw.len() - bytes::unfinished_tail(raw, &text.encoding)?,
+        };
+        let (raw, tail) = raw.split_at(end);
+        let s = bytes::decode(raw, &text.encoding, &text.errors)?;
+        let translate = text.newline.is_none();
+        let mut out = String::new();
+        let mut chars = s.char_indices().peekable();
+        let mut split = s.len();
+        let mut count = 0;
+        while let Some((i, c)) = chars.next() {
+            if size == Some(count) {
+                split = i;
+                break;
+            }
+            match c {
+                '\r' if translate => {
+                    chars.next_if(|(_, c)| *c == '\n');
+                    out.push('\n');
+                }
+                c => out.push(c),
+            }
+            count += 1;
+        }
+        let used = match split < s.len() {
+            true => bytes::decode_chars(raw, &text.encoding, &text.errors, Some(s[..split].chars().count()))?.1,
+            false => raw.len(),
+        };
+        let mut rest = [&raw[used..], tail].concat();
+        rest.append(&mut self.chunk);
+        self.chunk = rest;
+        Ok(out)
+    }
+
+    // read(size=-1), everything left when size is negative
+    fn read(&mut self, size: Option<usize>) -> Result<Obj, PyException> {
+        self.check_readable()?;
+        if self.text.is_some() {
+            // reading to the end writes what's held first, reading some doesn't
+            let Some(size) = size else {
+                self.flush_text()?;
+                let mut raw = std::mem::take(&mut self.chunk);
+                raw.append(&mut self.read_all()?);
+                return self.decode(&raw, None, true).map(Obj::Str);
+            };
+            // one char more than asked for, so a \r at the end sees its \n
+            let mut last = false;
+            while self.chunk_chars()? <= size && !last {
+                last = !self.fill_chunk()?;
+            }
+            let raw = std::mem::take(&mut self.chunk);
+            return self.decode(&raw, Some(size), last).map(Obj::Str);
+        }
+        let Some(size) = size else {
+            return self.read_all().map(Obj::Bytes);
+        };
+        while self.pending.len() < size && self.fill()? {}
+        let end = size.min(self.pending.len());
+        Ok(Obj::Bytes(self.pending.drain(..end).collect()))
+    }
+
+    // readline(size=-1), the line is cut off after size chars or bytes
+    fn readline(&mut self, size: Option<usize>) -> Result<Obj, PyException> {
+        self.check_readable()?;
+        let mut line = self.line_bytes()?;
+        if self.text.is_some() {
+            return self.decode(&line, size, true).map(Obj::Str);
+        }
+        if let Some(size) = size.filter(|size| *size < line.len()) {
+            let mut rest = line.split_off(size);
+            rest.append(&mut self.pending);
+            self.pending = rest;
+        }
+        Ok(Obj::Bytes(line))
+    }
+
+    // how many chars or bytes of obj were written
+    fn write(&mut self, obj: &Obj) -> Result<Obj, PyException> {
+        self.check_writable()?;
+        let (data, count) = match (&self.text, obj) {
+            (Some(text), Obj::Str(s)) => {
+                // the count is of the characters given, before \n is translated
+                let count = s.chars().count();
+                let s = match text.newline.as_deref() {
+                    Some(ending @ ("\r" | "\r\n")) => s.replace('\n', ending),
+                    _ => s.clone(),
+                };
+                (bytes::encode(&s, &text.encoding, &text.errors)?, count)
+            }
+            (Some(_), o) => return Err(PyException::type_error(format!("write() argument must be str, not {}", o.type_name()))),
+            (None, o) => match bytes::bytes_of(o) {
+                Some(data) => {
+                    let len = data.len();
+                    (data, len)
+                }
+                None => return Err(PyException::type_error(format!("a bytes-like object is required, not '{}'", o.type_name()))),
+            },
+        };
+        // text is held, and what's left of the chunk is dropped without
+        // moving back to it, like TextIOWrapper
+        if self.text.is_some() {
+            self.chunk.clear();
+            self.unwritten.extend(data);
+            if self.unwritten.len() >= 8192 {
+                self.flush_text()?;
+            }
+        } else {
+            self.write_bytes(&data)?;
+        }
+        Ok(Obj::Int(count.into()))
+    }
+
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), PyException> {
+        // what was read ahead is given back so the write goes where tell() says
+        if !self.pending.is_empty() {
+            let back = -(self.pending.len() as i64);
+            self.pending.clear();
+            self.handle()?.seek(SeekFrom::Current(back)).map_err(|e| PyException::os_error(e, None))?;
+        }
+        self.handle()?.write_all(data).map_err(|e| PyException::os_error(e, None))
+    }
+
+    // passes the text written so far on to the file
+    fn flush_text(&mut self) -> Result<(), PyException> {
+        if self.unwritten.is_empty() {
+            return Ok(());
+        }
+        let data = std::mem::take(&mut self.unwritten);
+        self.write_bytes(&data)
+    }
+
+    fn flush(&mut self) -> Result<(), PyException> {
+        self.flush_text()?;
+        self.handle()?.flush().map_err(|e| PyException::os_error(e, None))
+    }
+
+    fn tell(&mut self) -> Result<u64, PyException> {
+        self.flush_text()?;
+        let ahead = (self.pending.len() + self.chunk.len()) as u64;
+        let pos = self.handle()?.stream_position().map_err(|e| PyException::os_error(e, None))?;
+        Ok(pos - ahead)
+    }
+
+    // seek(offset, whence=0), text files only seek relative to the start
+    fn seek(&mut self, offset: i64, whence: i64) -> Result<u64, PyException> {
+        self.handle()?;
+        let from = match whence {
+            0 if offset < 0 => return Err(PyException::new(PyError::ValueError, format!("negative seek position {offset}"))),
+            0 => SeekFrom::Start(offset as u64),
+            1 if self.text.is_some() && offset != 0 => return Err(PyException::new(PyError::OSError, "can't do nonzero cur-relative seeks")),
+            1 if self.text.is_some() => SeekFrom::Start(self.tell()?),
+            1 => SeekFrom::Current(offset - self.pending.len() as i64),
+            2 if self.text.is_some() && offset != 0 => return Err(PyException::new(PyError::OSError, "can't do nonzero end-relative seeks")),
+            2 => SeekFrom::End(offset),
+            _ => {
+                return Err(PyException::new(PyError::ValueError, format!("invalid whence ({whence}, should be 0, 1 or 2)")));
+            }
+        };
+        self.flush_text()?;
+        self.pending.clear();
+        self.chunk.clear();
+        self.handle()?.seek(from).map_err(|e| PyException::os_error(e, None))
+    }
+
+    fn close(&mut self) -> Result<(), PyException> {
+        if self.file.is_some() {
+            self.flush()?;
+            self.file = None;
+        }
+        Ok(())
+    }
+}
+
+// held text is written when a file that wasn't closed goes away
+impl Drop for File
+{
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+// _io.TextIOWrapper for text files, _io.BufferedReader, BufferedWriter
+// and BufferedRandom for binary ones
+fn file_class(binary: bool, readable: bool, writable: bool) -> &'static Arc<NativeClass> {
+    static CLASSES: OnceLock<[Arc<NativeClass>; 4]> = OnceLock::new();
+    let classes = CLASSES.get_or_init(|| {
+        ["TextIOWrapper", "BufferedReader", "BufferedWriter", "BufferedRandom"].map(|name| {
+            let mut class = file_methods(NativeClass::new(name));
+            class.module = "_io".to_string();
+            Arc::new(class)
+        })
+    });
+    match (binary, readable, writable) {
+        (false, _, _) => &classes[0],
+        (true, true, true) => &classes[3],
+        (true, true, false) => &classes[1],
+        (true, false, _) => &classes[2],
+    }
+}
+
+// an int arg where -1 or None mean no limit
+fn size_arg(func: &str, args: &[Arc<Obj>]) -> Result<Option<usize>, PyException> {
+    match args.get(1).map(|arg| arg.as_ref()) {
+        _ if args.len() > 2 => Err(PyException::type_error(format!("{func}() takes at most 1 argument ({} given)", args.len() - 1))),
+        None | Some(Obj::None) => Ok(None),
+        Some(o) => match o.to_integer() {
+            Some(size) if size < 0 => Ok(None),
+            Some(size) => Ok(Some(size.to_usize().unwrap_or(usize::MAX))),
+            None => Err(PyException::type_error(format!("argument should be integer or None, not '{}'", o.type_name()))),
+        },
+    }
+}
+
+fn file_methods(class: NativeClass) -> NativeClass {
+    class
+        .method("read", |_, args, _| {
+            let size = size_arg("read", args)?;
+            with_self(args, |f: &mut File| f.read(size))?.map(Arc::from)
+        })
+        .method("readline", |_, args, _| {
+            let size = size_arg("readline", args)?;
+            with_self(args, |f: &mut File| f.readline(size))?.map(Arc::from)
+        })
+        .method("readlines", |_, args, _| {
+            with_self(args, |f: &mut File| {
+                let mut lines = vec![];
+                loop {
+                    let line = f.readline(None)?;
+                    if !line.__bool__() {
+                        return Ok(Obj::new_list(lines).into());
+                    }
+                    lines.push(line.into());
+                }
+            })?
+        })
+        .method("write", |_, args, _| match args {
+            [_, obj] => with_self(args, |f: &mut File| f.write(obj))?.map(Arc::from),
+            _ => Err(PyException::type_error(format!("write() takes exactly one argument ({} given)", args.len().saturating_sub(1)))),
+        })
+        // the lines are taken out of the iterable before any is written
+        .method("writelines", |vm, args, _| {
+            let [_, lines] = args else {
+                return Err(PyException::type_error(format!("writelines() takes exactly one argument ({} given)", args.len().saturating_sub(1))));
+            };
+            let lines = vm.iter_items(lines)?;
+            with_self(args, |f: &mut File| {
+                for line in &lines {
+                    f.write(line)?;
+                }
+                Ok(Obj::None.into())
+            })?
+        })
+        .method("seek", |_, args, _| {
+            let int = |arg: &Arc<Obj>| {
+                arg.to_integer()
+                    .and_then(|i| i.to_i64())
+                    .ok_or_else(|| PyException::type_error(format!("'{}' object cannot be interpreted as an integer", arg.type_name())))
+            };
+            let (offset, whence) = match args {
+                [_, offset] => (int(offset)?, 0),
+                [_, offset, whence] => (int(offset)?, int(whence)?),
+                _ => return Err(PyException::type_error(format!("seek expected at least 1 argument, got {}", args.len().saturating_sub(1)))),
+            };
+            with_self(args, |f: &mut File| f.seek(offset, whence))?.map(|pos| Obj::Int(pos.into()).into())
+        })
+        .method("tell", |_, args, _| with_self(args, |f: &mut File| f.tell())?.map(|pos| Obj::Int(pos.into()).into()))
+        .method("flush", |_, args, _| {
+            with_self(args, |f: &mut File| f.flush())??;
+            Ok(Obj::None.into())
+        })
+        .method("close", |_, args, _| {
+            with_self(args, |f: &mut File| f.close())??;
+            Ok(Obj::None.into())
+        })
+        .method("readable", |_, args, _| {
+            with_self(args, |f: &mut File| {
+                f.handle()?;
+                Ok(Obj::Bool(f.readable).into())
+            })?
+        })
+        .method("writable", |_, args, _| {
+            with_self(args, |f: &mut File| {
+                f.handle()?;
+                Ok(Obj::Bool(f.writable).into())
+            })?
+        })
+        .method("__iter__", |_, args, _| {
+            with_self(args, |f: &mut File| f.handle().map(|_| ()))??;
+            Ok(args[0].clone())
+        })
+        .method("__next__", |_, args, _| {
+            let line = with_self(args, |f: &mut File| f.readline(None))??;
+            match line.__bool__() {
+                true => Ok(line.into()),
+                false => Err(PyException::new(PyError::StopIteration, "")),
+            }
+        })
+        .method("__enter__", |_, args, _| {
+            with_self(args, |f: &mut File| f.handle().map(|_| ()))??;
+            Ok(args[0].clone())
+        })
+        // closes the file whatever the with block raised, and doesn't swallow it
+        .method("__exit__", |_, args, _| {
+            with_self(args, |f: &mut File| f.close())??;
+            Ok(Obj::None.into())
+        })
+        .method("__repr__", |_, args, _| {
+            with_self(args, |f: &mut File| {
+                let repr = match &f.text {
+                    Some(text) => format!("<_io.TextIOWrapper name='{}' mode='{}' encoding='{}'>", f.name, f.mode, text.encoding),
+                    None => format!("<_io.{} name='{}'>", file_class(true, f.readable, f.writable).name, f.name),
+                };
+                Obj::Str(repr).into()
+            })
+        })
+        .attr("closed", |_, args, _| with_self(args, |f: &mut File| Obj::Bool(f.file.is_none()).into()))
+        .attr("name", |_, args, _| with_self(args, |f: &mut File| Obj::Str(f.name.clone()).into()))
+        .attr("mode", |_, args, _| with_self(args, |f: &mut File| Obj::Str(f.mode.clone()).into()))
+        .attr("encoding", |_, args, _| {
+            with_self(args, |f: &mut File| match &f.text {
+                Some(text) => Ok(Obj::Str(text.encoding.clone()).into()),
+                None => Err(PyException::new(PyError::AttributeError, format!("'_io.{}' object has no attribute 'encoding'", file_class(true, f.readable, f.writable).name))),
+            })?
+        })
+}
+
+// open(file, mode='r', buffering=-1, encoding=None, errors=None, newline=None)
+pub fn open(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let params = ["file", "mode", "buffering", "encoding", "errors", "newline"];
+    let bound = bind_native_args("open", &params, params.len(), args, kwargs)?;
+    let str_arg = |i: usize| match bound[i].as_deref() {
+        None | Some(Obj::None) => Ok(None),
+        Some(Obj::Str(s)) => Ok(Some(s.clone())),
+        Some(o) => Err(PyException::type_error(format!("open() argument '{}' must be str or None, not {}", params[i], o.type_name()))),
+    };
+    let path = match bound[0].as_deref() {
+        Some(Obj::Str(path)) => path.clone(),
+        Some(o) => return Err(PyException::type_error(format!("expected str, bytes or os.PathLike object, not {}", o.type_name()))),
+        None => return Err(PyException::type_error("open() missing required argument 'file' (pos 1)")),
+    };
+    let mode = match bound[1].as_deref() {
+        None => "r".to_string(),
+        Some(Obj::Str(mode)) => mode.clone(),
+        Some(o) => return Err(PyException::type_error(format!("open() argument 'mode' must be str, not {}", o.type_name()))),
+    };
+    let (encoding, errors, newline) = (str_arg(3)?, str_arg(4)?, str_arg(5)?);
+
+    let has = |c: char| mode.contains(c);
+    let invalid = mode.chars().any(|c| !"rwaxbt+".contains(c)) || mode.chars().any(|c| mode.matches(c).count() > 1);
+    if invalid {
+        return Err(PyException::new(PyError::ValueError, format!("invalid mode: '{mode}'")));
+    }
+    if ['r', 'w', 'a', 'x'].iter().filter(|c| has(**c)).count() != 1 {
+        return Err(PyException::new(PyError::ValueError, "must have exactly one of create/read/write/append mode"));
+    }
+    let binary = has('b');
+    if binary && has('t') {
+        return Err(PyException::new(PyError::ValueError, "can't have text and binary mode at once"));
+    }
+    for (name, arg) in [("encoding", &encoding), ("errors", &errors), ("newline", &newline)] {
+        if binary && arg.is_some() {
+            return Err(PyException::new(PyError::ValueError, format!("binary mode doesn't take an {name} argument")));
+        }
+    }
+    if let Some(newline) = newline.as_deref().filter(|n| !["", "\n", "\r", "\r\n"].contains(n)) {
+        return Err(PyException::new(PyError::ValueError, format!("illegal newline value: {newline}")));
+    }
+    let text = match binary {
+        true => None,
+        false => {
+            let encoding = encoding.unwrap_or("utf-8".to_string());
+            // an unknown encoding is an error now, not at the first read
+            bytes::decode(b"", &encoding, "strict")?;
+            Some(TextMode {
+                encoding,
+                errors: errors.unwrap_or("strict".to_string()),
+                newline,
+            })
+        }
+    };
+
+    let (readable, writable) = (has('r') || has('+'), !has('r') || has('+'));
+    let file = fs::OpenOptions::new()
+        .read(readable)
+        .write(writable && !has('a'))
+        .append(has('a'))
+        .truncate(has('w'))
+        .create(has('w') || has('a'))
+        .create_new(has('x'))
+        .open(&path)
+        .map_err(|e| PyException::os_error(e, Some(&path)))?;
+    // opening a directory for reading works, reading it doesn't
+    if file.metadata().is_ok_and(|meta| meta.is_dir()) {
+        return Err(PyException::os_error(io::Error::from_raw_os_error(21), Some(&path)));
+    }
+    // binary files show the mode the way the OS file was opened
+    let mode = match binary {
+        true if has('+') && (has('r') || has('w')) => "rb+".to_string(),
+        true => format!("{}b{}", mode.replace(['b', '+'], ""), if has('+') { "+" } else { "" }),
+        false => mode.clone(),
+    };
+    let file = File {
+        name: path,
+        mode,
+        file: Some(file),
+        readable,
+        writable,
+        text,
+        pending: vec![],
+        chunk: vec![],
+        unwritten: vec![],
+    };
+    Ok(NativeObj::wrap(file_class(binary, readable, writable), file).into())
+}
@@ -0,0 +1,720 @@
+use crate::{
+    pyrs_error::{PyError, PyException},
+    pyrs_obj::{int_to_f64, Obj, PyObj},
+};
+use rug::Integer;
+
+// The format spec mini-language, what goes after the ':' in "{x:>10.2f}"
+// [[fill]align][sign][z][#][0][width][grouping][.precision][type]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatSpec {
+    pub fill: Option<char>,
+    pub align: Option<char>,
+    pub sign: Option<char>,
+    pub no_neg_zero: bool,
+    pub alternate: bool,
+    pub zero_pad: bool,
+    pub width: usize,
+    pub grouping: Option<char>,
+    pub precision: Option<usize>,
+    pub kind: Option<char>,
+}
+
+impl FormatSpec
+{
+    pub fn parse(spec: &str, type_name: &str) -> Result<FormatSpec, PyException> {
+        let chars: Vec<char> = spec.chars().collect();
+        let is_align = |c: &char| matches!(c, '<' | '>' | '^' | '=');
+        let mut fs = FormatSpec::default();
+        let mut i = 0;
+
+        if chars.get(1).is_some_and(is_align) {
+            fs.fill = Some(chars[0]);
+            fs.align = Some(chars[1]);
+            i = 2;
+        } else if chars.first().is_some_and(is_align) {
+            fs.align = Some(chars[0]);
+            i = 1;
+        }
+        if let Some(&sign @ ('+' | '-' | ' ')) = chars.get(i) {
+            fs.sign = Some(sign);
+            i += 1;
+        }
+        if chars.get(i) == Some(&'z') {
+            fs.no_neg_zero = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'#') {
+            fs.alternate = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            fs.zero_pad = true;
+            i += 1;
+        }
+        let too_many = "Too many decimal digits in format string";
+        fs.width = read_digits(&chars, &mut i, too_many)?.unwrap_or(0);
+        if let Some(&sep @ (',' | '_')) = chars.get(i) {
+            fs.grouping = Some(sep);
+            i += 1;
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            match read_digits(&chars, &mut i, too_many)? {
+                Some(precision) => fs.precision = Some(precision),
+                None => return Err(PyException::new(PyError::ValueError, "Format specifier missing precision")),
+            }
+        }
+        match &chars[i..] {
+            [] => {}
+            [kind] => fs.kind = Some(*kind),
+            _ => {
+                return Err(PyException::new(PyError::ValueError, format!(
+                    "Invalid format specifier '{spec}' for object of type '{type_name}'"
+                )))
+            }
+        }
+        Ok(fs)
+    }
+
+    fn unknown_code(&self, type_name: &str) -> PyException {
+        PyException::new(PyError::ValueError, format!(
+            "Unknown format code '{}' for object of type '{type_name}'",
+            self.kind.unwrap_or(' ')
+        ))
+    }
+
+    fn fill(&self) -> char {
+        match (self.fill, self.zero_pad) {
+            (Some(fill), _) => fill,
+            (None, true) => '0',
+            (None, false) => ' ',
+        }
+    }
+
+    fn pad(&self, body: &str, align: char) -> String {
+        let len = body.chars().count();
+        if len >= self.width {
+            return body.to_string();
+        }
+        let fill = self.fill().to_string();
+        let count = self.width - len;
+        match align {
+            '<' => format!("{body}{}", fill.repeat(count)),
+            '^' => format!("{}{body}{}", fill.repeat(count / 2), fill.repeat(count - count / 2)),
+            _ => format!("{}{body}", fill.repeat(count)),
+        }
+    }
+
+    pub fn format_str(&self, s: &str) -> Result<String, PyException> {
+        if !matches!(self.kind, None | Some('s')) {
+            return Err(self.unknown_code("str"));
+        }
+        if self.sign.is_some() {
+            return Err(PyException::new(PyError::ValueError, "Sign not allowed in string format specifier"));
+        }
+        if self.alternate {
+            return Err(PyException::new(PyError::ValueError, "Alternate form (#) not allowed in string format specifier"));
+        }
+        if self.align == Some('=') {
+            return Err(PyException::new(PyError::ValueError, "'=' alignment not allowed in string format specifier"));
+        }
+        if let Some(sep) = self.grouping {
+            return Err(PyException::new(PyError::ValueError, format!("Cannot specify '{sep}' with 's'.")));
+        }
+        let body: String = match self.precision {
+            Some(precision) => s.chars().take(precision).collect(),
+            None => s.to_string(),
+        };
+        Ok(self.pad(&body, self.align.unwrap_or('<')))
+    }
+
+    pub fn format_int(&self, int: &Integer) -> Result<String, PyException> {
+        self.format_int_digits(int, 0)
+    }
+
+    // printf's "%.3d" is a least number of digits, a format spec has no way to ask for one
+    fn format_int_digits(&self, int: &Integer, min_digits: usize) -> Result<String, PyException> {
+        let (radix, prefix) = match self.kind {
+            None | Some('d') | Some('n') => (10, ""),
+            Some('b') => (2, "0b"),
+            Some('o') => (8, "0o"),
+            Some('x') => (16, "0x"),
+            Some('X') => (16, "0X"),
+            Some('c') => (10, ""),
+            Some('e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%') => return self.format_float(int_to_f64(int)?),
+            Some(_) => return Err(self.unknown_code("int")),
+        };
+        if self.precision.is_some() {
+            return Err(PyException::new(PyError::ValueError, "Precision not allowed in integer format specifier"));
+        }
+        match (self.grouping, self.kind) {
+            (Some(','), Some(kind @ ('b' | 'o' | 'x' | 'X' | 'n' | 'c'))) | (Some('_'), Some(kind @ ('n' | 'c'))) => {
+                return Err(PyException::new(PyError::ValueError, format!("Cannot specify '{}' with '{kind}'.", self.grouping.unwrap())));
+            }
+            _ => {}
+        }
+        if self.kind == Some('c') {
+            if self.sign.is_some() {
+                return Err(PyException::new(PyError::ValueError, "Sign not allowed with integer format specifier 'c'"));
+            }
+            let c = int.to_u32().and_then(char::from_u32).ok_or_else(|| PyException {
+                error: PyError::ArithmeticError,
+                msg: "%c arg not in range(0x110000)".to_string(),
+            })?;
+            return Ok(self.pad(&c.to_string(), self.align.unwrap_or('>')));
+        }
+
+        let mut digits = int.clone().abs().to_string_radix(radix);
+        if self.kind == Some('X') {
+            digits = digits.to_uppercase();
+        }
+        if digits.len() < min_digits {
+            digits.insert_str(0, &"0".repeat(min_digits - digits.len()));
+        }
+        let prefix = if self.alternate { prefix } else { "" };
+        let interval = if radix == 10 { 3 } else { 4 };
+        Ok(self.pad_number(*int < 0, prefix, &digits, "", interval))
+    }
+
+    pub fn format_float(&self, val: f64) -> Result<String, PyException> {
+        let upper = matches!(self.kind, Some('E' | 'F' | 'G'));
+        let mut body = match self.kind {
+            _ if !val.is_finite() => {
+                let body = if val.is_nan() { "nan" } else { "inf" };
+                let body = if self.kind == Some('%') { format!("{body}%") } else { body.to_string() };
+                return Ok(self.pad_number(val.is_sign_negative() && !val.is_nan(), "", "", &self.case(body, upper), 3));
+            }
+            None if self.precision.is_none() => Obj::Float(val.abs()).__str__(),
+            None => general(val.abs(), self.precision.unwrap(), self.alternate, true),
+            Some('f' | 'F') => fixed(val.abs(), self.precision.unwrap_or(6), self.alternate),
+            Some('e' | 'E') => scientific(val.abs(), self.precision.unwrap_or(6), self.alternate),
+            Some('g' | 'G' | 'n') => general(val.abs(), self.precision.unwrap_or(6), self.alternate, false),
+            Some('%') => fixed(val.abs() * 100.0, self.precision.unwrap_or(6), self.alternate) + "%",
+            Some(_) => return Err(self.unknown_code("float")),
+        };
+        body = self.case(body, upper);
+        // -0.0 and values that round to zero lose their sign with z
+        let zero = body.chars().all(|c| matches!(c, '0' | '.' | '%' | 'e' | 'E' | '+' | '-'));
+        let negative = val.is_sign_negative() && !(self.no_neg_zero && zero);
+
+        let split = body.find(|c: char| !c.is_ascii_digit()).unwrap_or(body.len());
+        let (int_part, rest) = body.split_at(split);
+        Ok(self.pad_number(negative, "", int_part, rest, 3))
+    }
+
+    fn case(&self, body: String, upper: bool) -> String {
+        match upper {
+            true => body.to_uppercase(),
+            false => body,
+        }
+    }
+
+    // sign and prefix, then the integer digits with their separators and
+    // then the rest, zero padding goes between the sign and the digits
+    pub(crate) fn pad_number(&self, negative: bool, prefix: &str, int_part: &str, rest: &str, interval: usize) -> String {
+        let sign = match (negative, self.sign) {
+            (true, _) => "-",
+            (false, Some('+')) => "+",
+            (false, Some(' ')) => " ",
+            _ => "",
+        };
+        let lead = format!("{sign}{prefix}");
+        let align = self.align.unwrap_or(if self.zero_pad { '=' } else { '>' });
+        let mut grouped = group(int_part, self.grouping, interval);
+
+        // zeros are digits, so separators go into them too
+        if align == '=' && self.fill() == '0' && !int_part.is_empty() {
+            let min = self.width.saturating_sub(lead.len() + rest.chars().count());
+            let mut digits = int_part.to_string();
+            while grouped.len() < min {
+                digits.insert(0, '0');
+                grouped = group(&digits, self.grouping, interval);
+            }
+        }
+        let body = format!("{grouped}{rest}");
+        match align {
+            '=' => {
+                let count = self.width.saturating_sub(lead.chars().count() + body.chars().count());
+                format!("{lead}{}{body}", self.fill().to_string().repeat(count))
+            }
+            align => self.pad(&format!("{lead}{body}"), align),
+        }
+    }
+}
+
+fn group(digits: &str, sep: Option<char>, interval: usize) -> String {
+    let Some(sep) = sep else {
+        return digits.to_string();
+    };
+    let mut grouped = String::with_capacity(digits.len() * 2);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(interval) {
+            grouped.push(sep);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+fn fixed(val: f64, precision: usize, alternate: bool) -> String {
+    let body = format!("{val:.precision$}");
+    match alternate && precision == 0 {
+        true => body + ".",
+        false => body,
+    }
+}
+
+// rust writes 1.5e2, python 1.5e+02
+fn scientific(val: f64, precision: usize, alternate: bool) -> String {
+    let body = format!("{val:.precision$e}");
+    let (mantissa, exp) = body.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let dot = if alternate && precision == 0 { "." } else { "" };
+    let sign = if exp < 0 { '-' } else { '+' };
+    format!("{mantissa}{dot}e{sign}{:02}", exp.abs())
+}
+
+// 'g', precision significant digits in whichever of fixed or scientific
+// suits the exponent, trailing zeros dropped. With no type the fixed form
+// always keeps a digit after the point
+fn general(val: f64, precision: usize, alternate: bool, no_type: bool) -> String {
+    let precision = precision.max(1);
+    let sci = scientific(val, precision - 1, alternate);
+    let exp: i32 = sci.split_once('e').unwrap().1.parse().unwrap();
+    let too_big = match no_type {
+        true => exp >= precision as i32 - 1,
+        false => exp >= precision as i32,
+    };
+    let mut body = match exp < -4 || too_big {
+        true => sci,
+        false => fixed(val, (precision as i32 - 1 - exp) as usize, alternate),
+    };
+    if !alternate {
+        let (mantissa, exp) = match body.split_once('e') {
+            Some((mantissa, exp)) => (mantissa.to_string(), format!("e{exp}")),
+            None => (body.clone(), String::new()),
+        };
+        let mantissa = match mantissa.contains('.') {
+            true => mantissa.trim_end_matches('0').trim_end_matches('.').to_string(),
+            false => mantissa,
+        };
+        body = format!("{mantissa}{exp}");
+    }
+    if no_type && !body.contains(['.', 'e']) {
+        body.push_str(".0");
+    }
+    body
+}
+
+// The shortest digits that read back as the same float, in fixed notation
+// when the exponent is from -4 up to 16 and in scientific otherwise, the
+// way repr(float) writes 1.0, 1e+16 and 1.5e-07
+pub fn float_repr(val: f64) -> String {
+    if val.is_nan() {
+        return "nan".to_string();
+    }
+    let sign = if val.is_sign_negative() { "-" } else { "" };
+    if val.is_infinite() {
+        return format!("{sign}inf");
+    }
+    // rust's {:e} is shortest round trip too, 1.5e-7
+    let sci = format!("{:e}", val.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    let digits = mantissa.replace('.', "");
+    let body = match exp {
+        -4..=-1 => format!("0.{}{digits}", "0".repeat((-exp - 1) as usize)),
+        0..=15 => {
+            let int_len = exp as usize + 1;
+            match digits.len() > int_len {
+                true => format!("{}.{}", &digits[..int_len], &digits[int_len..]),
+                false => format!("{digits}{}.0", "0".repeat(int_len - digits.len())),
+            }
+        }
+        _ => format!("{mantissa}e{}{:02}", if exp < 0 { '-' } else { '+' }, exp.abs()),
+    };
+    format!("{sign}{body}")
+}
+
+// format(obj, spec) for the builtin types
+pub fn format_obj(obj: &Obj, spec: &str) -> Result<String, PyException> {
+    if spec.is_empty() {
+        return Ok(obj.__str__());
+    }
+    let fs = FormatSpec::parse(spec, obj.type_name())?;
+    match obj {
+        Obj::Str(s) => fs.format_str(s),
+        Obj::Bool(b) => fs.format_int(&Integer::from(*b)),
+        Obj::Int(int) => fs.format_int(int),
+        Obj::Float(val) => fs.format_float(*val),
+        Obj::Decimal(d) => d.format(&fs),
+        o => Err(PyException::type_error(format!("unsupported format string passed to {}.__format__", o.type_name()))),
+    }
+}
+
+// ascii(), a repr with everything past ascii escaped
+pub fn ascii_escape(repr: &str) -> String {
+    let mut escaped = String::with_capacity(repr.len());
+    for c in repr.chars() {
+        match c as u32 {
+            0..=0x7f => escaped.push(c),
+            code @ 0..=0xff => escaped.push_str(&format!("\\x{code:02x}")),
+            code @ 0..=0xffff => escaped.push_str(&format!("\\u{code:04x}")),
+            code => escaped.push_str(&format!("\\U{code:08x}")),
+        }
+    }
+    escaped
+}
+
+// One piece of a str.format template, a field is "{name!conversion:spec}"
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    Text(String),
+    Field { name: String, conversion: Option<char>, spec: String },
+}
+
+// "a.b[0]" is the argument a, then its attribute b, then item 0 of that
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldAccess {
+    Attr(String),
+    Item(String),
+}
+
+// The argument a field names, by position or by keyword
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldArg<'a> {
+    Index(usize),
+    Key(&'a str),
+}
+
+// "{}" fields are numbered in order, "{0}" by hand, a template can't mix them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum FieldNumbering {
+    #[default]
+    Unset,
+    Auto(usize),
+    Manual,
+}
+
+impl FieldNumbering
+{
+    pub fn resolve<'a>(&mut self, first: &'a str) -> Result<FieldArg<'a>, PyException> {
+        if first.is_empty() {
+            let idx = match self {
+                FieldNumbering::Unset => 0,
+                FieldNumbering::Auto(idx) => *idx,
+                FieldNumbering::Manual => {
+                    return Err(PyException::new(PyError::ValueError, 
+                        "cannot switch from manual field specification to automatic field numbering",
+                    ))
+                }
+            };
+            *self = FieldNumbering::Auto(idx + 1);
+            return Ok(FieldArg::Index(idx));
+        }
+        if !first.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(FieldArg::Key(first));
+        }
+        if let FieldNumbering::Auto(_) = self {
+            return Err(PyException::new(PyError::ValueError, 
+                "cannot switch from automatic field numbering to manual field specification",
+            ));
+        }
+        *self = FieldNumbering::Manual;
+        first.parse().map(FieldArg::Index).map_err(|_| PyException::new(PyError::ValueError, "Too many decimal digits in format string"))
+    }
+}
+
+// "{{" and "}}" are literal braces, a field's spec can hold fields of its own
+pub fn parse_template(template: &str) -> Result<Vec<TemplatePart>, PyException> {
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.next_if_eq(&c).is_some() => text.push(c),
+            '}' => return Err(PyException::new(PyError::ValueError, "Single '}' encountered in format string")),
+            '{' if chars.peek().is_none() => {
+                return Err(PyException::new(PyError::ValueError, "Single '{' encountered in format string"))
+            }
+            '{' => {
+                let mut field = String::new();
+                let mut depth = 0;
+                loop {
+                    match chars.next() {
+                        None => return Err(PyException::new(PyError::ValueError, "expected '}' before end of string")),
+                        Some('}') if depth == 0 => break,
+                        Some(c) => {
+                            match c {
+                                '{' => depth += 1,
+                                '}' => depth -= 1,
+                                _ => {}
+                            }
+                            field.push(c);
+                        }
+                    }
+                }
+                if !text.is_empty() {
+                    parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(parse_field(&field)?);
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(TemplatePart::Text(text));
+    }
+    Ok(parts)
+}
+
+// a '!' or ':' inside [] is part of the name
+fn parse_field(field: &str) -> Result<TemplatePart, PyException> {
+    let mut in_brackets = false;
+    let end = field
+        .find(|c| {
+            match c {
+                '[' => in_brackets = true,
+                ']' => in_brackets = false,
+                _ => {}
+            }
+            !in_brackets && matches!(c, '!' | ':')
+        })
+        .unwrap_or(field.len());
+    let (name, rest) = field.split_at(end);
+    let (conversion, spec) = match rest.strip_prefix('!') {
+        Some(rest) => {
+            let mut chars = rest.chars();
+            let conversion = match chars.next() {
+                Some(c @ ('r' | 's' | 'a')) => c,
+                Some(c) => return Err(PyException::new(PyError::ValueError, format!("Unknown conversion specifier {c}"))),
+                None => {
+                    return Err(PyException::new(PyError::ValueError, "end of string while looking for conversion specifier"))
+                }
+            };
+            match chars.as_str() {
+                "" => (Some(conversion), ""),
+                rest => match rest.strip_prefix(':') {
+                    Some(spec) => (Some(conversion), spec),
+                    None => return Err(PyException::new(PyError::ValueError, "expected ':' after conversion specifier")),
+                },
+            }
+        }
+        None => (None, rest.strip_prefix(':').unwrap_or(rest)),
+    };
+    Ok(TemplatePart::Field {
+        name: name.to_string(),
+        conversion,
+        spec: spec.to_string(),
+    })
+}
+
+pub fn split_field_name(name: &str) -> Result<(&str, Vec<FieldAccess>), PyException> {
+    let end = name.find(['.', '[']).unwrap_or(name.len());
+    let (first, mut rest) = name.split_at(end);
+    let mut accesses = vec![];
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(PyException::new(PyError::ValueError, "Empty attribute in format string"));
+            }
+            accesses.push(FieldAccess::Attr(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let Some(end) = after.find(']') else {
+                return Err(PyException::new(PyError::ValueError, "Missing ']' in format string"));
+            };
+            if end == 0 {
+                return Err(PyException::new(PyError::ValueError, "Empty attribute in format string"));
+            }
+            accesses.push(FieldAccess::Item(after[..end].to_string()));
+            rest = &after[end + 1..];
+        } else {
+            return Err(PyException::new(PyError::ValueError, "Only '.' or '[' may follow ']' in format field specifier"));
+        }
+    }
+    Ok((first, accesses))
+}
+
+// One piece of a printf style template, "%(key)-08.3f". The conversion type
+// is the spec's kind, a '*' width or precision comes from the next argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum PercentPart {
+    Text(String),
+    Conversion {
+        key: Option<String>,
+        spec: FormatSpec,
+        star_width: bool,
+        star_precision: bool,
+    },
+}
+
+// a width or precision, None when there are no digits and a ValueError
+// with too_big when there are too many for an isize
+fn read_digits(chars: &[char], i: &mut usize, too_big: &str) -> Result<Option<usize>, PyException> {
+    let start = *i;
+    while chars.get(*i).is_some_and(char::is_ascii_digit) {
+        *i += 1;
+    }
+    if start == *i {
+        return Ok(None);
+    }
+    match chars[start..*i].iter().collect::<String>().parse::<isize>() {
+        Ok(n) => Ok(Some(n as usize)),
+        Err(_) => Err(PyException::new(PyError::ValueError, too_big)),
+    }
+}
+
+// %[(key)][flags][width][.precision][length]type
+pub fn parse_percent(template: &str) -> Result<Vec<PercentPart>, PyException> {
+    let chars: Vec<char> = template.chars().collect();
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '%' {
+            text.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        let mut key = None;
+        if chars.get(i) == Some(&'(') {
+            let start = i + 1;
+            let mut depth = 1;
+            while depth > 0 {
+                i += 1;
+                match chars.get(i) {
+                    None => return Err(PyException::new(PyError::ValueError, "incomplete format key")),
+                    Some('(') => depth += 1,
+                    Some(')') => depth -= 1,
+                    _ => {}
+                }
+            }
+            key = Some(chars[start..i].iter().collect());
+            i += 1;
+        }
+        let mut spec = FormatSpec::default();
+        while let Some(&flag @ ('-' | '+' | ' ' | '#' | '0')) = chars.get(i) {
+            match flag {
+                '-' => spec.align = Some('<'),
+                '+' => spec.sign = Some('+'),
+                ' ' => spec.sign = spec.sign.or(Some(' ')),
+                '#' => spec.alternate = true,
+                _ => spec.zero_pad = true,
+            }
+            i += 1;
+        }
+        // left aligned numbers are padded with spaces
+        spec.zero_pad &= spec.align.is_none();
+        let star_width = chars.get(i) == Some(&'*');
+        match star_width {
+            true => i += 1,
+            false => spec.width = read_digits(&chars, &mut i, "width too big")?.unwrap_or(0),
+        }
+        let mut star_precision = false;
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            star_precision = chars.get(i) == Some(&'*');
+            match star_precision {
+                true => i += 1,
+                false => spec.precision = Some(read_digits(&chars, &mut i, "precision too big")?.unwrap_or(0)),
+            }
+        }
+        while matches!(chars.get(i), Some('h' | 'l' | 'L')) {
+            i += 1;
+        }
+        match chars.get(i) {
+            None => return Err(PyException::new(PyError::ValueError, "incomplete format")),
+            Some('%') => text.push('%'),
+            Some(&kind) if "diouxXeEfFgGcrsa".contains(kind) => {
+                if !text.is_empty() {
+                    parts.push(PercentPart::Text(std::mem::take(&mut text)));
+                }
+                spec.kind = Some(kind);
+                parts.push(PercentPart::Conversion {
+                    key,
+                    spec,
+                    star_width,
+                    star_precision,
+                });
+            }
+            Some(&c) => {
+                return Err(PyException::new(PyError::ValueError, format!(
+                    "unsupported format character '{c}' ({:#x}) at index {i}",
+                    c as u32
+                )))
+            }
+        }
+        i += 1;
+    }
+    if !text.is_empty() {
+        parts.push(PercentPart::Text(text));
+    }
+    Ok(parts)
+}
+
+// The %c, %d, %x and %f family, spec.kind is the conversion type
+pub fn percent_number(obj: &Obj, spec: &FormatSpec) -> Result<String, PyException> {
+    let kind = spec.kind.unwrap_or('d');
+    match kind {
+        'c' => {
+            let spec = FormatSpec {
+                sign: None,
+                alternate: false,
+                zero_pad: false,
+                precision: None,
+                align: Some(spec.align.unwrap_or('>')),
+                ..spec.clone()
+            };
+            match obj {
+                Obj::Str(s) if s.chars().count() == 1 => Ok(spec.pad(s, spec.align.unwrap())),
+                Obj::Str(s) => Err(PyException::type_error(format!(
+                    "%c requires an int or a unicode character, not a string of length {}",
+                    s.chars().count()
+                ))),
+                Obj::Int(int) => spec.format_int(int),
+                Obj::Bool(b) => spec.format_int(&Integer::from(*b)),
+                o => Err(PyException::type_error(format!(
+                    "%c requires an int or a unicode character, not {}",
+                    o.type_name()
+                ))),
+            }
+        }
+        'd' | 'i' | 'u' | 'o' | 'x' | 'X' => {
+            let decimal = matches!(kind, 'd' | 'i' | 'u');
+            let int = match obj {
+                Obj::Int(int) => int.clone(),
+                Obj::Bool(b) => Integer::from(*b),
+                Obj::Float(val) if decimal => Integer::from_f64(val.trunc()).ok_or_else(|| PyException {
+                    error: if val.is_nan() { PyError::ValueError } else { PyError::ArithmeticError },
+                    msg: format!("cannot convert float {} to integer", if val.is_nan() { "NaN" } else { "infinity" }),
+                })?,
+                Obj::Decimal(d) if decimal => d.to_integer()?,
+                o if decimal => {
+                    return Err(PyException::type_error(format!("%{kind} format: a real number is required, not {}", o.type_name())))
+                }
+                o => return Err(PyException::type_error(format!("%{kind} format: an integer is required, not {}", o.type_name()))),
+            };
+            let digits_spec = FormatSpec {
+                precision: None,
+                kind: Some(if decimal { 'd' } else { kind }),
+                ..spec.clone()
+            };
+            digits_spec.format_int_digits(&int, spec.precision.unwrap_or(0))
+        }
+        _ => {
+            let val = match obj {
+                Obj::Float(val) => *val,
+                Obj::Int(int) => int_to_f64(int)?,
+                Obj::Bool(b) => f64::from(u8::from(*b)),
+                // like CPython a Decimal goes through float
+                Obj::Decimal(d) => d.to_f64(),
+                o => return Err(PyException::type_error(format!("must be real number, not {}", o.type_name()))),
+            };
+            spec.format_float(val)
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{
+    pyrs_error::{PyError, PyException},
+    pyrs_native::NativeModule,
+    pyrs_obj::{rational_to_f64, Obj},
+    pyrs_parsing::Op,
+    pyrs_std::bind_native_args,
+    pyrs_utils as Utils,
+};
+use rug::{
+    ops::{DivRounding, Pow},
+    Integer, Rational,
+};
+use std::sync::Arc;
+
+pub const MODULE_NAME: &str = "fractions";
+pub const TYPE_NAME: &str = "fractions.Fraction";
+
+// the methods of Fraction the VM implements
+pub const METHODS: [&str; 3] = ["limit_denominator", "as_integer_ratio", "is_integer"];
+
+// fractions.Fraction, the one name the module has
+pub fn module() -> NativeModule {
+    NativeModule::new(MODULE_NAME).constant("Fraction", Obj::BuiltinType(TYPE_NAME))
+}
+
+fn zero_denominator(numer: &Integer) -> PyException {
+    PyException {
+        error: PyError::ZeroDivisionError,
+        msg: format!("Fraction({numer}, 0)"),
+    }
+}
+
+// the operands a Fraction does exact arithmetic with
+fn operand(obj: &Obj) -> Option<Rational> {
+    match obj {
+        Obj::Int(_) | Obj::Bool(_) | Obj::Fraction(_) => obj.to_rational(),
+        _ => None,
+    }
+}
+
+// [sign]n[/d] or [sign]digits[.digits][e[sign]digits], whitespace around
+// it and underscores between digits allowed
+fn parse(s: &str) -> Result<Rational, PyException> {
+    let invalid = || PyException {
+        error: PyError::ValueError,
+        msg: format!("Invalid literal for Fraction: '{s}'"),
+    };
+    let digits = |part: &str| {
+        let digits = Utils::strip_underscores(part, 10).ok_or_else(invalid)?;
+        match !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+            true => Ok(Integer::from_str_radix(&digits, 10).unwrap()),
+            false => Err(invalid()),
+        }
+    };
+    let trimmed = s.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let val = match unsigned.split_once('/') {
+        Some((numer, denom)) => {
+            let (numer, denom) = (digits(numer)?, digits(denom)?);
+            if denom == 0 {
+                return Err(zero_denominator(&numer));
+            }
+            Rational::from((numer, denom))
+        }
+        None => {
+            let (mantissa, exp) = match unsigned.split_once(['e', 'E']) {
+                Some((mantissa, exp)) => (mantissa, Some(exp)),
+                None => (unsigned, None),
+            };
+            let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+            if int_part.is_empty() && frac_part.is_empty() {
+                return Err(invalid());
+            }
+            let int_part = if int_part.is_empty() { Integer::ZERO } else { digits(int_part)? };
+            let frac_digits = Utils::strip_underscores(frac_part, 10).ok_or_else(invalid)?.len();
+            let frac = if frac_part.is_empty() { Integer::ZERO } else { digits(frac_part)? };
+            let exp = match exp {
+                None => 0,
+                Some(exp) => {
+                    let (sign, unsigned) = match exp.strip_prefix('-') {
+                        Some(rest) => (-1, rest),
+                        None => (1, exp.strip_prefix('+').unwrap_or(exp)),
+                    };
+                    sign * digits(unsigned)?.to_i64().ok_or_else(invalid)?
+                }
+            } - frac_digits as i64;
+            let coef = int_part * Integer::from(10).pow(frac_digits as u32) + frac;
+            let scale = Integer::from(10).pow(exp.unsigned_abs() as u32);
+            match exp >= 0 {
+                true => Rational::from(coef * scale),
+                false => Rational::from((coef, scale)),
+            }
+        }
+    };
+    Ok(if negative { -val } else { val })
+}
+
+// Fraction(numerator=0, denominator=None)
+pub fn new(args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Obj, PyException> {
+    let params = bind_native_args("Fraction", &["numerator", "denominator"], 2, args, kwargs)?;
+    let val = match (params[0].as_deref(), params[1].as_deref()) {
+        (None, _) => Rational::new(),
+        (Some(numer), None | Some(Obj::None)) => match numer {
+            Obj::Str(s) => parse(s)?,
+            Obj::Float(_) | Obj::Decimal(_) => match numer.to_rational() {
+                Some(val) => val,
+                None if numer.to_float().is_ok_and(f64::is_nan) => {
+                    return Err(PyException {
+                        error: PyError::ValueError,
+                        msg: "cannot convert NaN to integer ratio".to_string(),
+                    })
+                }
+                None => {
+                    return Err(PyException {
+                        error: PyError::OverflowError,
+                        msg: "cannot convert Infinity to integer ratio".to_string(),
+                    })
+                }
+            },
+            o => operand(o).ok_or_else(|| PyException::type_error("argument should be a string or a Rational instance"))?,
+        },
+        (Some(numer), Some(denom)) => match (operand(numer), operand(denom)) {
+            (Some(_), Some(denom)) if denom == 0 => {
+                let numer = operand(numer).unwrap() * Rational::from(denom.denom());
+                return Err(zero_denominator(&numer.into_numer_denom().0));
+            }
+            (Some(numer), Some(denom)) => numer / denom,
+            _ => return Err(PyException::type_error("both arguments should be Rational instances")),
+        },
+    };
+    Ok(Obj::Fraction(val))
+}
+
+// Arithmetic with a Fraction on one side. Ints and Fractions stay exact,
+// floats and complexes turn the Fraction into a float
+pub fn binary_op(lhs: &Obj, rhs: &Obj, op: Op) -> Result<Obj, PyException> {
+    let symbol = match op {
+        Op::Power => "** or pow()".to_string(),
+        op => op.to_string(),
+    };
+    if matches!(lhs, Obj::Float(_) | Obj::Complex(_)) || matches!(rhs, Obj::Float(_) | Obj::Complex(_)) {
+        let inexact = |obj: &Obj| match obj {
+            Obj::Fraction(r) => Obj::Float(rational_to_f64(r)),
+            o => o.clone(),
+        };
+        return Obj::arith(&inexact(lhs), &inexact(rhs), op);
+    }
+    let (Some(a), Some(b)) = (operand(lhs), operand(rhs)) else {
+        return Err(Obj::unsupported(lhs, rhs, &symbol));
+    };
+    let zero_div = |msg: &str| PyException {
+        error: PyError::ZeroDivisionError,
+        msg: msg.to_string(),
+    };
+    let val = match op {
+        Op::Plus => a + b,
+        Op::Minus => a - b,
+        Op::Asterisk => a * b,
+        Op::ForwardSlash if b == 0 => return Err(zero_denominator(&(a.numer().clone() * b.denom()))),
+        Op::ForwardSlash => a / b,
+        Op::DoubleSlash if b == 0 => return Err(zero_div("integer division or modulo by zero")),
+        Op::DoubleSlash => return Ok(Obj::Int((a / b).floor().into_numer_denom().0)),
+        Op::Percent if b == 0 => return Err(zero_div("integer modulo by zero")),
+        Op::Percent => {
+            let floor = Rational::from(&a / &b).floor();
+            a - b * floor
+        }
+        Op::Power => return power(lhs, a, b),
+        _ => return Err(Obj::unsupported(lhs, rhs, &symbol)),
+    };
+    Ok(Obj::Fraction(val))
+}
+
+// An integral exponent keeps the power exact, int ** Fraction(n) is an
+// int when n isn't negative. Any other exponent goes through floats
+fn power(lhs: &Obj, base: Rational, exp: Rational) -> Result<Obj, PyException> {
+    if *exp.denom() != 1 {
+        return Obj::arith(&Obj::Float(rational_to_f64(&base)), &Obj::Float(rational_to_f64(&exp)), Op::Power);
+    }
+    let n = exp.into_numer_denom().0;
+    let Some(abs_n) = n.clone().abs().to_u32() else {
+        return Err(PyException {
+            error: PyError::OverflowError,
+            msg: "exponent too large".to_string(),
+        });
+    };
+    match (lhs, n < 0) {
+        (Obj::Int(_) | Obj::Bool(_), false) => Ok(Obj::Int(base.into_numer_denom().0.pow(abs_n))),
+        (_, false) => Ok(Obj::Fraction(base.pow(abs_n))),
+        (_, true) if base == 0 => Err(zero_denominator(&Integer::from(1))),
+        (_, true) => Ok(Obj::Fraction(base.recip().pow(abs_n))),
+    }
+}
+
+pub fn get_attr(val: &Rational, name: &str) -> Option<Obj> {
+    match name {
+        "numerator" => Some(Obj::Int(val.numer().clone())),
+        "denominator" => Some(Obj::Int(val.denom().clone())),
+        _ => None,
+    }
+}
+
+// The closest Fraction with a denominator of at most max_denominator,
+// the best of the last two convergents of the continued fraction
+fn limit_denominator(val: &Rational, max_denominator: &Integer) -> Result<Rational, PyException> {
+    if *max_denominator < 1 {
+        return Err(PyException {
+            error: PyError::ValueError,
+            msg: "max_denominator should be at least 1".to_string(),
+        });
+    }
+    if val.denom() <= max_denominator {
+        return Ok(val.clone());
+    }
+    let (mut p0, mut q0, mut p1, mut q1) = (Integer::ZERO, Integer::from(1), Integer::from(1), Integer::ZERO);
+    let (mut n, mut d) = (val.numer().clone(), val.denom().clone());
+    loop {
+        let a = n.clone().div_floor(&d);
+        let q2 = Integer::from(&q0 + &a * &q1);
+        if q2 > *max_denominator {
+            break;
+        }
+        let p2 = Integer::from(&p0 + &a * &p1);
+        (p0, q0, p1, q1) = (p1, q1, p2, q2);
+        let rem = Integer::from(&n - &a * &d);
+        (n, d) = (d, rem);
+    }
+    let k = Integer::from(max_denominator - &q0) / &q1;
+    let bound1 = Rational::from((p0 + &k * &p1, q0 + k * &q1));
+    let bound2 = Rational::from((p1, q1));
+    let dist = |bound: &Rational| Rational::from(bound - val).abs();
+    Ok(match dist(&bound2) <= dist(&bound1) {
+        true => bound2,
+        false => bound1,
+    })
+}
+
+pub fn call_method(
+    val: &Rational,
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+) -> Result<Obj, PyException> {
+    let no_args = |ret: Obj| match args.is_empty() && kwargs.is_empty() {
+        true => Ok(ret),
+        false => Err(PyException::type_error(format!("{name}() takes no arguments ({} given)", args.len() + kwargs.len()))),
+    };
+    match name {
+        "limit_denominator" => {
+            let params = bind_native_args("limit_denominator", &["max_denominator"], 1, args, kwargs)?;
+            let max_denominator = match params[0].as_deref() {
+                None => Integer::from(1_000_000),
+                Some(o) => o.to_integer().ok_or(PyException::type_error(format!("'{}' object cannot be interpreted as an integer", o.type_name())))?,
+            };
+            Ok(Obj::Fraction(limit_denominator(val, &max_denominator)?))
+        }
+        "as_integer_ratio" => no_args(Obj::Tuple(vec![
+            Obj::Int(val.numer().clone()).into(),
+            Obj::Int(val.denom().clone()).into(),
+        ])),
+        "is_integer" => no_args(Obj::Bool(*val.denom() == 1)),
+        _ => Err(PyException {
+            error: PyError::AttributeError,
+            msg: format!("'Fraction' object has no attribute '{name}'"),
+        }),
+    }
+}
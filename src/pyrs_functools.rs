@@ -0,0 +1,169 @@
+use crate::{
+    pyrs_codeobject::UserFunc,
+    pyrs_error::PyException,
+    pyrs_native::{with_self, NativeClass, NativeObj},
+    pyrs_obj::Obj,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+pub const MODULE_NAME: &str = "functools";
+
+// the functools functions the VM implements, see PyVM::call_builtin_method
+pub const NAMES: [&str; 4] = ["wraps", "update_wrapper", "lru_cache", "cache"];
+
+// A function wrapped by lru_cache, results are kept by their arguments
+// and the least recently used one goes once there are maxsize of them
+#[derive(Debug)]
+pub struct LruCache {
+    pub func: Arc<Obj>,
+    pub maxsize: Option<usize>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    results: HashMap<Obj, Arc<Obj>>,
+    order: Vec<Obj>, // least recently used first
+    hits: usize,
+    misses: usize,
+}
+
+impl LruCache
+{
+    pub fn new(func: Arc<Obj>, maxsize: Option<usize>) -> Self {
+        LruCache {
+            func,
+            maxsize,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    // the key for a call, keyword args are kept apart from the positional ones
+    pub fn make_key(args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Obj {
+        let mut key = args.to_vec();
+        if !kwargs.is_empty() {
+            key.push(Obj::Null.into());
+            for (name, val) in kwargs {
+                key.push(Obj::Str(name.clone()).into());
+                key.push(val.clone());
+            }
+        }
+        Obj::Tuple(key)
+    }
+
+    pub fn get(&self, key: &Obj) -> Option<Arc<Obj>> {
+        let mut state = self.state.lock().unwrap();
+        match state.results.get(key).cloned() {
+            Some(val) => {
+                state.hits += 1;
+                if let Some(idx) = state.order.iter().position(|k| k == key) {
+                    let k = state.order.remove(idx);
+                    state.order.push(k);
+                }
+                Some(val)
+            }
+            None => {
+                state.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, key: Obj, val: Arc<Obj>) {
+        let mut state = self.state.lock().unwrap();
+        if self.maxsize == Some(0) || state.results.contains_key(&key) {
+            return;
+        }
+        if self.maxsize.is_some_and(|max| state.results.len() >= max) {
+            let oldest = state.order.remove(0);
+            state.results.remove(&oldest);
+        }
+        state.order.push(key.clone());
+        state.results.insert(key, val);
+    }
+
+    // CacheInfo(hits, misses, maxsize, currsize)
+    pub fn info(&self) -> Obj {
+        let state = self.state.lock().unwrap();
+        let maxsize = match self.maxsize {
+            Some(max) => Obj::Int(max.into()),
+            None => Obj::None,
+        };
+        let fields = Obj::Tuple(vec![
+            Obj::Int(state.hits.into()).into(),
+            Obj::Int(state.misses.into()).into(),
+            maxsize.into(),
+            Obj::Int(state.results.len().into()).into(),
+        ]);
+        NativeObj::wrap(cache_info(), Arc::new(fields))
+    }
+
+    pub fn clear(&self) {
+        *self.state.lock().unwrap() = CacheState::default();
+    }
+}
+
+const CACHE_INFO_FIELDS: [&str; 4] = ["hits", "misses", "maxsize", "currsize"];
+
+// What cache_info() returns, the tuple of the counts that also has them
+// as attributes like CPython's named tuple
+fn cache_info() -> &'static Arc<NativeClass> {
+    static CLASS: OnceLock<Arc<NativeClass>> = OnceLock::new();
+    CLASS.get_or_init(|| {
+        let mut class = NativeClass::new("CacheInfo")
+            .method("__repr__", |vm, args, _| {
+                let fields = fields(args)?;
+                let Obj::Tuple(items) = fields.as_ref() else {
+                    unreachable!("CacheInfo of a {}", fields.type_name())
+                };
+                let mut shown = vec![];
+                for (name, item) in CACHE_INFO_FIELDS.iter().zip(items) {
+                    shown.push(format!("{name}={}", vm.str_of(item, true)?));
+                }
+                Ok(Obj::Str(format!("CacheInfo({})", shown.join(", "))).into())
+            })
+            .method("__getitem__", |_, args, _| match args {
+                [_, key] => Obj::get_item(&fields(args)?, key),
+                _ => Err(PyException::type_error(format!("__getitem__() takes exactly one argument ({} given)", args.len() - 1))),
+            })
+            .method("__len__", |_, _, _| Ok(Obj::Int(CACHE_INFO_FIELDS.len().into()).into()))
+            .method("__iter__", |_, args, _| Ok(Obj::Iter(fields(args)?.iter_py().unwrap()).into()))
+            .attr("hits", |_, args, _| field(args, 0))
+            .attr("misses", |_, args, _| field(args, 1))
+            .attr("maxsize", |_, args, _| field(args, 2))
+            .attr("currsize", |_, args, _| field(args, 3));
+        class.module = MODULE_NAME.to_string();
+        Arc::new(class)
+    })
+}
+
+// the tuple a CacheInfo holds
+fn fields(args: &[Arc<Obj>]) -> Result<Arc<Obj>, PyException> {
+    with_self(args, |fields: &mut Arc<Obj>| fields.clone())
+}
+
+fn field(args: &[Arc<Obj>], idx: usize) -> Result<Arc<Obj>, PyException> {
+    Obj::get_item(&fields(args)?, &Obj::Int(idx.into()))
+}
+
+// Makes the wrapper look like the function it wraps: copies its name and
+// docstring and points __wrapped__ at it. Only functions have the attributes
+pub fn update_wrapper(wrapper: &Arc<Obj>, wrapped: &Arc<Obj>) -> Arc<Obj> {
+    let Obj::UserFunc(func) = wrapper.as_ref() else {
+        return wrapper.clone();
+    };
+    let mut attrs = func.attrs.clone();
+    if let Obj::UserFunc(inner) = wrapped.as_ref() {
+        attrs.extend(inner.attrs.clone());
+        let name: Arc<Obj> = Obj::Str(inner.name().to_string()).into();
+        attrs.insert("__name__".to_string(), name.clone());
+        attrs.insert("__qualname__".to_string(), name);
+        let doc = inner.attrs.get("__doc__").cloned().unwrap_or(Obj::None.into());
+        attrs.insert("__doc__".to_string(), doc);
+    }
+    attrs.insert("__wrapped__".to_string(), wrapped.clone());
+    Obj::UserFunc(Arc::new(UserFunc { attrs, ..func.as_ref().clone() })).into()
+}
@@ -0,0 +1,62 @@
+use crate::{
+    pyrs_codeobject::CodeObject,
+    pyrs_import::PyModule,
+    pyrs_obj::Obj,
+    pyrs_vm::WithBlock,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorState {
+    Created,
+    Suspended,
+    Running,
+    Finished,
+}
+
+// What a generator did when it was resumed
+#[derive(Debug, Clone)]
+pub enum GeneratorStep {
+    Yielded(Arc<Obj>),
+    Returned(Arc<Obj>),
+}
+
+// A suspended frame: the code, where to carry on from, and the stack,
+// locals and with blocks that were live at the last yield
+#[derive(Debug)]
+pub struct Generator {
+    pub code: Arc<CodeObject>,
+    pub ip: usize,
+    pub stack: Vec<Arc<Obj>>,
+    pub locals: HashMap<String, Arc<Obj>>,
+    pub globals: Option<Arc<PyModule>>,
+    pub(crate) with_blocks: Vec<WithBlock>,
+    pub state: GeneratorState,
+}
+
+pub type GeneratorRef = Arc<Mutex<Generator>>;
+
+impl Generator {
+    pub fn new(
+        code: Arc<CodeObject>,
+        locals: HashMap<String, Arc<Obj>>,
+        globals: Option<Arc<PyModule>>,
+    ) -> GeneratorRef {
+        Arc::new(Mutex::new(Generator {
+            code,
+            ip: 0,
+            stack: vec![],
+            locals,
+            globals,
+            with_blocks: vec![],
+            state: GeneratorState::Created,
+        }))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.code.co_name
+    }
+}
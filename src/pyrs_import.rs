@@ -0,0 +1,421 @@
+use crate::{
+    pyrs_builtins as builtins,
+    pyrs_contextlib as contextlib,
+    pyrs_decimal as decimal,
+    pyrs_error::{PyError, PyException},
+    pyrs_fractions as fractions,
+    pyrs_functools as functools,
+    pyrs_embed::{InputSource, OutputSink},
+    pyrs_interpreter::Interpreter,
+    pyrs_io as io,
+    pyrs_native::NativeModule,
+    pyrs_obj::{Obj, PyObj},
+    pyrs_vm::PyVM,
+};
+#[cfg(feature = "cmath")]
+use crate::pyrs_cmath as cmath;
+#[cfg(feature = "math")]
+use crate::pyrs_math as math;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+pub const SYS_MODULE_NAME: &str = "sys";
+
+// the modules written in rust, importing them doesn't look for a file
+const NATIVE_MODULES: &[&str] = &[
+    builtins::MODULE_NAME,
+    functools::MODULE_NAME,
+    SYS_MODULE_NAME,
+];
+
+// A module loaded from a .py file. Its attributes are the globals of the
+// code in it, the functions it defines keep hold of it to look names up
+pub struct PyModule {
+    pub name: String,
+    pub attrs: Mutex<HashMap<String, Arc<Obj>>>,
+}
+
+// sys.modules and sys.path, kept as the dict and list python sees so
+// sys.path[0] = d stores the changed list back through PyVM::set_attr
+#[derive(Debug, Clone)]
+pub struct ImportState {
+    pub modules: Arc<Obj>,
+    pub path: Arc<Obj>,
+    natives: HashMap<String, Arc<NativeModule>>, // see PyVM::register_module
+
+    // sys.stdin, sys.stdout and sys.stderr, then the __stdin__, __stdout__
+    // and __stderr__ they started as
+    streams: [Arc<Obj>; 3],
+    std_streams: [Arc<Obj>; 3],
+}
+
+const STREAM_NAMES: [&str; 3] = ["stdin", "stdout", "stderr"];
+
+fn stream_index(name: &str) -> Option<usize> {
+    STREAM_NAMES.iter().position(|n| *n == name)
+}
+
+// the attrs hold the module's own functions, which hold the module
+impl std::fmt::Debug for PyModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PyModule({})", self.name)
+    }
+}
+
+impl PartialEq for PyModule {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl PyModule
+{
+    pub fn new(name: &str, attrs: HashMap<String, Arc<Obj>>) -> Self {
+        PyModule {
+            name: name.to_string(),
+            attrs: Mutex::new(attrs),
+        }
+    }
+
+    pub fn get_attr(&self, name: &str) -> Option<Arc<Obj>> {
+        self.attrs.lock().unwrap().get(name).cloned()
+    }
+
+    pub fn set_attr(&self, name: String, val: Arc<Obj>) {
+        self.attrs.lock().unwrap().insert(name, val);
+    }
+
+    pub fn remove_attr(&self, name: &str) -> Option<Arc<Obj>> {
+        self.attrs.lock().unwrap().remove(name)
+    }
+
+    pub fn file(&self) -> Option<String> {
+        self.get_attr("__file__").map(|f| f.__str__())
+    }
+}
+
+// sys.path starts with the current directory, then PYTHONPATH
+impl Default for ImportState
+{
+    fn default() -> Self {
+        let mut path = vec![Obj::Str(String::new()).into()];
+        if let Some(dirs) = std::env::var_os("PYTHONPATH") {
+            for dir in std::env::split_paths(&dirs) {
+                path.push(Obj::Str(dir.to_string_lossy().to_string()).into());
+            }
+        }
+        let streams = [
+            io::reader("<stdin>", InputSource::Stdin),
+            io::writer("<stdout>", OutputSink::Stdout),
+            io::writer("<stderr>", OutputSink::Stderr),
+        ];
+        ImportState {
+            modules: Obj::new_dict().into(),
+            path: Obj::new_list(path).into(),
+            natives: [
+                contextlib::module(),
+                #[cfg(feature = "math")]
+                math::module(),
+                #[cfg(feature = "cmath")]
+                cmath::module(),
+                fractions::module(),
+                decimal::module(),
+            ]
+            .into_iter()
+            .map(|module| (module.name.clone(), Arc::new(module)))
+            .collect(),
+            streams: streams.clone(),
+            std_streams: streams,
+        }
+    }
+}
+
+impl ImportState
+{
+    pub fn get(&self, name: &str) -> Option<Arc<Obj>> {
+        match self.modules.as_ref() {
+            Obj::Dict(map) => map.lock().unwrap().get(&Obj::Str(name.to_string())).cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, module: Arc<Obj>) {
+        if let Obj::Dict(map) = self.modules.as_ref() {
+            map.lock().unwrap().insert(Obj::Str(name.to_string()), module);
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        if let Obj::Dict(map) = self.modules.as_ref() {
+            map.lock().unwrap().remove(&Obj::Str(name.to_string()));
+        }
+    }
+
+    pub fn register(&mut self, module: NativeModule) {
+        self.natives.insert(module.name.clone(), Arc::new(module));
+    }
+
+    // a script is run with its own directory first on the path
+    pub fn set_script_dir(&mut self, dir: &Path) {
+        if let Obj::List(path) = self.path.as_ref() {
+            let dir = Obj::Str(dir.to_string_lossy().to_string()).into();
+            let mut path = path.lock().unwrap();
+            match path.first_mut() {
+                Some(first) => *first = dir,
+                None => path.push(dir),
+            }
+        }
+    }
+
+    // replaces sys.stdin, sys.stdout or sys.stderr and the stream it started as
+    pub fn set_std_stream(&mut self, name: &str, stream: Arc<Obj>) {
+        if let Some(i) = stream_index(name) {
+            self.streams[i] = stream.clone();
+            self.std_streams[i] = stream;
+        }
+    }
+
+    pub fn sys_attr(&self, name: &str) -> Option<Arc<Obj>> {
+        match name {
+            "path" => Some(self.path.clone()),
+            "modules" => Some(self.modules.clone()),
+            _ => match stream_index(name) {
+                Some(i) => Some(self.streams[i].clone()),
+                None => Some(self.std_streams[stream_index(name.strip_prefix("__")?.strip_suffix("__")?)?].clone()),
+            },
+        }
+    }
+
+    pub fn set_sys_attr(&mut self, name: &str, val: Arc<Obj>) -> Result<(), PyException> {
+        // anything can stand in for a stream, print only needs its write method
+        if let Some(i) = stream_index(name) {
+            self.streams[i] = val;
+            return Ok(());
+        }
+        match (name, val.as_ref()) {
+            ("path", Obj::List(_)) => self.path = val,
+            ("modules", Obj::Dict(_)) => self.modules = val,
+            ("path" | "modules", o) => {
+                let expected = if name == "path" { "list" } else { "dict" };
+                return Err(PyException::type_error(format!("sys.{name} must be a {expected}, not '{}'", o.type_name())));
+            }
+            _ => {
+                return Err(PyException {
+                    error: PyError::AttributeError,
+                    msg: format!("module 'sys' has no attribute '{name}'"),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    // the str entries of sys.path, '' is the current directory
+    fn search_path(&self) -> Vec<PathBuf> {
+        dirs_of(&self.path)
+    }
+}
+
+fn dirs_of(list: &Obj) -> Vec<PathBuf> {
+    match list {
+        Obj::List(dirs) => dirs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|d| match d.as_ref() {
+                Obj::Str(dir) => Some(PathBuf::from(dir)),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// a package directory with an __init__.py comes before a module of the same name
+fn find_source(dirs: &[PathBuf], name: &str) -> Option<(PathBuf, bool)> {
+    dirs.iter().find_map(|dir| {
+        let init = dir.join(name).join("__init__.py");
+        if init.is_file() {
+            return Some((init, true));
+        }
+        let file = dir.join(format!("{name}.py"));
+        file.is_file().then_some((file, false))
+    })
+}
+
+// what from m import * takes from the rust modules
+fn native_all(module: &str) -> Vec<&'static str> {
+    match module {
+        functools::MODULE_NAME => functools::NAMES.to_vec(),
+        _ => vec![],
+    }
+}
+
+fn import_err(msg: String) -> PyException {
+    PyException {
+        error: PyError::ImportError,
+        msg,
+    }
+}
+
+fn not_found(msg: String) -> PyException {
+    PyException {
+        error: PyError::ModuleNotFoundError,
+        msg,
+    }
+}
+
+impl PyVM
+{
+    // import a.b.c imports each package along the way and sets the
+    // submodule on its parent. Without a fromlist it's the top package
+    // that gets bound, with one it's the module itself
+    pub(crate) fn import(&mut self, name: &str, level: usize, fromlist: &[String]) -> Result<Arc<Obj>, PyException> {
+        let full_name = self.resolve_name(name, level)?;
+
+        let mut top = None;
+        let mut module: Option<Arc<Obj>> = None;
+        let mut prefix = String::new();
+        for part in full_name.split('.') {
+            if !prefix.is_empty() {
+                prefix.push('.');
+            }
+            prefix.push_str(part);
+            let parent = module.take();
+            let m = self.import_one(&prefix, parent.as_ref())?;
+            if let Some(Obj::PyModule(parent)) = parent.as_deref() {
+                parent.set_attr(part.to_string(), m.clone());
+            }
+            top.get_or_insert(m.clone());
+            module = Some(m);
+        }
+        let module = module.unwrap();
+        if fromlist.is_empty() {
+            return Ok(top.unwrap());
+        }
+
+        // from pkg import sub imports pkg.sub when pkg has no sub yet
+        if let Obj::PyModule(pkg) = module.as_ref() {
+            if pkg.get_attr("__path__").is_some() {
+                for name in fromlist.iter().filter(|n| *n != "*" && pkg.get_attr(n).is_none()) {
+                    let sub_name = format!("{full_name}.{name}");
+                    match self.import_one(&sub_name, Some(&module)) {
+                        Ok(sub) => pkg.set_attr(name.clone(), sub),
+                        // ImportFrom says what's missing
+                        Err(e) if e.msg == format!("No module named '{sub_name}'") => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+            }
+        }
+        Ok(module)
+    }
+
+    // ..a from inside package p.q.r is p.q.a
+    fn resolve_name(&self, name: &str, level: usize) -> Result<String, PyException> {
+        if level == 0 {
+            return Ok(name.to_string());
+        }
+        let package = match self.lookup_global("__package__").as_deref() {
+            Some(Obj::Str(package)) if !package.is_empty() => package.clone(),
+            _ => return Err(import_err("attempted relative import with no known parent package".to_string())),
+        };
+        let parts: Vec<&str> = package.split('.').collect();
+        if level > parts.len() {
+            return Err(import_err("attempted relative import beyond top-level package".to_string()));
+        }
+        let base = parts[..parts.len() + 1 - level].join(".");
+        Ok(match name {
+            "" => base,
+            name => format!("{base}.{name}"),
+        })
+    }
+
+    // one module of a dotted name, from sys.modules if it's been imported before
+    fn import_one(&mut self, name: &str, parent: Option<&Arc<Obj>>) -> Result<Arc<Obj>, PyException> {
+        if let Some(module) = self.import_state().get(name) {
+            return Ok(module);
+        }
+        if parent.is_none() && NATIVE_MODULES.contains(&name) {
+            let module: Arc<Obj> = Obj::Module(name.to_string()).into();
+            self.import_state().insert(name, module.clone());
+            return Ok(module);
+        }
+        if let Some(native) = self.import_state().natives.get(name).cloned() {
+            let module: Arc<Obj> = Obj::PyModule(Arc::new(native.to_module())).into();
+            self.import_state().insert(name, module.clone());
+            return Ok(module);
+        }
+
+        let dirs = match parent.map(|p| p.as_ref()) {
+            None => self.import_state().search_path(),
+            Some(Obj::PyModule(pkg)) if pkg.get_attr("__path__").is_some() => dirs_of(&pkg.get_attr("__path__").unwrap()),
+            Some(pkg) => {
+                return Err(not_found(format!("No module named '{name}'; '{}' is not a package", pkg.func_name())))
+            }
+        };
+        let short_name = name.rsplit('.').next().unwrap();
+        match find_source(&dirs, short_name) {
+            Some((file, is_package)) => self.load_source(name, &file, is_package),
+            None => Err(not_found(format!("No module named '{name}'"))),
+        }
+    }
+
+    // Runs the file's code with a new module as its globals. The module
+    // is in sys.modules while it runs so a circular import gets it half made
+    fn load_source(&mut self, name: &str, file: &Path, is_package: bool) -> Result<Arc<Obj>, PyException> {
+        let str_obj = |s: &str| -> Arc<Obj> { Obj::Str(s.to_string()).into() };
+        let package = match is_package {
+            true => name,
+            false => name.rsplit_once('.').map_or("", |(package, _)| package),
+        };
+        let mut attrs = HashMap::new();
+        attrs.insert("__name__".to_string(), str_obj(name));
+        attrs.insert("__file__".to_string(), str_obj(&file.to_string_lossy()));
+        attrs.insert("__package__".to_string(), str_obj(package));
+        if is_package {
+            let dir = file.parent().unwrap_or(Path::new(""));
+            attrs.insert("__path__".to_string(), Obj::new_list(vec![str_obj(&dir.to_string_lossy())]).into());
+        }
+
+        let code = Interpreter::compile_file(&file.to_string_lossy())?;
+        let module = Arc::new(PyModule::new(name, attrs));
+        let obj: Arc<Obj> = Obj::PyModule(module.clone()).into();
+        self.import_state().insert(name, obj.clone());
+
+        if let Err(e) = self.exec_module(code, &module) {
+            self.import_state().remove(name);
+            return Err(e);
+        }
+        // the module may have put something else in its place
+        Ok(self.import_state().get(name).unwrap_or(obj))
+    }
+
+    // from m import *, the names in __all__ or else the ones without a leading underscore
+    pub(crate) fn public_names(&mut self, module: &Arc<Obj>) -> Result<Vec<(String, Arc<Obj>)>, PyException> {
+        let names: Vec<String> = match module.as_ref() {
+            Obj::PyModule(m) => match m.get_attr("__all__") {
+                Some(all) => self.iter_items(&all)?.iter().map(|n| n.__str__()).collect(),
+                None => {
+                    let mut names: Vec<String> =
+                        m.attrs.lock().unwrap().keys().filter(|k| !k.starts_with('_')).cloned().collect();
+                    names.sort();
+                    names
+                }
+            },
+            Obj::Module(name) => native_all(name).into_iter().map(|n| n.to_string()).collect(),
+            o => return Err(import_err(format!("'{}' object is not a module", o.type_name()))),
+        };
+        let mut items = vec![];
+        for name in names {
+            let val = self.get_attr(module, &name).map_err(|_| PyException {
+                error: PyError::AttributeError,
+                msg: format!("module '{}' has no attribute '{name}'", module.func_name()),
+            })?;
+            items.push((name, val));
+        }
+        Ok(items)
+    }
+}
@@ -6,17 +6,15 @@ use std::{
 
 use crate::{
     pyrs_bytecode::PyBytecode,
-    pyrs_error::PyException,
+    pyrs_error::{PyError, PyException},
     pyrs_obj::{Obj, PyObj},
     pyrs_parsing::{Expression, Keyword},
-    pyrs_std::{FnPtr, Funcs},
     pyrs_utils::get_indent,
     pyrs_vm::PyVM,
 };
 
 pub struct Interpreter {
     variables: HashMap<String, Arc<Obj>>,
-    funcs: HashMap<String, FnPtr>,
     running: bool,
     curr_line: isize,
 
@@ -44,6 +42,7 @@ pub enum InterpreterFlags {
     Debug,
     AnyFile,
     Compile,
+    Optimize,
 }
 
 pub enum InterpreterCommand {
@@ -59,7 +58,6 @@ impl Interpreter {
         Interpreter {
             variables: HashMap::new(),
             running: true,
-            funcs: Funcs::get_std_map(),
             curr_line: -1,
             curr_indent: 0,
             //cache: Expression::None,
@@ -76,6 +74,10 @@ impl Interpreter {
         self.vm.set_debug_mode(debug);
     }
 
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.vm.set_optimize(optimize);
+    }
+
     pub fn get_version() -> &'static str {
         "pyrs-0-1"
     }
@@ -95,13 +97,15 @@ impl Interpreter {
                 Compiles the file
             -d, --debug
                 Runs in debug mode, this means it will print various things inc stack traces or parsed exprs
+            -O, --optimize
+                Skips assert statements, __debug__ is False
 
         "#;
         println!("{help}");
     }
 
     fn eval_expr(&mut self, expr: &Expression) -> Result<Arc<Obj>, PyException> {
-        expr.eval(&mut self.variables, &mut self.funcs)
+        expr.eval(&mut self.variables, &mut self.vm)
     }
 
     fn push_to_current_block(&mut self, expr: Expression) {
@@ -161,6 +165,7 @@ impl Interpreter {
                     "-a" | "--all" => flags.push(InterpreterFlags::AnyFile),
                     "-d" | "--debug" => flags.push(InterpreterFlags::Debug),
                     "-c" | "--compile" => flags.push(InterpreterFlags::Compile),
+                    "-O" | "--optimize" => flags.push(InterpreterFlags::Optimize),
                     "-h" | "--help" => commands.push(InterpreterCommand::PrintHelp),
                     a if a.contains('.') => {
                         let mut file_flags = vec![];
@@ -202,12 +207,15 @@ impl Interpreter {
             line = line_before;
         }
 
-        let expr = Expression::from_line(&line);
+        let expr = match Expression::from_line(line) {
+            Ok(expr) => expr,
+            Err(e) => return e.print(),
+        };
         if line.trim().ends_with(":") {
             if let Expression::Keyword(_, _, _) = expr {
                 self.start_block(line_indent + 4, expr);
             } else {
-                panic!("Only keywords can start blocks");
+                PyException::syntax_error("expected a statement that starts a block before ':'").print();
             }
         } else {
             if self.block_stack.is_empty() {
@@ -247,13 +255,19 @@ impl Interpreter {
                         }
                     }
                 },
-                _ => unimplemented!(),
+                _ => {
+                    return PyException {
+                        error: PyError::NotImplementedError,
+                        msg: format!("'{keyword}' blocks are not supported in the interactive mode"),
+                    }
+                    .print()
+                }
             },
             _ => {}
         }
 
         if let Some((var_name, lhs)) = expr.is_assign() {
-            let value = lhs.eval(&mut self.variables, &mut self.funcs);
+            let value = lhs.eval(&mut self.variables, &mut self.vm);
             match value {
                 Ok(val) => {
                     self.variables.insert(var_name.to_string(), val);
@@ -301,32 +315,47 @@ impl Interpreter {
         }
     }
 
-    pub fn interpret_file(&mut self, filepath: &str) {
-        let bytecode = Interpreter::compile_file(filepath);
-        self.vm.execute(bytecode);
+    pub fn interpret_file(&mut self, filepath: &str) -> Result<(), PyException> {
+        self.vm.set_script_path(filepath);
+        let bytecode = Interpreter::compile_file(filepath)?;
+        self.vm.execute(bytecode)
     }
 
     // vvvv using byte code vvvv
-    pub fn compile_file(filepath: &str) -> Vec<PyBytecode> {
+    pub fn compile_file(filepath: &str) -> Result<Vec<PyBytecode>, PyException> {
+        let contents = std::fs::read_to_string(filepath)
+            .map_err(|e| PyException::os_error(e, Some(filepath)))?;
+        Interpreter::compile_source(&contents)
+    }
+
+    pub fn compile_source(source: &str) -> Result<Vec<PyBytecode>, PyException> {
         let mut bytecode: Vec<PyBytecode> = vec![];
-        let contents = match std::fs::read_to_string(filepath) {
-            Ok(f) => f,
-            Err(e) => panic!("Fileread error: {e}"),
-        };
-        let parsed = Expression::from_multiline(contents.as_str());
+        let parsed = Expression::from_multiline(source)?;
         //dbg!(&parsed);
+        if !Expression::declared_names(&parsed, Keyword::Nonlocal).is_empty() {
+            return Err(PyException::syntax_error("nonlocal declaration not allowed at module level"));
+        }
+        Expression::check_declarations(&parsed)?;
         for expr in parsed {
-            PyBytecode::from_expr(expr, &mut bytecode);
+            PyBytecode::from_stmt(expr, &mut bytecode)?;
+        }
+        // a nonlocal no function bound on the way out
+        for inst in &bytecode {
+            if let PyBytecode::LoadConst(Obj::Code(code)) = inst {
+                if let Some(name) = code.co_nonlocals.first() {
+                    return Err(PyException::syntax_error(format!("no binding for nonlocal '{name}' found")));
+                }
+            }
         }
 
-        bytecode
+        Ok(bytecode)
     }
 
     #[allow(dead_code)]
-    fn execute_expr(&mut self, expr: Expression) {
+    fn execute_expr(&mut self, expr: Expression) -> Result<(), PyException> {
         let mut bytecode = vec![];
-        PyBytecode::from_expr(expr, &mut bytecode);
-        self.vm.execute(bytecode);
+        PyBytecode::from_expr(expr, &mut bytecode)?;
+        self.vm.execute(bytecode)
     }
 
     pub fn seralize_bytecode(filename: &str, bytecode: &Vec<PyBytecode>) -> std::io::Result<()> {
@@ -0,0 +1,87 @@
+use crate::{
+    pyrs_embed::{InputSource, OutputSink},
+    pyrs_error::{PyError, PyException},
+    pyrs_native::{with_self, NativeClass, NativeObj},
+    pyrs_obj::Obj,
+    pyrs_vm::PyVM,
+};
+use std::sync::{Arc, OnceLock};
+
+pub const MODULE_NAME: &str = "_io";
+
+// sys.stdin, sys.stdout and sys.stderr. Each reads or writes one of the
+// process's streams unless an embedder swapped a buffer in, a script
+// swaps them by assigning any object with a write or readline method
+#[derive(Debug)]
+enum Stream {
+    Reader(&'static str, InputSource),
+    Writer(&'static str, OutputSink),
+}
+
+fn text_io() -> &'static Arc<NativeClass> {
+    static CLASS: OnceLock<Arc<NativeClass>> = OnceLock::new();
+    CLASS.get_or_init(|| {
+        let mut class = NativeClass::new("TextIOWrapper")
+            .method("write", write)
+            .method("flush", |_, args, _| {
+                with_self(args, |stream: &mut Stream| {
+                    if let Stream::Writer(_, sink) = stream {
+                        sink.flush();
+                    }
+                    Obj::None.into()
+                })
+            })
+            .method("readline", |_, args, _| with_self(args, |stream: &mut Stream| read(stream, false))?)
+            .method("read", |_, args, _| with_self(args, |stream: &mut Stream| read(stream, true))?)
+            .method("readable", |_, args, _| with_self(args, |stream: &mut Stream| Obj::Bool(matches!(stream, Stream::Reader(..))).into()))
+            .method("writable", |_, args, _| with_self(args, |stream: &mut Stream| Obj::Bool(matches!(stream, Stream::Writer(..))).into()))
+            .method("__repr__", |_, args, _| {
+                with_self(args, |stream: &mut Stream| {
+                    let (name, mode) = match stream {
+                        Stream::Reader(name, _) => (name, 'r'),
+                        Stream::Writer(name, _) => (name, 'w'),
+                    };
+                    Obj::Str(format!("<_io.TextIOWrapper name='{name}' mode='{mode}' encoding='utf-8'>")).into()
+                })
+            });
+        class.module = MODULE_NAME.to_string();
+        Arc::new(class)
+    })
+}
+
+// write(s), how many characters were written
+fn write(_: &mut PyVM, args: &[Arc<Obj>], _: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let text = match args.get(1).map(|arg| arg.as_ref()) {
+        Some(Obj::Str(text)) if args.len() == 2 => text,
+        Some(o) if args.len() == 2 => {
+            return Err(PyException::type_error(format!("write() argument must be str, not {}", o.type_name())))
+        }
+        _ => {
+            return Err(PyException::type_error(format!("write() takes exactly one argument ({} given)", args.len().saturating_sub(1))))
+        }
+    };
+    with_self(args, |stream: &mut Stream| match stream {
+        Stream::Writer(_, sink) => {
+            sink.write(text);
+            Ok(Obj::Int(text.chars().count().into()).into())
+        }
+        Stream::Reader(..) => Err(PyException::new(PyError::OSError, "not writable")),
+    })?
+}
+
+// a line with its newline, or everything left, '' once the input is used up
+fn read(stream: &mut Stream, all: bool) -> Result<Arc<Obj>, PyException> {
+    match stream {
+        Stream::Reader(_, source) if all => source.read_to_end().map(|s| Obj::Str(s).into()),
+        Stream::Reader(_, source) => source.read_line().map(|s| Obj::Str(s).into()),
+        Stream::Writer(..) => Err(PyException::new(PyError::OSError, "not readable")),
+    }
+}
+
+pub fn reader(name: &'static str, source: InputSource) -> Arc<Obj> {
+    NativeObj::wrap(text_io(), Stream::Reader(name, source)).into()
+}
+
+pub fn writer(name: &'static str, sink: OutputSink) -> Arc<Obj> {
+    NativeObj::wrap(text_io(), Stream::Writer(name, sink)).into()
+}
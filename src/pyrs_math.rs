@@ -0,0 +1,603 @@
+use crate::{
+    pyrs_builtins::{int_arg, no_kwargs, one_arg},
+    pyrs_error::{PyError, PyException},
+    pyrs_native::NativeModule,
+    pyrs_obj::{int_to_f64, Obj, PyObj},
+    pyrs_std::bind_native_args,
+    pyrs_vm::PyVM,
+};
+use rug::{Float, Integer, Rational};
+use std::{f64::consts, sync::Arc};
+
+pub const MODULE_NAME: &str = "math";
+
+// math.sqrt and the rest, each its own native function
+pub fn module() -> NativeModule {
+    NativeModule::new(MODULE_NAME)
+        .function("acos", |_, args, kwargs| real_1("acos", args, kwargs, f64::acos, false))
+        .function("acosh", |_, args, kwargs| real_1("acosh", args, kwargs, f64::acosh, false))
+        .function("asin", |_, args, kwargs| real_1("asin", args, kwargs, f64::asin, false))
+        .function("asinh", |_, args, kwargs| real_1("asinh", args, kwargs, f64::asinh, false))
+        .function("atan", |_, args, kwargs| real_1("atan", args, kwargs, f64::atan, false))
+        .function("atan2", |_, args, kwargs| real_2("atan2", args, kwargs, f64::atan2))
+        .function("atanh", |_, args, kwargs| real_1("atanh", args, kwargs, f64::atanh, false))
+        .function("cbrt", |_, args, kwargs| real_1("cbrt", args, kwargs, f64::cbrt, false))
+        .function("ceil", |_, args, kwargs| int(to_int(one_arg("math.ceil", args, kwargs)?.as_ref(), Rational::ceil, f64::ceil)?))
+        .function("comb", |_, args, kwargs| {
+            let (n, k) = two_args("comb", args, kwargs)?;
+            int(comb("comb", n, k)?)
+        })
+        .function("copysign", |_, args, kwargs| real_2("copysign", args, kwargs, f64::copysign))
+        .function("cos", |_, args, kwargs| real_1("cos", args, kwargs, f64::cos, false))
+        .function("cosh", |_, args, kwargs| real_1("cosh", args, kwargs, f64::cosh, true))
+        .function("degrees", |_, args, kwargs| float(real_arg(one_arg("math.degrees", args, kwargs)?.as_ref())? * (180.0 / consts::PI)))
+        .function("dist", dist)
+        .function("erf", |_, args, kwargs| real_1("erf", args, kwargs, |x| Float::with_val(53, x).erf().to_f64(), false))
+        .function("erfc", |_, args, kwargs| real_1("erfc", args, kwargs, |x| Float::with_val(53, x).erfc().to_f64(), false))
+        .function("exp", |_, args, kwargs| real_1("exp", args, kwargs, f64::exp, true))
+        .function("exp2", |_, args, kwargs| real_1("exp2", args, kwargs, f64::exp2, true))
+        .function("expm1", |_, args, kwargs| real_1("expm1", args, kwargs, f64::exp_m1, true))
+        .function("fabs", |_, args, kwargs| float(real_arg(one_arg("math.fabs", args, kwargs)?.as_ref())?.abs()))
+        .function("factorial", |_, args, kwargs| int(factorial(one_arg("math.factorial", args, kwargs)?.as_ref())?))
+        .function("floor", |_, args, kwargs| int(to_int(one_arg("math.floor", args, kwargs)?.as_ref(), Rational::floor, f64::floor)?))
+        .function("fmod", |_, args, kwargs| real_2("fmod", args, kwargs, |x, y| x % y))
+        .function("frexp", |_, args, kwargs| {
+            let (mantissa, exp) = frexp(real_arg(one_arg("math.frexp", args, kwargs)?.as_ref())?);
+            Ok(Obj::Tuple(vec![Obj::Float(mantissa).into(), Obj::Int(exp.into()).into()]).into())
+        })
+        .function("fsum", |vm, args, kwargs| {
+            let items = vm.iter_items(&one_arg("math.fsum", args, kwargs)?)?;
+            let items = items.iter().map(|item| real_arg(item)).collect::<Result<Vec<_>, _>>()?;
+            float(fsum(&items)?)
+        })
+        .function("gamma", |_, args, kwargs| {
+            let x = pole_arg(one_arg("math.gamma", args, kwargs)?.as_ref())?;
+            float(math_1(x, |x| Float::with_val(53, x).gamma().to_f64(), true)?)
+        })
+        .function("gcd", |_, args, kwargs| int(fold_ints("gcd", args, kwargs, Integer::ZERO, |a, b| a.gcd(&b))?))
+        .function("hypot", |_, args, kwargs| {
+            no_kwargs("hypot", kwargs)?;
+            let coords = args.iter().map(|arg| real_arg(arg)).collect::<Result<Vec<_>, _>>()?;
+            float(hypot(&coords))
+        })
+        .function("isclose", |_, args, kwargs| Ok(Obj::Bool(isclose(args, kwargs)?).into()))
+        .function("isfinite", |_, args, kwargs| Ok(Obj::Bool(real_arg(one_arg("math.isfinite", args, kwargs)?.as_ref())?.is_finite()).into()))
+        .function("isinf", |_, args, kwargs| Ok(Obj::Bool(real_arg(one_arg("math.isinf", args, kwargs)?.as_ref())?.is_infinite()).into()))
+        .function("isnan", |_, args, kwargs| Ok(Obj::Bool(real_arg(one_arg("math.isnan", args, kwargs)?.as_ref())?.is_nan()).into()))
+        .function("isqrt", |_, args, kwargs| {
+            let n = int_arg(one_arg("math.isqrt", args, kwargs)?.as_ref())?;
+            if n < 0 {
+                return Err(PyException::new(PyError::ValueError, "isqrt() argument must be nonnegative"));
+            }
+            int(n.sqrt())
+        })
+        .function("lcm", |_, args, kwargs| int(fold_ints("lcm", args, kwargs, Integer::from(1), |a, b| a.lcm(&b))?))
+        .function("ldexp", |_, args, kwargs| {
+            let (x, exp) = two_args("ldexp", args, kwargs)?;
+            let exp = match exp.as_ref() {
+                Obj::Int(_) | Obj::Bool(_) => int_arg(exp)?,
+                _ => return Err(PyException::type_error("Expected an int as second argument to ldexp.")),
+            };
+            float(ldexp(real_arg(x)?, &exp)?)
+        })
+        .function("lgamma", |_, args, kwargs| {
+            let x = pole_arg(one_arg("math.lgamma", args, kwargs)?.as_ref())?;
+            float(math_1(x, |x| Float::with_val(53, x).ln_abs_gamma().0.to_f64(), true)?)
+        })
+        .function("log", log)
+        .function("log10", |_, args, kwargs| float(log_with(one_arg("math.log10", args, kwargs)?.as_ref(), f64::log10)?))
+        .function("log1p", |_, args, kwargs| real_1("log1p", args, kwargs, f64::ln_1p, false))
+        .function("log2", |_, args, kwargs| float(log_with(one_arg("math.log2", args, kwargs)?.as_ref(), f64::log2)?))
+        .function("modf", |_, args, kwargs| {
+            let x = real_arg(one_arg("math.modf", args, kwargs)?.as_ref())?;
+            let frac = if x.is_infinite() { 0.0f64.copysign(x) } else { x.fract() };
+            Ok(Obj::Tuple(vec![Obj::Float(frac).into(), Obj::Float(x.trunc()).into()]).into())
+        })
+        .function("nextafter", |_, args, kwargs| real_2("nextafter", args, kwargs, nextafter))
+        .function("perm", perm)
+        .function("pow", |_, args, kwargs| {
+            let (x, y) = two_args("pow", args, kwargs)?;
+            float(pow(real_arg(x)?, real_arg(y)?)?)
+        })
+        .function("prod", prod)
+        .function("radians", |_, args, kwargs| float(real_arg(one_arg("math.radians", args, kwargs)?.as_ref())? * (consts::PI / 180.0)))
+        .function("remainder", |_, args, kwargs| real_2("remainder", args, kwargs, remainder))
+        .function("sin", |_, args, kwargs| real_1("sin", args, kwargs, f64::sin, false))
+        .function("sinh", |_, args, kwargs| real_1("sinh", args, kwargs, f64::sinh, true))
+        .function("sqrt", |_, args, kwargs| real_1("sqrt", args, kwargs, f64::sqrt, false))
+        .function("tan", |_, args, kwargs| real_1("tan", args, kwargs, f64::tan, false))
+        .function("tanh", |_, args, kwargs| real_1("tanh", args, kwargs, f64::tanh, false))
+        .function("trunc", |_, args, kwargs| int(to_int(one_arg("math.trunc", args, kwargs)?.as_ref(), Rational::trunc, f64::trunc)?))
+        .function("ulp", |_, args, kwargs| float(ulp(real_arg(one_arg("math.ulp", args, kwargs)?.as_ref())?)))
+        .constant("e", Obj::Float(consts::E))
+        .constant("inf", Obj::Float(f64::INFINITY))
+        .constant("nan", Obj::Float(f64::NAN))
+        .constant("pi", Obj::Float(consts::PI))
+        .constant("tau", Obj::Float(consts::TAU))
+}
+
+fn float(f: f64) -> Result<Arc<Obj>, PyException> {
+    Ok(Obj::Float(f).into())
+}
+
+fn int(i: Integer) -> Result<Arc<Obj>, PyException> {
+    Ok(Obj::Int(i).into())
+}
+
+fn two_args<'a>(
+    name: &str,
+    args: &'a [Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+) -> Result<(&'a Arc<Obj>, &'a Arc<Obj>), PyException> {
+    no_kwargs(name, kwargs)?;
+    match args {
+        [x, y] => Ok((x, y)),
+        _ => Err(PyException::type_error(format!("{name} expected 2 arguments, got {}", args.len()))),
+    }
+}
+
+fn real_1(
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+    f: fn(f64) -> f64,
+    can_overflow: bool,
+) -> Result<Arc<Obj>, PyException> {
+    // like CPython's, the one argument functions name the module in their errors
+    let x = one_arg(&format!("math.{name}"), args, kwargs)?;
+    float(math_1(real_arg(&x)?, f, can_overflow)?)
+}
+
+fn real_2(
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+    f: fn(f64, f64) -> f64,
+) -> Result<Arc<Obj>, PyException> {
+    let (x, y) = two_args(name, args, kwargs)?;
+    float(math_2(real_arg(x)?, real_arg(y)?, f)?)
+}
+
+fn domain_err() -> PyException {
+    PyException::new(PyError::ValueError, "math domain error")
+}
+
+fn range_err() -> PyException {
+    PyException::new(PyError::OverflowError, "math range error")
+}
+
+fn real_arg(obj: &Obj) -> Result<f64, PyException> {
+    obj.to_float()
+}
+
+// the u32 the rug functions take, for a count that is already non-negative
+fn count_arg(val: &Integer, func: &str) -> Result<u32, PyException> {
+    val.to_u32().ok_or_else(|| PyException {
+        error: PyError::OverflowError,
+        msg: format!("{func}() argument should not exceed {}", u32::MAX),
+    })
+}
+
+// f(x) with the errors of C's libm: a nan from a number is outside the
+// domain and an inf from a finite number overflowed, or for the functions
+// that can't overflow, like log, is a pole
+fn math_1(x: f64, f: fn(f64) -> f64, can_overflow: bool) -> Result<f64, PyException> {
+    let ret = f(x);
+    if ret.is_nan() && !x.is_nan() {
+        return Err(domain_err());
+    }
+    if ret.is_infinite() && x.is_finite() {
+        return Err(if can_overflow { range_err() } else { domain_err() });
+    }
+    Ok(ret)
+}
+
+fn math_2(x: f64, y: f64, f: fn(f64, f64) -> f64) -> Result<f64, PyException> {
+    let ret = f(x, y);
+    if ret.is_nan() && !x.is_nan() && !y.is_nan() {
+        return Err(domain_err());
+    }
+    if ret.is_infinite() && x.is_finite() && y.is_finite() {
+        return Err(range_err());
+    }
+    Ok(ret)
+}
+
+// gamma and lgamma have a pole at 0 and the negative ints, CPython calls it a domain error
+fn pole_arg(obj: &Obj) -> Result<f64, PyException> {
+    match real_arg(obj)? {
+        x if x <= 0.0 && x == x.floor() => Err(domain_err()),
+        x => Ok(x),
+    }
+}
+
+// a log of an int too big for a float is taken from its mantissa and exponent
+fn log_with(obj: &Obj, f: fn(f64) -> f64) -> Result<f64, PyException> {
+    match obj {
+        Obj::Int(i) if *i <= 0 => Err(domain_err()),
+        Obj::Int(i) if int_to_f64(i).is_err() => {
+            let (mantissa, exp) = i.to_f64_exp();
+            Ok(f(mantissa) + f(2.0) * f64::from(exp))
+        }
+        o => {
+            let x = real_arg(o)?;
+            if x <= 0.0 {
+                return Err(domain_err());
+            }
+            math_1(x, f, false)
+        }
+    }
+}
+
+// floor, ceil or trunc as an int
+fn to_int(obj: &Obj, exact: fn(Rational) -> Rational, round: fn(f64) -> f64) -> Result<Integer, PyException> {
+    if let Some(int) = obj.to_integer() {
+        return Ok(int);
+    }
+    if let (Obj::Fraction(_) | Obj::Decimal(_), Some(r)) = (obj, obj.to_rational()) {
+        return Ok(exact(r).numer().clone());
+    }
+    // the decimal infinities and nans
+    if let Obj::Decimal(d) = obj {
+        return d.to_integer();
+    }
+    let x = round(real_arg(obj)?);
+    Integer::from_f64(x).ok_or_else(|| match x.is_nan() {
+        true => PyException::new(PyError::ValueError, "cannot convert float NaN to integer"),
+        false => PyException::new(PyError::OverflowError, "cannot convert float infinity to integer"),
+    })
+}
+
+// IEEE 754 remainder, x - n*y for the n nearest x/y, ties to even
+fn remainder(x: f64, y: f64) -> f64 {
+    if x.is_nan() || y.is_nan() {
+        return x + y;
+    }
+    if x.is_infinite() || y == 0.0 {
+        return f64::NAN;
+    }
+    if y.is_infinite() {
+        return x;
+    }
+    let (abs_x, abs_y) = (x.abs(), y.abs());
+    let m = abs_x % abs_y;
+    let c = abs_y - m;
+    let ret = if m < c {
+        m
+    } else if m > c {
+        -c
+    } else {
+        // half way, the n that is even
+        m - 2.0 * ((0.5 * (abs_x - m)) % abs_y)
+    };
+    1.0f64.copysign(x) * ret
+}
+
+// pow, a zero to a negative power is a domain error not an inf
+fn pow(x: f64, y: f64) -> Result<f64, PyException> {
+    let ret = x.powf(y);
+    if x.is_finite() && y.is_finite() {
+        if ret.is_nan() {
+            return Err(domain_err());
+        }
+        if ret.is_infinite() {
+            return Err(if x == 0.0 { domain_err() } else { range_err() });
+        }
+    }
+    Ok(ret)
+}
+
+// The exactly rounded sum, Shewchuk's algorithm as CPython has it: the
+// partials are non-overlapping floats that add up to the sum so far
+fn fsum(items: &[f64]) -> Result<f64, PyException> {
+    let mut partials: Vec<f64> = vec![];
+    let (mut special_sum, mut inf_sum) = (0.0, 0.0);
+    for &item in items {
+        if !item.is_finite() {
+            if item.is_infinite() {
+                inf_sum += item;
+            }
+            special_sum += item;
+            continue;
+        }
+        let mut x = item;
+        let mut count = 0;
+        for idx in 0..partials.len() {
+            let mut y = partials[idx];
+            if x.abs() < y.abs() {
+                (x, y) = (y, x);
+            }
+            let hi = x + y;
+            let lo = y - (hi - x);
+            if lo != 0.0 {
+                partials[count] = lo;
+                count += 1;
+            }
+            x = hi;
+        }
+        if !x.is_finite() {
+            return Err(PyException::new(PyError::OverflowError, "intermediate overflow in fsum"));
+        }
+        partials.truncate(count);
+        partials.push(x);
+    }
+    if special_sum != 0.0 {
+        if inf_sum.is_nan() {
+            return Err(PyException::new(PyError::ValueError, "-inf + inf in fsum"));
+        }
+        return Ok(special_sum);
+    }
+    let Some(mut hi) = partials.pop() else {
+        return Ok(0.0);
+    };
+    let mut lo = 0.0;
+    while let Some(y) = partials.pop() {
+        let x = hi;
+        hi = x + y;
+        lo = y - (hi - x);
+        if lo != 0.0 {
+            break;
+        }
+    }
+    // round half even the way the partials left would have
+    if let Some(&last) = partials.last() {
+        if (lo < 0.0 && last < 0.0) || (lo > 0.0 && last > 0.0) {
+            let y = lo * 2.0;
+            let x = hi + y;
+            if y == x - hi {
+                hi = x;
+            }
+        }
+    }
+    Ok(hi)
+}
+
+// 2**exp, down into the subnormals
+fn pow2(exp: i32) -> f64 {
+    match exp {
+        -1022.. => f64::from_bits(((exp + 1023) as u64) << 52),
+        _ => f64::from_bits(1 << (exp + 1074)),
+    }
+}
+
+// x as m * 2**e with 0.5 <= abs(m) < 1, zero, inf and nan are their own m
+fn frexp(x: f64) -> (f64, i32) {
+    if x == 0.0 || !x.is_finite() {
+        return (x, 0);
+    }
+    // a subnormal is scaled up into the normals first
+    let (x, shift) = match x.abs() < f64::MIN_POSITIVE {
+        true => (x * pow2(54), -54),
+        false => (x, 0),
+    };
+    let bits = x.to_bits();
+    let exp = ((bits >> 52) & 0x7ff) as i32 - 1022;
+    (f64::from_bits((bits & !(0x7ff << 52)) | (1022 << 52)), exp + shift)
+}
+
+// x * 2**exp rounded once, so a result in the subnormals is still the nearest float
+fn ldexp(x: f64, exp: &Integer) -> Result<f64, PyException> {
+    if x == 0.0 || !x.is_finite() {
+        return Ok(x);
+    }
+    let (mantissa, x_exp) = frexp(x);
+    // past these the result is inf or 0 whatever x is
+    let exp = exp.clamp(&Integer::from(-2200), &Integer::from(2200)).to_i32().unwrap_or(0) + x_exp;
+    match exp {
+        1025.. => Err(range_err()),
+        1024 => Ok(mantissa * 2.0 * pow2(1023)),
+        -1074..=1023 => Ok(mantissa * pow2(exp)),
+        _ => Ok(0.0f64.copysign(x)),
+    }
+}
+
+// the float next to x in the direction of y
+fn nextafter(x: f64, y: f64) -> f64 {
+    if x.is_nan() || y.is_nan() {
+        return x + y;
+    }
+    if x == y {
+        return y;
+    }
+    if x == 0.0 {
+        return pow2(-1074).copysign(y);
+    }
+    // away from zero is one up in the bits, whatever the sign
+    match (y > x) == (x > 0.0) {
+        true => f64::from_bits(x.to_bits() + 1),
+        false => f64::from_bits(x.to_bits() - 1),
+    }
+}
+
+// the gap from abs(x) to the next float up, or down from the biggest one
+fn ulp(x: f64) -> f64 {
+    let x = x.abs();
+    if !x.is_finite() {
+        return x;
+    }
+    match nextafter(x, f64::INFINITY) {
+        up if up.is_infinite() => x - nextafter(x, 0.0),
+        up => up - x,
+    }
+}
+
+// add x*y to the sums, exactly as a float and the errors left
+fn add_product(sums: &mut [f64; 3], x: f64, y: f64) {
+    let hi = x * y;
+    let sum = sums[0] + hi;
+    sums[1] += x.mul_add(y, -hi);
+    sums[2] += (sums[0] - sum) + hi;
+    sums[0] = sum;
+}
+
+// The length of the vector as CPython's vector_norm has it: scaled by a
+// power of two so the squares can't overflow, summed along with their
+// rounding errors and then corrected by one Newton step
+fn vector_norm(coords: &[f64], max: f64) -> f64 {
+    if max.is_infinite() {
+        return max;
+    }
+    if coords.iter().any(|x| x.is_nan()) {
+        return f64::NAN;
+    }
+    if max == 0.0 || coords.len() <= 1 {
+        return max;
+    }
+    if max < f64::MIN_POSITIVE {
+        let coords: Vec<f64> = coords.iter().map(|x| x / f64::MIN_POSITIVE).collect();
+        return f64::MIN_POSITIVE * vector_norm(&coords, max / f64::MIN_POSITIVE);
+    }
+    let max_exp = frexp(max).1;
+    let scale = pow2(-max_exp);
+    // the sum, with the rounding errors of the squares and of the sum
+    let mut sums = [1.0, 0.0, 0.0];
+    for x in coords {
+        let x = x * scale;
+        add_product(&mut sums, x, x);
+    }
+    let h = (sums[0] - 1.0 + (sums[1] + sums[2])).sqrt();
+    add_product(&mut sums, -h, h);
+    let x = sums[0] - 1.0 + (sums[1] + sums[2]);
+    (h + x / (2.0 * h)) / scale
+}
+
+fn hypot(coords: &[f64]) -> f64 {
+    let coords: Vec<f64> = coords.iter().map(|x| x.abs()).collect();
+    let max = coords.iter().fold(0.0, |max: f64, x| max.max(*x));
+    vector_norm(&coords, max)
+}
+
+// math.isclose(a, b, *, rel_tol=1e-09, abs_tol=0.0)
+fn isclose(args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<bool, PyException> {
+    if args.len() > 2 {
+        return Err(PyException::type_error(format!(
+            "isclose() takes exactly 2 positional arguments ({} given)",
+            args.len()
+        )));
+    }
+    let params = bind_native_args("isclose", &["a", "b", "rel_tol", "abs_tol"], 2, args, kwargs)?;
+    let mut vals = [0.0, 0.0, 1e-9, 0.0];
+    for (idx, (val, param)) in vals.iter_mut().zip(params).enumerate() {
+        match param {
+            Some(param) => *val = real_arg(&param)?,
+            None if idx < 2 => {
+                return Err(PyException::type_error(format!(
+                    "isclose() missing required argument '{}' (pos {})",
+                    ["a", "b"][idx],
+                    idx + 1
+                )))
+            }
+            None => {}
+        }
+    }
+    let [a, b, rel_tol, abs_tol] = vals;
+    if rel_tol < 0.0 || abs_tol < 0.0 {
+        return Err(PyException::new(PyError::ValueError, "tolerances must be non-negative"));
+    }
+    if a == b {
+        return Ok(true);
+    }
+    if a.is_infinite() || b.is_infinite() {
+        return Ok(false);
+    }
+    let diff = (b - a).abs();
+    Ok(diff <= (rel_tol * b).abs() || diff <= (rel_tol * a).abs() || diff <= abs_tol)
+}
+
+fn log(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("log", kwargs)?;
+    let (x, base) = match args {
+        [x] => (x, None),
+        [x, base] => (x, Some(base)),
+        _ => return Err(PyException::type_error("math.log requires 1 to 2 arguments")),
+    };
+    let num = log_with(x, f64::ln)?;
+    let Some(base) = base else {
+        return float(num);
+    };
+    let den = log_with(base, f64::ln)?;
+    if den == 0.0 {
+        return Err(PyException::new(PyError::ZeroDivisionError, "float division by zero"));
+    }
+    float(num / den)
+}
+
+fn factorial(obj: &Obj) -> Result<Integer, PyException> {
+    let n = int_arg(obj)?;
+    if n < 0 {
+        return Err(PyException::new(PyError::ValueError, "factorial() not defined for negative values"));
+    }
+    Ok(Integer::from(Integer::factorial(count_arg(&n, "factorial")?)))
+}
+
+// the number of ways to choose k of n items, without order
+fn comb(name: &str, n: &Obj, k: &Obj) -> Result<Integer, PyException> {
+    let (n, k) = (int_arg(n)?, int_arg(k)?);
+    if n < 0 {
+        return Err(PyException::new(PyError::ValueError, "n must be a non-negative integer"));
+    }
+    if k < 0 {
+        return Err(PyException::new(PyError::ValueError, "k must be a non-negative integer"));
+    }
+    if k > n {
+        return Ok(Integer::ZERO);
+    }
+    Ok(n.binomial(count_arg(&k, name)?))
+}
+
+// perm(n) is n!, perm(n, k) the ways to choose k of n items in order
+fn perm(_: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    no_kwargs("perm", kwargs)?;
+    match args {
+        [] => Err(PyException::type_error("perm expected at least 1 argument, got 0")),
+        [n] => int(factorial(n)?),
+        [n, k] => {
+            let comb = comb("perm", n, k)?;
+            // a k past n has no ways, otherwise it fits the u32 comb took
+            match int_arg(k)?.to_u32() {
+                Some(k) if comb != 0 => int(comb * Integer::from(Integer::factorial(k))),
+                _ => int(comb),
+            }
+        }
+        _ => Err(PyException::type_error(format!("perm expected at most 2 arguments, got {}", args.len()))),
+    }
+}
+
+// math.prod(iterable, *, start=1), the product the same * would give
+fn prod(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let [iterable] = args else {
+        return Err(PyException::type_error(format!("prod() takes exactly 1 positional argument ({} given)", args.len())));
+    };
+    let params = bind_native_args("prod", &["iterable", "start"], 1, args, kwargs)?;
+    let mut total = params[1].clone().unwrap_or(Obj::Int(1.into()).into());
+    let iter = vm.iter_of(iterable)?;
+    while let Some(item) = vm.next_item(&iter)? {
+        total = Obj::__mul__(&total, &item)?;
+    }
+    Ok(total)
+}
+
+fn fold_ints(
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+    init: Integer,
+    f: fn(Integer, Integer) -> Integer,
+) -> Result<Integer, PyException> {
+    no_kwargs(name, kwargs)?;
+    args.iter().try_fold(init, |acc, arg| Ok(f(acc, int_arg(arg)?)))
+}
+
+fn dist(vm: &mut PyVM, args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException> {
+    let (p, q) = two_args("dist", args, kwargs)?;
+    let (p, q) = (vm.iter_items(p)?, vm.iter_items(q)?);
+    if p.len() != q.len() {
+        return Err(PyException::new(PyError::ValueError, "both points must have the same number of dimensions"));
+    }
+    let diffs = p
+        .iter()
+        .zip(&q)
+        .map(|(a, b)| Ok(real_arg(a)? - real_arg(b)?))
+        .collect::<Result<Vec<_>, PyException>>()?;
+    float(hypot(&diffs))
+}
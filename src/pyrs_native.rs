@@ -0,0 +1,310 @@
+use crate::{
+    pyrs_error::{PyError, PyException},
+    pyrs_import::PyModule,
+    pyrs_obj::{Obj, PyObj},
+    pyrs_std::{NativeFn, NativeFunc},
+    pyrs_vm::PyVM,
+};
+use rug::Integer;
+use std::{
+    any::Any,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+// Modules written in rust outside the interpreter. A NativeModule is
+// built up with its functions, constants and classes and handed to
+// PyVM::register_module, after which import finds it by name:
+//
+//     let counter = NativeClass::new("Counter")
+//         .init(|_, args, _| Ok(Box::new(from_args::<(i64,)>("Counter", args)?.0)))
+//         .method("incr", |_, args, _| with_self(args, |n: &mut i64| { *n += 1; (*n).to_arc() }));
+//     vm.register_module(NativeModule::new("counters").class(counter));
+
+// a native class's constructor, it makes the rust value the instance holds
+pub type NativeInit =
+    fn(&mut PyVM, &[Arc<Obj>], &[(String, Arc<Obj>)]) -> Result<Box<dyn Any + Send + Sync>, PyException>;
+
+// The rust value an argument converts to, the other way from ToObj
+pub trait FromObj: Sized {
+    fn from_obj(obj: &Obj) -> Result<Self, PyException>;
+}
+
+impl FromObj for Obj {
+    fn from_obj(obj: &Obj) -> Result<Self, PyException> {
+        Ok(obj.clone())
+    }
+}
+
+// any object, by whether it's true like in an if
+impl FromObj for bool {
+    fn from_obj(obj: &Obj) -> Result<Self, PyException> {
+        Ok(obj.__bool__())
+    }
+}
+
+impl FromObj for Integer {
+    fn from_obj(obj: &Obj) -> Result<Self, PyException> {
+        obj.to_integer()
+            .ok_or_else(|| PyException::type_error(format!("'{}' object cannot be interpreted as an integer", obj.type_name())))
+    }
+}
+
+macro_rules! from_int {
+    ($($ty:ty),*) => {$(
+        impl FromObj for $ty {
+            fn from_obj(obj: &Obj) -> Result<Self, PyException> {
+                let int = Integer::from_obj(obj)?;
+                int.to_i128().and_then(|i| <$ty>::try_from(i).ok()).ok_or_else(|| PyException {
+                    error: PyError::OverflowError,
+                    msg: format!("Python int too large to convert to {}", stringify!($ty)),
+                })
+            }
+        }
+    )*};
+}
+
+from_int!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl FromObj for f64 {
+    fn from_obj(obj: &Obj) -> Result<Self, PyException> {
+        obj.to_float()
+    }
+}
+
+impl FromObj for String {
+    fn from_obj(obj: &Obj) -> Result<Self, PyException> {
+        match obj {
+            Obj::Str(s) => Ok(s.clone()),
+            o => Err(PyException::type_error(format!("expected str, not {}", o.type_name()))),
+        }
+    }
+}
+
+// a list or a tuple, item by item
+impl<T: FromObj> FromObj for Vec<T> {
+    fn from_obj(obj: &Obj) -> Result<Self, PyException> {
+        match obj {
+            Obj::List(items) => items.lock().unwrap().iter().map(|item| T::from_obj(item)).collect(),
+            Obj::Tuple(items) => items.iter().map(|item| T::from_obj(item)).collect(),
+            o => Err(PyException::type_error(format!("expected list or tuple, not {}", o.type_name()))),
+        }
+    }
+}
+
+// None, or a T
+impl<T: FromObj> FromObj for Option<T> {
+    fn from_obj(obj: &Obj) -> Result<Self, PyException> {
+        match obj {
+            Obj::None => Ok(None),
+            o => T::from_obj(o).map(Some),
+        }
+    }
+}
+
+// The positional args of a native function as a tuple of rust values,
+// there have to be as many args as the tuple has
+pub trait FromArgs: Sized {
+    fn from_args(func: &str, args: &[Arc<Obj>]) -> Result<Self, PyException>;
+}
+
+macro_rules! from_args {
+    ($count:literal; $($ty:ident $idx:tt),*) => {
+        impl<$($ty: FromObj),*> FromArgs for ($($ty,)*) {
+            fn from_args(func: &str, args: &[Arc<Obj>]) -> Result<Self, PyException> {
+                if args.len() != $count {
+                    return Err(PyException::type_error(format!(
+                        "{func}() takes exactly {} argument{} ({} given)",
+                        $count,
+                        if $count == 1 { "" } else { "s" },
+                        args.len()
+                    )));
+                }
+                Ok(($($ty::from_obj(&args[$idx])?,)*))
+            }
+        }
+    };
+}
+
+from_args!(0;);
+from_args!(1; A 0);
+from_args!(2; A 0, B 1);
+from_args!(3; A 0, B 1, C 2);
+from_args!(4; A 0, B 1, C 2, D 3);
+
+pub fn from_args<T: FromArgs>(func: &str, args: &[Arc<Obj>]) -> Result<T, PyException> {
+    T::from_args(func, args)
+}
+
+// A class whose instances hold a rust value. Its methods are native
+// functions that get the instance as their first arg
+#[derive(Debug, Clone)]
+pub struct NativeClass {
+    pub name: String,
+    pub module: String,
+    init: Option<NativeInit>,
+    methods: HashMap<String, NativeFunc>,
+    attrs: HashMap<String, NativeFunc>,
+}
+
+impl NativeClass
+{
+    pub fn new(name: &str) -> Self {
+        NativeClass {
+            name: name.to_string(),
+            module: String::new(),
+            init: None,
+            methods: HashMap::new(),
+            attrs: HashMap::new(),
+        }
+    }
+
+    // without one calling the class is a TypeError, only rust code makes instances
+    pub fn init(mut self, init: NativeInit) -> Self {
+        self.init = Some(init);
+        self
+    }
+
+    pub fn method(mut self, name: &'static str, ptr: NativeFn) -> Self {
+        self.methods.insert(name.to_string(), NativeFunc { name, ptr });
+        self
+    }
+
+    // a read-only attribute of the instances, ptr gets the instance as its one arg
+    pub fn attr(mut self, name: &'static str, ptr: NativeFn) -> Self {
+        self.attrs.insert(name.to_string(), NativeFunc { name, ptr });
+        self
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Arc<Obj>> {
+        self.methods.get(name).map(|func| Obj::Function(func.clone()).into())
+    }
+
+    pub fn lookup_attr(&self, name: &str) -> Option<NativeFn> {
+        self.attrs.get(name).map(|func| func.ptr)
+    }
+
+    // the names of its methods and attributes, for dir()
+    pub fn names(&self) -> Vec<String> {
+        self.methods.keys().chain(self.attrs.keys()).cloned().collect()
+    }
+
+    // module.Name, what repr shows
+    pub fn qualname(&self) -> String {
+        match self.module.as_str() {
+            "" => self.name.clone(),
+            module => format!("{module}.{}", self.name),
+        }
+    }
+
+    pub(crate) fn instantiate(
+        self: &Arc<Self>,
+        vm: &mut PyVM,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+    ) -> Result<Arc<Obj>, PyException> {
+        let Some(init) = self.init else {
+            return Err(PyException::type_error(format!("cannot create '{}' instances", self.qualname())));
+        };
+        let data = init(vm, args, kwargs)?;
+        Ok(Obj::Native(Arc::new(NativeObj {
+            class: self.clone(),
+            data: Mutex::new(data),
+        }))
+        .into())
+    }
+}
+
+// an instance of a NativeClass
+pub struct NativeObj {
+    pub class: Arc<NativeClass>,
+    data: Mutex<Box<dyn Any + Send + Sync>>,
+}
+
+impl std::fmt::Debug for NativeObj {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeObj({})", self.class.qualname())
+    }
+}
+
+impl NativeObj
+{
+    // an instance made from rust code, like a native function returning one
+    pub fn wrap<T: Any + Send + Sync>(class: &Arc<NativeClass>, data: T) -> Obj {
+        Obj::Native(Arc::new(NativeObj {
+            class: class.clone(),
+            data: Mutex::new(Box::new(data)),
+        }))
+    }
+
+    // runs f on the value the instance holds, a TypeError if it isn't a T
+    pub fn with<T: Any, R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R, PyException> {
+        let mut data = self.data.lock().unwrap();
+        match data.downcast_mut::<T>() {
+            Some(val) => Ok(f(val)),
+            None => Err(PyException::type_error(format!("'{}' object doesn't hold a {}", self.class.name, std::any::type_name::<T>()))),
+        }
+    }
+}
+
+// The value of self in a native method, args[0]
+pub fn with_self<T: Any, R>(args: &[Arc<Obj>], f: impl FnOnce(&mut T) -> R) -> Result<R, PyException> {
+    match args.first().map(|arg| arg.as_ref()) {
+        Some(Obj::Native(native)) => native.with(f),
+        Some(o) => Err(PyException::type_error(format!("descriptor requires a native object but received a '{}'", o.type_name()))),
+        None => Err(PyException::type_error("descriptor needs an argument")),
+    }
+}
+
+// A module written in rust, what PyVM::register_module takes
+#[derive(Debug, Clone)]
+pub struct NativeModule {
+    pub name: String,
+    attrs: Vec<(String, Arc<Obj>)>,
+}
+
+impl NativeModule
+{
+    pub fn new(name: &str) -> Self {
+        NativeModule {
+            name: name.to_string(),
+            attrs: vec![],
+        }
+    }
+
+    pub fn function(mut self, name: &'static str, ptr: NativeFn) -> Self {
+        self.attrs.push((name.to_string(), Obj::Function(NativeFunc { name, ptr }).into()));
+        self
+    }
+
+    pub fn constant(mut self, name: &str, val: Obj) -> Self {
+        self.attrs.push((name.to_string(), val.into()));
+        self
+    }
+
+    // A class kept outside too, for NativeObj::wrap to make instances of,
+    // is shared so it keeps the module it was given
+    pub fn class(mut self, class: impl Into<Arc<NativeClass>>) -> Self {
+        let mut class = class.into();
+        if let Some(class) = Arc::get_mut(&mut class) {
+            class.module = self.name.clone();
+        }
+        self.attrs.push((class.name.clone(), Obj::NativeClass(class).into()));
+        self
+    }
+
+    // the module import gives, a new one each time it isn't in sys.modules
+    pub(crate) fn to_module(&self) -> PyModule {
+        let mut attrs: HashMap<String, Arc<Obj>> = self.attrs.iter().cloned().collect();
+        attrs.insert("__name__".to_string(), Obj::Str(self.name.clone()).into());
+        PyModule::new(&self.name, attrs)
+    }
+}
+
+impl PyVM
+{
+    // makes the module importable by its name, replacing one registered before
+    pub fn register_module(&mut self, module: NativeModule) {
+        self.import_state().register(module);
+    }
+}
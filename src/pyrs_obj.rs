@@ -1,18 +1,35 @@
 use crate::{
+    pyrs_builtins::LazyIter,
+    pyrs_bytes::{self as bytes, ByteArray, MemoryView},
+    pyrs_codeobject::{CodeObject, UserFunc},
+    pyrs_decimal::{self as decimal, Decimal, DecimalContext},
     pyrs_error::{PyError, PyException},
+    pyrs_format,
+    pyrs_fractions as fractions,
+    pyrs_functools::LruCache,
+    pyrs_generator::GeneratorRef,
+    pyrs_import::PyModule,
+    pyrs_native::{NativeClass, NativeObj},
     pyrs_parsing::{Expression, Op},
-    pyrs_std::{FnPtr, RangeObj},
-    pyrs_userclass::{UserClassInstance, UserClassDef},
+    pyrs_std::{NativeFunc, RangeObj},
+    pyrs_userclass::{Property, UserClassInstance, UserClassDef},
+    pyrs_utils as Utils,
 };
 use std::{
     collections::HashMap,
     ops::{Add, Mul, Neg, Sub},
     process::{ExitCode, Termination},
     str::FromStr,
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
-use rug::Integer;
+pub type CellRef = Arc<Mutex<Arc<Obj>>>;
+// a list and a dict are shared like a bytearray, a change through one
+// reference shows in every other
+pub type ListRef = Arc<Mutex<Vec<Arc<Obj>>>>;
+pub type DictRef = Arc<Mutex<HashMap<Obj, Arc<Obj>>>>;
+
+use rug::{float::Round, ops::Pow, Complex, Float, Integer, Rational};
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -23,24 +40,56 @@ pub enum Obj {
 
     Bool(bool),
     Float(f64),
+    Complex(Box<Complex>), // boxed, it is two mpfr floats
+    Fraction(Rational),
+    Decimal(Box<Decimal>),
     Str(String),
     Int(Integer),
+    Bytes(Vec<u8>),
+    ByteArray(Arc<ByteArray>), // shared, changes through any reference show in all
+    MemoryView(Arc<MemoryView>),
+    Slice(Arc<Obj>, Arc<Obj>, Arc<Obj>), // start, stop, step
 
-    Function(FnPtr),
+    Function(NativeFunc),
+    UserFunc(Arc<UserFunc>),
+    Code(Arc<CodeObject>),
+    BuiltinMethod(Arc<Obj>, String), // receiver, method name
 
     Except(PyException),
+    ExceptType(PyError),
+    BuiltinType(&'static str), // int and the other types the VM implements
+    DecimalContext(Arc<Mutex<DecimalContext>>),
 
-    List(Vec<Arc<Obj>>),  // [], mutable, ordered, duplicates, int indexing,
+    List(ListRef),        // [], mutable, ordered, duplicates, int indexing,
     Tuple(Vec<Arc<Obj>>), // (), immutable, ordered, duplicates, int indexing,
     Set(Vec<Arc<Obj>>),   // {}, mutable, unordered, no dupes, no indexing,
+    FrozenSet(Vec<Arc<Obj>>), // frozenset(), the immutable and hashable set
     Range(RangeObj),
 
-    Dict(HashMap<Obj, Arc<Obj>>),
+    Dict(DictRef),
 
     Iter(ObjIter),
+    LazyIter(Arc<Mutex<LazyIter>>), // map(), filter(), zip() and enumerate()
 
     Class(UserClassInstance),
     ClassDef(Arc<UserClassDef>),
+    Method(Arc<Obj>, Arc<Obj>), // self, function
+    Super(Arc<UserClassDef>, Arc<Obj>), // super(), the class to look past and the instance
+    StaticMethod(Arc<Obj>),
+    ClassMethod(Arc<Obj>),
+    Property(Arc<Property>),
+    NativeClass(Arc<NativeClass>), // a class written in rust
+    Native(Arc<NativeObj>),        // and its instances
+
+    Module(String),          // the modules written in rust
+    PyModule(Arc<PyModule>), // and the ones imported from a .py file
+    LruCache(Arc<LruCache>),
+
+    Generator(GeneratorRef),
+
+    // a variable shared between a function and the closures made in it,
+    // holds Null until it is assigned
+    Cell(CellRef),
 
     // Iterator
     // - containters
@@ -48,32 +97,11 @@ pub enum Obj {
     // Sequence
     // - range
 
-    // Binary
-    // - bytes
-    // - bytearray,
-    // - memoryview,
-
-    // Set
-    // - frozenset
-
     // Mapping
     // - dict (HashMap)
 }
 pub trait PyObj: std::fmt::Debug + Clone {
 
-    fn compare_op(lhs: &Arc<Self>, rhs: &Arc<Self>, op: &Op) -> bool {
-        let ret = match op {
-            Op::Eq => Self::__eq__(lhs, rhs),
-            Op::Neq => Self::__ne__(lhs, rhs),
-            Op::LessThan => Self::__lt__(lhs, rhs),
-            Op::GreaterThan => Self::__gt__(lhs, rhs),
-            Op::LessEq => Self::__le__(lhs, rhs),
-            Op::GreaterEq => Self::__ge__(lhs, rhs),
-            _ => return Self::__default__().__bool__(),
-        };
-        ret
-    }
-
     fn __dot__(&self, _ident: &String) -> Result<&Arc<Obj>, PyException> {
         panic!();
     }
@@ -87,10 +115,7 @@ pub trait PyObj: std::fmt::Debug + Clone {
     }
 
     fn __unpack__(self) -> Result<Vec<Arc<Obj>>, PyException> {
-        Err(PyException {
-            error: PyError::TypeError,
-            msg: format!("Unable to deref the PyObj: {:?}", self),
-        })
+        Err(PyException::type_error(format!("Unable to deref the PyObj: {:?}", self)))
     }
 
     fn __repr__(&self) -> String {
@@ -127,38 +152,29 @@ pub trait PyObj: std::fmt::Debug + Clone {
     }
 
     fn __add__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
-        Err(PyException {
-            error: PyError::TypeError,
-            msg: format!("Unable to add the two PyObj types : {:?}, {:?}", lhs, rhs),
-        })
+        Err(PyException::type_error(format!("Unable to add the two PyObj types : {:?}, {:?}", lhs, rhs)))
     }
 
     fn __sub__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
-        Err(PyException {
-            error: PyError::TypeError,
-            msg: format!(
-                "Unable to subtract the two PyObj types : {:?}, {:?}",
-                lhs, rhs
-            ),
-        })
+        Err(PyException::type_error(format!("Unable to subtract the two PyObj types : {:?}, {:?}", lhs, rhs)))
     }
     fn __mul__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
-        Err(PyException {
-            error: PyError::TypeError,
-            msg: format!(
-                "Unable to multiply the two PyObj types : {:?}, {:?}",
-                lhs, rhs
-            ),
-        })
+        Err(PyException::type_error(format!("Unable to multiply the two PyObj types : {:?}, {:?}", lhs, rhs)))
     }
     fn __div__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
-        Err(PyException {
-            error: PyError::TypeError,
-            msg: format!(
-                "Unable to divide the two PyObj types : {:?}, {:?}",
-                lhs, rhs
-            ),
-        })
+        Err(PyException::type_error(format!("Unable to divide the two PyObj types : {:?}, {:?}", lhs, rhs)))
+    }
+
+    fn __mod__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
+        Err(PyException::type_error(format!("Unable to modulo the two PyObj types : {:?}, {:?}", lhs, rhs)))
+    }
+
+    fn __floordiv__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
+        Err(PyException::type_error(format!("Unable to floor divide the two PyObj types : {:?}, {:?}", lhs, rhs)))
+    }
+
+    fn __pow__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
+        Err(PyException::type_error(format!("Unable to raise the two PyObj types : {:?}, {:?}", lhs, rhs)))
     }
 
     fn __pos__(obj: &Arc<Self>) -> Result<Arc<Self>, PyException> {
@@ -166,17 +182,11 @@ pub trait PyObj: std::fmt::Debug + Clone {
     }
 
     fn __neg__(obj: &Arc<Self>) -> Result<Arc<Self>, PyException> {
-        Err(PyException {
-            error: PyError::TypeError,
-            msg: format!(" __neg__: not implemented for {:?}", obj),
-        })
+        Err(PyException::type_error(format!(" __neg__: not implemented for {:?}", obj)))
     }
 
     fn __call__(&self, objs: &Vec<Arc<Self>>) -> Result<Arc<Self>, PyException> {
-        Err(PyException {
-            error: PyError::TypeError,
-            msg: format!(" __call__: not implemented for {:?}", objs),
-        })
+        Err(PyException::type_error(format!(" __call__: not implemented for {:?}", objs)))
     }
 
     fn to_arc(self) -> Arc<Self> {
@@ -202,7 +212,16 @@ impl Obj {
     }
 
     pub fn new_dict() -> Obj {
-        Obj::Dict(HashMap::new())
+        Obj::new_dict_from(HashMap::new())
+    }
+
+    #[allow(clippy::mutable_key_type)]
+    pub fn new_dict_from(map: HashMap<Obj, Arc<Obj>>) -> Obj {
+        Obj::Dict(Arc::new(Mutex::new(map)))
+    }
+
+    pub fn new_list(items: Vec<Arc<Obj>>) -> Obj {
+        Obj::List(Arc::new(Mutex::new(items)))
     }
 
     pub fn is_num(&self) -> bool {
@@ -221,19 +240,140 @@ impl Obj {
     }
 
     pub fn from_atom(c: &str) -> Self {
-        if let Ok(val) = Integer::from_str(c) {
-            return Obj::Int(val);
+        Obj::from_number_literal(c).unwrap_or_else(|_| Obj::Str(c.to_string()))
+    }
+
+    // A number as Python source writes them: 0xff, 0o17, 0b101, 1_000,
+    // 1.5e-3, .5 and 3j. The error is the SyntaxError's message
+    pub fn from_number_literal(literal: &str) -> Result<Obj, String> {
+        let lower = literal.to_ascii_lowercase();
+        let (radix, name) = match lower.get(..2) {
+            Some("0x") => (16, "hexadecimal"),
+            Some("0o") => (8, "octal"),
+            Some("0b") => (2, "binary"),
+            _ => (10, "decimal"),
+        };
+        if radix != 10 {
+            // 0x_ff, the first underscore may follow the prefix
+            let digits = lower[2..].strip_prefix('_').unwrap_or(&lower[2..]);
+            if let Some(c) = digits.chars().find(|c| !c.is_digit(radix) && *c != '_') {
+                return Err(match c.is_ascii_digit() {
+                    true => format!("invalid digit '{c}' in {name} literal"),
+                    false => format!("invalid {name} literal"),
+                });
+            }
+            return Utils::strip_underscores(digits, radix)
+                .and_then(|digits| Integer::from_str_radix(&digits, radix as i32).ok())
+                .map(Obj::Int)
+                .ok_or(format!("invalid {name} literal"));
+        }
+
+        let invalid = || "invalid decimal literal".to_string();
+        let (body, imaginary) = match lower.strip_suffix('j') {
+            Some(body) => (body, true),
+            None => (lower.as_str(), false),
+        };
+        let digits = Utils::strip_underscores(body, 10).ok_or_else(invalid)?;
+        if imaginary || digits.contains(['.', 'e']) {
+            let val = digits.parse::<f64>().map_err(|_| invalid())?;
+            return match imaginary {
+                true => Ok(Obj::Complex(Box::new(Complex::with_val(53, (0.0, val))))),
+                false => Ok(Obj::Float(val)),
+            };
+        }
+        let int = Integer::from_str(&digits).map_err(|_| invalid())?;
+        if int != 0 && digits.trim_start_matches(['-', '+']).starts_with('0') {
+            return Err(
+                "leading zeros in decimal integer literals are not permitted; use an 0o prefix for octal integers"
+                    .to_string(),
+            );
+        }
+        Ok(Obj::Int(int))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Obj::Null | Obj::None => "NoneType",
+            Obj::Bool(_) => "bool",
+            Obj::Float(_) => "float",
+            Obj::Complex(_) => "complex",
+            Obj::Fraction(_) => "Fraction",
+            Obj::Decimal(_) => "decimal.Decimal",
+            Obj::DecimalContext(_) => "decimal.Context",
+            Obj::Str(_) => "str",
+            Obj::Int(_) => "int",
+            Obj::Bytes(_) => "bytes",
+            Obj::ByteArray(_) => "bytearray",
+            Obj::MemoryView(_) => "memoryview",
+            Obj::Slice(..) => "slice",
+            Obj::Function(_) | Obj::BuiltinMethod(_, _) => "builtin_function_or_method",
+            Obj::UserFunc(_) => "function",
+            Obj::Code(_) => "code",
+            Obj::Except(_) | Obj::ExceptType(_) => "exception",
+            Obj::List(_) => "list",
+            Obj::Tuple(_) => "tuple",
+            Obj::Set(_) => "set",
+            Obj::FrozenSet(_) => "frozenset",
+            Obj::Range(_) => "range",
+            Obj::Dict(_) => "dict",
+            Obj::Iter(_) => "iterator",
+            Obj::LazyIter(lazy) => lazy.lock().unwrap().type_name(),
+            Obj::Class(_) | Obj::Native(_) => "object",
+            Obj::ClassDef(_) | Obj::BuiltinType(_) | Obj::NativeClass(_) => "type",
+            Obj::Method(_, _) => "method",
+            Obj::Super(..) => "super",
+            Obj::StaticMethod(_) => "staticmethod",
+            Obj::ClassMethod(_) => "classmethod",
+            Obj::Property(_) => "property",
+            Obj::Module(_) | Obj::PyModule(_) => "module",
+            Obj::LruCache(_) => "functools._lru_cache_wrapper",
+            Obj::Generator(_) => "generator",
+            Obj::Cell(_) => "cell",
+        }
+    }
+
+    // what __name__ gives for functions, methods and classes
+    pub fn func_name(&self) -> String {
+        match self {
+            Obj::Function(func) => func.name.to_string(),
+            Obj::UserFunc(func) => func.name().to_string(),
+            Obj::BuiltinMethod(_, name) => name.clone(),
+            Obj::ClassDef(class) => class.name.clone(),
+            Obj::NativeClass(class) => class.name.clone(),
+            // the name without the module, Fraction for fractions.Fraction
+            Obj::BuiltinType(name) => name.rsplit('.').next().unwrap_or(name).to_string(),
+            Obj::ExceptType(error) => format!("{error:?}"),
+            Obj::Method(_, func) | Obj::StaticMethod(func) | Obj::ClassMethod(func) => func.func_name(),
+            Obj::LruCache(cache) => cache.func.func_name(),
+            Obj::Module(name) => name.clone(),
+            Obj::PyModule(module) => module.name.clone(),
+            o => o.type_name().to_string(),
         }
-        if let Ok(val) = c.parse::<f64>() {
-            return Obj::Float(val);
-        } else {
-            Obj::Str(c.to_string())
+    }
+
+    pub fn is_callable(&self) -> bool {
+        match self {
+            Obj::Function(_) | Obj::UserFunc(_) | Obj::BuiltinMethod(_, _) | Obj::Method(_, _) => true,
+            Obj::ClassDef(_) | Obj::ExceptType(_) | Obj::BuiltinType(_) | Obj::LruCache(_) => true,
+            Obj::NativeClass(_) => true,
+            Obj::Class(instance) => instance.class.lookup("__call__").is_some(),
+            _ => false,
+        }
+    }
+
+    // only immutable objects can be dict keys or set members
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Obj::List(_) | Obj::Set(_) | Obj::Dict(_) | Obj::ByteArray(_) => false,
+            Obj::MemoryView(view) => view.readonly(),
+            Obj::Tuple(objs) => objs.iter().all(|o| o.is_hashable()),
+            _ => true,
         }
     }
 
     pub fn is_iterable(&self) -> bool {
         match self {
-            Obj::Set(_) | Obj::Str(_) | Obj::List(_) | Obj::Dict(_) | Obj::Tuple(_) => true,
+            Obj::Set(_) | Obj::FrozenSet(_) | Obj::Str(_) | Obj::List(_) | Obj::Dict(_) | Obj::Tuple(_) => true,
             _ => false,
         }
     }
@@ -245,45 +385,478 @@ impl Obj {
         }
     }
 
-    fn add(lhs: &Obj, rhs: &Obj) -> Obj {
-        let err = Obj::Except(PyException {
-            error: PyError::TypeError,
-            msg: format!("No valid way to add: {} and {}", lhs, rhs.clone(),),
+    // What a slice picks out of a sequence of len items: the index it
+    // starts at, its step and how many items, like PySlice_AdjustIndices
+    pub fn slice_indices(&self, len: usize) -> Result<(isize, isize, usize), PyException> {
+        let (start, stop, step) = self.slice_bounds(len)?;
+        let count = match step > 0 {
+            true if stop > start => (stop - start - 1) / step + 1,
+            false if start > stop => (start - stop - 1) / -step + 1,
+            _ => 0,
+        };
+        Ok((start, step, count as usize))
+    }
+
+    // the start, stop and step of a slice over len items, with the ends
+    // clamped to the items, a stop of -1 is before the first going backwards
+    pub fn slice_bounds(&self, len: usize) -> Result<(isize, isize, isize), PyException> {
+        let Obj::Slice(start, stop, step) = self else {
+            unreachable!("slice_bounds of a {}", self.type_name())
+        };
+        let index = |o: &Obj| match o {
+            Obj::None => Ok(None),
+            o => match o.to_integer() {
+                Some(i) => Ok(Some(i.clamp(&isize::MIN, &isize::MAX).to_isize().unwrap())),
+                None => Err(PyException::type_error("slice indices must be integers or None or have an __index__ method")),
+            },
+        };
+        let len = len as isize;
+        let step = index(step)?.unwrap_or(1);
+        if step == 0 {
+            return Err(PyException {
+                error: PyError::ValueError,
+                msg: "slice step cannot be zero".to_string(),
+            });
+        }
+        // negative indices count from the end, the rest are clamped to the items
+        let adjust = |i: Option<isize>, default: isize| match i {
+            None => default,
+            Some(i) if i < 0 => match i.saturating_add(len) {
+                i if i < 0 => if step < 0 { -1 } else { 0 },
+                i => i,
+            },
+            Some(i) if i >= len => if step < 0 { len - 1 } else { len },
+            Some(i) => i,
+        };
+        let (start, stop) = match step > 0 {
+            true => (adjust(index(start)?, 0), adjust(index(stop)?, len)),
+            false => (adjust(index(start)?, len - 1), adjust(index(stop)?, -1)),
+        };
+        Ok((start, stop, step))
+    }
+
+    // the items a slice picks out of a sequence
+    pub fn slice_items<T: Clone>(&self, items: &[T]) -> Result<Vec<T>, PyException> {
+        let (start, step, count) = self.slice_indices(items.len())?;
+        Ok((0..count).map(|k| items[(start + k as isize * step) as usize].clone()).collect())
+    }
+
+    // removes the items a slice picks out of a sequence, in place
+    pub fn del_slice<T>(&self, items: &mut Vec<T>) -> Result<(), PyException> {
+        let (start, step, count) = self.slice_indices(items.len())?;
+        let doomed: Vec<usize> = (0..count).map(|k| (start + k as isize * step) as usize).collect();
+        let mut i = 0;
+        items.retain(|_| {
+            i += 1;
+            !doomed.contains(&(i - 1))
         });
+        Ok(())
+    }
+
+    // items[key] for a list or tuple obj, key counts from either end
+    fn index_item(obj: &Obj, items: &[Arc<Obj>], key: &Obj) -> Result<Arc<Obj>, PyException> {
+        match key.to_integer() {
+            Some(idx) => match seq_index(&idx, items.len()) {
+                Some(i) => Ok(items[i].clone()),
+                None => Err(PyException {
+                    error: PyError::IndexError,
+                    msg: format!("{} index out of range", obj.type_name()),
+                }),
+            },
+            None => Err(PyException::type_error(format!(
+                "{} indices must be integers or slices, not {}",
+                obj.type_name(),
+                key.type_name()
+            ))),
+        }
+    }
+
+    // obj[key] for the types the VM implements, ints index a sequence from
+    // either end and a slice copies part of it out
+    pub fn get_item(obj: &Arc<Obj>, key: &Obj) -> Result<Arc<Obj>, PyException> {
+        let index_err = |msg: &str| PyException {
+            error: PyError::IndexError,
+            msg: msg.to_string(),
+        };
+        match (obj.as_ref(), key) {
+            (Obj::Dict(_), k) if !k.is_hashable() => Err(PyException::type_error(format!("unhashable type: '{}'", k.type_name()))),
+            (Obj::Dict(map), _) => map.lock().unwrap().get(key).cloned().ok_or_else(|| PyException {
+                error: PyError::KeyError,
+                msg: key.__repr__(),
+            }),
+            (Obj::List(items), Obj::Slice(..)) => Ok(Obj::new_list(key.slice_items(&items.lock().unwrap())?).into()),
+            (Obj::Tuple(items), Obj::Slice(..)) => Ok(Obj::Tuple(key.slice_items(items)?).into()),
+            (Obj::List(items), _) => Obj::index_item(obj, &items.lock().unwrap(), key),
+            (Obj::Tuple(items), _) => Obj::index_item(obj, items, key),
+            (Obj::Str(s), Obj::Slice(..)) => {
+                let chars: Vec<char> = s.chars().collect();
+                Ok(Obj::Str(key.slice_items(&chars)?.into_iter().collect()).into())
+            }
+            (Obj::Str(s), _) => match key.to_integer() {
+                Some(idx) => match seq_index(&idx, s.chars().count()) {
+                    Some(i) => Ok(Obj::Str(s.chars().nth(i).unwrap().to_string()).into()),
+                    None => Err(index_err("string index out of range")),
+                },
+                None => Err(PyException::type_error(format!("string indices must be integers, not '{}'", key.type_name()))),
+            },
+            (Obj::Bytes(_) | Obj::ByteArray(_) | Obj::MemoryView(_), _) => bytes::get_item(obj, key).map(|o| o.into()),
+            (Obj::Range(range), Obj::Slice(..)) => Ok(Obj::Range(range.slice(key)?).into()),
+            (Obj::Range(range), _) => match key.to_integer() {
+                Some(idx) => match range.item(&idx) {
+                    Some(i) => Ok(Obj::Int(i).into()),
+                    None => Err(index_err("range object index out of range")),
+                },
+                None => Err(PyException::type_error(format!("range indices must be integers or slices, not {}", key.type_name()))),
+            },
+            (o, _) => Err(PyException::type_error(format!("'{}' object is not subscriptable", o.type_name()))),
+        }
+    }
+
+    // obj[key] = val, changing the container in place. A bytearray, whose
+    // hash looks inside its Mutex, is never a key
+    #[allow(clippy::mutable_key_type)]
+    pub fn set_item(obj: &Arc<Obj>, key: &Obj, val: Arc<Obj>) -> Result<(), PyException> {
+        match (obj.as_ref(), key) {
+            (Obj::Dict(_), k) if !k.is_hashable() => Err(PyException::type_error(format!("unhashable type: '{}'", k.type_name()))),
+            (Obj::Dict(map), _) => {
+                map.lock().unwrap().insert(key.clone(), val);
+                Ok(())
+            }
+            (Obj::List(items), Obj::Slice(..)) => {
+                // collected before the lock, x[:] = x reads the list it writes
+                let new = val.iter_py().map(|new| new.collect::<Vec<Arc<Obj>>>());
+                let mut items = items.lock().unwrap();
+                let new = new.ok_or_else(|| match key.slice_indices(items.len()) {
+                    Ok((_, 1, _)) => PyException::type_error("can only assign an iterable"),
+                    _ => PyException::type_error("must assign iterable to extended slice"),
+                })?;
+                let (start, step, count) = key.slice_indices(items.len())?;
+                match step {
+                    // a simple slice can be replaced by any number of items
+                    1 => {
+                        let start = start as usize;
+                        items.splice(start..start + count, new);
+                    }
+                    _ if new.len() != count => {
+                        return Err(PyException {
+                            error: PyError::ValueError,
+                            msg: format!(
+                                "attempt to assign sequence of size {} to extended slice of size {count}",
+                                new.len()
+                            ),
+                        })
+                    }
+                    _ => {
+                        for (k, item) in new.into_iter().enumerate() {
+                            items[(start + k as isize * step) as usize] = item;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            (Obj::List(items), _) => match key.to_integer() {
+                Some(idx) => {
+                    let mut items = items.lock().unwrap();
+                    match seq_index(&idx, items.len()) {
+                        Some(i) => {
+                            items[i] = val;
+                            Ok(())
+                        }
+                        None => Err(PyException {
+                            error: PyError::IndexError,
+                            msg: "list assignment index out of range".to_string(),
+                        }),
+                    }
+                }
+                None => Err(PyException::type_error(format!("list indices must be integers or slices, not {}", key.type_name()))),
+            },
+            (Obj::ByteArray(_) | Obj::MemoryView(_), _) => bytes::set_item(obj, key, &val),
+            (o, _) => Err(PyException::type_error(format!("'{}' object does not support item assignment", o.type_name()))),
+        }
+    }
+
+    // del obj[key], in place like set_item
+    #[allow(clippy::mutable_key_type)]
+    pub fn del_item(obj: &Arc<Obj>, key: &Obj) -> Result<(), PyException> {
+        match (obj.as_ref(), key) {
+            (Obj::Dict(_), k) if !k.is_hashable() => Err(PyException::type_error(format!("unhashable type: '{}'", k.type_name()))),
+            (Obj::Dict(map), _) => match map.lock().unwrap().remove(key) {
+                Some(_) => Ok(()),
+                None => Err(PyException {
+                    error: PyError::KeyError,
+                    msg: key.__repr__(),
+                }),
+            },
+            (Obj::List(items), Obj::Slice(..)) => key.del_slice(&mut items.lock().unwrap()),
+            (Obj::List(items), _) => match key.to_integer() {
+                Some(idx) => {
+                    let mut items = items.lock().unwrap();
+                    match seq_index(&idx, items.len()) {
+                        Some(i) => {
+                            items.remove(i);
+                            Ok(())
+                        }
+                        None => Err(PyException {
+                            error: PyError::IndexError,
+                            msg: "list assignment index out of range".to_string(),
+                        }),
+                    }
+                }
+                None => Err(PyException::type_error(format!("list indices must be integers or slices, not {}", key.type_name()))),
+            },
+            (Obj::ByteArray(_) | Obj::MemoryView(_), _) => bytes::del_item(obj, key),
+            (o, _) => Err(PyException::type_error(format!("'{}' object doesn't support item deletion", o.type_name()))),
+        }
+    }
+
+    // the numbers as complex, for arithmetic with a complex on either side
+    pub fn to_complex(&self) -> Option<Complex> {
+        let (real, imag) = match self {
+            Obj::Complex(c) => return Some(c.as_ref().clone()),
+            Obj::Float(f) => (*f, 0.0),
+            Obj::Int(i) => return Some(Complex::with_val(53, i)),
+            Obj::Bool(b) => (f64::from(u8::from(*b)), 0.0),
+            Obj::Fraction(r) => (rational_to_f64(r), 0.0),
+            _ => return None,
+        };
+        Some(Complex::with_val(53, (real, imag)))
+    }
+
+    // ints and bools as an Integer, for the ops that stay exact on them
+    pub fn to_integer(&self) -> Option<Integer> {
+        match self {
+            Obj::Int(i) => Some(i.clone()),
+            Obj::Bool(b) => Some(Integer::from(*b)),
+            _ => None,
+        }
+    }
+
+    // the real numbers as a float, an int too big for one is an OverflowError
+    pub fn to_float(&self) -> Result<f64, PyException> {
+        match self {
+            Obj::Float(f) => Ok(*f),
+            Obj::Int(i) => int_to_f64(i),
+            Obj::Bool(b) => Ok(f64::from(u8::from(*b))),
+            Obj::Fraction(r) => Ok(rational_to_f64(r)),
+            Obj::Decimal(d) => Ok(d.to_f64()),
+            o => Err(PyException::type_error(format!("must be real number, not {}", o.type_name()))),
+        }
+    }
+
+    // both sides as floats for a float op, the TypeError is left to the op
+    fn float_operands(lhs: &Obj, rhs: &Obj, op: &str) -> Result<(f64, f64), PyException> {
+        match (lhs.to_float(), rhs.to_float()) {
+            (Ok(a), Ok(b)) => Ok((a, b)),
+            (Err(e), _) | (_, Err(e)) if e.error == PyError::OverflowError => Err(e),
+            _ => Err(Obj::unsupported(lhs, rhs, op)),
+        }
+    }
+
+    // the exact value of a real number, None for inf and nan
+    pub fn to_rational(&self) -> Option<Rational> {
+        match self {
+            Obj::Int(i) => Some(Rational::from(i)),
+            Obj::Bool(b) => Some(Rational::from(u8::from(*b))),
+            Obj::Float(f) => Rational::from_f64(*f),
+            Obj::Fraction(r) => Some(r.clone()),
+            Obj::Decimal(d) => d.to_rational(),
+            _ => None,
+        }
+    }
+
+    // Fraction and Decimal, the numbers that compare by their exact value
+    pub fn is_exact_number(&self) -> bool {
+        matches!(self, Obj::Fraction(_) | Obj::Decimal(_))
+    }
+
+    // compares the exact values, inf and nan compare as floats
+    fn exact_cmp(lhs: &Obj, rhs: &Obj) -> Option<std::cmp::Ordering> {
+        match (lhs.to_rational(), rhs.to_rational()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => match (lhs, rhs) {
+                (Obj::Int(i), o) => i.partial_cmp(&o.to_float().ok()?),
+                (o, Obj::Int(i)) => o.to_float().ok()?.partial_cmp(i),
+                _ => lhs.to_float().ok()?.partial_cmp(&rhs.to_float().ok()?),
+            },
+        }
+    }
+
+    // arithmetic with a Fraction or a Decimal on either side
+    fn exact_op(lhs: &Obj, rhs: &Obj, op: Op) -> Result<Obj, PyException> {
+        match matches!(lhs, Obj::Decimal(_)) || matches!(rhs, Obj::Decimal(_)) {
+            true => decimal::binary_op(lhs, rhs, op),
+            false => fractions::binary_op(lhs, rhs, op),
+        }
+    }
+
+    // lhs op rhs for the arithmetic operators
+    pub fn arith(lhs: &Obj, rhs: &Obj, op: Op) -> Result<Obj, PyException> {
+        let ret = match op {
+            Op::Plus => Obj::add(lhs, rhs),
+            Op::Minus => Obj::sub(lhs, rhs),
+            Op::Asterisk => Obj::mul(lhs, rhs),
+            Op::ForwardSlash => Obj::div(lhs, rhs),
+            Op::DoubleSlash => return Ok(Obj::divmod(lhs, rhs, "//")?.0),
+            Op::Percent => return Ok(Obj::divmod(lhs, rhs, "%")?.1),
+            Op::Power => return Obj::pow(lhs, rhs),
+            op => {
+                return Err(PyException {
+                    error: PyError::NotImplementedError,
+                    msg: format!("binary operator {op} not implemented"),
+                })
+            }
+        };
+        match ret {
+            Obj::Except(e) => Err(e),
+            val => Ok(val),
+        }
+    }
+
+    pub fn unsupported(lhs: &Obj, rhs: &Obj, op: &str) -> PyException {
+        PyException::type_error(format!("unsupported operand type(s) for {op}: '{}' and '{}'", lhs.type_name(), rhs.type_name()))
+    }
+
+    // The quotient rounded toward negative infinity and the remainder that
+    // goes with it, so the remainder has the sign of the divisor. // and %
+    // are each one half of it, op is the one asked for, for the errors
+    pub fn divmod(lhs: &Obj, rhs: &Obj, op: &str) -> Result<(Obj, Obj), PyException> {
+        let zero_div_err = |msg: &str| PyException {
+            error: PyError::ZeroDivisionError,
+            msg: msg.to_string(),
+        };
+        if lhs.is_exact_number() || rhs.is_exact_number() {
+            let quot = Obj::exact_op(lhs, rhs, Op::DoubleSlash)?;
+            return Ok((quot, Obj::exact_op(lhs, rhs, Op::Percent)?));
+        }
+        if let (Some(a), Some(b)) = (lhs.to_integer(), rhs.to_integer()) {
+            if b == 0 {
+                return Err(match op {
+                    "%" => zero_div_err("integer modulo by zero"),
+                    _ => zero_div_err("integer division or modulo by zero"),
+                });
+            }
+            let (quot, rem) = a.div_rem_floor(b);
+            return Ok((Obj::Int(quot), Obj::Int(rem)));
+        }
+        match Obj::float_operands(lhs, rhs, op)? {
+            (_, 0.0) => Err(match op {
+                "%" => zero_div_err("float modulo by zero"),
+                "//" => zero_div_err("float floor division by zero"),
+                _ => zero_div_err("float divmod()"),
+            }),
+            (a, b) => {
+                let (quot, rem) = float_divmod(a, b);
+                Ok((Obj::Float(quot), Obj::Float(rem)))
+            }
+        }
+    }
+
+    // a ** b, ints stay exact unless the exponent is negative and a negative
+    // base to a fractional power is complex
+    pub fn pow(lhs: &Obj, rhs: &Obj) -> Result<Obj, PyException> {
+        if matches!(lhs, Obj::Complex(_)) || matches!(rhs, Obj::Complex(_)) {
+            return match (lhs.to_complex(), rhs.to_complex()) {
+                (Some(a), Some(b)) => complex_pow(
+                    (a.real().to_f64(), a.imag().to_f64()),
+                    (b.real().to_f64(), b.imag().to_f64()),
+                ),
+                _ => Err(Obj::unsupported(lhs, rhs, "** or pow()")),
+            };
+        }
+        if lhs.is_exact_number() || rhs.is_exact_number() {
+            return Obj::exact_op(lhs, rhs, Op::Power);
+        }
+        match (lhs.to_integer(), rhs.to_integer()) {
+            (Some(base), Some(exp)) if exp >= 0 => match exp.to_u32() {
+                Some(exp) => Ok(Obj::Int(base.pow(exp))),
+                None if base == 0 || base == 1 => Ok(Obj::Int(base)),
+                None if base == -1 => Ok(Obj::Int(if exp.is_odd() { base } else { Integer::from(1) })),
+                None => Err(PyException {
+                    error: PyError::OverflowError,
+                    msg: "exponent too large".to_string(),
+                }),
+            },
+            _ => {
+                let (x, y) = Obj::float_operands(lhs, rhs, "** or pow()")?;
+                float_pow(x, y)
+            }
+        }
+    }
+
+    fn complex_op(lhs: &Obj, rhs: &Obj, op: Op) -> Option<Obj> {
+        let (a, b) = (lhs.to_complex()?, rhs.to_complex()?);
+        let val = match op {
+            Op::Plus => a + b,
+            Op::Minus => a - b,
+            Op::Asterisk => a * b,
+            _ if b.is_zero() => {
+                return Some(Obj::Except(PyException {
+                    error: PyError::ZeroDivisionError,
+                    msg: "complex division by zero".to_string(),
+                }))
+            }
+            _ => a / b,
+        };
+        Some(Obj::Complex(Box::new(val)))
+    }
+
+    fn add(lhs: &Obj, rhs: &Obj) -> Obj {
+        let err = Obj::Except(PyException::type_error(format!("No valid way to add: {} and {}", lhs, rhs.clone(),)));
 
         let obj = match (lhs, rhs) {
+            (Obj::Complex(_), _) | (_, Obj::Complex(_)) => match Obj::complex_op(lhs, rhs, Op::Plus) {
+                Some(val) => val,
+                None => return err,
+            },
+            (a, b) if a.is_exact_number() || b.is_exact_number() => match Obj::exact_op(lhs, rhs, Op::Plus) {
+                Ok(val) => val,
+                Err(e) => return Obj::Except(e),
+            },
             (Obj::Float(dbl), other) => {
                 let val = match other {
                     Obj::Float(v) => *v,
-                    Obj::Int(v) => v.to_f64(),
+                    Obj::Int(v) => match int_to_f64(v) {
+                        Ok(v) => v,
+                        Err(e) => return Obj::Except(e),
+                    },
                     _ => return err,
                 };
                 Obj::Float(dbl + val)
             }
             (Obj::Int(int), other) => match other {
                 Obj::Int(v) => Obj::Int(int.clone().add(v)),
-                Obj::Float(v) => Obj::Float(int.to_f64() + v),
+                Obj::Float(v) => match int_to_f64(int) {
+                    Ok(int) => Obj::Float(int + v),
+                    Err(e) => return Obj::Except(e),
+                },
                 _ => return err,
             },
             (Obj::Str(s), other) => match other {
                 Obj::Str(v) => Obj::Str(format!("{s}{v}")),
                 _ => return err,
             },
+            // the result is the kind of the left side, bytes + bytearray is bytes
+            (Obj::Bytes(_) | Obj::ByteArray(_), other) => match bytes::bytes_of(other) {
+                Some(b2) => {
+                    let joined = [bytes::bytes_of(lhs).unwrap(), b2].concat();
+                    match lhs {
+                        Obj::ByteArray(_) => bytes::new_bytearray(joined),
+                        _ => Obj::Bytes(joined),
+                    }
+                }
+                None => {
+                    return Obj::Except(PyException::type_error(format!("can't concat {} to {}", other.type_name(), lhs.type_name())))
+                }
+            },
             (Obj::List(l1), other) => match other {
                 Obj::List(l2) => {
-                    let mut new_list = Vec::with_capacity(l1.len() + l2.len());
-                    new_list.extend(l1.iter().cloned());
-                    new_list.extend(l2.iter().cloned());
-                    Obj::List(new_list)
+                    // one lock at a time, x + x is the same list twice
+                    let mut new_list = l1.lock().unwrap().clone();
+                    new_list.extend(l2.lock().unwrap().iter().cloned());
+                    Obj::new_list(new_list)
                 }
                 _ => {
-                    return Obj::Except(PyException {
-                        error: PyError::TypeError,
-                        msg: format!(
-                            "TypeError: can only concatenate list (not \"{:?}\") to list",
-                            other
-                        ),
-                    });
+                    return Obj::Except(PyException::type_error(format!("TypeError: can only concatenate list (not \"{:?}\") to list", other)));
                 }
             },
             _ => return err,
@@ -292,23 +865,34 @@ impl Obj {
     }
 
     fn sub(lhs: &Obj, rhs: &Obj) -> Obj {
-        let err = Obj::Except(PyException {
-            error: PyError::TypeError,
-            msg: format!("No valid way to subtract: {} and {}", lhs, rhs.clone(),),
-        });
+        let err = Obj::Except(PyException::type_error(format!("No valid way to subtract: {} and {}", lhs, rhs.clone(),)));
 
         let obj = match (lhs, rhs) {
+            (Obj::Complex(_), _) | (_, Obj::Complex(_)) => match Obj::complex_op(lhs, rhs, Op::Minus) {
+                Some(val) => val,
+                None => return err,
+            },
+            (a, b) if a.is_exact_number() || b.is_exact_number() => match Obj::exact_op(lhs, rhs, Op::Minus) {
+                Ok(val) => val,
+                Err(e) => return Obj::Except(e),
+            },
             (Obj::Float(dbl), other) => {
                 let val = match other {
                     Obj::Float(v) => *v,
-                    Obj::Int(v) => v.to_f64(),
+                    Obj::Int(v) => match int_to_f64(v) {
+                        Ok(v) => v,
+                        Err(e) => return Obj::Except(e),
+                    },
                     _ => return err,
                 };
                 Obj::Float(dbl - val)
             }
             (Obj::Int(int), other) => match other {
                 Obj::Int(v) => Obj::Int(int.clone().sub(v)),
-                Obj::Float(v) => Obj::Float(int.to_f64() - v),
+                Obj::Float(v) => match int_to_f64(int) {
+                    Ok(int) => Obj::Float(int - v),
+                    Err(e) => return Obj::Except(e),
+                },
                 _ => return err,
             },
             _ => return err,
@@ -317,23 +901,44 @@ impl Obj {
     }
 
     fn mul(lhs: &Obj, rhs: &Obj) -> Obj {
-        let err = Obj::Except(PyException {
-            error: PyError::TypeError,
-            msg: format!("No valid way to subtract: {} and {}", lhs, rhs.clone(),),
-        });
+        let err = Obj::Except(PyException::type_error(format!("No valid way to subtract: {} and {}", lhs, rhs.clone(),)));
 
         let obj = match (lhs, rhs) {
+            (Obj::Complex(_), _) | (_, Obj::Complex(_)) => match Obj::complex_op(lhs, rhs, Op::Asterisk) {
+                Some(val) => val,
+                None => return err,
+            },
+            (a, b) if a.is_exact_number() || b.is_exact_number() => match Obj::exact_op(lhs, rhs, Op::Asterisk) {
+                Ok(val) => val,
+                Err(e) => return Obj::Except(e),
+            },
+            (seq @ (Obj::Bytes(_) | Obj::ByteArray(_)), Obj::Int(n)) | (Obj::Int(n), seq @ (Obj::Bytes(_) | Obj::ByteArray(_))) => {
+                let repeated = match bytes::repeat(&bytes::bytes_of(seq).unwrap(), n) {
+                    Ok(repeated) => repeated,
+                    Err(e) => return Obj::Except(e),
+                };
+                match seq {
+                    Obj::ByteArray(_) => bytes::new_bytearray(repeated),
+                    _ => Obj::Bytes(repeated),
+                }
+            }
             (Obj::Float(dbl), other) => {
                 let val = match other {
                     Obj::Float(v) => *v,
-                    Obj::Int(v) => v.to_f64(),
+                    Obj::Int(v) => match int_to_f64(v) {
+                        Ok(v) => v,
+                        Err(e) => return Obj::Except(e),
+                    },
                     _ => return err,
                 };
                 Obj::Float(dbl * val)
             }
             (Obj::Int(int), other) => match other {
                 Obj::Int(v) => Obj::Int(int.clone().mul(v)),
-                Obj::Float(v) => Obj::Float(int.to_f64() * v),
+                Obj::Float(v) => match int_to_f64(int) {
+                    Ok(int) => Obj::Float(int * v),
+                    Err(e) => return Obj::Except(e),
+                },
                 _ => return err,
             },
             (Obj::Str(s), other) => match other {
@@ -345,10 +950,7 @@ impl Obj {
                         }
                         Obj::Str(mult)
                     } else {
-                        return Obj::Except(PyException {
-                            error: PyError::TypeError,
-                            msg: format!(" can't multiply sequence by non-int of type {}", lhs),
-                        });
+                        return Obj::Except(PyException::type_error(format!(" can't multiply sequence by non-int of type {}", lhs)));
                     }
                 }
                 _ => return err,
@@ -359,20 +961,28 @@ impl Obj {
     }
 
     pub fn div(lhs: &Obj, rhs: &Obj) -> Obj {
-        let type_err = Obj::Except(PyException {
-            error: PyError::TypeError,
-            msg: format!("No valid way to divide: {} and {}", lhs, rhs.clone(),),
-        });
+        let type_err = Obj::Except(PyException::type_error(format!("No valid way to divide: {} and {}", lhs, rhs.clone(),)));
         let zero_div_err = Obj::Except(PyException {
             error: PyError::ZeroDivisionError,
-            msg: format!(" tried to divide {lhs} by {rhs}"),
+            msg: "float division by zero".to_string(),
         });
 
         let obj = match (lhs, rhs) {
+            (Obj::Complex(_), _) | (_, Obj::Complex(_)) => match Obj::complex_op(lhs, rhs, Op::ForwardSlash) {
+                Some(val) => val,
+                None => return type_err,
+            },
+            (a, b) if a.is_exact_number() || b.is_exact_number() => match Obj::exact_op(lhs, rhs, Op::ForwardSlash) {
+                Ok(val) => val,
+                Err(e) => return Obj::Except(e),
+            },
             (Obj::Float(dbl), other) => {
                 let val = match other {
                     Obj::Float(v) => *v,
-                    Obj::Int(v) => v.to_f64(),
+                    Obj::Int(v) => match int_to_f64(v) {
+                        Ok(v) => v,
+                        Err(e) => return Obj::Except(e),
+                    },
                     _ => return type_err,
                 };
                 if val == 0f64 {
@@ -381,17 +991,19 @@ impl Obj {
                 Obj::Float(dbl / val)
             }
             (Obj::Int(int), other) => match other {
-                Obj::Int(v) => {
-                    if *v == Integer::ZERO {
-                        return zero_div_err;
-                    }
-                    Obj::Float(int.to_f64() / v.to_f64())
-                }
+                Obj::Int(v) => match int_true_div(int, v) {
+                    Ok(val) => Obj::Float(val),
+                    Err(e) => return Obj::Except(e),
+                },
                 Obj::Float(v) => {
+                    let int = match int_to_f64(int) {
+                        Ok(int) => int,
+                        Err(e) => return Obj::Except(e),
+                    };
                     if *v == 0f64 {
                         return zero_div_err;
                     }
-                    Obj::Float(int.to_f64() / v)
+                    Obj::Float(int / v)
                 }
                 _ => return type_err,
             },
@@ -406,15 +1018,6 @@ impl PyObj for Obj {
         Obj::None
     }
 
-    fn __dot__(&self, field: &String) -> Result<&Arc<Obj>, PyException> {
-        match self {
-            Obj::Class(o) => {
-                o.get_field(field)
-            },
-            o => panic!("dot op not implemented for obj {o}"),
-        }
-    }
-
     fn __int__(&self) -> isize {
         match self {
             Obj::Bool(v) => *v as isize,
@@ -429,10 +1032,20 @@ impl PyObj for Obj {
             Obj::None => false,
             Obj::Bool(v) => *v,
             Obj::Float(v) => *v != 0f64,
+            Obj::Complex(c) => !c.is_zero(),
+            Obj::Fraction(r) => *r != 0,
+            Obj::Decimal(d) => !d.is_zero(),
             Obj::Int(v) => *v != Integer::ZERO,
             Obj::Str(v) => *v != "",
-            Obj::List(vec) | Obj::Tuple(vec) | Obj::Set(vec) => vec.len() != 0usize,
-            _ => panic!("TypeError: __bool__() not implemented for: {:?}", self),
+            Obj::Bytes(v) => !v.is_empty(),
+            Obj::ByteArray(v) => !v.lock().is_empty(),
+            Obj::MemoryView(view) => view.nbytes() != 0,
+            Obj::Slice(..) => true,
+            Obj::List(vec) => !vec.lock().unwrap().is_empty(),
+            Obj::Tuple(vec) | Obj::Set(vec) | Obj::FrozenSet(vec) => !vec.is_empty(),
+            Obj::Dict(map) => !map.lock().unwrap().is_empty(),
+            Obj::Range(range) => ObjIter::range(range).get_curr().is_some(),
+            _ => true,
         };
         return ret;
     }
@@ -440,24 +1053,22 @@ impl PyObj for Obj {
     fn __unpack__(self) -> Result<Vec<Arc<Obj>>, PyException> {
         if self.is_iterable() {
             Ok(match self {
-                Obj::List(vec) |
+                Obj::List(vec) => vec.lock().unwrap().clone(),
                 Obj::Set(vec) |
+                Obj::FrozenSet(vec) |
                 Obj::Tuple(vec) => vec, 
                 
                 Obj::Range(range) => range.to_vec(),
                 Obj::Dict(dict) => { 
-                    dict.into_iter()
-                    .map(|(key, _) | Arc::new(key))
+                    dict.lock().unwrap().keys()
+                    .map(|key| Arc::new(key.clone()))
                     .collect()
                 },
                 _ => unreachable!(),
             })
         }
         else {
-            Err(PyException { 
-                error: PyError::TypeError, 
-                msg: format!("Cannot unpack a non iterable type: {:?}", self) 
-            })
+            Err(PyException::type_error(format!("Cannot unpack a non iterable type: {:?}", self)))
         }
     }
 
@@ -469,14 +1080,44 @@ impl PyObj for Obj {
                 true => format!("True"),
                 false => format!("False"),
             },
-            Obj::Float(val) => format!("{}", val),
+            Obj::Float(val) => pyrs_format::float_repr(*val),
+            Obj::Complex(c) => complex_repr(c),
+            Obj::Fraction(r) if *r.denom() == 1 => r.numer().to_string(),
+            Obj::Fraction(r) => format!("{}/{}", r.numer(), r.denom()),
+            Obj::Decimal(d) => d.to_string(),
+            Obj::DecimalContext(ctx) => ctx.lock().unwrap().to_string(),
             Obj::Str(s) => format!("{}", s),
             Obj::Int(val) => format!("{}", val),
-            Obj::Function(ptr) => format!("{}", ptr),
-            Obj::Except(e) => format!("{}", e),
+            Obj::Bytes(_) | Obj::ByteArray(_) => self.__repr__(),
+            Obj::MemoryView(view) if view.is_released() => format!("<released memory at {:p}>", Arc::as_ptr(view)),
+            Obj::MemoryView(view) => format!("<memory at {:p}>", Arc::as_ptr(view)),
+            Obj::Slice(start, stop, step) => {
+                format!("slice({}, {}, {})", start.__repr__(), stop.__repr__(), step.__repr__())
+            }
+            Obj::Function(func) => func.to_string(),
+            Obj::UserFunc(func) => format!("<function {} at {:p}>", func.name(), Arc::as_ptr(func)),
+            Obj::Code(code) => format!("<code object {} at {:p}>", code.co_name, Arc::as_ptr(code)),
+            Obj::BuiltinMethod(obj, name) if matches!(obj.as_ref(), Obj::Module(_)) => {
+                format!("<built-in function {}>", name)
+            }
+            // unbound, like str.lower
+            Obj::BuiltinMethod(obj, name)
+                if matches!(obj.as_ref(), Obj::BuiltinType(_)) && !matches!(name.as_str(), "from_bytes" | "fromhex" | "maketrans") =>
+            {
+                format!("<method '{}' of '{}' objects>", name, obj.func_name())
+            }
+            Obj::BuiltinMethod(obj, name) => format!("<built-in method {} of {} object>", name, obj.type_name()),
+            Obj::Except(e) => e.msg.clone(),
+            Obj::ExceptType(e) => format!("<class '{:?}'>", e),
+            Obj::BuiltinType(name) => format!("<class '{name}'>"),
+            // the lock is held while the items print, so a list that
+            // contains itself finds it taken and prints [...]
             Obj::List(objs) => {
+                let Ok(objs) = objs.try_lock() else {
+                    return "[...]".to_string();
+                };
                 let mut list = String::from("[");
-                for o in objs {
+                for o in objs.iter() {
                     list.push_str(o.__repr__().as_str());
                     list.push(',');
                     list.push(' ');
@@ -510,9 +1151,17 @@ impl PyObj for Obj {
                 set.push_str("}");
                 format!("{}", set)
             }
+            Obj::FrozenSet(objs) if objs.is_empty() => "frozenset()".to_string(),
+            Obj::FrozenSet(objs) => {
+                let items: Vec<String> = objs.iter().map(|o| o.__repr__()).collect();
+                format!("frozenset({{{}}})", items.join(", "))
+            }
             Obj::Dict(objs) => {
+                let Ok(objs) = objs.try_lock() else {
+                    return "{...}".to_string();
+                };
                 let mut map = String::from("{");
-                for (key, value) in objs {
+                for (key, value) in objs.iter() {
                     map.push_str(key.__repr__().as_str());
                     map.push(':');
                     map.push_str(value.__repr__().as_str());
@@ -535,68 +1184,59 @@ impl PyObj for Obj {
                 if let Some(inc) = &range.inc {
                     r.push_str(&format!(", {}", inc.to_string()));
                 };
+                r.push(')');
                 r
             }
-            Obj::Iter(iter) => {
-                format!("Iter[ {:#?} {} ]", iter.items, iter.index)
-            }
-            Obj::Class(instance ) => {
-                let class = &instance.class;
-                
-                let mut c = format!("Class[ {} fields[", class.name);
-                for (name, _) in &class.fields {
-                    c.push_str(name);
-                    c.push_str(": ");
-                    c.push_str(&instance.get_field(name).unwrap().__str__());
-                    c.push('\n');
-                }
-                c.push(']');
-                c
+            Obj::Iter(iter) => match &iter.source {
+                IterSource::Items(items, index) => format!("Iter[ {:#?} {} ]", items, index.lock().unwrap()),
+                IterSource::Range { next, stop, step } => format!("Iter[ range({}, {stop}, {step}) ]", next.lock().unwrap()),
+            },
+            Obj::LazyIter(lazy) => format!("<{} object at {:p}>", lazy.lock().unwrap().type_name(), Arc::as_ptr(lazy)),
+            Obj::Class(instance) => {
+                format!("<{} object at {:p}>", instance.class.name, Arc::as_ptr(&instance.fields))
             }
-            Obj::ClassDef(class) => {
-                let mut c = format!("Class[ {} fields[", class.name);
-                for (name, _) in &class.fields {
-                    c.push_str(name);
-                    c.push('\n');
-                }
-                c.push_str("] methods[");
-                for (name, _) in &class.methods {
-                    c.push_str(name);
-                    c.push('\n');
-                }
-                c.push(']');
-                c
+            Obj::ClassDef(class) => format!("<class '{}'>", class.name),
+            Obj::NativeClass(class) => format!("<class '{}'>", class.qualname()),
+            Obj::Native(native) => format!("<{} object at {:p}>", native.class.qualname(), Arc::as_ptr(native)),
+            Obj::Method(recv, func) => format!("<bound method {} of {}>", func.func_name(), recv.__repr__()),
+            Obj::Super(class, obj) => format!("<super: <class '{}'>, {}>", class.name, obj.__repr__()),
+            Obj::StaticMethod(func) => format!("<staticmethod({})>", func.__repr__()),
+            Obj::ClassMethod(func) => format!("<classmethod({})>", func.__repr__()),
+            Obj::Property(p) => format!("<property object at {:p}>", Arc::as_ptr(p)),
+            Obj::Module(name) => format!("<module '{}' (built-in)>", name),
+            Obj::PyModule(module) => match module.file() {
+                Some(file) => format!("<module '{}' from '{}'>", module.name, file),
+                None => format!("<module '{}'>", module.name),
+            },
+            Obj::LruCache(cache) => format!("<functools._lru_cache_wrapper object at {:p}>", Arc::as_ptr(cache)),
+            Obj::Generator(generator) => {
+                let name = generator.lock().unwrap().name().to_string();
+                format!("<generator object {} at {:p}>", name, Arc::as_ptr(generator))
             }
+            Obj::Cell(cell) => format!("<cell at {:p}>", Arc::as_ptr(cell)),
         }
     }
 
     fn __repr__(&self) -> String {
         match self {
-            Obj::Str(s) => format!("\'{}\'", s),
+            Obj::Str(s) => repr_quoted(s.chars(), ""),
+            Obj::Bytes(b) => repr_quoted(b.iter().map(|&b| b as char), "b"),
+            Obj::ByteArray(b) => format!("bytearray({})", repr_quoted(b.lock().iter().map(|&b| b as char), "b")),
+            Obj::Fraction(r) => format!("Fraction({}, {})", r.numer(), r.denom()),
+            Obj::Decimal(d) => format!("Decimal('{d}')"),
+            Obj::Except(e) if e.msg.is_empty() => format!("{:?}()", e.error),
+            Obj::Except(e) => format!("{:?}({})", e.error, repr_quoted(e.msg.chars(), "")),
             _ => self.__str__(),
         }
     }
 
     fn __len__(&self) -> usize {
         match self {
-            Obj::List(list) => list.len(),
+            Obj::List(list) => list.lock().unwrap().len(),
             _ => panic!("TypeError: __len__() not implemented for: {:?}", self),
         }
     }
 
-    fn compare_op(lhs: &Arc<Obj>, rhs: &Arc<Obj>, op: &Op) -> bool {
-        let ret = match op {
-            Op::Eq => lhs.eq(rhs),
-            Op::Neq => lhs.ne(rhs),
-            Op::LessThan => lhs.lt(rhs),
-            Op::GreaterThan => lhs.gt(rhs),
-            Op::LessEq => lhs.le(rhs),
-            Op::GreaterEq => lhs.ge(rhs),
-            _ => return Obj::__default__().__bool__(),
-        };
-        ret
-    }
-
     fn __lt__(lhs: &Arc<Obj>, rhs: &Arc<Obj>) -> bool {
         lhs.as_ref().lt(rhs.as_ref())
     }
@@ -640,6 +1280,20 @@ impl PyObj for Obj {
         }
     }
 
+    // the result takes the sign of the divisor, -7 % 3 is 2
+    fn __mod__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
+        Ok(Obj::divmod(lhs, rhs, "%")?.1.into())
+    }
+
+    // rounds toward negative infinity, -7 // 2 is -4
+    fn __floordiv__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
+        Ok(Obj::divmod(lhs, rhs, "//")?.0.into())
+    }
+
+    fn __pow__(lhs: &Arc<Self>, rhs: &Arc<Self>) -> Result<Arc<Self>, PyException> {
+        Ok(Obj::pow(lhs, rhs)?.into())
+    }
+
     fn __eq__(lhs: &Arc<Obj>, rhs: &Arc<Obj>) -> bool {
         lhs.eq(rhs)
     }
@@ -657,6 +1311,9 @@ impl PyObj for Obj {
             Obj::None => Obj::None,
             Obj::Bool(b) => Obj::Bool(!b),
             Obj::Float(f) => Obj::Float(-f),
+            Obj::Complex(c) => Obj::Complex(Box::new(-c.as_ref().clone())),
+            Obj::Fraction(r) => Obj::Fraction(-r.clone()),
+            Obj::Decimal(d) => Obj::Decimal(Box::new(d.neg(&decimal::context()))),
             Obj::Int(i) => Obj::Int(i.clone().neg()),
             _ => {
                 return Err(PyException {
@@ -668,34 +1325,281 @@ impl PyObj for Obj {
         Ok(ret.into())
     }
 
-    fn __call__(&self, objs: &Vec<Arc<Obj>>) -> Result<Arc<Obj>, PyException> {
-        match self {
-            Obj::Function(fn_ptr) => Ok((fn_ptr.ptr)(objs)),
-            _ => Err(PyException {
-                error: PyError::TypeError,
-                msg: format!("Type is not a function"),
-            }),
+    fn to_arc(self) -> Arc<Self> {
+        Arc::from(self)
+    }
+}
+
+// an index into len items counting from the end when it is negative,
+// None when it is out of range
+pub fn seq_index(idx: &Integer, len: usize) -> Option<usize> {
+    match *idx < 0 {
+        true => Integer::from(idx + len).to_usize(),
+        false => idx.to_usize(),
+    }
+    .filter(|i| *i < len)
+}
+
+// the nearest float to an exact value, ties to even like every float op
+pub fn rational_to_f64(val: &Rational) -> f64 {
+    let (mut float, dir) = Float::with_val_round(f64::MANTISSA_DIGITS, val, Round::Nearest);
+    float.subnormalize_ieee_round(dir, Round::Nearest);
+    float.to_f64()
+}
+
+// the nearest float to an int, ties to even, and an OverflowError past the biggest one
+pub fn int_to_f64(val: &Integer) -> Result<f64, PyException> {
+    match Float::with_val(f64::MANTISSA_DIGITS, val).to_f64() {
+        f if f.is_infinite() => Err(PyException {
+            error: PyError::OverflowError,
+            msg: "int too large to convert to float".to_string(),
+        }),
+        f => Ok(f),
+    }
+}
+
+// Exact numbers hash like the int or float they are equal to, so a
+// Fraction and a Decimal can find the same dict key as 0.5 or 2
+fn hash_rational<H: std::hash::Hasher>(val: &Rational, state: &mut H) {
+    use std::hash::Hash;
+    let float = rational_to_f64(val);
+    match (*val.denom() == 1, Rational::from_f64(float)) {
+        (true, _) => val.numer().hash(state),
+        (false, Some(exact)) if exact == *val => float.to_bits().hash(state),
+        _ => (val.numer(), val.denom()).hash(state),
+    }
+}
+
+// int / int rounded once to the nearest float, so it stays exact however
+// big the ints are rather than going through two lossy conversions first
+fn int_true_div(lhs: &Integer, rhs: &Integer) -> Result<f64, PyException> {
+    if *rhs == 0 {
+        return Err(PyException {
+            error: PyError::ZeroDivisionError,
+            msg: "division by zero".to_string(),
+        });
+    }
+    match rational_to_f64(&Rational::from((lhs, rhs))) {
+        f if f.is_infinite() => Err(PyException {
+            error: PyError::OverflowError,
+            msg: "integer division result too large for a float".to_string(),
+        }),
+        f => Ok(f),
+    }
+}
+
+// CPython's float divmod, the quotient is rounded to the nearest integral
+// value so that quot * b + rem stays as close to a as floats get
+fn float_divmod(a: f64, b: f64) -> (f64, f64) {
+    let mut rem = a % b;
+    let mut div = (a - rem) / b;
+    if rem == 0.0 {
+        rem = 0.0f64.copysign(b);
+    }
+    else if (b < 0.0) != (rem < 0.0) {
+        rem += b;
+        div -= 1.0;
+    }
+    let quot = match div {
+        0.0 => 0.0f64.copysign(a / b),
+        _ if div - div.floor() > 0.5 => div.floor() + 1.0,
+        _ => div.floor(),
+    };
+    (quot, rem)
+}
+
+fn float_pow(x: f64, y: f64) -> Result<Obj, PyException> {
+    if x == 0.0 && y < 0.0 {
+        return Err(PyException {
+            error: PyError::ZeroDivisionError,
+            msg: "0.0 cannot be raised to a negative power".to_string(),
+        });
+    }
+    if x < 0.0 && y.is_finite() && y.fract() != 0.0 {
+        return complex_pow((x, 0.0), (y, 0.0));
+    }
+    match x.powf(y) {
+        val if val.is_infinite() && x.is_finite() && y.is_finite() => Err(PyException {
+            error: PyError::OverflowError,
+            msg: "(34, 'Numerical result out of range')".to_string(),
+        }),
+        val => Ok(Obj::Float(val)),
+    }
+}
+
+type Parts = (f64, f64);
+
+fn parts_mul(a: Parts, b: Parts) -> Parts {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+// 1 / z the way CPython divides, scaled by the larger part of z
+fn parts_recip(z: Parts) -> Parts {
+    if z.0.abs() >= z.1.abs() {
+        let ratio = z.1 / z.0;
+        let denom = z.0 + z.1 * ratio;
+        (1.0 / denom, -ratio / denom)
+    }
+    else {
+        let ratio = z.0 / z.1;
+        let denom = z.0 * ratio + z.1;
+        (ratio / denom, -1.0 / denom)
+    }
+}
+
+// Complex powers as CPython does them: small integer exponents by repeated
+// squaring, which keeps (1+2j)**3 exact, anything else through polar form
+fn complex_pow(a: Parts, b: Parts) -> Result<Obj, PyException> {
+    let val = if b == (0.0, 0.0) {
+        (1.0, 0.0)
+    }
+    else if a == (0.0, 0.0) {
+        if b.1 != 0.0 || b.0 < 0.0 {
+            return Err(PyException {
+                error: PyError::ZeroDivisionError,
+                msg: "0.0 to a negative or complex power".to_string(),
+            });
         }
+        (0.0, 0.0)
     }
+    else if b.1 == 0.0 && b.0.fract() == 0.0 && b.0.abs() <= 100.0 {
+        let (mut val, mut square, mut n) = ((1.0, 0.0), a, b.0.abs() as u32);
+        while n > 0 {
+            if n & 1 == 1 {
+                val = parts_mul(val, square);
+            }
+            square = parts_mul(square, square);
+            n >>= 1;
+        }
+        if b.0 < 0.0 { parts_recip(val) } else { val }
+    }
+    else {
+        let modulus = a.0.hypot(a.1);
+        let arg = a.1.atan2(a.0);
+        let (mut len, mut phase) = (modulus.powf(b.0), arg * b.0);
+        if b.1 != 0.0 {
+            len /= (arg * b.1).exp();
+            phase += b.1 * modulus.ln();
+        }
+        (len * phase.cos(), len * phase.sin())
+    };
+    if !(val.0.is_finite() && val.1.is_finite()) {
+        return Err(PyException {
+            error: PyError::OverflowError,
+            msg: "complex exponentiation".to_string(),
+        });
+    }
+    Ok(Obj::Complex(Box::new(Complex::with_val(53, val))))
+}
 
-    fn to_arc(self) -> Arc<Self> {
-        Arc::from(self)
+// How python writes a str or bytes: in single quotes unless only double
+// quotes avoid escaping, with the unprintable chars escaped. A bytes' chars
+// are its bytes, the ones past ascii always get a \x escape
+fn repr_quoted(chars: impl Iterator<Item = char> + Clone, prefix: &str) -> String {
+    let quote = match chars.clone().any(|c| c == '\'') && !chars.clone().any(|c| c == '"') {
+        true => '"',
+        false => '\'',
+    };
+    let mut repr = format!("{prefix}{quote}");
+    for c in chars {
+        match c {
+            '\\' => repr.push_str("\\\\"),
+            '\n' => repr.push_str("\\n"),
+            '\r' => repr.push_str("\\r"),
+            '\t' => repr.push_str("\\t"),
+            c if c == quote => {
+                repr.push('\\');
+                repr.push(c);
+            }
+            c if c.is_control() || (!prefix.is_empty() && !c.is_ascii()) => match c as u32 {
+                code @ 0..=0xff => repr.push_str(&format!("\\x{code:02x}")),
+                code @ 0..=0xffff => repr.push_str(&format!("\\u{code:04x}")),
+                code => repr.push_str(&format!("\\U{code:08x}")),
+            },
+            c => repr.push(c),
+        }
     }
+    repr.push(quote);
+    repr
 }
 
+// CPython's complex repr, 3j, (1+2j) or (-0-1j). The parts are float
+// reprs without a trailing .0
+fn complex_repr(c: &Complex) -> String {
+    let part = |val: f64| {
+        let s = Obj::Float(val).__str__();
+        s.strip_suffix(".0").map(str::to_string).unwrap_or(s)
+    };
+    let (real, imag) = (c.real().to_f64(), c.imag().to_f64());
+    if real == 0.0 && real.is_sign_positive() {
+        return format!("{}j", part(imag));
+    }
+    let sign = if imag.is_sign_negative() && !imag.is_nan() { '-' } else { '+' };
+    format!("({}{sign}{}j)", part(real), part(imag.abs()))
+}
+
+// Equal numbers hash the same whatever their type, so 1, 1.0, True and
+// 1+0j are the same dict key as in Python
+impl std::hash::Hash for Obj {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        match self {
+            Obj::Complex(c) if c.imag().is_zero() => Obj::Float(c.real().to_f64()).hash(state),
+            Obj::Complex(c) => (c.real().to_f64().to_bits(), c.imag().to_f64().to_bits()).hash(state),
+            Obj::Fraction(r) => hash_rational(r, state),
+            Obj::Decimal(d) if d.is_nan() => std::mem::discriminant(self).hash(state),
+            Obj::Decimal(d) => match d.to_rational() {
+                Some(r) => hash_rational(&r, state),
+                None => d.to_f64().to_bits().hash(state),
+            },
+            Obj::Bool(b) => Integer::from(*b).hash(state),
+            Obj::Int(i) => i.hash(state),
+            Obj::Float(f) => match Integer::from_f64(*f) {
+                Some(i) if f.fract() == 0.0 => i.hash(state),
+                _ => f.to_bits().hash(state),
+            },
+            Obj::Str(s) => s.hash(state),
+            Obj::Bytes(b) => b.hash(state),
+            Obj::ByteArray(_) | Obj::MemoryView(_) => bytes::bytes_of(self).hash(state),
+            Obj::Tuple(objs) => objs.hash(state),
+            // the same whatever order the items are in
+            Obj::FrozenSet(objs) => objs
+                .iter()
+                .map(|o| {
+                    let mut hasher = std::hash::DefaultHasher::new();
+                    o.hash(&mut hasher);
+                    std::hash::Hasher::finish(&hasher)
+                })
+                .fold(0u64, |sum, h| sum.wrapping_add(h))
+                .hash(state),
+            Obj::ExceptType(e) => e.hash(state),
+            Obj::BuiltinType(name) => name.hash(state),
+            o => std::mem::discriminant(o).hash(state),
+        }
+    }
+}
+
+impl Eq for Obj {}
+
 impl PartialEq for Obj {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Obj::Null, Obj::Null) |
             (Obj::None, Obj::None) => true,
+            (Obj::Complex(c1), Obj::Complex(c2)) => c1 == c2,
+            (Obj::Complex(c), num) | (num, Obj::Complex(c)) => {
+                c.imag().is_zero() && Obj::Float(c.real().to_f64()) == *num
+            }
+            (a, b) if a.is_exact_number() || b.is_exact_number() => {
+                Obj::exact_cmp(a, b) == Some(std::cmp::Ordering::Equal)
+            }
             (Obj::Float(flt), other) => match other {
                 Obj::Float(same) => *flt == *same,
-                Obj::Int(i) => *flt == i.to_f64(),
+                Obj::Int(i) => *flt == *i,
                 Obj::Bool(b) => *flt == f64::from(*b),
                 _ => false,
             },
             (Obj::Int(i), other) => match other {
-                Obj::Float(f) => i.to_f64() == *f,
+                Obj::Float(f) => *i == *f,
                 Obj::Int(same) => *i == *same,
                 Obj::Bool(b) => *i == Integer::from(*b),
                 _ => false,
@@ -707,7 +1611,39 @@ impl PartialEq for Obj {
                 _ => false,
             },
             (Obj::Str(s1), Obj::Str(s2)) => s1 == s2,
+            (Obj::Bytes(b1), Obj::Bytes(b2)) => b1 == b2,
+            // bytes, bytearrays and memoryviews are equal when their bytes are
+            (Obj::Bytes(_) | Obj::ByteArray(_) | Obj::MemoryView(_), other) => {
+                bytes::bytes_of(other).is_some_and(|b| bytes::bytes_of(self) == Some(b))
+            }
+            (Obj::Slice(a1, b1, c1), Obj::Slice(a2, b2, c2)) => a1 == a2 && b1 == b2 && c1 == c2,
+            // the same list is equal without locking it twice
+            (Obj::List(v1), Obj::List(v2)) => Arc::ptr_eq(v1, v2) || *v1.lock().unwrap() == *v2.lock().unwrap(),
+            (Obj::Tuple(v1), Obj::Tuple(v2)) => v1 == v2,
+            // a set and a frozenset are equal when they have the same items
+            (Obj::Set(v1) | Obj::FrozenSet(v1), Obj::Set(v2) | Obj::FrozenSet(v2)) => {
+                v1.len() == v2.len() && v1.iter().all(|o| v2.contains(o))
+            }
+            (Obj::Dict(d1), Obj::Dict(d2)) => Arc::ptr_eq(d1, d2) || *d1.lock().unwrap() == *d2.lock().unwrap(),
             (Obj::Dict(_), _) | (_, Obj::Dict(_)) => false,
+            (Obj::UserFunc(f1), Obj::UserFunc(f2)) => Arc::ptr_eq(f1, f2),
+            (Obj::Code(c1), Obj::Code(c2)) => c1 == c2,
+            (Obj::Generator(g1), Obj::Generator(g2)) => Arc::ptr_eq(g1, g2),
+            (Obj::LazyIter(l1), Obj::LazyIter(l2)) => Arc::ptr_eq(l1, l2),
+            (Obj::Cell(c1), Obj::Cell(c2)) => Arc::ptr_eq(c1, c2),
+            (Obj::ExceptType(e1), Obj::ExceptType(e2)) => e1 == e2,
+            (Obj::BuiltinType(t1), Obj::BuiltinType(t2)) => t1 == t2,
+            (Obj::Class(i1), Obj::Class(i2)) => Arc::ptr_eq(&i1.fields, &i2.fields),
+            (Obj::ClassDef(c1), Obj::ClassDef(c2)) => Arc::ptr_eq(c1, c2),
+            (Obj::NativeClass(c1), Obj::NativeClass(c2)) => Arc::ptr_eq(c1, c2),
+            (Obj::Native(n1), Obj::Native(n2)) => Arc::ptr_eq(n1, n2),
+            (Obj::Method(r1, f1), Obj::Method(r2, f2)) => r1 == r2 && f1 == f2,
+            (Obj::StaticMethod(f1), Obj::StaticMethod(f2)) |
+            (Obj::ClassMethod(f1), Obj::ClassMethod(f2)) => f1 == f2,
+            (Obj::Property(p1), Obj::Property(p2)) => Arc::ptr_eq(p1, p2),
+            (Obj::Module(m1), Obj::Module(m2)) => m1 == m2,
+            (Obj::PyModule(m1), Obj::PyModule(m2)) => Arc::ptr_eq(m1, m2),
+            (Obj::LruCache(c1), Obj::LruCache(c2)) => Arc::ptr_eq(c1, c2),
             (_, _) => false,
         }
     }
@@ -732,14 +1668,17 @@ impl PartialOrd for Obj {
 
     fn lt(&self, other: &Self) -> bool {
         let ret = match (self, other) {
+            (a, b) if a.is_exact_number() || b.is_exact_number() => {
+                Obj::exact_cmp(a, b) == Some(std::cmp::Ordering::Less)
+            }
             (Obj::Float(flt), other) => match other {
                 Obj::Float(same) => *flt < *same,
-                Obj::Int(i) => *flt < i.to_f64(),
+                Obj::Int(i) => *flt < *i,
                 Obj::Bool(b) => *flt < f64::from(*b),
                 _ => false,
             },
             (Obj::Int(i), other) => match other {
-                Obj::Float(flt) => i.to_f64() < *flt,
+                Obj::Float(flt) => *i < *flt,
                 Obj::Int(same) => *i < *same,
                 Obj::Bool(b) => *i < Integer::from(*b),
                 _ => false,
@@ -751,6 +1690,9 @@ impl PartialOrd for Obj {
                 _ => false,
             },
             (Obj::Str(s1), Obj::Str(s2)) => s1 < s2,
+            (Obj::Bytes(_) | Obj::ByteArray(_), Obj::Bytes(_) | Obj::ByteArray(_)) => {
+                bytes::bytes_of(self) < bytes::bytes_of(other)
+            }
             _ => false,
         };
         ret
@@ -758,14 +1700,17 @@ impl PartialOrd for Obj {
 
     fn gt(&self, other: &Self) -> bool {
         let ret = match (self, other) {
+            (a, b) if a.is_exact_number() || b.is_exact_number() => {
+                Obj::exact_cmp(a, b) == Some(std::cmp::Ordering::Greater)
+            }
             (Obj::Float(flt), other) => match other {
                 Obj::Float(same) => *flt > *same,
-                Obj::Int(i) => *flt > i.to_f64(),
+                Obj::Int(i) => *flt > *i,
                 Obj::Bool(b) => *flt > f64::from(*b),
                 _ => false,
             },
             (Obj::Int(i), other) => match other {
-                Obj::Float(flt) => i.to_f64() > *flt,
+                Obj::Float(flt) => *i > *flt,
                 Obj::Int(same) => *i > *same,
                 Obj::Bool(b) => *i > Integer::from(*b),
                 _ => false,
@@ -777,6 +1722,9 @@ impl PartialOrd for Obj {
                 _ => false,
             },
             (Obj::Str(s1), Obj::Str(s2)) => s1 > s2,
+            (Obj::Bytes(_) | Obj::ByteArray(_), Obj::Bytes(_) | Obj::ByteArray(_)) => {
+                bytes::bytes_of(self) > bytes::bytes_of(other)
+            }
             _ => false,
         };
         ret
@@ -809,55 +1757,87 @@ impl Termination for Obj {
     }
 }
 
-// obj iter
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+// obj iter, the copies of an iterator share how far it has got so next()
+// on any of them moves them all on
+#[derive(Debug, Clone)]
 pub struct ObjIter {
-    items: Vec<Arc<Obj>>,
-    index: usize,
+    source: IterSource,
+}
+
+#[derive(Debug, Clone)]
+enum IterSource {
+    Items(Vec<Arc<Obj>>, Arc<Mutex<usize>>),
+    // a range counts on from its start instead of listing its items first
+    Range { next: Arc<Mutex<Integer>>, stop: Integer, step: Integer },
 }
 
 impl ObjIter 
 {
+    pub fn new(items: Vec<Arc<Obj>>) -> Self {
+        ObjIter {
+            source: IterSource::Items(items, Arc::new(Mutex::new(0))),
+        }
+    }
+
+    pub fn range(range: &RangeObj) -> Self {
+        let (start, stop, step) = range.bounds();
+        ObjIter {
+            source: IterSource::Range { next: Arc::new(Mutex::new(start)), stop, step },
+        }
+    }
+
     pub fn from(obj: &Arc<Obj>) -> Option<Self> {
-        let iter = match obj.as_ref() {
-            Obj::List(v) | Obj::Tuple(v) | Obj::Set(v) => ObjIter {
-                items: v.clone(),
-                index: 0,
-            },
-            Obj::Str(s) => {
-                let items = s
-                    .chars()
-                    .map(|c| Arc::new(Obj::Str(c.to_string())))
-                    .collect();
-                ObjIter { items, index: 0 }
-            }
-            Obj::Dict(m) => {
-                let items = m.keys().cloned().map(|k| Arc::new(k)).collect();
-                ObjIter { items, index: 0 }
-            }
-            _ => return None,
-        };
-        Some(iter)
+        match obj.as_ref() {
+            Obj::Iter(_) => None,
+            o => o.iter_py(),
+        }
     }
 
     pub fn get_curr(&self) -> Option<Arc<Obj>> {
-        self.items.get(self.index).cloned()
+        match &self.source {
+            IterSource::Items(items, index) => items.get(*index.lock().unwrap()).cloned(),
+            IterSource::Range { next, stop, step } => {
+                let next = next.lock().unwrap();
+                let more = (*step > 0 && *next < *stop) || (*step < 0 && *next > *stop);
+                more.then(|| Obj::Int(next.clone()).into())
+            }
+        }
     }
 
+    // the next item, the position is shared so every copy of the iterator moves on
+    pub fn advance(&self) -> Option<Arc<Obj>> {
+        match &self.source {
+            IterSource::Items(items, index) => {
+                let mut index = index.lock().unwrap();
+                let out = items.get(*index).cloned();
+                if out.is_some() {
+                    *index += 1;
+                }
+                out
+            }
+            IterSource::Range { next, stop, step } => {
+                let mut next = next.lock().unwrap();
+                if !((*step > 0 && *next < *stop) || (*step < 0 && *next > *stop)) {
+                    return None;
+                }
+                let out = next.clone();
+                *next += step;
+                Some(Obj::Int(out).into())
+            }
+        }
+    }
+
+    // the items not yet gone through, which uses them up like list(it)
     pub fn get_items(self) -> Vec<Arc<Obj>>
     {
-        self.items
+        std::iter::from_fn(|| self.advance()).collect()
     }
 }
 
 impl Iterator for ObjIter {
     type Item = Arc<Obj>;
     fn next(&mut self) -> Option<Self::Item> {
-        let out = self.get_curr();
-        if out.is_some() {
-            self.index += 1;
-        }
-        out
+        self.advance()
     }
 }
 
@@ -871,7 +1851,11 @@ pub struct ObjIntoIter {
 impl ObjIntoIter {
     fn from(obj: Arc<Obj>) -> Option<Self> {
         let iter = match obj.as_ref() {
-            Obj::List(v) | Obj::Tuple(v) | Obj::Set(v) => ObjIntoIter {
+            Obj::List(v) => ObjIntoIter {
+                items: v.lock().unwrap().clone(),
+                index: 0,
+            },
+            Obj::Tuple(v) | Obj::Set(v) | Obj::FrozenSet(v) => ObjIntoIter {
                 items: v.clone(),
                 index: 0,
             },
@@ -883,9 +1867,13 @@ impl ObjIntoIter {
                 ObjIntoIter { items, index: 0 }
             }
             Obj::Dict(m) => {
-                let items = m.keys().cloned().map(|k| Arc::new(k)).collect();
+                let items = m.lock().unwrap().keys().cloned().map(Arc::new).collect();
                 ObjIntoIter { items, index: 0 }
             }
+            o @ (Obj::Bytes(_) | Obj::ByteArray(_) | Obj::MemoryView(_)) => ObjIntoIter {
+                items: byte_items(o),
+                index: 0,
+            },
             _ => return None,
         };
         Some(iter)
@@ -920,26 +1908,33 @@ impl IntoIterator for Obj {
 impl Obj {
     pub fn iter_py(&self) -> Option<ObjIter> {
         match self {
-            Obj::List(v) | Obj::Tuple(v) | Obj::Set(v) => Some(ObjIter {
-                items: v.clone(),
-                index: 0,
-            }),
+            Obj::List(v) => Some(ObjIter::new(v.lock().unwrap().clone())),
+            Obj::Tuple(v) | Obj::Set(v) | Obj::FrozenSet(v) => Some(ObjIter::new(v.clone())),
             Obj::Str(s) => {
                 let items = s
                     .chars()
                     .map(|c| Arc::new(Obj::Str(c.to_string())))
                     .collect();
-                Some(ObjIter { items, index: 0 })
+                Some(ObjIter::new(items))
             }
             Obj::Dict(m) => {
-                let items = m.keys().cloned().map(|k| Arc::new(k)).collect();
-                Some(ObjIter { items, index: 0 })
+                let items = m.lock().unwrap().keys().cloned().map(Arc::new).collect();
+                Some(ObjIter::new(items))
             }
+            Obj::Bytes(_) | Obj::ByteArray(_) | Obj::MemoryView(_) => Some(ObjIter::new(byte_items(self))),
+            Obj::Range(range) => Some(ObjIter::range(range)),
+            Obj::Iter(iter) => Some(iter.clone()),
             _ => None,
         }
     }
 }
 
+// the bytes of a bytes-like object as ints, what iterating over it gives
+fn byte_items(obj: &Obj) -> Vec<Arc<Obj>> {
+    let bytes = bytes::bytes_of(obj).unwrap_or_default();
+    bytes.into_iter().map(|b| Arc::new(Obj::Int(b.into()))).collect()
+}
+
 // Extension trait so Arc<Obj>.iter() and Arc<Obj>.into_obj_iter() are available
 pub trait ArcObjIterExt {
     fn iter(&self) -> Option<ObjIter>;
@@ -974,13 +1969,15 @@ impl ToObj for Expression {
     fn to_obj(self) -> Obj {
         match self {
             Expression::Atom(atom) => Obj::from_atom(&atom),
+            Expression::Str(s) => Obj::Str(s),
+            Expression::Bytes(b) => Obj::Bytes(b),
             Expression::Operation(op, args) => match op {
                 Op::List => {
                     let mut objs = vec![];
                     for a in args {
                         objs.push(a.to_arc());
                     }
-                    Obj::List(objs.into())
+                    Obj::new_list(objs)
                 }
                 Op::Plus => {
                     let lhs = args.first().cloned().unwrap().to_obj();
@@ -988,15 +1985,9 @@ impl ToObj for Expression {
                     let sum = Obj::add(&lhs, &rhs);
                     sum
                 }
-                _ => Obj::Except(PyException {
-                    error: PyError::TypeError,
-                    msg: format!("cannot convert op {:#?} with args {:#?} to Obj", op, args),
-                }),
+                _ => Obj::Except(PyException::type_error(format!("cannot convert op {:#?} with args {:#?} to Obj", op, args))),
             },
-            _ => Obj::Except(PyException {
-                error: PyError::TypeError,
-                msg: format!("cannot convert {:#?} to Obj", self),
-            }),
+            _ => Obj::Except(PyException::type_error(format!("cannot convert {:#?} to Obj", self))),
         }
     }
 }
@@ -1,8 +1,9 @@
 use crate::{
     pyrs_error::{PyError, PyException},
     pyrs_obj::{Obj, PyObj, ToObj},
-    pyrs_std::{FnPtr},
+    pyrs_builtins as builtins,
     pyrs_utils as Utils,
+    pyrs_vm::PyVM,
 };
 
 use std::{collections::HashMap, sync::Arc};
@@ -12,6 +13,7 @@ use std::{collections::HashMap, sync::Arc};
 pub enum Token<'a> {
     Ident(&'a str),
     Atom(&'a str),
+    Str(&'a str), // the literal as written, prefix and quotes included
     Op(Op),
     Sep(char),
     Eof,
@@ -24,14 +26,21 @@ pub enum Op {
     Minus,
     Asterisk,
     ForwardSlash,
+    DoubleSlash,
+    Percent,
+    Power,
     Equals,
 
     AddEquals,
     SubEquals,
     MulEquals,
     DivEquals,
+    FloorDivEquals,
+    ModEquals,
+    PowEquals,
 
     Unpack,
+    UnpackDict,
 
     Colon,
     SemiColon,
@@ -60,8 +69,20 @@ pub enum Op {
     List,
     Tuple,
     Set,
+    Dict,
+    GenExpr,
+    ListComp,
+    SetComp,
+    DictComp,
 
     Dot,
+    Call,
+    Subscript,
+    Slice,
+    Decorator,
+
+    FString,
+    FormatValue,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -83,6 +104,18 @@ pub enum Keyword {
 
     None,
     Pass,
+
+    Yield,
+    YieldFrom,
+    From,
+    Lambda,
+    Import,
+    As,
+    With,
+    Assert,
+    Del,
+    Global,
+    Nonlocal,
 }
 
 impl Op {
@@ -91,6 +124,7 @@ impl Op {
             Op::Plus => Some(Op::Pos),
             Op::Minus => Some(Op::Neg),
             Op::Asterisk => Some(Op::Unpack),
+            Op::Power => Some(Op::UnpackDict),
             _ => None,
         }
     }
@@ -98,8 +132,8 @@ impl Op {
     pub fn prefix_binding_power(op: &Op) -> ((), f32) {
         match op {
             Op::Pos | Op::Neg => ((), 3.0),
-            Op::Unpack => ((), 4.0),
-            _ => panic!("Unknown prefix operator {:?}", op),
+            // *x and **x
+            _ => ((), 4.0),
         }
     }
 
@@ -116,12 +150,18 @@ impl Op {
             Op::SubEquals => Some(Op::SubEquals),
             Op::MulEquals => Some(Op::MulEquals),
             Op::DivEquals => Some(Op::DivEquals),
+            Op::ModEquals => Some(Op::ModEquals),
+            Op::FloorDivEquals => Some(Op::FloorDivEquals),
+            Op::PowEquals => Some(Op::PowEquals),
             Op::Eq => Some(Op::Eq),
             Op::Neq => Some(Op::Neq),
             Op::Plus => Some(Op::Plus),
             Op::Minus => Some(Op::Minus),
             Op::Asterisk => Some(Op::Asterisk),
             Op::ForwardSlash => Some(Op::ForwardSlash),
+            Op::Percent => Some(Op::Percent),
+            Op::DoubleSlash => Some(Op::DoubleSlash),
+            Op::Power => Some(Op::Power),
             Op::Dot => Some(Op::Dot),
             Op::List => Some(Op::List),
             Op::Set => Some(Op::Set),
@@ -129,23 +169,33 @@ impl Op {
         }
     }
 
-    pub fn infix_binding_power(op: &Op) -> (f32, f32) {
-        match op {
+    pub fn infix_binding_power(op: &Op) -> Option<(f32, f32)> {
+        let bp = match op {
             Op::RoundBracketsOpen | Op::RoundBracketsClose => (0.0, 0.1),
             Op::CurlyBracketsOpen | Op::CurlyBracketsClose => (0.0, 0.1),
             Op::SquareBracketsOpen | Op::SquareBracketsClose => (0.0, 0.1),
 
-            Op::Equals | Op::AddEquals | Op::SubEquals | Op::MulEquals | Op::DivEquals => {
-                (0.2, 0.3)
-            }
+            Op::Equals
+            | Op::AddEquals
+            | Op::SubEquals
+            | Op::MulEquals
+            | Op::DivEquals
+            | Op::FloorDivEquals
+            | Op::ModEquals
+            | Op::PowEquals => (0.2, 0.3),
             Op::Eq | Op::Neq | Op::LessEq | Op::LessThan | Op::GreaterEq | Op::GreaterThan => {
                 (0.5, 0.6)
             }
             Op::Plus | Op::Minus => (1.0, 1.1),
-            Op::Asterisk | Op::ForwardSlash => (2.0, 2.1),
-            Op::Dot => (4.1, 4.0),
-            _ => panic!("Unknown operator {:?}", op),
-        }
+            Op::Asterisk | Op::ForwardSlash | Op::DoubleSlash | Op::Percent => (2.0, 2.1),
+            // right associative and tighter than a unary minus, -2 ** 2 is -4
+            Op::Power => (3.6, 3.5),
+            // x.y[0] is (x.y)[0]
+            Op::Dot | Op::Subscript => (4.0, 4.1),
+            Op::Call => (5.0, 5.1),
+            _ => return None,
+        };
+        Some(bp)
     }
 }
 
@@ -155,6 +205,7 @@ impl<'a, 'b> PartialEq<Token<'b>> for Token<'a> {
         match (self, other) {
             (Ident(a), Ident(b)) => a == b,
             (Atom(a), Atom(b)) => a == b,
+            (Str(a), Str(b)) => a == b,
             (Op(a), Op(b)) => a == b,
             (Sep(a), Sep(b)) => a == b,
             (Eof, Eof) => true,
@@ -174,12 +225,18 @@ impl std::fmt::Display for Op {
             Op::Plus | Op::Pos => "+",
             Op::Minus | Op::Neg => "-",
             Op::Asterisk | Op::Unpack => "*",
+            Op::UnpackDict | Op::Power => "**",
             Op::ForwardSlash => "/",
+            Op::DoubleSlash => "//",
+            Op::Percent => "%",
             Op::Equals => "=",
             Op::AddEquals => "+=",
             Op::SubEquals => "-=",
             Op::MulEquals => "*=",
             Op::DivEquals => "/=",
+            Op::FloorDivEquals => "//=",
+            Op::ModEquals => "%=",
+            Op::PowEquals => "**=",
             Op::Eq => "==",
             Op::Neq => "!=",
             Op::LessThan => "<",
@@ -199,9 +256,20 @@ impl std::fmt::Display for Op {
             Op::SquareBracketsOpen => "[",
             Op::SquareBracketsClose => "]",
             Op::Dot => ".",
+            Op::Call => "call",
+            Op::Subscript => "subscript",
+            Op::Slice => "slice",
+            Op::Decorator => "@",
+            Op::FString => "fstring",
+            Op::FormatValue => "format",
             Op::List => "list",
             Op::Tuple => "tuple",
             Op::Set => "set",
+            Op::Dict => "dict",
+            Op::GenExpr => "genexpr",
+            Op::ListComp => "listcomp",
+            Op::SetComp => "setcomp",
+            Op::DictComp => "dictcomp",
         };
         write!(f, "{}", ident)
     }
@@ -211,6 +279,7 @@ impl<'a> std::fmt::Display for Token<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Token::Atom(atom) => write!(f, "Atom{{'{}'}}", atom),
+            Token::Str(literal) => write!(f, "Str{{{}}}", literal),
             Token::Eof => write!(f, "EOF"),
             Token::Ident(ident) => write!(f, "Ident{{'{}'}}", ident),
             Token::Keyword(keyword) => write!(f, "Keyword{{'{}'}}", keyword),
@@ -236,6 +305,16 @@ impl<'a> Token<'a> {
             "None" => Keyword::None,
             "pass" => Keyword::Pass,
             "class" => Keyword::Class,
+            "yield" => Keyword::Yield,
+            "from" => Keyword::From,
+            "lambda" => Keyword::Lambda,
+            "import" => Keyword::Import,
+            "as" => Keyword::As,
+            "with" => Keyword::With,
+            "assert" => Keyword::Assert,
+            "del" => Keyword::Del,
+            "global" => Keyword::Global,
+            "nonlocal" => Keyword::Nonlocal,
             _ => return None,
         };
         return Some(Token::Keyword(keyword));
@@ -264,7 +343,7 @@ impl<'a> std::fmt::Display for Lexer<'a> {
 }
 
 impl<'a> Lexer<'a> {
-    pub fn from(words: &Vec<&'a str>) -> Self {
+    pub fn from(words: &Vec<&'a str>) -> Result<Self, PyException> {
         let mut token_list: Vec<Token<'a>> = vec![];
         //dbg!(words);
         for &word in words {
@@ -274,10 +353,16 @@ impl<'a> Lexer<'a> {
                 "-" => Token::Op(Op::Minus),
                 "/" => Token::Op(Op::ForwardSlash),
                 "*" => Token::Op(Op::Asterisk),
+                "%" => Token::Op(Op::Percent),
+                "**" => Token::Op(Op::Power),
+                "//" => Token::Op(Op::DoubleSlash),
                 "+=" => Token::Op(Op::AddEquals),
                 "-=" => Token::Op(Op::SubEquals),
                 "*=" => Token::Op(Op::MulEquals),
                 "/=" => Token::Op(Op::DivEquals),
+                "%=" => Token::Op(Op::ModEquals),
+                "**=" => Token::Op(Op::PowEquals),
+                "//=" => Token::Op(Op::FloorDivEquals),
                 "=" => Token::Op(Op::Equals),
                 "(" => Token::Op(Op::RoundBracketsOpen),
                 ")" => Token::Op(Op::RoundBracketsClose),
@@ -294,26 +379,27 @@ impl<'a> Lexer<'a> {
                 "<=" => Token::Op(Op::LessEq),
                 ">=" => Token::Op(Op::GreaterEq),
                 "." => Token::Op(Op::Dot),
+                "@" => Token::Op(Op::Decorator),
                 "," => Token::Sep(','),
                 word if Token::try_get_keyword(word).is_some() => {
                     Token::try_get_keyword(word).unwrap()
                 }
+                word if Utils::split_string_prefix(word).is_some() => Token::Str(word),
                 word if Utils::str_starts_with(word, char::is_numeric) => Token::Atom(word),
+                word if word.len() > 1 && word.starts_with('.') => Token::Atom(word),
 
                 word if Utils::str_starts_with(word, char::is_alphabetic) | 
                         word.starts_with('_') => Token::Ident(word),
 
-                word if word.starts_with('\"') => Token::Atom(Utils::trim_first_and_last(word)),
-                word if word.starts_with('\'') => Token::Atom(Utils::trim_first_and_last(word)),
                 "" => continue,
-                t => panic!("ParseError: Bad token: {:?}", t),
+                t => return Err(PyException::syntax_error(format!("invalid syntax '{t}'"))),
             };
             token_list.push(token);
         }
 
         token_list.push(Token::Sep('\n'));
         token_list.reverse();
-        return Lexer { tokens: token_list };
+        Ok(Lexer { tokens: token_list })
     }
 
     pub fn next(&mut self) -> Token<'a> {
@@ -324,12 +410,350 @@ impl<'a> Lexer<'a> {
         self.tokens.last().copied().unwrap_or(Token::Eof)
     }
 
+    // [a, b] or a list comprehension, after the '['
+    fn parse_list(&mut self) -> Result<Expression, PyException> {
+        let mut args = vec![];
+        if !matches!(self.peek(), Token::Op(Op::SquareBracketsClose) | Token::Eof) {
+            let first = self.parse_expression(0.0)?;
+            if self.peek() == Token::Keyword(Keyword::For) {
+                let comp = self.parse_comprehension(Op::ListComp, vec![first])?;
+                if self.next() != Token::Op(Op::SquareBracketsClose) {
+                    return Err(PyException::syntax_error("expected ']' after list comprehension"));
+                }
+                return Ok(comp);
+            }
+            args.push(first);
+        }
+        loop {
+            let next = self.peek();
+            match next {
+                Token::Eof => return Err(PyException::syntax_error("expected ']' at end of file")),
+                Token::Op(Op::SquareBracketsClose) => {
+                    self.next();
+                    break;
+                }
+                Token::Sep(_) => {
+                    self.next();
+                    continue;
+                }
+                _ => args.push(self.parse_expression(0.0)?),
+            }
+        }
+        Ok(Expression::Operation(Op::List, args))
+    }
+
+    // a dict, a set or a comprehension of either, after the '{'
+    fn parse_braces(&mut self) -> Result<Expression, PyException> {
+        // {} is an empty dict, {k: v ...} a dict, {x ...} a set
+        if self.peek() == Token::Op(Op::CurlyBracketsClose) {
+            self.next();
+            return Ok(Expression::Operation(Op::Dict, vec![]));
+        }
+        let first = self.parse_expression(0.0)?;
+        let is_dict = self.peek() == Token::Op(Op::Colon);
+        let mut args = vec![first];
+        if is_dict {
+            self.next();
+            args.push(self.parse_expression(0.0)?);
+        }
+        if self.peek() == Token::Keyword(Keyword::For) {
+            let op = if is_dict { Op::DictComp } else { Op::SetComp };
+            let comp = self.parse_comprehension(op, args)?;
+            if self.next() != Token::Op(Op::CurlyBracketsClose) {
+                return Err(PyException::syntax_error("expected '}' after comprehension"));
+            }
+            return Ok(comp);
+        }
+        loop {
+            let next = self.peek();
+            match next {
+                Token::Eof => return Err(PyException::syntax_error("expected '}' at end of file")),
+                Token::Op(Op::CurlyBracketsClose) => {
+                    self.next();
+                    break;
+                }
+                Token::Sep(_) => {
+                    self.next();
+                    continue;
+                }
+                _ => {
+                    args.push(self.parse_expression(0.0)?);
+                    if is_dict {
+                        if self.next() != Token::Op(Op::Colon) {
+                            return Err(PyException::syntax_error("expected ':' after dict key"));
+                        }
+                        args.push(self.parse_expression(0.0)?);
+                    }
+                }
+            }
+        }
+        match is_dict {
+            true => Ok(Expression::Operation(Op::Dict, args)),
+            false => Ok(Expression::Operation(Op::Set, args)),
+        }
+    }
+
+    // for <target> in <iter> [if <cond>]... [for ...]...
+    // each clause becomes a Keyword[for] or Keyword[if] arg after the element,
+    // dict comprehensions have a key and a value element
+    pub fn parse_comprehension(&mut self, op: Op, elements: Vec<Expression>) -> Result<Expression, PyException> {
+        let mut args = elements;
+        loop {
+            match self.peek() {
+                Token::Keyword(Keyword::For) => {
+                    self.next();
+                    let target = self.parse_target()?;
+                    let iter = self.parse_expression(0.0)?;
+                    args.push(Expression::Keyword(Keyword::For, vec![target, iter], vec![]));
+                }
+                Token::Keyword(Keyword::If) => {
+                    self.next();
+                    let cond = self.parse_expression(0.0)?;
+                    args.push(Expression::Keyword(Keyword::If, vec![cond], vec![]));
+                }
+                _ => break,
+            }
+        }
+        Ok(Expression::Operation(op, args))
+    }
+
+    // The parameters of a def or lambda, consumes the closing `end` token.
+    // x=1 becomes Op[= x 1], *args Op[* args], a bare * Op[*] and **kw Op[** kw]
+    pub fn parse_params(&mut self, end: Op) -> Result<Vec<Expression>, PyException> {
+        let mut params = vec![];
+        loop {
+            match self.next() {
+                Token::Op(op) if op == end => break,
+                Token::Sep(',') => continue,
+                Token::Ident(var) => {
+                    let name = Expression::Ident(var.to_string());
+                    if self.peek() == Token::Op(Op::Equals) {
+                        self.next();
+                        let default = self.parse_expression(0.0)?;
+                        params.push(Expression::Operation(Op::Equals, vec![name, default]));
+                    } else {
+                        params.push(name);
+                    }
+                }
+                Token::Op(star @ (Op::Asterisk | Op::Power)) => {
+                    let op = match star {
+                        Op::Power => Op::UnpackDict,
+                        _ => Op::Unpack,
+                    };
+                    match self.peek() {
+                        Token::Ident(var) => {
+                            self.next();
+                            params.push(Expression::Operation(op, vec![Expression::Ident(var.to_string())]));
+                        }
+                        _ if op == Op::Unpack => params.push(Expression::Operation(op, vec![])),
+                        t => return Err(PyException::syntax_error(format!("expected a name after '**', found \'{}\'", t))),
+                    }
+                }
+                t => return Err(PyException::syntax_error(format!("Unexpected token \'{}\'", t))),
+            }
+        }
+        Ok(params)
+    }
+
+    // the arguments after a '(' up to and including the ')'
+    pub fn parse_call_args(&mut self) -> Result<Vec<Expression>, PyException> {
+        let mut args = vec![];
+        while self.peek() != Token::Op(Op::RoundBracketsClose) {
+            if self.peek() == Token::Sep(',') {
+                self.next();
+                continue;
+            }
+            if self.peek() == Token::Eof {
+                return Err(PyException::syntax_error("expected ')' to close the call"));
+            }
+            let arg = self.parse_expression(0.0)?;
+            if self.peek() == Token::Keyword(Keyword::For) {
+                args.push(self.parse_comprehension(Op::GenExpr, vec![arg])?);
+            } else {
+                args.push(arg);
+            }
+        }
+        self.next();
+        Ok(args)
+    }
+
+    // what is between the brackets of x[...], consumes the ']'. Several
+    // comma separated items index with a tuple of them
+    pub fn parse_subscript(&mut self) -> Result<Expression, PyException> {
+        let mut items = vec![];
+        let mut trailing_comma = false;
+        loop {
+            match self.peek() {
+                Token::Op(Op::SquareBracketsClose) => {
+                    self.next();
+                    break;
+                }
+                Token::Sep(',') => {
+                    self.next();
+                    trailing_comma = true;
+                }
+                Token::Eof => return Err(PyException::syntax_error("expected ']' to close the subscript")),
+                _ => {
+                    items.push(self.parse_slice()?);
+                    trailing_comma = false;
+                }
+            }
+        }
+        match (items.len(), trailing_comma) {
+            (0, _) => Err(PyException::syntax_error("invalid syntax, empty subscript")),
+            (1, false) => Ok(items.remove(0)),
+            _ => Ok(Expression::Operation(Op::Tuple, items)),
+        }
+    }
+
+    // one subscript item, an index or a slice a:b:c with any part left out.
+    // A left out part is None
+    fn parse_slice(&mut self) -> Result<Expression, PyException> {
+        let none = || Expression::Keyword(Keyword::None, vec![], vec![]);
+        let mut parts = vec![];
+        let mut part = None;
+        loop {
+            match self.peek() {
+                Token::Op(Op::Colon) => {
+                    self.next();
+                    parts.push(part.take().unwrap_or_else(none));
+                }
+                Token::Op(Op::SquareBracketsClose) | Token::Sep(',') | Token::Eof => break,
+                _ if part.is_some() => return Err(PyException::syntax_error("invalid syntax in subscript")),
+                _ => part = Some(self.parse_expression(0.0)?),
+            }
+        }
+        if parts.is_empty() {
+            return part.ok_or_else(|| PyException::syntax_error("invalid syntax in subscript"));
+        }
+        parts.push(part.unwrap_or_else(none));
+        if parts.len() > 3 {
+            return Err(PyException::syntax_error("invalid syntax, a slice has at most three parts"));
+        }
+        Ok(Expression::Operation(Op::Slice, parts))
+    }
+
+    // the comma separated names after 'import', a name with an alias is
+    // Keyword[as conds[name alias]]. from m import * has the one name '*'
+    pub fn parse_import_names(&mut self) -> Result<Vec<Expression>, PyException> {
+        let mut names = vec![];
+        loop {
+            match self.peek() {
+                Token::Ident(_) => {
+                    let name = Expression::Ident(self.parse_dotted_name());
+                    if self.peek() != Token::Keyword(Keyword::As) {
+                        names.push(name);
+                        continue;
+                    }
+                    self.next();
+                    let alias = match self.next() {
+                        Token::Ident(alias) => Expression::Ident(alias.to_string()),
+                        t => return Err(PyException::syntax_error(format!("expected a name after 'as', found {}", t))),
+                    };
+                    names.push(Expression::Keyword(Keyword::As, vec![name, alias], vec![]));
+                    continue;
+                }
+                Token::Op(Op::Asterisk) => names.push(Expression::Ident("*".to_string())),
+                Token::Sep(',') | Token::Op(Op::RoundBracketsOpen | Op::RoundBracketsClose) => {}
+                Token::Sep('\n') | Token::Eof => break,
+                t => return Err(PyException::syntax_error(format!("expected a name to import, found {}", t))),
+            }
+            self.next();
+        }
+        Ok(names)
+    }
+
+    // the context managers of a with, up to and including the ':'. One
+    // bound to a target is Keyword[as conds[manager target]]
+    pub fn parse_with_items(&mut self) -> Result<Vec<Expression>, PyException> {
+        let mut items = vec![];
+        loop {
+            match self.peek() {
+                Token::Op(Op::Colon) if !items.is_empty() => break,
+                Token::Sep(',') if !items.is_empty() => {}
+                Token::Op(Op::Colon) | Token::Sep(_) | Token::Eof => {
+                    return Err(PyException::syntax_error(format!("expected a context manager after 'with', found {}", self.peek())))
+                }
+                _ => {
+                    let manager = self.parse_expression(0.0)?;
+                    if self.peek() != Token::Keyword(Keyword::As) {
+                        items.push(manager);
+                        continue;
+                    }
+                    self.next();
+                    let target = self.parse_expression(0.0)?;
+                    items.push(Expression::Keyword(Keyword::As, vec![manager, target], vec![]));
+                    continue;
+                }
+            }
+            self.next();
+        }
+        self.next();
+        Ok(items)
+    }
+
+    // a.b.c, a relative one starts with its dots, ..a or .
+    pub fn parse_dotted_name(&mut self) -> String {
+        let mut name = String::new();
+        loop {
+            match self.peek() {
+                Token::Op(Op::Dot) => name.push('.'),
+                Token::Ident(part) if name.is_empty() || name.ends_with('.') => name.push_str(part),
+                _ => break,
+            }
+            self.next();
+        }
+        name
+    }
+
+    // the names between 'for' and 'in', consumes the 'in'
+    pub fn parse_target(&mut self) -> Result<Expression, PyException> {
+        let mut names = vec![];
+        loop {
+            match self.next() {
+                Token::Ident(ident) => names.push(Expression::Ident(ident.to_string())),
+                Token::Sep(',') => continue,
+                Token::Keyword(Keyword::In) => break,
+                t => return Err(PyException::syntax_error(format!("expected an ident token, but found {}", t))),
+            }
+        }
+        match names.len() {
+            1 => Ok(names.remove(0)),
+            _ => Ok(Expression::Operation(Op::Tuple, names)),
+        }
+    }
+
+    // "a" 'b' is "ab", adjacent literals are joined into one
+    pub fn parse_string_literals(&mut self, first: &str) -> Result<Expression, PyException> {
+        let mut literal = Expression::from_string_literal(first)?;
+        while let Token::Str(next) = self.peek() {
+            self.next();
+            literal = match (literal, Expression::from_string_literal(next)?) {
+                (Expression::Bytes(mut a), Expression::Bytes(b)) => {
+                    a.extend(b);
+                    Expression::Bytes(a)
+                }
+                (Expression::Bytes(_), _) | (_, Expression::Bytes(_)) => {
+                    return Err(PyException::syntax_error("cannot mix bytes and nonbytes literals"))
+                }
+                (a, b) => Expression::join_fstring(vec![a, b]),
+            };
+        }
+        Ok(literal)
+    }
+
     #[allow(unused_variables)]
-    pub fn parse_expression(&mut self, min_bp: f32) -> Expression {
+    pub fn parse_expression(&mut self, min_bp: f32) -> Result<Expression, PyException> {
         //println!("Expr: {:?}", self.peek());
         let mut lhs = match self.next() {
-            Token::Eof => return Expression::None,
-            Token::Atom(it) => Expression::Atom(it.to_string()),
+            Token::Eof => return Ok(Expression::None),
+            Token::Atom(it) => {
+                if let Err(msg) = Obj::from_number_literal(it) {
+                    return Err(PyException::syntax_error(msg));
+                }
+                Expression::Atom(it.to_string())
+            }
+            Token::Str(literal) => self.parse_string_literals(literal)?,
             Token::Ident(ident) => {
                 /*
                 
@@ -346,7 +770,7 @@ impl<'a> Lexer<'a> {
                         if self.peek() == Token::Sep(',') {
                             self.next();
                         }
-                        args.push(self.parse_expression(0.0));
+                        args.push(self.parse_expression(0.0)?);
                     }
                     let close = self.next();
                     assert_eq!(
@@ -362,15 +786,7 @@ impl<'a> Lexer<'a> {
                 */
                 if self.peek() == Token::Op(Op::RoundBracketsOpen) {
                     self.next();
-                    let mut args = vec![];
-                    while self.peek() != Token::Op(Op::RoundBracketsClose) {
-                        if self.peek() == Token::Sep(',') {
-                            self.next();
-                            continue;
-                        }
-                        args.push(self.parse_expression(0.0));
-                    }
-                    self.next();
+                    let args = self.parse_call_args()?;
                     //println!("args: {:#?}", args);
                     Expression::Call(ident.to_string(), args)
                 } else {
@@ -381,27 +797,28 @@ impl<'a> Lexer<'a> {
             Token::Keyword(keyword) => {
                 match keyword {
                     Keyword::True => Expression::Keyword(Keyword::True, vec![], vec![]),
+                    Keyword::None => Expression::Keyword(Keyword::None, vec![], vec![]),
                     Keyword::False => Expression::Keyword(Keyword::False, vec![], vec![]),
                     Keyword::If | Keyword::Elif | Keyword::While => {
                         let mut conditions: Vec<Expression> = vec![];
                         while self.peek() != Token::Op(Op::Colon) && self.peek() != Token::Eof {
-                            conditions.push(self.parse_expression(0.0));
+                            conditions.push(self.parse_expression(0.0)?);
                         }
-                        return Expression::Keyword(keyword, conditions, vec![]);
+                        return Ok(Expression::Keyword(keyword, conditions, vec![]));
                     }
-                    Keyword::Else => return Expression::Keyword(Keyword::Else, vec![], vec![]),
+                    Keyword::Else => return Ok(Expression::Keyword(Keyword::Else, vec![], vec![])),
                     Keyword::For => {
                         let mut objs = vec![];
 
                         let x = match self.next() {
                             Token::Ident(ident) => ident,
-                            e => panic!("Syntax Error: expected an ident token, but found {}", e),
+                            e => return Err(PyException::syntax_error(format!("expected an ident token, but found {}", e))),
                         };
                         objs.push(Expression::Ident(x.into()));
 
                         let _in_tk = match self.next() {
                             Token::Keyword(Keyword::In) => {}
-                            e => panic!("Syntax Error: expected token \'in\', but found {}", e),
+                            e => return Err(PyException::syntax_error(format!("expected token \'in\', but found {}", e))),
                         };
 
                         loop {
@@ -409,79 +826,31 @@ impl<'a> Lexer<'a> {
                                 self.next();
                                 break;
                             }
-                            let cond = self.parse_expression(0.0);
+                            let cond = self.parse_expression(0.0)?;
                             objs.push(cond);
                         }
-                        return Expression::Keyword(Keyword::For, objs, vec![]);
+                        return Ok(Expression::Keyword(Keyword::For, objs, vec![]));
                     }
                     Keyword::Def => {
                         let name = match self.next() {
                             Token::Ident(ident) => ident.to_string(),
-                            t => panic!("Syntax Error: must be ident after def, not {}", t),
+                            t => return Err(PyException::syntax_error(format!("must be ident after def, not {}", t))),
                         };
                         if self.next() != Token::Op(Op::RoundBracketsOpen) {
-                            panic!();
+                            return Err(PyException::syntax_error("expected '(' after the function name"));
                         }
 
                         let mut args = vec![Expression::Ident(name)];
-
-                        let mut i = 0;
-                        let max_args = 1000;
-                        loop {
-                            i += 1;
-                            if i > max_args {
-                                panic!("Max loops");
-                            }
-
-                            let next = self.next();
-                            //println!("tk: {}",tk);
-                            match next {
-                                Token::Ident(var) => {
-                                    let expr = match self.peek() {
-                                        Token::Op(Op::Equals) => {
-                                            self.next();
-                                            let mut vals = vec![Expression::Ident(var.to_string())];
-                                            while self.peek() != Token::Sep(',')
-                                                && self.peek() != Token::Op(Op::RoundBracketsClose)
-                                            {
-                                                let v = self.parse_expression(0.0);
-                                                vals.push(v);
-                                            }
-                                            match self.peek() {
-                                                Token::Sep(_) => self.next(),
-                                                Token::Op(Op::RoundBracketsClose) => {
-                                                    self.next();
-                                                    break;
-                                                }
-                                                t => panic!(
-                                                    "Syntax Error: Unexpected token \'{}\'",
-                                                    t
-                                                ),
-                                            };
-                                            println!("vals: {:#?}", vals);
-                                            Expression::Operation(Op::Equals, vals)
-                                        }
-                                        Token::Sep(_) => {
-                                            self.next();
-                                            Expression::Ident(var.to_string())
-                                        }
-                                        Token::Op(Op::RoundBracketsClose) => {
-                                            Expression::Ident(var.to_string())
-                                        }
-                                        t => panic!("Syntax Error: Unexpected token \'{}\'", t),
-                                    };
-                                    //println!("expr: {}", expr);
-                                    args.push(expr);
-                                }
-                                Token::Op(Op::RoundBracketsClose) => {
-                                    break;
-                                }
-                                t => panic!("Syntax Error: Unexpected token \'{}\'", t),
-                            }
+                        args.append(&mut self.parse_params(Op::RoundBracketsClose)?);
+                        if self.next() != Token::Op(Op::Colon) {
+                            return Err(PyException::syntax_error("expected ':' after the parameters"));
                         }
-                        let colon = self.next();
-                        assert_eq!(colon, Token::Op(Op::Colon));
-                        return Expression::Keyword(Keyword::Def, args, vec![]);
+                        return Ok(Expression::Keyword(Keyword::Def, args, vec![]));
+                    }
+                    Keyword::Lambda => {
+                        let params = self.parse_params(Op::Colon)?;
+                        let body = self.parse_expression(0.0)?;
+                        return Ok(Expression::Keyword(Keyword::Lambda, params, vec![body]));
                     }
                     Keyword::Return => {
                         let mut args = vec![];
@@ -495,60 +864,134 @@ impl<'a> Lexer<'a> {
                                 Token::Eof => {
                                     break;
                                 }
-                                t => args.push(self.parse_expression(0.0)),
+                                t => args.push(self.parse_expression(0.0)?),
                             }
                         }
-                        assert_eq!(
-                            args.len(),
-                            1,
-                            "return can only return 1 expr not {:#?}",
-                            args
-                        );
-                        return Expression::Keyword(Keyword::Return, args, vec![]);
+                        if args.len() > 1 {
+                            return Err(PyException::syntax_error("invalid syntax after 'return'"));
+                        }
+                        return Ok(Expression::Keyword(Keyword::Return, args, vec![]));
                     }
                     Keyword::Class => {
                         // conds [name]
                         // body (to be filled)
                         let mut conditions: Vec<Expression> = vec![];
                         while self.peek() != Token::Op(Op::Colon) && self.peek() != Token::Eof {
-                            conditions.push(self.parse_expression(0.0));
+                            conditions.push(self.parse_expression(0.0)?);
                         }
-                        return Expression::Keyword(Keyword::Class, conditions, vec![]);
+                        return Ok(Expression::Keyword(Keyword::Class, conditions, vec![]));
                     }
                     Keyword::Pass => {
-                        return Expression::Keyword(Keyword::Pass, vec![], vec![]);
+                        return Ok(Expression::Keyword(Keyword::Pass, vec![], vec![]));
+                    }
+                    Keyword::Yield => {
+                        if self.peek() == Token::Keyword(Keyword::From) {
+                            self.next();
+                            let iter = self.parse_expression(0.0)?;
+                            return Ok(Expression::Keyword(Keyword::YieldFrom, vec![iter], vec![]));
+                        }
+                        let args = match self.peek() {
+                            Token::Sep('\n') | Token::Eof | Token::Op(Op::RoundBracketsClose) => vec![],
+                            _ => vec![self.parse_expression(0.0)?],
+                        };
+                        return Ok(Expression::Keyword(Keyword::Yield, args, vec![]));
+                    }
+                    Keyword::Import => {
+                        let module = self.parse_import_names()?;
+                        return Ok(Expression::Keyword(Keyword::Import, module, vec![]));
+                    }
+                    Keyword::From => {
+                        // from <module> import <names>, the module comes first
+                        let module = self.parse_dotted_name();
+                        if module.is_empty() {
+                            return Err(PyException::syntax_error(format!("expected a module name after 'from', found {}", self.peek())));
+                        }
+                        let mut names = vec![Expression::Ident(module)];
+                        if self.next() != Token::Keyword(Keyword::Import) {
+                            return Err(PyException::syntax_error("expected 'import' after the module name"));
+                        }
+                        names.append(&mut self.parse_import_names()?);
+                        return Ok(Expression::Keyword(Keyword::From, names, vec![]));
+                    }
+                    Keyword::With => {
+                        let items = self.parse_with_items()?;
+                        return Ok(Expression::Keyword(Keyword::With, items, vec![]));
+                    }
+                    // assert cond or assert cond, msg
+                    Keyword::Assert => {
+                        let mut args = vec![self.parse_expression(0.0)?];
+                        if self.peek() == Token::Sep(',') {
+                            self.next();
+                            args.push(self.parse_expression(0.0)?);
+                        }
+                        return Ok(Expression::Keyword(Keyword::Assert, args, vec![]));
+                    }
+                    Keyword::Del => {
+                        let mut targets = vec![];
+                        loop {
+                            match self.peek() {
+                                Token::Sep(',') if !targets.is_empty() => {
+                                    self.next();
+                                }
+                                Token::Sep(_) | Token::Eof if !targets.is_empty() => break,
+                                Token::Sep(_) | Token::Eof => return Err(PyException::syntax_error("expected a target after 'del'")),
+                                _ => targets.push(self.parse_expression(0.0)?),
+                            }
+                        }
+                        return Ok(Expression::Keyword(Keyword::Del, targets, vec![]));
+                    }
+                    Keyword::Global | Keyword::Nonlocal => {
+                        let mut names = vec![];
+                        loop {
+                            match self.next() {
+                                Token::Ident(name) => names.push(Expression::Ident(name.to_string())),
+                                Token::Sep(',') if !names.is_empty() => {}
+                                Token::Sep('\n') | Token::Eof if !names.is_empty() => break,
+                                t => return Err(PyException::syntax_error(format!("expected a name after '{keyword}', found {t}"))),
+                            }
+                        }
+                        return Ok(Expression::Keyword(keyword, names, vec![]));
                     }
-                    t => panic!("Unimplemented keyword \'{:?}\' in parse_expression()", t),
+                    t => return Err(PyException::syntax_error(format!("invalid syntax at '{t}'"))),
                 }
             }
+            // -x % y is (-x) % y, the operand is followed by the infix operators
+            Token::Op(op) if op.try_get_prefix_binding().is_some() => {
+                let prefix = op.try_get_prefix_binding().unwrap_or(op);
+                let ((), r_bp) = Op::prefix_binding_power(&prefix);
+                let rhs = self.parse_operand(r_bp)?;
+                Expression::Operation(prefix, vec![rhs])
+            }
             Token::Op(op) => {
-                if let Some(prefix) = op.try_get_prefix_binding() {
-                    let ((), r_bp) = Op::prefix_binding_power(&prefix);
-                    let rhs = self.parse_expression(r_bp);
-                    return Expression::Operation(prefix, vec![rhs]);
-                }
-
                 match op {
                     Op::Colon => {
-                        return Expression::Operation(Op::Colon, vec![]);
+                        return Ok(Expression::Operation(Op::Colon, vec![]));
+                    }
+                    Op::Decorator => {
+                        let decorator = self.parse_expression(0.0)?;
+                        return Ok(Expression::Operation(Op::Decorator, vec![decorator]));
                     }
                     Op::RoundBracketsOpen => {
                         //println!("next: {}", self.peek());
                         if self.peek() == Token::Op(Op::RoundBracketsClose) {
                             //println!("next: {}", self.next());
-                            return Expression::None;
+                            return Ok(Expression::None);
                         } else {
-                            let lhs = self.parse_expression(0.0);
-
-                            let open = self.next();
-                            if open == Token::Op(Op::RoundBracketsClose) {
+                            let lhs = self.parse_expression(0.0)?;
+                            if self.peek() == Token::Keyword(Keyword::For) {
+                                let genexpr = self.parse_comprehension(Op::GenExpr, vec![lhs])?;
+                                if self.next() != Token::Op(Op::RoundBracketsClose) {
+                                    return Err(PyException::syntax_error("expected ')' after generator expression"));
+                                }
+                                genexpr
+                            } else if self.next() == Token::Op(Op::RoundBracketsClose) {
                                 lhs
                             } else {
                                 let mut args = vec![lhs];
                                 loop {
                                     let next = self.peek();
                                     match next {
-                                        Token::Eof => panic!("Expected \')\' at end of file"),
+                                        Token::Eof => return Err(PyException::syntax_error("expected ')' at end of file")),
                                         Token::Op(Op::RoundBracketsClose) => {
                                             self.next();
                                             break;
@@ -557,58 +1000,19 @@ impl<'a> Lexer<'a> {
                                             self.next();
                                             continue;
                                         }
-                                        _ => args.push(self.parse_expression(0.0)),
+                                        _ => args.push(self.parse_expression(0.0)?),
                                     }
                                 }
-                                dbg!(&args);
                                 Expression::Operation(Op::Tuple, args)
                             }
                         }
                     }
-                    Op::SquareBracketsOpen => {
-                        let mut args = vec![];
-                        loop {
-                            let next = self.peek();
-                            match next {
-                                Token::Eof => panic!("Expected \']\' at end of file"),
-                                Token::Op(Op::SquareBracketsClose) => {
-                                    self.next();
-                                    break;
-                                }
-                                Token::Sep(_) => {
-                                    self.next();
-                                    continue;
-                                }
-                                _ => args.push(self.parse_expression(0.0)),
-                            }
-                        }
-                        dbg!(&args);
-                        Expression::Operation(Op::List, args)
-                    }
-                    Op::CurlyBracketsOpen => {
-                        let mut args = vec![];
-                        loop {
-                            let next = self.peek();
-                            match next {
-                                Token::Eof => panic!("Expected \'}}\' at end of file"),
-                                Token::Op(Op::CurlyBracketsClose) => {
-                                    self.next();
-                                    break;
-                                }
-                                Token::Sep(_) => {
-                                    self.next();
-                                    continue;
-                                }
-                                _ => args.push(self.parse_expression(0.0)),
-                            }
-                        }
-                        dbg!(&args);
-                        Expression::Operation(Op::Set, args)
-                    }
-                    t => panic!("Syntax Error: Unimplemented Op: {:?}", t),
+                    Op::SquareBracketsOpen => self.parse_list()?,
+                    Op::CurlyBracketsOpen => self.parse_braces()?,
+                    t => return Err(PyException::syntax_error(format!("invalid syntax at '{t}'"))),
                 }
             }
-            Token::Sep(_) => return Expression::None,
+            Token::Sep(_) => return Ok(Expression::None),
             //t => panic!("Syntax Error: Bad token: {:?}", t),
         };
         loop {
@@ -622,16 +1026,44 @@ impl<'a> Lexer<'a> {
                 _ => break,
             };
 
-            let (l_bp, r_bp) = Op::infix_binding_power(&op);
+            // calling the result of an expression, f(1)(2) or (lambda: 0)(),
+            // and indexing it, x[0], f()[1:2] or d[k][0]
+            let bp_op = match op {
+                Op::RoundBracketsOpen => Op::Call,
+                Op::SquareBracketsOpen => Op::Subscript,
+                op => op,
+            };
+            let Some((l_bp, r_bp)) = Op::infix_binding_power(&bp_op) else {
+                return Err(PyException::syntax_error(format!("invalid syntax at '{op}'")));
+            };
             if l_bp < min_bp {
                 break;
             }
 
             self.next();
-            let rhs = self.parse_expression(r_bp);
-            lhs = Expression::Operation(op, vec![lhs, rhs])
+            lhs = match bp_op {
+                Op::Call => {
+                    let mut args = vec![lhs];
+                    args.append(&mut self.parse_call_args()?);
+                    Expression::Operation(Op::Call, args)
+                }
+                Op::Subscript => Expression::Operation(Op::Subscript, vec![lhs, self.parse_subscript()?]),
+                _ => Expression::Operation(op, vec![lhs, self.parse_operand(r_bp)?]),
+            };
+        }
+        Ok(lhs)
+    }
+
+    // what follows an operator, x = 1 + and x = - leave it out
+    fn parse_operand(&mut self, min_bp: f32) -> Result<Expression, PyException> {
+        match self.peek() {
+            Token::Eof
+            | Token::Sep(_)
+            | Token::Op(Op::RoundBracketsClose | Op::SquareBracketsClose | Op::CurlyBracketsClose) => {
+                Err(PyException::syntax_error("invalid syntax"))
+            }
+            _ => self.parse_expression(min_bp),
         }
-        lhs
     }
 }
 
@@ -653,6 +1085,17 @@ impl std::fmt::Display for Keyword {
             Keyword::None => "None",
             Keyword::Pass => "pass",
             Keyword::Class => "class",
+            Keyword::Yield => "yield",
+            Keyword::YieldFrom => "yield from",
+            Keyword::Lambda => "lambda",
+            Keyword::From => "from",
+            Keyword::Import => "import",
+            Keyword::As => "as",
+            Keyword::With => "with",
+            Keyword::Assert => "assert",
+            Keyword::Del => "del",
+            Keyword::Global => "global",
+            Keyword::Nonlocal => "nonlocal",
         };
         write!(f, "{}", s)
     }
@@ -663,8 +1106,9 @@ pub enum Expression {
     None,
     Ident(String),
     Atom(String),
+    Str(String),
+    Bytes(Vec<u8>),
     Operation(Op, Vec<Expression>),
-    //Func(FnPtr, Vec<Expression>),
     Call(String, Vec<Expression>),
     Keyword(Keyword, Vec<Expression>, Vec<Expression>),
     // Definition(String, Vec<Expression>, String, Vec<Expression>),
@@ -677,32 +1121,77 @@ impl Default for Expression {
 }
 
 impl Expression {
-    pub fn get_value_string(&self) -> String {
+    pub fn get_value_string(&self) -> Result<String, PyException> {
         match self {
-            Expression::Ident(ident) => ident.clone(),
-            Expression::Atom(atom) => atom.clone(),
-            _ => unimplemented!(),
+            Expression::Ident(ident) => Ok(ident.clone()),
+            Expression::Atom(atom) | Expression::Str(atom) => Ok(atom.clone()),
+            e => Err(PyException::syntax_error(format!("expected a name, found {e}"))),
+        }
+    }
+
+    // the value of one str or bytes literal token, r"" keeps its backslashes
+    pub fn from_string_literal(literal: &str) -> Result<Expression, PyException> {
+        let Some((prefix, quoted)) = Utils::split_string_prefix(literal) else {
+            return Err(PyException::syntax_error(format!("invalid string literal: {literal}")));
+        };
+        let prefix = prefix.to_ascii_lowercase();
+        let quotes = match quoted.starts_with(&quoted[..1].repeat(3)) {
+            true => 3,
+            false => 1,
+        };
+        if quoted.len() < quotes * 2 || !quoted.ends_with(&quoted[..quotes]) {
+            return Err(PyException::syntax_error(format!("unterminated string literal: {literal}")));
+        }
+        let body = &quoted[quotes..quoted.len() - quotes];
+        // a backslash escapes the quote after it even in a raw string, so
+        // r"\" has no closing quote
+        if (body.len() - body.trim_end_matches('\\').len()) % 2 == 1 {
+            return Err(PyException::syntax_error(format!("unterminated string literal: {literal}")));
+        }
+        let is_bytes = prefix.contains('b');
+        if prefix.contains('f') {
+            return Expression::from_fstring(body, prefix.contains('r'), literal);
+        }
+        Expression::decode_literal(body, prefix.contains('r'), is_bytes, literal)
+    }
+
+    // the Str or Bytes a literal's body between the quotes stands for
+    fn decode_literal(body: &str, raw: bool, is_bytes: bool, literal: &str) -> Result<Expression, PyException> {
+        let decoded = match raw {
+            true => match is_bytes && !body.is_ascii() {
+                true => Err("bytes can only contain ASCII literal characters".to_string()),
+                false => Ok(body.chars().map(|c| c as u32).collect()),
+            },
+            false => Utils::unescape(body, is_bytes),
+        };
+        let decoded = match decoded {
+            Ok(decoded) => decoded,
+            Err(msg) => return Err(PyException::syntax_error(format!("{msg} in {literal}"))),
+        };
+        match is_bytes {
+            true => Ok(Expression::Bytes(decoded.into_iter().map(|b| b as u8).collect())),
+            false => match decoded.into_iter().map(char::from_u32).collect() {
+                Some(s) => Ok(Expression::Str(s)),
+                None => Err(PyException::syntax_error(format!("surrogates are not allowed in {literal}"))),
+            },
         }
     }
 
-    pub fn from_multiline(input: &str) -> Vec<Expression> {
-        let lines: Vec<&str> = input.lines().collect();
+    pub fn from_multiline(input: &str) -> Result<Vec<Expression>, PyException> {
+        let lines = Utils::logical_lines(input);
         let mut exprs: Vec<Expression> = vec![];
         let mut block_stack: Vec<(usize, Expression, Vec<Expression>)> = vec![];
+        let mut decorators: Vec<Expression> = vec![];
 
-        for line in lines {
+        for line in &lines {
             // println!("{}", line);
-            let mut trimmed = line.trim();
+            let trimmed = line.trim();
             if trimmed.is_empty() {
                 continue;
             }
 
-            if let Some((code, _comment)) = trimmed.split_once('#') {
-                trimmed = code;
-            }
-
             let indent = crate::pyrs_utils::get_indent(line);
-            let expr = Expression::from_line(trimmed);
+            let expr = Expression::from_line(trimmed)?;
 
             let is_elif_else = trimmed.starts_with("elif ") || trimmed.starts_with("else:");
             let is_if_like = matches!(
@@ -711,8 +1200,7 @@ impl Expression {
             );
 
             // Close blocks if indentation decreased
-            while !block_stack.is_empty() {
-                let (block_indent, _, _) = block_stack.last().unwrap();
+            while let Some((block_indent, _, _)) = block_stack.last() {
 
                 if is_elif_else && is_if_like && indent == *block_indent {
                     break;
@@ -722,10 +1210,10 @@ impl Expression {
                     break;
                 }
 
-                let (_, mut keyword_expr, body) = block_stack.pop().unwrap();
-                if let Expression::Keyword(kw, cond, _) = keyword_expr {
-                    keyword_expr = Expression::Keyword(kw, cond, body);
-                }
+                let Some((_, keyword_expr, body)) = block_stack.pop() else {
+                    break;
+                };
+                let keyword_expr = keyword_expr.with_body(body);
 
                 if let Some((_, _, parent_body)) = block_stack.last_mut() {
                     parent_body.push(keyword_expr);
@@ -734,6 +1222,27 @@ impl Expression {
                 }
             }
 
+            // decorators wait for the def or class they go on
+            if let Expression::Operation(Op::Decorator, mut d) = expr {
+                decorators.append(&mut d);
+                continue;
+            }
+            let expr = match decorators.is_empty() {
+                true => expr,
+                false => match expr {
+                    Expression::Keyword(Keyword::Def | Keyword::Class, _, _) => {
+                        let mut args = std::mem::take(&mut decorators);
+                        args.push(expr);
+                        Expression::Operation(Op::Decorator, args)
+                    }
+                    e => {
+                        return Err(PyException::syntax_error(format!(
+                            "decorators must be followed by a def or class, not {e}"
+                        )))
+                    }
+                },
+            };
+
             if trimmed.ends_with(":") {
                 if is_elif_else && is_if_like && !block_stack.is_empty() {
                     if let Some((_, _, body)) = block_stack.last_mut() {
@@ -750,45 +1259,216 @@ impl Expression {
         }
 
         // Finalize remaining blocks
-        while let Some((_, mut keyword_expr, body)) = block_stack.pop() {
-            if let Expression::Keyword(kw, cond, _) = keyword_expr {
-                keyword_expr = Expression::Keyword(kw, cond, body);
+        while let Some((_, keyword_expr, body)) = block_stack.pop() {
+            let keyword_expr = keyword_expr.with_body(body);
+            match block_stack.last_mut() {
+                Some((_, _, parent_body)) => parent_body.push(keyword_expr),
+                None => exprs.push(keyword_expr),
             }
-            exprs.push(keyword_expr);
         }
         //Expression::print_vec(&exprs);
         //panic!();
-        exprs
+        Ok(exprs)
+    }
+
+    // fills in the body of a block's first line, through any decorators on it
+    fn with_body(self, body: Vec<Expression>) -> Expression {
+        match self {
+            Expression::Keyword(kw, cond, _) => Expression::Keyword(kw, cond, body),
+            Expression::Operation(Op::Decorator, mut args) => {
+                if let Some(definition) = args.pop() {
+                    args.push(definition.with_body(body));
+                }
+                Expression::Operation(Op::Decorator, args)
+            }
+            e => e,
+        }
     }
 
-    pub fn from_line(input: &str) -> Expression {
+    pub fn from_line(input: &str) -> Result<Expression, PyException> {
         let word_list = Utils::split_to_words(&input);
-        let mut token_list = Lexer::from(&word_list);
+        let mut token_list = Lexer::from(&word_list)?;
+
+        token_list.parse_expression(0f32)
+    }
+
+    // f"a{x!r:>{width}}" is Op[fstring Str(a) Op[format Ident(x) Str(r) Op[fstring Str(>) ...]]],
+    // the format has the value, the conversion or None and the spec or None
+    fn from_fstring(body: &str, raw: bool, literal: &str) -> Result<Expression, PyException> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = body.char_indices().peekable();
+
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '{' | '}' if chars.next_if(|&(_, next)| next == c).is_some() => text.push(c),
+                '}' => {
+                    return Err(PyException::syntax_error(format!(
+                        "f-string: single '}}' is not allowed in {literal}"
+                    )))
+                }
+                // the braces of \N{...} aren't a field
+                '\\' if !raw => {
+                    text.push(c);
+                    if let Some((_, escape)) = chars.next() {
+                        text.push(escape);
+                        if escape == 'N' && chars.peek().is_some_and(|&(_, c)| c == '{') {
+                            for (_, c) in chars.by_ref() {
+                                text.push(c);
+                                if c == '}' {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                '{' => {
+                    parts.push(Expression::decode_literal(&text, raw, false, literal)?);
+                    text.clear();
+                    let end = Expression::from_fstring_field(body, idx + 1, raw, literal, &mut parts)?;
+                    while chars.next_if(|&(i, _)| i < end).is_some() {}
+                }
+                c => text.push(c),
+            }
+        }
+        parts.push(Expression::decode_literal(&text, raw, false, literal)?);
+        Ok(Expression::join_fstring(parts))
+    }
+
+    // {expr=!conv:spec} starting just after the '{', pushes the text of a
+    // self documenting = and the format, gives the index past the '}'
+    fn from_fstring_field(body: &str, start: usize, raw: bool, literal: &str, parts: &mut Vec<Expression>) -> Result<usize, PyException> {
+        let field = &body[start..];
+        let mut depth = 0;
+        let mut quote = None;
+        let mut self_doc = false;
+        let mut prev = ' ';
+        let mut expr_end = None;
+        for (i, c) in field.char_indices() {
+            let next = field[i + c.len_utf8()..].chars().next();
+            match (c, quote) {
+                (c, Some(q)) if c == q => quote = None,
+                (_, Some(_)) => {}
+                ('\'' | '"', None) => quote = Some(c),
+                ('(' | '[' | '{', None) => depth += 1,
+                (')' | ']' | '}', None) if depth > 0 => depth -= 1,
+                ('!', None) if depth == 0 && next != Some('=') => expr_end = Some(i),
+                ('=', None) if depth == 0 && next != Some('=') && !"=!<>".contains(prev) => self_doc = true,
+                ('}' | ':', None) if depth == 0 => expr_end = Some(i),
+                _ => {}
+            }
+            if expr_end.is_some() {
+                break;
+            }
+            prev = c;
+        }
+        let expecting_brace = || PyException::syntax_error(format!("f-string: expecting '}}' in {literal}"));
+        let Some(expr_end) = expr_end else {
+            return Err(expecting_brace());
+        };
+
+        let mut expr_text = &field[..expr_end];
+        if self_doc {
+            parts.push(Expression::Str(expr_text.to_string()));
+            expr_text = expr_text.rsplit_once('=').map_or(expr_text, |(text, _)| text);
+        }
+        if expr_text.trim().is_empty() {
+            return Err(PyException::syntax_error(format!(
+                "f-string: valid expression required before '}}' in {literal}"
+            )));
+        }
+        let value = Expression::from_line(expr_text.trim())?;
+
+        let mut rest = &field[expr_end..];
+        let mut conversion = Expression::None;
+        if let Some(after) = rest.strip_prefix('!') {
+            match after.chars().next() {
+                Some(c @ ('r' | 's' | 'a')) => conversion = Expression::Str(c.to_string()),
+                _ => {
+                    return Err(PyException::syntax_error(format!(
+                        "f-string: invalid conversion character in {literal}"
+                    )))
+                }
+            }
+            rest = &after[1..];
+        }
+        // a self documenting field shows the repr unless it has a spec
+        if self_doc && conversion == Expression::None && !rest.starts_with(':') {
+            conversion = Expression::Str("r".to_string());
+        }
 
-        let expr = token_list.parse_expression(0f32);
-        expr
+        let mut spec = Expression::None;
+        if let Some(after) = rest.strip_prefix(':') {
+            // the spec can have fields of its own, it ends at the brace matching ours
+            let mut depth = 0;
+            let spec_end = after.char_indices().find_map(|(i, c)| match c {
+                '{' => {
+                    depth += 1;
+                    None
+                }
+                '}' if depth > 0 => {
+                    depth -= 1;
+                    None
+                }
+                '}' => Some(i),
+                _ => None,
+            });
+            let Some(spec_end) = spec_end else {
+                return Err(expecting_brace());
+            };
+            spec = Expression::from_fstring(&after[..spec_end], raw, literal)?;
+            rest = &after[spec_end..];
+        }
+        if !rest.starts_with('}') {
+            return Err(expecting_brace());
+        }
+        parts.push(Expression::Operation(Op::FormatValue, vec![value, conversion, spec]));
+        Ok(start + (field.len() - rest.len()) + 1)
+    }
+
+    // joins strings and f-strings into one, neighbouring Strs are merged
+    // and an f-string with nothing to format is just a Str
+    fn join_fstring(pieces: Vec<Expression>) -> Expression {
+        let mut parts: Vec<Expression> = vec![];
+        for piece in pieces {
+            let piece_parts = match piece {
+                Expression::Operation(Op::FString, piece_parts) => piece_parts,
+                p => vec![p],
+            };
+            for part in piece_parts {
+                match (parts.last_mut(), part) {
+                    (_, Expression::Str(s)) if s.is_empty() => {}
+                    (Some(Expression::Str(last)), Expression::Str(s)) => last.push_str(&s),
+                    (_, part) => parts.push(part),
+                }
+            }
+        }
+        match parts.as_slice() {
+            [] => Expression::Str(String::new()),
+            [Expression::Str(_)] => parts.pop().unwrap(),
+            _ => Expression::Operation(Op::FString, parts),
+        }
     }
 
     pub fn is_assign(&self) -> Option<(String, &Expression)> {
         match self {
             Expression::None => return None,
             //Expression::Func(_, _) => return None,
-            Expression::Atom(_) => return None,
+            Expression::Atom(_) | Expression::Str(_) | Expression::Bytes(_) => None,
             Expression::Ident(_) => return None,
             Expression::Keyword(_, _, _) => return None,
             Expression::Call(_, _) => return None,
             Expression::Operation(c, operands) => {
                 if *c == Op::Equals {
-                    let var_name = match operands.first().unwrap() {
+                    let var_name = match operands.first()? {
                         Expression::Atom(c) => c.to_string(),
                         Expression::Ident(ident) => ident.to_string(),
-                        Expression::Keyword(kw, _cond, _args) => {
-                            println!("Syntax Error: cannot assign to {}", kw);
+                        e => {
+                            println!("Syntax Error: cannot assign to {}", e);
                             return None;
                         }
-                        _ => unreachable!(),
                     };
-                    return Some((var_name, operands.last().unwrap()));
+                    return Some((var_name, operands.last()?));
                 }
                 return None;
             }
@@ -799,12 +1479,14 @@ impl Expression {
     pub fn eval(
         &self,
         variables: &mut HashMap<String, Arc<Obj>>,
-        funcs: &mut HashMap<String, FnPtr>,
+        vm: &mut PyVM,
     ) -> Result<Arc<Obj>, PyException> {
         // println!("Eval: {self}");
         let ret: Arc<Obj> = match self {
             Expression::None => Obj::None.into(),
             Expression::Atom(c) => Obj::from_atom(c).into(),
+            Expression::Str(s) => Obj::Str(s.clone()).into(),
+            Expression::Bytes(b) => Obj::Bytes(b.clone()).into(),
             Expression::Ident(ident) => {
                 let obj = match variables.get(ident) {
                     Some(var) => var.clone(),
@@ -819,82 +1501,264 @@ impl Expression {
                 };
                 obj
             }
-            Expression::Operation(operator, operands) => {
+            Expression::Operation(operator, operands) => match (operator, operands.as_slice()) {
                 // assign
-                let first = operands.first().unwrap();
-                if *operator == Op::Equals {
-                    let value = operands
-                        .get(1)
-                        .unwrap()
-                        .eval(&mut *variables, &mut *funcs)?;
-                    let var_name = first.get_value_string();
-                    variables.insert(var_name, value.clone());
-                    return Ok(value);
-                } else if *operator == Op::List {
+                (Op::Equals, [target, value]) => {
+                    let value = value.eval(&mut *variables, &mut *vm)?;
+                    variables.insert(target.get_value_string()?, value.clone());
+                    value
+                }
+                (Op::List, _) => {
                     let mut objs: Vec<Arc<Obj>> = vec![];
                     for o in operands {
-                        let obj = o.eval(variables, funcs)?;
-                        objs.push(Arc::from(obj));
+                        objs.push(o.eval(variables, vm)?);
                     }
-                    return Ok(Obj::List(objs).into());
+                    Obj::new_list(objs).into()
                 }
 
                 // unary
-                let rhs = operands
-                    .get(1)
-                    .unwrap()
-                    .eval(&mut *variables, &mut *funcs)?;
-                let lhs = first.eval(&mut *variables, &mut *funcs)?;
-                match operator {
-                    Op::Pos => return Obj::__pos__(&lhs),
-                    Op::Neg => return Obj::__neg__(&lhs),
-                    _ => {}
-                };
+                (Op::Pos, [operand]) => Obj::__pos__(&operand.eval(&mut *variables, &mut *vm)?)?,
+                (Op::Neg, [operand]) => Obj::__neg__(&operand.eval(&mut *variables, &mut *vm)?)?,
 
                 // binary
-                let val: Arc<Obj> = match operator {
-                    Op::Plus => PyObj::__add__(&lhs, &rhs)?,
-                    Op::Minus => PyObj::__sub__(&lhs, &rhs)?,
-                    Op::Asterisk => PyObj::__mul__(&lhs, &rhs)?,
-                    Op::ForwardSlash => PyObj::__div__(&lhs, &rhs)?,
-                    Op::Eq => PyObj::__eq__(&lhs, &rhs).to_arc(),
-                    Op::Neq => PyObj::__ne__(&lhs, &rhs).to_arc(),
-                    Op::LessThan => PyObj::__lt__(&lhs, &rhs).to_arc(),
-                    Op::GreaterThan => PyObj::__gt__(&lhs, &rhs).to_arc(),
-                    Op::LessEq => PyObj::__le__(&lhs, &rhs).to_arc(),
-                    Op::GreaterEq => PyObj::__ge__(&lhs, &rhs).to_arc(),
-                    Op::Equals => Obj::__default__().into(),
-                    op => panic!("Bad operator: {}", op),
+                (operator, [lhs, rhs]) => {
+                    let lhs = lhs.eval(&mut *variables, &mut *vm)?;
+                    let rhs = rhs.eval(&mut *variables, &mut *vm)?;
+                    match operator {
+                        Op::Plus => PyObj::__add__(&lhs, &rhs)?,
+                        Op::Minus => PyObj::__sub__(&lhs, &rhs)?,
+                        Op::Asterisk => PyObj::__mul__(&lhs, &rhs)?,
+                        Op::ForwardSlash => PyObj::__div__(&lhs, &rhs)?,
+                        Op::Eq => PyObj::__eq__(&lhs, &rhs).to_arc(),
+                        Op::Neq => PyObj::__ne__(&lhs, &rhs).to_arc(),
+                        Op::LessThan => PyObj::__lt__(&lhs, &rhs).to_arc(),
+                        Op::GreaterThan => PyObj::__gt__(&lhs, &rhs).to_arc(),
+                        Op::LessEq => PyObj::__le__(&lhs, &rhs).to_arc(),
+                        Op::GreaterEq => PyObj::__ge__(&lhs, &rhs).to_arc(),
+                        op => {
+                            return Err(PyException {
+                                error: PyError::NotImplementedError,
+                                msg: format!("the operator {op} is not supported here"),
+                            })
+                        }
+                    }
+                }
+                (op, _) => {
+                    return Err(PyException {
+                        error: PyError::NotImplementedError,
+                        msg: format!("the operator {op} is not supported here"),
+                    })
+                }
+            },
+            Expression::Call(name, args) => {
+                // a variable first, then the builtins namespace the VM uses
+                let func = match variables.get(name) {
+                    Some(var) => var.clone(),
+                    None => match builtins::lookup_name(name) {
+                        Some(val) => val.into(),
+                        None => {
+                            return Err(PyException {
+                                error: PyError::UndefinedVariableError,
+                                msg: format!("name '{name}' is not defined"),
+                            })
+                        }
+                    },
                 };
-                val
-            }
-            Expression::Call(_name, _args) => {
-                panic!();
+                let mut pos = vec![];
+                let mut kwargs = vec![];
+                for a in args {
+                    match a {
+                        Expression::Operation(Op::Equals, kv) => {
+                            kwargs.push((kv[0].get_value_string()?, kv[1].eval(&mut *variables, &mut *vm)?))
+                        }
+                        a => pos.push(a.eval(&mut *variables, &mut *vm)?),
+                    }
+                }
+                vm.call_object(func, pos, kwargs)?
             }
             Expression::Keyword(keyword, conds, _args) => match keyword {
                 Keyword::True => true.to_arc(),
                 Keyword::False => false.to_arc(),
                 Keyword::If | Keyword::While => {
-                    let condition = conds
-                        .iter()
-                        .map(|x| x.eval(&mut *variables, &mut *funcs).unwrap().__bool__())
-                        .all(|x| x);
+                    let mut condition = true;
+                    for x in conds {
+                        condition &= x.eval(&mut *variables, &mut *vm)?.__bool__();
+                    }
                     condition.to_arc()
                 }
-                _ => panic!("Unimplemented Keyword: {:?}", keyword),
-            }, /*
-               Expression::Func(func, vals) => {
-                   let mut args: Vec<Arc<Obj>> = vec![];
-                   for val in vals {
-                       args.push(val.eval(&mut *variables, &mut *funcs)?);
-                   }
-                   (func.ptr)(&args)
-               }
-               */
+                _ => {
+                    return Err(PyException {
+                        error: PyError::NotImplementedError,
+                        msg: format!("'{keyword}' is not supported here"),
+                    })
+                }
+            },
         };
         Ok(ret)
     }
 
+    // the names a body declares global or nonlocal, anywhere but in nested defs and classes
+    pub fn declared_names(exprs: &[Expression], declaration: Keyword) -> Vec<String> {
+        let mut names = vec![];
+        for e in exprs {
+            match e {
+                Expression::Keyword(k, decl, _) if *k == declaration => {
+                    names.extend(decl.iter().filter_map(|n| n.get_value_string().ok()))
+                }
+                Expression::Keyword(Keyword::Def | Keyword::Class, _, _) => {}
+                Expression::Keyword(_, _, body) => names.append(&mut Expression::declared_names(body, declaration)),
+                _ => {}
+            }
+        }
+        names
+    }
+
+    // Like CPython's symtable the names a scope binds and reads are recorded
+    // as its body goes, a global or nonlocal after either is a SyntaxError.
+    // Nested defs and classes only bind their name, lambdas and
+    // comprehensions are scopes of their own
+    pub fn check_declarations(exprs: &[Expression]) -> Result<(), PyException> {
+        Expression::record_all(exprs, &mut vec![], &mut vec![])
+    }
+
+    fn record_all(exprs: &[Expression], assigned: &mut Vec<String>, used: &mut Vec<String>) -> Result<(), PyException> {
+        exprs.iter().try_for_each(|e| e.record_names(assigned, used))
+    }
+
+    fn record_names(&self, assigned: &mut Vec<String>, used: &mut Vec<String>) -> Result<(), PyException> {
+        match self {
+            Expression::Ident(name) => used.push(name.clone()),
+            Expression::Keyword(keyword @ (Keyword::Global | Keyword::Nonlocal), names, _) => {
+                for name in names.iter().filter_map(|n| n.get_value_string().ok()) {
+                    let prior = match (used.contains(&name), assigned.contains(&name)) {
+                        (true, _) => "used prior to",
+                        (false, true) => "assigned to before",
+                        (false, false) => continue,
+                    };
+                    return Err(PyException::syntax_error(format!("name '{name}' is {prior} {keyword} declaration")));
+                }
+            }
+            Expression::Keyword(Keyword::Def, args, _) => {
+                for a in args.iter().skip(1) {
+                    if let Expression::Operation(Op::Equals, param) = a {
+                        Expression::record_all(param.get(1..).unwrap_or_default(), assigned, used)?;
+                    }
+                }
+                if let Some(name) = args.first() {
+                    name.record_target(assigned, used)?;
+                }
+            }
+            Expression::Keyword(Keyword::Class, args, _) => match args.last() {
+                Some(Expression::Call(name, bases)) => {
+                    Expression::record_args(bases, assigned, used)?;
+                    assigned.push(name.clone());
+                }
+                Some(name) => name.record_target(assigned, used)?,
+                None => {}
+            },
+            Expression::Keyword(Keyword::For, conds, body) => {
+                if let [target, iter] = conds.as_slice() {
+                    iter.record_names(assigned, used)?;
+                    target.record_target(assigned, used)?;
+                }
+                Expression::record_all(body, assigned, used)?;
+            }
+            Expression::Keyword(Keyword::With, items, body) => {
+                for item in items {
+                    match item {
+                        Expression::Keyword(Keyword::As, conds, _) if conds.len() == 2 => {
+                            conds[0].record_names(assigned, used)?;
+                            conds[1].record_target(assigned, used)?;
+                        }
+                        item => item.record_names(assigned, used)?,
+                    }
+                }
+                Expression::record_all(body, assigned, used)?;
+            }
+            Expression::Keyword(Keyword::Del, targets, _) => {
+                targets.iter().try_for_each(|t| t.record_target(assigned, used))?;
+            }
+            // an import may come before the declaration in CPython too
+            Expression::Keyword(Keyword::Lambda | Keyword::Import | Keyword::From, _, _) => {}
+            Expression::Keyword(_, conds, body) => {
+                Expression::record_all(conds, assigned, used)?;
+                Expression::record_all(body, assigned, used)?;
+            }
+            Expression::Operation(Op::GenExpr | Op::ListComp | Op::SetComp | Op::DictComp, _) => {}
+            Expression::Operation(
+                Op::Equals
+                | Op::AddEquals
+                | Op::SubEquals
+                | Op::MulEquals
+                | Op::DivEquals
+                | Op::FloorDivEquals
+                | Op::ModEquals
+                | Op::PowEquals,
+                args,
+            ) => {
+                Expression::record_all(args.get(1..).unwrap_or_default(), assigned, used)?;
+                if let Some(target) = args.first() {
+                    target.record_target(assigned, used)?;
+                }
+            }
+            Expression::Operation(Op::Dot, sides) => match sides.as_slice() {
+                [obj, Expression::Call(_, args)] => {
+                    obj.record_names(assigned, used)?;
+                    Expression::record_args(args, assigned, used)?;
+                }
+                [obj, ..] => obj.record_names(assigned, used)?,
+                [] => {}
+            },
+            Expression::Operation(Op::Call, args) => Expression::record_args(args, assigned, used)?,
+            Expression::Call(name, args) => {
+                used.push(name.clone());
+                Expression::record_args(args, assigned, used)?;
+            }
+            Expression::Operation(_, args) => Expression::record_all(args, assigned, used)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // a name is bound, what an attribute or subscript target is taken from is read
+    fn record_target(&self, assigned: &mut Vec<String>, used: &mut Vec<String>) -> Result<(), PyException> {
+        match self {
+            Expression::Ident(name) => assigned.push(name.clone()),
+            Expression::Operation(Op::Tuple | Op::List | Op::Equals, targets) => {
+                targets.iter().try_for_each(|t| t.record_target(assigned, used))?
+            }
+            target => target.record_names(assigned, used)?,
+        }
+        Ok(())
+    }
+
+    // call arguments, the name of a keyword argument isn't one of the scope's
+    fn record_args(args: &[Expression], assigned: &mut Vec<String>, used: &mut Vec<String>) -> Result<(), PyException> {
+        for a in args {
+            match a {
+                Expression::Operation(Op::Equals, kv) => Expression::record_all(kv.get(1..).unwrap_or_default(), assigned, used)?,
+                a => a.record_names(assigned, used)?,
+            }
+        }
+        Ok(())
+    }
+
+    // true if a function body yields, nested defs and genexprs are their own scope
+    pub fn contains_yield(exprs: &[Expression]) -> bool {
+        exprs.iter().any(|e| match e {
+            Expression::Keyword(Keyword::Yield | Keyword::YieldFrom, _, _) => true,
+            Expression::Keyword(Keyword::Def | Keyword::Lambda | Keyword::Class, _, _) => false,
+            Expression::Keyword(_, conds, body) => {
+                Expression::contains_yield(conds) || Expression::contains_yield(body)
+            }
+            Expression::Operation(Op::GenExpr | Op::ListComp | Op::SetComp | Op::DictComp, _) => false,
+            Expression::Operation(_, args) | Expression::Call(_, args) => {
+                Expression::contains_yield(args)
+            }
+            _ => false,
+        })
+    }
+
     pub fn print_vec(exprs: &Vec<Expression>) {
         for e in exprs {
             println!("{e}");
@@ -940,6 +1804,8 @@ impl std::fmt::Display for Expression {
         match self {
             Expression::None => write!(f, "None"),
             Expression::Atom(i) => write!(f, "Atom({})", i),
+            Expression::Str(s) => write!(f, "Str({})", s),
+            Expression::Bytes(b) => write!(f, "Bytes({})", Obj::Bytes(b.clone()).__repr__()),
             Expression::Ident(ident) => write!(f, "Ident({})", ident),
             Expression::Call(name, args) => {
                 write!(f, "Call[{name} args[")?;
@@ -965,15 +1831,7 @@ impl std::fmt::Display for Expression {
                     write!(f, " {}", a)?;
                 }
                 write!(f, "]]")
-            } /*
-              Expression::Func(func, args) => {
-                  write!(f, "Func[{} args[", func)?;
-                  for a in args {
-                      write!(f, " {}", a)?;
-                  }
-                  write!(f, "]]")
-              }
-              */
+            }
         }
     }
 }
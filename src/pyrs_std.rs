@@ -1,137 +1,695 @@
 use crate::{
+    pyrs_bytes as bytes,
+    pyrs_decimal as decimal,
     pyrs_error::{PyError, PyException},
-    pyrs_obj::{Obj, ToObj},
+    pyrs_format,
+    pyrs_obj::{rational_to_f64, Obj, PyObj, ToObj},
+    pyrs_utils as Utils,
+    pyrs_vm::PyVM,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
-use rug::Integer;
+use rug::{integer::Order, ops::Pow, Complex, Integer, Rational};
 
-pub trait Import {
-    fn get_name() -> &'static str;
-    fn try_get(name: &str) -> Option<FnPtr>;
-}
+// the types a name like int resolves to, see PyVM::load_name
+pub const BUILTIN_TYPES: [&str; 17] = [
+    "int", "float", "complex", "str", "bool", "bytes", "bytearray", "memoryview", "list", "tuple", "set", "frozenset",
+    "dict", "slice", "range", "type", "super",
+];
+
+// The signature every function written in Rust has, positional then
+// keyword args. It gets the VM so it can call back into Python code
+pub type NativeFn = fn(&mut PyVM, &[Arc<Obj>], &[(String, Arc<Obj>)]) -> Result<Arc<Obj>, PyException>;
 
 #[derive(Debug, Clone)]
-pub struct FnPtr {
-    pub ptr: fn(&Vec<Arc<Obj>>) -> Arc<Obj>,
-    pub name: String,
+pub struct NativeFunc {
+    pub name: &'static str,
+    pub ptr: NativeFn,
 }
 
-impl PartialEq for FnPtr {
-    fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
-    }
-    fn ne(&self, other: &Self) -> bool {
-        self.name != other.name
+impl NativeFunc
+{
+    pub fn call(
+        &self,
+        vm: &mut PyVM,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+    ) -> Result<Arc<Obj>, PyException> {
+        (self.ptr)(vm, args, kwargs)
     }
 }
-impl PartialOrd for FnPtr {
-    fn partial_cmp(&self, _other: &Self) -> Option<std::cmp::Ordering> {
-        None
+
+impl PartialEq for NativeFunc {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
     }
 }
 
-impl std::fmt::Display for FnPtr {
+impl std::fmt::Display for NativeFunc {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.name)
+        write!(f, "<built-in function {}>", self.name)
     }
 }
 
 pub struct Funcs {}
 
 impl Funcs {
-    pub fn get_std_map() -> HashMap<String, FnPtr> {
-        let mut func_map: HashMap<String, FnPtr> = HashMap::new();
-        func_map.insert(
-            "print".to_string(),
-            FnPtr {
-                ptr: Funcs::print,
-                name: "print".to_string(),
+    // float(x), a string may have whitespace around it, underscores
+    // between its digits, or be inf, infinity or nan in any case
+    pub fn float(obj: &Obj) -> Result<Obj, PyException> {
+        let ret = match obj {
+            Obj::Float(_) => obj.clone(),
+            Obj::Int(_) | Obj::Bool(_) | Obj::Fraction(_) | Obj::Decimal(_) => Obj::Float(obj.to_float()?),
+            Obj::Str(s) => match Funcs::parse_float(s) {
+                Some(f) => Obj::Float(f),
+                None => {
+                    return Err(PyException {
+                        error: PyError::ValueError,
+                        msg: format!("could not convert string to float: {}", obj.__repr__()),
+                    });
+                }
+            },
+            _ => {
+                return Err(PyException::type_error(format!("float() argument must be a string or a real number, not '{}'", obj.type_name())));
+            }
+        };
+        Ok(ret)
+    }
+
+    fn parse_float(s: &str) -> Option<f64> {
+        let s = s.trim().to_ascii_lowercase();
+        let (negative, body) = match s.strip_prefix('-') {
+            Some(body) => (true, body),
+            None => (false, s.strip_prefix('+').unwrap_or(&s)),
+        };
+        let val = match body {
+            "inf" | "infinity" => f64::INFINITY,
+            "nan" => f64::NAN,
+            _ => {
+                let digits = Utils::strip_underscores(body, 10)?;
+                if !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+                    || !digits.bytes().all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'+' | b'-'))
+                {
+                    return None;
+                }
+                digits.parse::<f64>().ok()?
+            }
+        };
+        Some(if negative { -val } else { val })
+    }
+
+    // complex(real=0, imag=0) is real + imag * 1j, or complex(string)
+    // float(x=0.0)
+    pub fn call_float(args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+        match args {
+            [] => Ok(Obj::Float(0.0)),
+            [arg] => Funcs::float(arg),
+            _ => Err(PyException::type_error(format!("float expected at most 1 argument, got {}", args.len()))),
+        }
+    }
+
+    // the methods of float the VM implements, see PyVM::call_builtin_method
+    pub fn float_method(val: f64, name: &str, args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+        if !args.is_empty() {
+            return Err(PyException::type_error(format!("float.{name}() takes no arguments ({} given)", args.len())));
+        }
+        match name {
+            "is_integer" => Ok(Obj::Bool(val.is_finite() && val.fract() == 0.0)),
+            "hex" => Ok(Obj::Str(float_hex(val))),
+            // the smallest ints whose ratio is exactly val
+            "as_integer_ratio" => match Rational::from_f64(val) {
+                Some(ratio) => {
+                    let (numer, denom) = ratio.into_numer_denom();
+                    Ok(Obj::Tuple(vec![Obj::Int(numer).into(), Obj::Int(denom).into()]))
+                }
+                None if val.is_nan() => Err(PyException {
+                    error: PyError::ValueError,
+                    msg: "cannot convert NaN to integer ratio".to_string(),
+                }),
+                None => Err(PyException {
+                    error: PyError::OverflowError,
+                    msg: "cannot convert Infinity to integer ratio".to_string(),
+                }),
             },
-        );
-        func_map.insert(
-            "print_ret".to_string(),
-            FnPtr {
-                ptr: Funcs::print_ret,
-                name: "print_ret".to_string(),
+            _ => Err(PyException {
+                error: PyError::AttributeError,
+                msg: format!("'float' object has no attribute '{name}'"),
+            }),
+        }
+    }
+
+    // float.fromhex(s), the inverse of float.hex()
+    pub fn float_fromhex(args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+        match args {
+            [arg] => match arg.as_ref() {
+                Obj::Str(s) => parse_hex_float(s).map(Obj::Float),
+                _ => Err(PyException::type_error("bad argument type for built-in operation")),
             },
-        );
-        return func_map;
+            _ => Err(PyException::type_error(format!("float.fromhex() takes exactly one argument ({} given)", args.len()))),
+        }
     }
 
-    pub fn print(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        let mut msg = String::new();
-        for arg in args {
-            msg += &(format!("{} ", arg).as_str());
+    // round(number, ndigits=None), a half goes to the even side. Without
+    // ndigits the result is an int, with them it has the type of number
+    pub fn round(args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+        let (number, ndigits) = match args {
+            [] => return Err(PyException::type_error("round() missing required argument 'number' (pos 1)")),
+            [number] => (number, None),
+            [number, ndigits] => match ndigits.as_ref() {
+                Obj::None => (number, None),
+                o => match o.to_integer() {
+                    Some(ndigits) => (number, Some(ndigits)),
+                    None => {
+                        return Err(PyException::type_error(format!(
+                            "'{}' object cannot be interpreted as an integer",
+                            o.type_name()
+                        )))
+                    }
+                },
+            },
+            _ => return Err(PyException::type_error(format!("round() takes at most 2 arguments ({} given)", args.len()))),
+        };
+        match (number.as_ref(), ndigits) {
+            (Obj::Float(f), None) if f.is_nan() => Err(PyException {
+                error: PyError::ValueError,
+                msg: "cannot convert float NaN to integer".to_string(),
+            }),
+            (Obj::Float(f), None) => Integer::from_f64(f.round_ties_even()).map(Obj::Int).ok_or(PyException {
+                error: PyError::OverflowError,
+                msg: "cannot convert float infinity to integer".to_string(),
+            }),
+            (Obj::Float(f), Some(ndigits)) => {
+                let ndigits = ndigits.to_i64().unwrap_or(if ndigits < 0 { i64::MIN } else { i64::MAX });
+                round_float(*f, ndigits).map(Obj::Float)
+            }
+            (Obj::Int(_) | Obj::Bool(_), ndigits) => {
+                let int = number.to_integer().unwrap();
+                match ndigits {
+                    Some(ndigits) if ndigits < 0 => Ok(Obj::Int(round_int(int, ndigits.abs()))),
+                    _ => Ok(Obj::Int(int)),
+                }
+            }
+            (Obj::Fraction(r), None) => Ok(Obj::Int(round_half_even(r.clone()))),
+            (Obj::Fraction(r), Some(ndigits)) => {
+                let scale = Rational::from(Integer::from(10).pow(ndigits.as_abs().to_u32().unwrap_or(u32::MAX)));
+                Ok(Obj::Fraction(match ndigits >= 0 {
+                    true => Rational::from(round_half_even(r.clone() * &scale)) / scale,
+                    false => Rational::from(round_half_even(r.clone() / &scale)) * scale,
+                }))
+            }
+            (Obj::Decimal(d), None) => d.round_to_int().map(Obj::Int),
+            (Obj::Decimal(d), Some(ndigits)) => {
+                let ndigits = ndigits.to_i64().unwrap_or(if ndigits < 0 { i64::MIN } else { i64::MAX });
+                d.round_to(ndigits).map(|d| Obj::Decimal(Box::new(d)))
+            }
+            (o, _) => Err(PyException::type_error(format!("type {} doesn't define __round__ method", o.type_name()))),
         }
-        println!("{}", msg);
-        Arc::from(Obj::None)
     }
 
-    pub fn print_ret(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        let mut msg = String::new();
-        for arg in args {
-            msg += &(format!("{} ", arg).as_str());
+    pub fn complex(args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+        let zero = || Complex::new(53);
+        let real = match args.first().map(|a| a.as_ref()) {
+            None => zero(),
+            Some(Obj::Str(_)) if args.len() > 1 => {
+                return Err(PyException::type_error("complex() can't take second arg if first is a string"))
+            }
+            Some(Obj::Str(s)) => Funcs::parse_complex(s).ok_or_else(|| PyException {
+                error: PyError::ValueError,
+                msg: "complex() arg is a malformed string".to_string(),
+            })?,
+            Some(o) => o.to_complex().ok_or_else(|| {
+                PyException::type_error(format!(
+                    "complex() first argument must be a string or a number, not '{}'",
+                    o.type_name()
+                ))
+            })?,
+        };
+        let imag = match args.get(1) {
+            None => zero(),
+            Some(o) => o.to_complex().ok_or_else(|| {
+                PyException::type_error(format!("complex() second argument must be a number, not '{}'", o.type_name()))
+            })?,
+        };
+        if args.len() > 2 {
+            return Err(PyException::type_error(format!("complex() takes at most 2 arguments ({} given)", args.len())));
         }
-        println!("{}", msg);
-        Arc::from(Obj::Str(msg))
+        // part by part, so complex(0, inf) doesn't pick up a nan from 0 * inf
+        let real_part = real.real().to_f64() - imag.imag().to_f64();
+        let imag_part = real.imag().to_f64() + imag.real().to_f64();
+        Ok(Obj::Complex(Box::new(Complex::with_val(53, (real_part, imag_part)))))
     }
 
-    pub fn bin(obj: &Obj) -> Arc<Obj> {
-        // num.index_
-        let s = match obj {
-            Obj::Int(i) => format!("{:b}", i),
-            _ => unimplemented!(),
+    // "1+2j", "(1-2.5e3j)", "3j" or a real number, spaces only around it
+    fn parse_complex(s: &str) -> Option<Complex> {
+        let s = s.trim();
+        let s = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')).map(str::trim).unwrap_or(s);
+        if s.contains(char::is_whitespace) {
+            return None;
+        }
+        let Some(body) = s.strip_suffix(['j', 'J']) else {
+            return Some(Complex::with_val(53, (Funcs::parse_float(s)?, 0.0)));
         };
-        Arc::from(Obj::Str(s))
+        // the real part ends at the last sign that isn't an exponent's
+        let split = body
+            .char_indices()
+            .rev()
+            .find(|&(i, c)| matches!(c, '+' | '-') && i > 0 && !matches!(body.as_bytes()[i - 1], b'e' | b'E'))
+            .map(|(i, _)| i);
+        let (real, imag) = match split {
+            Some(i) => (Funcs::parse_float(&body[..i])?, &body[i..]),
+            None => (0.0, body),
+        };
+        let imag = match imag {
+            "" | "+" => 1.0,
+            "-" => -1.0,
+            imag => Funcs::parse_float(imag)?,
+        };
+        Some(Complex::with_val(53, (real, imag)))
     }
 
-    pub fn float(obj: &Obj) -> Result<Obj, PyException> {
+    // abs(x) for the numbers, a complex's is its modulus
+    pub fn abs(obj: &Obj) -> Result<Obj, PyException> {
         let ret = match obj {
-            Obj::Float(_) => obj.clone(),
-            Obj::Int(i) => Obj::Float(i.to_f64()),
-            Obj::Str(s) => match s.parse::<f64>() {
-                Ok(f) => Obj::Float(f),
-                Err(e) => {
-                    return Err(PyException {
-                        error: PyError::FloatParseError,
-                        msg: format!("Failed to parse \"{s}\" to float. {e}"),
-                    });
-                }
-            },
-            _ => {
-                return Err(PyException {
-                    error: PyError::FloatParseError,
-                    msg: format!("Unable to convert {obj} to float"),
-                });
+            Obj::Int(i) => Obj::Int(i.clone().abs()),
+            Obj::Bool(b) => Obj::Int(Integer::from(*b)),
+            Obj::Float(f) => Obj::Float(f.abs()),
+            Obj::Complex(c) => Obj::Float(c.real().to_f64().hypot(c.imag().to_f64())),
+            Obj::Fraction(r) => Obj::Fraction(r.clone().abs()),
+            Obj::Decimal(d) => Obj::Decimal(Box::new(d.abs(&decimal::context()))),
+            o => {
+                return Err(PyException::type_error(format!("bad operand type for abs(): '{}'", o.type_name())))
             }
         };
         Ok(ret)
     }
 
-    // TODO: Implement -
-    // abs, aiter, all, anext, any, ascii,
-    // bin, bool, breakpoint, bytearray, bytes,
-    // callable, chr, classmethod, compile, complex,
-    // delattr, dict, dir, divmod,
-    // enumerate, eval, exec,
-    // filter, float, format, frozenset,
-    // getattr, globals,
-    // hasattr, hash, help, hex,
-    // id, input, int, isinstance, issubclass, iter,
-    // len, list, locals
-    // map, max, memoryview, min,
-    // next,
-    // object, oct, open, ord,
-    // pow, print, property,
-    // range, repr, reversed, round,
-    // set, setattr, slice, sorted, staticmethod, str, sum, super,
-    // tuple, type,
-    // vars,
-    // zip,
-    // __import__
+    // pow(base, exp, mod), with a mod it stays in ints and a negative exp
+    // means the inverse of base modulo mod
+    pub fn pow(args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+        let value_err = |msg: &str| PyException {
+            error: PyError::ValueError,
+            msg: msg.to_string(),
+        };
+        let (base, exp, modulus) = match args {
+            [] => return Err(PyException::type_error("pow() missing required argument 'base' (pos 1)")),
+            [_] => return Err(PyException::type_error("pow() missing required argument 'exp' (pos 2)")),
+            [base, exp] => (base, exp, None),
+            [base, exp, modulus] if matches!(modulus.as_ref(), Obj::None) => (base, exp, None),
+            [base, exp, modulus] => (base, exp, Some(modulus)),
+            _ => return Err(PyException::type_error(format!("pow() takes at most 3 arguments ({} given)", args.len()))),
+        };
+        let Some(modulus) = modulus else {
+            return Obj::pow(base, exp);
+        };
+        let (Some(base), Some(exp), Some(modulus)) = (base.to_integer(), exp.to_integer(), modulus.to_integer()) else {
+            return Err(PyException::type_error("pow() 3rd argument not allowed unless all arguments are integers"));
+        };
+        if modulus == 0 {
+            return Err(value_err("pow() 3rd argument cannot be 0"));
+        }
+        // the result takes the sign of the modulus, like %
+        let val = base
+            .pow_mod(&exp, &modulus.clone().abs())
+            .map_err(|_| value_err("base is not invertible for the given modulus"))?;
+        match modulus < 0 && val != 0 {
+            true => Ok(Obj::Int(val + modulus)),
+            false => Ok(Obj::Int(val)),
+        }
+    }
+
+    // divmod(a, b), the pair (a // b, a % b)
+    pub fn divmod(args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+        let [lhs, rhs] = args else {
+            return Err(PyException::type_error(format!("divmod expected 2 arguments, got {}", args.len())));
+        };
+        let (quot, rem) = Obj::divmod(lhs, rhs, "divmod()")?;
+        Ok(Obj::Tuple(vec![quot.into(), rem.into()]))
+    }
+
+    // int(x=0, base=10), a float is truncated toward zero and a str is
+    // parsed in base, where base 0 takes it from the 0x/0o/0b prefix
+    pub fn int(args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Obj, PyException> {
+        let params = bind_native_args("int", &["x", "base"], 2, args, kwargs)?;
+        let (x, base) = (&params[0], &params[1]);
+        let Some(x) = x else {
+            return match base {
+                Some(_) => Err(PyException::type_error("int() missing string argument")),
+                None => Ok(Obj::Int(Integer::ZERO)),
+            };
+        };
+        let base = match base.as_deref() {
+            None => None,
+            Some(Obj::Int(b)) if *b == 0 || (*b >= 2 && *b <= 36) => b.to_u32(),
+            Some(Obj::Int(_)) => {
+                return Err(PyException {
+                    error: PyError::ValueError,
+                    msg: "int() base must be >= 2 and <= 36, or 0".to_string(),
+                })
+            }
+            Some(b) => {
+                return Err(PyException::type_error(format!(
+                    "'{}' object cannot be interpreted as an integer",
+                    b.type_name()
+                )))
+            }
+        };
+        match (x.as_ref(), base) {
+            (Obj::Str(s), base) => parse_int(s, base.unwrap_or(10)).map(Obj::Int),
+            (_, Some(_)) => Err(PyException::type_error("int() can't convert non-string with explicit base")),
+            (Obj::Int(i), None) => Ok(Obj::Int(i.clone())),
+            (Obj::Bool(b), None) => Ok(Obj::Int(Integer::from(*b))),
+            (Obj::Float(f), None) if f.is_nan() => Err(PyException {
+                error: PyError::ValueError,
+                msg: "cannot convert float NaN to integer".to_string(),
+            }),
+            (Obj::Float(f), None) => Integer::from_f64(f.trunc()).map(Obj::Int).ok_or(PyException {
+                error: PyError::OverflowError,
+                msg: "cannot convert float infinity to integer".to_string(),
+            }),
+            (Obj::Fraction(r), None) => Ok(Obj::Int(r.clone().trunc().into_numer_denom().0)),
+            (Obj::Decimal(d), None) => d.to_integer().map(Obj::Int),
+            (o, None) => Err(PyException::type_error(format!(
+                "int() argument must be a string, a bytes-like object or a real number, not '{}'",
+                o.type_name()
+            ))),
+        }
+    }
+
+    // the methods of int the VM implements, see PyVM::call_builtin_method
+    pub fn int_method(
+        int: &Integer,
+        name: &str,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+    ) -> Result<Obj, PyException> {
+        if name != "to_bytes" && !args.is_empty() {
+            return Err(PyException::type_error(format!("int.{name}() takes no arguments ({} given)", args.len())));
+        }
+        match name {
+            // bits in abs(int), without the sign and leading zeros
+            "bit_length" => Ok(Obj::Int(int.significant_bits().into())),
+            "bit_count" => Ok(Obj::Int(int.clone().abs().count_ones().unwrap_or(0).into())),
+            "as_integer_ratio" => Ok(Obj::Tuple(vec![Obj::Int(int.clone()).into(), Obj::Int(1.into()).into()])),
+            "to_bytes" => {
+                let params = bind_native_args("to_bytes", &["length", "byteorder", "signed"], 2, args, kwargs)?;
+                let length = match params[0].as_deref() {
+                    None => 1,
+                    Some(Obj::Int(len)) => match len.to_isize() {
+                        Some(len) if len >= 0 => len as usize,
+                        Some(_) => {
+                            return Err(PyException {
+                                error: PyError::ValueError,
+                                msg: "length argument must be non-negative".to_string(),
+                            })
+                        }
+                        None => return Err(overflow("Python int too large to convert to C ssize_t")),
+                    },
+                    Some(o) => {
+                        return Err(PyException::type_error(format!("'{}' object cannot be interpreted as an integer", o.type_name())))
+                    }
+                };
+                let little = byteorder_is_little(params[1].as_deref())?;
+                let signed = params[2].as_ref().is_some_and(|s| s.__bool__());
+                int_to_bytes(int, length, little, signed).map(Obj::Bytes)
+            }
+            _ => Err(PyException {
+                error: PyError::AttributeError,
+                msg: format!("'int' object has no attribute '{name}'"),
+            }),
+        }
+    }
+
+    // int.from_bytes(bytes, byteorder='big', *, signed=False)
+    pub fn int_from_bytes(args: &[Arc<Obj>], kwargs: &[(String, Arc<Obj>)]) -> Result<Obj, PyException> {
+        let params = bind_native_args("from_bytes", &["bytes", "byteorder", "signed"], 2, args, kwargs)?;
+        let mut bytes = match params[0].as_deref() {
+            Some(o @ (Obj::Bytes(_) | Obj::ByteArray(_) | Obj::MemoryView(_))) => bytes::bytes_of(o).unwrap(),
+            Some(o) => {
+                return Err(PyException::type_error(format!("cannot convert '{}' object to bytes", o.type_name())))
+            }
+            None => {
+                return Err(PyException::type_error("from_bytes() missing required argument 'bytes' (pos 1)"))
+            }
+        };
+        if byteorder_is_little(params[1].as_deref())? {
+            bytes.reverse();
+        }
+        let mut int = Integer::from_digits(&bytes, Order::Msf);
+        // two's complement, the top bit set means negative
+        if params[2].as_ref().is_some_and(|s| s.__bool__()) && bytes.first().is_some_and(|b| b & 0x80 != 0) {
+            int -= Integer::from(1) << (8 * bytes.len() as u32);
+        }
+        Ok(Obj::Int(int))
+    }
+
+    // the other builtins are in pyrs_builtins
+}
+
+// Matches a native function's args to its params the way a call to a def
+// would, the first max_positional of them can be passed by position. The
+// params no arg was given for are None
+pub fn bind_native_args(
+    func: &str,
+    params: &[&str],
+    max_positional: usize,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+) -> Result<Vec<Option<Arc<Obj>>>, PyException> {
+    if args.len() > max_positional {
+        return Err(PyException::type_error(format!(
+            "{func}() takes at most {max_positional} positional arguments ({} given)",
+            args.len()
+        )));
+    }
+    let mut bound: Vec<Option<Arc<Obj>>> = vec![None; params.len()];
+    for (slot, arg) in bound.iter_mut().zip(args) {
+        *slot = Some(arg.clone());
+    }
+    for (name, val) in kwargs {
+        match params.iter().position(|p| p == name) {
+            Some(idx) if idx < args.len() => {
+                return Err(PyException::type_error(format!(
+                    "argument for {func}() given by name ('{name}') and position ({})",
+                    idx + 1
+                )))
+            }
+            Some(idx) => bound[idx] = Some(val.clone()),
+            None => return Err(PyException::type_error(format!("'{name}' is an invalid keyword argument for {func}()"))),
+        }
+    }
+    Ok(bound)
+}
+
+// the digits of an int() string in base, with an optional sign and the
+// whitespace around it
+fn parse_int(s: &str, base: u32) -> Result<Integer, PyException> {
+    let invalid = || PyException {
+        error: PyError::ValueError,
+        msg: format!("invalid literal for int() with base {base}: {}", Obj::Str(s.to_string()).__repr__()),
+    };
+    let trimmed = s.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let prefix = unsigned.get(..2).map(|p| p.to_ascii_lowercase());
+    let prefix_radix = match prefix.as_deref() {
+        Some("0x") => Some(16),
+        Some("0o") => Some(8),
+        Some("0b") => Some(2),
+        _ => None,
+    };
+    let (radix, digits) = match (base, prefix_radix) {
+        (0, Some(radix)) => (radix, unsigned[2..].strip_prefix('_').unwrap_or(&unsigned[2..])),
+        // base 0 reads like a literal, so no leading zeros
+        (0, None) if unsigned.trim_start_matches(['0', '_']).is_empty() => (10, unsigned),
+        (0, None) if unsigned.starts_with('0') => return Err(invalid()),
+        (0, None) => (10, unsigned),
+        (base, Some(radix)) if base == radix => (radix, unsigned[2..].strip_prefix('_').unwrap_or(&unsigned[2..])),
+        (base, _) => (base, unsigned),
+    };
+    let digits = Utils::strip_underscores(digits, radix).ok_or_else(invalid)?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return Err(invalid());
+    }
+    let int = Integer::from_str_radix(&digits, radix as i32).map_err(|_| invalid())?;
+    Ok(if negative { -int } else { int })
+}
+
+// val rounded to the nearest integer, a half to the even one
+fn round_half_even(val: Rational) -> Integer {
+    let (fract, floor) = val.fract_floor(Integer::new());
+    let doubled = fract * 2u32;
+    match doubled > 1 || doubled == 1 && floor.is_odd() {
+        true => floor + 1u32,
+        false => floor,
+    }
+}
+
+// int rounded to a multiple of 10**places
+fn round_int(int: Integer, places: Integer) -> Integer {
+    // past its digit count every int rounds to 0
+    if places > int.to_string_radix(10).len() {
+        return Integer::ZERO;
+    }
+    let scale = Integer::from(10).pow(places.to_u32().unwrap());
+    round_half_even(Rational::from((int, scale.clone()))) * scale
+}
+
+// Rounds the exact value of val to ndigits decimal places and then to the
+// nearest float, so round(2.675, 2) is 2.67 since 2.675 is a bit under it
+fn round_float(val: f64, ndigits: i64) -> Result<f64, PyException> {
+    // CPython's bounds, past them no float changes or every float is 0
+    if !val.is_finite() || ndigits > 323 {
+        return Ok(val);
+    }
+    if ndigits < -308 {
+        return Ok(0.0 * val);
+    }
+    let scale = Rational::from(Integer::from(10).pow(ndigits.unsigned_abs() as u32));
+    let exact = Rational::from_f64(val).unwrap();
+    let rounded = match ndigits >= 0 {
+        true => Rational::from(round_half_even(exact * &scale)) / scale,
+        false => Rational::from(round_half_even(exact / &scale)) * scale,
+    };
+    match rational_to_f64(&rounded) {
+        f if f.is_infinite() => Err(PyException {
+            error: PyError::OverflowError,
+            msg: "rounded value too large to represent".to_string(),
+        }),
+        // rounding to zero keeps the sign, round(-0.4) is -0.0
+        0.0 => Ok(0.0f64.copysign(val)),
+        f => Ok(f),
+    }
+}
+
+// 0x1.8000000000000p+0, the 52 stored mantissa bits as 13 hex digits
+fn float_hex(val: f64) -> String {
+    if !val.is_finite() {
+        return pyrs_format::float_repr(val);
+    }
+    let sign = if val.is_sign_negative() { "-" } else { "" };
+    if val == 0.0 {
+        return format!("{sign}0x0.0p+0");
+    }
+    let bits = val.to_bits();
+    let (exp_bits, mantissa) = ((bits >> 52) & 0x7ff, bits & ((1 << 52) - 1));
+    // subnormals have no implicit leading 1
+    let (lead, exp) = match exp_bits {
+        0 => (0, -1022),
+        e => (1, e as i64 - 1023),
+    };
+    format!("{sign}0x{lead}.{mantissa:013x}p{exp:+}")
 }
 
+// [sign]['0x']digits['.'digits]['p'[sign]digits] or inf and nan, the hex
+// digits are exact so the only rounding is the one to the nearest float
+fn parse_hex_float(s: &str) -> Result<f64, PyException> {
+    let invalid = || PyException {
+        error: PyError::ValueError,
+        msg: "invalid hexadecimal floating-point string".to_string(),
+    };
+    let trimmed = s.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let sign = if negative { -1.0 } else { 1.0 };
+    match unsigned.to_ascii_lowercase().as_str() {
+        "inf" | "infinity" => return Ok(sign * f64::INFINITY),
+        "nan" => return Ok(f64::NAN),
+        _ => {}
+    }
+    let unsigned = match unsigned.get(..2) {
+        Some("0x" | "0X") => &unsigned[2..],
+        _ => unsigned,
+    };
+    let (mantissa, exp) = match unsigned.split_once(['p', 'P']) {
+        Some((mantissa, exp)) => (mantissa, Some(exp)),
+        None => (unsigned, None),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{int_part}{frac_part}");
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    // exponents this far out are inf or 0 whatever the digits
+    let exp = match exp {
+        None => 0,
+        Some(exp) => {
+            let digits = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            exp.parse::<i64>().unwrap_or(if exp.starts_with('-') { i64::MIN } else { i64::MAX })
+        }
+    };
+    let exp = exp.clamp(-(1 << 20), 1 << 20) - 4 * frac_part.len() as i64;
+    let int = Integer::from_str_radix(&digits, 16).map_err(|_| invalid())?;
+    let exact = Rational::from(int) << exp as i32;
+    match rational_to_f64(&exact) {
+        f if f.is_infinite() => Err(PyException {
+            error: PyError::OverflowError,
+            msg: "hexadecimal value too large to represent as a float".to_string(),
+        }),
+        f => Ok(sign * f),
+    }
+}
+
+fn byteorder_is_little(byteorder: Option<&Obj>) -> Result<bool, PyException> {
+    match byteorder {
+        None => Ok(false),
+        Some(Obj::Str(order)) if order == "big" => Ok(false),
+        Some(Obj::Str(order)) if order == "little" => Ok(true),
+        Some(Obj::Str(_)) => Err(PyException {
+            error: PyError::ValueError,
+            msg: "byteorder must be either 'little' or 'big'".to_string(),
+        }),
+        Some(o) => Err(PyException::type_error(format!("to_bytes() argument 'byteorder' must be str, not {}", o.type_name()))),
+    }
+}
+
+fn overflow(msg: &str) -> PyException {
+    PyException {
+        error: PyError::OverflowError,
+        msg: msg.to_string(),
+    }
+}
+
+// length bytes big endian first, a negative int as its two's complement
+fn int_to_bytes(int: &Integer, length: usize, little: bool, signed: bool) -> Result<Vec<u8>, PyException> {
+    if *int < 0 && !signed {
+        return Err(overflow("can't convert negative int to unsigned"));
+    }
+    // bits counted rather than compared with 2**(8*length), which a big
+    // length can't be shifted to
+    let bits = match signed {
+        true => int.signed_bits(),
+        false => int.significant_bits(),
+    };
+    if bits as u64 > (length as u64).saturating_mul(8) {
+        return Err(overflow("int too big to convert"));
+    }
+    // the fewest bytes that hold it, the rest is sign fill
+    let used = (bits as usize).div_ceil(8);
+    let digits = match *int < 0 {
+        true => (int.clone() + (Integer::from(1) << (8 * used as u32))).to_digits::<u8>(Order::Msf),
+        false => int.to_digits::<u8>(Order::Msf),
+    };
+    let fill = match *int < 0 {
+        true => 0xff,
+        false => 0,
+    };
+    let mut bytes = bytes::with_capacity(length)?;
+    bytes.resize(length - digits.len(), fill);
+    bytes.extend(digits);
+    if little {
+        bytes.reverse();
+    }
+    Ok(bytes)
+}
+
+
 #[derive(Debug, Clone)]
 pub struct RangeObj {
     pub start: Option<Integer>,
@@ -171,204 +729,65 @@ impl RangeObj {
             inc = self.inc.unwrap_or(Integer::from(1));
         }
 
-        if start < end {
-            let mut curr = start;
-            while curr < end {
-                objs.push(curr.clone().to_arc());
-                curr += inc.clone();
-            }
-        } else {
-            let mut curr = start;
-            while curr > end {
-                objs.push(curr.clone().to_arc());
-                curr += inc.clone();
-            }
+        // the step's sign says which way it counts
+        let mut curr = start;
+        while (inc > 0 && curr < end) || (inc < 0 && curr > end) {
+            objs.push(curr.clone().to_arc());
+            curr += &inc;
         }
 
         objs
     }
-}
-
-pub struct Maths {}
 
-impl Import for Maths {
-    fn get_name() -> &'static str {
-        "maths"
-    }
-    fn try_get(name: &str) -> Option<FnPtr> {
-        match name {
-            "sin" => Some(FnPtr {
-                ptr: Maths::sin,
-                name: "sin".to_string(),
-            }),
-            "cos" => Some(FnPtr {
-                ptr: Maths::cos,
-                name: "cos".to_string(),
-            }),
-            "tan" => Some(FnPtr {
-                ptr: Maths::tan,
-                name: "tan".to_string(),
-            }),
-            "sqrt" => Some(FnPtr {
-                ptr: Maths::sqrt,
-                name: "sqrt".to_string(),
-            }),
-            "abs" => Some(FnPtr {
-                ptr: Maths::abs,
-                name: "abs".to_string(),
-            }),
-            "ln" => Some(FnPtr {
-                ptr: Maths::ln,
-                name: "ln".to_string(),
-            }),
-            "log10" => Some(FnPtr {
-                ptr: Maths::log10,
-                name: "log10".to_string(),
-            }),
-            "exp" => Some(FnPtr {
-                ptr: Maths::exp,
-                name: "exp".to_string(),
-            }),
-            _ => None,
-        }
-    }
-}
-
-#[allow(dead_code)]
-impl Maths {
-    pub fn sin(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        if args.len() != 1 {
-            panic!("[Type Error] Func{{sin}} only takes 1 argument");
-        }
-        let arg = args.first().unwrap();
-
-        let val = match arg.as_ref() {
-            Obj::Float(d) => *d,
-            Obj::Int(i) => i.to_f64(),
-            _ => panic!(
-                "[Type Error] Func{{sin}} only takes a number types: {:?}",
-                arg
-            ),
-        };
-        Arc::from(Obj::Float(val.sin()))
-    }
-
-    pub fn cos(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        if args.len() != 1 {
-            panic!("[Type Error] Func{{cos}} only takes 1 argument");
-        }
-        let arg = args.first().unwrap();
-
-        let val = match arg.as_ref() {
-            Obj::Float(d) => *d,
-            Obj::Int(i) => i.to_f64(),
-            _ => panic!(
-                "[Type Error] Func{{cos}} only takes a number types: {:?}",
-                arg
-            ),
-        };
-        Arc::from(Obj::Float(val.cos()))
-    }
-
-    pub fn tan(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        if args.len() != 1 {
-            panic!("[Type Error] Func{{tan}} only takes 1 argument");
-        }
-        let arg = args.first().unwrap();
-
-        let val = match arg.as_ref() {
-            Obj::Float(d) => *d,
-            Obj::Int(i) => i.to_f64(),
-            _ => panic!(
-                "[Type Error] Func{{tan}} only takes a number types: {:?}",
-                arg
-            ),
-        };
-        Arc::from(Obj::Float(val.tan()))
-    }
-
-    pub fn sqrt(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        if args.len() != 1 {
-            panic!("[Type Error] Func{{sqrt}} only takes 1 argument");
+    // the start, end and step, however many of them it was given
+    pub(crate) fn bounds(&self) -> (Integer, Integer, Integer) {
+        if self.one_arg {
+            (Integer::ZERO, self.start.clone().unwrap_or_default(), Integer::from(1))
+        } else {
+            (
+                self.start.clone().unwrap_or_default(),
+                self.end.clone().unwrap_or_default(),
+                self.inc.clone().unwrap_or(Integer::from(1)),
+            )
         }
-        let arg = args.first().unwrap();
-
-        let val = match arg.as_ref() {
-            Obj::Float(d) => *d,
-            Obj::Int(i) => i.to_f64(),
-            _ => panic!(
-                "[Type Error] Func{{sqrt}} only takes a number types: {:?}",
-                arg
-            ),
-        };
-        Arc::from(Obj::Float(val.sqrt()))
     }
 
-    pub fn abs(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        if args.len() != 1 {
-            panic!("[Type Error] Func{{abs}} only takes 1 argument");
+    // how many items it counts through, without building them
+    pub fn len(&self) -> Integer {
+        let (start, end, inc) = self.bounds();
+        let span = if inc > 0 { end - start } else { start - end };
+        let inc = inc.abs();
+        if span <= 0 {
+            Integer::ZERO
+        } else {
+            (span + &inc - 1u32) / inc
         }
-        let arg = args.first().unwrap();
-
-        let val = match arg.as_ref() {
-            Obj::Float(d) => *d,
-            Obj::Int(i) => i.to_f64(),
-            _ => panic!(
-                "[Type Error] Func{{abs}} only takes a number types: {:?}",
-                arg
-            ),
-        };
-        Arc::from(Obj::Float(val.abs()))
     }
 
-    pub fn ln(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        if args.len() != 1 {
-            panic!("[Type Error] Func{{ln}} only takes 1 argument");
-        }
-        let arg = args.first().unwrap();
-
-        let val = match arg.as_ref() {
-            Obj::Float(d) => *d,
-            Obj::Int(i) => i.to_f64(),
-            _ => panic!(
-                "[Type Error] Func{{ln}} only takes a number types: {:?}",
-                arg
-            ),
+    // the idx-th number it counts through, a negative idx counts from the end
+    pub fn item(&self, idx: &Integer) -> Option<Integer> {
+        let len = self.len();
+        let idx = match *idx < 0 {
+            true => Integer::from(idx + &len),
+            false => idx.clone(),
         };
-        Arc::from(Obj::Float(val.ln()))
-    }
-
-    pub fn log10(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        if args.len() != 1 {
-            panic!("[Type Error] Func{{log10}} only takes 1 argument");
+        if idx < 0 || idx >= len {
+            return None;
         }
-        let arg = args.first().unwrap();
-
-        let val = match arg.as_ref() {
-            Obj::Float(d) => *d,
-            Obj::Int(i) => i.to_f64(),
-            _ => panic!(
-                "[Type Error] Func{{log10}} only takes a number types: {:?}",
-                arg
-            ),
-        };
-        Arc::from(Obj::Float(val.log10()))
+        let (start, _, inc) = self.bounds();
+        Some(start + idx * inc)
     }
 
-    pub fn exp(args: &Vec<Arc<Obj>>) -> Arc<Obj> {
-        if args.len() != 1 {
-            panic!("[Type Error] Func{{exp}} only takes 1 argument");
-        }
-        let arg = args.first().unwrap();
-
-        let val = match arg.as_ref() {
-            Obj::Float(d) => *d,
-            Obj::Int(i) => i.to_f64(),
-            _ => panic!(
-                "[Type Error] Func{{exp}} only takes a number types: {:?}",
-                arg
-            ),
-        };
-        Arc::from(Obj::Float(val.exp()))
+    // the range of the numbers a slice picks out of it
+    pub fn slice(&self, key: &Obj) -> Result<RangeObj, PyException> {
+        let len = self.len().to_usize().ok_or_else(|| PyException {
+            error: PyError::OverflowError,
+            msg: "Python int too large to convert to C ssize_t".to_string(),
+        })?;
+        let (lo, hi, step) = key.slice_bounds(len)?;
+        let (start, _, inc) = self.bounds();
+        let at = |i: isize| &start + Integer::from(i) * &inc;
+        let inc = Integer::from(step) * &inc;
+        Ok(RangeObj::from(Some(at(lo)), Some(at(hi)), Some(inc).filter(|inc| *inc != 1)))
     }
 }
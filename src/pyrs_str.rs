@@ -0,0 +1,631 @@
+use crate::{
+    pyrs_error::{PyError, PyException},
+    pyrs_obj::Obj,
+    pyrs_std::bind_native_args,
+};
+use std::{collections::HashMap, sync::Arc};
+
+// the methods of str besides format, format_map, encode, translate and
+// maketrans, which the VM calls itself
+pub const METHODS: [&str; 42] = [
+    "split", "rsplit", "splitlines", "join", "strip", "lstrip", "rstrip", "removeprefix", "removesuffix",
+    "replace", "find", "rfind", "index", "rindex", "count", "startswith", "endswith", "upper", "lower", "title",
+    "capitalize", "swapcase", "center", "ljust", "rjust", "zfill", "partition", "rpartition", "expandtabs",
+    "isdigit", "isdecimal", "isnumeric", "isalpha", "isalnum", "isspace", "islower", "isupper", "istitle",
+    "isidentifier", "isascii", "isprintable", "casefold",
+];
+
+// the ones that also take their arguments by name
+pub const KWARG_METHODS: [&str; 5] = ["split", "rsplit", "splitlines", "replace", "expandtabs"];
+
+// what isspace() and split() count as whitespace, std leaves out the
+// four separator controls
+fn is_space(c: char) -> bool {
+    c.is_whitespace() || ('\x1c'..='\x1f').contains(&c)
+}
+
+fn is_line_break(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\x0b' | '\x0c' | '\x1c' | '\x1d' | '\x1e' | '\u{85}' | '\u{2028}' | '\u{2029}')
+}
+
+// std can't tell the digits apart from the other numeric chars, so the
+// ranges of each Numeric_Type are in tables, from Unicode 14.0 like
+// python 3.11. A decimal (Nd) is also a digit
+fn in_table(table: &[(char, char)], c: char) -> bool {
+    table
+        .binary_search_by(|&(lo, hi)| match (hi < c, lo > c) {
+            (true, _) => std::cmp::Ordering::Less,
+            (_, true) => std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal,
+        })
+        .is_ok()
+}
+
+fn is_decimal(c: char) -> bool {
+    in_table(&DECIMALS, c)
+}
+
+fn is_digit(c: char) -> bool {
+    is_decimal(c) || in_table(&DIGITS, c)
+}
+
+fn is_cased(c: char) -> bool {
+    c.is_uppercase() || c.is_lowercase()
+}
+
+fn str_arg(obj: &Obj, err: impl Fn(&str) -> String) -> Result<&str, PyException> {
+    match obj {
+        Obj::Str(s) => Ok(s),
+        o => Err(PyException::type_error(err(o.type_name()))),
+    }
+}
+
+fn int_arg(obj: &Obj) -> Result<isize, PyException> {
+    match obj.to_integer() {
+        Some(i) => i.to_isize().ok_or_else(|| PyException {
+            error: PyError::OverflowError,
+            msg: "Python int too large to convert to C ssize_t".to_string(),
+        }),
+        None => Err(PyException::type_error(format!("'{}' object cannot be interpreted as an integer", obj.type_name()))),
+    }
+}
+
+// room for len more bytes in out, a MemoryError instead of aborting when
+// there isn't
+fn reserve(out: &mut String, len: usize) -> Result<(), PyException> {
+    out.try_reserve(len).map_err(|_| PyException::memory_error())
+}
+
+// the byte offset of the i-th code point
+fn offset(s: &str, i: usize) -> usize {
+    s.char_indices().nth(i).map(|(b, _)| b).unwrap_or(s.len())
+}
+
+// The code point the optional start and end arguments of find() and the
+// like begin at and the bytes they pick out. None when start is past end,
+// then not even the empty string is found
+fn span<'a>(s: &'a str, bounds: &[Arc<Obj>]) -> Result<Option<(usize, &'a str)>, PyException> {
+    let len = s.chars().count() as isize;
+    let bound = |i: usize, default: isize| match bounds.get(i).map(|b| b.as_ref()) {
+        None | Some(Obj::None) => Ok(default),
+        Some(o) => match o.to_integer() {
+            // negative ones count from the end
+            Some(b) => {
+                let b = b.clamp(&isize::MIN, &isize::MAX).to_isize().unwrap();
+                Ok(if b < 0 { (b + len).max(0) } else { b })
+            }
+            None => Err(PyException::type_error("slice indices must be integers or None or have an __index__ method")),
+        },
+    };
+    let (start, end) = (bound(0, 0)?, bound(1, len)?.min(len));
+    if start > end {
+        return Ok(None);
+    }
+    let (start, end) = (start as usize, end as usize);
+    Ok(Some((start, &s[offset(s, start)..offset(s, end)])))
+}
+
+fn split_whitespace(s: &str, maxsplit: Option<usize>) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut rest = s.trim_start_matches(is_space);
+    while !rest.is_empty() {
+        // the last part keeps the whitespace at its end
+        if maxsplit == Some(parts.len()) {
+            parts.push(rest);
+            break;
+        }
+        let end = rest.find(is_space).unwrap_or(rest.len());
+        parts.push(&rest[..end]);
+        rest = rest[end..].trim_start_matches(is_space);
+    }
+    parts
+}
+
+fn rsplit_whitespace(s: &str, maxsplit: Option<usize>) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut rest = s.trim_end_matches(is_space);
+    while !rest.is_empty() {
+        if maxsplit == Some(parts.len()) {
+            parts.push(rest);
+            break;
+        }
+        let start = rest.char_indices().rev().find(|(_, c)| is_space(*c)).map(|(i, c)| i + c.len_utf8()).unwrap_or(0);
+        parts.push(&rest[start..]);
+        rest = rest[..start].trim_end_matches(is_space);
+    }
+    parts.reverse();
+    parts
+}
+
+fn split_lines(s: &str, keepends: bool) -> Vec<&str> {
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !is_line_break(c) {
+            continue;
+        }
+        let mut end = i + c.len_utf8();
+        if c == '\r' && chars.next_if(|(_, c)| *c == '\n').is_some() {
+            end += 1;
+        }
+        lines.push(if keepends { &s[start..end] } else { &s[start..i] });
+        start = end;
+    }
+    if start < s.len() {
+        lines.push(&s[start..]);
+    }
+    lines
+}
+
+fn title(s: &str) -> String {
+    let mut out = String::new();
+    let mut prev_cased = false;
+    for c in s.chars() {
+        match prev_cased {
+            true => out.extend(c.to_lowercase()),
+            false => out.extend(c.to_uppercase()),
+        }
+        prev_cased = is_cased(c);
+    }
+    out
+}
+
+// every uppercase char starts a word and every lowercase one is in one
+fn is_title(s: &str) -> bool {
+    let (mut cased, mut prev_cased) = (false, false);
+    for c in s.chars() {
+        if c.is_uppercase() {
+            if prev_cased {
+                return false;
+            }
+            (cased, prev_cased) = (true, true);
+        } else if c.is_lowercase() {
+            if !prev_cased {
+                return false;
+            }
+            (cased, prev_cased) = (true, true);
+        } else {
+            prev_cased = false;
+        }
+    }
+    cased
+}
+
+fn pad(s: &str, name: &str, width: isize, fill: char) -> Result<String, PyException> {
+    let len = s.chars().count() as isize;
+    if width <= len {
+        return Ok(s.to_string());
+    }
+    let marg = width - len;
+    let left = match name {
+        "ljust" => 0,
+        "rjust" => marg,
+        // an odd margin leaves the extra fill on the left when width is odd
+        _ => marg / 2 + (marg & width & 1),
+    };
+    let mut out = String::new();
+    reserve(&mut out, (marg as usize).saturating_mul(fill.len_utf8()).saturating_add(s.len()))?;
+    out.extend(std::iter::repeat_n(fill, left as usize));
+    out.push_str(s);
+    out.extend(std::iter::repeat_n(fill, (marg - left) as usize));
+    Ok(out)
+}
+
+fn zfill(s: &str, width: isize) -> Result<String, PyException> {
+    let len = s.chars().count() as isize;
+    if width <= len {
+        return Ok(s.to_string());
+    }
+    let mut out = String::new();
+    reserve(&mut out, (width - len) as usize + s.len())?;
+    let digits = match s.strip_prefix(['+', '-']) {
+        Some(digits) => {
+            out.push_str(&s[..1]);
+            digits
+        }
+        None => s,
+    };
+    out.extend(std::iter::repeat_n('0', (width - len) as usize));
+    out.push_str(digits);
+    Ok(out)
+}
+
+fn expand_tabs(s: &str, tabsize: isize) -> Result<String, PyException> {
+    let mut out = String::new();
+    let mut col = 0;
+    for c in s.chars() {
+        match c {
+            '\t' if tabsize > 0 => {
+                let n = tabsize - col % tabsize;
+                reserve(&mut out, n as usize)?;
+                out.extend(std::iter::repeat_n(' ', n as usize));
+                col += n;
+            }
+            '\t' => {}
+            '\n' | '\r' => {
+                out.push(c);
+                col = 0;
+            }
+            _ => {
+                out.push(c);
+                col += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+// replace() with an empty old puts new before every char and at the end
+fn replace(s: &str, old: &str, new: &str, count: Option<usize>) -> String {
+    let count = count.unwrap_or(usize::MAX);
+    if !old.is_empty() {
+        return s.replacen(old, new, count);
+    }
+    let mut out = String::new();
+    let mut n = 0;
+    for c in s.chars() {
+        if n < count {
+            out.push_str(new);
+            n += 1;
+        }
+        out.push(c);
+    }
+    if n < count {
+        out.push_str(new);
+    }
+    out
+}
+
+// Full case folding. Lowering the uppercase of a char folds it, but for
+// the dotless i, which stays, the capital sharp s, and Cherokee, which
+// folds to its capitals
+fn casefold(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\u{131}' => out.push(c),
+            '\u{1e9e}' => out.push_str("ss"),
+            '\u{13a0}'..='\u{13fd}' | '\u{ab70}'..='\u{abbf}' => out.extend(c.to_uppercase()),
+            _ => out.extend(c.to_uppercase().flat_map(char::to_lowercase)),
+        }
+    }
+    out
+}
+
+fn code_point(c: char) -> Obj {
+    Obj::Int((c as u32).into())
+}
+
+// str.maketrans(x[, y[, z]]), the dict of code points translate() takes
+pub fn maketrans(args: &[Arc<Obj>]) -> Result<Obj, PyException> {
+    let value_err = |msg: &str| PyException {
+        error: PyError::ValueError,
+        msg: msg.to_string(),
+    };
+    #[allow(clippy::mutable_key_type)]
+    let mut table = HashMap::new();
+    match args {
+        [] => return Err(PyException::type_error("maketrans expected at least 1 argument, got 0")),
+        [x] => {
+            let Obj::Dict(map) = x.as_ref() else {
+                return Err(PyException::type_error("if you give only one argument to maketrans it must be a dict"));
+            };
+            for (key, val) in map.lock().unwrap().iter() {
+                let key = match key {
+                    Obj::Int(_) | Obj::Bool(_) => key.clone(),
+                    Obj::Str(s) => {
+                        let mut chars = s.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => code_point(c),
+                            _ => return Err(value_err("string keys in translate table must be of length 1")),
+                        }
+                    }
+                    _ => return Err(PyException::type_error("keys in translate table must be strings or integers")),
+                };
+                table.insert(key, val.clone());
+            }
+        }
+        [x, y, rest @ ..] if rest.len() <= 1 => {
+            let x = str_arg(x, |_| "first maketrans argument must be a string if there is a second argument".to_string())?;
+            let y = str_arg(y, |t| format!("maketrans() argument 2 must be str, not {t}"))?;
+            if x.chars().count() != y.chars().count() {
+                return Err(value_err("the first two maketrans arguments must have equal length"));
+            }
+            for (from, to) in x.chars().zip(y.chars()) {
+                table.insert(code_point(from), code_point(to).into());
+            }
+            if let Some(z) = rest.first() {
+                for c in str_arg(z, |t| format!("maketrans() argument 3 must be str, not {t}"))?.chars() {
+                    table.insert(code_point(c), Obj::None.into());
+                }
+            }
+        }
+        _ => return Err(PyException::type_error(format!("maketrans expected at most 3 arguments, got {}", args.len()))),
+    }
+    Ok(Obj::new_dict_from(table))
+}
+
+// str.translate(table), lookup gives what the table has for a code point:
+// None when it has nothing and the char stays, Obj::None to drop it or
+// an int or a str in its place
+pub fn translate(
+    s: &str,
+    mut lookup: impl FnMut(char) -> Result<Option<Arc<Obj>>, PyException>,
+) -> Result<Obj, PyException> {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match lookup(c)?.as_deref() {
+            None => out.push(c),
+            Some(Obj::None) => {}
+            Some(Obj::Str(to)) => out.push_str(to),
+            Some(Obj::Int(i)) => match i.to_u32().and_then(char::from_u32) {
+                Some(to) => out.push(to),
+                None => {
+                    return Err(PyException {
+                        error: PyError::ValueError,
+                        msg: "character mapping must be in range(0x110000)".to_string(),
+                    })
+                }
+            },
+            Some(_) => return Err(PyException::type_error("character mapping must return integer, None or str")),
+        }
+    }
+    Ok(Obj::Str(out))
+}
+
+pub fn call_method(
+    s: &str,
+    name: &str,
+    args: &[Arc<Obj>],
+    kwargs: &[(String, Arc<Obj>)],
+) -> Result<Obj, PyException> {
+    let str_list = |parts: Vec<&str>| Obj::new_list(parts.into_iter().map(|p| Obj::Str(p.to_string()).into()).collect());
+    let str_tuple = |parts: [&str; 3]| Obj::Tuple(parts.into_iter().map(|p| Obj::Str(p.to_string()).into()).collect());
+    let value_err = |msg: &str| PyException {
+        error: PyError::ValueError,
+        msg: msg.to_string(),
+    };
+    let arg_range = |min: usize, max: usize| {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        match args.len() {
+            n if n < min => Err(PyException::type_error(format!("{name}() takes at least {min} argument{} ({n} given)", plural(min)))),
+            n if n > max => Err(PyException::type_error(format!("{name}() takes at most {max} argument{} ({n} given)", plural(max)))),
+            _ => Ok(()),
+        }
+    };
+    let one_arg = || match args {
+        [arg] => Ok(arg),
+        _ => Err(PyException::type_error(format!("str.{name}() takes exactly one argument ({} given)", args.len()))),
+    };
+    // maxsplit and count, a negative one is no limit
+    let limit = |obj: Option<&Arc<Obj>>| obj.map(|o| int_arg(o)).transpose().map(|n| n.and_then(|n| n.try_into().ok()));
+    let check = |pred: fn(char) -> bool| Obj::Bool(!s.is_empty() && s.chars().all(pred));
+
+    if KWARG_METHODS.contains(&name) {
+        let params: &[&str] = match name {
+            "split" | "rsplit" => &["sep", "maxsplit"],
+            "splitlines" => &["keepends"],
+            "replace" => &["old", "new", "count"],
+            _ => &["tabsize"],
+        };
+        let params = bind_native_args(name, params, params.len(), args, kwargs)?;
+        return match name {
+            "split" | "rsplit" => {
+                let maxsplit = limit(params[1].as_ref())?;
+                let sep = match params[0].as_deref() {
+                    None | Some(Obj::None) => None,
+                    Some(sep) => Some(str_arg(sep, |t| format!("must be str or None, not {t}"))?),
+                };
+                let parts = match (name, sep) {
+                    (_, Some("")) => return Err(value_err("empty separator")),
+                    ("split", None) => split_whitespace(s, maxsplit),
+                    (_, None) => rsplit_whitespace(s, maxsplit),
+                    ("split", Some(sep)) => s.splitn(maxsplit.map_or(usize::MAX, |n| n + 1), sep).collect(),
+                    (_, Some(sep)) => {
+                        let mut parts: Vec<&str> = s.rsplitn(maxsplit.map_or(usize::MAX, |n| n + 1), sep).collect();
+                        parts.reverse();
+                        parts
+                    }
+                };
+                Ok(str_list(parts))
+            }
+            "splitlines" => {
+                let keepends = params[0].as_deref().map(int_arg).transpose()?.unwrap_or(0) != 0;
+                Ok(str_list(split_lines(s, keepends)))
+            }
+            "replace" => {
+                let (Some(old), Some(new)) = (&params[0], &params[1]) else {
+                    return Err(PyException::type_error(format!("replace expected at least 2 arguments, got {}", args.len())));
+                };
+                let old = str_arg(old, |t| format!("replace() argument 1 must be str, not {t}"))?;
+                let new = str_arg(new, |t| format!("replace() argument 2 must be str, not {t}"))?;
+                Ok(Obj::Str(replace(s, old, new, limit(params[2].as_ref())?)))
+            }
+            _ => {
+                let tabsize = params[0].as_deref().map(int_arg).transpose()?.unwrap_or(8);
+                Ok(Obj::Str(expand_tabs(s, tabsize)?))
+            }
+        };
+    }
+
+    match name {
+        "strip" | "lstrip" | "rstrip" => {
+            arg_range(0, 1)?;
+            let chars = match args.first().map(|a| a.as_ref()) {
+                None | Some(Obj::None) => None,
+                Some(Obj::Str(chars)) => Some(chars),
+                Some(_) => return Err(PyException::type_error(format!("{name} arg must be None or str"))),
+            };
+            let strip = |c: char| chars.map_or(is_space(c), |chars| chars.contains(c));
+            Ok(Obj::Str(
+                match name {
+                    "strip" => s.trim_matches(strip),
+                    "lstrip" => s.trim_start_matches(strip),
+                    _ => s.trim_end_matches(strip),
+                }
+                .to_string(),
+            ))
+        }
+        "removeprefix" | "removesuffix" => {
+            let affix = str_arg(one_arg()?, |t| format!("{name}() argument must be str, not {t}"))?;
+            let rest = match name {
+                "removeprefix" => s.strip_prefix(affix),
+                _ => s.strip_suffix(affix),
+            };
+            Ok(Obj::Str(rest.unwrap_or(s).to_string()))
+        }
+        "join" => {
+            let items = one_arg()?.iter_py().ok_or_else(|| PyException::type_error("can only join an iterable"))?;
+            let mut parts = vec![];
+            for (i, item) in items.enumerate() {
+                match item.as_ref() {
+                    Obj::Str(part) => parts.push(part.clone()),
+                    o => {
+                        return Err(PyException::type_error(format!(
+                            "sequence item {i}: expected str instance, {} found",
+                            o.type_name()
+                        )))
+                    }
+                }
+            }
+            Ok(Obj::Str(parts.join(s)))
+        }
+        "find" | "rfind" | "index" | "rindex" | "count" => {
+            arg_range(1, 3)?;
+            let sub = str_arg(&args[0], |t| format!("must be str, not {t}"))?;
+            let Some((start, part)) = span(s, &args[1..])? else {
+                return match name {
+                    "count" => Ok(Obj::Int(0.into())),
+                    "find" | "rfind" => Ok(Obj::Int((-1).into())),
+                    _ => Err(value_err("substring not found")),
+                };
+            };
+            let found = match name {
+                "count" if sub.is_empty() => return Ok(Obj::Int((part.chars().count() + 1).into())),
+                "count" => return Ok(Obj::Int(part.matches(sub).count().into())),
+                "find" | "index" => part.find(sub),
+                _ => part.rfind(sub),
+            };
+            match (name, found) {
+                (_, Some(i)) => Ok(Obj::Int((start + part[..i].chars().count()).into())),
+                ("find" | "rfind", None) => Ok(Obj::Int((-1).into())),
+                _ => Err(value_err("substring not found")),
+            }
+        }
+        "startswith" | "endswith" => {
+            arg_range(1, 3)?;
+            let (candidates, not_str) = match args[0].as_ref() {
+                Obj::Tuple(items) => (items.clone(), format!("tuple for {name} must only contain str, not")),
+                _ => (vec![args[0].clone()], format!("{name} first arg must be str or a tuple of str, not")),
+            };
+            let Some((_, part)) = span(s, &args[1..])? else {
+                return Ok(Obj::Bool(false));
+            };
+            // the candidates are only checked up to the first that matches
+            for c in candidates {
+                let c = str_arg(&c, |t| format!("{not_str} {t}"))?;
+                if name == "startswith" && part.starts_with(c) || name == "endswith" && part.ends_with(c) {
+                    return Ok(Obj::Bool(true));
+                }
+            }
+            Ok(Obj::Bool(false))
+        }
+        "center" | "ljust" | "rjust" => {
+            arg_range(1, 2)?;
+            let width = int_arg(&args[0])?;
+            let fill = match args.get(1) {
+                None => ' ',
+                Some(fill) => {
+                    let fill = str_arg(fill, |t| format!("The fill character must be a unicode character, not {t}"))?;
+                    let mut chars = fill.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => return Err(PyException::type_error("The fill character must be exactly one character long")),
+                    }
+                }
+            };
+            Ok(Obj::Str(pad(s, name, width, fill)?))
+        }
+        "zfill" => Ok(Obj::Str(zfill(s, int_arg(one_arg()?)?)?)),
+        "partition" | "rpartition" => {
+            let sep = str_arg(one_arg()?, |t| format!("must be str, not {t}"))?;
+            if sep.is_empty() {
+                return Err(value_err("empty separator"));
+            }
+            let parts = match name {
+                "partition" => s.split_once(sep).map(|(l, r)| [l, sep, r]).unwrap_or([s, "", ""]),
+                _ => s.rsplit_once(sep).map(|(l, r)| [l, sep, r]).unwrap_or(["", "", s]),
+            };
+            Ok(str_tuple(parts))
+        }
+        _ if !args.is_empty() => Err(PyException::type_error(format!("str.{name}() takes no arguments ({} given)", args.len()))),
+        "upper" => Ok(Obj::Str(s.to_uppercase())),
+        "lower" => Ok(Obj::Str(s.to_lowercase())),
+        "casefold" => Ok(Obj::Str(casefold(s))),
+        "title" => Ok(Obj::Str(title(s))),
+        "capitalize" => {
+            let mut chars = s.chars();
+            let first = chars.next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default();
+            Ok(Obj::Str(first + &chars.as_str().to_lowercase()))
+        }
+        "swapcase" => Ok(Obj::Str(
+            s.chars()
+                .flat_map(|c| match c.is_uppercase() {
+                    true => c.to_lowercase().collect::<Vec<_>>(),
+                    false => c.to_uppercase().collect(),
+                })
+                .collect(),
+        )),
+        "isdigit" => Ok(check(is_digit)),
+        "isdecimal" => Ok(check(is_decimal)),
+        "isnumeric" => Ok(check(char::is_numeric)),
+        "isalpha" => Ok(check(char::is_alphabetic)),
+        "isalnum" => Ok(check(|c| c.is_alphabetic() || c.is_numeric())),
+        "isspace" => Ok(check(is_space)),
+        "islower" => Ok(Obj::Bool(s.chars().any(char::is_lowercase) && !s.chars().any(char::is_uppercase))),
+        "isupper" => Ok(Obj::Bool(s.chars().any(char::is_uppercase) && !s.chars().any(char::is_lowercase))),
+        "istitle" => Ok(Obj::Bool(is_title(s))),
+        // the same chars the lexer takes in a name
+        "isidentifier" => {
+            let mut chars = s.chars();
+            let start = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_');
+            Ok(Obj::Bool(start && chars.all(|c| c.is_alphanumeric() || c == '_')))
+        }
+        "isascii" => Ok(Obj::Bool(s.is_ascii())),
+        "isprintable" => Ok(Obj::Bool(s.chars().all(|c| c == ' ' || !(c.is_control() || c.is_whitespace())))),
+        _ => Err(PyException {
+            error: PyError::AttributeError,
+            msg: format!("'str' object has no attribute '{name}'"),
+        }),
+    }
+}
+
+// Numeric_Type=Decimal
+const DECIMALS: [(char, char); 62] = [
+    ('\u{30}', '\u{39}'), ('\u{660}', '\u{669}'), ('\u{6f0}', '\u{6f9}'), ('\u{7c0}', '\u{7c9}'),
+    ('\u{966}', '\u{96f}'), ('\u{9e6}', '\u{9ef}'), ('\u{a66}', '\u{a6f}'), ('\u{ae6}', '\u{aef}'),
+    ('\u{b66}', '\u{b6f}'), ('\u{be6}', '\u{bef}'), ('\u{c66}', '\u{c6f}'), ('\u{ce6}', '\u{cef}'),
+    ('\u{d66}', '\u{d6f}'), ('\u{de6}', '\u{def}'), ('\u{e50}', '\u{e59}'), ('\u{ed0}', '\u{ed9}'),
+    ('\u{f20}', '\u{f29}'), ('\u{1040}', '\u{1049}'), ('\u{1090}', '\u{1099}'), ('\u{17e0}', '\u{17e9}'),
+    ('\u{1810}', '\u{1819}'), ('\u{1946}', '\u{194f}'), ('\u{19d0}', '\u{19d9}'), ('\u{1a80}', '\u{1a89}'),
+    ('\u{1a90}', '\u{1a99}'), ('\u{1b50}', '\u{1b59}'), ('\u{1bb0}', '\u{1bb9}'), ('\u{1c40}', '\u{1c49}'),
+    ('\u{1c50}', '\u{1c59}'), ('\u{a620}', '\u{a629}'), ('\u{a8d0}', '\u{a8d9}'), ('\u{a900}', '\u{a909}'),
+    ('\u{a9d0}', '\u{a9d9}'), ('\u{a9f0}', '\u{a9f9}'), ('\u{aa50}', '\u{aa59}'), ('\u{abf0}', '\u{abf9}'),
+    ('\u{ff10}', '\u{ff19}'), ('\u{104a0}', '\u{104a9}'), ('\u{10d30}', '\u{10d39}'), ('\u{11066}', '\u{1106f}'),
+    ('\u{110f0}', '\u{110f9}'), ('\u{11136}', '\u{1113f}'), ('\u{111d0}', '\u{111d9}'), ('\u{112f0}', '\u{112f9}'),
+    ('\u{11450}', '\u{11459}'), ('\u{114d0}', '\u{114d9}'), ('\u{11650}', '\u{11659}'), ('\u{116c0}', '\u{116c9}'),
+    ('\u{11730}', '\u{11739}'), ('\u{118e0}', '\u{118e9}'), ('\u{11950}', '\u{11959}'), ('\u{11c50}', '\u{11c59}'),
+    ('\u{11d50}', '\u{11d59}'), ('\u{11da0}', '\u{11da9}'), ('\u{16a60}', '\u{16a69}'), ('\u{16ac0}', '\u{16ac9}'),
+    ('\u{16b50}', '\u{16b59}'), ('\u{1d7ce}', '\u{1d7ff}'), ('\u{1e140}', '\u{1e149}'), ('\u{1e2f0}', '\u{1e2f9}'),
+    ('\u{1e950}', '\u{1e959}'), ('\u{1fbf0}', '\u{1fbf9}'),
+];
+
+// Numeric_Type=Digit
+const DIGITS: [(char, char); 20] = [
+    ('\u{b2}', '\u{b3}'), ('\u{b9}', '\u{b9}'), ('\u{1369}', '\u{1371}'), ('\u{19da}', '\u{19da}'),
+    ('\u{2070}', '\u{2070}'), ('\u{2074}', '\u{2079}'), ('\u{2080}', '\u{2089}'), ('\u{2460}', '\u{2468}'),
+    ('\u{2474}', '\u{247c}'), ('\u{2488}', '\u{2490}'), ('\u{24ea}', '\u{24ea}'), ('\u{24f5}', '\u{24fd}'),
+    ('\u{24ff}', '\u{24ff}'), ('\u{2776}', '\u{277e}'), ('\u{2780}', '\u{2788}'), ('\u{278a}', '\u{2792}'),
+    ('\u{10a40}', '\u{10a43}'), ('\u{10e60}', '\u{10e68}'), ('\u{11052}', '\u{1105a}'), ('\u{1f100}', '\u{1f10a}'),
+];
@@ -3,13 +3,22 @@
 use crate::{
     pyrs_interpreter::{Interpreter, InterpreterCommand},
     pyrs_obj::{Obj, PyObj, ToObj},
-    pyrs_error::{PyException}, 
+    pyrs_error::{PyError, PyException}, 
     pyrs_parsing::{Expression, Token, Op, Keyword, Lexer},
-    pyrs_std::{FnPtr, Funcs},
+    pyrs_std::Funcs,
     pyrs_bytecode::{PyBytecode},
-    pyrs_vm::{PyVM, IntrinsicFunc},
-    pyrs_utils::{split_to_words},
+    pyrs_vm::PyVM,
+    pyrs_builtins as builtins,
+    pyrs_utils::{logical_lines, split_to_words},
+    pyrs_fractions as fractions,
+    pyrs_decimal as decimal,
+    pyrs_native::{from_args, with_self, FromObj, NativeClass, NativeModule, NativeObj},
+    pyrs_embed::PyRuntime,
+    pyrs_bytes as bytes,
+    pyrs_str,
 };
+#[cfg(feature = "math")]
+use crate::pyrs_math as math;
 
 #[cfg(test)]
 mod tests {
@@ -22,12 +31,13 @@ mod tests {
     };
 
     use pretty_assertions::{assert_eq};
+    use rug::{ops::Pow, Complex, Integer};
     use super::*;
 
     struct EqTester
     {
         vars: HashMap<String, Arc<Obj>>,
-        funcs: HashMap<String, FnPtr>,
+        vm: PyVM,
     }
 
     impl EqTester 
@@ -35,13 +45,13 @@ mod tests {
         fn new() -> Self {
             EqTester { 
                 vars: Obj::new_map(), 
-                funcs: Funcs::get_std_map() 
+                vm: PyVM::new() 
             }
         }
 
         fn eval_eq(&mut self, expr: &Expression, result: &str)
         {
-            let res = match expr.eval(&mut self.vars, &mut self.funcs) {
+            let res = match expr.eval(&mut self.vars, &mut self.vm) {
                 Ok(val) => val,
                 Err(e) => panic!("{e}"),
             };
@@ -69,19 +79,19 @@ mod tests {
         assert_eq!(24, size_of::<Token>(), "Token size not 24 bytes");
         assert_eq!(56, size_of::<Expression>(), "Expression size not 56 bytes");
         assert_eq!(64, size_of::<PyBytecode>(), "Bytecode size not 64 bytes");
-        assert_eq!(280, size_of::<PyVM>(), "VirtualMachine size not 200 bytes");
+        assert_eq!(344, size_of::<PyVM>(), "VirtualMachine size not 344 bytes");
     }
 
     #[test]
     fn parse() 
     {
-        let s1 = Expression::from_line("1");
-        let s2 = Expression::from_line("1 + 2 * 3");
-        let s3 = Expression::from_line("(1 + 2) * 3");
-        let s4 = Expression::from_line("print(100)");
-        let s5 = Expression::from_line("print(1, 2, \"5\")");
-        let s6 = Expression::from_line("x=2");
-        let s7 = Expression::from_line("x+=2");
+        let s1 = Expression::from_line("1").unwrap();
+        let s2 = Expression::from_line("1 + 2 * 3").unwrap();
+        let s3 = Expression::from_line("(1 + 2) * 3").unwrap();
+        let s4 = Expression::from_line("print(100)").unwrap();
+        let s5 = Expression::from_line("print(1, 2, \"5\")").unwrap();
+        let s6 = Expression::from_line("x=2").unwrap();
+        let s7 = Expression::from_line("x+=2").unwrap();
         
         let final_str = join_expr_strings(vec![&s1, &s2, &s3, &s4, &s5, &s6, &s7]);
         let res_str = 
@@ -89,7 +99,7 @@ mod tests {
         Op[+ Atom(1) Op[* Atom(2) Atom(3)]] | \
         Op[* Op[+ Atom(1) Atom(2)] Atom(3)] | \
         Call[print args[ Atom(100)]] | \
-        Call[print args[ Atom(1) Atom(2) Atom(5)]] | \
+        Call[print args[ Atom(1) Atom(2) Str(5)]] | \
         Op[= Ident(x) Atom(2)] | \
         Op[+= Ident(x) Atom(2)]";
         assert_eq!(final_str, res_str);
@@ -106,10 +116,10 @@ mod tests {
     #[test]
     fn strlit_parse_eval() 
     {
-        let s1 = Expression::from_line("\"smelly\"");
-        assert_eq!(s1.to_string(), "Atom(smelly)");
-        let s2 = Expression::from_line("\"smelly\" + \"poop\"");
-        assert_eq!(s2.to_string(), "Op[+ Atom(smelly) Atom(poop)]");
+        let s1 = Expression::from_line("\"smelly\"").unwrap();
+        assert_eq!(s1.to_string(), "Str(smelly)");
+        let s2 = Expression::from_line("\"smelly\" + \"poop\"").unwrap();
+        assert_eq!(s2.to_string(), "Op[+ Str(smelly) Str(poop)]");
 
         let mut eq = EqTester::new();
         eq.eval_eq(&s1, "smelly");
@@ -118,20 +128,20 @@ mod tests {
 
     #[test]
     fn test_7() {
-        let s = Expression::from_line(" print(\" y = \", 5) ");
-        assert_eq!(s.to_string(), "Call[print args[ Atom( y = ) Atom(5)]]");
+        let s = Expression::from_line(" print(\" y = \", 5) ").unwrap();
+        assert_eq!(s.to_string(), "Call[print args[ Str( y = ) Atom(5)]]");
     }
 
     #[test]
     fn test_8() {
-        let s = Expression::from_line("y = 5");
+        let s = Expression::from_line("y = 5").unwrap();
         assert_eq!(s.to_string(), "Op[= Ident(y) Atom(5)]");
     }
 
     #[test]
     fn test_10() {
-        let s = Expression::from_line(" \"la\" * 3");
-        assert_eq!(s.to_string(), "Op[* Atom(la) Atom(3)]");
+        let s = Expression::from_line(" \"la\" * 3").unwrap();
+        assert_eq!(s.to_string(), "Op[* Str(la) Atom(3)]");
 
         let mut eq = EqTester::new();
         eq.eval_eq(&s, "lalala");
@@ -139,7 +149,7 @@ mod tests {
 
     #[test]
     fn test_11() {
-        let exprs = Expression::from_multiline("if 1:\n\t print(1) ");
+        let exprs = Expression::from_multiline("if 1:\n\t print(1) ").unwrap();
         dbg!(&exprs);
         assert_eq!(exprs.len(), 1);
         let expr_results = vec!["Keyword[if conds[ Atom(1)] args[ Call[print args[ Atom(1)]]]]"];
@@ -150,19 +160,19 @@ mod tests {
 
     #[test]
     fn test_12() -> Result<Obj, PyException> {
-        let exprs = Expression::from_multiline("x = 2\n if x:\n\t print_ret(x) ");
+        let exprs = Expression::from_multiline("x = 2\n if x:\n\t print_ret(x) ").unwrap();
         assert_eq!(exprs.len(), 2);
         println!("Exprs: {:?}", exprs);
 
         let mut vars = Obj::new_map();
-        let mut funcs = Funcs::get_std_map();
+        let mut vm = PyVM::new();
         let expr_results = vec!["Op[= Ident(x) Atom(2)]","Keyword[if conds[ Ident(x)] args[ Call[print_ret args[ Ident(x)]]]]"];
         let obj_results: Vec<Arc<Obj>> = vec![Obj::from(2usize), Obj::from(true), Obj::from("2 ")];
         
         for (idx, expr) in exprs.iter().enumerate() {
             println!("Evaluating: {expr}");
             assert_eq!(expr.to_string(), expr_results.index(idx).to_string());
-            let obj = expr.eval(&mut vars, &mut funcs)?;
+            let obj = expr.eval(&mut vars, &mut vm)?;
             println!("Obj: {}", obj.to_string());
             println!("vars: {:?}", vars);
             assert_eq!(obj, obj_results.index(idx).clone());
@@ -173,15 +183,15 @@ mod tests {
     #[test]
     fn equality() -> Result<Obj, PyException> 
     {
-        let s1 = Expression::from_line("1 < 0");
-        let s2 = Expression::from_line("1 > 0");
-        let s3 = Expression::from_line("\"poop\" != 0");
-        let s4 = Expression::from_line("1 == 0");
-        let s5 = Expression::from_line("1.0 <= 0");
-        let s6 = Expression::from_line("1 >= 0.0");
+        let s1 = Expression::from_line("1 < 0").unwrap();
+        let s2 = Expression::from_line("1 > 0").unwrap();
+        let s3 = Expression::from_line("\"poop\" != 0").unwrap();
+        let s4 = Expression::from_line("1 == 0").unwrap();
+        let s5 = Expression::from_line("1.0 <= 0").unwrap();
+        let s6 = Expression::from_line("1 >= 0.0").unwrap();
 
         let expr_str = join_expr_strings(vec![&s1, &s2, &s3, &s4, &s5, &s6]);
-        let res_str = "Op[< Atom(1) Atom(0)] | Op[> Atom(1) Atom(0)] | Op[!= Atom(poop) Atom(0)] | Op[== Atom(1) Atom(0)] | Op[<= Atom(1.0) Atom(0)] | Op[>= Atom(1) Atom(0.0)]";
+        let res_str = "Op[< Atom(1) Atom(0)] | Op[> Atom(1) Atom(0)] | Op[!= Str(poop) Atom(0)] | Op[== Atom(1) Atom(0)] | Op[<= Atom(1.0) Atom(0)] | Op[>= Atom(1) Atom(0.0)]";
 
         assert_eq!(expr_str, res_str);
 
@@ -203,7 +213,7 @@ mod tests {
         assert_eq!(Obj::None.to_arc().as_ref(), &Obj::None);
 
         assert_eq!(Obj::Bool(true).to_arc(), Obj::Float(1.0).to_arc());
-        assert_ne!(Obj::new_dict(), Obj::new_dict());
+        assert_eq!(Obj::new_dict(), Obj::new_dict());
 
         let null_obj = Arc::new(Obj::Null);
         let null_ref = null_obj.clone();
@@ -214,12 +224,12 @@ mod tests {
     #[test]
     fn parse_assign()
     {
-        let s1 = Expression::from_line("x = 2");
-        let s2 = Expression::from_line("six = 6");
-        let s3 = Expression::from_line("y = x");
-        let s4 = Expression::from_line("z = 20 * 4");
-        let s5 = Expression::from_line("x += 2");
-        let s6 = Expression::from_line("x /= 2");
+        let s1 = Expression::from_line("x = 2").unwrap();
+        let s2 = Expression::from_line("six = 6").unwrap();
+        let s3 = Expression::from_line("y = x").unwrap();
+        let s4 = Expression::from_line("z = 20 * 4").unwrap();
+        let s5 = Expression::from_line("x += 2").unwrap();
+        let s6 = Expression::from_line("x /= 2").unwrap();
 
         let expr_strs = join_expr_strings(vec![&s1, &s2, &s3, &s4, &s5, &s6]);
         let res_strs = "Op[= Ident(x) Atom(2)] | Op[= Ident(six) Atom(6)] | Op[= Ident(y) Ident(x)] | Op[= Ident(z) Op[* Atom(20) Atom(4)]] | Op[+= Ident(x) Atom(2)] | Op[/= Ident(x) Atom(2)]";
@@ -243,7 +253,7 @@ mod tests {
             n1 = n2
             n2 = n3
             i = i + 1
-        "#);
+        "#)?;
 
         let ret_strs = vec![
             
@@ -251,10 +261,10 @@ mod tests {
             "Op[= Ident(n1) Atom(0)]",
             "Op[= Ident(n2) Atom(1)]",
             "Op[= Ident(n3) Atom(0)]",
-            "Call[print args[ Atom(Fibbonacci: )]]",
+            "Call[print args[ Str(Fibbonacci: )]]",
             "Keyword[while conds[ Op[< Ident(i) Atom(20)]] args[ \
             Op[= Ident(n3) Op[+ Ident(n1) Ident(n2)]] \
-            Call[print args[ Atom(() Ident(i) Atom() ) Ident(n3)]] \
+            Call[print args[ Str(() Ident(i) Str() ) Ident(n3)]] \
             Op[= Ident(n1) Ident(n2)] \
             Op[= Ident(n2) Ident(n3)] \
             Op[= Ident(i) Op[+ Ident(i) Atom(1)]]]]",
@@ -262,14 +272,14 @@ mod tests {
         ];
         
         let mut vars = Obj::new_map();
-        let mut funcs = Funcs::get_std_map();
+        let mut vm = PyVM::new();
 
         let idx_err= "[Bad Index]";
 
         let mut ret_objs: Vec<Arc<Obj>> = vec![];
         let mut idx = 0;
         for e in expr {
-            let obj = e.eval(&mut vars, &mut funcs)?;
+            let obj = e.eval(&mut vars, &mut vm)?;
             assert_eq!(e.to_string(), ret_strs.get(idx).unwrap_or(&idx_err).to_string());
             ret_objs.push(obj);
             idx += 1;
@@ -290,21 +300,21 @@ mod tests {
          \tif True:\n\
          \t\tprint_ret(\"c: good\")\n\
          \tprint(\"d: good\")"
-        );
+        ).unwrap();
 
         let ret_strs = vec![
-            r#"Keyword[if conds[ Keyword[True conds[] args[]]] args[ Call[print_ret args[ Atom(a: good)]] Keyword[if conds[ Keyword[False conds[] args[]]] args[ Call[print_ret args[ Atom(b: bad)]]]] Keyword[if conds[ Keyword[True conds[] args[]]] args[ Call[print_ret args[ Atom(c: good)]]]] Call[print args[ Atom(d: good)]]]]"#
+            r#"Keyword[if conds[ Keyword[True conds[] args[]]] args[ Call[print_ret args[ Str(a: good)]] Keyword[if conds[ Keyword[False conds[] args[]]] args[ Call[print_ret args[ Str(b: bad)]]]] Keyword[if conds[ Keyword[True conds[] args[]]] args[ Call[print_ret args[ Str(c: good)]]]] Call[print args[ Str(d: good)]]]]"#
         ];
 
         let mut vars = Obj::new_map();
-        let mut funcs = Funcs::get_std_map();
+        let mut vm = PyVM::new();
 
         let idx_err= "[Bad Index]";
 
         let mut ret_objs: Vec<Arc<Obj>> = vec![];
         let mut idx = 0;
         for e in expr {
-            let obj = e.eval(&mut vars, &mut funcs)?;
+            let obj = e.eval(&mut vars, &mut vm)?;
             assert_eq!(e.to_string(), ret_strs.get(idx).unwrap_or(&idx_err).to_string());
             ret_objs.push(obj);
             idx += 1;
@@ -326,22 +336,22 @@ mod tests {
          \tprint_ret(\"c: good\")\n\
          else:\n\
          \tprint(\"d: good\")"
-        );
+        ).unwrap();
 
         let ret_strs = vec![
-            r#"Keyword[if conds[ Keyword[False conds[] args[]]] args[ Call[print_ret args[ Atom(a: bad)]] Keyword[elif conds[ Keyword[True conds[] args[]]] args[]] Call[print_ret args[ Atom(b: good)]]]]"#,
-            r#"Keyword[if conds[ Keyword[False conds[] args[]]] args[ Call[print_ret args[ Atom(c: good)]] Keyword[else conds[] args[]] Call[print args[ Atom(d: good)]]]]"#,
+            r#"Keyword[if conds[ Keyword[False conds[] args[]]] args[ Call[print_ret args[ Str(a: bad)]] Keyword[elif conds[ Keyword[True conds[] args[]]] args[]] Call[print_ret args[ Str(b: good)]]]]"#,
+            r#"Keyword[if conds[ Keyword[False conds[] args[]]] args[ Call[print_ret args[ Str(c: good)]] Keyword[else conds[] args[]] Call[print args[ Str(d: good)]]]]"#,
         ];
 
         let mut vars = Obj::new_map();
-        let mut funcs = Funcs::get_std_map();
+        let mut vm = PyVM::new();
 
         let idx_err= "[Bad Index]";
 
         let mut ret_objs: Vec<Arc<Obj>> = vec![];
         let mut idx = 0;
         for e in expr {
-            let obj = e.eval(&mut vars, &mut funcs)?;
+            let obj = e.eval(&mut vars, &mut vm)?;
             assert_eq!(e.to_string(), ret_strs.get(idx).unwrap_or(&idx_err).to_string());
             ret_objs.push(obj);
             idx += 1;
@@ -356,29 +366,30 @@ mod tests {
         let code = vec![
             PyBytecode::LoadConst(Obj::Int(5.into())),
             PyBytecode::StoreName("x".to_string()),
-            PyBytecode::LoadConst(Obj::Null.into()),
+            PyBytecode::LoadName("print".to_string()),
             PyBytecode::LoadName("x".to_string()),
-            PyBytecode::CallInstrinsic1(IntrinsicFunc::Print),
+            PyBytecode::CallFunction(1),
+            PyBytecode::PopTop,
         ];
         println!("Instruction Queue: ");
         println!("{}", PyBytecode::to_string(&code));
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
     }
 
     #[test]
     fn bytecode_from_expr()
     {
-        let expr = Expression::from_multiline("x = 2\n if x:\n\t print(x) ");
+        let expr = Expression::from_multiline("x = 2\n if x:\n\t print(x) ").unwrap();
         let mut code = vec![];
         for e in expr {
-            PyBytecode::from_expr(e, &mut code);
+            PyBytecode::from_expr(e, &mut code).unwrap();
         }
         println!("Instructions:\n{}", PyBytecode::to_string(&code));
-        assert_eq!(format!("{:?}", code), r#"[LoadConst(Int(2)), StoreName("x"), LoadName("x"), PopJumpIfFalse(3), PushNull, LoadName("x"), CallInstrinsic1(Print)]"#);
+        assert_eq!(format!("{:?}", code), r#"[LoadConst(Int(2)), StoreName("x"), LoadName("x"), PopJumpIfFalse(4), LoadName("print"), LoadName("x"), CallFunction(1), PopTop]"#);
         
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
     }
 
     #[test]
@@ -389,12 +400,12 @@ mod tests {
         while x < 3:
 	        print(x)
 	        x += 1
-        "#);
+        "#).unwrap();
         println!("Instructions:\n{}", PyBytecode::to_string(&code));
-        assert_eq!(format!("{:?}", code), r#"[LoadConst(Int(0)), StoreName("x"), LoadName("x"), LoadConst(Int(3)), CompareOp(LessThan), PopJumpIfFalse(8), PushNull, LoadName("x"), CallInstrinsic1(Print), LoadName("x"), LoadConst(Int(1)), BinaryAdd, StoreName("x"), JumpBackward(12), LoadConst(None)]"#.to_string());
+        assert_eq!(format!("{:?}", code), r#"[LoadConst(Int(0)), StoreName("x"), LoadName("x"), LoadConst(Int(3)), CompareOp(LessThan), PopJumpIfFalse(9), LoadName("print"), LoadName("x"), CallFunction(1), PopTop, LoadName("x"), LoadConst(Int(1)), BinaryAdd, StoreName("x"), JumpBackward(13), LoadConst(None), PopTop]"#.to_string());
         
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
 
     }
 
@@ -408,29 +419,30 @@ mod tests {
             PyBytecode::LoadName("x".to_string()), 
             PyBytecode::LoadConst(Obj::Int(3.into())), 
             PyBytecode::CompareOp(Op::LessThan), 
-            PyBytecode::PopJumpIfFalse(8),
-            PyBytecode::PushNull,
+            PyBytecode::PopJumpIfFalse(9),
+            PyBytecode::LoadName("print".to_string()),
             PyBytecode::LoadName("x".to_string()),
-            PyBytecode::CallInstrinsic1(IntrinsicFunc::Print),
+            PyBytecode::CallFunction(1),
+            PyBytecode::PopTop,
             PyBytecode::LoadName("x".to_string()),
             PyBytecode::LoadConst(Obj::Int(1.into())),
             PyBytecode::BinaryAdd,
             PyBytecode::StoreName("x".to_string()),
-            PyBytecode::JumpBackward(12),
+            PyBytecode::JumpBackward(13),
             PyBytecode::NOP,
         ];
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
     }
 
     #[test]
     #[ignore]
     fn file_to_bytecode()
     {
-        let code = Interpreter::compile_file("bytecode_t.py");
+        let code = Interpreter::compile_file("bytecode_t.py").unwrap();
         println!("Bytecode from file:\n{}", PyBytecode::to_string(&code));
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
     }
 
     use std::{
@@ -480,25 +492,25 @@ mod tests {
     #[test]
     fn list()
     {
-        let line1 = Expression::from_line("x = [2, 3, 4]");
+        let line1 = Expression::from_line("x = [2, 3, 4]").unwrap();
         assert_eq!(line1.to_string(), "Op[= Ident(x) Op[list Atom(2) Atom(3) Atom(4)]]".to_string());
     
-        let line2 = Expression::from_line("print(x + [\"add\", \"none\"])");
-        assert_eq!(line2.to_string(), "Call[print args[ Op[+ Ident(x) Op[list Atom(add) Atom(none)]]]]");
+        let line2 = Expression::from_line("print(x + [\"add\", \"none\"])").unwrap();
+        assert_eq!(line2.to_string(), "Call[print args[ Op[+ Ident(x) Op[list Str(add) Str(none)]]]]");
 
         let mut bytecode = vec![];
-        PyBytecode::from_expr(line1, &mut bytecode);
-        PyBytecode::from_expr(line2, &mut bytecode);
+        PyBytecode::from_expr(line1, &mut bytecode).unwrap();
+        PyBytecode::from_expr(line2, &mut bytecode).unwrap();
 
-        assert_eq!(format!("{:?}", bytecode), r#"[LoadConst(Int(2)), LoadConst(Int(3)), LoadConst(Int(4)), BuildList(3), StoreName("x"), PushNull, LoadName("x"), LoadConst(Str("add")), LoadConst(Str("none")), BuildList(2), BinaryAdd, CallInstrinsic1(Print)]"#.to_string());
+        assert_eq!(format!("{:?}", bytecode), r#"[LoadConst(Int(2)), LoadConst(Int(3)), LoadConst(Int(4)), BuildList(3), StoreName("x"), LoadName("print"), LoadName("x"), LoadConst(Str("add")), LoadConst(Str("none")), BuildList(2), BinaryAdd, CallFunction(1)]"#.to_string());
         let mut vm = PyVM::new();
-        vm.execute(bytecode);
+        vm.execute(bytecode).unwrap();
     }
 
     #[test]
     fn definition()
     {
-        let line1 = Expression::from_multiline("def go(a):\n\tprint(1)\ngo()");
+        let line1 = Expression::from_multiline("def go(a):\n\tprint(1)\ngo()").unwrap();
 
         let expr_strs = join_expr_strings(vec![&line1[0], &line1[1]]);
         let res_strs = "Keyword[def conds[ Ident(go) Ident(a)] args[ Call[print args[ Atom(1)]]]] | Call[go args[]]";
@@ -520,44 +532,49 @@ mod tests {
             \tprint(\"c: good\")\n\
             else:\n\
             \tprint(\"d: good\")"
-        );
+        ).unwrap();
 
         println!("{}", PyBytecode::to_string(&code));
         let instructions = vec![
             PyBytecode::LoadConst(false.to_obj()),
-            PyBytecode::PopJumpIfFalse(4),
-            PyBytecode::PushNull,
+            PyBytecode::PopJumpIfFalse(5),
+            PyBytecode::LoadName("print".to_string()),
             PyBytecode::LoadConst("a: bad".to_obj()),
-            PyBytecode::CallInstrinsic1(IntrinsicFunc::Print),
-            PyBytecode::JumpForward(12),
+            PyBytecode::CallFunction(1),
+            PyBytecode::PopTop,
+            PyBytecode::JumpForward(14),
             PyBytecode::LoadConst(false.to_obj()),
-            PyBytecode::PopJumpIfFalse(4),
-            PyBytecode::PushNull,
+            PyBytecode::PopJumpIfFalse(5),
+            PyBytecode::LoadName("print".to_string()),
             PyBytecode::LoadConst("b: good".to_obj()),
-            PyBytecode::CallInstrinsic1(IntrinsicFunc::Print),
-            PyBytecode::JumpForward(6),
+            PyBytecode::CallFunction(1),
+            PyBytecode::PopTop,
+            PyBytecode::JumpForward(7),
             PyBytecode::LoadConst(true.to_obj()),
-            PyBytecode::PopJumpIfFalse(4),
-            PyBytecode::PushNull,
+            PyBytecode::PopJumpIfFalse(5),
+            PyBytecode::LoadName("print".to_string()),
             PyBytecode::LoadConst("e: good".to_obj()),
-            PyBytecode::CallInstrinsic1(IntrinsicFunc::Print),
+            PyBytecode::CallFunction(1),
+            PyBytecode::PopTop,
             PyBytecode::JumpForward(0),
             PyBytecode::LoadConst(false.to_obj()),
-            PyBytecode::PopJumpIfFalse(4),
-            PyBytecode::PushNull,
+            PyBytecode::PopJumpIfFalse(5),
+            PyBytecode::LoadName("print".to_string()),
             PyBytecode::LoadConst("c: good".to_obj()),
-            PyBytecode::CallInstrinsic1(IntrinsicFunc::Print),
-            PyBytecode::JumpForward(3),
-            PyBytecode::PushNull,
+            PyBytecode::CallFunction(1),
+            PyBytecode::PopTop,
+            PyBytecode::JumpForward(4),
+            PyBytecode::LoadName("print".to_string()),
             PyBytecode::LoadConst("d: good".to_obj()),
-            PyBytecode::CallInstrinsic1(IntrinsicFunc::Print),
+            PyBytecode::CallFunction(1),
+            PyBytecode::PopTop,
         ];
         assert_eq!(PyBytecode::to_string(&code), PyBytecode::to_string(&instructions));
         //for i in 0..code.len() {
         //    assert_eq!(code[i], instructions[i], "Instruction ({i})");
         //}
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
 
     }
 
@@ -567,33 +584,39 @@ mod tests {
             "def add(x, y):\n\
              \treturn x + y\n\
             result = add(5, 3)"
-        );
+        ).unwrap();
         
         println!("Function definition bytecode:\n{}", PyBytecode::to_string(&code));
         
         // Test that function definition generates proper bytecode
         assert!(code.iter().any(|inst| matches!(inst, PyBytecode::MakeFunction)));
-        assert!(code.iter().any(|inst| matches!(inst, PyBytecode::ReturnValue)));
-        assert!(code.iter().any(|inst| matches!(inst, PyBytecode::JumpForward(_))));
+
+        // the body is compiled into its own code object
+        let body = code.iter().find_map(|inst| match inst {
+            PyBytecode::LoadConst(Obj::Code(c)) => Some(c.clone()),
+            _ => None,
+        }).expect("def should load a code object");
+        assert_eq!(body.arg_names(), ["x", "y"]);
+        assert!(body.co_code.iter().any(|inst| matches!(inst, PyBytecode::ReturnValue)));
         
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
+        assert_eq!(vm.get_vars()[0]["result"], 8.to_arc());
     }
 
     #[test]
-    #[ignore]
     fn function_with_default_args() {
-        let expr = Expression::from_multiline("def greet(name, msg=\"Hello\"):\n\tprint(msg, name)");
+        let expr = Expression::from_multiline("def greet(name, msg=\"Hello\"):\n\tprint(msg, name)").unwrap();
         assert_eq!(expr.len(), 1);
         
-        let expected = "Keyword[def conds[ Ident(greet) Ident(name) Op[= Ident(msg) Atom(Hello)]] args[ Call[print args[ Ident(msg) Ident(name)]]]]";
+        let expected = "Keyword[def conds[ Ident(greet) Ident(name) Op[= Ident(msg) Str(Hello)]] args[ Call[print args[ Ident(msg) Ident(name)]]]]";
         assert_eq!(expr[0].to_string(), expected);
     }
 
     #[test]
     fn unary_operations() {
-        let pos_expr = Expression::from_line("+5");
-        let neg_expr = Expression::from_line("-10");
+        let pos_expr = Expression::from_line("+5").unwrap();
+        let neg_expr = Expression::from_line("-10").unwrap();
         
         assert_eq!(pos_expr.to_string(), "Op[+ Atom(5)]");
         assert_eq!(neg_expr.to_string(), "Op[- Atom(10)]");
@@ -602,7 +625,7 @@ mod tests {
     #[test]
     fn bytecode_unary() {
         let mut code = vec![];
-        PyBytecode::from_expr(Expression::from_line("-42"), &mut code);
+        PyBytecode::from_expr(Expression::from_line("-42").unwrap(), &mut code).unwrap();
         
         let expected = vec![
             PyBytecode::LoadConst(Obj::Int(42.into())),
@@ -626,43 +649,43 @@ mod tests {
         ];
         
         let mut vs = Obj::new_map();
-        let mut fns = Funcs::get_std_map();
+        let mut vm = PyVM::new();
 
         for (expr_str, expected) in comparisons {
-            let expr = Expression::from_line(expr_str);
-            assert_eq!(expr.eval(&mut vs, &mut fns).unwrap().to_string(), expected, "{}", expr.to_string());
+            let expr = Expression::from_line(expr_str).unwrap();
+            assert_eq!(expr.eval(&mut vs, &mut vm).unwrap().to_string(), expected, "{}", expr.to_string());
         }
     }
 
     #[test]
     fn ops_tuple() 
     {
-        let tuple_expr = Expression::from_line("(1, 2, 3)");
+        let tuple_expr = Expression::from_line("(1, 2, 3)").unwrap();
         println!("Tuple expression: {}", tuple_expr);
         
         let mut bytecode = vec![];
-        PyBytecode::from_expr(tuple_expr, &mut bytecode);
+        PyBytecode::from_expr(tuple_expr, &mut bytecode).unwrap();
         println!("Tuple bytecode: {:?}", bytecode);
     }
 
     #[test]
     fn ops_set()
     {
-        let tuple_expr = Expression::from_line("{1, 2, 3}");
+        let tuple_expr = Expression::from_line("{1, 2, 3}").unwrap();
         println!("Tuple expression: {}", tuple_expr);
         
         let mut bytecode = vec![];
-        PyBytecode::from_expr(tuple_expr, &mut bytecode);
+        PyBytecode::from_expr(tuple_expr, &mut bytecode).unwrap();
         println!("Tuple bytecode: {:?}", bytecode);
     }
 
     #[test]
     fn ops_dot()
     {
-        let expr1 = Expression::from_line("a.x");
+        let expr1 = Expression::from_line("a.x").unwrap();
         assert_eq!(&expr1.to_string(), "Op[. Ident(a) Ident(x)]");
 
-        let expr2 = Expression::from_line("a.x()");
+        let expr2 = Expression::from_line("a.x()").unwrap();
         assert_eq!(&expr2.to_string(), "Op[. Ident(a) Call[x args[]]]");
     }
 
@@ -673,7 +696,7 @@ mod tests {
             for i in v:\n\
                 \tprint(i)";
 
-        let for_expr = Expression::from_multiline(source_code);
+        let for_expr = Expression::from_multiline(source_code).unwrap();
         
         assert_eq!(for_expr.len(), 2);
         println!("For loop: {}", for_expr[1]);
@@ -695,21 +718,21 @@ mod tests {
             _ => panic!("Expected for loop keyword expression"),
         }
 
-        let code = PyBytecode::from_str(source_code);
+        let code = PyBytecode::from_str(source_code).unwrap();
         println!("code: \n{}", PyBytecode::to_string(&code));
         
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
 
     }
 
     #[test]
     fn nested_list() {
-        let nested_list = Expression::from_line("[[1, 2], [3, 4]]");
+        let nested_list = Expression::from_line("[[1, 2], [3, 4]]").unwrap();
         assert_eq!(nested_list.to_string(), "Op[list Op[list Atom(1) Atom(2)] Op[list Atom(3) Atom(4)]]");
         
         let mut bytecode = vec![];
-        PyBytecode::from_expr(nested_list, &mut bytecode);
+        PyBytecode::from_expr(nested_list, &mut bytecode).unwrap();
         
         // Should have multiple BuildList instructions
         let build_list_count = bytecode.iter().filter(|inst| matches!(inst, PyBytecode::BuildList(_))).count();
@@ -717,37 +740,34 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn error_bytecode_generation() {
-        // Test that unsupported operations generate error bytecode
+        // unsupported operations are syntax errors
         let mut bytecode = vec![];
         let invalid_expr = Expression::Operation(Op::Dot, vec![
             Expression::Atom("obj".to_string()),
             Expression::Atom("method".to_string())
         ]);
         
-        PyBytecode::from_expr(invalid_expr, &mut bytecode);
-        
-        // Should generate an Error bytecode
-        assert!(bytecode.iter().any(|inst| matches!(inst, PyBytecode::Error(_))));
+        let err = PyBytecode::from_expr(invalid_expr, &mut bytecode).unwrap_err();
+        assert_eq!(err.to_string(), "SyntaxError: expected an attribute name after '.', found Atom(method)");
     }
 
     #[test]
     fn parse_precedence_simple() {
-        let e = Expression::from_line("1 + 2 * 3");
+        let e = Expression::from_line("1 + 2 * 3").unwrap();
         assert_eq!(e.to_string(), "Op[+ Atom(1) Op[* Atom(2) Atom(3)]]");
     }
 
     #[test]
     fn parse_precedence_complex() {
-        let e = Expression::from_line("2 + 3 * 4 - 5 / 2");
+        let e = Expression::from_line("2 + 3 * 4 - 5 / 2").unwrap();
         let expected = "Op[- Op[+ Atom(2) Op[* Atom(3) Atom(4)]] Op[/ Atom(5) Atom(2)]]";
         assert_eq!(e.to_string(), expected);
     }
 
     #[test]
     fn parse_precedence_parentheses_override() {
-        let e = Expression::from_line("(2 + 3) * 4 - 5 / 2");
+        let e = Expression::from_line("(2 + 3) * 4 - 5 / 2").unwrap();
         let expected = "Op[- Op[* Op[+ Atom(2) Atom(3)] Atom(4)] Op[/ Atom(5) Atom(2)]]";
         assert_eq!(e.to_string(), expected);
     }
@@ -755,10 +775,10 @@ mod tests {
     #[test]
     fn parse_precedence_complex_maths() 
     {
-        let code = PyBytecode::from_str("2 + 3 * 4 - 5 / 2");
+        let code = PyBytecode::from_str("2 + 3 * 4 - 5 / 2").unwrap();
         println!("code: \n{}", PyBytecode::to_string(&code));
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
 
         let stack = vm.view_stack();
         let expected = vec![vec![11.5.to_arc()]];
@@ -773,10 +793,10 @@ mod tests {
             "x = 10\n\
              y = x * 2\n\
              print(y)"
-        );
+        ).unwrap();
         
         let mut vm = PyVM::new();
-        vm.execute(code);
+        vm.execute(code).unwrap();
         let v = vm.get_vars();
         let vars = &v[0];
         
@@ -787,28 +807,29 @@ mod tests {
     }
 
     #[test]
-    fn intrinsic_functions() {
-        // Test that intrinsic functions are properly identified
-        assert!(IntrinsicFunc::try_get("print").is_some());
-        assert!(IntrinsicFunc::try_get("input").is_some());
-        assert!(IntrinsicFunc::try_get("nonexistent").is_none());
+    fn builtin_functions() {
+        // Test that builtins are found in the builtins namespace
+        assert!(builtins::lookup("print").is_some());
+        assert!(builtins::lookup("input").is_some());
+        assert!(builtins::lookup("nonexistent").is_none());
         
-        // Test intrinsic function bytecode generation
-        let print_expr = Expression::from_line("print(\"Hello World\")");
+        // Test builtin calls compile like any other call
+        let print_expr = Expression::from_line("print(\"Hello World\")").unwrap();
         let mut bytecode = vec![];
-        PyBytecode::from_expr(print_expr, &mut bytecode);
+        PyBytecode::from_expr(print_expr, &mut bytecode).unwrap();
         
-        assert!(bytecode.iter().any(|inst| matches!(inst, PyBytecode::CallInstrinsic1(IntrinsicFunc::Print))));
+        assert_eq!(bytecode.first(), Some(&PyBytecode::LoadName("print".to_string())));
+        assert!(bytecode.iter().any(|inst| matches!(inst, PyBytecode::CallFunction(1))));
     }
 
     #[test]
     fn multiline_string_parsing() {
         // Test parsing of strings with quotes
-        let single_quote = Expression::from_line("'single quoted'");
-        let double_quote = Expression::from_line("\"double quoted\"");
+        let single_quote = Expression::from_line("'single quoted'").unwrap();
+        let double_quote = Expression::from_line("\"double quoted\"").unwrap();
         
-        assert_eq!(single_quote.to_string(), "Atom(single quoted)");
-        assert_eq!(double_quote.to_string(), "Atom(double quoted)");
+        assert_eq!(single_quote.to_string(), "Str(single quoted)");
+        assert_eq!(double_quote.to_string(), "Str(double quoted)");
         
         let mut eq = EqTester::new();
         eq.eval_eq(&single_quote, "single quoted");
@@ -827,14 +848,13 @@ mod tests {
         let _unary_inv = PyBytecode::UnaryInvert;
         let _to_bool = PyBytecode::ToBool;
         let _binary_xor = PyBytecode::BinaryXOR;
-        let _load_global = PyBytecode::LoadGlobal;
-        let _store_global = PyBytecode::StoreGlobal;
-        let _call_intrinsic2 = PyBytecode::CallInstrinsic2(IntrinsicFunc::Print);
+        let _load_global = PyBytecode::LoadGlobal(String::new());
+        let _store_global = PyBytecode::StoreGlobal(String::new());
         let _jump_if_false = PyBytecode::JumpIfFalse;
         let _jump_absolute = PyBytecode::JumpAbsolute;
         let _build_tuple = PyBytecode::BuildTuple(3);
-        let _build_map = PyBytecode::BuildMap;
-        let _list_append = PyBytecode::ListAppend;
+        let _build_map = PyBytecode::BuildMap(0);
+        let _list_append = PyBytecode::ListAppend(1);
         let _for_iter = PyBytecode::ForIter;
         let _get_iter = PyBytecode::GetIter;
         let _new_stack = PyBytecode::NewStack;
@@ -847,7 +867,7 @@ mod tests {
         assert_eq!(empty_expr.to_string(), "None");
         
         let mut bytecode = vec![];
-        PyBytecode::from_expr(empty_expr, &mut bytecode);
+        PyBytecode::from_expr(empty_expr, &mut bytecode).unwrap();
         
         // Should not generate any bytecode for None expression
         assert!(bytecode.is_empty());
@@ -969,7 +989,7 @@ mod tests {
              \tresult = \"medium\"\n\
              else:\n\
              \tresult = \"large\""
-        );
+        ).unwrap();
         
         let expected = vec![
             PyBytecode::LoadConst(15.to_obj()),
@@ -995,7 +1015,7 @@ mod tests {
         assert_eq!(PyBytecode::to_string(&code), PyBytecode::to_string(&expected));
         let mut vm = PyVM::new();
         vm.set_debug_mode(true);
-        vm.execute(code);
+        vm.execute(code).unwrap();
         let vars = vm.get_vars();
         
         let mut expected_vars = HashMap::new();
@@ -1015,7 +1035,7 @@ mod tests {
              \t\tprint(i, j)\n\
              \t\tj = j + 1\n\
              \ti = i + 1"
-        );
+        ).unwrap();
         
         // Just test that it parses correctly
         assert!(expr.len() >= 2); // At least assignment and while loop
@@ -1023,7 +1043,7 @@ mod tests {
         // Test bytecode generation doesn't crash
         let mut bytecode = vec![];
         for e in expr {
-            PyBytecode::from_expr(e, &mut bytecode);
+            PyBytecode::from_expr(e, &mut bytecode).unwrap();
         }
         
         println!("Nested while loops bytecode:\n{}", PyBytecode::to_string(&bytecode));
@@ -1038,7 +1058,7 @@ mod tests {
         
         for (i, (expr_str, expected)) in list_ops.iter().enumerate() {
             println!("Line: {}", expr_str);
-            let exprs = Expression::from_multiline(expr_str);
+            let exprs = Expression::from_multiline(expr_str).unwrap();
             let expr = exprs.first().unwrap();
             let obj = expr.clone().to_obj();
             assert_eq!(&obj.__str__(), expected, "expr(#{i}) {}", expr.to_string());
@@ -1048,12 +1068,2559 @@ mod tests {
     #[test]
     fn iteration() 
     {
-        let list = Obj::List(vec![1.to_arc(), 2.to_arc()]);
+        let list = Obj::new_list(vec![1.to_arc(), 2.to_arc()]);
         for x in list {
             println!("{}", x);
         }
     }
 
+    fn run_source(source: &str) -> PyVM {
+        let code = PyBytecode::from_str(source).unwrap();
+        let mut vm = PyVM::new();
+        vm.execute(code).unwrap();
+        vm
+    }
+
+    #[test]
+    fn parse_yield() {
+        assert_eq!(Expression::from_line("yield x").unwrap().to_string(), "Keyword[yield conds[ Ident(x)] args[]]");
+        assert_eq!(Expression::from_line("yield").unwrap().to_string(), "Keyword[yield conds[] args[]]");
+        assert_eq!(Expression::from_line("yield from g").unwrap().to_string(), "Keyword[yield from conds[ Ident(g)] args[]]");
+        assert_eq!(
+            Expression::from_line("x = (i for i in y if i)").unwrap().to_string(),
+            "Op[= Ident(x) Op[genexpr Ident(i) Keyword[for conds[ Ident(i) Ident(y)] args[]] Keyword[if conds[ Ident(i)] args[]]]]"
+        );
+    }
+
+    #[test]
+    fn generator_next_and_for() {
+        let vm = run_source(
+            "def counter(n):\n\
+             \ti = 0\n\
+             \twhile i < n:\n\
+             \t\tyield i\n\
+             \t\ti += 1\n\
+             g = counter(3)\n\
+             a = next(g)\n\
+             b = next(g)\n\
+             total = 0\n\
+             for v in counter(4):\n\
+             \ttotal += v\n\
+             done = next(counter(0), \"empty\")"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], 0.to_arc());
+        assert_eq!(vars["b"], 1.to_arc());
+        assert_eq!(vars["total"], 6.to_arc());
+        assert_eq!(vars["done"], "empty".to_arc());
+        assert!(matches!(vars["g"].as_ref(), Obj::Generator(_)));
+    }
+
+    #[test]
+    fn generator_send_and_close() {
+        let vm = run_source(
+            "def echo():\n\
+             \treceived = yield 1\n\
+             \tyield received * 2\n\
+             \tyield 3\n\
+             g = echo()\n\
+             first = next(g)\n\
+             second = g.send(5)\n\
+             closed = g.close()\n\
+             after = next(g, \"finished\")"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["first"], 1.to_arc());
+        assert_eq!(vars["second"], 10.to_arc());
+        assert_eq!(vars["closed"], Obj::None.into());
+        assert_eq!(vars["after"], "finished".to_arc());
+    }
+
+    #[test]
+    fn generator_yield_from() {
+        let vm = run_source(
+            "def inner():\n\
+             \tyield 1\n\
+             \tyield 2\n\
+             \treturn 3\n\
+             def outer():\n\
+             \tresult = yield from inner()\n\
+             \tyield result\n\
+             \tyield from [10, 20]\n\
+             total = 0\n\
+             for v in outer():\n\
+             \ttotal += v"
+        );
+        assert_eq!(vm.get_vars()[0]["total"], 36.to_arc());
+    }
+
+    #[test]
+    fn generator_expression() {
+        let vm = run_source(
+            "scale = 2\n\
+             total = 0\n\
+             for x in (i * scale for i in [1, 2, 3] if i > 1):\n\
+             \ttotal += x\n\
+             g = (c for c in \"ab\")\n\
+             first = next(g)"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["total"], 10.to_arc());
+        assert_eq!(vars["first"], "a".to_arc());
+    }
+
+    #[test]
+    fn parse_comprehensions() {
+        assert_eq!(
+            Expression::from_line("[x * 2 for x in y if x]").unwrap().to_string(),
+            "Op[listcomp Op[* Ident(x) Atom(2)] Keyword[for conds[ Ident(x) Ident(y)] args[]] Keyword[if conds[ Ident(x)] args[]]]"
+        );
+        assert_eq!(
+            Expression::from_line("{k: v for k, v in y}").unwrap().to_string(),
+            "Op[dictcomp Ident(k) Ident(v) Keyword[for conds[ Op[tuple Ident(k) Ident(v)] Ident(y)] args[]]]"
+        );
+        assert_eq!(Expression::from_line("{x for x in y}").unwrap().to_string(), "Op[setcomp Ident(x) Keyword[for conds[ Ident(x) Ident(y)] args[]]]");
+        assert_eq!(Expression::from_line("{\"a\": 1, \"b\": 2}").unwrap().to_string(), "Op[dict Str(a) Atom(1) Str(b) Atom(2)]");
+        assert_eq!(Expression::from_line("{}").unwrap().to_string(), "Op[dict]");
+    }
+
+    #[test]
+    fn list_comprehension() {
+        let vm = run_source(
+            "evens = [x * 2 for x in [1, 2, 3, 4] if x > 2]\n\
+             pairs = [(a, b) for a in [1, 2] for b in \"xy\"]\n\
+             squares = [n * n for n in (i + 1 for i in [0, 1, 2])]"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["evens"].to_string(), "[6, 8]");
+        assert_eq!(vars["pairs"].to_string(), "[(1, \'x\'), (1, \'y\'), (2, \'x\'), (2, \'y\')]");
+        assert_eq!(vars["squares"].to_string(), "[1, 4, 9]");
+        // the loop variables belong to the comprehension's own scope
+        assert!(!vars.contains_key("x"));
+        assert!(!vars.contains_key("a"));
+    }
+
+    #[test]
+    fn set_and_dict_comprehension() {
+        let vm = run_source(
+            "offset = 10\n\
+             s = {x * 0 + 1 for x in [1, 2, 3]}\n\
+             d = {k: v + offset for k, v in [(\"a\", 1), (\"b\", 2)]}\n\
+             lit = {\"c\": 3, 1: [4]}\n\
+             empty = {}"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["s"].to_string(), "{1}");
+        match vars["d"].as_ref() {
+            Obj::Dict(d) => {
+                let d = d.lock().unwrap();
+                assert_eq!(d.len(), 2);
+                assert_eq!(d[&"a".to_obj()], 11.to_arc());
+                assert_eq!(d[&"b".to_obj()], 12.to_arc());
+            }
+            o => panic!("expected a dict, found {o}"),
+        }
+        match vars["lit"].as_ref() {
+            Obj::Dict(d) => {
+                let d = d.lock().unwrap();
+                assert_eq!(d[&"c".to_obj()], 3.to_arc());
+                assert_eq!(d[&1.0.to_obj()].to_string(), "[4]");
+            }
+            o => panic!("expected a dict, found {o}"),
+        }
+        assert!(matches!(vars["empty"].as_ref(), Obj::Dict(d) if d.lock().unwrap().is_empty()));
+        assert!(!vars.contains_key("k"));
+    }
+
+    // comprehensions and {} take operators after them like any other operand
+    #[test]
+    fn comprehension_operands() {
+        let vm = run_source(
+            "first = [i for i in range(3)][0]\n\
+             tail = [c for c in \"xyz\"][1:]\n\
+             called = {k: len for k in \"ab\"}[\"a\"](\"xyz\")\n\
+             nxt = (x * 2 for x in [4, 5]).__next__()\n\
+             same = [{k: 1 for k in \"ab\"} == {\"a\": 1, \"b\": 1}, {} == {}, {x for x in [1, 1]} == {1}, {} != {1: 2}]"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["first"], 0.to_arc());
+        assert_eq!(vars["tail"].__repr__(), "['y', 'z']");
+        assert_eq!(vars["called"], 3.to_arc());
+        assert_eq!(vars["nxt"], 8.to_arc());
+        assert_eq!(vars["same"].__repr__(), "[True, True, True, True]");
+    }
+
+    #[test]
+    fn parse_lambda() {
+        assert_eq!(
+            Expression::from_line("f = lambda x, y=2: x + y").unwrap().to_string(),
+            "Op[= Ident(f) Keyword[lambda conds[ Ident(x) Op[= Ident(y) Atom(2)]] args[ Op[+ Ident(x) Ident(y)]]]]"
+        );
+        assert_eq!(
+            Expression::from_line("lambda *args, key=None, **kw: args").unwrap().to_string(),
+            "Keyword[lambda conds[ Op[* Ident(args)] Op[= Ident(key) Keyword[None conds[] args[]]] Op[** Ident(kw)]] args[ Ident(args)]]"
+        );
+        assert_eq!(Expression::from_line("lambda: 0").unwrap().to_string(), "Keyword[lambda conds[] args[ Atom(0)]]");
+    }
+
+    #[test]
+    fn lambda_calls() {
+        let vm = run_source(
+            "add = lambda x, y=10: x + y\n\
+             a = add(1)\n\
+             b = add(1, 2)\n\
+             c = add(y=5, x=3)\n\
+             total = lambda *nums: [n * 2 for n in nums]\n\
+             d = total(1, 2, 3)\n\
+             opts = lambda a, *, scale=3, **rest: [a * scale, rest]\n\
+             e = opts(2, extra=1)\n\
+             f = (lambda: \"called\")()"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], 11.to_arc());
+        assert_eq!(vars["b"], 3.to_arc());
+        assert_eq!(vars["c"], 8.to_arc());
+        assert_eq!(vars["d"].to_string(), "[2, 4, 6]");
+        assert_eq!(vars["e"].to_string(), "[6, {\'extra\':1}]");
+        assert_eq!(vars["f"], "called".to_arc());
+        assert_eq!(vars["add"].to_string().split(" at ").next(), Some("<function <lambda>"));
+    }
+
+    #[test]
+    fn closures() {
+        let vm = run_source(
+            "def make_adder(n):\n\
+             \treturn lambda x: x + n\n\
+             def late():\n\
+             \tget = lambda: value\n\
+             \tvalue = \"bound later\"\n\
+             \treturn get()\n\
+             def countdown(n):\n\
+             \tdef step(i):\n\
+             \t\tif i == 0:\n\
+             \t\t\treturn 0\n\
+             \t\treturn i + step(i - 1)\n\
+             \treturn step(n)\n\
+             add5 = make_adder(5)\n\
+             a = add5(1)\n\
+             b = make_adder(100)(1)\n\
+             c = late()\n\
+             d = countdown(4)"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], 6.to_arc());
+        assert_eq!(vars["b"], 101.to_arc());
+        assert_eq!(vars["c"], "bound later".to_arc());
+        assert_eq!(vars["d"], 10.to_arc());
+    }
+
+    #[test]
+    fn parse_decorators() {
+        assert_eq!(Expression::from_line("@staticmethod").unwrap().to_string(), "Op[@ Ident(staticmethod)]");
+        assert_eq!(
+            Expression::from_line("@functools.lru_cache(maxsize=2)").unwrap().to_string(),
+            "Op[@ Op[. Ident(functools) Call[lru_cache args[ Op[= Ident(maxsize) Atom(2)]]]]]"
+        );
+        let exprs = Expression::from_multiline(
+            "@a\n\
+             @b(1)\n\
+             def f():\n\
+             \treturn 1"
+        ).unwrap();
+        assert_eq!(exprs.len(), 1);
+        assert_eq!(
+            exprs[0].to_string(),
+            "Op[@ Ident(a) Call[b args[ Atom(1)]] Keyword[def conds[ Ident(f)] args[ Keyword[return conds[ Atom(1)] args[]]]]]"
+        );
+    }
+
+    #[test]
+    fn function_decorators() {
+        let vm = run_source(
+            "from functools import wraps\n\
+             class Log:\n\
+             \torder = \"\"\n\
+             def tag(name):\n\
+             \tLog.order += name + \" \"\n\
+             \tdef deco(f):\n\
+             \t\t@wraps(f)\n\
+             \t\tdef wrapper(*args, **kwargs):\n\
+             \t\t\treturn [name, f(*args, **kwargs)]\n\
+             \t\treturn wrapper\n\
+             \treturn deco\n\
+             @tag(\"outer\")\n\
+             @tag(\"inner\")\n\
+             def greet(x, punct=\"!\"):\n\
+             \treturn x + punct\n\
+             a = greet(\"hi\", punct=\"?\")\n\
+             name = greet.__name__\n\
+             unwrapped = greet.__wrapped__.__wrapped__(\"yo\")\n\
+             order = Log.order"
+        );
+        let vars = &vm.get_vars()[0];
+        // evaluated top down, applied bottom up
+        assert_eq!(vars["order"], "outer inner ".to_arc());
+        assert_eq!(vars["a"].to_string(), "['outer', ['inner', 'hi?']]");
+        assert_eq!(vars["name"], "greet".to_arc());
+        assert_eq!(vars["unwrapped"], "yo!".to_arc());
+    }
+
+    #[test]
+    fn classes_and_descriptors() {
+        let vm = run_source(
+            "class Temp:\n\
+             \tcount = 0\n\
+             \tdef __init__(self, c):\n\
+             \t\tself.c = c\n\
+             \t\tTemp.count += 1\n\
+             \t@property\n\
+             \tdef f(self):\n\
+             \t\treturn self.c * 2 + 30\n\
+             \t@f.setter\n\
+             \tdef f(self, value):\n\
+             \t\tself.c = (value - 30) / 2\n\
+             \t@staticmethod\n\
+             \tdef scale():\n\
+             \t\treturn \"celsius\"\n\
+             \t@classmethod\n\
+             \tdef freezing(cls):\n\
+             \t\treturn cls(0)\n\
+             class Reading(Temp):\n\
+             \tdef describe(self):\n\
+             \t\treturn [self.scale(), self.f]\n\
+             t = Temp(10)\n\
+             a = t.f\n\
+             t.f = 50\n\
+             b = t.c\n\
+             r = Reading.freezing()\n\
+             c = r.describe()\n\
+             d = Temp.count"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], 50.to_arc());
+        assert_eq!(vars["b"], 10.to_arc());
+        assert_eq!(vars["c"].to_string(), "['celsius', 30]");
+        assert_eq!(vars["d"], 2.to_arc());
+        assert!(vars["r"].to_string().starts_with("<Reading object at "));
+    }
+
+    #[test]
+    fn lru_cache_decorator() {
+        let vm = run_source(
+            "import functools\n\
+             class Calls:\n\
+             \tn = 0\n\
+             @functools.lru_cache(maxsize=2)\n\
+             def square(x):\n\
+             \tCalls.n += 1\n\
+             \treturn x * x\n\
+             a = [square(2), square(2), square(3), square(4), square(2)]\n\
+             info = square.cache_info()\n\
+             fields = [repr(info), info.hits, info.misses, info.maxsize, info.currsize, info[-1], len(info), list(info)]\n\
+             @functools.cache\n\
+             def fib(n):\n\
+             \tif n < 2:\n\
+             \t\treturn n\n\
+             \treturn fib(n - 1) + fib(n - 2)\n\
+             b = fib(40)\n\
+             unbounded = fib.cache_info().maxsize\n\
+             calls = Calls.n"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"].to_string(), "[4, 4, 9, 16, 4]");
+        // 2 was the least recently used once 4 came in, so it's computed again
+        assert_eq!(vars["calls"], 4.to_arc());
+        assert_eq!(vars["fields"].__repr__(), "['CacheInfo(hits=1, misses=4, maxsize=2, currsize=2)', 1, 4, 2, 2, 2, 4, [1, 4, 2, 2]]");
+        assert_eq!(vars["unbounded"], Obj::None.into());
+        assert_eq!(vars["b"], 102334155.to_arc());
+    }
+
+    #[test]
+    fn split_string_literals() {
+        assert_eq!(split_to_words(r#"x = "a\"b" + 'c'"#), vec!["x", "=", r#""a\"b""#, "+", "'c'"]);
+        assert_eq!(split_to_words(r#"f(r"\d", b'x', Rb"y")"#), vec!["f", "(", r#"r"\d""#, ",", "b'x'", ",", r#"Rb"y""#, ")"]);
+        assert_eq!(split_to_words(r#""""a "quoted" b""" + rb"""""#), vec![r#""""a "quoted" b""""#, "+", r#"rb"""""#]);
+        // a prefix is only a prefix right before the quote
+        assert_eq!(split_to_words("br = b"), vec!["br", "=", "b"]);
+    }
+
+    #[test]
+    fn string_literal_escapes() {
+        let parse = |s: &str| Expression::from_line(s).unwrap();
+        assert_eq!(parse(r#""tab\tnew\nline""#), Expression::Str("tab\tnew\nline".into()));
+        assert_eq!(parse(r#"'it\'s \\ \"q\"'"#), Expression::Str("it's \\ \"q\"".into()));
+        assert_eq!(parse(r#""\x41\101\u00e9\U0001F600\N{GREEK SMALL LETTER ALPHA}""#), Expression::Str("AAé😀α".into()));
+        // unknown escapes keep their backslash
+        assert_eq!(parse(r#""\d\q""#), Expression::Str("\\d\\q".into()));
+        assert_eq!(parse(r#"r"\d\n\"""#), Expression::Str("\\d\\n\\\"".into()));
+        assert_eq!(parse(r#"b"\x00\xff\777\u""#), Expression::Bytes(vec![0, 0xff, 0xff, b'\\', b'u']));
+        assert_eq!(parse(r#"rb"\x00""#), Expression::Bytes(b"\\x00".to_vec()));
+        assert_eq!(parse(r#""a" 'b' """c""""#), Expression::Str("abc".into()));
+        assert_eq!(parse(r#"b"a" b'b'"#), Expression::Bytes(b"ab".to_vec()));
+        assert_eq!(parse(r#"b"a\"" + b"""#).to_string(), r#"Op[+ Bytes(b'a"') Bytes(b'')]"#);
+        assert_eq!(parse(r#"r"\\""#), Expression::Str("\\\\".into()));
+
+        // the backslash takes the closing quote, raw or not
+        for unclosed in [r#"r"\""#, r#""a\""#, r#"rb'\\\'"#, r#"r"""a\""""#] {
+            let e = Expression::from_line(unclosed).unwrap_err();
+            assert_eq!(e.error, PyError::SyntaxError);
+            assert!(e.msg.starts_with("unterminated string literal"), "{unclosed}: {}", e.msg);
+        }
+    }
+
+    #[test]
+    fn mixed_bytes_literals() {
+        let err = Expression::from_line(r#""a" b"b""#).unwrap_err().to_string();
+        assert!(err.starts_with("SyntaxError: "), "{err}");
+        assert!(err.contains("cannot mix bytes and nonbytes literals"), "{err}");
+    }
+
+    #[test]
+    fn truncated_hex_escape() {
+        let err = Expression::from_line(r#""\x4""#).unwrap_err().to_string();
+        assert!(err.starts_with("SyntaxError: "), "{err}");
+        assert!(err.contains("truncated \\xXX escape"), "{err}");
+    }
+
+    #[test]
+    fn triple_quoted_strings() {
+        let vm = run_source(
+            "def f():\n\
+             \t\"\"\"Adds one.\n\
+             \n\
+             # not a comment\n\
+             \t\"\"\"\n\
+             \treturn 1\n\
+             class C:\n\
+             \t'''A class.'''\n\
+             \tpass\n\
+             class D(C):\n\
+             \tpass\n\
+             a = f.__doc__\n\
+             b = C.__doc__\n\
+             c = D.__doc__\n\
+             d = '''one\n\
+             two # three''' + \"#\" # a comment\n\
+             e = \"5\" + '1'\n\
+             g = [\"a'b\\n\", b'\\x00\"']"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], "Adds one.\n\n# not a comment\n\t".to_arc());
+        assert_eq!(vars["b"], "A class.".to_arc());
+        assert_eq!(vars["c"], Obj::None.into());
+        assert_eq!(vars["d"], "one\ntwo # three#".to_arc());
+        // quoted digits stay a str
+        assert_eq!(vars["e"], Obj::Str("51".into()).into());
+        assert_eq!(vars["g"].to_string(), r#"["a'b\n", b'\x00"']"#);
+    }
+
+    #[test]
+    fn format_spec() {
+        use crate::pyrs_format::format_obj;
+        // checked against CPython's format()
+        let cases = [
+            (Obj::Int(1234.into()), "0=10,", "00,001,234"),
+            (Obj::Int(1234.into()), "010,", "00,001,234"),
+            (Obj::Float(-1234.5), "012,.1f", "-0,001,234.5"),
+            (Obj::Int(1234.into()), "x<10,", "1,234xxxxx"),
+            (Obj::Str("ab".into()), "05", "ab000"),
+            (Obj::Bool(true), "5", "    1"),
+            (Obj::Int(255.into()), "#010_x", "0x000_00ff"),
+            (Obj::Float(1.5), "10", "       1.5"),
+            (Obj::Float(1e+16), ".3", "1e+16"),
+            (Obj::Float(1e-05), ".3", "1e-05"),
+            (Obj::Float(123.0), ".3", "1.23e+02"),
+            (Obj::Float(1.0), ".0", "1e+00"),
+            (Obj::Float(12345.678), "g", "12345.7"),
+            (Obj::Float(-0.0), "z.1f", "0.0"),
+            (Obj::Float(f64::INFINITY), "010.2f", "0000000inf"),
+            (Obj::Float(2.5), ".0f", "2"),
+            (Obj::Float(0.5), "%", "50.000000%"),
+            (Obj::Float(3.0), "#.0f", "3."),
+            (Obj::Float(100000.0), ".2e", "1.00e+05"),
+            (Obj::Int(123.into()), "e", "1.230000e+02"),
+            (Obj::Int((-7).into()), "^+9", "   -7    "),
+            (Obj::Int(42.into()), "c", "*"),
+            (Obj::Int("1000000000000000000000000000000".parse().unwrap()), ",", "1,000,000,000,000,000,000,000,000,000,000"),
+            (Obj::Int("-100000000000000000000".parse().unwrap()), "_x", "-5_6bc7_5e2d_6310_0000"),
+            (Obj::Float(1.23456), "+.3f", "+1.235"),
+            (Obj::Float(-2.5), " 8.2f", "   -2.50"),
+            (Obj::Float(0.0001234), "g", "0.0001234"),
+            (Obj::Float(123456789.0), "g", "1.23457e+08"),
+            (Obj::Float(1.5e-05), ".2g", "1.5e-05"),
+            (Obj::Float(100.0), "g", "100"),
+            (Obj::Float(100.0), "#g", "100.000"),
+            (Obj::Float(0.1), ".17g", "0.10000000000000001"),
+            (Obj::Str("hello".into()), "^11", "   hello   "),
+            (Obj::Str("hi".into()), "=^6", "==hi=="),
+            (Obj::Float(12.0), ".3", "12.0"),
+            (Obj::Float(99.96), ".3", "1e+02"),
+            (Obj::Int(255.into()), "#o", "0o377"),
+            (Obj::Float(-0.0), "f", "-0.000000"),
+            (Obj::Float(f64::NAN), "+f", "+nan"),
+            (Obj::Float(f64::NEG_INFINITY), "E", "-INF"),
+            (Obj::Float(1234.5678), ",.2f", "1,234.57"),
+            (Obj::Float(1234.5678), "_.2f", "1_234.57"),
+            (Obj::Int((-7).into()), "03", "-07"),
+            (Obj::Int(12.into()), "<05", "12000"),
+            (Obj::Int("18446744073709551616".parse().unwrap()), "#X", "0X10000000000000000"),
+            (Obj::Float(0.000123456), ".3e", "1.235e-04"),
+            (Obj::Float(9.999), ".2f", "10.00"),
+        ];
+        for (obj, spec, expected) in cases {
+            assert_eq!(format_obj(&obj, spec).unwrap(), expected, "format({obj:?}, {spec:?})");
+        }
+        let err = |obj: Obj, spec: &str| format_obj(&obj, spec).unwrap_err().msg;
+        assert_eq!(err(Obj::Int(1.into()), ".2d"), "Precision not allowed in integer format specifier");
+        assert_eq!(err(Obj::Str("s".into()), "+"), "Sign not allowed in string format specifier");
+        assert_eq!(err(Obj::Float(1.0), "d"), "Unknown format code 'd' for object of type 'float'");
+        assert_eq!(err(Obj::Int(1.into()), "5,x"), "Cannot specify ',' with 'x'.");
+        assert_eq!(err(Obj::Int(1.into()), "10ab"), "Invalid format specifier '10ab' for object of type 'int'");
+        assert_eq!(err(Obj::Int(1.into()), "99999999999999999999"), "Too many decimal digits in format string");
+        assert_eq!(err(Obj::Float(1.0), ".9223372036854775808"), "Too many decimal digits in format string");
+    }
+
+    #[test]
+    fn parse_fstrings() {
+        assert_eq!(Expression::from_line(r#"f"a{x}b""#).unwrap().to_string(), "Op[fstring Str(a) Op[format Ident(x) None None] Str(b)]");
+        assert_eq!(
+            Expression::from_line(r#"f"{x!r:>{w}.2f}""#).unwrap().to_string(),
+            "Op[fstring Op[format Ident(x) Str(r) Op[fstring Str(>) Op[format Ident(w) None None] Str(.2f)]]]"
+        );
+        assert_eq!(Expression::from_line(r#"f"{x = }""#).unwrap().to_string(), "Op[fstring Str(x = ) Op[format Ident(x) Str(r) None]]");
+        assert_eq!(Expression::from_line(r#"f"{{}}" 'x' f"\N{BULLET}""#).unwrap(), Expression::Str("{}x•".into()));
+        assert_eq!(Expression::from_line(r#"f"{a != b}""#).unwrap().to_string(), "Op[fstring Op[format Op[!= Ident(a) Ident(b)] None None]]");
+    }
+
+    #[test]
+    fn fstrings() {
+        let vm = run_source(
+            "class P:\n\
+             \tdef __init__(self, n):\n\
+             \t\tself.n = n\n\
+             \tdef __repr__(self):\n\
+             \t\treturn f\"P({self.n!r})\"\n\
+             x = 3.14159\n\
+             width = 10\n\
+             name = \"Bob\"\n\
+             p = P(\"é\")\n\
+             a = f\"{x:>{width}.2f}|{name!r:^9}|{width * 2:05d}\"\n\
+             b = f\"{x=}, {width = :#x}, {p=}\"\n\
+             c = f'{p} {p!s} {p!a} {[1, name]}'\n\
+             d = rf\"\\d{{{name}}}\" f\"{1234567:,}\""
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], "      3.14|  'Bob'  |00020".to_arc());
+        assert_eq!(vars["b"], "x=3.14159, width = 0xa, p=P('é')".to_arc());
+        assert_eq!(vars["c"], "P('é') P('é') P('\\xe9') [1, 'Bob']".to_arc());
+        assert_eq!(vars["d"], "\\d{Bob}1,234,567".to_arc());
+    }
+
+    #[test]
+    fn fstring_single_brace() {
+        let err = Expression::from_line(r#"f"a}""#).unwrap_err().to_string();
+        assert!(err.starts_with("SyntaxError: "), "{err}");
+        assert!(err.contains("single '}' is not allowed"), "{err}");
+    }
+
+    #[test]
+    fn parse_percent() {
+        assert_eq!(Expression::from_line("a % b * c").unwrap().to_string(), "Op[* Op[% Ident(a) Ident(b)] Ident(c)]");
+        assert_eq!(Expression::from_line("x = -7 % 3").unwrap().to_string(), "Op[= Ident(x) Op[% Op[- Atom(7)] Atom(3)]]");
+        assert_eq!(Expression::from_line("x %= 2").unwrap().to_string(), "Op[%= Ident(x) Atom(2)]");
+    }
+
+    #[test]
+    fn percent_formatting() {
+        let vm = run_source(
+            "a = \"%s %5.2f %-4d|%x\" % (\"hi\", 3.14159, 42, 255)\n\
+             b = \"%(name)s is %(age)03d\" % {\"name\": \"Bob\", \"age\": 7}\n\
+             c = \"%c%c %r %5s|%-5s|\" % (72, \"i\", \"q\", \"ab\", \"cd\")\n\
+             d = \"%#x %#o %+d % d %.3d %e %g %%\" % (255, 8, 5, 5, 7, 12345.678, 0.00001)\n\
+             e = \"%*d|%-*d|%.*f\" % (5, 1, 5, 2, 2, 3.14159)\n\
+             f = \"%s\" % [1, 2]\n\
+             g = \"%d%%\"\n\
+             g %= 50\n\
+             h = -7 % 3\n\
+             i = 7.5 % -2"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], "hi  3.14 42  |ff".to_arc());
+        assert_eq!(vars["b"], "Bob is 007".to_arc());
+        assert_eq!(vars["c"], "Hi 'q'    ab|cd   |".to_arc());
+        assert_eq!(vars["d"], "0xff 0o10 +5  5 007 1.234568e+04 1e-05 %".to_arc());
+        assert_eq!(vars["e"], "    1|2    |3.14".to_arc());
+        assert_eq!(vars["f"], "[1, 2]".to_arc());
+        assert_eq!(vars["g"], Obj::Str("50%".into()).into());
+        assert_eq!(vars["h"], Obj::Int(2.into()).into());
+        assert_eq!(vars["i"], Obj::Float(-0.5).into());
+    }
+
+    #[test]
+    fn percent_format_errors() {
+        use crate::pyrs_format::{parse_percent, percent_number, FormatSpec};
+        let err = |template: &str| parse_percent(template).unwrap_err().msg;
+        assert_eq!(err("%q"), "unsupported format character 'q' (0x71) at index 1");
+        assert_eq!(err("ab %-5"), "incomplete format");
+        assert_eq!(err("%(key"), "incomplete format key");
+        assert_eq!(err("%99999999999999999999d"), "width too big");
+        assert_eq!(err("%.99999999999999999999f"), "precision too big");
+        let spec = |kind| FormatSpec { kind: Some(kind), ..Default::default() };
+        let err = |obj: Obj, kind| percent_number(&obj, &spec(kind)).unwrap_err().msg;
+        assert_eq!(err(Obj::Str("1".into()), 'd'), "%d format: a real number is required, not str");
+        assert_eq!(err(Obj::Float(1.5), 'x'), "%x format: an integer is required, not float");
+        assert_eq!(err(Obj::Str("ab".into()), 'c'), "%c requires an int or a unicode character, not a string of length 2");
+        assert_eq!(percent_number(&Obj::Float(-3.9), &spec('d')).unwrap(), "-3");
+    }
+
+    #[test]
+    fn str_format() {
+        let vm = run_source(
+            "class P:\n\
+             \tdef __init__(self, x):\n\
+             \t\tself.x = x\n\
+             \tdef __format__(self, spec):\n\
+             \t\treturn \"P<\" + spec + \">\"\n\
+             a = \"{} {name:>5} {}\".format(1, 2, name=\"x\")\n\
+             b = \"{0}{1}{0} {{}}\".format(\"ab\", \"cd\")\n\
+             c = \"{0:abc} {p.x} {1!r:>5}\".format(P(1), \"q\", p=P(5))\n\
+             d = \"{:{w}.{p}f}|{t[1]}|{m[k]}\".format(3.14159, w=8, p=2, t=(1, 2), m={\"k\": \"v\"})\n\
+             e = f\"{P(2):xyz}\"\n\
+             f = format(3.5, \"08.3f\")\n\
+             g = format(P(3), \"q\")\n\
+             h = format(\"s\")"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], "1     x 2".to_arc());
+        assert_eq!(vars["b"], "abcdab {}".to_arc());
+        assert_eq!(vars["c"], "P<abc> 5   'q'".to_arc());
+        assert_eq!(vars["d"], "    3.14|2|v".to_arc());
+        assert_eq!(vars["e"], "P<xyz>".to_arc());
+        assert_eq!(vars["f"], Obj::Str("0003.500".into()).into());
+        assert_eq!(vars["g"], "P<q>".to_arc());
+        assert_eq!(vars["h"], "s".to_arc());
+    }
+
+    #[test]
+    fn str_format_errors() {
+        use crate::pyrs_format::{parse_template, split_field_name, FieldNumbering};
+        let err = |template: &str| parse_template(template).unwrap_err().msg;
+        assert_eq!(err("a}b"), "Single '}' encountered in format string");
+        assert_eq!(err("a{"), "Single '{' encountered in format string");
+        assert_eq!(err("{0:x"), "expected '}' before end of string");
+        assert_eq!(err("{0!x}"), "Unknown conversion specifier x");
+        assert_eq!(err("{0!rx}"), "expected ':' after conversion specifier");
+        assert_eq!(split_field_name("a[0").unwrap_err().msg, "Missing ']' in format string");
+        assert_eq!(split_field_name("a.").unwrap_err().msg, "Empty attribute in format string");
+
+        let mut numbering = FieldNumbering::default();
+        assert!(numbering.resolve("").is_ok());
+        assert_eq!(
+            numbering.resolve("0").unwrap_err().msg,
+            "cannot switch from automatic field numbering to manual field specification"
+        );
+        let mut numbering = FieldNumbering::default();
+        assert!(numbering.resolve("1").is_ok());
+        assert!(numbering.resolve("key").is_ok());
+        assert_eq!(
+            numbering.resolve("").unwrap_err().msg,
+            "cannot switch from manual field specification to automatic field numbering"
+        );
+    }
+
+    #[test]
+    fn split_number_literals() {
+        assert_eq!(split_to_words("x=0x_fF+.5-1.e5j"), vec!["x", "=", "0x_fF", "+", ".5", "-", "1.e5j"]);
+        assert_eq!(split_to_words("f(1_000, 2e-3, 3.)"), vec!["f", "(", "1_000", ",", "2e-3", ",", "3.", ")"]);
+        assert_eq!(split_to_words("a.b + 1e"), vec!["a", ".", "b", "+", "1e"]);
+        assert_eq!(split_to_words("1e+)"), vec!["1e+", ")"]);
+        assert_eq!(split_to_words("1else"), vec!["1", "else"]);
+    }
+
+    #[test]
+    fn number_literals() {
+        let num = |s: &str| Obj::from_number_literal(s).unwrap();
+        assert_eq!(num("0xff"), Obj::Int(255.into()));
+        assert_eq!(num("0o17"), Obj::Int(15.into()));
+        assert_eq!(num("0B1_01"), Obj::Int(5.into()));
+        assert_eq!(num("1_000_000"), Obj::Int(1_000_000.into()));
+        assert_eq!(num("0x1_0000_0000_0000_0000"), Obj::Int("18446744073709551616".parse().unwrap()));
+        assert_eq!(num("1e-9"), Obj::Float(1e-9));
+        assert_eq!(num(".5"), Obj::Float(0.5));
+        assert_eq!(num("1."), Obj::Float(1.0));
+        assert_eq!(num("000"), Obj::Int(0.into()));
+        assert_eq!(num("3j").to_string(), "3j");
+        assert_eq!(num("1.5e3J").to_string(), "1500j");
+        assert_eq!(num("0j"), Obj::Int(0.into()));
+
+        let err = |s: &str| Obj::from_number_literal(s).unwrap_err();
+        assert_eq!(err("0b102"), "invalid digit '2' in binary literal");
+        assert_eq!(err("0o8"), "invalid digit '8' in octal literal");
+        assert_eq!(err("0xg"), "invalid hexadecimal literal");
+        assert_eq!(err("0x"), "invalid hexadecimal literal");
+        assert_eq!(err("1__0"), "invalid decimal literal");
+        assert_eq!(err("1_"), "invalid decimal literal");
+        assert_eq!(err("1_.5"), "invalid decimal literal");
+        assert_eq!(err("1e"), "invalid decimal literal");
+        assert_eq!(err("1.5E+"), "invalid decimal literal");
+        assert_eq!(
+            err("012"),
+            "leading zeros in decimal integer literals are not permitted; use an 0o prefix for octal integers"
+        );
+    }
+
+    #[test]
+    fn bad_binary_literal() {
+        let err = Expression::from_line("x = 0b12").unwrap_err().to_string();
+        assert!(err.starts_with("SyntaxError: "), "{err}");
+        assert!(err.contains("SyntaxError: invalid digit '2' in binary literal"), "{err}");
+    }
+
+    #[test]
+    fn exponent_without_digits() {
+        let err = Expression::from_line("x = 1e + 2").unwrap_err().to_string();
+        assert!(err.starts_with("SyntaxError: "), "{err}");
+        assert!(err.contains("SyntaxError: invalid decimal literal"), "{err}");
+    }
+
+    #[test]
+    fn float_builtin() {
+        let vm = run_source(
+            "a = 0xff + 0o17 + 0b101 + 1_000\n\
+             b = .5 + 1e-1\n\
+             c = float(\"inf\")\n\
+             d = float(\" -1_0.5e1 \")\n\
+             e = float(\"-Infinity\")\n\
+             f = float(7)\n\
+             g = float()\n\
+             h = float(\"nan\")"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], Obj::Int(1275.into()).into());
+        assert_eq!(vars["b"], Obj::Float(0.6).into());
+        assert_eq!(vars["c"], Obj::Float(f64::INFINITY).into());
+        assert_eq!(vars["d"], Obj::Float(-105.0).into());
+        assert_eq!(vars["e"], Obj::Float(f64::NEG_INFINITY).into());
+        assert_eq!(vars["f"], Obj::Float(7.0).into());
+        assert_eq!(vars["g"], Obj::Float(0.0).into());
+        assert!(matches!(vars["h"].as_ref(), Obj::Float(f) if f.is_nan()));
+
+        let err = |s: &str| Funcs::float(&Obj::Str(s.into())).unwrap_err().msg;
+        assert_eq!(err("1_"), "could not convert string to float: '1_'");
+        assert_eq!(err("0x10"), "could not convert string to float: '0x10'");
+        assert_eq!(err("infx"), "could not convert string to float: 'infx'");
+    }
+
+    #[test]
+    fn complex_numbers() {
+        let vm = run_source(
+            "z = 3 + 4j\n\
+             a = abs(z)\n\
+             b = z * (1 - 2j)\n\
+             c = z / 2\n\
+             d = z.conjugate()\n\
+             e = z.real + z.imag\n\
+             f = 2.5 - z\n\
+             g = -z\n\
+             h = 1j == complex(0, 1)\n\
+             i = complex(\"-1.5+2e1j\")\n\
+             k = complex(\" (3j) \")\n\
+             l = complex()\n\
+             m = complex(1j, 1j)\n\
+             n = [hash(1 + 0j) == hash(1), hash(2.5 + 0j) == hash(2.5), hash(1 + 2j)]\n\
+             o = {1: 'a'}[1 + 0j]"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], Obj::Float(5.0).into());
+        assert_eq!(vars["b"].to_string(), "(11-2j)");
+        assert_eq!(vars["c"].to_string(), "(1.5+2j)");
+        assert_eq!(vars["d"].to_string(), "(3-4j)");
+        assert_eq!(vars["e"], Obj::Float(7.0).into());
+        assert_eq!(vars["f"].to_string(), "(-0.5-4j)");
+        assert_eq!(vars["g"].to_string(), "(-3-4j)");
+        assert_eq!(vars["h"], Obj::Bool(true).into());
+        assert_eq!(vars["i"].to_string(), "(-1.5+20j)");
+        assert_eq!(vars["k"].to_string(), "3j");
+        assert_eq!(vars["l"].to_string(), "0j");
+        assert_eq!(vars["m"].to_string(), "(-1+1j)");
+        assert_eq!(vars["n"].__repr__(), "[True, True, 2000007]");
+        assert_eq!(vars["o"].__repr__(), "'a'");
+
+        let err = |s: &str| Funcs::complex(&[Obj::Str(s.into()).into()]).unwrap_err().msg;
+        assert_eq!(err("1 + 2j"), "complex() arg is a malformed string");
+        assert_eq!(err("1+2"), "complex() arg is a malformed string");
+        let err = Funcs::complex(&[Obj::None.into()]).unwrap_err().msg;
+        assert_eq!(err, "complex() first argument must be a string or a number, not 'NoneType'");
+    }
+
+    #[test]
+    #[cfg(feature = "cmath")]
+    fn cmath_module() {
+        let vm = run_source(
+            "import cmath\n\
+             a = cmath.sqrt(-4)\n\
+             b = cmath.polar(1j)\n\
+             c = cmath.rect(2, 0)\n\
+             d = cmath.phase(-1)\n\
+             e = cmath.exp(0)\n\
+             f = cmath.polar(3 + 4j)"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"].to_string(), "2j");
+        assert_eq!(vars["b"], Obj::Tuple(vec![Obj::Float(1.0).into(), Obj::Float(std::f64::consts::FRAC_PI_2).into()]).into());
+        assert_eq!(vars["c"].to_string(), "(2+0j)");
+        assert_eq!(vars["d"], Obj::Float(std::f64::consts::PI).into());
+        assert_eq!(vars["e"].to_string(), "(1+0j)");
+        assert_eq!(vars["f"], Obj::Tuple(vec![Obj::Float(5.0).into(), Obj::Float(4f64.atan2(3.0)).into()]).into());
+
+        let module = crate::pyrs_cmath::module().to_module();
+        let Some(Obj::Function(sqrt)) = module.get_attr("sqrt").as_deref().cloned() else {
+            panic!("cmath.sqrt isn't a native function");
+        };
+        let err = (sqrt.ptr)(&mut PyVM::new(), &[Obj::Int(1.into()).into(), Obj::Int(2.into()).into()], &[]).unwrap_err();
+        assert_eq!(err.msg, "cmath.sqrt() takes exactly one argument (2 given)");
+    }
+
+    #[test]
+    #[cfg(feature = "math")]
+    fn math_module() {
+        let vm = run_source(
+            "import math\n\
+             from math import pi, isclose\n\
+             a = (math.floor(-2.5), math.ceil(2.1), math.trunc(-3.7))\n\
+             b = math.factorial(25)\n\
+             c = (math.gcd(12, 18, -8), math.lcm(4, 6, 10))\n\
+             d = (math.comb(100, 50), math.perm(10, 3), math.isqrt(10**40))\n\
+             e = (math.log(8, 2), math.log2(2**2000), math.log10(10**400))\n\
+             f = (math.hypot(1, 2, 3), math.dist((0, 0), (3, 4)))\n\
+             g = (math.fsum([0.1, 0.1, 0.1, 0.1, 0.1, 0.1, 0.1, 0.1, 0.1, 0.1]), math.fsum([1e100, 1.0, -1e100]))\n\
+             h = (isclose(1.0, 1.0000000001), isclose(1e-12, 0, abs_tol=1e-9), math.isclose(1, 1.1))\n\
+             i = (math.degrees(pi), math.tau, math.remainder(5, 3), math.modf(-2.5))\n\
+             j = (math.floor(10**30), math.sqrt)\n\
+             k = (math.prod([1, 2, 3, 4]), math.prod([]), math.prod([2, 0.5], start=3), math.prod(range(1, 30)))\n\
+             l = (math.frexp(8.0), math.frexp(-0.1), math.frexp(0.0), math.frexp(5e-324))\n\
+             m = (math.ldexp(0.5, 4), math.ldexp(1.0, -1074), math.ldexp(1.0, -1075), math.ldexp(3.0, -1075), math.ldexp(1.0, -10**100))\n\
+             n = (math.gamma(5), math.gamma(1.5), math.gamma(-0.5), math.lgamma(2), math.lgamma(100))\n\
+             o = (math.erf(0), math.erf(0.5), math.erfc(0.5), math.erfc(30))\n\
+             p = (math.cbrt(-8), math.cbrt(2), math.exp2(10), math.exp2(0.5))\n\
+             q = (math.nextafter(1.0, 2.0), math.nextafter(0.0, -1.0), math.nextafter(1.7976931348623157e308, math.inf))\n\
+             r = (math.ulp(1.0), math.ulp(-2.5), math.ulp(0.0), math.ulp(1.7976931348623157e308), math.ulp(math.inf))"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"].__repr__(), "(-3, 3, -3)");
+        assert_eq!(vars["b"].__repr__(), "15511210043330985984000000");
+        assert_eq!(vars["c"].__repr__(), "(2, 60)");
+        assert_eq!(vars["d"].__repr__(), "(100891344545564193334812497256, 720, 100000000000000000000)");
+        assert_eq!(vars["e"].__repr__(), "(3.0, 2000.0, 400.0)");
+        assert_eq!(vars["f"].__repr__(), "(3.7416573867739413, 5.0)");
+        assert_eq!(vars["g"].__repr__(), "(1.0, 1.0)");
+        assert_eq!(vars["h"].__repr__(), "(True, True, False)");
+        assert_eq!(vars["i"].__repr__(), "(180.0, 6.283185307179586, -1.0, (-0.5, -2.0))");
+        assert_eq!(vars["j"].__repr__(), "(1000000000000000000000000000000, <built-in function sqrt>)");
+        assert_eq!(vars["k"].__repr__(), "(24, 1, 3.0, 8841761993739701954543616000000)");
+        assert_eq!(vars["l"].__repr__(), "((0.5, 4), (-0.8, -3), (0.0, 0), (0.5, -1073))");
+        assert_eq!(vars["m"].__repr__(), "(8.0, 5e-324, 0.0, 1e-323, 0.0)");
+        assert_eq!(vars["n"].__repr__(), "(24.0, 0.886226925452758, -3.544907701811032, 0.0, 359.1342053695754)");
+        assert_eq!(vars["o"].__repr__(), "(0.0, 0.5204998778130465, 0.4795001221869535, 0.0)");
+        assert_eq!(vars["p"].__repr__(), "(-2.0, 1.2599210498948732, 1024.0, 1.4142135623730951)");
+        assert_eq!(vars["q"].__repr__(), "(1.0000000000000002, -5e-324, inf)");
+        assert_eq!(vars["r"].__repr__(), "(2.220446049250313e-16, 4.440892098500626e-16, 5e-324, 1.99584030953472e+292, inf)");
+    }
+
+    #[test]
+    #[cfg(feature = "math")]
+    fn math_errors() {
+        let mut vm = PyVM::new();
+        let module = math::module().to_module();
+        let mut call = |name: &str, args: Vec<Obj>, kwargs: Vec<(&str, Obj)>| {
+            let args: Vec<_> = args.into_iter().map(Arc::new).collect();
+            let kwargs: Vec<_> = kwargs.into_iter().map(|(k, v)| (k.to_string(), Arc::new(v))).collect();
+            let Some(Obj::Function(func)) = module.get_attr(name).as_deref().cloned() else {
+                panic!("math.{name} isn't a native function");
+            };
+            let err = (func.ptr)(&mut vm, &args, &kwargs).unwrap_err();
+            format!("{:?}: {}", err.error, err.msg)
+        };
+        let int = |i: i64| Obj::Int(i.into());
+        assert_eq!(call("sqrt", vec![int(-1)], vec![]), "ValueError: math domain error");
+        assert_eq!(call("log", vec![int(0)], vec![]), "ValueError: math domain error");
+        assert_eq!(call("acos", vec![int(2)], vec![]), "ValueError: math domain error");
+        assert_eq!(call("exp", vec![int(1000)], vec![]), "OverflowError: math range error");
+        assert_eq!(call("pow", vec![int(0), int(-1)], vec![]), "ValueError: math domain error");
+        assert_eq!(call("log", vec![int(2), int(1)], vec![]), "ZeroDivisionError: float division by zero");
+        assert_eq!(
+            call("sin", vec![Obj::Int(Integer::from(10).pow(400))], vec![]),
+            "OverflowError: int too large to convert to float"
+        );
+        assert_eq!(call("sin", vec![Obj::Str("a".to_string())], vec![]), "TypeError: must be real number, not str");
+        assert_eq!(call("sin", vec![], vec![]), "TypeError: math.sin() takes exactly one argument (0 given)");
+        assert_eq!(call("sin", vec![], vec![("x", int(1))]), "TypeError: math.sin() takes no keyword arguments");
+        assert_eq!(call("atan2", vec![int(1)], vec![]), "TypeError: atan2 expected 2 arguments, got 1");
+        assert_eq!(
+            call("floor", vec![Obj::Float(f64::INFINITY)], vec![]),
+            "OverflowError: cannot convert float infinity to integer"
+        );
+        assert_eq!(call("floor", vec![Obj::Float(f64::NAN)], vec![]), "ValueError: cannot convert float NaN to integer");
+        assert_eq!(
+            call("factorial", vec![Obj::Float(2.0)], vec![]),
+            "TypeError: 'float' object cannot be interpreted as an integer"
+        );
+        assert_eq!(call("factorial", vec![int(-1)], vec![]), "ValueError: factorial() not defined for negative values");
+        assert_eq!(call("comb", vec![int(-1), int(2)], vec![]), "ValueError: n must be a non-negative integer");
+        assert_eq!(call("isqrt", vec![int(-1)], vec![]), "ValueError: isqrt() argument must be nonnegative");
+        assert_eq!(call("gamma", vec![int(0)], vec![]), "ValueError: math domain error");
+        assert_eq!(call("gamma", vec![int(-2)], vec![]), "ValueError: math domain error");
+        assert_eq!(call("gamma", vec![int(172)], vec![]), "OverflowError: math range error");
+        assert_eq!(call("lgamma", vec![int(-1)], vec![]), "ValueError: math domain error");
+        assert_eq!(call("ldexp", vec![Obj::Float(1.0), int(1024)], vec![]), "OverflowError: math range error");
+        assert_eq!(
+            call("ldexp", vec![Obj::Float(1.0), Obj::Float(1.5)], vec![]),
+            "TypeError: Expected an int as second argument to ldexp."
+        );
+        assert_eq!(call("exp2", vec![int(2000)], vec![]), "OverflowError: math range error");
+        assert_eq!(call("prod", vec![], vec![]), "TypeError: prod() takes exactly 1 positional argument (0 given)");
+        assert_eq!(call("erf", vec![Obj::Str("a".to_string())], vec![]), "TypeError: must be real number, not str");
+        assert_eq!(call("nextafter", vec![int(1)], vec![]), "TypeError: nextafter expected 2 arguments, got 1");
+        assert_eq!(
+            call("isclose", vec![int(1), int(1)], vec![("rel_tol", int(-1))]),
+            "ValueError: tolerances must be non-negative"
+        );
+        assert_eq!(
+            call("isclose", vec![int(1), int(2), int(3)], vec![]),
+            "TypeError: isclose() takes exactly 2 positional arguments (3 given)"
+        );
+        let list = |items: Vec<Obj>| Obj::new_list(items.into_iter().map(Arc::new).collect());
+        assert_eq!(
+            call("dist", vec![list(vec![int(1)]), list(vec![int(1), int(2)])], vec![]),
+            "ValueError: both points must have the same number of dimensions"
+        );
+        assert_eq!(
+            call("fsum", vec![list(vec![Obj::Float(f64::INFINITY), Obj::Float(f64::NEG_INFINITY)])], vec![]),
+            "ValueError: -inf + inf in fsum"
+        );
+        assert_eq!(call("fsum", vec![int(1)], vec![]), "TypeError: 'int' object is not iterable");
+    }
+
+    #[test]
+    fn floor_division_and_modulo() {
+        let vm = run_source(
+            "a = -7 // 2\n\
+             b = 7 // -2\n\
+             c = -7.5 // 2\n\
+             d = 7.5 % -2\n\
+             e = divmod(-7, 2)\n\
+             f = divmod(7.5, -2)\n\
+             g = 2**100 // 3\n\
+             h = 17\n\
+             h //= 5"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], Obj::Int((-4).into()).into());
+        assert_eq!(vars["b"], Obj::Int((-4).into()).into());
+        assert_eq!(vars["c"], Obj::Float(-4.0).into());
+        assert_eq!(vars["d"], Obj::Float(-0.5).into());
+        assert_eq!(vars["e"], Obj::Tuple(vec![Obj::Int((-4).into()).into(), Obj::Int(1.into()).into()]).into());
+        assert_eq!(vars["f"], Obj::Tuple(vec![Obj::Float(-4.0).into(), Obj::Float(-0.5).into()]).into());
+        assert_eq!(vars["g"].to_string(), "422550200076076467165567735125");
+        assert_eq!(vars["h"], Obj::Int(3.into()).into());
+
+        let err = |a: Obj, b: Obj, op| Obj::divmod(&a, &b, op).unwrap_err().msg;
+        assert_eq!(err(Obj::Int(7.into()), Obj::Int(0.into()), "//"), "integer division or modulo by zero");
+        assert_eq!(err(Obj::Int(7.into()), Obj::Int(0.into()), "%"), "integer modulo by zero");
+        assert_eq!(err(Obj::Float(7.0), Obj::Int(0.into()), "//"), "float floor division by zero");
+        assert_eq!(
+            err(Obj::Complex(Box::new(Complex::with_val(53, (0.0, 1.0)))), Obj::Int(2.into()), "//"),
+            "unsupported operand type(s) for //: 'complex' and 'int'"
+        );
+    }
+
+    #[test]
+    fn power() {
+        let vm = run_source(
+            "a = 2 ** 100\n\
+             b = 2 ** -2\n\
+             c = -2 ** 2\n\
+             d = 2 ** 3 ** 2\n\
+             e = (1 + 2j) ** 3\n\
+             f = (1 + 2j) ** -2\n\
+             g = 4 ** 0.5\n\
+             h = 3\n\
+             h **= 3"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"].to_string(), "1267650600228229401496703205376");
+        assert_eq!(vars["b"], Obj::Float(0.25).into());
+        assert_eq!(vars["c"], Obj::Int((-4).into()).into());
+        assert_eq!(vars["d"], Obj::Int(512.into()).into());
+        assert_eq!(vars["e"].to_string(), "(-11-2j)");
+        assert_eq!(vars["f"].to_string(), "(-0.12-0.16j)");
+        assert_eq!(vars["g"], Obj::Float(2.0).into());
+        assert_eq!(vars["h"], Obj::Int(27.into()).into());
+
+        // a negative base to a fractional power is complex
+        let root = Obj::pow(&Obj::Int((-8).into()), &Obj::Float(1.0 / 3.0)).unwrap();
+        assert_eq!(root.to_string(), "(1.0000000000000002+1.7320508075688772j)");
+
+        let err = |a: Obj, b: Obj| Obj::pow(&a, &b).unwrap_err();
+        assert_eq!(err(Obj::Int(0.into()), Obj::Int((-1).into())).msg, "0.0 cannot be raised to a negative power");
+        assert_eq!(err(Obj::Float(10.0), Obj::Int(400.into())).error, PyError::OverflowError);
+        assert_eq!(
+            err(Obj::Str("a".into()), Obj::Int(2.into())).msg,
+            "unsupported operand type(s) for ** or pow(): 'str' and 'int'"
+        );
+    }
+
+    #[test]
+    fn pow_with_modulus() {
+        let int = |i: i64| -> Arc<Obj> { Obj::Int(i.into()).into() };
+        assert_eq!(Funcs::pow(&[int(3), int(4), int(5)]).unwrap(), Obj::Int(1.into()));
+        assert_eq!(Funcs::pow(&[int(3), int(-1), int(7)]).unwrap(), Obj::Int(5.into()));
+        assert_eq!(Funcs::pow(&[int(3), int(2), int(-5)]).unwrap(), Obj::Int((-1).into()));
+        assert_eq!(Funcs::pow(&[int(2), int(10), Obj::None.into()]).unwrap(), Obj::Int(1024.into()));
+
+        let err = |args: &[Arc<Obj>]| Funcs::pow(args).unwrap_err().msg;
+        assert_eq!(err(&[int(2), int(-1), int(4)]), "base is not invertible for the given modulus");
+        assert_eq!(err(&[int(2), int(1), int(0)]), "pow() 3rd argument cannot be 0");
+        assert_eq!(
+            err(&[Obj::Float(2.0).into(), int(2), int(3)]),
+            "pow() 3rd argument not allowed unless all arguments are integers"
+        );
+        assert_eq!(err(&[int(2)]), "pow() missing required argument 'exp' (pos 2)");
+    }
+
+    #[test]
+    fn int_true_division() {
+        let vm = run_source(
+            "a = 10**30 // 3 * 3 / 10**20\n\
+             b = (2**1100 + 1) / 2**1050\n\
+             c = 1 / 3"
+        );
+        let vars = &vm.get_vars()[0];
+        // rounded once, so ints past 2**53 still divide exactly
+        assert_eq!(vars["a"], Obj::Float(1e10).into());
+        assert_eq!(vars["b"], Obj::Float(2f64.powi(50)).into());
+        assert_eq!(vars["c"], Obj::Float(1.0 / 3.0).into());
+
+        let big = Obj::Int(Integer::from(10).pow(400));
+        let err = Obj::div(&big, &Obj::Int(3.into()));
+        assert!(matches!(err, Obj::Except(e) if e.msg == "integer division result too large for a float"));
+        let err = Obj::div(&Obj::Int(1.into()), &Obj::Int(0.into()));
+        assert!(matches!(err, Obj::Except(e) if e.msg == "division by zero"));
+    }
+
+    #[test]
+    fn int_float_mixing() {
+        let vm = run_source(
+            "a = float(2**53 + 3)\n\
+             b = (2**53 + 3) * 1.0\n\
+             c = [2**53 + 1 == 2.0**53, 2**53 == 2.0**53, 2**53 + 1 > 2.0**53, 2.0**53 < 2**53 + 1]\n\
+             d = [(2**60 + 1) // 1.0, (2**53 + 3) % 7.0]\n\
+             e = [10**400 > 1e308, 10**400 < float('inf'), float(2**1024 - 2**971)]"
+        );
+        let vars = &vm.get_vars()[0];
+        // rounded to the nearest float, ties to even
+        assert_eq!(vars["a"], Obj::Float(9007199254740996.0).into());
+        assert_eq!(vars["b"], Obj::Float(9007199254740996.0).into());
+        assert_eq!(vars["c"].__repr__(), "[False, True, True, True]");
+        assert_eq!(vars["d"].__repr__(), "[1.152921504606847e+18, 1.0]");
+        assert_eq!(vars["e"].__repr__(), "[True, True, 1.7976931348623157e+308]");
+
+        let big = Obj::Int(Integer::from(10).pow(400));
+        let err = Obj::arith(&big, &Obj::Float(1.0), Op::Asterisk).unwrap_err();
+        assert_eq!(format!("{:?}: {}", err.error, err.msg), "OverflowError: int too large to convert to float");
+        assert_eq!(Funcs::float(&big).unwrap_err().error, PyError::OverflowError);
+        assert_eq!(Obj::divmod(&big, &Obj::Float(2.5), "//").unwrap_err().error, PyError::OverflowError);
+    }
+
+    #[test]
+    fn int_methods() {
+        let vm = run_source(
+            "a = (255).bit_length()\n\
+             b = (-256).bit_length()\n\
+             c = (7).bit_count()\n\
+             d = (1024).to_bytes(2, 'big')\n\
+             e = (1024).to_bytes(2, byteorder='little')\n\
+             f = (-1).to_bytes(2, 'big', signed=True)\n\
+             g = int.from_bytes(d, 'big')\n\
+             h = int.from_bytes(f, 'little', signed=True)\n\
+             i = int('ff', 16) + int('0x_10', 0) + int(' -12 ') + int(3.9)"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], Obj::Int(8.into()).into());
+        assert_eq!(vars["b"], Obj::Int(9.into()).into());
+        assert_eq!(vars["c"], Obj::Int(3.into()).into());
+        assert_eq!(vars["d"], Obj::Bytes(vec![4, 0]).into());
+        assert_eq!(vars["e"], Obj::Bytes(vec![0, 4]).into());
+        assert_eq!(vars["f"], Obj::Bytes(vec![0xff, 0xff]).into());
+        assert_eq!(vars["g"], Obj::Int(1024.into()).into());
+        assert_eq!(vars["h"], Obj::Int((-1).into()).into());
+        assert_eq!(vars["i"], Obj::Int(262.into()).into());
+
+        let to_bytes = |i: i64, kwargs: &[(String, Arc<Obj>)]| {
+            Funcs::int_method(&i.into(), "to_bytes", &[Obj::Int(1.into()).into()], kwargs).unwrap_err().msg
+        };
+        assert_eq!(to_bytes(256, &[]), "int too big to convert");
+        assert_eq!(to_bytes(-1, &[]), "can't convert negative int to unsigned");
+        assert_eq!(to_bytes(128, &[("signed".into(), Obj::Bool(true).into())]), "int too big to convert");
+        assert_eq!(to_bytes(-129, &[("signed".into(), Obj::Bool(true).into())]), "int too big to convert");
+        // past a u32 of bits, the length is checked without building 2**(8*length)
+        let big = |i: Integer, length: Integer, signed: bool| {
+            let args = [Obj::Int(length).into(), Obj::Str("big".into()).into()];
+            Funcs::int_method(&i, "to_bytes", &args, &[("signed".into(), Obj::Bool(signed).into())])
+        };
+        let huge = Integer::from(1) << 40000u32;
+        assert_eq!(big(huge.clone(), 5000.into(), false).unwrap_err().msg, "int too big to convert");
+        assert_eq!(big(huge.clone(), 5000.into(), true).unwrap_err().msg, "int too big to convert");
+        assert_eq!(big(-huge, 5000.into(), true).unwrap_err().msg, "int too big to convert");
+        let err = big((-1).into(), Integer::from(1) << 61u32, true).unwrap_err();
+        assert_eq!(err.error, PyError::MemoryError);
+        let err = big(1.into(), Integer::from(1) << 70u32, false).unwrap_err();
+        assert_eq!(err.msg, "Python int too large to convert to C ssize_t");
+        assert_eq!(big((-256).into(), 3.into(), true).unwrap(), Obj::Bytes(vec![0xff, 0xff, 0]));
+        assert_eq!(
+            to_bytes(1, &[("byteorder".into(), Obj::Str("mid".into()).into())]),
+            "byteorder must be either 'little' or 'big'"
+        );
+        assert_eq!(
+            to_bytes(1, &[("x".into(), Obj::Int(1.into()).into())]),
+            "'x' is an invalid keyword argument for to_bytes()"
+        );
+        let err = Funcs::int(&[Obj::Str("12a".into()).into()], &[]).unwrap_err().msg;
+        assert_eq!(err, "invalid literal for int() with base 10: '12a'");
+    }
+
+    #[test]
+    fn float_repr() {
+        let repr = |val: f64| Obj::Float(val).to_string();
+        assert_eq!(repr(1.0), "1.0");
+        assert_eq!(repr(-0.0), "-0.0");
+        assert_eq!(repr(0.1), "0.1");
+        assert_eq!(repr(1.0 / 3.0), "0.3333333333333333");
+        assert_eq!(repr(1e15), "1000000000000000.0");
+        assert_eq!(repr(1e16), "1e+16");
+        assert_eq!(repr(123456789012345678.0), "1.2345678901234568e+17");
+        assert_eq!(repr(0.0001), "0.0001");
+        assert_eq!(repr(0.00001), "1e-05");
+        assert_eq!(repr(1.5e-7), "1.5e-07");
+        assert_eq!(repr(2.5e300), "2.5e+300");
+        assert_eq!(repr(5e-324), "5e-324");
+        assert_eq!(repr(f64::INFINITY), "inf");
+        assert_eq!(repr(f64::NEG_INFINITY), "-inf");
+        assert_eq!(repr(f64::NAN), "nan");
+        assert_eq!(Obj::new_list(vec![Obj::Float(2.0).into(), Obj::Float(1e22).into()]).to_string(), "[2.0, 1e+22]");
+    }
+
+    #[test]
+    fn round_builtin() {
+        let vm = run_source(
+            "a = round(2.5)\n\
+             b = round(-0.5)\n\
+             c = round(0.125, 2)\n\
+             d = round(2.675, 2)\n\
+             e = round(150.0, -2)\n\
+             f = round(250, -2)\n\
+             g = round(1350, -2)\n\
+             h = round(-0.4, 0)\n\
+             i = round(7, 2)"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], Obj::Int(2.into()).into());
+        assert_eq!(vars["b"], Obj::Int(0.into()).into());
+        assert_eq!(vars["c"], Obj::Float(0.12).into());
+        // 2.675 is stored a bit under it, so it rounds down
+        assert_eq!(vars["d"], Obj::Float(2.67).into());
+        assert_eq!(vars["e"], Obj::Float(200.0).into());
+        assert_eq!(vars["f"], Obj::Int(200.into()).into());
+        assert_eq!(vars["g"], Obj::Int(1400.into()).into());
+        assert_eq!(vars["h"].to_string(), "-0.0");
+        assert_eq!(vars["i"], Obj::Int(7.into()).into());
+
+        let round = |val: f64, ndigits: i64| Funcs::round(&[Obj::Float(val).into(), Obj::Int(ndigits.into()).into()]);
+        assert_eq!(round(0.5, 400).unwrap(), Obj::Float(0.5));
+        assert_eq!(round(-1.0, -400).unwrap().to_string(), "-0.0");
+        assert_eq!(round(f64::MAX, -308).unwrap_err().msg, "rounded value too large to represent");
+        let err = |val: Obj| Funcs::round(&[val.into()]).unwrap_err().msg;
+        assert_eq!(err(Obj::Float(f64::NAN)), "cannot convert float NaN to integer");
+        assert_eq!(err(Obj::Float(f64::INFINITY)), "cannot convert float infinity to integer");
+        assert_eq!(err(Obj::Str("a".into())), "type str doesn't define __round__ method");
+    }
+
+    #[test]
+    fn float_methods() {
+        let vm = run_source(
+            "a = (1.5).hex()\n\
+             b = (0.1).hex()\n\
+             c = float.fromhex('0x1.8p1')\n\
+             d = float.fromhex(b) == 0.1\n\
+             e = (2.0).is_integer()\n\
+             f = (1.5).is_integer()\n\
+             g = (0.75).as_integer_ratio()\n\
+             h = (-0.0).hex()"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], Obj::Str("0x1.8000000000000p+0".into()).into());
+        assert_eq!(vars["b"], Obj::Str("0x1.999999999999ap-4".into()).into());
+        assert_eq!(vars["c"], Obj::Float(3.0).into());
+        assert_eq!(vars["d"], Obj::Bool(true).into());
+        assert_eq!(vars["e"], Obj::Bool(true).into());
+        assert_eq!(vars["f"], Obj::Bool(false).into());
+        assert_eq!(vars["g"], Obj::Tuple(vec![Obj::Int(3.into()).into(), Obj::Int(4.into()).into()]).into());
+        assert_eq!(vars["h"], Obj::Str("-0x0.0p+0".into()).into());
+
+        let fromhex = |s: &str| Funcs::float_fromhex(&[Obj::Str(s.into()).into()]);
+        assert_eq!(fromhex(" -0X1.FFFFFFFFFFFFFp+1023 ").unwrap(), Obj::Float(-f64::MAX));
+        // halfway between 0 and the smallest subnormal goes to the even 0
+        assert_eq!(fromhex("0x1p-1075").unwrap(), Obj::Float(0.0));
+        assert_eq!(fromhex("0x3p-1076").unwrap(), Obj::Float(5e-324));
+        assert_eq!(fromhex("0x1p1024").unwrap_err().msg, "hexadecimal value too large to represent as a float");
+        assert_eq!(fromhex("0xg").unwrap_err().msg, "invalid hexadecimal floating-point string");
+        let ratio = |val: f64| Funcs::float_method(val, "as_integer_ratio", &[]).unwrap_err().msg;
+        assert_eq!(ratio(f64::NAN), "cannot convert NaN to integer ratio");
+        assert_eq!(ratio(f64::INFINITY), "cannot convert Infinity to integer ratio");
+    }
+
+    #[test]
+    fn fractions_module() {
+        let vm = run_source(
+            "from fractions import Fraction\n\
+             a = Fraction(1, 3) + Fraction(1, 6)\n\
+             b = Fraction(' -1.5e-2 ')\n\
+             c = Fraction(0.1)\n\
+             d = Fraction(-7, 2) // 2\n\
+             e = Fraction(-7, 2) % 2\n\
+             f = Fraction(1, 2) + 0.5\n\
+             g = Fraction(1, 2) ** -2\n\
+             h = 2 ** Fraction(2)\n\
+             i = Fraction(3, 4) == 0.75\n\
+             j = round(Fraction(5, 2))\n\
+             k = round(Fraction(1, 3), 2)\n\
+             l = Fraction(3.141592653589793).limit_denominator(100)\n\
+             m = Fraction(6, 4).numerator\n\
+             n = int(Fraction(-7, 2))"
+        );
+        let vars = &vm.get_vars()[0];
+        let repr = |name: &str| vars[name].__repr__();
+        assert_eq!(repr("a"), "Fraction(1, 2)");
+        assert_eq!(repr("b"), "Fraction(-3, 200)");
+        assert_eq!(repr("c"), "Fraction(3602879701896397, 36028797018963968)");
+        assert_eq!(vars["d"], Obj::Int((-2).into()).into());
+        assert_eq!(repr("e"), "Fraction(1, 2)");
+        assert_eq!(vars["f"], Obj::Float(1.0).into());
+        assert_eq!(repr("g"), "Fraction(4, 1)");
+        assert_eq!(vars["h"], Obj::Int(4.into()).into());
+        assert_eq!(vars["i"], Obj::Bool(true).into());
+        assert_eq!(vars["j"], Obj::Int(2.into()).into());
+        assert_eq!(repr("k"), "Fraction(33, 100)");
+        assert_eq!(repr("l"), "Fraction(311, 99)");
+        assert_eq!(vars["m"], Obj::Int(3.into()).into());
+        assert_eq!(vars["n"], Obj::Int((-3).into()).into());
+        assert_eq!(vars["a"].to_string(), "1/2");
+
+        let new = |args: Vec<Obj>| {
+            let args: Vec<Arc<Obj>> = args.into_iter().map(Arc::new).collect();
+            fractions::new(&args, &[]).unwrap_err().msg
+        };
+        assert_eq!(new(vec![Obj::Int(1.into()), Obj::Int(0.into())]), "Fraction(1, 0)");
+        assert_eq!(new(vec![Obj::Str("x".into())]), "Invalid literal for Fraction: 'x'");
+        assert_eq!(new(vec![Obj::Float(1.5), Obj::Int(2.into())]), "both arguments should be Rational instances");
+        let half = Obj::Fraction((1, 2).into());
+        let err = |op: Op| fractions::binary_op(&half, &Obj::Int(0.into()), op).unwrap_err().msg;
+        assert_eq!(err(Op::ForwardSlash), "Fraction(1, 0)");
+        assert_eq!(err(Op::DoubleSlash), "integer division or modulo by zero");
+        assert_eq!(err(Op::Percent), "integer modulo by zero");
+    }
+
+    #[test]
+    fn decimal_module() {
+        let vm = run_source(
+            "from decimal import Decimal\n\
+             import decimal\n\
+             a = Decimal('1.10') + Decimal('2.205')\n\
+             b = Decimal(1) / Decimal(3)\n\
+             c = Decimal('1.00') / Decimal(2)\n\
+             d = Decimal(6) / Decimal(2)\n\
+             e = Decimal(-7) // Decimal(2)\n\
+             f = Decimal(-7) % Decimal(2)\n\
+             g = Decimal(0.1)\n\
+             h = Decimal('1.000') * Decimal('2.00')\n\
+             i = Decimal(10) ** 30\n\
+             j = Decimal(2).sqrt()\n\
+             k = Decimal('2.5').quantize(Decimal('1'), rounding=decimal.ROUND_HALF_UP)\n\
+             l = round(Decimal('2.675'), 2)\n\
+             m = Decimal('123.4500').normalize()\n\
+             n = Decimal('1.5') == 1.5\n\
+             o = int(Decimal('-2.7'))\n\
+             p = Decimal('0.0000001')"
+        );
+        let vars = &vm.get_vars()[0];
+        let repr = |name: &str| vars[name].__repr__();
+        assert_eq!(repr("a"), "Decimal('3.305')");
+        assert_eq!(repr("b"), "Decimal('0.3333333333333333333333333333')");
+        assert_eq!(repr("c"), "Decimal('0.50')");
+        assert_eq!(repr("d"), "Decimal('3')");
+        assert_eq!(repr("e"), "Decimal('-3')");
+        assert_eq!(repr("f"), "Decimal('-1')");
+        assert_eq!(repr("g"), "Decimal('0.1000000000000000055511151231257827021181583404541015625')");
+        assert_eq!(repr("h"), "Decimal('2.00000')");
+        assert_eq!(repr("i"), "Decimal('1.000000000000000000000000000E+30')");
+        assert_eq!(repr("j"), "Decimal('1.414213562373095048801688724')");
+        assert_eq!(repr("k"), "Decimal('3')");
+        assert_eq!(repr("l"), "Decimal('2.68')");
+        assert_eq!(repr("m"), "Decimal('123.45')");
+        assert_eq!(vars["n"], Obj::Bool(true).into());
+        assert_eq!(vars["o"], Obj::Int((-2).into()).into());
+        assert_eq!(vars["p"].to_string(), "1E-7");
+
+        let one = Obj::Decimal(Box::new(decimal::Decimal::parse("1").unwrap()));
+        let err = |rhs: Obj, op: Op| decimal::binary_op(&one, &rhs, op).unwrap_err();
+        let zero = || Obj::Int(0.into());
+        assert_eq!(err(zero(), Op::ForwardSlash).msg, "[<class 'decimal.DivisionByZero'>]");
+        assert_eq!(err(zero(), Op::ForwardSlash).error, PyError::ZeroDivisionError);
+        assert_eq!(err(Obj::Float(1.0), Op::Plus).msg, "unsupported operand type(s) for +: 'decimal.Decimal' and 'float'");
+        assert_eq!(decimal::Decimal::parse("abc").unwrap_err().msg, "[<class 'decimal.ConversionSyntax'>]");
+    }
+
+    #[test]
+    fn decimal_context() {
+        let vm = run_source(
+            "import decimal\n\
+             from decimal import Decimal\n\
+             ctx = decimal.getcontext()\n\
+             ctx.prec = 6\n\
+             a = Decimal(1) / Decimal(7)\n\
+             b = Decimal(1234567) + 0\n\
+             ctx.rounding = decimal.ROUND_DOWN\n\
+             c = Decimal(2) / Decimal(3)\n\
+             decimal.setcontext(decimal.Context(prec=3, rounding=decimal.ROUND_CEILING))\n\
+             d = Decimal(1) / Decimal(3)\n\
+             e = decimal.getcontext().prec"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"].to_string(), "0.142857");
+        assert_eq!(vars["b"].to_string(), "1.23457E+6");
+        assert_eq!(vars["c"].to_string(), "0.666666");
+        assert_eq!(vars["d"].to_string(), "0.334");
+        assert_eq!(vars["e"], Obj::Int(3.into()).into());
+        assert_eq!(
+            decimal::DecimalContext::default().to_string(),
+            "Context(prec=28, rounding=ROUND_HALF_EVEN, Emin=-999999, Emax=999999, capitals=1, clamp=0, \
+             flags=[], traps=[InvalidOperation, DivisionByZero, Overflow])"
+        );
+
+        let mut ctx = decimal::DecimalContext::default();
+        let set = |ctx: &mut decimal::DecimalContext, name: &str, val: Obj| {
+            decimal::set_context_attr(ctx, name, &val).unwrap_err().msg
+        };
+        assert_eq!(set(&mut ctx, "prec", Obj::Int(0.into())), "valid range for prec is [1, MAX_PREC]");
+        assert!(set(&mut ctx, "rounding", Obj::Str("x".into())).starts_with("valid values for rounding are:"));
+    }
+
+    #[test]
+    fn decimal_format() {
+        use crate::pyrs_format::format_obj;
+        let dec = |s: &str| Obj::Decimal(Box::new(decimal::Decimal::parse(s).unwrap()));
+        // checked against CPython's format()
+        let cases = [
+            ("1.5", ".2f", "1.50"),
+            ("1.5", ">10", "       1.5"),
+            ("1e20", ">10", "     1E+20"),
+            ("1.5", "e", "1.5e+0"),
+            ("1.5", ".3e", "1.500e+0"),
+            ("0", ".2e", "0.00e+2"),
+            ("1.5", "%", "150%"),
+            ("0.125", ".1%", "12.5%"),
+            ("1.5", "10.4%", " 150.0000%"),
+            ("-0", "z.1f", "0.0"),
+            ("-0.00", "z.1e", "0.0e-1"),
+            ("1234567.891", ",.2f", "1,234,567.89"),
+            ("-1234.5", "012,.1f", "-0,001,234.5"),
+            ("1234.5", "_<12,", "1,234.5_____"),
+            ("1.50", "f", "1.50"),
+            ("1E+2", "f", "100"),
+            ("0E+2", "f", "0"),
+            ("9.99", ".1f", "10.0"),
+            ("2.5", ".0f", "2"),
+            ("5E-7", ".3f", "0.000"),
+            ("123456", ".3g", "1.23e+5"),
+            ("9.99", ".2g", "10"),
+            ("1.5", ".0g", "2"),
+            ("0.0000001", "g", "1e-7"),
+            ("1E+3", "g", "1e+3"),
+            ("0E-10", ".2g", "0e-10"),
+            ("12345", ".2", "1.2E+4"),
+            ("1e-10", "G", "1E-10"),
+            ("1.5", "=+8", "+    1.5"),
+            ("NaN", "*^9", "***NaN***"),
+            ("-Infinity", ".2f", "-Infinity"),
+            ("-inf", "010", " -Infinity"),
+            ("-inf", "=12", "-   Infinity"),
+            ("Infinity", "%", "Infinity%"),
+        ];
+        for (d, spec, expected) in cases {
+            assert_eq!(format_obj(&dec(d), spec).unwrap(), expected, "format(Decimal({d:?}), {spec:?})");
+        }
+        for spec in ["d", "x", "#e", "_"] {
+            assert_eq!(format_obj(&dec("1"), spec).unwrap_err().msg, "invalid format string", "{spec:?}");
+        }
+
+        let vm = run_source(
+            "from decimal import Decimal\n\
+             d = Decimal('1.555')\n\
+             a = f\"{d:.2f}|{d:>8}|{d!r}\"\n\
+             b = '{:>8.3}'.format(Decimal('3.14159'))\n\
+             c = '%.2f %d %e %s %5.1f' % (d, Decimal('-1.7'), Decimal('1.5'), Decimal('1.50'), Decimal('2.25'))"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"], "1.56|   1.555|Decimal('1.555')".to_arc());
+        assert_eq!(vars["b"], "    3.14".to_arc());
+        assert_eq!(vars["c"], "1.55 -1 1.500000e+00 1.50   2.2".to_arc());
+        use crate::pyrs_format::{percent_number, FormatSpec};
+        let spec = FormatSpec { kind: Some('x'), ..Default::default() };
+        assert_eq!(percent_number(&dec("1.5"), &spec).unwrap_err().msg, "%x format: an integer is required, not decimal.Decimal");
+    }
+
+    #[test]
+    fn subscripts_and_slices() {
+        let vm = run_source(
+            "a = [1, 2, 3, 4, 5]\n\
+             b = a[-1]\n\
+             c = a[1:3]\n\
+             d = a[::-2]\n\
+             a[0] = 10\n\
+             a[1:3] = [7]\n\
+             a[-1] += 5\n\
+             m = {\"k\": [1, 2]}\n\
+             m[\"k\"][0] = 9\n\
+             k = m[\"k\"]\n\
+             s = \"h\u{e9}llo\"[1:4]\n\
+             t = (1, 2, 3)[::2]"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"].__repr__(), "[10, 7, 4, 10]");
+        assert_eq!(vars["b"], Obj::Int(5.into()).into());
+        assert_eq!(vars["c"].__repr__(), "[2, 3]");
+        assert_eq!(vars["d"].__repr__(), "[5, 3, 1]");
+        assert_eq!(vars["k"].__repr__(), "[9, 2]");
+        assert_eq!(vars["s"].__repr__(), "'\u{e9}ll'");
+        assert_eq!(vars["t"].__repr__(), "(1, 3)");
+
+        let list: Arc<Obj> = Obj::new_list(vec![Obj::Int(1.into()).into()]).into();
+        let get = |obj: &Arc<Obj>, key: Obj| Obj::get_item(obj, &key).unwrap_err().msg;
+        assert_eq!(get(&list, Obj::Int(1.into())), "list index out of range");
+        assert_eq!(get(&list, Obj::Str("a".into())), "list indices must be integers or slices, not str");
+        assert_eq!(get(&Obj::Str("ab".into()).into(), Obj::Int(2.into())), "string index out of range");
+        let zero_step = Obj::Slice(Obj::None.into(), Obj::None.into(), Obj::Int(0.into()).into());
+        assert_eq!(get(&list, zero_step), "slice step cannot be zero");
+        assert_eq!(get(&Obj::Int(5.into()).into(), Obj::Int(0.into())), "'int' object is not subscriptable");
+        let tuple: Arc<Obj> = Obj::Tuple(vec![]).into();
+        let set = Obj::set_item(&tuple, &Obj::Int(0.into()), Obj::None.into()).unwrap_err();
+        assert_eq!(set.msg, "'tuple' object does not support item assignment");
+
+        // lists and dicts are shared, a change through one name shows in the others
+        let vm = run_source(
+            "a = [1, 2]\n\
+             b = a\n\
+             b[0] = 10\n\
+             m = [[0, 0], [0, 0]]\n\
+             row = m[0]\n\
+             m[0][1] = 5\n\
+             d = {}\n\
+             e = d\n\
+             e['k'] = a\n\
+             n = [0]\n\
+             def f():\n    n[0] += 1\n    return a\n\
+             f()[1] += 100\n\
+             a[:1] = a\n\
+             x = [1]\n\
+             x[0] = x"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["b"].__repr__(), "[10, 102, 102]");
+        assert_eq!(vars["row"].__repr__(), "[0, 5]");
+        assert_eq!(vars["d"].__repr__(), "{'k':[10, 102, 102]}");
+        assert_eq!(vars["n"].__repr__(), "[1]");
+        assert_eq!(vars["x"].__repr__(), "[[...]]");
+    }
+
+    #[test]
+    fn bytes_and_bytearray() {
+        let vm = run_source(
+            "b = b\"ab\\xff\"\n\
+             i = b[-1]\n\
+             s = b[:2]\n\
+             h = b.hex(\":\")\n\
+             f = bytes.fromhex(\"de ad\")\n\
+             ba = bytearray(b\"hello\")\n\
+             alias = ba\n\
+             ba[0] = 72\n\
+             ba.append(33)\n\
+             ba.extend([63, 63])\n\
+             p = ba.pop()\n\
+             ba[1:5] = b\"i\"\n\
+             joined = b\"-\".join([b\"x\", bytearray(b\"y\")])\n\
+             found = b\"abcabc\".find(b\"c\", 3)\n\
+             z = bytes(3)"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["i"], Obj::Int(255.into()).into());
+        assert_eq!(vars["s"].__repr__(), "b'ab'");
+        assert_eq!(vars["h"].__repr__(), "'61:62:ff'");
+        assert_eq!(vars["f"].__repr__(), "b'\\xde\\xad'");
+        assert_eq!(vars["p"], Obj::Int(63.into()).into());
+        assert_eq!(vars["ba"].__repr__(), "bytearray(b'Hi!?')");
+        assert_eq!(vars["alias"].__repr__(), "bytearray(b'Hi!?')");
+        assert_eq!(vars["joined"].__repr__(), "b'x-y'");
+        assert_eq!(vars["found"], Obj::Int(5.into()).into());
+        assert_eq!(vars["z"].__repr__(), "b'\\x00\\x00\\x00'");
+        assert_eq!(vars["ba"], Obj::Bytes(b"Hi!?".to_vec()).into());
+
+        let args = |objs: Vec<Obj>| objs.into_iter().map(Arc::new).collect::<Vec<_>>();
+        let new = |objs: Vec<Obj>| bytes::new("bytearray", &args(objs), &[]).unwrap_err().msg;
+        assert_eq!(new(vec![Obj::Str("x".into())]), "string argument without an encoding");
+        assert_eq!(new(vec![Obj::new_list(vec![Obj::Int(256.into()).into()])]), "byte must be in range(0, 256)");
+        assert_eq!(new(vec![Obj::Float(1.5)]), "cannot convert 'float' object to bytearray");
+        let fromhex = bytes::fromhex("bytes", &args(vec![Obj::Str("abc".into())])).unwrap_err();
+        assert_eq!(fromhex.msg, "non-hexadecimal number found in fromhex() arg at position 3");
+
+        // a count past isize overflows and one that can't be allocated is a MemoryError
+        let count = |n: Integer| bytes::new("bytearray", &args(vec![Obj::Int(n)]), &[]).unwrap_err();
+        assert_eq!(count(Integer::from(-1)).msg, "negative count");
+        let e = count(Integer::from(10).pow(20));
+        assert_eq!((e.error, e.msg.as_str()), (PyError::OverflowError, "cannot fit 'int' into an index-sized integer"));
+        assert_eq!(count(Integer::from(1) << 62).error, PyError::MemoryError);
+        assert_eq!(bytes::repeat(b"ab", &Integer::from(-3)).unwrap(), b"");
+        assert_eq!(bytes::repeat(b"x", &Integer::from(10).pow(20)).unwrap_err().error, PyError::OverflowError);
+        assert_eq!(bytes::repeat(b"ab", &(Integer::from(1) << 62)).unwrap_err().error, PyError::MemoryError);
+    }
+
+    #[test]
+    fn memoryview_and_codecs() {
+        let vm = run_source(
+            "ba = bytearray(b\"abcdef\")\n\
+             mv = memoryview(ba)\n\
+             sub = mv[1:5:2]\n\
+             sub[0] = 66\n\
+             mv[4:6] = b\"XY\"\n\
+             l = sub.tolist()\n\
+             ro = memoryview(b\"xyz\").readonly\n\
+             e = \"h\u{e9}\u{20ac}\".encode()\n\
+             d = e.decode(\"utf-8\")\n\
+             r = b\"a\\xffb\".decode(\"ascii\", \"replace\")\n\
+             q = \"a\u{e9}\".encode(\"ascii\", errors=\"replace\")\n\
+             lat = \"\u{e9}\".encode(\"latin-1\")\n\
+             kept = bytearray(b\"ab\")\n\
+             memoryview(kept)\n\
+             kept.append(99)\n\
+             with memoryview(kept) as w:\n    first = w[0]\n\
+             kept.append(100)\n\
+             gone = repr(w)"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["ba"].__repr__(), "bytearray(b'aBcdXY')");
+        assert_eq!(vars["l"].__repr__(), "[66, 100]");
+        assert_eq!(vars["ro"], Obj::Bool(true).into());
+        assert_eq!(vars["e"].__repr__(), "b'h\\xc3\\xa9\\xe2\\x82\\xac'");
+        assert_eq!(vars["d"].__str__(), "h\u{e9}\u{20ac}");
+        assert_eq!(vars["r"].__str__(), "a\u{fffd}b");
+        assert_eq!(vars["q"].__repr__(), "b'a?'");
+        assert_eq!(vars["lat"].__repr__(), "b'\\xe9'");
+        assert_eq!(vars["kept"].__repr__(), "bytearray(b'abcd')");
+        assert_eq!(vars["first"], Obj::Int(97.into()).into());
+        assert!(vars["gone"].__str__().starts_with("<released memory at "));
+
+        let decode = |b: &[u8], encoding: &str| bytes::decode(b, encoding, "strict").unwrap_err();
+        let e = decode(b"\xff", "utf-8");
+        assert_eq!(e.error, PyError::UnicodeDecodeError);
+        assert_eq!(e.msg, "'utf-8' codec can't decode byte 0xff in position 0: invalid start byte");
+        assert_eq!(
+            decode(b"a\xe2\x82", "utf-8").msg,
+            "'utf-8' codec can't decode bytes in position 1-2: unexpected end of data"
+        );
+        assert_eq!(decode(b"x", "nope").error, PyError::LookupError);
+        let e = bytes::encode("a\u{e9}\u{e8}", "ascii", "strict").unwrap_err();
+        assert_eq!(e.msg, "'ascii' codec can't encode characters in position 1-2: ordinal not in range(128)");
+        let e = bytes::encode("\u{20ac}", "latin-1", "strict").unwrap_err();
+        assert_eq!(e.msg, "'latin-1' codec can't encode character '\\u20ac' in position 0: ordinal not in range(256)");
+
+        let view = bytes::MemoryView::new(&Obj::Bytes(b"ab".to_vec()).into()).unwrap();
+        let view = Obj::MemoryView(Arc::new(view));
+        let e = bytes::set_item(&view, &Obj::Int(0.into()), &Obj::Int(1.into())).unwrap_err();
+        assert_eq!(e.msg, "cannot modify read-only memory");
+
+        // a bytearray with views can't resize until they are dropped
+        let ba: Arc<Obj> = bytes::new_bytearray(b"abc".to_vec()).into();
+        let view = Obj::MemoryView(Arc::new(bytes::MemoryView::new(&ba).unwrap()));
+        let append = || bytes::call_method(&ba, "append", &[Obj::Int(1.into()).into()], &[]);
+        assert_eq!(append().unwrap_err().error, PyError::BufferError);
+        let e = bytes::set_item(&ba, &Obj::Slice(Obj::None.into(), Obj::None.into(), Obj::None.into()), &Obj::Bytes(vec![])).unwrap_err();
+        assert_eq!(e.msg, "Existing exports of data: object cannot be re-sized");
+        assert!(Obj::del_item(&ba, &Obj::Int(0.into())).is_err());
+        bytes::set_item(&ba, &Obj::Int(0.into()), &Obj::Int(65.into())).unwrap();
+        assert_eq!(bytes::bytes_of(&view), Some(b"Abc".to_vec()));
+        drop(view);
+        append().unwrap();
+        assert_eq!(ba.__repr__(), "bytearray(b'Abc\\x01')");
+
+        // so can it once they are released, and they can't be used after
+        let view = Obj::MemoryView(Arc::new(bytes::MemoryView::new(&ba).unwrap()));
+        assert_eq!(append().unwrap_err().error, PyError::BufferError);
+        bytes::call_method(&view, "release", &[], &[]).unwrap();
+        bytes::call_method(&view, "release", &[], &[]).unwrap();
+        append().unwrap();
+        let e = bytes::get_item(&view, &Obj::Int(0.into())).unwrap_err();
+        assert_eq!((e.error, e.msg.as_str()), (PyError::ValueError, "operation forbidden on released memoryview object"));
+        assert_eq!(bytes::call_method(&view, "tobytes", &[], &[]).unwrap_err().error, PyError::ValueError);
+    }
+
+    #[test]
+    fn str_methods() {
+        let vm = run_source(
+            "s = \"  a b  c  \"\n\
+             w = s.split(None, 1)\n\
+             rw = s.rsplit(maxsplit=1)\n\
+             parts = \"a,b,,c\".rsplit(\",\", 2)\n\
+             lines = \"ab\\ncd\\r\\nef\".splitlines(True)\n\
+             j = \"-\".join([\"x\", \"y\"])\n\
+             st = \"xxhixy\".strip(\"xy\")\n\
+             rep = \"abc\".replace(\"\", \"-\", 2)\n\
+             f = \"h\u{e9}llo w\u{f6}rld\".find(\"w\")\n\
+             rf = \"abcabc\".rfind(\"c\", 0, -1)\n\
+             c = \"aaaa\".count(\"aa\")\n\
+             sw = \"hello\".startswith((\"x\", \"hel\"))\n\
+             t = \"they're bill's 1st\".title()\n\
+             cap = \"hELLO\".capitalize()\n\
+             up = \"stra\u{df}e\".upper()\n\
+             cen = \"ab\".center(7, \"*\")\n\
+             z = \"-42\".zfill(5)\n\
+             p = \"a=b=c\".rpartition(\"=\")\n\
+             tabs = \"a\\tbc\".expandtabs(4)\n\
+             checks = [\"123\".isdigit(), \"\u{b2}\".isdecimal(), \"Hello World\".istitle(), \"ABC1\".isupper(), \"_x1\".isidentifier()]\n\
+             nums = [(c.isdecimal(), c.isdigit(), c.isnumeric()) for c in \"\u{ff11}\u{1369}\u{19da}\u{2460}\u{bd}\"]\n\
+             u = \"h\u{e9}llo\"\n\
+             ch = u[1]\n\
+             back = u[::-1]"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["w"].__repr__(), "['a', 'b  c  ']");
+        assert_eq!(vars["rw"].__repr__(), "['  a b', 'c']");
+        assert_eq!(vars["parts"].__repr__(), "['a,b', '', 'c']");
+        assert_eq!(vars["lines"].__repr__(), "['ab\\n', 'cd\\r\\n', 'ef']");
+        assert_eq!(vars["j"].__str__(), "x-y");
+        assert_eq!(vars["st"].__str__(), "hi");
+        assert_eq!(vars["rep"].__str__(), "-a-bc");
+        assert_eq!(vars["f"], Obj::Int(6.into()).into());
+        assert_eq!(vars["rf"], Obj::Int(2.into()).into());
+        assert_eq!(vars["c"], Obj::Int(2.into()).into());
+        assert_eq!(vars["sw"], Obj::Bool(true).into());
+        assert_eq!(vars["t"].__str__(), "They'Re Bill'S 1St");
+        assert_eq!(vars["cap"].__str__(), "Hello");
+        assert_eq!(vars["up"].__str__(), "STRASSE");
+        assert_eq!(vars["cen"].__str__(), "***ab**");
+        assert_eq!(vars["z"].__str__(), "-0042");
+        assert_eq!(vars["p"].__repr__(), "('a=b', '=', 'c')");
+        assert_eq!(vars["tabs"].__str__(), "a   bc");
+        assert_eq!(vars["checks"].__repr__(), "[True, False, True, True, True]");
+        assert_eq!(
+            vars["nums"].__repr__(),
+            "[(True, True, True), (False, True, True), (False, True, True), (False, True, True), (False, False, True)]"
+        );
+        assert_eq!(vars["ch"].__str__(), "\u{e9}");
+        assert_eq!(vars["back"].__str__(), "oll\u{e9}h");
+
+        let call = |s: &str, name: &str, args: Vec<Obj>| {
+            let args: Vec<Arc<Obj>> = args.into_iter().map(Arc::new).collect();
+            pyrs_str::call_method(s, name, &args, &[]).unwrap_err()
+        };
+        let e = call("a", "index", vec![Obj::Str("z".to_string())]);
+        assert_eq!((e.error, e.msg.as_str()), (PyError::ValueError, "substring not found"));
+        assert_eq!(call("a", "split", vec![Obj::Str(String::new())]).msg, "empty separator");
+        assert_eq!(call("a", "find", vec![]).msg, "find() takes at least 1 argument (0 given)");
+        assert_eq!(call("a", "upper", vec![Obj::Int(1.into())]).msg, "str.upper() takes no arguments (1 given)");
+        assert_eq!(
+            call("a", "join", vec![Obj::new_list(vec![Obj::Int(1.into()).into()])]).msg,
+            "sequence item 0: expected str instance, int found"
+        );
+        assert_eq!(
+            call("a", "center", vec![Obj::Int(5.into()), Obj::Str("ab".to_string())]).msg,
+            "The fill character must be exactly one character long"
+        );
+
+        // a width past isize overflows and one that can't be allocated is a MemoryError
+        let e = call("a", "zfill", vec![Obj::Int(Integer::from(10).pow(20))]);
+        assert_eq!((e.error, e.msg.as_str()), (PyError::OverflowError, "Python int too large to convert to C ssize_t"));
+        assert_eq!(call("a", "zfill", vec![Obj::Int(isize::MAX.into())]).error, PyError::MemoryError);
+        assert_eq!(call("a", "center", vec![Obj::Int(isize::MAX.into())]).error, PyError::MemoryError);
+        assert_eq!(call("a", "rjust", vec![Obj::Int(Integer::from(-10).pow(19))]).error, PyError::OverflowError);
+        assert_eq!(call("\t", "expandtabs", vec![Obj::Int(isize::MAX.into())]).error, PyError::MemoryError);
+    }
+
+    #[test]
+    fn str_translate() {
+        let vm = run_source(
+            "fold = \"Stra\u{df}e \u{3a3}\u{391}\u{3a3} \u{131} \u{1e9e}\".casefold()\n\
+             t = str.maketrans(\"abc\", \"xyz\", \"d\")\n\
+             entries = [len(t), t[97], t[100]]\n\
+             tr = \"abcdabcd\".translate(t)\n\
+             drop = \"hello\".translate({104: \"J\", 111: None})\n\
+             one = \"hello\".translate(\"\".maketrans({\"l\": \"LL\", 101: 69}))\n\
+             fm = \"{a}-{b[0]:>3}\".format_map({\"a\": 1, \"b\": [5]})"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["fold"].__str__(), "strasse \u{3c3}\u{3b1}\u{3c3} \u{131} ss");
+        assert_eq!(vars["entries"].__repr__(), "[4, 120, None]");
+        assert_eq!(vars["tr"].__str__(), "xyzxyz");
+        assert_eq!(vars["drop"].__str__(), "Jell");
+        assert_eq!(vars["one"].__str__(), "hELLLLo");
+        assert_eq!(vars["fm"].__str__(), "1-  5");
+
+        let maketrans = |args: Vec<Obj>| {
+            let args: Vec<Arc<Obj>> = args.into_iter().map(Arc::new).collect();
+            pyrs_str::maketrans(&args).unwrap_err()
+        };
+        let s = |s: &str| Obj::Str(s.to_string());
+        let e = maketrans(vec![s("ab"), s("c")]);
+        assert_eq!((e.error, e.msg.as_str()), (PyError::ValueError, "the first two maketrans arguments must have equal length"));
+        assert_eq!(maketrans(vec![s("a")]).msg, "if you give only one argument to maketrans it must be a dict");
+        assert_eq!(maketrans(vec![s("a"), s("b"), Obj::Int(1.into())]).msg, "maketrans() argument 3 must be str, not int");
+
+        let translate = |to: Obj| pyrs_str::translate("a", |_| Ok(Some(to.clone().into()))).unwrap_err();
+        let e = translate(Obj::Int((-1).into()));
+        assert_eq!((e.error, e.msg.as_str()), (PyError::ValueError, "character mapping must be in range(0x110000)"));
+        assert_eq!(translate(Obj::Float(1.5)).msg, "character mapping must return integer, None or str");
+
+        let mut py = PyRuntime::new();
+        let e = py.eval("\"{0}\".format_map({})").unwrap_err();
+        assert_eq!((e.error, e.msg.as_str()), (PyError::ValueError, "Format string contains positional fields"));
+    }
+
+    #[test]
+    fn builtins() {
+        let vm = run_source(
+            "class A:\n    pass\n\
+             class B(A):\n    def __len__(self):\n        return 2\n\
+             e = list(enumerate([\"a\", \"b\"], 1))\n\
+             m = map(lambda x, y: x + y, [1, 2, 3], [10, 20])\n\
+             first = next(m)\n\
+             rest = list(m)\n\
+             f = list(filter(None, [0, 1, 2, 0]))\n\
+             z = list(zip([1, 2, 3], \"ab\"))\n\
+             srt = sorted([(2, \"a\"), (1, \"b\"), (1, \"a\")])\n\
+             by_len = sorted([\"bb\", \"a\", \"ccc\"], key=len, reverse=True)\n\
+             lo = min([], default=7)\n\
+             hi = max(\"abc\", key=ord)\n\
+             total = sum([1, 2], 10)\n\
+             n = len(\"h\u{e9}llo\")\n\
+             hashes = [hash(1) == hash(1.0), hash(-1), hash(0.5), hash(2**61)]\n\
+             checks = [isinstance(B(), A), isinstance(True, int), issubclass(A, B), isinstance(\"a\", (int, str))]\n\
+             b = B()\n\
+             setattr(b, \"x\", 5)\n\
+             attrs = [getattr(b, \"x\"), getattr(b, \"y\", 9), hasattr(b, \"y\"), len(b), bool(b)]\n\
+             conv = [bin(-5), oct(8), hex(255), chr(233), ord(\"a\"), ascii(\"h\u{e9}\")]\n\
+             rev = list(reversed(\"abc\"))\n\
+             s = set([1, 1, 2])\n\
+             d = dict([(1, 2)], a=3)\n\
+             C = type(\"C\", (A,), {\"v\": 3})\n\
+             made = [C.v, type(C()) == C, type(1) == int]\n\
+             lazy = all(1 / x > 0 for x in [1, -1, 0])\n\
+             same = [1]\n\
+             self_cmp = [max(same, same), sorted([same, [0], same])]\n\
+             it = iter([1, 2, 3])\n\
+             drained = [list(it), list(it) == []]\n\
+             it = iter((1, 2, 3))\n\
+             skip = [next(it), sorted(it), next(it, \"end\")]\n\
+             big = range(10**9)\n\
+             ranges = [next(map(str, big)), next(enumerate(big)), next(zip(big, big)), list(range(10, 0, -3)), bool(range(0))]\n\
+             err = ValueError(\"x\")\n\
+             exc = [str(err), repr(err), err.args[0], len(err.args), repr(KeyError()), len(KeyError().args)]"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["e"].__repr__(), "[(1, 'a'), (2, 'b')]");
+        assert_eq!(vars["first"], Obj::Int(11.into()).into());
+        assert_eq!(vars["rest"].__repr__(), "[22]");
+        assert_eq!(vars["f"].__repr__(), "[1, 2]");
+        assert_eq!(vars["z"].__repr__(), "[(1, 'a'), (2, 'b')]");
+        assert_eq!(vars["srt"].__repr__(), "[(1, 'a'), (1, 'b'), (2, 'a')]");
+        assert_eq!(vars["by_len"].__repr__(), "['ccc', 'bb', 'a']");
+        assert_eq!(vars["lo"], Obj::Int(7.into()).into());
+        assert_eq!(vars["hi"].__str__(), "c");
+        assert_eq!(vars["total"], Obj::Int(13.into()).into());
+        assert_eq!(vars["n"], Obj::Int(5.into()).into());
+        assert_eq!(vars["hashes"].__repr__(), "[True, -2, 1152921504606846976, 1]");
+        assert_eq!(vars["checks"].__repr__(), "[True, True, False, True]");
+        assert_eq!(vars["attrs"].__repr__(), "[5, 9, False, 2, True]");
+        assert_eq!(vars["conv"].__repr__(), "['-0b101', '0o10', '0xff', '\u{e9}', 97, \"'h\\\\xe9'\"]");
+        assert_eq!(vars["rev"].__repr__(), "['c', 'b', 'a']");
+        assert_eq!(vars["s"].__repr__(), "{1, 2}");
+        assert_eq!(vars["made"].__repr__(), "[3, True, True]");
+        assert_eq!(vars["lazy"], Obj::Bool(false).into());
+        assert_eq!(vars["self_cmp"].__repr__(), "[[1], [[0], [1], [1]]]");
+        assert_eq!(vars["drained"].__repr__(), "[[1, 2, 3], True]");
+        assert_eq!(vars["skip"].__repr__(), "[1, [2, 3], 'end']");
+        assert_eq!(vars["ranges"].__repr__(), "['0', (0, 0), (0, 0), [10, 7, 4, 1], False]");
+        assert_eq!(vars["exc"].__repr__(), "['x', \"ValueError('x')\", 'x', 1, 'KeyError()', 0]");
+    }
+
+    #[test]
+    fn comparisons() {
+        let vm = run_source(
+            "class V:\n    def __init__(self, v):\n        self.v = v\n    def __lt__(self, other):\n        return self.v < other.v\n\
+             ordered = [[1] < [1, 2], (1,) < (1, 2), [2] > [1, 5], (1, 2) <= (1, 2), V(1) < V(2), V(2) > V(1), {1} < {1, 2}, {1} >= {1}]\n\
+             same = [1 < 2.5, \"b\" >= \"a\", 1 == 1.0, [1, 2] != [1, 2]]",
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["ordered"].__repr__(), "[True, True, True, True, True, True, True, True]");
+        assert_eq!(vars["same"].__repr__(), "[True, True, True, False]");
+        let mut py = PyRuntime::new();
+        let e = py.eval("1 < \"a\"").unwrap_err();
+        assert_eq!((e.error, e.msg.as_str()), (PyError::TypeError, "'<' not supported between instances of 'int' and 'str'"));
+        let e = py.eval("[1] >= (1,)").unwrap_err();
+        assert_eq!((e.error, e.msg.as_str()), (PyError::TypeError, "'>=' not supported between instances of 'list' and 'tuple'"));
+    }
+
+    #[test]
+    fn introspection_builtins() {
+        let vm = run_source(
+            "x = 1\n\
+             def f(a):\n    b = 2\n    return [sorted(locals()), dir()]\n\
+             scopes = f(0) + [sorted(globals())[:3]]\n\
+             class C:\n    y = 1\n    def m(self):\n        pass\n\
+             c = C()\n\
+             c.z = 2\n\
+             c.w = 3\n\
+             delattr(c, \"z\")\n\
+             attrs = [vars(c), [n for n in dir(c) if n[0] != \"_\"], dir(5)[:3]]\n\
+             class Count:\n    def __init__(self):\n        self.n = 0\n    def __call__(self):\n        self.n = self.n + 1\n        return self.n\n\
+             counted = list(iter(Count(), 4))\n\
+             unbound = [sorted([\"b\", \"A\", \"c\"], key=str.lower), str.upper(\"x\"), int.bit_length(255)]\n\
+             names = [int.__name__, ValueError.__name__, int.__module__, str.lower, __import__(\"sys\").__name__]",
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["scopes"].__repr__(), "[['a', 'b'], ['a', 'b'], ['__name__', 'f', 'x']]");
+        assert_eq!(vars["attrs"].__repr__(), "[{'w':3}, ['m', 'w', 'y'], ['as_integer_ratio', 'bit_count', 'bit_length']]");
+        assert_eq!(vars["counted"].__repr__(), "[1, 2, 3]");
+        assert_eq!(vars["unbound"].__repr__(), "[['A', 'b', 'c'], 'X', 8]");
+        assert_eq!(vars["names"].__repr__(), "['int', 'ValueError', 'builtins', <method 'lower' of 'str' objects>, 'sys']");
+    }
+
+    #[test]
+    fn object_super_frozenset() {
+        let vm = run_source(
+            "class A:\n    def __init__(self, x):\n        super().__init__()\n        self.x = x\n    def who(self):\n        return 'A'\n    @classmethod\n    def make(cls):\n        return cls.__name__\n\
+             class B(A):\n    def __init__(self, x):\n        super().__init__(x * 2)\n    def who(self):\n        return 'B' + super().who()\n    @classmethod\n    def make(cls):\n        return 'B' + super().make()\n\
+             class C(B, object):\n    def who(self):\n        return 'C' + super(C, self).who()\n\
+             c = C(3)\n\
+             sup = [c.x, c.who(), C.make(), super(B, c).who()]\n\
+             objs = [isinstance(c, object), isinstance(5, object), issubclass(int, object), type(object()), object]\n\
+             f = frozenset([1, 2, 2, 3])\n\
+             g = frozenset([3, 2, 1])\n\
+             d = {f: 'found'}\n\
+             sets = [f, len(f), f == g, d[g], hash(f) == hash(g), f == set([1, 2, 3]), frozenset(), sorted(f)]",
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["sup"].__repr__(), "[6, 'CBA', 'BC', 'A']");
+        assert_eq!(vars["objs"].__repr__(), "[True, True, True, <class 'object'>, <class 'object'>]");
+        assert_eq!(
+            vars["sets"].__repr__(),
+            "[frozenset({1, 2, 3}), 3, True, 'found', True, True, frozenset(), [1, 2, 3]]"
+        );
+    }
+
+    #[test]
+    fn eval_exec_compile() {
+        let mut py = PyRuntime::new();
+        py.capture_output();
+        let src = "x = 10\n\
+                   e = [eval('x + 1'), eval('y * 2', {'y': 4}), eval('a + b', {'a': 1}, {'b': 2})]\n\
+                   exec('z = x * 3\\ndef f():\\n    return z + late')\n\
+                   late = 1\n\
+                   g = {'n': 2}\n\
+                   exec('m = n + 1', g)\n\
+                   loc = {}\n\
+                   exec('k = 5', {'q': 1}, loc)\n\
+                   def h(v):\n    exec('u = v + 1')\n    return [eval('v * 2'), sorted(locals())]\n\
+                   code = compile('1 + 2', '<s>', 'eval')\n\
+                   exec(compile('r = 7', '<s>', 'exec'))\n\
+                   def doc():\n    \"\"\"Says hi.\n\n    Twice.\"\"\"\n\
+                   help(doc)\n";
+        py.exec(src, "script.py").unwrap();
+        assert_eq!(py.eval("e").unwrap().__repr__(), "[11, 8, 3]");
+        assert_eq!(py.eval("[z, f(), g['m'], loc, h(3), eval(code), r]").unwrap().__repr__(), "[30, 31, 3, {'k':5}, [6, ['v']], 3, 7]");
+        assert_eq!(py.take_stdout(), "Help on function doc:\n\n    Says hi.\n\n    Twice.\n\n");
+        let err = py.exec("eval('x = 1')", "bad.py").unwrap_err();
+        assert_eq!((err.error, err.msg.as_str()), (PyError::SyntaxError, "invalid syntax (<string>)"));
+    }
+
+    #[test]
+    fn builtin_errors() {
+        let mut vm = PyVM::new();
+        let mut call = |name: &str, args: Vec<Obj>, kwargs: Vec<(&str, Obj)>| {
+            let args: Vec<_> = args.into_iter().map(Arc::new).collect();
+            let kwargs: Vec<_> = kwargs.into_iter().map(|(k, v)| (k.to_string(), Arc::new(v))).collect();
+            builtins::lookup(name).unwrap().call(&mut vm, &args, &kwargs).unwrap_err().msg
+        };
+        let list = |items: Vec<Obj>| Obj::new_list(items.into_iter().map(Arc::new).collect());
+        assert_eq!(call("len", vec![Obj::Int(5.into())], vec![]), "object of type 'int' has no len()");
+        assert_eq!(call("min", vec![list(vec![])], vec![]), "min() arg is an empty sequence");
+        assert_eq!(
+            call("sum", vec![list(vec![]), Obj::Str(String::new())], vec![]),
+            "sum() can't sum strings [use ''.join(seq) instead]"
+        );
+        assert_eq!(
+            call("sorted", vec![list(vec![Obj::Int(1.into()), Obj::Str("a".to_string())])], vec![]),
+            "'<' not supported between instances of 'str' and 'int'"
+        );
+        assert_eq!(call("chr", vec![Obj::Int((-1).into())], vec![]), "chr() arg not in range(0x110000)");
+        assert_eq!(
+            call("ord", vec![Obj::Str("ab".to_string())], vec![]),
+            "ord() expected a character, but string of length 2 found"
+        );
+        assert_eq!(call("map", vec![Obj::None], vec![]), "map() must have at least two arguments.");
+        assert_eq!(call("reversed", vec![Obj::Set(vec![])], vec![]), "'set' object is not reversible");
+        assert_eq!(
+            call("isinstance", vec![Obj::Int(1.into()), Obj::Int(5.into())], vec![]),
+            "isinstance() arg 2 must be a type, a tuple of types, or a union"
+        );
+        assert_eq!(
+            call("min", vec![Obj::Int(1.into()), Obj::Int(2.into())], vec![("default", Obj::None)]),
+            "Cannot specify a default for min() with multiple positional arguments"
+        );
+        assert_eq!(call("len", vec![Obj::Str("a".to_string())], vec![("x", Obj::None)]), "len() takes no keyword arguments");
+        assert_eq!(call("iter", vec![Obj::None, Obj::None], vec![]), "iter(v, w): v must be callable");
+        assert_eq!(call("vars", vec![Obj::Int(1.into())], vec![]), "vars() argument must have __dict__ attribute");
+        assert_eq!(call("globals", vec![Obj::None], vec![]), "globals() takes no arguments (1 given)");
+        assert_eq!(call("__import__", vec![Obj::Str(String::new())], vec![]), "Empty module name");
+        assert_eq!(call("aiter", vec![Obj::None], vec![]), "'NoneType' object is not an async iterable");
+        assert_eq!(
+            call("compile", vec![Obj::Str("1".to_string()), Obj::Str("f".to_string()), Obj::Str("run".to_string())], vec![]),
+            "compile() mode must be 'exec', 'eval' or 'single'"
+        );
+        assert_eq!(call("exec", vec![Obj::Int(1.into())], vec![]), "exec() arg 1 must be a string, bytes or code object, not int");
+        assert_eq!(
+            call("eval", vec![Obj::Str("1".to_string()), Obj::Int(1.into())], vec![]),
+            "eval() globals must be a dict, not int"
+        );
+        assert_eq!(vm.construct("super", &[], &[]).unwrap_err().msg, "super(): no arguments");
+        let object = builtins::lookup_name("object").unwrap().to_arc();
+        assert_eq!(vm.call_object(object.clone(), vec![Obj::None.into()], vec![]).unwrap_err().msg, "object() takes no arguments");
+        let instance = vm.call_object(object, vec![], vec![]).unwrap();
+        assert_eq!(vm.set_attr(&instance, "x".to_string(), Obj::None.into()).unwrap_err().msg, "'object' object has no attribute 'x'");
+        let lower = vm.get_attr(&Obj::BuiltinType("str").into(), "lower").unwrap();
+        assert_eq!(
+            vm.call_object(lower, vec![Obj::Int(5.into()).into()], vec![]).unwrap_err().msg,
+            "descriptor 'lower' for 'str' objects doesn't apply to a 'int' object"
+        );
+
+        let zip = builtins::lookup("zip")
+            .unwrap()
+            .call(
+                &mut vm,
+                &[list(vec![Obj::None]).into(), list(vec![Obj::None, Obj::None]).into()],
+                &[("strict".to_string(), Obj::Bool(true).into())],
+            )
+            .unwrap();
+        assert!(vm.next_item(&zip).unwrap().is_some());
+        assert_eq!(vm.next_item(&zip).unwrap_err().msg, "zip() argument 2 is longer than argument 1");
+    }
+
+    #[test]
+    fn native_functions() {
+        let vm = run_source(
+            "class A:\n    @staticmethod\n    def s(x):\n        return x * 2\n    @property\n    def p(self):\n        return 7\n\
+             total = 0\n\
+             for i in range(3):\n    total = total + i\n\
+             down = list(range(10, 0, -3))\n\
+             r = [range(5), range(1, 9, 2), len(range(1, 10, 4)), len(range(5, 0)), isinstance(range(1), range)]\n\
+             ri = [range(5)[2], range(10, 0, -3)[-1], range(10)[2:7:2], range(10)[::-1], range(0, 10, 3)[1:], range(9)[3:5]]\n\
+             p = print\n\
+             ret = p()\n\
+             attrs = [A.s(4), A().p]"
+        );
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["total"], Obj::Int(3.into()).into());
+        assert_eq!(vars["down"].__repr__(), "[10, 7, 4, 1]");
+        assert_eq!(vars["r"].__repr__(), "[range(0, 5), range(1, 9, 2), 3, 0, True]");
+        assert_eq!(vars["ri"].__repr__(), "[2, 1, range(2, 7, 2), range(9, -1, -1), range(3, 12, 3), range(3, 5)]");
+        assert_eq!(vars["p"].__str__(), "<built-in function print>");
+        assert_eq!(vars["ret"], Obj::None.into());
+        assert_eq!(vars["attrs"].__repr__(), "[8, 7]");
+
+        let mut vm = PyVM::new();
+        let step = [1, 2, 0].map(|i| Arc::new(Obj::Int(i.into())));
+        assert_eq!(vm.construct("range", &step, &[]).unwrap_err().msg, "range() arg 3 must not be zero");
+        let five = vm.construct("range", &[Obj::Int(5.into()).into()], &[]).unwrap();
+        let e = Obj::get_item(&five, &Obj::Int(5.into())).unwrap_err();
+        assert_eq!((e.error, e.msg.as_str()), (PyError::IndexError, "range object index out of range"));
+        let e = Obj::get_item(&five, &Obj::Str("a".to_string())).unwrap_err();
+        assert_eq!(e.msg, "range indices must be integers or slices, not str");
+    }
+
+    #[test]
+    fn parse_imports() {
+        assert_eq!(
+            Expression::from_line("import a.b as c, d").unwrap().to_string(),
+            "Keyword[import conds[ Keyword[as conds[ Ident(a.b) Ident(c)] args[]] Ident(d)] args[]]"
+        );
+        assert_eq!(
+            Expression::from_line("from ..pkg import (x as y, z)").unwrap().to_string(),
+            "Keyword[from conds[ Ident(..pkg) Keyword[as conds[ Ident(x) Ident(y)] args[]] Ident(z)] args[]]"
+        );
+        assert_eq!(Expression::from_line("from . import *").unwrap().to_string(), "Keyword[from conds[ Ident(.) Ident(*)] args[]]");
+
+        let mut code = vec![];
+        PyBytecode::from_expr(Expression::from_line("import a.b as c").unwrap(), &mut code).unwrap();
+        assert_eq!(
+            code,
+            vec![
+                PyBytecode::LoadConst(Obj::Int(0.into())),
+                PyBytecode::LoadConst(Obj::None),
+                PyBytecode::ImportName("a.b".to_string()),
+                PyBytecode::ImportFrom("b".to_string()),
+                PyBytecode::Swap(2),
+                PyBytecode::PopTop,
+                PyBytecode::StoreName("c".to_string()),
+            ]
+        );
+    }
+
+    // writes the files into a new directory under the temp dir
+    fn module_dir(files: &[(&str, &str)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("pyrs_modules_{:?}", std::thread::current().id()));
+        for (name, source) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn import_modules() {
+        let dir = module_dir(&[
+            ("counter.py", "N = 1\ndef get():\n    return N\ndef gen():\n    yield N\nprint_name = __name__\n"),
+            ("shapes/__init__.py", "from .square import area\nfrom . import circle\n"),
+            ("shapes/square.py", "def area(s):\n    return s * s\n"),
+            ("shapes/circle.py", "from counter import get\nNAME = __name__\n"),
+            ("shapes/solid/__init__.py", ""),
+            ("shapes/solid/cube.py", "from ..square import area\ndef volume(s):\n    return area(s) * s\n"),
+            ("stars.py", "__all__ = ['a']\na = 1\nb = 2\n"),
+            ("cycle_a.py", "import cycle_b\nA = 1\n"),
+            ("cycle_b.py", "import cycle_a\ndef get_a():\n    return cycle_a.A\n"),
+        ]);
+        let vm = run_source(&format!(
+            "import sys\n\
+             sys.path = ['{}'] + sys.path\n\
+             import counter\n\
+             import counter as c2\n\
+             N = 5\n\
+             first = [counter.get(), list(counter.gen()), counter.print_name, c2 == counter]\n\
+             counter.N = 3\n\
+             from counter import get as g\n\
+             second = [g(), __name__]\n\
+             import shapes.solid.cube\n\
+             from shapes import circle, area\n\
+             from shapes.solid import cube as c\n\
+             pkg = [shapes.solid.cube.volume(2), area(3), circle.NAME, circle.get(), c.volume(1)]\n\
+             from stars import *\n\
+             import cycle_a\n\
+             cyc = cycle_a.cycle_b.get_a()\n\
+             cached = sys.modules['shapes.square'] == shapes.square",
+            dir.display()
+        ));
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["first"].__repr__(), "[1, [1], 'counter', True]");
+        assert_eq!(vars["second"].__repr__(), "[3, '__main__']");
+        assert_eq!(vars["pkg"].__repr__(), "[8, 9, 'shapes.circle', 3, 1]");
+        assert_eq!(vars["a"], Obj::Int(1.into()).into());
+        assert!(!vars.contains_key("b"));
+        assert_eq!(vars["cyc"], Obj::Int(1.into()).into());
+        assert_eq!(vars["cached"], Obj::Bool(true).into());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn import_errors() {
+        let mut vm = PyVM::new();
+        assert_eq!(vm.import("no_such_module", 0, &[]).unwrap_err().msg, "No module named 'no_such_module'");
+        #[cfg(feature = "cmath")]
+        assert_eq!(vm.import("cmath.x", 0, &[]).unwrap_err().msg, "No module named 'cmath.x'; 'cmath' is not a package");
+        assert_eq!(
+            vm.import("x", 1, &["y".to_string()]).unwrap_err().msg,
+            "attempted relative import with no known parent package"
+        );
+    }
+
+    // a module the way code outside the interpreter would write one
+    fn geometry_module() -> NativeModule {
+        static HANDLE: std::sync::OnceLock<Arc<NativeClass>> = std::sync::OnceLock::new();
+        let handle = HANDLE.get_or_init(|| {
+            let mut handle = NativeClass::new("Handle").method("id", |_, args, _| with_self(args, |id: &mut u8| (*id).to_arc()));
+            handle.module = "geometry".to_string();
+            Arc::new(handle)
+        });
+        let counter = NativeClass::new("Counter")
+            .init(|_, args, _| Ok(Box::new(from_args::<(i64,)>("Counter", args)?.0)))
+            .method("incr", |_, args, _| {
+                let (by,) = from_args::<(i64,)>("incr", &args[1..])?;
+                with_self(args, |n: &mut i64| {
+                    *n += by;
+                    (*n).to_arc()
+                })
+            })
+            .method("__repr__", |_, args, _| with_self(args, |n: &mut i64| Obj::Str(format!("Counter({n})")).into()));
+        NativeModule::new("geometry")
+            .function("scale", |_, args, _| {
+                let (points, by) = from_args::<(Vec<f64>, f64)>("scale", args)?;
+                Ok(Obj::new_list(points.into_iter().map(|p| Obj::Float(p * by).into()).collect()).into())
+            })
+            .function("handle", |_, _, _| Ok(NativeObj::wrap(HANDLE.get().unwrap(), 7u8).into()))
+            .constant("UNIT", Obj::Int(1.into()))
+            .class(counter)
+            .class(handle.clone())
+    }
+
+    #[test]
+    fn native_modules() {
+        let code = PyBytecode::from_str(
+            "import geometry\n\
+             from geometry import Counter, scale\n\
+             c = Counter(5)\n\
+             a = [c.incr(2), c.incr(3), repr(c), str(c)]\n\
+             b = [isinstance(c, Counter), type(c) == geometry.Counter, Counter.__name__, repr(Counter)]\n\
+             d = [scale([1, 2.5], 2), geometry.UNIT, hasattr(c, 'decr')]\n\
+             e = geometry.__name__\n\
+             h = geometry.handle()\n\
+             f = [h.id(), isinstance(h, geometry.Handle)]",
+        ).unwrap();
+        let mut vm = PyVM::new();
+        vm.register_module(geometry_module());
+        vm.execute(code).unwrap();
+        let vars = &vm.get_vars()[0];
+        assert_eq!(vars["a"].__repr__(), "[7, 10, 'Counter(10)', 'Counter(10)']");
+        assert_eq!(vars["b"].__repr__(), "[True, True, 'Counter', \"<class 'geometry.Counter'>\"]");
+        assert_eq!(vars["d"].__repr__(), "[[2.0, 5.0], 1, False]");
+        assert_eq!(vars["e"].__repr__(), "'geometry'");
+        assert_eq!(vars["f"].__repr__(), "[7, True]");
+    }
+
+    #[test]
+    fn native_module_errors() {
+        let mut vm = PyVM::new();
+        vm.register_module(geometry_module());
+        let geometry = vm.import("geometry", 0, &[]).unwrap();
+        let mut call = |name: &str, args: Vec<Obj>| {
+            let func = vm.get_attr(&geometry, name).unwrap();
+            let args = args.into_iter().map(Arc::new).collect();
+            vm.call_object(func, args, vec![]).unwrap_err().msg
+        };
+        assert_eq!(call("Counter", vec![Obj::Str("a".to_string())]), "'str' object cannot be interpreted as an integer");
+        assert_eq!(call("Counter", vec![]), "Counter() takes exactly 1 argument (0 given)");
+        assert_eq!(call("Handle", vec![]), "cannot create 'geometry.Handle' instances");
+        assert_eq!(call("scale", vec![Obj::Int(1.into()), Obj::Int(2.into())]), "expected list or tuple, not int");
+        let counter = vm.get_attr(&geometry, "Counter").unwrap();
+        let counter = vm.call_object(counter, vec![Obj::Int(1.into()).into()], vec![]).unwrap();
+        assert_eq!(vm.get_attr(&counter, "decr").unwrap_err().msg, "'Counter' object has no attribute 'decr'");
+        assert_eq!(i8::from_obj(&Obj::Int(300.into())).unwrap_err().msg, "Python int too large to convert to i8");
+        assert_eq!(f64::from_obj(&Obj::None).unwrap_err().msg, "must be real number, not NoneType");
+        assert_eq!(Option::<String>::from_obj(&Obj::None).unwrap(), None);
+    }
+
+    #[test]
+    fn embedding() {
+        let mut py = PyRuntime::new();
+        py.capture_output();
+        py.set_global("n", 3.to_obj());
+        py.set_global("name", Obj::Str("pyrs".to_string()));
+        let src = "def double(x):\n    return x * 2\ntotal = double(n)\nprint(name, total)\n";
+        py.exec(src, "script.py").unwrap();
+        assert_eq!(py.take_stdout(), "pyrs 6\n");
+        assert_eq!(py.take_stdout(), "");
+        assert_eq!(py.get_global::<i64>("total").unwrap(), 6);
+        assert_eq!(py.get_global::<String>("__file__").unwrap(), "script.py");
+        assert_eq!(py.eval("total + 1").unwrap().__repr__(), "7");
+        assert_eq!(py.eval("double(n) if n > 2 else 0").unwrap().__repr__(), "6");
+        assert_eq!(py.eval("[name, None]").unwrap().__repr__(), "['pyrs', None]");
+        assert_eq!(py.call("double", &[Obj::Str("ab".to_string()).into()]).unwrap().__repr__(), "'abab'");
+        let double = py.global("double").unwrap();
+        assert_eq!(i64::from_obj(&py.call_object(&double, &[], &[("x".to_string(), 5.to_arc())]).unwrap()).unwrap(), 10);
+
+        // errors leave the runtime usable
+        assert_eq!(py.exec("total = total + 1\nundefined()\n", "b.py").unwrap_err().error, PyError::UndefinedVariableError);
+        assert_eq!(py.get_global::<i64>("total").unwrap(), 7);
+        assert_eq!(py.call("double", &[]).unwrap_err().msg, "double() missing required positional argument: 'x'");
+        assert_eq!(py.eval("total").unwrap().__repr__(), "7");
+        assert_eq!(py.take_stderr(), "");
+    }
+
+    #[test]
+    fn embedding_errors() {
+        let mut py = PyRuntime::new();
+        py.capture_output();
+        let err = |e: PyException| format!("{:?}: {}", e.error, e.msg);
+        assert_eq!(err(py.eval("x = 1").unwrap_err()), "SyntaxError: invalid syntax (<string>)");
+        assert_eq!(err(py.eval("1 / 0").unwrap_err()), "ZeroDivisionError: division by zero");
+        assert_eq!(err(py.get_global::<i64>("missing").unwrap_err()), "UndefinedVariableError: name 'missing' is not defined");
+        assert_eq!(err(py.call("missing", &[]).unwrap_err()), "UndefinedVariableError: name 'missing' is not defined");
+        py.set_global("s", Obj::Str("a".to_string()));
+        assert_eq!(err(py.get_global::<i64>("s").unwrap_err()), "TypeError: 'str' object cannot be interpreted as an integer");
+        assert_eq!(err(py.eval("s * s").unwrap_err()).split(':').next(), Some("TypeError"));
+        assert_eq!(py.exec("def f(:\n", "bad.py").unwrap_err().error, PyError::SyntaxError);
+        assert_eq!(err(py.eval("a @ b").unwrap_err()), "SyntaxError: invalid syntax at '@' (<string>)");
+        assert_eq!(err(py.exec("x = !y\n", "c.py").unwrap_err()), "SyntaxError: invalid syntax at '!' (c.py)");
+        assert_eq!(err(py.exec("x = f\"{}\"\n", "c.py").unwrap_err()).split(" in ").next(), Some("SyntaxError: f-string: valid expression required before '}'"));
+        assert_eq!(err(py.exec("(a, b) = 5\n", "c.py").unwrap_err()), "TypeError: cannot unpack non-iterable int object");
+        assert_eq!(err(py.eval("1 +").unwrap_err()), "SyntaxError: invalid syntax (<string>)");
+        assert_eq!(py.exec("x = -\n", "d.py").unwrap_err().error, PyError::SyntaxError);
+        assert_eq!(py.exec("x = 1 <\n", "d.py").unwrap_err().error, PyError::SyntaxError);
+        assert_eq!(py.exec("x = (1 *)\n", "d.py").unwrap_err().error, PyError::SyntaxError);
+        assert_eq!(py.eval("2 + 3").unwrap().__repr__(), "5");
+        assert_eq!(py.take_stdout(), "");
+    }
+
+    #[test]
+    fn sys_streams() {
+        let mut py = PyRuntime::new();
+        py.capture_output();
+        py.set_stdin("bob\nlast");
+        let src = "import sys\n\
+                   print(1, 'a', [2])\n\
+                   print('x', 'y', sep='-', end='!\\n')\n\
+                   print('no newline', end='', flush=True)\n\
+                   print(None, 1.5, sep=None, end=None)\n\
+                   class W:\n    def __init__(self):\n        self.text = ''\n    def write(self, s):\n        self.text = self.text + '[' + s + ']'\n\
+                   w = W()\n\
+                   print('a', 'b', file=w)\n\
+                   sys.stdout = w\n\
+                   print('captured')\n\
+                   sys.stdout = sys.__stdout__\n\
+                   n = sys.stdout.write('abc')\n\
+                   sys.stderr.write('oops\\n')\n\
+                   name = input('name? ')\n\
+                   last = input()\n\
+                   r = [repr(sys.stdin), sys.stdout.writable(), sys.stdin.readable()]";
+        py.exec(src, "streams.py").unwrap();
+        assert_eq!(py.take_stdout(), "1 a [2]\nx-y!\nno newlineNone 1.5\nabcname? ");
+        assert_eq!(py.take_stderr(), "oops\n");
+        assert_eq!(py.eval("w.text").unwrap().__repr__(), "'[a][ ][b][\\n][captured][\\n]'");
+        assert_eq!(py.eval("[n, name, last]").unwrap().__repr__(), "[3, 'bob', 'last']");
+        assert_eq!(
+            py.eval("r").unwrap().__repr__(),
+            "[\"<_io.TextIOWrapper name='<stdin>' mode='r' encoding='utf-8'>\", True, True]"
+        );
+
+        let err = |py: &mut PyRuntime, src: &str| {
+            let e = py.exec(src, "e.py").unwrap_err();
+            format!("{:?}: {}", e.error, e.msg)
+        };
+        assert_eq!(err(&mut py, "input()\n"), "EOFError: EOF when reading a line");
+        assert_eq!(err(&mut py, "print(1, sep=3)\n"), "TypeError: sep must be None or a string, not int");
+        assert_eq!(err(&mut py, "print(1, bogus=3)\n"), "TypeError: 'bogus' is an invalid keyword argument for print()");
+        assert_eq!(err(&mut py, "import sys\nsys.stdout.write(5)\n"), "TypeError: write() argument must be str, not int");
+        assert_eq!(err(&mut py, "import sys\nsys.stdin.write('x')\n"), "OSError: not writable");
+        assert_eq!(err(&mut py, "import sys\nsys.stdin = None\ninput()\n"), "RuntimeError: input(): lost sys.stdin");
+        assert_eq!(err(&mut py, "print(1, file=5)\n"), "AttributeError: 'int' object has no attribute 'write'");
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn file_objects() {
+        let dir = std::env::temp_dir().join(format!("pyrs_files_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut py = PyRuntime::new();
+        py.set_global("d", Obj::Str(dir.to_str().unwrap().to_string()));
+        let src = "f = open(d + '/a.txt', 'w')\n\
+                   n = f.write('one\\ntwo\\r\\nthree\\rfour')\n\
+                   f.writelines(['x\\n', 'y'])\n\
+                   f.close()\n\
+                   f = open(d + '/a.txt')\n\
+                   first = f.readline()\n\
+                   pos = f.tell()\n\
+                   rest = f.readlines()\n\
+                   f.seek(0)\n\
+                   head = f.read(5)\n\
+                   f.close()\n\
+                   raw = []\n\
+                   f = open(d + '/a.txt', newline='')\n\
+                   for line in f:\n    raw = raw + [line]\n\
+                   f.close()\n\
+                   b = open(d + '/a.txt', 'rb')\n\
+                   start = b.read(4)\n\
+                   b.seek(-3, 2)\n\
+                   end = b.read()\n\
+                   entered = b.__enter__()\n\
+                   b.__exit__(None, None, None)\n\
+                   w = open(d + '/c.txt', 'w', newline='\\r\\n')\n\
+                   wrote = [w.write('a\\nb\\n')]\n\
+                   w.close()\n\
+                   w = open(d + '/e.txt', 'w', encoding='utf-8', newline='\\r\\n')\n\
+                   wrote = wrote + [w.write('\u{e9}\\nb\\n')]\n\
+                   w.close()\n\
+                   c = open(d + '/c.txt', 'r+b')\n\
+                   cmode = c.mode\n\
+                   crlf = c.read()\n\
+                   c.seek(0)\n\
+                   c.write(b'A')\n\
+                   c.close()\n\
+                   c = open(d + '/c.txt')\n\
+                   patched = c.read()\n\
+                   c.close()\n\
+                   t = open(d + '/t.txt', 'w')\n\
+                   t.write('hello\\nworld\\n')\n\
+                   t.close()\n\
+                   t = open(d + '/t.txt', 'r+')\n\
+                   t.write('J')\n\
+                   got = t.read(3)\n\
+                   t.write('XY')\n\
+                   tpos = t.tell()\n\
+                   t.close()\n\
+                   t = open(d + '/t.txt', 'r+')\n\
+                   t.write('Q')\n\
+                   whole = t.read()\n\
+                   t.seek(0)\n\
+                   t.write('12')\n\
+                   tpos2 = t.tell()\n\
+                   again = t.read(2)\n\
+                   t.close()\n\
+                   u = open(d + '/u.bin', 'wb')\n\
+                   u.write(b'a\\xffbc\\xc3\\xa9d')\n\
+                   u.close()\n\
+                   u = open(d + '/u.bin', 'r+', errors='replace')\n\
+                   one = u.read(1)\n\
+                   upos = u.tell()\n\
+                   u.write('Z')\n\
+                   u.close()";
+        py.exec(src, "files.py").unwrap();
+        let get = |py: &mut PyRuntime, src: &str| py.eval(src).unwrap().__repr__();
+        assert_eq!(get(&mut py, "[n, first, pos, rest, head]"), "[19, 'one\\n', 4, ['two\\n', 'three\\n', 'fourx\\n', 'y'], 'one\\nt']");
+        assert_eq!(get(&mut py, "raw"), "['one\\n', 'two\\r\\n', 'three\\r', 'fourx\\n', 'y']");
+        assert_eq!(get(&mut py, "[start, end, id(entered) == id(b), b.closed, f.closed]"), "[b'one\\n', b'x\\ny', True, True, True]");
+        assert_eq!(get(&mut py, "[crlf, patched, cmode, w.mode, wrote]"), "[b'a\\r\\nb\\r\\n', 'A\\nb\\n', 'rb+', 'w', [4, 4]]");
+        // as in CPython, text r+ holds writes until tell() or reading to the
+        // end, and they land after the chunk the last read took
+        assert_eq!(get(&mut py, "[got, tpos, whole]"), "['hel', 15, 'ello\\nworld\\nJXY']");
+        assert_eq!(get(&mut py, "[tpos2, again, open(d + '/t.txt').read()]"), "[2, 'll', '12llo\\nworld\\nJXY']");
+        assert_eq!(get(&mut py, "[one, upos, open(d + '/u.bin', 'rb').read()]"), "['a', 1, b'a\\xffbc\\xc3\\xa9dZ']");
+        assert_eq!(get(&mut py, "repr(b)"), format!("\"<_io.BufferedReader name='{}/a.txt'>\"", dir.display()));
+
+        let err = |py: &mut PyRuntime, src: &str| {
+            let e = py.exec(src, "e.py").unwrap_err();
+            format!("{:?}: {}", e.error, e.msg)
+        };
+        assert_eq!(
+            err(&mut py, "open(d + '/nope.txt')\n"),
+            format!("FileNotFoundError: [Errno 2] No such file or directory: '{}/nope.txt'", dir.display())
+        );
+        assert_eq!(err(&mut py, "open(d)\n"), format!("IsADirectoryError: [Errno 21] Is a directory: '{}'", dir.display()));
+        assert_eq!(
+            err(&mut py, "open(d + '/a.txt', 'x')\n"),
+            format!("FileExistsError: [Errno 17] File exists: '{}/a.txt'", dir.display())
+        );
+        assert_eq!(err(&mut py, "open(d, 'rw')\n"), "ValueError: must have exactly one of create/read/write/append mode");
+        assert_eq!(err(&mut py, "open(d, 'rr')\n"), "ValueError: invalid mode: 'rr'");
+        assert_eq!(err(&mut py, "open(d, 'rb', encoding='utf-8')\n"), "ValueError: binary mode doesn't take an encoding argument");
+        assert_eq!(err(&mut py, "open(d, newline='x')\n"), "ValueError: illegal newline value: x");
+        assert_eq!(err(&mut py, "f.read()\n"), "ValueError: I/O operation on closed file.");
+        assert_eq!(err(&mut py, "f = open(d + '/a.txt')\nf.write('x')\n"), "OSError: not writable");
+        assert_eq!(err(&mut py, "f.seek(1, 1)\n"), "OSError: can't do nonzero cur-relative seeks");
+        assert_eq!(err(&mut py, "f = open(d + '/z.txt', 'wb')\nf.write('x')\n"), "TypeError: a bytes-like object is required, not 'str'");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn with_statement() {
+        let mut py = PyRuntime::new();
+        py.capture_output();
+        let src = "class CM:\n    def __init__(self, name, suppress):\n        self.name = name\n        self.suppress = suppress\n    \
+                   def __enter__(self):\n        print('enter', self.name)\n        return self.name\n    \
+                   def __exit__(self, typ, val, tb):\n        print('exit', self.name, typ)\n        return self.suppress\n\
+                   with CM('a', False) as x, CM('b', False) as y:\n    print(x, y)\n\
+                   def ret():\n    with CM('ret', False) as r:\n        return r + '!'\n    print('unreachable')\n\
+                   print(ret())\n\
+                   def swallowed():\n    with CM('outer', True):\n        with CM('inner', False):\n            n = 1 / 0\n        print('skipped')\n    return 'after'\n\
+                   print(swallowed())\n\
+                   def gen():\n    with CM('gen', True):\n        yield 1\n        yield 2\n    yield 3\n\
+                   it = gen()\n\
+                   first = next(it)\n\
+                   thrown = it.throw(ValueError('boom'))\n\
+                   with CM('loop', False):\n    print('body')";
+        py.exec(src, "with.py").unwrap();
+        assert_eq!(
+            py.take_stdout(),
+            "enter a\nenter b\na b\nexit b None\nexit a None\n\
+             enter ret\nexit ret None\nret!\n\
+             enter outer\nenter inner\nexit inner <class 'ZeroDivisionError'>\nexit outer <class 'ZeroDivisionError'>\nafter\n\
+             enter gen\nexit gen <class 'ValueError'>\n\
+             enter loop\nbody\nexit loop None\n"
+        );
+        assert_eq!(py.eval("[first, thrown]").unwrap().__repr__(), "[1, 3]");
+
+        let err = |py: &mut PyRuntime, src: &str| {
+            let e = py.exec(src, "e.py").unwrap_err();
+            format!("{:?}: {}", e.error, e.msg)
+        };
+        assert_eq!(err(&mut py, "with 5:\n    pass\n"), "TypeError: 'int' object does not support the context manager protocol");
+        assert_eq!(
+            err(&mut py, "class A:\n    def __enter__(self):\n        return 1\nwith A():\n    pass\n"),
+            "TypeError: 'A' object does not support the context manager protocol (missed __exit__ method)"
+        );
+        assert_eq!(err(&mut py, "with CM('raise', False):\n    x = [][1]\n"), "IndexError: list index out of range");
+        assert_eq!(py.take_stdout(), "enter raise\nexit raise <class 'IndexError'>\n");
+        assert_eq!(err(&mut py, "with :\n    pass\n").split(" (").next(), Some("SyntaxError: expected a context manager after 'with', found Op{':'}"));
+    }
+
+    #[test]
+    fn contextlib_contextmanager() {
+        let mut py = PyRuntime::new();
+        py.capture_output();
+        let src = "from contextlib import contextmanager\n\
+                   @contextmanager\n\
+                   def tag(name):\n    print('<' + name + '>')\n    yield name.upper()\n    print('</' + name + '>')\n\
+                   with tag('p') as t:\n    print(t)\n\
+                   class Sup:\n    def __enter__(self):\n        return self\n    def __exit__(self, t, v, tb):\n        print(t.__name__, str(v))\n        return True\n\
+                   @contextmanager\n\
+                   def swallow():\n    with Sup():\n        yield\n\
+                   with swallow():\n    n = 1 / 0\n\
+                   names = [tag.__name__, type(tag('x')).__name__]";
+        py.exec(src, "cm.py").unwrap();
+        assert_eq!(py.take_stdout(), "<p>\nP\n</p>\nZeroDivisionError division by zero\n");
+        assert_eq!(py.eval("names").unwrap().__repr__(), "['tag', '_GeneratorContextManager']");
+
+        let err = |py: &mut PyRuntime, src: &str| {
+            let e = py.exec(src, "e.py").unwrap_err();
+            format!("{:?}: {}", e.error, e.msg)
+        };
+        assert_eq!(err(&mut py, "with tag('q'):\n    x = int('z')\n"), "ValueError: invalid literal for int() with base 10: 'z'");
+        assert_eq!(py.take_stdout(), "<q>\n");
+        assert_eq!(
+            err(&mut py, "@contextmanager\ndef none():\n    if False:\n        yield 1\nwith none():\n    pass\n"),
+            "RuntimeError: generator didn't yield"
+        );
+        assert_eq!(
+            err(&mut py, "@contextmanager\ndef two():\n    yield 1\n    yield 2\nwith two():\n    pass\n"),
+            "RuntimeError: generator didn't stop"
+        );
+    }
+
+    #[test]
+    fn assert_and_del_statements() {
+        let mut py = PyRuntime::new();
+        let err = |py: &mut PyRuntime, src: &str| {
+            let e = py.exec(src, "e.py").unwrap_err();
+            format!("{:?}: {}", e.error, e.msg)
+        };
+        py.exec("n = 3\nassert n == 3\nassert n > 0, 'positive'\n", "a.py").unwrap();
+        assert_eq!(err(&mut py, "assert n == 4\n"), "AssertionError: ");
+        assert_eq!(err(&mut py, "def f(x):\n    assert x, f'bad {x}'\nf(0)\n"), "AssertionError: bad 0");
+        assert_eq!(py.eval("__debug__").unwrap().__repr__(), "True");
+        py.vm().set_optimize(true);
+        py.exec("assert False, 'skipped'\n", "a.py").unwrap();
+        assert_eq!(py.eval("__debug__").unwrap().__repr__(), "False");
+        py.vm().set_optimize(false);
+
+        let src = "xs = [0, 1, 2, 3, 4, 5]; del xs[0]; del xs[-1]\n\
+                   grid = [[1, 2], [3, 4]]\n\
+                   del grid[0][1], xs[::2]\n\
+                   d = {'a': 1, 'b': 2}\n\
+                   del d['a']\n\
+                   class A:\n    def __init__(self):\n        self.v = 1\n\
+                   a = A()\n\
+                   del a.v\n\
+                   z = 1\n\
+                   del z\n\
+                   def local():\n    y = 2\n    del y\n    return 'ok'\n\
+                   res = [xs, grid, len(d), hasattr(a, 'v'), local()]";
+        py.exec(src, "d.py").unwrap();
+        assert_eq!(py.eval("res").unwrap().__repr__(), "[[2, 4], [[1], [3, 4]], 1, False, 'ok']");
+        assert_eq!(err(&mut py, "z\n"), "UndefinedVariableError: No variable with name: \"z\" in current scope");
+        assert_eq!(err(&mut py, "del z\n"), "UndefinedVariableError: name 'z' is not defined");
+        assert_eq!(err(&mut py, "del d['a']\n"), "KeyError: 'a'");
+        assert_eq!(err(&mut py, "del a.v\n"), "AttributeError: 'A' object has no attribute 'v'");
+        assert_eq!(err(&mut py, "s = 'abc'\ndel s[0]\n"), "TypeError: 'str' object doesn't support item deletion");
+
+        // in place, an alias sees it and the container is evaluated once
+        let src = "ys = [0, 1, 2, 3]\nalias = ys\ndel ys[0], alias[::2]\n\
+                   calls = [0]\n\
+                   def get():\n    calls[0] += 1\n    return grid\n\
+                   del get()[0]\n\
+                   ba = bytearray(b'abcdef')\ndel ba[0]\ndel ba[-1]\ndel ba[::2]\n\
+                   res = [alias, grid, calls, ba]";
+        py.exec(src, "d.py").unwrap();
+        assert_eq!(py.eval("res").unwrap().__repr__(), "[[2], [[3, 4]], [1], bytearray(b'ce')]");
+        assert_eq!(err(&mut py, "del ba[5]\n"), "IndexError: bytearray index out of range");
+        assert_eq!(err(&mut py, "del ba['x']\n"), "TypeError: bytearray indices must be integers or slices, not str");
+        assert_eq!(err(&mut py, "del memoryview(ba)[0]\n"), "TypeError: cannot delete memory");
+    }
+
+    #[test]
+    fn one_line_blocks() {
+        let mut py = PyRuntime::new();
+        py.capture_output();
+        let src = "if False: x = 1; print('X')\n\
+                   def f(): x = 1; return x + 1\n\
+                   for i in range(2): print(i); print('-')\n\
+                   class A: pass\n\
+                   n = 0\n\
+                   if n: print('t')\n\
+                   elif n == 0: g = lambda: 5; print(g(), {1: 2}[1])\n\
+                   else: print('f')\n\
+                   if True:  # not a body\n    print('a:b;c')\n\
+                   res = [f(), A.__name__]";
+        py.exec(src, "one.py").unwrap();
+        assert_eq!(py.take_stdout(), "0\n-\n1\n-\n5 2\na:b;c\n");
+        assert_eq!(py.eval("res").unwrap().__repr__(), "[2, 'A']");
+        assert_eq!(
+            logical_lines("if c: a; b\nd"),
+            vec!["if c:", " a", " b", "d"]
+        );
+    }
+
+    #[test]
+    fn global_and_nonlocal_declarations() {
+        let mut py = PyRuntime::new();
+        let src = "count = 0\n\
+                   def bump():\n    global count\n    count += 1\n\
+                   bump(); bump()\n\
+                   def outer():\n    count = 100\n    def inner():\n        global count\n        count = count + 1\n    inner()\n    return count\n\
+                   shadowed = outer()\n\
+                   def make():\n    global made\n    made = 'yes'\n\
+                   make()\n\
+                   def counter():\n    c = 0\n    def inc():\n        nonlocal c\n        c += 1\n        return c\n    return inc\n\
+                   inc = counter()\n\
+                   inc(); inc()\n\
+                   def deep():\n    x = 1\n    def mid():\n        def leaf():\n            nonlocal x\n            x = x * 10\n        leaf()\n    mid()\n    return x\n\
+                   res = [count, shadowed, made, inc(), deep()]";
+        py.exec(src, "g.py").unwrap();
+        assert_eq!(py.eval("res").unwrap().__repr__(), "[3, 100, 'yes', 3, 10]");
+        py.exec("def drop():\n    global made\n    del made\ndrop()\n", "g.py").unwrap();
+        assert!(py.global("made").is_none());
+
+        // nonlocal needs an enclosing function that binds the name
+        let err = py.exec("def f():\n    nonlocal q\n", "n.py").unwrap_err();
+        assert_eq!(err.error, PyError::SyntaxError);
+        assert_eq!(err.msg, "no binding for nonlocal 'q' found (n.py)");
+        let err = py.exec("def f():\n    y = 1\n    class C:\n        def g(self):\n            nonlocal q\n", "n.py").unwrap_err();
+        assert!(err.msg.contains("no binding for nonlocal 'q' found"), "{}", err.msg);
+        let err = py.exec("def f():\n    global q\n    def g():\n        nonlocal q\n", "n.py").unwrap_err();
+        assert!(err.msg.contains("no binding for nonlocal 'q' found"), "{}", err.msg);
+        let err = py.exec("nonlocal q\n", "n.py").unwrap_err();
+        assert!(err.msg.contains("nonlocal declaration not allowed at module level"), "{}", err.msg);
+
+        // the declaration has to come before the scope binds or reads the name
+        let declare_err = |src: &str| Interpreter::compile_source(src).unwrap_err();
+        let err = declare_err("def f():\n    x = 1\n    global x\n");
+        assert_eq!(err.error, PyError::SyntaxError);
+        assert_eq!(err.msg, "name 'x' is assigned to before global declaration");
+        let err = declare_err("def f():\n    print(x)\n    global x\n");
+        assert_eq!(err.msg, "name 'x' is used prior to global declaration");
+        assert_eq!(declare_err("x = 1\nglobal x\n").msg, "name 'x' is assigned to before global declaration");
+        let err = declare_err("def f():\n    for x in []:\n        pass\n    if x:\n        global x\n");
+        assert_eq!(err.msg, "name 'x' is used prior to global declaration");
+        let err = declare_err("def f():\n    x = 0\n    def g():\n        x += 1\n        nonlocal x\n");
+        assert_eq!(err.msg, "name 'x' is assigned to before nonlocal declaration");
+        assert_eq!(declare_err("def f(x):\n    global x\n").msg, "name 'x' is parameter and global");
+        // keyword arguments, attributes and nested scopes aren't the scope's own names
+        Interpreter::compile_source("def f():\n    g(x=1)\n    o.x = 2\n    h = lambda: x\n    global x\n").unwrap();
+    }
+
     /*
     Usage: cargo.exe test [OPTIONS] [TESTNAME] [-- [ARGS]...]
 
@@ -1,83 +1,127 @@
-use crate::{ 
-    pyrs_bytecode::PyBytecode, pyrs_error::{PyError, PyException}, pyrs_obj::{Obj}
-};
+use crate::pyrs_obj::Obj;
 use std::{
     collections::HashMap,
-    sync::{Arc},
+    sync::{Arc, Mutex, OnceLock},
 };
 
-#[derive(Debug, Clone, PartialEq)]
+// A class made by a class statement, its attributes are the names the
+// class body assigned. Names it doesn't have are looked up on the bases
+#[derive(Debug)]
 pub struct UserClassDef {
     pub name: String,
-    pub fields: HashMap<String, (usize, Obj)>, // offset
-    pub methods: HashMap<String, Vec<PyBytecode>>,
+    pub bases: Vec<Arc<UserClassDef>>,
+    pub attrs: Mutex<HashMap<String, Arc<Obj>>>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+// Every reference to an instance shares the one attribute dict
+#[derive(Debug, Clone)]
 pub struct UserClassInstance {
     pub class: Arc<UserClassDef>,
-    pub fields: Vec<Arc<Obj>>,
+    pub fields: Arc<Mutex<HashMap<String, Arc<Obj>>>>,
+}
+
+// property(fget, fset, fdel), an attribute computed by calling fget
+#[derive(Debug, Clone, Default)]
+pub struct Property {
+    pub fget: Option<Arc<Obj>>,
+    pub fset: Option<Arc<Obj>>,
+    pub fdel: Option<Arc<Obj>>,
 }
 
 impl UserClassDef
 {
+    pub fn new(name: &str, bases: Vec<Arc<UserClassDef>>, attrs: HashMap<String, Arc<Obj>>) -> Self {
+        UserClassDef {
+            name: name.to_string(),
+            bases,
+            attrs: Mutex::new(attrs),
+        }
+    }
+
     pub fn new_instance(class: &Arc<Self>) -> UserClassInstance {
         UserClassInstance {
             class: class.clone(),
-            fields: class.default_fields() 
+            fields: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    fn default_fields(&self) -> Vec<Arc<Obj>>
-    {
-        let mut fields = vec![];
-        for _ in 0..self.fields.len() { // placeholder, construct default of type later
-            fields.push(Obj::None.into());
+    // the class's own attributes first, then each base depth first, left to right
+    pub fn lookup(&self, name: &str) -> Option<Arc<Obj>> {
+        if let Some(attr) = self.attrs.lock().unwrap().get(name) {
+            return Some(attr.clone());
         }
-        fields
+        self.bases.iter().find_map(|base| base.lookup(name))
     }
 
-    pub fn default_methods() -> HashMap<String, Vec<PyBytecode>>
-    {
-        let mut map = HashMap::new();
-        map.insert("__init__".into(), vec![PyBytecode::ReturnValue]);
-        map.insert("__str__".into(), vec![PyBytecode::ReturnValue]);
-        map
+    pub fn set_attr(&self, name: String, val: Arc<Obj>) {
+        self.attrs.lock().unwrap().insert(name, val);
     }
 
-}
-
-impl UserClassInstance
-{
-    pub fn get_field(&self, field: &String) -> Result<&Arc<Obj>, PyException>
-    {
-        if let Some((idx, _)) = self.class.fields.get(field) {
-            Ok(&self.fields[*idx])
+    // the classes its attributes are looked up in, itself and then each
+    // base depth first, left to right, a class reached twice only once
+    pub fn mro(class: &Arc<Self>) -> Vec<Arc<UserClassDef>> {
+        let mut order = vec![class.clone()];
+        for base in &class.bases {
+            for c in UserClassDef::mro(base) {
+                if !order.iter().any(|o| Arc::ptr_eq(o, &c)) {
+                    order.push(c);
+                }
+            }
         }
-        else {
-            Err(PyException{
-                error: PyError::UndefinedVariableError,
-                msg: format!("no field \'{field}\' for object {}", &self.class.name),
-            })
+        order
+    }
+
+    // the names of its attributes and its bases', for dir()
+    pub fn attr_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.attrs.lock().unwrap().keys().cloned().collect();
+        for base in &self.bases {
+            names.extend(base.attr_names());
         }
-    } 
+        names
+    }
+
+    pub fn remove_attr(&self, name: &str) -> Option<Arc<Obj>> {
+        self.attrs.lock().unwrap().remove(name)
+    }
 }
 
+// object, the base of every class. A class statement without bases
+// doesn't list it, and it has no attributes of its own
+pub fn object_class() -> Arc<UserClassDef> {
+    static OBJECT: OnceLock<Arc<UserClassDef>> = OnceLock::new();
+    OBJECT.get_or_init(|| Arc::new(UserClassDef::new("object", vec![], HashMap::new()))).clone()
+}
 
-// class <name>:
-// \t def __init__(self):
-// \t\t self.x = 0
-// \t\t self.y = 1
+pub fn is_object(class: &Arc<UserClassDef>) -> bool {
+    Arc::ptr_eq(class, &object_class())
+}
 
-/*
+impl UserClassInstance
+{
+    pub fn get_field(&self, field: &str) -> Option<Arc<Obj>> {
+        self.fields.lock().unwrap().get(field).cloned()
+    }
 
-What to implement:
-    - default func impls (in bytecode)
+    pub fn set_field(&self, field: String, val: Arc<Obj>) {
+        self.fields.lock().unwrap().insert(field, val);
+    }
 
-    basically i can make a class a instruction addr,
-    fields an instance a hashmap
-    access with . operator 
+    pub fn remove_field(&self, field: &str) -> Option<Arc<Obj>> {
+        self.fields.lock().unwrap().remove(field)
+    }
+}
 
+impl Property
+{
+    pub fn with_getter(&self, fget: Arc<Obj>) -> Property {
+        Property { fget: Some(fget), ..self.clone() }
+    }
 
-*/
+    pub fn with_setter(&self, fset: Arc<Obj>) -> Property {
+        Property { fset: Some(fset), ..self.clone() }
+    }
 
+    pub fn with_deleter(&self, fdel: Arc<Obj>) -> Property {
+        Property { fdel: Some(fdel), ..self.clone() }
+    }
+}
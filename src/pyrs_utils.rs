@@ -57,65 +57,25 @@ pub fn split_to_words(sentence: &str) -> Vec<&str> {
 
             // Handle string literals
             '"' | '\'' => {
-                let quote_char = ch;
-                let mut end_idx = start_idx + ch.len_utf8();
-
-                // Find the closing quote
-                while let Some((idx, c)) = chars.next() {
-                    end_idx = idx + c.len_utf8();
-                    if c == quote_char {
-                        break;
-                    }
-                }
+                let end_idx = string_end(&mut chars, sentence, start_idx);
                 words.push(&sentence[start_idx..end_idx]);
             }
 
             '!' | '=' | '<' | '>' | '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' => {
-                if let Some(&(_, next_ch)) = chars.peek() {
-                    if next_ch == '=' {
-                        chars.next();
-                        let end_idx = start_idx + ch.len_utf8() + next_ch.len_utf8();
-                        words.push(&sentence[start_idx..end_idx]);
-                    } else {
-                        let end_idx = start_idx + ch.len_utf8();
-                        words.push(&sentence[start_idx..end_idx]);
-                    }
-                } else {
-                    words.push(&sentence[start_idx..start_idx + ch.len_utf8()]);
+                let mut end_idx = start_idx + 1;
+                // ** and //, then the = of an augmented assignment
+                if matches!(ch, '*' | '/') && chars.next_if(|&(_, c)| c == ch).is_some() {
+                    end_idx += 1;
                 }
-            }
-
-            c if c.is_numeric() => {
-                let mut end_idx = start_idx + c.len_utf8();
-                let mut has_dot = false;
-
-                while let Some(&(idx, next_ch)) = chars.peek() {
-                    if next_ch.is_numeric() {
-                        chars.next();
-                        end_idx = idx + next_ch.len_utf8();
-                    } else if next_ch == '.' && !has_dot {
-                        // Look ahead to see if there's a digit after the dot
-                        let mut temp_chars = chars.clone();
-                        temp_chars.next(); // consume the dot
-                        if let Some(&(_, char_after_dot)) = temp_chars.peek() {
-                            if char_after_dot.is_numeric() {
-                                // It's a float like 3.14
-                                chars.next();
-                                end_idx = idx + next_ch.len_utf8();
-                                has_dot = true;
-                            } else {
-                                // Dot is not followed by a number, stop here
-                                break;
-                            }
-                        } else {
-                            // Dot at end of input, stop here
-                            break;
-                        }
-                    } else {
-                        break;
-                    }
+                if chars.next_if(|&(_, c)| c == '=').is_some() {
+                    end_idx += 1;
                 }
+                words.push(&sentence[start_idx..end_idx]);
+            }
 
+            c if c.is_ascii_digit() => {
+                let end_idx = start_idx + number_len(&sentence[start_idx..]);
+                while chars.next_if(|&(idx, _)| idx < end_idx).is_some() {}
                 words.push(&sentence[start_idx..end_idx]);
             }
 
@@ -131,6 +91,14 @@ pub fn split_to_words(sentence: &str) -> Vec<&str> {
                     }
                 }
 
+                // r"...", b'...' and the like, the prefix is part of the literal
+                if is_string_prefix(&sentence[start_idx..end_idx]) {
+                    if let Some(&(quote_idx, '"' | '\'')) = chars.peek() {
+                        chars.next();
+                        end_idx = string_end(&mut chars, sentence, quote_idx);
+                    }
+                }
+
                 words.push(&sentence[start_idx..end_idx]);
             }
 
@@ -138,6 +106,13 @@ pub fn split_to_words(sentence: &str) -> Vec<&str> {
                 words.push(&sentence[start_idx..start_idx + c.len_utf8()]);
             }
 
+            // .5 is a float
+            '.' if chars.peek().is_some_and(|&(_, c)| c.is_ascii_digit()) => {
+                let end_idx = start_idx + number_len(&sentence[start_idx..]);
+                while chars.next_if(|&(idx, _)| idx < end_idx).is_some() {}
+                words.push(&sentence[start_idx..end_idx]);
+            }
+
             // Handle standalone dot
             '.' => {
                 words.push(&sentence[start_idx..start_idx + 1]);
@@ -152,3 +127,304 @@ pub fn split_to_words(sentence: &str) -> Vec<&str> {
     //dbg!(&words);
     words
 }
+
+// The length of the number literal s starts with: 0xff, 1_000, 1.5e-3, .5, 1.
+// or 3j. Letters and underscores after a 0x, 0o or 0b are taken along so a
+// bad digit is reported when the literal is parsed
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let digits = |mut i: usize| {
+        while bytes.get(i).is_some_and(|b| b.is_ascii_digit() || *b == b'_') {
+            i += 1;
+        }
+        i
+    };
+    if bytes.len() > 1 && bytes[0] == b'0' && matches!(bytes[1], b'x' | b'X' | b'o' | b'O' | b'b' | b'B') {
+        let mut i = 2;
+        while bytes.get(i).is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_') {
+            i += 1;
+        }
+        return i;
+    }
+    let mut i = digits(0);
+    // 1.real would be an attribute, but 1.e5 and 1.j are numbers
+    if bytes.get(i) == Some(&b'.') {
+        match bytes.get(i + 1) {
+            Some(b'e' | b'E' | b'j' | b'J') => i += 1,
+            Some(b) if b.is_ascii_alphabetic() || *b == b'_' || *b == b'.' => {}
+            _ => i = digits(i + 1),
+        }
+    }
+    if matches!(bytes.get(i), Some(b'e' | b'E')) {
+        let sign = matches!(bytes.get(i + 1), Some(b'+' | b'-')) as usize;
+        match bytes.get(i + 1 + sign) {
+            Some(b) if b.is_ascii_digit() => i = digits(i + 1 + sign),
+            // 1else is 1 and a name, 1e and 1e+ are an exponent without digits
+            Some(b) if sign == 0 && (b.is_ascii_alphabetic() || *b == b'_') => {}
+            _ => i += 1 + sign,
+        }
+    }
+    if matches!(bytes.get(i), Some(b'j' | b'J')) {
+        i += 1;
+    }
+    i
+}
+
+// 1_000 is 1000, an underscore has to sit between two digits
+pub fn strip_underscores(digits: &str, radix: u32) -> Option<String> {
+    let chars: Vec<char> = digits.chars().collect();
+    let is_digit = |i: Option<usize>| i.and_then(|i| chars.get(i)).is_some_and(|c| c.is_digit(radix));
+    for (i, c) in chars.iter().enumerate() {
+        if *c == '_' && !(is_digit(i.checked_sub(1)) && is_digit(Some(i + 1))) {
+            return None;
+        }
+    }
+    Some(digits.replace('_', ""))
+}
+
+pub fn is_string_prefix(word: &str) -> bool {
+    matches!(
+        word.to_ascii_lowercase().as_str(),
+        "r" | "u" | "b" | "f" | "br" | "rb" | "fr" | "rf"
+    )
+}
+
+// the prefix and the quoted part of a string literal word, None for other words
+pub fn split_string_prefix(word: &str) -> Option<(&str, &str)> {
+    let quote_idx = word.find(['"', '\''])?;
+    let (prefix, quoted) = word.split_at(quote_idx);
+    (prefix.is_empty() || is_string_prefix(prefix)).then_some((prefix, quoted))
+}
+
+// The index just past the string literal whose opening quote was the last
+// char taken from chars. A backslash always takes the char after it along,
+// even in raw strings, so an escaped quote doesn't close the literal.
+// Unterminated literals run to the end of the sentence
+fn string_end(
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+    sentence: &str,
+    quote_idx: usize,
+) -> usize {
+    let quote = &sentence[quote_idx..quote_idx + 1];
+    let closing = match sentence[quote_idx..].starts_with(&quote.repeat(3)) {
+        true => {
+            chars.next();
+            chars.next();
+            quote.repeat(3)
+        }
+        false => quote.to_string(),
+    };
+    while let Some((idx, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if sentence[idx..].starts_with(&closing) {
+            for _ in 1..closing.len() {
+                chars.next();
+            }
+            return idx + closing.len();
+        }
+    }
+    sentence.len()
+}
+
+// the statements whose header ends in ':' and can have their body on the same line
+fn is_compound_header(line: &str) -> bool {
+    let word: String = line.trim_start().chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+    matches!(
+        word.as_str(),
+        "if" | "elif" | "else" | "while" | "for" | "def" | "class" | "with"
+    )
+}
+
+// Splits source into logical lines: drops comments and keeps a string
+// literal that runs over several physical lines together on one line.
+// a; b is two lines at the same indent, and the body of if c: a; b goes
+// on the lines after the header, one space deeper
+pub fn logical_lines(input: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    // the closing quotes of the string we are in
+    let mut closing: Option<String> = None;
+    // open brackets, and the lambdas whose ':' is still to come, on this line
+    let mut depth = 0usize;
+    let mut lambdas = 0usize;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, &closing) {
+            ('\n', None) => {
+                depth = 0;
+                lambdas = 0;
+                lines.push(std::mem::take(&mut line));
+            }
+            ('(' | '[' | '{', None) => {
+                depth += 1;
+                line.push(c);
+            }
+            (')' | ']' | '}', None) => {
+                depth = depth.saturating_sub(1);
+                line.push(c);
+            }
+            (':', None) if depth == 0 && lambdas > 0 => {
+                lambdas -= 1;
+                line.push(c);
+            }
+            (':', None) if depth == 0 && is_compound_header(&line) => {
+                line.push(c);
+                let rest: String = chars.clone().take_while(|&c| c != '\n').collect();
+                let rest = rest.trim();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+                    lines.push(std::mem::replace(&mut line, indent + " "));
+                    while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+                }
+            }
+            (';', None) => {
+                let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+                lines.push(std::mem::replace(&mut line, indent));
+                while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            }
+            ('#', None) => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            ('"' | '\'', None) => {
+                line.push(c);
+                let triple: String = [c, c].iter().collect();
+                let rest: String = chars.clone().take(2).collect();
+                closing = match rest == triple {
+                    true => {
+                        line.push_str(&triple);
+                        chars.nth(1);
+                        Some(c.to_string().repeat(3))
+                    }
+                    false => Some(c.to_string()),
+                };
+            }
+            ('\\', Some(_)) => {
+                line.push(c);
+                if let Some(escaped) = chars.next() {
+                    line.push(escaped);
+                }
+            }
+            // a single quoted string can't go past the end of the line
+            ('\n', Some(quotes)) if quotes.len() == 1 => {
+                closing = None;
+                lines.push(std::mem::take(&mut line));
+            }
+            (_, Some(quotes)) if quotes.starts_with(c) => {
+                let rest: String = chars.clone().take(quotes.len() - 1).collect();
+                line.push(c);
+                if quotes[1..] == rest {
+                    line.push_str(&rest);
+                    for _ in 0..rest.len() {
+                        chars.next();
+                    }
+                    closing = None;
+                }
+            }
+            (_, None) if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                if word == "lambda" {
+                    lambdas += 1;
+                }
+                line.push_str(&word);
+            }
+            _ => line.push(c),
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    for line in lines.iter_mut() {
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    lines
+}
+
+// Decodes the backslash escapes in the body of a str or bytes literal into
+// its chars, or its bytes for a bytes literal. Escapes python doesn't know
+// are left as they are, \u, \U and \N are only escapes in a str
+pub fn unescape(body: &str, is_bytes: bool) -> Result<Vec<u32>, String> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if is_bytes && !c.is_ascii() {
+            return Err("bytes can only contain ASCII literal characters".to_string());
+        }
+        if c != '\\' {
+            out.push(c as u32);
+            continue;
+        }
+        let Some(escape) = chars.next() else {
+            out.push('\\' as u32);
+            break;
+        };
+        let code = match escape {
+            '\n' => continue,
+            '\\' | '\'' | '"' => escape as u32,
+            'a' => 0x07,
+            'b' => 0x08,
+            'f' => 0x0c,
+            'n' => 0x0a,
+            'r' => 0x0d,
+            't' => 0x09,
+            'v' => 0x0b,
+            '0'..='7' => {
+                let mut code = escape.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            code = code * 8 + digit;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                // bytes wrap like CPython does, '\777' is 511 but b'\777' is 255
+                match is_bytes {
+                    true => code & 0xff,
+                    false => code,
+                }
+            }
+            'x' => hex_escape(&mut chars, 2, "\\xXX")?,
+            'u' if !is_bytes => hex_escape(&mut chars, 4, "\\uXXXX")?,
+            'U' if !is_bytes => {
+                let code = hex_escape(&mut chars, 8, "\\UXXXXXXXX")?;
+                if char::from_u32(code).is_none() {
+                    return Err("illegal Unicode character".to_string());
+                }
+                code
+            }
+            'N' if !is_bytes => {
+                if chars.next() != Some('{') {
+                    return Err("malformed \\N character escape".to_string());
+                }
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                match unicode_names2::character(&name) {
+                    Some(c) => c as u32,
+                    None => return Err("unknown Unicode character name".to_string()),
+                }
+            }
+            other => {
+                out.push('\\' as u32);
+                other as u32
+            }
+        };
+        out.push(code);
+    }
+    Ok(out)
+}
+
+fn hex_escape(chars: &mut std::iter::Peekable<std::str::Chars>, len: usize, form: &str) -> Result<u32, String> {
+    let digits: String = (0..len).map_while(|_| chars.next_if(char::is_ascii_hexdigit)).collect();
+    match digits.len() == len {
+        true => Ok(u32::from_str_radix(&digits, 16).unwrap()),
+        false => Err(format!("truncated {form} escape")),
+    }
+}
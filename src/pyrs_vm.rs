@@ -1,20 +1,52 @@
 use std::{
     boxed::Box,
     collections::HashMap,
-    io::{self, Write},
-    sync::Arc,
+    sync::{Arc, Mutex},
     usize,
 };
 
 use crate::{
+    pyrs_builtins as builtins,
     pyrs_bytecode::PyBytecode,
+    pyrs_bytes as bytes,
+    pyrs_codeobject::{CodeObject, UserFunc},
+    pyrs_decimal as decimal,
+    pyrs_embed::{InputSource, OutputSink},
     pyrs_error::{PyError, PyException},
+    pyrs_format::{self, FieldAccess, FieldArg, FieldNumbering, FormatSpec, PercentPart, TemplatePart},
+    pyrs_fractions as fractions,
+    pyrs_functools::{self as functools, LruCache},
+    pyrs_generator::{Generator, GeneratorRef, GeneratorState, GeneratorStep},
+    pyrs_import::{self as import, ImportState, PyModule},
+    pyrs_io,
     pyrs_obj::{Obj, PyObj, ToObj},
     pyrs_parsing::Op,
-    pyrs_std::RangeObj,
-    pyrs_userclass::UserClassDef,
+    pyrs_std::{Funcs, NativeFunc},
+    pyrs_str,
+    pyrs_userclass::{self as userclass, UserClassDef},
 };
 
+// Where to go back to when the frame on top of the call stack returns,
+// generator frames also keep hold of the generator they were resumed from
+#[derive(Debug, Clone)]
+struct CallFrame {
+    return_code: Arc<CodeObject>,
+    return_addr: usize,
+    return_globals: Option<Arc<PyModule>>,
+    generator: Option<GeneratorRef>,
+}
+
+// A with block being run: the call stack depth of the frame it's in, where
+// that frame carries on after it, how high its stack was when the block
+// was entered and the manager's bound __exit__
+#[derive(Debug, Clone)]
+pub(crate) struct WithBlock {
+    pub depth: usize,
+    pub handler: usize,
+    pub stack_len: usize,
+    pub exit: Arc<Obj>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct PyVM {
@@ -22,19 +54,27 @@ pub struct PyVM {
     var_maps: Vec<HashMap<String, Arc<Obj>>>,
     curr_namespace: String,
 
-    funcs: HashMap<String, usize>,
+    // the module the running code comes from, None for the script
+    // itself whose globals are var_maps[0]
+    globals: Option<Arc<PyModule>>,
+    imports: ImportState,
+
     local_stacks: Vec<Vec<Arc<Obj>>>,
+    call_stack: Vec<CallFrame>,
+    with_blocks: Vec<WithBlock>,
 
     cache_vec: Box<[Arc<Obj>; 64]>,
     cache_ptr: usize,
 
-    class_defs: HashMap<String, Arc<UserClassDef>>,
-
-    instruction_queue: Vec<PyBytecode>,
+    code: Arc<CodeObject>,
     instruction_counter: usize,
     error_state: bool,
+    // popping an empty stack, see PyVM::pop
+    stack_fault: Option<Box<PyException>>,
 
     debug_mode: bool,
+    // -O, __debug__ is False and asserts are skipped
+    optimize: bool,
 
     null_obj: Arc<Obj>,
 }
@@ -44,17 +84,21 @@ impl PyVM {
     pub fn new() -> Self {
         PyVM {
             global_vars: HashMap::new(),
-            var_maps: vec![HashMap::new()],
+            var_maps: vec![HashMap::from([("__name__".to_string(), Obj::Str("__main__".to_string()).into())])],
             curr_namespace: String::from(""),
-            funcs: HashMap::new(),
+            globals: None,
+            imports: ImportState::default(),
             local_stacks: vec![Vec::new()],
+            call_stack: vec![],
+            with_blocks: vec![],
             cache_vec: Box::new(core::array::from_fn(|_| Arc::new(Obj::default()))),
             cache_ptr: 0,
-            class_defs: HashMap::new(),
-            instruction_queue: vec![],
+            code: Arc::new(CodeObject::new("<module>", vec![])),
             instruction_counter: 0,
             error_state: false,
+            stack_fault: None,
             debug_mode: false,
+            optimize: false,
             null_obj: Obj::Null.into(),
         }
     }
@@ -63,27 +107,112 @@ impl PyVM {
         self.debug_mode = debug;
     }
 
-    pub fn execute(&mut self, queue: Vec<PyBytecode>) {
-        self.instruction_queue = queue;
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    // __file__ of the script, its directory goes first on sys.path
+    pub fn set_script_path(&mut self, filepath: &str) {
+        let dir = match std::path::Path::new(filepath).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => std::path::Path::new("."),
+        };
+        let dir = dir.canonicalize().unwrap_or(dir.to_path_buf());
+        self.imports.set_script_dir(&dir);
+        self.var_maps[0].insert("__file__".to_string(), Obj::Str(filepath.to_string()).into());
+    }
+
+    // An uncaught error is handed back with the frames it was raised in
+    // dropped, in debug mode where it happened is printed first
+    pub fn execute(&mut self, queue: Vec<PyBytecode>) -> Result<(), PyException> {
+        self.code = Arc::new(CodeObject::new("<module>", queue));
+        self.instruction_counter = 0;
         if self.debug_mode {
             self.print_instruction_queue();
         }
-        while let Some(instruction) = self.instruction_queue.get(self.instruction_counter) {
-            self.execute_instruction(instruction.clone());
+        let result = self.run(0);
+        if let Err(e) = &result {
+            if self.debug_mode {
+                self.print_error(e);
+            }
+            self.reset_frames();
         }
+        result
     }
 
-    fn execute_instruction(&mut self, inst: PyBytecode) {
-        if inst == PyBytecode::NOP {
-            self.instruction_counter += 1;
-            return;
+    // the value an expression left on the script's stack
+    pub(crate) fn take_result(&mut self) -> Option<Arc<Obj>> {
+        self.local_stacks[0].pop()
+    }
+
+    // back to the script's top level with nothing on its stack
+    pub(crate) fn reset_frames(&mut self) {
+        self.unwind(0);
+        self.with_blocks.clear();
+        self.error_state = false;
+        self.stack_fault = None;
+        self.local_stacks.truncate(1);
+        self.local_stacks[0].clear();
+        self.var_maps.truncate(1);
+        self.globals = None;
+    }
+
+    // Swaps the streams sys.stdout, sys.stderr and sys.stdin start as, for
+    // a host embedding the VM to capture output and feed it input
+    pub fn set_stdout(&mut self, sink: OutputSink) {
+        self.imports.set_std_stream("stdout", pyrs_io::writer("<stdout>", sink));
+    }
+
+    pub fn set_stderr(&mut self, sink: OutputSink) {
+        self.imports.set_std_stream("stderr", pyrs_io::writer("<stderr>", sink));
+    }
+
+    pub fn set_stdin(&mut self, source: InputSource) {
+        self.imports.set_std_stream("stdin", pyrs_io::reader("<stdin>", source));
+    }
+
+    // Runs until the frame at call stack depth `depth` returns or yields,
+    // errors inside nested runs unwind back to that frame and are returned
+    fn run(&mut self, depth: usize) -> Result<(), PyException> {
+        loop {
+            if self.error_state {
+                let e = self.take_err();
+                if self.with_blocks.last().is_some_and(|block| block.depth >= depth) {
+                    self.exit_with_error(e);
+                    continue;
+                }
+                // the top level keeps its frames for the caller to report
+                if depth > 0 {
+                    self.unwind(depth);
+                }
+                return Err(e);
+            }
+            if self.call_stack.len() < depth {
+                return Ok(());
+            }
+
+            let Some(instruction) = self.code.co_code.get(self.instruction_counter).cloned() else {
+                if self.call_stack.is_empty() {
+                    return Ok(());
+                }
+                // falling off the end of a function returns None
+                self.push(Obj::None.into());
+                self.return_value();
+                continue;
+            };
+            self.execute_instruction(instruction);
         }
+    }
 
+    fn execute_instruction(&mut self, inst: PyBytecode) {
         if self.debug_mode {
             println!("Executing: ({})   {:?}", self.instruction_counter, inst);
             self.print_stack();
         }
 
+        // jumps are relative to the next instruction
+        self.instruction_counter += 1;
+
         match inst {
             PyBytecode::PopTop => self.pop_top(),
             PyBytecode::EndFor => self.end_for(),
@@ -93,13 +222,35 @@ impl PyVM {
             PyBytecode::StoreFast(i) => self.store_fast(i),
             PyBytecode::LoadName(name) => self.load_name(name),
             PyBytecode::StoreName(name) => self.store_name(name),
+            PyBytecode::DeleteName(name) => self.delete_name(name),
+            PyBytecode::LoadGlobal(name) => self.load_global(name),
+            PyBytecode::StoreGlobal(name) => self.store_global(name),
+            PyBytecode::DeleteGlobal(name) => self.delete_global(name),
+            PyBytecode::LoadAttr(name) => self.load_attr(name),
+            PyBytecode::StoreAttr(name) => self.store_attr(name),
+            PyBytecode::DeleteAttr(name) => self.delete_attr(name),
+            PyBytecode::ImportName(name) => self.import_name(name),
+            PyBytecode::ImportFrom(name) => self.import_from(name),
+            PyBytecode::ImportStar => self.import_star(),
+            PyBytecode::Copy(i) => self.copy(i),
+            PyBytecode::Swap(i) => self.swap(i),
 
             PyBytecode::PushNull => self.push_null(),
 
             PyBytecode::BuildList(len) => self.build_list(len),
             PyBytecode::BuildTuple(count) => self.build_tuple(count),
+            PyBytecode::BuildSet(count) => self.build_set(count),
+            PyBytecode::BuildMap(count) => self.build_map(count),
+            PyBytecode::BuildString(count) => self.build_string(count),
+            PyBytecode::FormatValue(flags) => self.format_value(flags),
+            PyBytecode::ListAppend(i) => self.list_append(i),
+            PyBytecode::SetAdd(i) => self.set_add(i),
+            PyBytecode::MapAdd(i) => self.map_add(i),
+            PyBytecode::ListExtend(i) => self.list_extend(i),
+            PyBytecode::DictMerge(i) => self.dict_merge(i),
 
             PyBytecode::GetIter => self.get_iter(),
+            PyBytecode::GetYieldFromIter => self.get_yield_from_iter(),
             PyBytecode::ForIter(delta) => self.for_iter(delta),
             PyBytecode::UnpackSequence => self.unpack_sequence(),
 
@@ -107,17 +258,28 @@ impl PyVM {
             PyBytecode::BinarySubtract => self.binary_subtract(),
             PyBytecode::BinaryMultiply => self.binary_multiply(),
             PyBytecode::BinaryDivide => self.binary_divide(),
+            PyBytecode::BinaryOp(op) => self.binary_op(op),
+            PyBytecode::BinarySubscr => self.binary_subscr(),
+            PyBytecode::StoreSubscr => self.store_subscr(),
+            PyBytecode::DeleteSubscr => self.delete_subscr(),
+            PyBytecode::BuildSlice(count) => self.build_slice(count),
 
             PyBytecode::UnaryNegative => self.unary_negative(),
 
             PyBytecode::CallFunction(argc) => self.call_function(argc),
-            PyBytecode::CallInstrinsic1(ptr) => self.call_intrinsic_1(ptr),
+            PyBytecode::CallFunctionKw(argc) => self.call_function_kw(argc),
+            PyBytecode::CallFunctionEx(flags) => self.call_function_ex(flags),
             PyBytecode::ReturnValue => self.return_value(),
+            PyBytecode::YieldValue => self.yield_value(),
+            PyBytecode::Send(delta) => self.send(delta),
 
             PyBytecode::PopJumpIfFalse(delta) => self.pop_jump_if_false(delta),
             PyBytecode::PopJumpIfTrue(delta) => self.pop_jump_if_true(delta),
             PyBytecode::JumpForward(delta) => self.jump_forward(delta),
             PyBytecode::JumpBackward(delta) => self.jump_backward(delta),
+            PyBytecode::SetupWith(delta) => self.setup_with(delta),
+            PyBytecode::ExitWith => self.exit_with(),
+            PyBytecode::RaiseAssertion(argc) => self.raise_assertion(argc),
 
             PyBytecode::CompareOp(op) => self.compare_op(op),
 
@@ -126,14 +288,11 @@ impl PyVM {
             PyBytecode::DestroyStack => self.pop_stack(),
 
             PyBytecode::LoadBuildClass => self.load_build_class(),
+            PyBytecode::LoadLocals => self.load_locals(),
 
             PyBytecode::NOP => {}
-            _ => panic!("Instruction {:?} not implemented ", inst),
+            _ => self.stack_err(format!("instruction {inst:?} is not implemented")),
         }
-        if self.error_state {
-            self.throw();
-        }
-        self.instruction_counter += 1;
     }
 
     pub fn get_vars(&self) -> &Vec<HashMap<String, Arc<Obj>>> {
@@ -152,53 +311,92 @@ impl PyVM {
         self.error_state = true;
     }
 
+    // bytecode the compiler doesn't emit
+    fn stack_err(&mut self, msg: String) {
+        self.push_err(PyException {
+            error: PyError::StackError,
+            msg,
+        });
+    }
+
+    fn take_err(&mut self) -> PyException {
+        self.error_state = false;
+        if let Some(e) = self.stack_fault.take() {
+            return *e;
+        }
+        match self.get_local_stack_mut().pop().as_deref() {
+            Some(Obj::Except(e)) => e.clone(),
+            _ => PyException {
+                error: PyError::StackError,
+                msg: "error state set without an exception on the stack".to_string(),
+            },
+        }
+    }
+
+    // pops frames until the one at `depth` is gone, generators that were
+    // running in them can't be resumed again
+    fn unwind(&mut self, depth: usize) {
+        while self.call_stack.len() >= depth {
+            let Some(frame) = self.call_stack.pop() else {
+                break;
+            };
+            self.local_stacks.pop();
+            self.var_maps.pop();
+            if let Some(gen) = frame.generator {
+                gen.lock().unwrap().state = GeneratorState::Finished;
+            }
+            self.code = frame.return_code;
+            self.instruction_counter = frame.return_addr;
+            self.globals = frame.return_globals;
+        }
+    }
+
     fn print_debug_info(&self) {
         self.print_instruction_queue();
-        println!(
-            "Curr Instruction: \n({}) \t{}",
-            self.instruction_counter, self.instruction_queue[self.instruction_counter]
-        );
+        println!("Curr Instruction: ");
+        self.print_instruction(self.instruction_counter.saturating_sub(1));
 
         println!("\nStack Trace: ");
         self.print_stack();
 
         println!("\nVariableMaps: ");
-        self.get_vars();
+        self.print_var_maps();
     }
 
-    fn throw(&mut self) {
-        let e = self.pop();
+    fn print_error(&self, e: &PyException) {
         println!();
         println!("---- PyVM Error ---- ");
 
         println!(
-            "Error: at bytecode instruction {}",
-            self.instruction_counter
+            "Error: at bytecode instruction {} in {}",
+            self.instruction_counter.saturating_sub(1),
+            self.code.co_name
         );
-        self.print_instruction(self.instruction_counter);
         println!("{e}");
 
         self.print_debug_info();
-
         println!();
-        panic!("\n ^^^ PyVM Error Thrown ^^^ \n");
     }
 
     fn push(&mut self, obj: Arc<Obj>) {
         self.local_stacks.last_mut().unwrap().push(obj);
     }
 
+    // An empty stack isn't how the compiler leaves it, which valid code
+    // can't do. The instruction gets Null and the error is raised once it
+    // is done, ahead of anything the instruction raised itself
     fn pop(&mut self) -> Arc<Obj> {
         match self.local_stacks.last_mut().unwrap().pop() {
             Some(obj) => obj,
             None => {
-                let e = PyException {
-                    error: PyError::StackError,
-                    msg: "Tried to pop empty stack".to_string(),
-                };
-                self.push_err(e);
-                self.throw();
-                unreachable!();
+                self.stack_fault.get_or_insert_with(|| {
+                    Box::new(PyException {
+                        error: PyError::StackError,
+                        msg: "Tried to pop empty stack".to_string(),
+                    })
+                });
+                self.error_state = true;
+                self.null_obj.clone()
             }
         }
     }
@@ -211,12 +409,122 @@ impl PyVM {
         return self.var_maps.last_mut().unwrap();
     }
 
+    // the globals of the module the running code was defined in
+    pub(crate) fn lookup_global(&self, name: &str) -> Option<Arc<Obj>> {
+        match &self.globals {
+            Some(module) => module.get_attr(name),
+            None => self.get_global_vars().get(name).cloned(),
+        }
+    }
+
+    // the variables globals() gives, with the values of cells rather than the cells
+    pub(crate) fn global_names(&self) -> HashMap<String, Arc<Obj>> {
+        let vars = match &self.globals {
+            Some(module) => module.attrs.lock().unwrap().clone(),
+            None => self.get_global_vars().clone(),
+        };
+        PyVM::cell_values(vars)
+    }
+
+    // and locals(), at the top level of a module they are its globals
+    pub(crate) fn local_names(&self) -> HashMap<String, Arc<Obj>> {
+        match self.in_function() {
+            true => PyVM::cell_values(self.get_local_vars().clone()),
+            false => self.global_names(),
+        }
+    }
+
+    // stores them where the running code's global names are
+    pub(crate) fn set_global_names(&mut self, names: HashMap<String, Arc<Obj>>) {
+        match &self.globals {
+            Some(module) => module.attrs.lock().unwrap().extend(names),
+            None => self.var_maps[0].extend(names),
+        }
+    }
+
+    pub(crate) fn in_function(&self) -> bool {
+        self.module_body().is_none() && self.var_maps.len() > 1
+    }
+
+    // a cell that hasn't been assigned yet isn't a variable yet
+    fn cell_values(vars: HashMap<String, Arc<Obj>>) -> HashMap<String, Arc<Obj>> {
+        vars.into_iter()
+            .map(|(name, val)| match val.as_ref() {
+                Obj::Cell(cell) => (name, cell.lock().unwrap().clone()),
+                _ => (name, val),
+            })
+            .filter(|(_, val)| **val != Obj::Null)
+            .collect()
+    }
+
+    // What zero-argument super() in a method stands for: the method's first
+    // argument, and the class in its type's that has the running code
+    // among its functions
+    pub(crate) fn super_args(&self) -> Result<(Arc<UserClassDef>, Arc<Obj>), PyException> {
+        let runtime_error = |msg: &str| PyException {
+            error: PyError::RuntimeError,
+            msg: msg.to_string(),
+        };
+        let first = match (self.call_stack.is_empty(), self.code.arg_names().first()) {
+            (false, Some(name)) => self.get_local_vars().get(name).cloned(),
+            _ => None,
+        };
+        let Some(first) = first else {
+            return Err(runtime_error("super(): no arguments"));
+        };
+        let first = match first.as_ref() {
+            Obj::Cell(cell) => cell.lock().unwrap().clone(),
+            _ => first,
+        };
+        let class = match first.as_ref() {
+            Obj::Class(instance) => Some(instance.class.clone()),
+            Obj::ClassDef(class) => Some(class.clone()),
+            _ => None,
+        };
+        class
+            .and_then(|class| UserClassDef::mro(&class).into_iter().find(|c| builtins::defines(c, &self.code)))
+            .map(|class| (class, first))
+            .ok_or_else(|| runtime_error("super(): __class__ cell not found"))
+    }
+
+    // the module whose top level code is running, if it isn't the script's
+    pub(crate) fn module_body(&self) -> Option<Arc<PyModule>> {
+        self.globals.clone().filter(|_| self.code.co_name == "<module>")
+    }
+
+    pub(crate) fn import_state(&mut self) -> &mut ImportState {
+        &mut self.imports
+    }
+
+    // runs a module's code on a new frame with the module as its globals,
+    // it gives what the code returns
+    pub(crate) fn exec_module(&mut self, code: Vec<PyBytecode>, module: &Arc<PyModule>) -> Result<Arc<Obj>, PyException> {
+        let code = Arc::new(CodeObject::new("<module>", code));
+        self.push_frame(code, HashMap::new(), vec![], None, Some(module.clone()));
+        let depth = self.call_stack.len();
+        self.run(depth)?;
+        Ok(self.pop())
+    }
+
+    // and exec() and eval() code in a function or the script, on a frame
+    // whose locals start as these
+    pub(crate) fn exec_locals(
+        &mut self,
+        code: CodeObject,
+        locals: HashMap<String, Arc<Obj>>,
+    ) -> Result<Arc<Obj>, PyException> {
+        self.push_frame(Arc::new(code), locals, vec![], None, self.globals.clone());
+        let depth = self.call_stack.len();
+        self.run(depth)?;
+        Ok(self.pop())
+    }
+
     fn get_global_vars(&self) -> &HashMap<String, Arc<Obj>> {
-        return &self.global_vars;
+        &self.var_maps[0]
     }
 
-    fn get_global_vars_mut(&mut self) -> &mut HashMap<String, Arc<Obj>> {
-        return &mut self.global_vars;
+    pub(crate) fn get_global_vars_mut(&mut self) -> &mut HashMap<String, Arc<Obj>> {
+        &mut self.var_maps[0]
     }
 
     fn get_local_stack(&self) -> &Vec<Arc<Obj>> {
@@ -291,14 +599,408 @@ impl PyVM {
     }
 
     fn print_instruction(&self, index: usize) {
-        if index < self.instruction_queue.len() {
-            println!("\t ({}) \t{}", index, self.instruction_queue[index]);
+        if index < self.code.co_code.len() {
+            println!("\t ({}) \t{}", index, self.code.co_code[index]);
         }
     }
 
     fn print_instruction_queue(&self) {
-        println!("Instructions: ");
-        println!("{}", PyBytecode::to_string(&self.instruction_queue));
+        println!("Instructions ({}): ", self.code.co_name);
+        println!("{}", PyBytecode::to_string(&self.code.co_code));
+    }
+
+    // -------------- Frames ----------------
+    fn push_frame(
+        &mut self,
+        code: Arc<CodeObject>,
+        locals: HashMap<String, Arc<Obj>>,
+        stack: Vec<Arc<Obj>>,
+        generator: Option<GeneratorRef>,
+        globals: Option<Arc<PyModule>>,
+    ) {
+        let return_code = std::mem::replace(&mut self.code, code);
+        let return_globals = std::mem::replace(&mut self.globals, globals);
+        self.call_stack.push(CallFrame {
+            return_code,
+            return_addr: self.instruction_counter,
+            return_globals,
+            generator,
+        });
+        self.var_maps.push(locals);
+        self.local_stacks.push(stack);
+        self.instruction_counter = 0;
+    }
+
+    // Binds call arguments to the parameter names, filling in defaults and
+    // collecting extras into *args and **kwargs. Then adds the closure's cells
+    // and makes new ones for the locals that inner functions capture
+    #[allow(clippy::mutable_key_type)]
+    fn bind_args(
+        func: &UserFunc,
+        args: Vec<Arc<Obj>>,
+        kwargs: Vec<(String, Arc<Obj>)>,
+    ) -> Result<HashMap<String, Arc<Obj>>, PyException> {
+        let code = &func.code;
+        let names = code.arg_names();
+        let type_err = |msg: String| PyException::type_error(format!("{}() {}", func.name(), msg));
+
+        let mut locals = HashMap::new();
+        let mut args = args.into_iter();
+        for name in names {
+            match args.next() {
+                Some(arg) => locals.insert(name.clone(), arg),
+                None => break,
+            };
+        }
+        let extra: Vec<Arc<Obj>> = args.collect();
+        match code.varargs_name() {
+            Some(name) => {
+                locals.insert(name.clone(), Obj::Tuple(extra).into());
+            }
+            None if !extra.is_empty() => {
+                return Err(type_err(format!(
+                    "takes {} positional arguments but {} were given",
+                    names.len(),
+                    names.len() + extra.len()
+                )))
+            }
+            None => {}
+        }
+
+        let mut varkw = HashMap::new();
+        for (key, val) in kwargs {
+            let is_param = names.contains(&key) || code.kwonly_names().contains(&key);
+            if is_param && locals.contains_key(&key) {
+                return Err(type_err(format!("got multiple values for argument '{key}'")));
+            } else if is_param {
+                locals.insert(key, val);
+            } else if code.varkw_name().is_some() {
+                varkw.insert(Obj::Str(key), val);
+            } else {
+                return Err(type_err(format!("got an unexpected keyword argument '{key}'")));
+            }
+        }
+        if let Some(name) = code.varkw_name() {
+            locals.insert(name.clone(), Obj::new_dict_from(varkw).into());
+        }
+
+        let first_default = names.len() - func.defaults.len();
+        for (idx, name) in names.iter().enumerate() {
+            if locals.contains_key(name) {
+                continue;
+            }
+            match idx >= first_default {
+                true => locals.insert(name.clone(), func.defaults[idx - first_default].clone()),
+                false => {
+                    return Err(type_err(format!("missing required positional argument: '{name}'")))
+                }
+            };
+        }
+        for name in code.kwonly_names() {
+            if locals.contains_key(name) {
+                continue;
+            }
+            match func.kwdefaults.get(name) {
+                Some(default) => locals.insert(name.clone(), default.clone()),
+                None => {
+                    return Err(type_err(format!("missing required keyword-only argument: '{name}'")))
+                }
+            };
+        }
+
+        locals.extend(func.closure.clone());
+        for name in &code.co_cellvars {
+            let val = locals.remove(name).unwrap_or(Obj::Null.into());
+            locals.insert(name.clone(), Obj::Cell(Arc::new(Mutex::new(val))).into());
+        }
+        Ok(locals)
+    }
+
+    // Runs a generator until it yields or returns. `sent` becomes the value
+    // of the yield it was paused on, `thrown` is raised at that yield instead
+    pub(crate) fn resume_generator(
+        &mut self,
+        gen: &GeneratorRef,
+        sent: Arc<Obj>,
+        thrown: Option<PyException>,
+    ) -> Result<GeneratorStep, PyException> {
+        let (code, ip, stack, locals, globals, blocks, was_created) = {
+            let mut g = gen.lock().unwrap();
+            match g.state {
+                GeneratorState::Running => {
+                    return Err(PyException {
+                        error: PyError::ValueError,
+                        msg: "generator already executing".to_string(),
+                    })
+                }
+                GeneratorState::Finished => {
+                    return match thrown {
+                        Some(e) => Err(e),
+                        None => Ok(GeneratorStep::Returned(Obj::None.into())),
+                    };
+                }
+                GeneratorState::Created if thrown.is_none() && *sent != Obj::None => {
+                    return Err(PyException::type_error("can't send non-None value to a just-started generator"))
+                }
+                _ => {}
+            }
+            let was_created = g.state == GeneratorState::Created;
+            g.state = GeneratorState::Running;
+            (
+                g.code.clone(),
+                g.ip,
+                std::mem::take(&mut g.stack),
+                std::mem::take(&mut g.locals),
+                g.globals.clone(),
+                std::mem::take(&mut g.with_blocks),
+                was_created,
+            )
+        };
+
+        self.push_frame(code, locals, stack, Some(gen.clone()), globals);
+        self.instruction_counter = ip;
+        let depth = self.call_stack.len();
+        self.with_blocks.extend(blocks.into_iter().map(|block| WithBlock { depth, ..block }));
+        if !was_created {
+            self.push(sent);
+        }
+        if let Some(e) = thrown {
+            self.push_err(e);
+        }
+
+        self.run(depth)?;
+
+        let value = self.pop();
+        match gen.lock().unwrap().state {
+            GeneratorState::Finished => Ok(GeneratorStep::Returned(value)),
+            _ => Ok(GeneratorStep::Yielded(value)),
+        }
+    }
+
+    // next()/send()/throw() on a finished generator raise StopIteration
+    pub(crate) fn step_to_result(step: GeneratorStep) -> Result<Arc<Obj>, PyException> {
+        match step {
+            GeneratorStep::Yielded(val) => Ok(val),
+            GeneratorStep::Returned(val) => Err(PyException {
+                error: PyError::StopIteration,
+                msg: match val.as_ref() {
+                    Obj::None => String::new(),
+                    v => v.__str__(),
+                },
+            }),
+        }
+    }
+
+    fn close_generator(&mut self, gen: &GeneratorRef) -> Result<Arc<Obj>, PyException> {
+        {
+            let mut g = gen.lock().unwrap();
+            if g.state == GeneratorState::Created {
+                g.state = GeneratorState::Finished;
+            }
+        }
+        let exit = PyException {
+            error: PyError::GeneratorExit,
+            msg: String::new(),
+        };
+        match self.resume_generator(gen, Obj::None.into(), Some(exit)) {
+            Ok(GeneratorStep::Yielded(_)) => Err(PyException {
+                error: PyError::RuntimeError,
+                msg: "generator ignored GeneratorExit".to_string(),
+            }),
+            Ok(GeneratorStep::Returned(_)) => Ok(Obj::None.into()),
+            Err(e) if matches!(e.error, PyError::GeneratorExit | PyError::StopIteration) => {
+                Ok(Obj::None.into())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn call_builtin_method(
+        &mut self,
+        recv: &Arc<Obj>,
+        name: &str,
+        args: Vec<Arc<Obj>>,
+        kwargs: Vec<(String, Arc<Obj>)>,
+    ) -> Result<Arc<Obj>, PyException> {
+        // str.lower(s) is s.lower()
+        if let Obj::BuiltinType(t) = recv.as_ref() {
+            if !matches!((*t, name), ("int", "from_bytes") | ("str", "maketrans") | (_, "fromhex")) {
+                let Some(instance) = args.first() else {
+                    return Err(PyException::type_error(format!(
+                        "unbound method {}.{name}() needs an argument",
+                        recv.func_name()
+                    )));
+                };
+                if !builtins::is_instance(instance, recv) {
+                    return Err(PyException::type_error(format!(
+                        "descriptor '{name}' for '{}' objects doesn't apply to a '{}' object",
+                        recv.func_name(),
+                        builtins::class_name(instance)
+                    )));
+                }
+                return self.call_builtin_method(&instance.clone(), name, args[1..].to_vec(), kwargs);
+            }
+        }
+        let arg_count_err = |expected: &str| PyException::type_error(format!("{name}() takes {expected} arguments ({} given)", args.len()));
+        let takes_kwargs = matches!(recv.as_ref(), Obj::Str(_)) && pyrs_str::KWARG_METHODS.contains(&name)
+            || matches!(
+            (recv.as_ref(), name),
+            (Obj::Module(_), "lru_cache")
+                | (Obj::Str(_), "format" | "encode")
+                | (Obj::BuiltinType("str"), "maketrans")
+                | (Obj::Bytes(_) | Obj::ByteArray(_), "decode" | "hex")
+                | (Obj::MemoryView(_), "hex")
+                | (Obj::Int(_), "to_bytes")
+                | (Obj::BuiltinType("int"), "from_bytes")
+                | (Obj::Fraction(_), "limit_denominator")
+                | (Obj::Decimal(_), "quantize" | "to_integral_value")
+        );
+        if !kwargs.is_empty() && !takes_kwargs {
+            return Err(PyException::type_error(format!("{name}() takes no keyword arguments")));
+        }
+
+        match (recv.as_ref(), name) {
+            (Obj::Generator(gen), "__next__") => {
+                if !args.is_empty() {
+                    return Err(arg_count_err("no"));
+                }
+                let step = self.resume_generator(gen, Obj::None.into(), None)?;
+                PyVM::step_to_result(step)
+            }
+            (Obj::Generator(gen), "send") => {
+                if args.len() != 1 {
+                    return Err(arg_count_err("exactly one"));
+                }
+                let step = self.resume_generator(gen, args[0].clone(), None)?;
+                PyVM::step_to_result(step)
+            }
+            (Obj::Generator(gen), "throw") => {
+                let exc = match args.first().map(|a| a.as_ref()) {
+                    Some(Obj::ExceptType(error)) => PyException {
+                        error: *error,
+                        msg: args.get(1).map(|m| m.__str__()).unwrap_or_default(),
+                    },
+                    Some(Obj::Except(e)) => e.clone(),
+                    _ => {
+                        return Err(PyException::type_error("exceptions must derive from BaseException"))
+                    }
+                };
+                let step = self.resume_generator(gen, Obj::None.into(), Some(exc))?;
+                PyVM::step_to_result(step)
+            }
+            (Obj::Generator(gen), "close") => {
+                if !args.is_empty() {
+                    return Err(arg_count_err("no"));
+                }
+                self.close_generator(gen)
+            }
+            (Obj::Property(p), "getter" | "setter" | "deleter") => {
+                let [func] = args.as_slice() else {
+                    return Err(arg_count_err("exactly one"));
+                };
+                let p = match name {
+                    "getter" => p.with_getter(func.clone()),
+                    "setter" => p.with_setter(func.clone()),
+                    _ => p.with_deleter(func.clone()),
+                };
+                Ok(Obj::Property(Arc::new(p)).into())
+            }
+            (Obj::Fraction(r), _) => fractions::call_method(r, name, &args, &kwargs).map(|f| f.into()),
+            (Obj::Decimal(d), _) => decimal::call_method(d, name, &args, &kwargs).map(|d| d.into()),
+            (Obj::Complex(c), "conjugate") => Ok(Obj::Complex(Box::new(c.as_ref().clone().conj())).into()),
+            (Obj::Int(_) | Obj::Float(_), "conjugate") => Ok(recv.clone()),
+            (Obj::Int(int), "bit_length" | "bit_count" | "to_bytes" | "as_integer_ratio") => {
+                Funcs::int_method(int, name, &args, &kwargs).map(|i| i.into())
+            }
+            (Obj::BuiltinType("int"), "from_bytes") => Funcs::int_from_bytes(&args, &kwargs).map(|i| i.into()),
+            (Obj::Float(f), "hex" | "is_integer" | "as_integer_ratio") => Funcs::float_method(*f, name, &args).map(|f| f.into()),
+            (Obj::BuiltinType("float"), "fromhex") => Funcs::float_fromhex(&args).map(|f| f.into()),
+            (Obj::Str(template), "format") => Ok(Obj::Str(self.str_format(template, &args, &kwargs)?).into()),
+            (Obj::Str(template), "format_map") => {
+                let [mapping] = args.as_slice() else {
+                    return Err(arg_count_err("exactly one"));
+                };
+                Ok(Obj::Str(self.str_format_map(template, mapping)?).into())
+            }
+            (Obj::Str(s), "encode") => bytes::encode_method(s, &args, &kwargs).map(|b| b.into()),
+            // a table without the code point leaves the char as it is
+            (Obj::Str(s), "translate") => {
+                let [table] = args.as_slice() else {
+                    return Err(arg_count_err("exactly one"));
+                };
+                let found = pyrs_str::translate(s, |c| match self.get_item(table, &Obj::Int((c as u32).into()).into()) {
+                    Ok(to) => Ok(Some(to)),
+                    Err(e) if matches!(e.error, PyError::KeyError | PyError::IndexError | PyError::LookupError) => Ok(None),
+                    Err(e) => Err(e),
+                });
+                found.map(|s| s.into())
+            }
+            (Obj::Str(_) | Obj::BuiltinType("str"), "maketrans") => pyrs_str::maketrans(&args).map(|t| t.into()),
+            (Obj::Str(s), _) => pyrs_str::call_method(s, name, &args, &kwargs).map(|s| s.into()),
+            (Obj::MemoryView(view), "__enter__") => {
+                view.check()?;
+                Ok(recv.clone())
+            }
+            (Obj::Bytes(_) | Obj::ByteArray(_) | Obj::MemoryView(_), _) => {
+                bytes::call_method(recv, name, &args, &kwargs).map(|b| b.into())
+            }
+            (Obj::BuiltinType(type_name @ ("bytes" | "bytearray")), "fromhex") => {
+                bytes::fromhex(type_name, &args).map(|b| b.into())
+            }
+            (Obj::LruCache(cache), "cache_info") => Ok(cache.info().into()),
+            (Obj::LruCache(cache), "cache_clear") => {
+                cache.clear();
+                Ok(Obj::None.into())
+            }
+            (Obj::Module(_), "wraps") => match args.as_slice() {
+                [wrapped] => Ok(Obj::BuiltinMethod(wrapped.clone(), name.to_string()).into()),
+                _ => Err(arg_count_err("exactly one")),
+            },
+            // the decorator wraps(wrapped) returned, it has the wrapped function as its receiver
+            (wrapped, "wraps") => match args.as_slice() {
+                [wrapper] => Ok(functools::update_wrapper(wrapper, &Arc::new(wrapped.clone()))),
+                _ => Err(arg_count_err("exactly one")),
+            },
+            (Obj::Module(_), "update_wrapper") => match args.as_slice() {
+                [wrapper, wrapped] => Ok(functools::update_wrapper(wrapper, wrapped)),
+                _ => Err(arg_count_err("exactly two")),
+            },
+            (Obj::Module(_), "cache") => match args.as_slice() {
+                [func] => Ok(Obj::LruCache(Arc::new(LruCache::new(func.clone(), None))).into()),
+                _ => Err(arg_count_err("exactly one")),
+            },
+            // lru_cache(func) wraps func straight away, lru_cache(maxsize)
+            // gives back the decorator that will
+            (Obj::Module(_), "lru_cache") => {
+                let maxsize = match (args.first(), kwargs.iter().find(|(k, _)| k == "maxsize")) {
+                    (Some(func), None) if func.is_callable() => {
+                        let cache = LruCache::new(func.clone(), Some(128));
+                        return Ok(Obj::LruCache(Arc::new(cache)).into());
+                    }
+                    (_, Some((_, maxsize))) | (Some(maxsize), None) => maxsize.clone(),
+                    (None, None) => Obj::Int(128.into()).into(),
+                };
+                match maxsize.as_ref() {
+                    Obj::Int(_) | Obj::None => Ok(Obj::BuiltinMethod(maxsize, name.to_string()).into()),
+                    _ => Err(PyException::type_error("Expected first argument to be an integer, a callable, or None")),
+                }
+            }
+            (maxsize, "lru_cache") => {
+                let [func] = args.as_slice() else {
+                    return Err(arg_count_err("exactly one"));
+                };
+                // a negative maxsize caches nothing, like 0
+                let maxsize = match maxsize {
+                    Obj::Int(i) => Some(i.to_usize().unwrap_or(0)),
+                    _ => None,
+                };
+                Ok(Obj::LruCache(Arc::new(LruCache::new(func.clone(), maxsize))).into())
+            }
+            (obj, _) => Err(PyException {
+                error: PyError::AttributeError,
+                msg: format!("'{}' object has no attribute '{}'", obj.type_name(), name),
+            }),
+        }
     }
 
     // -------------- Instructions ----------------
@@ -323,15 +1025,50 @@ impl PyVM {
 
     fn store_name(&mut self, name: String) {
         let obj = self.pop();
-        self.get_local_vars_mut().insert(name, obj);
+        // the top level names of a module are its attributes
+        if let Some(module) = self.module_body() {
+            return module.set_attr(name, obj);
+        }
+        match self.get_local_vars().get(&name).map(|v| v.as_ref()) {
+            Some(Obj::Cell(cell)) => *cell.lock().unwrap() = obj,
+            _ => {
+                self.get_local_vars_mut().insert(name, obj);
+            }
+        }
     }
 
     fn load_name(&mut self, name: String) {
         if let Some(val) = self.get_local_vars().get(&name) {
-            self.push(val.clone());
+            let val = match val.as_ref() {
+                Obj::Cell(cell) => cell.lock().unwrap().clone(),
+                _ => val.clone(),
+            };
+            if val.as_ref() == self.null_obj.as_ref() {
+                return self.push_err(PyException {
+                    error: PyError::UndefinedVariableError,
+                    msg: format!("free variable \"{}\" referenced before assignment", name),
+                });
+            }
+            self.push(val);
         }
-        else if let Some(class) = self.class_defs.get(&name) {
-            self.push(Obj::Class(UserClassDef::new_instance(class)).into());
+        else {
+            self.load_global(name);
+        }
+    }
+
+    // the module's names, then the builtins
+    fn load_global(&mut self, name: String) {
+        if let Some(val) = self.lookup_global(&name) {
+            self.push(val);
+        }
+        else if let Some(error) = PyError::try_from_name(&name) {
+            self.push(Obj::ExceptType(error).into());
+        }
+        else if let Some(val) = builtins::lookup_name(&name) {
+            self.push(val.into());
+        }
+        else if name == "__debug__" {
+            self.push(Obj::Bool(!self.optimize).into());
         }
         else {
             self.push_err(PyException {
@@ -341,72 +1078,961 @@ impl PyVM {
         }
     }
 
-    fn push_null(&mut self) {
-        self.push(self.null_obj.clone());
+    fn store_global(&mut self, name: String) {
+        let obj = self.pop();
+        match &self.globals {
+            Some(module) => module.set_attr(name, obj),
+            None => {
+                self.get_global_vars_mut().insert(name, obj);
+            }
+        }
     }
 
-    fn build_list(&mut self, len: usize) {
-        let objs = self.pop_n(len);
-        let list = Arc::from(Obj::List(objs));
-        self.push(list);
+    // a deleted cell is empty again, like before it was first assigned
+    fn delete_name(&mut self, name: String) {
+        if self.module_body().is_some() {
+            return self.delete_global(name);
+        }
+        let removed = match self.get_local_vars().get(&name).map(|v| v.as_ref()) {
+            Some(Obj::Cell(cell)) => {
+                let mut cell = cell.lock().unwrap();
+                let val = std::mem::replace(&mut *cell, self.null_obj.clone());
+                val.as_ref() != self.null_obj.as_ref()
+            }
+            _ => self.get_local_vars_mut().remove(&name).is_some(),
+        };
+        if !removed {
+            let msg = match self.call_stack.is_empty() {
+                true => format!("name '{name}' is not defined"),
+                false => format!("cannot access local variable '{name}' where it is not associated with a value"),
+            };
+            self.push_err(PyException {
+                error: PyError::UndefinedVariableError,
+                msg,
+            });
+        }
     }
 
-    fn build_tuple(&mut self, count: usize) {
-        let objs = self.pop_n(count);
-        let tuple = Arc::from(Obj::Tuple(objs));
-        self.push(tuple);
+    fn delete_global(&mut self, name: String) {
+        let removed = match &self.globals {
+            Some(module) => module.remove_attr(&name),
+            None => self.get_global_vars_mut().remove(&name),
+        };
+        if removed.is_none() {
+            self.push_err(PyException {
+                error: PyError::UndefinedVariableError,
+                msg: format!("name '{name}' is not defined"),
+            });
+        }
     }
 
-    fn build_set(&mut self, count: usize) {
-        let objs = self.pop_n(count);
-        let set = Arc::from(Obj::Set(objs));
-        self.push(set);
+    fn load_attr(&mut self, name: String) {
+        let obj = self.pop();
+        match self.get_attr(&obj, &name) {
+            Ok(val) => self.push(val),
+            Err(e) => self.push_err(e),
+        }
     }
 
-    fn get_iter(&mut self) {
+    fn store_attr(&mut self, name: String) {
         let obj = self.pop();
-        let iter = match obj.iter_py() {
-            Some(i) => Obj::Iter(i),
-            None => Obj::Except(PyException {
-                error: PyError::TypeError,
-                msg: format!("Obj {} not iterable", obj),
-            }),
-        };
-        self.push(iter.into())
+        let val = self.pop();
+        if let Err(e) = self.set_attr(&obj, name, val) {
+            self.push_err(e);
+        }
     }
 
-    fn for_iter(&mut self, delta: usize) {
-        let top = self.pop();
-        match top.as_ref() {
-            Obj::Iter(iter) => {
-                let mut iter_clone = iter.clone();
-                match iter_clone.next() {
-                    Some(item) => {
-                        self.push(Arc::from(Obj::Iter(iter_clone)));
-                        self.push(item);
-                    }
-                    None => {
-                        self.instruction_counter += delta;
-                    }
-                }
-            }
-            _ => {
-                self.push_err(PyException {
-                    error: PyError::TypeError,
-                    msg: format!("FOR_ITER expected iterator, found {}", top),
-                });
-            }
+    fn delete_attr(&mut self, name: String) {
+        let obj = self.pop();
+        if let Err(e) = self.del_attr(&obj, name) {
+            self.push_err(e);
+        }
+    }
+
+    // pops the fromlist and the level, how many packages up a relative
+    // import starts from. See PyVM::import
+    fn import_name(&mut self, name: String) {
+        let fromlist = self.pop();
+        let level = self.pop();
+        let level = match level.as_ref() {
+            Obj::Int(level) => level.to_usize().unwrap_or(0),
+            _ => 0,
         };
+        let fromlist = match fromlist.as_ref() {
+            Obj::Tuple(names) => names.iter().map(|n| n.__str__()).collect(),
+            _ => vec![],
+        };
+        match self.import(&name, level, &fromlist) {
+            Ok(module) => self.push(module),
+            Err(e) => self.push_err(e),
+        }
     }
 
-    fn unpack_sequence(&mut self) {
-        let seq = self.pop();
-        if let Some(iter) = seq.iter_py() {
-            for o in iter.get_items() {
-                self.push(o);
-            }
-        } else {
-            panic!("Must be iterable sequence on top of stack");
+    // from module import name, the module stays on the stack for the next name
+    fn import_from(&mut self, name: String) {
+        let module = self.top();
+        if let Ok(val) = self.get_attr(&module, &name) {
+            return self.push(val);
+        }
+        // a submodule a circular import hasn't set on its package yet
+        if let Some(sub) = self.imports.get(&format!("{}.{}", module.func_name(), name)) {
+            return self.push(sub);
+        }
+        let file = match module.as_ref() {
+            Obj::PyModule(m) => m.file().map(|f| format!(" ({f})")).unwrap_or_default(),
+            _ => String::new(),
+        };
+        self.push_err(PyException {
+            error: PyError::ImportError,
+            msg: format!("cannot import name '{}' from '{}'{}", name, module.func_name(), file),
+        })
+    }
+
+    // from module import *
+    fn import_star(&mut self) {
+        let module = self.pop();
+        match self.public_names(&module) {
+            Ok(names) => {
+                for (name, val) in names {
+                    self.push(val);
+                    self.store_name(name);
+                }
+            }
+            Err(e) => self.push_err(e),
+        }
+    }
+
+    fn copy(&mut self, i: usize) {
+        let stack = self.get_local_stack();
+        let obj = stack[stack.len() - i].clone();
+        self.push(obj);
+    }
+
+    fn swap(&mut self, i: usize) {
+        let stack = self.get_local_stack_mut();
+        let top = stack.len() - 1;
+        stack.swap(top, top + 1 - i);
+    }
+
+    pub(crate) fn get_attr(&mut self, obj: &Arc<Obj>, name: &str) -> Result<Arc<Obj>, PyException> {
+        let not_found = |o: &Obj| PyException {
+            error: PyError::AttributeError,
+            msg: format!("'{}' object has no attribute '{}'", o.type_name(), name),
+        };
+        match (obj.as_ref(), name) {
+            (Obj::Generator(_), "__next__" | "send" | "throw" | "close")
+            | (Obj::Str(_), "format" | "format_map" | "encode" | "translate" | "maketrans") => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::Complex(c), "real") => Ok(Obj::Float(c.real().to_f64()).into()),
+            (Obj::Complex(c), "imag") => Ok(Obj::Float(c.imag().to_f64()).into()),
+            (Obj::Int(_) | Obj::Float(_), "real") => Ok(obj.clone()),
+            (Obj::Int(_), "imag") => Ok(Obj::Int(0.into()).into()),
+            (Obj::Float(_), "imag") => Ok(Obj::Float(0.0).into()),
+            (Obj::Complex(_) | Obj::Int(_) | Obj::Float(_), "conjugate")
+            | (Obj::Int(_), "bit_length" | "bit_count" | "to_bytes" | "as_integer_ratio")
+            | (Obj::Float(_), "hex" | "is_integer" | "as_integer_ratio")
+            | (Obj::BuiltinType("int"), "from_bytes")
+            | (Obj::BuiltinType("float" | "bytes" | "bytearray"), "fromhex") => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::Str(_), _) if pyrs_str::METHODS.contains(&name) => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::Bytes(_) | Obj::ByteArray(_) | Obj::MemoryView(_), _) if bytes::has_method(obj, name) => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::MemoryView(view), _) => bytes::view_attr(view, name)?.ok_or_else(|| not_found(obj)),
+            // the message is the only argument an exception keeps
+            (Obj::Except(e), "args") if e.msg.is_empty() => Ok(Obj::Tuple(vec![]).into()),
+            (Obj::Except(e), "args") => Ok(Obj::Tuple(vec![Obj::Str(e.msg.clone()).into()]).into()),
+            (Obj::Slice(start, _, _), "start") => Ok(start.clone()),
+            (Obj::Slice(_, stop, _), "stop") => Ok(stop.clone()),
+            (Obj::Slice(_, _, step), "step") => Ok(step.clone()),
+            (Obj::Fraction(_), _) if fractions::METHODS.contains(&name) => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::Fraction(r), _) => fractions::get_attr(r, name).map(|a| a.into()).ok_or_else(|| not_found(obj)),
+            (Obj::Decimal(_), _) if decimal::METHODS.contains(&name) => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::DecimalContext(ctx), _) => {
+                decimal::context_attr(&ctx.lock().unwrap(), name).map(|a| a.into()).ok_or_else(|| not_found(obj))
+            }
+            (Obj::Class(instance), "__class__") => Ok(Obj::ClassDef(instance.class.clone()).into()),
+            (Obj::Class(instance), _) => {
+                if let Some(val) = instance.get_field(name) {
+                    return Ok(val);
+                }
+                match instance.class.lookup(name) {
+                    Some(attr) => self.bind_attr(attr, obj, &instance.class),
+                    None => Err(PyException {
+                        error: PyError::AttributeError,
+                        msg: format!("'{}' object has no attribute '{}'", instance.class.name, name),
+                    }),
+                }
+            }
+            (Obj::ClassDef(class), "__name__" | "__qualname__") => Ok(Obj::Str(class.name.clone()).into()),
+            // a docstring isn't inherited
+            (Obj::ClassDef(class), "__doc__") => {
+                Ok(class.attrs.lock().unwrap().get("__doc__").cloned().unwrap_or(Obj::None.into()))
+            }
+            (Obj::ClassDef(class), _) => match class.lookup(name) {
+                Some(attr) => Ok(PyVM::bind_class_attr(attr, obj)),
+                None => Err(PyException {
+                    error: PyError::AttributeError,
+                    msg: format!("type object '{}' has no attribute '{}'", class.name, name),
+                }),
+            },
+            (Obj::Super(_, recv), "__self__") => Ok(recv.clone()),
+            (Obj::Super(class, _), "__thisclass__") => Ok(Obj::ClassDef(class.clone()).into()),
+            // looked up in the classes after this one in the instance's, and
+            // bound to the instance
+            (Obj::Super(class, recv), _) => {
+                let order = match recv.as_ref() {
+                    Obj::Class(instance) => UserClassDef::mro(&instance.class),
+                    Obj::ClassDef(class) => UserClassDef::mro(class),
+                    _ => vec![],
+                };
+                let attr = order
+                    .iter()
+                    .skip_while(|c| !Arc::ptr_eq(c, class))
+                    .skip(1)
+                    .find_map(|c| c.attrs.lock().unwrap().get(name).cloned());
+                match (attr, recv.as_ref()) {
+                    (Some(attr), Obj::Class(instance)) => self.bind_attr(attr, recv, &instance.class),
+                    (Some(attr), _) => Ok(PyVM::bind_class_attr(attr, recv)),
+                    (None, _) if name == "__init__" => {
+                        let init = NativeFunc { name: "__init__", ptr: builtins::object_init };
+                        Ok(Obj::Method(recv.clone(), Obj::Function(init).into()).into())
+                    }
+                    (None, _) => Err(not_found(obj)),
+                }
+            }
+            (Obj::Method(recv, _), "__self__") => Ok(recv.clone()),
+            (Obj::Method(_, func) | Obj::StaticMethod(func) | Obj::ClassMethod(func), "__func__") => {
+                Ok(func.clone())
+            }
+            // the rest come from the function the method wraps
+            (Obj::Method(_, func), _) => self.get_attr(func, name),
+            (Obj::Property(_), "getter" | "setter" | "deleter") => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::Property(p), "fget" | "fset" | "fdel") => {
+                let func = match name {
+                    "fget" => &p.fget,
+                    "fset" => &p.fset,
+                    _ => &p.fdel,
+                };
+                Ok(func.clone().unwrap_or(Obj::None.into()))
+            }
+            (Obj::UserFunc(func), _) => match (func.attrs.get(name), name) {
+                (Some(val), _) => Ok(val.clone()),
+                (None, "__name__" | "__qualname__") => Ok(Obj::Str(func.name().to_string()).into()),
+                (None, "__doc__") => Ok(Obj::None.into()),
+                (None, _) => Err(not_found(obj)),
+            },
+            (Obj::LruCache(_), "cache_info" | "cache_clear") => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::LruCache(cache), "__wrapped__") => Ok(cache.func.clone()),
+            (Obj::LruCache(cache), _) => self.get_attr(&cache.func, name),
+            (Obj::Module(module), _) if module == functools::MODULE_NAME && functools::NAMES.contains(&name) => {
+                Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into())
+            }
+            (Obj::NativeClass(class), "__name__" | "__qualname__") => Ok(Obj::Str(class.name.clone()).into()),
+            (Obj::NativeClass(class), "__module__") => Ok(Obj::Str(class.module.clone()).into()),
+            (Obj::NativeClass(class), _) => class.lookup(name).ok_or_else(|| PyException {
+                error: PyError::AttributeError,
+                msg: format!("type object '{}' has no attribute '{}'", class.name, name),
+            }),
+            (Obj::Native(native), _) if native.class.lookup_attr(name).is_some() => {
+                (native.class.lookup_attr(name).unwrap())(self, std::slice::from_ref(obj), &[])
+            }
+            (Obj::Native(native), _) => match native.class.lookup(name) {
+                Some(method) => Ok(Obj::Method(obj.clone(), method).into()),
+                None => Err(PyException {
+                    error: PyError::AttributeError,
+                    msg: format!("'{}' object has no attribute '{}'", native.class.name, name),
+                }),
+            },
+            (Obj::Module(module), "__name__") => Ok(Obj::Str(module.clone()).into()),
+            (Obj::Module(module), _) if module == import::SYS_MODULE_NAME && self.imports.sys_attr(name).is_some() => {
+                Ok(self.imports.sys_attr(name).unwrap())
+            }
+            (Obj::Module(module), _) if module == builtins::MODULE_NAME && builtins::lookup_name(name).is_some() => {
+                Ok(builtins::lookup_name(name).unwrap().into())
+            }
+            (Obj::Module(module), _) => Err(PyException {
+                error: PyError::AttributeError,
+                msg: format!("module '{}' has no attribute '{}'", module, name),
+            }),
+            (Obj::PyModule(module), _) => module.get_attr(name).ok_or_else(|| PyException {
+                error: PyError::AttributeError,
+                msg: format!("module '{}' has no attribute '{}'", module.name, name),
+            }),
+            (Obj::BuiltinType(_) | Obj::ExceptType(_), "__name__" | "__qualname__") => Ok(Obj::Str(obj.func_name()).into()),
+            (Obj::BuiltinType(t), "__module__") => {
+                Ok(Obj::Str(t.rsplit_once('.').map_or("builtins", |(module, _)| module).to_string()).into())
+            }
+            (Obj::ExceptType(_), "__module__") => Ok(Obj::Str(builtins::MODULE_NAME.to_string()).into()),
+            // str.lower and the other methods of the instances, unbound
+            (Obj::BuiltinType(t), _) => {
+                let is_method = match builtins::empty_instance(t) {
+                    Some(instance) => {
+                        matches!(self.get_attr(&instance.into(), name).as_deref(), Ok(Obj::BuiltinMethod(..)))
+                    }
+                    None => false,
+                };
+                match is_method {
+                    true => Ok(Obj::BuiltinMethod(obj.clone(), name.to_string()).into()),
+                    false => Err(PyException {
+                        error: PyError::AttributeError,
+                        msg: format!("type object '{}' has no attribute '{}'", obj.func_name(), name),
+                    }),
+                }
+            }
+            (o, _) => Err(not_found(o)),
+        }
+    }
+
+    // What a class attribute becomes when it is found through an instance:
+    // functions are bound to it, properties are called
+    fn bind_attr(
+        &mut self,
+        attr: Arc<Obj>,
+        instance: &Arc<Obj>,
+        class: &Arc<UserClassDef>,
+    ) -> Result<Arc<Obj>, PyException> {
+        match attr.as_ref() {
+            Obj::UserFunc(_) | Obj::LruCache(_) => Ok(Obj::Method(instance.clone(), attr).into()),
+            Obj::StaticMethod(func) => Ok(func.clone()),
+            Obj::ClassMethod(func) => {
+                Ok(Obj::Method(Obj::ClassDef(class.clone()).into(), func.clone()).into())
+            }
+            Obj::Property(p) => match &p.fget {
+                Some(fget) => self.call_object(fget.clone(), vec![instance.clone()], vec![]),
+                None => Err(PyException {
+                    error: PyError::AttributeError,
+                    msg: format!("property of '{}' object has no getter", class.name),
+                }),
+            },
+            _ => Ok(attr),
+        }
+    }
+
+    // and through the class itself: static methods unwrap, class methods
+    // are bound to the class
+    fn bind_class_attr(attr: Arc<Obj>, class: &Arc<Obj>) -> Arc<Obj> {
+        match attr.as_ref() {
+            Obj::StaticMethod(func) => func.clone(),
+            Obj::ClassMethod(func) => Obj::Method(class.clone(), func.clone()).into(),
+            _ => attr,
+        }
+    }
+
+    pub(crate) fn set_attr(&mut self, obj: &Arc<Obj>, name: String, val: Arc<Obj>) -> Result<(), PyException> {
+        match obj.as_ref() {
+            // object() has no attribute dict
+            Obj::Class(instance) if userclass::is_object(&instance.class) => Err(PyException {
+                error: PyError::AttributeError,
+                msg: format!("'object' object has no attribute '{name}'"),
+            }),
+            Obj::ClassDef(class) if userclass::is_object(class) => Err(PyException::type_error(format!(
+                "cannot set '{name}' attribute of immutable type 'object'"
+            ))),
+            Obj::Class(instance) => {
+                if let Some(Obj::Property(p)) = instance.class.lookup(&name).as_deref() {
+                    return match &p.fset {
+                        Some(fset) => self
+                            .call_object(fset.clone(), vec![obj.clone(), val], vec![])
+                            .map(|_| ()),
+                        None => Err(PyException {
+                            error: PyError::AttributeError,
+                            msg: format!(
+                                "property '{}' of '{}' object has no setter",
+                                name, instance.class.name
+                            ),
+                        }),
+                    };
+                }
+                instance.set_field(name, val);
+                Ok(())
+            }
+            Obj::ClassDef(class) => {
+                class.set_attr(name, val);
+                Ok(())
+            }
+            Obj::DecimalContext(ctx) => decimal::set_context_attr(&mut ctx.lock().unwrap(), &name, &val),
+            Obj::PyModule(module) => {
+                module.set_attr(name, val);
+                Ok(())
+            }
+            Obj::Module(module) if module == import::SYS_MODULE_NAME => self.imports.set_sys_attr(&name, val),
+            o => Err(PyException {
+                error: PyError::AttributeError,
+                msg: format!("'{}' object has no attribute '{}'", o.type_name(), name),
+            }),
+        }
+    }
+
+    // an instance's own attribute or a property with a deleter, a class's
+    // attribute or a module's name
+    pub(crate) fn del_attr(&mut self, obj: &Arc<Obj>, name: String) -> Result<(), PyException> {
+        let missing = |type_name: &str| PyException {
+            error: PyError::AttributeError,
+            msg: format!("'{}' object has no attribute '{}'", type_name, name),
+        };
+        match obj.as_ref() {
+            Obj::Class(instance) => {
+                if let Some(Obj::Property(p)) = instance.class.lookup(&name).as_deref() {
+                    return match &p.fdel {
+                        Some(fdel) => self.call_object(fdel.clone(), vec![obj.clone()], vec![]).map(|_| ()),
+                        None => Err(PyException {
+                            error: PyError::AttributeError,
+                            msg: format!("property '{}' of '{}' object has no deleter", name, instance.class.name),
+                        }),
+                    };
+                }
+                instance.remove_field(&name).map(|_| ()).ok_or_else(|| missing(&instance.class.name))
+            }
+            Obj::ClassDef(class) => class.remove_attr(&name).map(|_| ()).ok_or_else(|| PyException {
+                error: PyError::AttributeError,
+                msg: format!("type object '{}' has no attribute '{}'", class.name, name),
+            }),
+            Obj::PyModule(module) => module.remove_attr(&name).map(|_| ()).ok_or_else(|| PyException {
+                error: PyError::AttributeError,
+                msg: format!("module '{}' has no attribute '{}'", module.name, name),
+            }),
+            o => Err(missing(o.type_name())),
+        }
+    }
+
+    fn push_null(&mut self) {
+        self.push(self.null_obj.clone());
+    }
+
+    fn build_string(&mut self, count: usize) {
+        let parts = self.pop_n(count);
+        let s: String = parts.iter().map(|p| p.__str__()).collect();
+        self.push(Obj::Str(s).into());
+    }
+
+    // the conversion is in the low two bits of flags, 0x04 means the
+    // format spec is on top of the value
+    fn format_value(&mut self, flags: usize) {
+        let spec = match flags & 0x04 {
+            0 => String::new(),
+            _ => self.pop().__str__(),
+        };
+        let val = self.pop();
+        let val = match flags & 0x03 {
+            0 => Ok(val),
+            1 => self.str_of(&val, false).map(|s| Obj::Str(s).into()),
+            2 => self.str_of(&val, true).map(|s| Obj::Str(s).into()),
+            _ => self.str_of(&val, true).map(|s| Obj::Str(pyrs_format::ascii_escape(&s)).into()),
+        };
+        match val.and_then(|val| self.format(&val, &spec)) {
+            Ok(s) => self.push(Obj::Str(s).into()),
+            Err(e) => self.push_err(e),
+        }
+    }
+
+    // str() or repr(), calling the class's __str__ or __repr__ for instances.
+    // Without a __str__ an instance's str is its repr
+    pub(crate) fn str_of(&mut self, obj: &Arc<Obj>, repr: bool) -> Result<String, PyException> {
+        let lookup = |name: &str| match obj.as_ref() {
+            Obj::Class(instance) => instance.class.lookup(name),
+            Obj::Native(native) => native.class.lookup(name),
+            _ => None,
+        };
+        let method = match repr {
+            true => lookup("__repr__"),
+            false => lookup("__str__").or_else(|| lookup("__repr__")),
+        };
+        let Some(method) = method else {
+            return Ok(if repr { obj.__repr__() } else { obj.__str__() });
+        };
+        let name = if repr { "__repr__" } else { "__str__" };
+        match self.call_object(Obj::Method(obj.clone(), method).into(), vec![], vec![])?.as_ref() {
+            Obj::Str(s) => Ok(s.clone()),
+            o => Err(PyException::type_error(format!("{name} returned non-string (type {})", o.type_name()))),
+        }
+    }
+
+    // format(obj, spec), an instance formats itself with its class's __format__
+    pub(crate) fn format(&mut self, obj: &Arc<Obj>, spec: &str) -> Result<String, PyException> {
+        let Obj::Class(instance) = obj.as_ref() else {
+            return pyrs_format::format_obj(obj, spec);
+        };
+        match instance.class.lookup("__format__") {
+            Some(method) => {
+                let spec = Obj::Str(spec.to_string()).into();
+                match self.call_object(Obj::Method(obj.clone(), method).into(), vec![spec], vec![])?.as_ref() {
+                    Obj::Str(s) => Ok(s.clone()),
+                    o => Err(PyException::type_error(format!("__format__ must return a str, not {}", o.type_name()))),
+                }
+            }
+            None if spec.is_empty() => self.str_of(obj, false),
+            None => Err(PyException::type_error(format!("unsupported format string passed to {}.__format__", instance.class.name))),
+        }
+    }
+
+    // template.format(*args, **kwargs)
+    fn str_format(
+        &mut self,
+        template: &str,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+    ) -> Result<String, PyException> {
+        let mut numbering = FieldNumbering::default();
+        self.fill_template(template, args, kwargs, None, &mut numbering, 2)
+    }
+
+    // template.format_map(mapping), the fields are looked up in it one by one
+    fn str_format_map(&mut self, template: &str, mapping: &Arc<Obj>) -> Result<String, PyException> {
+        let mut numbering = FieldNumbering::default();
+        self.fill_template(template, &[], &[], Some(mapping), &mut numbering, 2)
+    }
+
+    // the spec of a field is a template too, filled in before it is used.
+    // CPython stops at two levels of nesting
+    fn fill_template(
+        &mut self,
+        template: &str,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+        mapping: Option<&Arc<Obj>>,
+        numbering: &mut FieldNumbering,
+        depth: usize,
+    ) -> Result<String, PyException> {
+        let mut out = String::new();
+        for part in pyrs_format::parse_template(template)? {
+            let (name, conversion, spec) = match part {
+                TemplatePart::Text(text) => {
+                    out.push_str(&text);
+                    continue;
+                }
+                TemplatePart::Field { .. } if depth == 0 => {
+                    return Err(PyException {
+                        error: PyError::ValueError,
+                        msg: "Max string recursion exceeded".to_string(),
+                    })
+                }
+                TemplatePart::Field { name, conversion, spec } => (name, conversion, spec),
+            };
+            let val = self.template_field(&name, args, kwargs, mapping, numbering)?;
+            let val = match conversion {
+                Some('s') => Obj::Str(self.str_of(&val, false)?).into(),
+                Some('r') => Obj::Str(self.str_of(&val, true)?).into(),
+                Some(_) => Obj::Str(pyrs_format::ascii_escape(&self.str_of(&val, true)?)).into(),
+                None => val,
+            };
+            let spec = self.fill_template(&spec, args, kwargs, mapping, numbering, depth - 1)?;
+            out.push_str(&self.format(&val, &spec)?);
+        }
+        Ok(out)
+    }
+
+    // the argument a field names, then the attributes and items after it
+    fn template_field(
+        &mut self,
+        name: &str,
+        args: &[Arc<Obj>],
+        kwargs: &[(String, Arc<Obj>)],
+        mapping: Option<&Arc<Obj>>,
+        numbering: &mut FieldNumbering,
+    ) -> Result<Arc<Obj>, PyException> {
+        let (first, accesses) = pyrs_format::split_field_name(name)?;
+        let mut val = match (numbering.resolve(first)?, mapping) {
+            (FieldArg::Index(_), Some(_)) => {
+                return Err(PyException {
+                    error: PyError::ValueError,
+                    msg: "Format string contains positional fields".to_string(),
+                })
+            }
+            (FieldArg::Key(key), Some(mapping)) => self.get_item(mapping, &Obj::Str(key.to_string()).into())?,
+            (FieldArg::Index(idx), None) => args.get(idx).cloned().ok_or_else(|| PyException {
+                error: PyError::IndexError,
+                msg: format!("Replacement index {idx} out of range for positional args tuple"),
+            })?,
+            (FieldArg::Key(key), None) => match kwargs.iter().find(|(k, _)| k == key) {
+                Some((_, val)) => val.clone(),
+                None => {
+                    return Err(PyException {
+                        error: PyError::KeyError,
+                        msg: format!("'{key}'"),
+                    })
+                }
+            },
+        };
+        for access in accesses {
+            val = match access {
+                FieldAccess::Attr(attr) => self.get_attr(&val, &attr)?,
+                FieldAccess::Item(key) => PyVM::template_item(&val, &key)?,
+            };
+        }
+        Ok(val)
+    }
+
+    // "{0[1]}" indexes with the int 1, "{0[a]}" with the str 'a'
+    fn template_item(obj: &Arc<Obj>, key: &str) -> Result<Arc<Obj>, PyException> {
+        let key = match key.parse::<usize>() {
+            Ok(idx) if key.bytes().all(|b| b.is_ascii_digit()) => Obj::Int(idx.into()),
+            _ => Obj::Str(key.to_string()),
+        };
+        Obj::get_item(obj, &key)
+    }
+
+    // template % values, values is a tuple of arguments, a dict for
+    // "%(key)s" conversions or else the one argument
+    fn percent_format(&mut self, template: &str, values: &Arc<Obj>) -> Result<String, PyException> {
+        let (args, mapping) = match values.as_ref() {
+            Obj::Tuple(items) => (items.clone(), None),
+            Obj::Dict(map) => (vec![values.clone()], Some(map)),
+            _ => (vec![values.clone()], None),
+        };
+        let mut args = args.into_iter();
+        let mut used_mapping = false;
+        let mut out = String::new();
+        for part in pyrs_format::parse_percent(template)? {
+            let (key, mut spec, star_width, star_precision) = match part {
+                PercentPart::Text(text) => {
+                    out.push_str(&text);
+                    continue;
+                }
+                PercentPart::Conversion { key, spec, star_width, star_precision } => {
+                    (key, spec, star_width, star_precision)
+                }
+            };
+            if star_width {
+                let width = args.next().ok_or_else(|| PyException::type_error("not enough arguments for format string"))?;
+                let Obj::Int(width) = width.as_ref() else {
+                    return Err(PyException::type_error("* wants int"));
+                };
+                // a negative width left aligns
+                if *width < 0 {
+                    spec.align = Some('<');
+                    spec.zero_pad = false;
+                }
+                spec.width = width.clone().abs().to_usize().unwrap_or(usize::MAX);
+            }
+            if star_precision {
+                let precision = args.next().ok_or_else(|| PyException::type_error("not enough arguments for format string"))?;
+                let Obj::Int(precision) = precision.as_ref() else {
+                    return Err(PyException::type_error("* wants int"));
+                };
+                spec.precision = Some(precision.to_usize().unwrap_or(0));
+            }
+            let val = match (key, mapping) {
+                (Some(key), Some(map)) => {
+                    used_mapping = true;
+                    let key = Obj::Str(key);
+                    map.lock().unwrap().get(&key).cloned().ok_or_else(|| PyException {
+                        error: PyError::KeyError,
+                        msg: key.__repr__(),
+                    })?
+                }
+                (Some(_), None) => return Err(PyException::type_error("format requires a mapping")),
+                (None, _) => args.next().ok_or_else(|| PyException::type_error("not enough arguments for format string"))?,
+            };
+            let s = match spec.kind {
+                Some(kind @ ('s' | 'r' | 'a')) => {
+                    let s = match kind {
+                        's' => self.str_of(&val, false)?,
+                        'r' => self.str_of(&val, true)?,
+                        _ => pyrs_format::ascii_escape(&self.str_of(&val, true)?),
+                    };
+                    // strings are right aligned and never zero padded
+                    let spec = FormatSpec {
+                        align: Some(spec.align.unwrap_or('>')),
+                        sign: None,
+                        alternate: false,
+                        zero_pad: false,
+                        kind: None,
+                        ..spec
+                    };
+                    spec.format_str(&s)?
+                }
+                _ => pyrs_format::percent_number(&val, &spec)?,
+            };
+            out.push_str(&s);
+        }
+        if !used_mapping && args.next().is_some() {
+            return Err(PyException::type_error("not all arguments converted during string formatting"));
+        }
+        Ok(out)
+    }
+
+    fn binary_subscr(&mut self) {
+        let key = self.pop();
+        let obj = self.pop();
+        match self.get_item(&obj, &key) {
+            Ok(val) => self.push(val),
+            Err(e) => self.push_err(e),
+        }
+    }
+
+    // obj[key], an instance's class can give it a __getitem__
+    fn get_item(&mut self, obj: &Arc<Obj>, key: &Arc<Obj>) -> Result<Arc<Obj>, PyException> {
+        match obj.as_ref() {
+            Obj::Native(native) => match native.class.lookup("__getitem__") {
+                Some(method) => self.call_object(Obj::Method(obj.clone(), method).into(), vec![key.clone()], vec![]),
+                None => Obj::get_item(obj, key),
+            },
+            Obj::Class(instance) => match instance.class.lookup("__getitem__") {
+                Some(method) => self.call_object(Obj::Method(obj.clone(), method).into(), vec![key.clone()], vec![]),
+                None => Err(PyException::type_error(format!("'{}' object is not subscriptable", instance.class.name))),
+            },
+            _ => Obj::get_item(obj, key),
+        }
+    }
+
+    // obj[key] = val with val under obj and key, the container changes in place
+    fn store_subscr(&mut self) {
+        let key = self.pop();
+        let obj = self.pop();
+        let val = self.pop();
+        let ret = match obj.as_ref() {
+            Obj::Class(instance) => match instance.class.lookup("__setitem__") {
+                Some(method) => self.call_object(Obj::Method(obj.clone(), method).into(), vec![key, val], vec![]).map(|_| ()),
+                None => Err(PyException::type_error(format!("'{}' object does not support item assignment", instance.class.name))),
+            },
+            _ => Obj::set_item(&obj, &key, val),
+        };
+        if let Err(e) = ret {
+            self.push_err(e);
+        }
+    }
+
+    // del obj[key] with key on top, in place like store_subscr
+    fn delete_subscr(&mut self) {
+        let key = self.pop();
+        let obj = self.pop();
+        let ret = match obj.as_ref() {
+            Obj::Class(instance) => match instance.class.lookup("__delitem__") {
+                Some(method) => self.call_object(Obj::Method(obj.clone(), method).into(), vec![key], vec![]).map(|_| ()),
+                None => Err(PyException::type_error(format!("'{}' object doesn't support item deletion", instance.class.name))),
+            },
+            _ => Obj::del_item(&obj, &key),
+        };
+        if let Err(e) = ret {
+            self.push_err(e);
+        }
+    }
+
+    // assert cond, msg raises AssertionError(msg)
+    fn raise_assertion(&mut self, argc: usize) {
+        let msg = match argc {
+            0 => String::new(),
+            _ => self.pop().__str__(),
+        };
+        self.push_err(PyException {
+            error: PyError::AssertionError,
+            msg,
+        });
+    }
+
+    // a slice from start and stop, and a step when count is 3
+    fn build_slice(&mut self, count: usize) {
+        let mut parts = self.pop_n(count);
+        let step = match count {
+            3 => parts.pop().unwrap(),
+            _ => Obj::None.into(),
+        };
+        let stop = parts.pop().unwrap();
+        let start = parts.pop().unwrap();
+        self.push(Obj::Slice(start, stop, step).into());
+    }
+
+    fn build_list(&mut self, len: usize) {
+        let objs = self.pop_n(len);
+        let list = Arc::from(Obj::new_list(objs));
+        self.push(list);
+    }
+
+    fn build_tuple(&mut self, count: usize) {
+        let objs = self.pop_n(count);
+        let tuple = Arc::from(Obj::Tuple(objs));
+        self.push(tuple);
+    }
+
+    fn build_set(&mut self, count: usize) {
+        let objs = self.pop_n(count);
+        let mut set = vec![];
+        for o in objs {
+            if let Err(e) = PyVM::check_hashable(&o) {
+                return self.push_err(e);
+            }
+            if !set.contains(&o) {
+                set.push(o);
+            }
+        }
+        self.push(Obj::Set(set).into());
+    }
+
+    // hashing never looks inside the Mutex a generator holds
+    #[allow(clippy::mutable_key_type)]
+    fn build_map(&mut self, count: usize) {
+        let objs = self.pop_n(count * 2);
+        let mut map = HashMap::new();
+        for pair in objs.chunks(2) {
+            if let Err(e) = PyVM::check_hashable(&pair[0]) {
+                return self.push_err(e);
+            }
+            map.insert(pair[0].as_ref().clone(), pair[1].clone());
+        }
+        self.push(Obj::new_dict_from(map).into());
+    }
+
+    pub(crate) fn check_hashable(obj: &Obj) -> Result<(), PyException> {
+        match obj.is_hashable() {
+            true => Ok(()),
+            false => Err(PyException::type_error(format!("unhashable type: '{}'", obj.type_name()))),
+        }
+    }
+
+    // the container a comprehension is building, `i` down from the top
+    // once the element has been popped
+    fn comprehension_target(&mut self, i: usize) -> &mut Obj {
+        let stack = self.get_local_stack_mut();
+        let idx = stack.len() - i;
+        Arc::make_mut(&mut stack[idx])
+    }
+
+    fn list_append(&mut self, i: usize) {
+        let obj = self.pop();
+        let msg = match self.comprehension_target(i) {
+            Obj::List(list) => return list.lock().unwrap().push(obj),
+            o => format!("LIST_APPEND expected a list, found {o}"),
+        };
+        self.stack_err(msg);
+    }
+
+    fn set_add(&mut self, i: usize) {
+        let obj = self.pop();
+        if let Err(e) = PyVM::check_hashable(&obj) {
+            return self.push_err(e);
+        }
+        let msg = match self.comprehension_target(i) {
+            Obj::Set(set) => {
+                if !set.contains(&obj) {
+                    set.push(obj);
+                }
+                return;
+            }
+            o => format!("SET_ADD expected a set, found {o}"),
+        };
+        self.stack_err(msg);
+    }
+
+    fn map_add(&mut self, i: usize) {
+        let value = self.pop();
+        let key = self.pop();
+        if let Err(e) = PyVM::check_hashable(&key) {
+            return self.push_err(e);
+        }
+        let msg = match self.comprehension_target(i) {
+            Obj::Dict(map) => {
+                map.lock().unwrap().insert(key.as_ref().clone(), value);
+                return;
+            }
+            o => format!("MAP_ADD expected a dict, found {o}"),
+        };
+        self.stack_err(msg);
+    }
+
+    fn list_extend(&mut self, i: usize) {
+        let iterable = self.pop();
+        let Some(items) = iterable.iter_py() else {
+            return self.push_err(PyException::type_error(format!("Value after * must be an iterable, not {}", iterable.type_name())));
+        };
+        let msg = match self.comprehension_target(i) {
+            Obj::List(list) => return list.lock().unwrap().extend(items),
+            o => format!("LIST_EXTEND expected a list, found {o}"),
+        };
+        self.stack_err(msg);
+    }
+
+    fn dict_merge(&mut self, i: usize) {
+        let other = self.pop();
+        let Obj::Dict(other) = other.as_ref() else {
+            return self.push_err(PyException::type_error(format!("argument after ** must be a mapping, not {}", other.type_name())));
+        };
+        let Obj::Dict(map) = self.comprehension_target(i).clone() else {
+            return self.stack_err("DICT_MERGE expected a dict".to_string());
+        };
+        let mut repeated = None;
+        for (key, val) in other.lock().unwrap().iter() {
+            if map.lock().unwrap().insert(key.clone(), val.clone()).is_some() {
+                repeated = Some(key.clone());
+            }
+        }
+        if let Some(key) = repeated {
+            self.push_err(PyException::type_error(format!("got multiple values for keyword argument '{}'", key)));
+        }
+    }
+
+    fn get_iter(&mut self) {
+        let obj = self.pop();
+        match self.iter_of(&obj) {
+            Ok(iter) => self.push(iter),
+            Err(e) => self.push_err(e),
+        }
+    }
+
+    fn get_yield_from_iter(&mut self) {
+        self.get_iter();
+    }
+
+    fn for_iter(&mut self, delta: usize) {
+        let top = self.pop();
+        match self.next_item(&top) {
+            Ok(Some(item)) => {
+                self.push(top);
+                self.push(item);
+            }
+            Ok(None) => self.instruction_counter += delta,
+            Err(e) => self.push_err(e),
+        };
+    }
+
+    // yield from: passes the sent value on to the sub iterator, jumps
+    // forward by delta with its return value once it is exhausted
+    fn send(&mut self, delta: usize) {
+        let sent = self.pop();
+        let iter = self.pop();
+        let step = match iter.as_ref() {
+            Obj::Generator(gen) => self.resume_generator(gen, sent, None),
+            _ => match self.next_item(&iter) {
+                Ok(Some(item)) => Ok(GeneratorStep::Yielded(item)),
+                Ok(None) => Ok(GeneratorStep::Returned(Obj::None.into())),
+                Err(e) => Err(e),
+            },
+        };
+        match step {
+            Ok(GeneratorStep::Yielded(item)) => {
+                self.push(iter);
+                self.push(item);
+            }
+            Ok(GeneratorStep::Returned(val)) => {
+                self.push(val);
+                self.instruction_counter += delta;
+            }
+            Err(e) => self.push_err(e),
+        }
+    }
+
+    fn unpack_sequence(&mut self) {
+        let seq = self.pop();
+        let Some(iter) = seq.iter_py() else {
+            return self.push_err(PyException::type_error(format!(
+                "cannot unpack non-iterable {} object",
+                seq.type_name()
+            )));
+        };
+        for o in iter.get_items() {
+            self.push(o);
         }
     }
 
@@ -435,9 +2061,10 @@ impl PyVM {
     fn compare_op(&mut self, op: Op) {
         let rhs = self.pop();
         let lhs = self.pop();
-        let cond = Obj::compare_op(&lhs, &rhs, &op);
-        // dbg!(&rhs, &lhs, &op, &cond);
-        self.push(cond.to_arc());
+        match self.compare(&op, &lhs, &rhs) {
+            Ok(cond) => self.push(Obj::Bool(cond).into()),
+            Err(e) => self.push_err(e),
+        }
     }
 
     fn binary_add(&mut self) {
@@ -461,27 +2088,38 @@ impl PyVM {
     fn binary_multiply(&mut self) {
         let rhs = self.pop();
         let lhs = self.pop();
-        let ret = match Obj::__mul__(&lhs, &rhs) {
-            Ok(val) => val,
-            Err(e) => {
-                println!("{e}");
-                e.to_arc()
-            }
+        match Obj::__mul__(&lhs, &rhs) {
+            Ok(val) => self.push(val),
+            Err(e) => self.push_err(e),
         };
-        self.push(Arc::from(ret));
     }
 
-    fn binary_divide(&mut self) {
+    fn binary_op(&mut self, op: Op) {
         let rhs = self.pop();
         let lhs = self.pop();
-        let ret = match Obj::__div__(&lhs, &rhs) {
-            Ok(val) => val,
-            Err(e) => {
-                println!("{e}");
-                e.to_arc()
-            }
+        let ret = match (op, lhs.as_ref()) {
+            (Op::Percent, Obj::Str(template)) => self.percent_format(template, &rhs).map(|s| Obj::Str(s).into()),
+            (Op::Percent, _) => Obj::__mod__(&lhs, &rhs),
+            (Op::DoubleSlash, _) => Obj::__floordiv__(&lhs, &rhs),
+            (Op::Power, _) => Obj::__pow__(&lhs, &rhs),
+            (op, _) => Err(PyException {
+                error: PyError::NotImplementedError,
+                msg: format!("binary operator {op} not implemented"),
+            }),
         };
-        self.push(Arc::from(ret));
+        match ret {
+            Ok(val) => self.push(val),
+            Err(e) => self.push_err(e),
+        }
+    }
+
+    fn binary_divide(&mut self) {
+        let rhs = self.pop();
+        let lhs = self.pop();
+        match Obj::__div__(&lhs, &rhs) {
+            Ok(val) => self.push(val),
+            Err(e) => self.push_err(e),
+        }
     }
 
     fn unary_negative(&mut self) {
@@ -493,105 +2131,391 @@ impl PyVM {
     }
 
     fn call_function(&mut self, argc: usize) {
-        let func = self.pop();
         let args = self.pop_n_or(argc, Obj::None.into());
+        let func = self.pop();
+        self.call(func, args, vec![]);
+    }
+
+    // the last len(names) args are keyword args, names is a tuple on top
+    fn call_function_kw(&mut self, argc: usize) {
+        let names = self.pop();
+        let mut args = self.pop_n(argc);
+        let func = self.pop();
+
+        let names = match names.as_ref() {
+            Obj::Tuple(names) => names.iter().map(|n| n.__str__()).collect::<Vec<_>>(),
+            o => return self.stack_err(format!("CALL_FUNCTION_KW expected a tuple of names, found {o}")),
+        };
+        let kw_values = args.split_off(args.len() - names.len());
+        self.call(func, args, names.into_iter().zip(kw_values).collect());
+    }
 
-        self.push_stack();
-        let return_addr = self.instruction_counter;
-        self.push(Obj::Int(return_addr.into()).into()); // return pos pointer
+    // f(*args, **kwargs), the flag is set when there is a kwargs dict on top
+    fn call_function_ex(&mut self, flags: usize) {
+        let kwargs = match flags & 1 {
+            0 => Obj::new_dict().into(),
+            _ => self.pop(),
+        };
+        let args = self.pop();
+        let func = self.pop();
 
-        for a in args {
-            self.push(a);
+        let args = match args.as_ref() {
+            Obj::List(args) => args.lock().unwrap().clone(),
+            Obj::Tuple(args) => args.clone(),
+            o => return self.stack_err(format!("CALL_FUNCTION_EX expected a list of args, found {o}")),
+        };
+        let mut kw = vec![];
+        if let Obj::Dict(map) = kwargs.as_ref() {
+            for (key, val) in map.lock().unwrap().iter() {
+                match key {
+                    Obj::Str(key) => kw.push((key.clone(), val.clone())),
+                    _ => {
+                        return self.push_err(PyException::type_error("keywords must be strings"))
+                    }
+                }
+            }
         }
+        self.call(func, args, kw);
+    }
 
-        let func_name = func.__str__();
-        /*
-        let mut namespaces: Vec<_> = func_name
-            .split(&['.'])
-            .filter(|k| !k.is_empty())
-            .collect();
-        namespaces.pop();
-        
-        for n in namespaces {
-            let map = self.var_maps;
+    fn call(&mut self, func: Arc<Obj>, args: Vec<Arc<Obj>>, kwargs: Vec<(String, Arc<Obj>)>) {
+        if !kwargs.is_empty() && matches!(func.as_ref(), Obj::ExceptType(_)) {
+            return self.push_err(PyException::type_error(format!("{}() takes no keyword arguments", func)));
         }
-        */
-        
-        match self.funcs.get(&func_name) {
-            Some(addr) => {
-                self.instruction_counter = *addr;
+
+        let ret = match func.as_ref() {
+            Obj::UserFunc(f) => {
+                let locals = match PyVM::bind_args(f, args, kwargs) {
+                    Ok(locals) => locals,
+                    Err(e) => return self.push_err(e),
+                };
+                if f.code.is_generator() {
+                    Ok(Obj::Generator(Generator::new(f.code.clone(), locals, f.globals.clone())).into())
+                } else {
+                    return self.push_frame(f.code.clone(), locals, vec![], None, f.globals.clone());
+                }
             }
-            None => {
-                self.push_err(PyException {
-                    error: PyError::SyntaxError,
-                    msg: format!("not a name of a func: {}", func_name),
-                });
+            Obj::Method(recv, f) => {
+                let mut bound = vec![recv.clone()];
+                bound.extend(args);
+                return self.call(f.clone(), bound, kwargs);
             }
+            Obj::Function(f) => f.call(self, &args, &kwargs),
+            Obj::BuiltinMethod(recv, name) => self.call_builtin_method(recv, name, args, kwargs),
+            Obj::ClassDef(class) => self.instantiate(class, args, kwargs),
+            Obj::NativeClass(class) => class.instantiate(self, &args, &kwargs),
+            Obj::Class(instance) => match instance.class.lookup("__call__") {
+                Some(call) => return self.call(Obj::Method(func.clone(), call).into(), args, kwargs),
+                None => Err(PyException::type_error(format!("'{}' object is not callable", instance.class.name))),
+            },
+            Obj::Native(native) => match native.class.lookup("__call__") {
+                Some(call) => return self.call(Obj::Method(func.clone(), call).into(), args, kwargs),
+                None => Err(PyException::type_error(format!("'{}' object is not callable", native.class.name))),
+            },
+            Obj::LruCache(cache) => self.call_cached(cache, args, kwargs),
+            Obj::BuiltinType("int") => Funcs::int(&args, &kwargs).map(|i| i.into()),
+            Obj::BuiltinType("float") if kwargs.is_empty() => Funcs::call_float(&args).map(|f| f.into()),
+            Obj::BuiltinType("bytes") => bytes::new("bytes", &args, &kwargs).map(|b| Obj::Bytes(b).into()),
+            Obj::BuiltinType("bytearray") => bytes::new("bytearray", &args, &kwargs).map(|b| bytes::new_bytearray(b).into()),
+            Obj::BuiltinType("memoryview") => bytes::memoryview(&args, &kwargs).map(|m| m.into()),
+            Obj::BuiltinType(fractions::TYPE_NAME) => fractions::new(&args, &kwargs).map(|f| f.into()),
+            Obj::BuiltinType(decimal::TYPE_NAME) => decimal::new(&args, &kwargs).map(|d| d.into()),
+            Obj::BuiltinType(decimal::CONTEXT_TYPE_NAME) => decimal::new_context(&args, &kwargs).map(|c| c.into()),
+            Obj::BuiltinType(type_name) => self.construct(type_name, &args, &kwargs),
+            Obj::ExceptType(error) => Ok(Obj::Except(PyException {
+                error: *error,
+                msg: args.iter().map(|a| a.__str__()).collect::<Vec<_>>().join(", "),
+            })
+            .into()),
+            o => Err(PyException::type_error(format!("'{}' object is not callable", o.type_name()))),
+        };
+        match ret {
+            Ok(val) => self.push(val),
+            Err(e) => self.push_err(e),
         }
     }
 
+    // Calls from inside an instruction and waits for the result, user
+    // functions are run to completion on a nested run loop
+    pub(crate) fn call_object(
+        &mut self,
+        func: Arc<Obj>,
+        args: Vec<Arc<Obj>>,
+        kwargs: Vec<(String, Arc<Obj>)>,
+    ) -> Result<Arc<Obj>, PyException> {
+        let depth = self.call_stack.len() + 1;
+        self.call(func, args, kwargs);
+        if self.error_state {
+            return Err(self.take_err());
+        }
+        if self.call_stack.len() >= depth {
+            self.run(depth)?;
+        }
+        Ok(self.pop())
+    }
+
+    // a new instance with __init__ run on it
+    fn instantiate(
+        &mut self,
+        class: &Arc<UserClassDef>,
+        args: Vec<Arc<Obj>>,
+        kwargs: Vec<(String, Arc<Obj>)>,
+    ) -> Result<Arc<Obj>, PyException> {
+        let instance: Arc<Obj> = Obj::Class(UserClassDef::new_instance(class)).into();
+        match class.lookup("__init__") {
+            Some(init) => {
+                let init = Obj::Method(instance.clone(), init).into();
+                let ret = self.call_object(init, args, kwargs)?;
+                if *ret != Obj::None {
+                    return Err(PyException::type_error(format!("__init__() should return None, not '{}'", ret.type_name())));
+                }
+            }
+            None if !args.is_empty() || !kwargs.is_empty() => {
+                return Err(PyException::type_error(format!("{}() takes no arguments", class.name)))
+            }
+            None => {}
+        }
+        Ok(instance)
+    }
+
+    fn call_cached(
+        &mut self,
+        cache: &Arc<LruCache>,
+        args: Vec<Arc<Obj>>,
+        kwargs: Vec<(String, Arc<Obj>)>,
+    ) -> Result<Arc<Obj>, PyException> {
+        let key = LruCache::make_key(&args, &kwargs);
+        PyVM::check_hashable(&key)?;
+        if let Some(val) = cache.get(&key) {
+            return Ok(val);
+        }
+        let val = self.call_object(cache.func.clone(), args, kwargs)?;
+        cache.insert(key, val.clone());
+        Ok(val)
+    }
+
     fn return_value(&mut self) {
-        let mut fn_objs = vec![self.null_obj.clone()];
+        // a return inside with blocks leaves them on the way out
+        while !self.call_stack.is_empty() && self.with_blocks.last().is_some_and(|b| b.depth == self.call_stack.len()) {
+            let block = self.with_blocks.pop().unwrap();
+            if let Err(e) = self.call_exit(block.exit, None) {
+                return self.push_err(e);
+            }
+        }
+        let val = self.pop();
+        let Some(frame) = self.call_stack.pop() else {
+            return self.push_err(PyException {
+                error: PyError::SyntaxError,
+                msg: "'return' outside function".to_string(),
+            });
+        };
 
-        let fn_stack = self.get_local_stack_mut();
-        while let Some(obj) = fn_stack.pop() {
-            fn_objs.push(obj);
+        self.local_stacks.pop();
+        self.var_maps.pop();
+        if let Some(gen) = frame.generator {
+            gen.lock().unwrap().state = GeneratorState::Finished;
         }
+        self.code = frame.return_code;
+        self.instruction_counter = frame.return_addr;
+        self.globals = frame.return_globals;
+        self.push(val);
+    }
 
-        let ret_ptr = fn_objs.pop().unwrap_or(self.null_obj.clone());
+    // saves the running frame back into its generator and hands the value
+    // to whoever resumed it
+    fn yield_value(&mut self) {
+        let val = self.pop();
+        let gen = match self.call_stack.last() {
+            Some(CallFrame { generator: Some(gen), .. }) => gen.clone(),
+            _ => {
+                return self.push_err(PyException {
+                    error: PyError::SyntaxError,
+                    msg: "'yield' outside function".to_string(),
+                })
+            }
+        };
+        let frame = self.call_stack.pop().unwrap();
+        // the with blocks it's paused inside go with it
+        let depth = self.call_stack.len() + 1;
+        let blocks = self.with_blocks.split_off(self.with_blocks.partition_point(|b| b.depth < depth));
+
+        {
+            let mut g = gen.lock().unwrap();
+            g.with_blocks = blocks;
+            g.ip = self.instruction_counter;
+            g.stack = self.local_stacks.pop().unwrap();
+            g.locals = self.var_maps.pop().unwrap();
+            g.state = GeneratorState::Suspended;
+        }
+        self.code = frame.return_code;
+        self.instruction_counter = frame.return_addr;
+        self.globals = frame.return_globals;
+        self.push(val);
+    }
 
-        if ret_ptr.as_ref() == self.null_obj.as_ref() {
-            self.push_err(PyException {
+    // Enters the manager on top of the stack, leaving what its __enter__
+    // returns. Its __exit__ is called however the block is left: by ExitWith
+    // at the end, by a return inside it, or by an error (see exit_with_error)
+    fn setup_with(&mut self, delta: usize) {
+        let manager = self.pop();
+        let unsupported = format!("'{}' object does not support the context manager protocol", builtins::class_name(&manager));
+        let (enter, exit) = match (self.get_attr(&manager, "__enter__"), self.get_attr(&manager, "__exit__")) {
+            (Ok(enter), Ok(exit)) => (enter, exit),
+            (Ok(_), Err(_)) => {
+                return self.push_err(PyException::type_error(format!("{unsupported} (missed __exit__ method)")))
+            }
+            (Err(_), _) => {
+                return self.push_err(PyException::type_error(unsupported))
+            }
+        };
+        let block = WithBlock {
+            depth: self.call_stack.len(),
+            handler: self.instruction_counter + delta,
+            stack_len: self.get_local_stack().len(),
+            exit,
+        };
+        match self.call_object(enter, vec![], vec![]) {
+            Ok(val) => {
+                self.with_blocks.push(block);
+                self.push(val);
+            }
+            Err(e) => self.push_err(e),
+        }
+    }
+
+    fn exit_with(&mut self) {
+        let Some(block) = self.with_blocks.pop() else {
+            return self.push_err(PyException {
                 error: PyError::StackError,
-                msg: "Must have already popped the return pointer ".to_string(),
+                msg: "EXIT_WITH outside a with block".to_string(),
             });
-            self.throw();
+        };
+        if let Err(e) = self.call_exit(block.exit, None) {
+            self.push_err(e);
         }
-        self.instruction_counter = ret_ptr.__int__() as usize;
-        self.pop_stack();
+    }
 
-        self.push(match fn_objs.len() {
-            0 => PyException {
-                error: PyError::StackError,
-                msg: "Popped too many objs when returning from function".to_string(),
-            }
-            .to_arc(),
-            1 => Obj::None.into(),
-            _ => fn_objs.pop().unwrap(),
-        });
+    // An error raised inside the innermost with block. The frames it came up
+    // through are dropped and __exit__ gets it, a true result swallows it and
+    // the frame carries on after the block, otherwise it's raised again there
+    fn exit_with_error(&mut self, e: PyException) {
+        let block = self.with_blocks.pop().unwrap();
+        self.unwind(block.depth + 1);
+        self.get_local_stack_mut().truncate(block.stack_len);
+        self.instruction_counter = block.handler;
+        let suppress = self.call_exit(block.exit, Some(&e)).and_then(|ret| self.truthy(&ret));
+        match suppress {
+            Ok(true) => {}
+            Ok(false) => self.push_err(e),
+            Err(e) => self.push_err(e),
+        }
     }
 
-    fn call_intrinsic_1(&mut self, ptr: IntrinsicFunc) {
-        let args = self.pop_until_null();
-        self.pop();
+    // __exit__(type, value, traceback), all None when there was no error
+    fn call_exit(&mut self, exit: Arc<Obj>, e: Option<&PyException>) -> Result<Arc<Obj>, PyException> {
+        let args = match e {
+            Some(e) => vec![Obj::ExceptType(e.error).into(), Obj::Except(e.clone()).into(), Obj::None.into()],
+            None => vec![Obj::None.into(), Obj::None.into(), Obj::None.into()],
+        };
+        self.call_object(exit, args, vec![])
+    }
 
-        let ret = match ptr {
-            IntrinsicFunc::Print => IntrinsicFunc::print(&args),
-            IntrinsicFunc::Input => IntrinsicFunc::input(&args),
-            IntrinsicFunc::Range => IntrinsicFunc::range(&args),
+    fn make_function(&mut self) {
+        let code = self.pop();
+        // keyword only defaults are only there when the def has some
+        let kwdefaults = match self.top().as_ref() {
+            Obj::Dict(map) => {
+                let map = map.lock().unwrap().iter().map(|(k, v)| (k.__str__(), v.clone())).collect();
+                self.pop();
+                map
+            }
+            _ => HashMap::new(),
         };
-        match ret {
-            Some(val) => {
-                self.push(Arc::from(val));
+        let defaults = self.pop();
+
+        let code = match code.as_ref() {
+            Obj::Code(code) => code.clone(),
+            o => {
+                return self.push_err(PyException::type_error(format!("MAKE_FUNCTION expected a code object, found {o}")))
             }
-            None => {}
+        };
+        let defaults = match defaults.as_ref() {
+            Obj::Tuple(objs) => objs.clone(),
+            _ => vec![],
+        };
+        // capture the enclosing function's cells, module level names are globals
+        let mut closure = HashMap::new();
+        if !self.call_stack.is_empty() {
+            for name in &code.co_freevars {
+                if let Some(cell) = self.get_local_vars().get(name) {
+                    if matches!(cell.as_ref(), Obj::Cell(_)) {
+                        closure.insert(name.clone(), cell.clone());
+                    }
+                }
+            }
+        }
+
+        let mut attrs = HashMap::new();
+        if let Some(doc) = code.co_consts.first() {
+            attrs.insert("__doc__".to_string(), Obj::Str(doc.clone()).into());
         }
+        let func = UserFunc {
+            code,
+            defaults,
+            kwdefaults,
+            closure,
+            attrs,
+            globals: self.globals.clone(),
+        };
+        self.push(Obj::UserFunc(Arc::new(func)).into());
     }
 
-    fn make_function(&mut self) {
-        let addr = self.pop();
+    // pops the bases, the class name and the class body function, the
+    // body is run to get the class attributes
+    fn load_build_class(&mut self) {
+        let bases = self.pop();
         let name = self.pop();
+        let body = self.pop();
+        match self.build_class(body, name.__str__(), &bases) {
+            Ok(class) => self.push(class),
+            Err(e) => self.push_err(e),
+        }
+    }
 
-        self.funcs.insert(name.__str__(), addr.__int__() as usize);
+    fn build_class(&mut self, body: Arc<Obj>, name: String, bases: &Obj) -> Result<Arc<Obj>, PyException> {
+        let bases = match bases {
+            Obj::Tuple(bases) => bases
+                .iter()
+                .map(|b| match b.as_ref() {
+                    Obj::ClassDef(class) => Ok(class.clone()),
+                    o => Err(PyException::type_error(format!("bases must be classes, not '{}'", o.type_name()))),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => vec![],
+        };
+        let namespace = self.call_object(body, vec![], vec![])?;
+        let attrs = match namespace.as_ref() {
+            Obj::Dict(map) => map.lock().unwrap().iter().map(|(k, v)| (k.__str__(), v.clone())).collect(),
+            _ => HashMap::new(),
+        };
+        Ok(Obj::ClassDef(Arc::new(UserClassDef::new(&name, bases, attrs))).into())
     }
 
-    fn load_build_class(&mut self) {
-        match self.pop().as_ref() {
-            Obj::ClassDef(def) => {
-                self.class_defs.insert(def.name.clone(), def.clone().into());
-            },
-            _ => panic!(),
+    // the names a class body assigned, for LoadBuildClass to make the class from
+    #[allow(clippy::mutable_key_type)]
+    fn load_locals(&mut self) {
+        let mut namespace = HashMap::new();
+        for (name, val) in self.get_local_vars() {
+            if !self.code.co_varnames.contains(name) {
+                continue;
+            }
+            let val = match val.as_ref() {
+                Obj::Cell(cell) => cell.lock().unwrap().clone(),
+                _ => val.clone(),
+            };
+            namespace.insert(Obj::Str(name.clone()), val);
         }
+        self.push(Obj::new_dict_from(namespace).into());
     }
 
     fn push_stack(&mut self) {
@@ -635,8 +2559,6 @@ impl PyVM {
         a[u8::from(PyBytecode::StoreGlobal) as usize] = no_instruction as fn();
 
         a[u8::from(PyBytecode::CallFunction) as usize] = no_instruction as fn();
-        a[u8::from(PyBytecode::CallInstrinsic1) as usize] = no_instruction as fn();
-        a[u8::from(PyBytecode::CallInstrinsic2) as usize] = no_instruction as fn();
         a[u8::from(PyBytecode::ReturnValue) as usize] = no_instruction as fn();
 
         a[u8::from(PyBytecode::PopJumpIfFalse) as usize] = no_instruction as fn();
@@ -665,64 +2587,3 @@ impl PyVM {
 
 fn no_instruction() {}
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub enum IntrinsicFunc {
-    Print,
-    Input,
-    Range,
-}
-
-impl IntrinsicFunc {
-    pub fn try_get(name: &str) -> Option<IntrinsicFunc> {
-        let func = match name {
-            "print" => IntrinsicFunc::Print,
-            "input" => IntrinsicFunc::Input,
-            "range" => IntrinsicFunc::Range,
-            _ => return None,
-        };
-        Some(func)
-    }
-
-    fn print(objs: &Vec<Arc<Obj>>) -> Option<Arc<Obj>> {
-        for o in objs {
-            print!("{} ", o);
-        }
-        println!();
-        None
-    }
-
-    fn input(words: &Vec<Arc<Obj>>) -> Option<Arc<Obj>> {
-        if words.len() != 1 {
-            panic!();
-        }
-        print!("{}", words.first().unwrap().__str__());
-        let _ = io::stdout().flush();
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .expect("error: unable to read user input");
-        Some(Obj::Str(input.trim().to_string()).into())
-    }
-
-    fn range(limits: &Vec<Arc<Obj>>) -> Option<Arc<Obj>> {
-        let (start, end, inc) = {
-            let s = match limits[0].as_ref() {
-                Obj::Int(i) => Some(i.clone()),
-                _ => None,
-            };
-            let e = match limits[1].as_ref() {
-                Obj::Int(i) => Some(i.clone()),
-                _ => None,
-            };
-            let i = match limits[2].as_ref() {
-                Obj::Int(i) => Some(i.clone()),
-                _ => None,
-            };
-            (s, e, i)
-        };
-
-        let r = RangeObj::from(start, end, inc);
-        let objs = r.to_vec();
-        Some(Obj::List(objs).into())
-    }
-}